@@ -0,0 +1,79 @@
+-- ============================================================================
+-- REFRESH TOKEN STORE FOR NWFTH WMS
+-- Mobile-Rust Backend - Session Management
+-- Purpose: Server-side rotating refresh tokens with family revocation
+-- Notes: Only SHA-256 hashes of refresh tokens are stored, never the token itself
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating refresh token store for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_auth_refresh_tokens
+-- Covers: backend/src/database/auth_sessions.rs (login, refresh, logout)
+-- One row per issued refresh token. All tokens descending from the same login
+-- share a FamilyId so that logout or token reuse revokes the whole chain.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_auth_refresh_tokens')
+BEGIN
+    PRINT 'Creating table: tbl_auth_refresh_tokens';
+    CREATE TABLE tbl_auth_refresh_tokens (
+        TokenHash       CHAR(64)        NOT NULL PRIMARY KEY,
+        FamilyId        VARCHAR(36)     NOT NULL,
+        UserId          NVARCHAR(100)   NOT NULL,
+        Username        NVARCHAR(100)   NOT NULL,
+        Email           NVARCHAR(255)   NOT NULL,
+        DisplayName     NVARCHAR(255)   NOT NULL,
+        IssuedAt        DATETIME2(3)    NOT NULL,
+        ExpiresAt       DATETIME2(3)    NOT NULL,
+        RevokedAt       DATETIME2(3)    NULL,
+        RevokedReason   NVARCHAR(50)    NULL,
+        ReplacedByHash  CHAR(64)        NULL
+    );
+    PRINT '✅ Created table: tbl_auth_refresh_tokens';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_auth_refresh_tokens';
+GO
+
+-- Index 1: Family lookups for logout / reuse revocation
+IF NOT EXISTS (SELECT * FROM sys.indexes WHERE name = 'IX_AuthRefreshTokens_Family')
+BEGIN
+    PRINT 'Creating index: IX_AuthRefreshTokens_Family';
+    CREATE NONCLUSTERED INDEX IX_AuthRefreshTokens_Family
+    ON tbl_auth_refresh_tokens(FamilyId)
+    INCLUDE (RevokedAt, ExpiresAt);
+    PRINT '✅ Created index: IX_AuthRefreshTokens_Family';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Index already exists: IX_AuthRefreshTokens_Family';
+GO
+
+-- Index 2: Recently revoked families (loaded into the revocation cache at startup)
+IF NOT EXISTS (SELECT * FROM sys.indexes WHERE name = 'IX_AuthRefreshTokens_RevokedAt')
+BEGIN
+    PRINT 'Creating index: IX_AuthRefreshTokens_RevokedAt';
+    CREATE NONCLUSTERED INDEX IX_AuthRefreshTokens_RevokedAt
+    ON tbl_auth_refresh_tokens(RevokedAt)
+    INCLUDE (FamilyId, RevokedReason)
+    WHERE RevokedAt IS NOT NULL;
+    PRINT '✅ Created index: IX_AuthRefreshTokens_RevokedAt';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Index already exists: IX_AuthRefreshTokens_RevokedAt';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Refresh token store created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+GO
@@ -0,0 +1,54 @@
+-- ============================================================================
+-- USER ROLES FOR NWFTH WMS
+-- Mobile-Rust Backend - Role-based authorization
+-- Purpose: Store application roles for SQL (tbl_user) accounts and carry roles
+--          across refresh token rotation
+-- Roles: picker, supervisor, inventory_controller, admin (comma separated)
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Adding role columns for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Column 1: tbl_user.wms_roles
+-- Covers: backend/src/main.rs authenticate_sql (NULL = picker only)
+-- LDAP users get roles from AD group membership instead (LDAP_ROLE_GROUPS)
+IF COL_LENGTH('tbl_user', 'wms_roles') IS NULL
+BEGIN
+    PRINT 'Adding column: tbl_user.wms_roles';
+    ALTER TABLE tbl_user ADD wms_roles NVARCHAR(100) NULL;
+    PRINT '✅ Added column: tbl_user.wms_roles';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Column already exists: tbl_user.wms_roles';
+GO
+
+-- Column 2: tbl_auth_refresh_tokens.Roles
+-- Covers: backend/src/database/auth_sessions.rs (roles re-issued on refresh)
+IF COL_LENGTH('tbl_auth_refresh_tokens', 'Roles') IS NULL
+BEGIN
+    PRINT 'Adding column: tbl_auth_refresh_tokens.Roles';
+    ALTER TABLE tbl_auth_refresh_tokens ADD Roles NVARCHAR(200) NULL;
+    PRINT '✅ Added column: tbl_auth_refresh_tokens.Roles';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Column already exists: tbl_auth_refresh_tokens.Roles';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Role columns created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+PRINT 'Example - grant supervisor rights to a SQL account:';
+PRINT '  UPDATE tbl_user SET wms_roles = ''supervisor'' WHERE uname = ''someuser'';';
+PRINT '';
+GO
@@ -0,0 +1,47 @@
+-- ============================================================================
+-- PASSWORD HASHING FOR NWFTH WMS
+-- Mobile-Rust Backend - tbl_user SQL fallback login
+-- Purpose: Widen tbl_user.pword so it can hold Argon2id hashes (~97 characters)
+-- Follow-up: run `bulk_picking_backend rehash-passwords` to hash remaining
+--            plaintext rows (rows are also upgraded on each successful login)
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Preparing tbl_user.pword for Argon2id hashes';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Column: tbl_user.pword -> NVARCHAR(255), keeping its current nullability
+IF EXISTS (
+    SELECT * FROM INFORMATION_SCHEMA.COLUMNS
+    WHERE TABLE_NAME = 'tbl_user' AND COLUMN_NAME = 'pword'
+      AND CHARACTER_MAXIMUM_LENGTH <> -1 AND CHARACTER_MAXIMUM_LENGTH < 255
+)
+BEGIN
+    DECLARE @nullability NVARCHAR(10) = (
+        SELECT CASE WHEN IS_NULLABLE = 'YES' THEN N'NULL' ELSE N'NOT NULL' END
+        FROM INFORMATION_SCHEMA.COLUMNS
+        WHERE TABLE_NAME = 'tbl_user' AND COLUMN_NAME = 'pword'
+    );
+    PRINT 'Widening column: tbl_user.pword';
+    EXEC (N'ALTER TABLE tbl_user ALTER COLUMN pword NVARCHAR(255) ' + @nullability);
+    PRINT '✅ Widened column: tbl_user.pword';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Column already wide enough: tbl_user.pword';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ tbl_user.pword ready for hashed passwords';
+PRINT '==========================================================================';
+PRINT '';
+PRINT 'Next step: bulk_picking_backend rehash-passwords --dry-run';
+PRINT '';
+GO
@@ -0,0 +1,53 @@
+-- ============================================================================
+-- API KEYS FOR NWFTH WMS SERVICE ACCOUNTS
+-- Mobile-Rust Backend - Machine-to-machine authentication
+-- Purpose: Scoped API keys for MES and label-printing hosts
+-- Notes: Only SHA-256 hashes of keys are stored; the key is shown once on creation
+-- Scopes: runs:read, putaway:read, putaway:transfer (comma separated)
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating API key store for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_api_keys
+-- Covers: backend/src/database/api_keys.rs (admin endpoints, jwt_auth_middleware)
+-- KeyId is the public prefix embedded in the key (bpk_<KeyId>_<secret>).
+-- AuditUser is written to RecUserid/ModifiedBy (8 characters max).
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_api_keys')
+BEGIN
+    PRINT 'Creating table: tbl_api_keys';
+    CREATE TABLE tbl_api_keys (
+        KeyId           VARCHAR(16)     NOT NULL PRIMARY KEY,
+        KeyHash         CHAR(64)        NOT NULL,
+        Name            NVARCHAR(100)   NOT NULL,
+        AuditUser       NVARCHAR(8)     NOT NULL,
+        Scopes          NVARCHAR(200)   NOT NULL,
+        CreatedBy       NVARCHAR(100)   NOT NULL,
+        CreatedAt       DATETIME2(3)    NOT NULL,
+        ExpiresAt       DATETIME2(3)    NULL,
+        LastUsedAt      DATETIME2(3)    NULL,
+        RevokedAt       DATETIME2(3)    NULL,
+        RevokedBy       NVARCHAR(100)   NULL
+    );
+    PRINT '✅ Created table: tbl_api_keys';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_api_keys';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ API key store created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+PRINT 'Keys are created through POST /api/auth/api-keys (admin role required)';
+PRINT '';
+GO
@@ -0,0 +1,74 @@
+-- ============================================================================
+-- SECURITY AUDIT TRAIL FOR NWFTH WMS
+-- Mobile-Rust Backend - Login and token events
+-- Purpose: Persist logins, failed attempts, SQL fallback usage and token
+--          rejections for supervisor security reviews
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating security audit trail for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_security_audit
+-- Covers: backend/src/database/security_audit.rs (login, refresh, jwt_auth_middleware)
+-- EventType: login_success, login_failure, login_throttled, sql_fallback,
+--            token_rejected, api_key_rejected, refresh_reuse, logout
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_security_audit')
+BEGIN
+    PRINT 'Creating table: tbl_security_audit';
+    CREATE TABLE tbl_security_audit (
+        AuditId         BIGINT IDENTITY(1,1) NOT NULL PRIMARY KEY,
+        EventTime       DATETIME2(3)    NOT NULL,
+        EventType       NVARCHAR(40)    NOT NULL,
+        Username        NVARCHAR(100)   NULL,
+        SourceIp        VARCHAR(45)     NULL,
+        AuthMethod      NVARCHAR(20)    NULL,
+        Outcome         NVARCHAR(20)    NOT NULL,
+        Detail          NVARCHAR(400)   NULL
+    );
+    PRINT '✅ Created table: tbl_security_audit';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_security_audit';
+GO
+
+-- Index 1: Newest-first review queries
+IF NOT EXISTS (SELECT * FROM sys.indexes WHERE name = 'IX_SecurityAudit_EventTime')
+BEGIN
+    PRINT 'Creating index: IX_SecurityAudit_EventTime';
+    CREATE NONCLUSTERED INDEX IX_SecurityAudit_EventTime
+    ON tbl_security_audit(EventTime DESC)
+    INCLUDE (EventType, Username, SourceIp, Outcome);
+    PRINT '✅ Created index: IX_SecurityAudit_EventTime';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Index already exists: IX_SecurityAudit_EventTime';
+GO
+
+-- Index 2: Per-user history
+IF NOT EXISTS (SELECT * FROM sys.indexes WHERE name = 'IX_SecurityAudit_Username')
+BEGIN
+    PRINT 'Creating index: IX_SecurityAudit_Username';
+    CREATE NONCLUSTERED INDEX IX_SecurityAudit_Username
+    ON tbl_security_audit(Username, EventTime DESC);
+    PRINT '✅ Created index: IX_SecurityAudit_Username';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Index already exists: IX_SecurityAudit_Username';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Security audit trail created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+GO
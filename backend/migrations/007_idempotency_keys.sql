@@ -0,0 +1,64 @@
+-- ============================================================================
+-- IDEMPOTENCY KEYS FOR NWFTH WMS WRITE ENDPOINTS
+-- Mobile-Rust Backend - Safe retries for confirm-pick and bin transfer
+-- Purpose: Store the first response for each Idempotency-Key so a retried
+--          request replays it instead of committing inventory twice
+-- Notes: Rows are written inside the pick / transfer transaction
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating idempotency key store for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_idempotency_keys
+-- Covers: backend/src/database/idempotency.rs (confirm-pick, putaway transfer)
+-- Operation: confirm_pick, bin_transfer
+-- RequestHash is the SHA-256 of the request body; a key reused with a different
+-- body is rejected instead of replayed.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_idempotency_keys')
+BEGIN
+    PRINT 'Creating table: tbl_idempotency_keys';
+    CREATE TABLE tbl_idempotency_keys (
+        Username        NVARCHAR(100)   NOT NULL,
+        Operation       VARCHAR(20)     NOT NULL,
+        IdempotencyKey  VARCHAR(64)     NOT NULL,
+        RequestHash     CHAR(64)        NOT NULL,
+        ResponseBody    NVARCHAR(MAX)   NOT NULL,
+        CreatedAt       DATETIME2(3)    NOT NULL,
+        CONSTRAINT PK_IdempotencyKeys PRIMARY KEY (Username, Operation, IdempotencyKey)
+    );
+    PRINT '✅ Created table: tbl_idempotency_keys';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_idempotency_keys';
+GO
+
+-- Index 1: Housekeeping of old keys
+IF NOT EXISTS (SELECT * FROM sys.indexes WHERE name = 'IX_IdempotencyKeys_CreatedAt')
+BEGIN
+    PRINT 'Creating index: IX_IdempotencyKeys_CreatedAt';
+    CREATE NONCLUSTERED INDEX IX_IdempotencyKeys_CreatedAt
+    ON tbl_idempotency_keys(CreatedAt);
+    PRINT '✅ Created index: IX_IdempotencyKeys_CreatedAt';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Index already exists: IX_IdempotencyKeys_CreatedAt';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Idempotency key store created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+PRINT 'Keys older than the retry window can be purged with:';
+PRINT '  DELETE FROM tbl_idempotency_keys WHERE CreatedAt < DATEADD(DAY, -7, SYSUTCDATETIME())';
+PRINT '';
+GO
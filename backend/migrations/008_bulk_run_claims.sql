@@ -0,0 +1,47 @@
+-- ============================================================================
+-- RUN CLAIMS FOR NWFTH WMS BULK PICKING
+-- Mobile-Rust Backend - Picker leases on runs and ingredient lines
+-- Purpose: Record who is picking a run (or one ingredient line of it) so a
+--          second picker is turned away instead of racing the first
+-- Notes: Claims expire on a lease (RUN_CLAIM_LEASE_MINUTES) and are renewed
+--        by claiming again; expired rows are cleared on the next claim
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating run claim store for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_bulk_run_claims
+-- Covers: backend/src/database/run_claims.rs (claim, release, active claims)
+-- LineId 0 claims the whole run; any other value claims that ingredient line.
+-- Times are UTC.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_bulk_run_claims')
+BEGIN
+    PRINT 'Creating table: tbl_bulk_run_claims';
+    CREATE TABLE tbl_bulk_run_claims (
+        RunNo           INT             NOT NULL,
+        LineId          INT             NOT NULL,
+        Username        NVARCHAR(100)   NOT NULL,
+        ClaimedAt       DATETIME2(3)    NOT NULL,
+        ExpiresAt       DATETIME2(3)    NOT NULL,
+        CONSTRAINT PK_BulkRunClaims PRIMARY KEY (RunNo, LineId)
+    );
+    PRINT '✅ Created table: tbl_bulk_run_claims';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_bulk_run_claims';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Run claim store created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+GO
@@ -0,0 +1,94 @@
+-- ============================================================================
+-- PICK PLANS FOR NWFTH WMS BULK PICKING
+-- Mobile-Rust Backend - FEFO lot/bin assignment for every batch of a run
+-- Purpose: Keep the generated plan so pickers follow it step by step, and
+--          record the batches no available lot could cover
+-- Notes: Regenerating a plan replaces the run's previous one; steps move to
+--        PICKED as confirm-picks take bags from the planned lot/bin
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating pick plan store for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_bulk_pick_plans
+-- Covers: backend/src/database/pick_plans.rs (plan header, one per run)
+-- Times are UTC.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_bulk_pick_plans')
+BEGIN
+    PRINT 'Creating table: tbl_bulk_pick_plans';
+    CREATE TABLE tbl_bulk_pick_plans (
+        RunNo           INT             NOT NULL,
+        GeneratedBy     NVARCHAR(100)   NOT NULL,
+        GeneratedAt     DATETIME2(3)    NOT NULL,
+        CONSTRAINT PK_BulkPickPlans PRIMARY KEY (RunNo)
+    );
+    PRINT '✅ Created table: tbl_bulk_pick_plans';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_bulk_pick_plans';
+GO
+
+-- Table: tbl_bulk_pick_plan_steps
+-- Covers: backend/src/database/pick_plans.rs (steps, marking steps picked)
+-- Status is PENDING or PICKED.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_bulk_pick_plan_steps')
+BEGIN
+    PRINT 'Creating table: tbl_bulk_pick_plan_steps';
+    CREATE TABLE tbl_bulk_pick_plan_steps (
+        RunNo           INT             NOT NULL,
+        StepNo          INT             NOT NULL,
+        RowNum          INT             NOT NULL,
+        LineId          INT             NOT NULL,
+        ItemKey         NVARCHAR(50)    NOT NULL,
+        LotNo           NVARCHAR(50)    NOT NULL,
+        BinNo           NVARCHAR(50)    NOT NULL,
+        Bags            FLOAT           NOT NULL,
+        QtyKg           FLOAT           NOT NULL,
+        DateExpiry      DATETIME2(3)    NULL,
+        Status          VARCHAR(10)     NOT NULL,
+        PickedAt        DATETIME2(3)    NULL,
+        CONSTRAINT PK_BulkPickPlanSteps PRIMARY KEY (RunNo, StepNo)
+    );
+    PRINT '✅ Created table: tbl_bulk_pick_plan_steps';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_bulk_pick_plan_steps';
+GO
+
+-- Table: tbl_bulk_pick_plan_shortages
+-- Covers: backend/src/database/pick_plans.rs (batches short of stock)
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_bulk_pick_plan_shortages')
+BEGIN
+    PRINT 'Creating table: tbl_bulk_pick_plan_shortages';
+    CREATE TABLE tbl_bulk_pick_plan_shortages (
+        RunNo           INT             NOT NULL,
+        RowNum          INT             NOT NULL,
+        LineId          INT             NOT NULL,
+        ItemKey         NVARCHAR(50)    NOT NULL,
+        RequiredBags    FLOAT           NOT NULL,
+        ShortBags       FLOAT           NOT NULL,
+        ShortKg         FLOAT           NOT NULL,
+        CONSTRAINT PK_BulkPickPlanShortages PRIMARY KEY (RunNo, RowNum, LineId)
+    );
+    PRINT '✅ Created table: tbl_bulk_pick_plan_shortages';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_bulk_pick_plan_shortages';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Pick plan store created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+GO
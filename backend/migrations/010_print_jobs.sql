@@ -0,0 +1,87 @@
+-- ============================================================================
+-- NETWORK LABEL PRINTING FOR NWFTH WMS BULK PICKING
+-- Mobile-Rust Backend - Printer registry and print job queue
+-- Purpose: Send rendered ZPL/EPL labels straight to network label printers
+--          over raw TCP (port 9100), with retries and an auditable reprint trail
+-- Notes: Jobs are QUEUED, then PRINTING while a worker sends them, then PRINTED
+--        or FAILED once attempts run out. A reprint is a new job pointing at the
+--        original through ReprintOf and must carry a reason
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating label printer registry and print job queue for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_label_printers
+-- Covers: backend/src/database/print_jobs.rs (register, list, look up printers)
+-- Language is ZPL or EPL. Times are UTC.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_label_printers')
+BEGIN
+    PRINT 'Creating table: tbl_label_printers';
+    CREATE TABLE tbl_label_printers (
+        PrinterId       NVARCHAR(50)    NOT NULL,
+        Name            NVARCHAR(100)   NOT NULL,
+        Host            NVARCHAR(255)   NOT NULL,
+        Port            INT             NOT NULL DEFAULT 9100,
+        Language        VARCHAR(3)      NOT NULL,
+        IsDefault       BIT             NOT NULL DEFAULT 0,
+        Active          BIT             NOT NULL DEFAULT 1,
+        UpdatedBy       NVARCHAR(100)   NOT NULL,
+        UpdatedAt       DATETIME2(3)    NOT NULL,
+        CONSTRAINT PK_LabelPrinters PRIMARY KEY (PrinterId)
+    );
+    PRINT '✅ Created table: tbl_label_printers';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_label_printers';
+GO
+
+-- Table: tbl_print_jobs
+-- Covers: backend/src/database/print_jobs.rs (queue, claim due jobs, record attempts)
+-- Status is QUEUED, PRINTING, PRINTED or FAILED. NextAttemptAt is when a QUEUED job
+-- is next due, or when a PRINTING job's send is considered abandoned. Times are UTC.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_print_jobs')
+BEGIN
+    PRINT 'Creating table: tbl_print_jobs';
+    CREATE TABLE tbl_print_jobs (
+        JobId           BIGINT          IDENTITY(1,1) NOT NULL,
+        PrinterId       NVARCHAR(50)    NOT NULL,
+        RunNo           INT             NOT NULL,
+        BatchNo         NVARCHAR(50)    NULL,
+        LabelKind       VARCHAR(20)     NOT NULL,
+        LabelCount      INT             NOT NULL,
+        Payload         NVARCHAR(MAX)   NOT NULL,
+        Status          VARCHAR(10)     NOT NULL,
+        Attempts        INT             NOT NULL DEFAULT 0,
+        MaxAttempts     INT             NOT NULL,
+        LastError       NVARCHAR(500)   NULL,
+        ReprintOf       BIGINT          NULL,
+        Reason          NVARCHAR(200)   NULL,
+        RequestedBy     NVARCHAR(100)   NOT NULL,
+        CreatedAt       DATETIME2(3)    NOT NULL,
+        NextAttemptAt   DATETIME2(3)    NOT NULL,
+        PrintedAt       DATETIME2(3)    NULL,
+        CONSTRAINT PK_PrintJobs PRIMARY KEY (JobId)
+    );
+    CREATE INDEX IX_PrintJobs_Due ON tbl_print_jobs (Status, NextAttemptAt);
+    CREATE INDEX IX_PrintJobs_Run ON tbl_print_jobs (RunNo, JobId);
+    PRINT '✅ Created table: tbl_print_jobs';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_print_jobs';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Label printer registry and print job queue created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+GO
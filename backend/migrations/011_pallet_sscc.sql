@@ -0,0 +1,52 @@
+-- ============================================================================
+-- GS1 SSCC PALLET IDENTIFIERS FOR NWFTH WMS BULK PICKING
+-- Mobile-Rust Backend - SSCC-18 per picked pallet (batch row) of a run
+-- Purpose: Give each pallet an identifier that scans across sites, printed as a
+--          GS1-128 barcode on the pallet label and looked up by scanning it
+-- Notes: The BME4 PalletID in Cust_BulkLotPicked/Cust_BulkPalletLotPicked is left
+--        as it is. SerialRef is the SSCC serial reference; the SSCC itself is built
+--        from SSCC_EXTENSION_DIGIT, SSCC_COMPANY_PREFIX, SerialRef and a check digit
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating SSCC pallet identifiers for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_pallet_sscc
+-- Covers: backend/src/database/pallets.rs (assign, look up by pallet or SSCC)
+-- One row per RunNo + RowNum. SSCC is NULL only inside the assigning transaction.
+-- Times are UTC.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_pallet_sscc')
+BEGIN
+    PRINT 'Creating table: tbl_pallet_sscc';
+    CREATE TABLE tbl_pallet_sscc (
+        SerialRef       BIGINT          IDENTITY(1,1) NOT NULL,
+        SSCC            CHAR(18)        NULL,
+        RunNo           INT             NOT NULL,
+        RowNum          INT             NOT NULL,
+        BatchNo         NVARCHAR(50)    NOT NULL,
+        AssignedBy      NVARCHAR(100)   NOT NULL,
+        AssignedAt      DATETIME2(3)    NOT NULL,
+        CONSTRAINT PK_PalletSscc PRIMARY KEY (SerialRef),
+        CONSTRAINT UQ_PalletSscc_Pallet UNIQUE (RunNo, RowNum)
+    );
+    CREATE UNIQUE INDEX IX_PalletSscc_SSCC ON tbl_pallet_sscc (SSCC) WHERE SSCC IS NOT NULL;
+    PRINT '✅ Created table: tbl_pallet_sscc';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_pallet_sscc';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ SSCC pallet identifiers created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+GO
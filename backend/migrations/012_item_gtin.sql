@@ -0,0 +1,47 @@
+-- ============================================================================
+-- SUPPLIER GTIN CROSS-REFERENCE FOR NWFTH WMS SCANNING
+-- Mobile-Rust Backend - GS1 GTIN-14 to INMAST item for supplier label scans
+-- Purpose: Resolve the item of a bag from the (01) GTIN on its GS1-128 or
+--          GS1 DataMatrix label, so one scan gives item, lot and expiry
+-- Notes: Supplier GTINs are not kept in INMAST. Mappings are registered with
+--        POST /api/item-gtins (supervisor); GTIN-8/12/13 are stored as GTIN-14
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating supplier GTIN cross-reference for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_item_gtin
+-- Covers: backend/src/database/scans.rs (GTIN lookup and registration)
+-- One row per GTIN; several GTINs (suppliers, pack sizes) may map to one item.
+-- Times are UTC.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_item_gtin')
+BEGIN
+    PRINT 'Creating table: tbl_item_gtin';
+    CREATE TABLE tbl_item_gtin (
+        GTIN            CHAR(14)        NOT NULL,
+        ItemKey         NVARCHAR(50)    NOT NULL,
+        RegisteredBy    NVARCHAR(100)   NOT NULL,
+        RegisteredAt    DATETIME2(3)    NOT NULL,
+        CONSTRAINT PK_ItemGtin PRIMARY KEY (GTIN)
+    );
+    CREATE INDEX IX_ItemGtin_ItemKey ON tbl_item_gtin (ItemKey);
+    PRINT '✅ Created table: tbl_item_gtin';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_item_gtin';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Supplier GTIN cross-reference created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+GO
@@ -0,0 +1,54 @@
+-- ============================================================================
+-- PICK SCAN VERIFICATION EXCEPTIONS FOR NWFTH WMS BULK PICKING
+-- Mobile-Rust Backend - Wrong bin/lot scans rejected at confirm-pick
+-- Purpose: Keep a record of every pick refused because the scanned bin or lot
+--          barcode did not match the allocation the picker selected
+-- Notes: Scans are required at the sites listed in PICK_SCAN_VERIFY_SITES and
+--        checked wherever they are sent. Rejected picks change no inventory.
+-- Compatible with: SQL Server Standard, Express, and Enterprise editions
+-- ============================================================================
+
+USE TFCPILOT3;
+GO
+
+PRINT '==========================================================================';
+PRINT 'Creating pick scan exceptions for NWFTH WMS';
+PRINT 'Database: TFCPILOT3';
+PRINT '==========================================================================';
+PRINT '';
+
+-- Table: tbl_pick_scan_exceptions
+-- Covers: backend/src/database/pick_scans.rs (record, list per run)
+-- ScannedLot/ScannedBin hold the raw scans (a GS1 lot label is kept whole).
+-- Times are UTC.
+IF NOT EXISTS (SELECT * FROM sys.tables WHERE name = 'tbl_pick_scan_exceptions')
+BEGIN
+    PRINT 'Creating table: tbl_pick_scan_exceptions';
+    CREATE TABLE tbl_pick_scan_exceptions (
+        ExceptionId     BIGINT          IDENTITY(1,1) NOT NULL,
+        RunNo           INT             NOT NULL,
+        RowNum          INT             NOT NULL,
+        LineId          INT             NOT NULL,
+        LotNo           NVARCHAR(50)    NOT NULL,
+        BinNo           NVARCHAR(50)    NOT NULL,
+        ScannedLot      NVARCHAR(200)   NULL,
+        ScannedBin      NVARCHAR(100)   NULL,
+        ErrorCode       VARCHAR(40)     NOT NULL,
+        UserId          NVARCHAR(100)   NOT NULL,
+        CreatedAt       DATETIME2(3)    NOT NULL,
+        CONSTRAINT PK_PickScanExceptions PRIMARY KEY (ExceptionId)
+    );
+    CREATE INDEX IX_PickScanExceptions_Run ON tbl_pick_scan_exceptions (RunNo, CreatedAt);
+    PRINT '✅ Created table: tbl_pick_scan_exceptions';
+    PRINT '';
+END
+ELSE
+    PRINT '⏭️  Table already exists: tbl_pick_scan_exceptions';
+GO
+
+PRINT '';
+PRINT '==========================================================================';
+PRINT '✅ Pick scan exceptions created/verified successfully';
+PRINT '==========================================================================';
+PRINT '';
+GO
@@ -0,0 +1,3 @@
+//! One-off administrative commands, run as `bulk_picking_backend <command>` instead of starting the server
+
+pub mod rehash_passwords;
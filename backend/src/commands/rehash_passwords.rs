@@ -0,0 +1,47 @@
+use anyhow::{bail, Result};
+
+use crate::database::Database;
+use crate::utils::password::hash_password;
+
+/// Length of an Argon2id PHC string with default parameters, rounded up
+const MIN_PASSWORD_COLUMN_LENGTH: i32 = 100;
+
+/// Hash every remaining plaintext password in tbl_user.
+///
+/// Usage: `bulk_picking_backend rehash-passwords [--dry-run]`
+pub async fn run(database: &Database, dry_run: bool) -> Result<()> {
+    match database.get_password_column_length().await? {
+        None => bail!("tbl_user.pword not found - nothing to rehash"),
+        Some(length) if length != -1 && length < MIN_PASSWORD_COLUMN_LENGTH => bail!(
+            "tbl_user.pword holds only {length} characters; run migrations/004_password_hashing.sql first"
+        ),
+        Some(_) => {}
+    }
+
+    let users = database.get_users_with_plaintext_passwords().await?;
+    println!("🔍 Found {} tbl_user row(s) with plaintext passwords", users.len());
+
+    if dry_run {
+        for (username, _) in &users {
+            println!("   would rehash: {username}");
+        }
+        println!("ℹ️  Dry run - no changes made");
+        return Ok(());
+    }
+
+    let mut rehashed = 0;
+    let mut skipped = 0;
+    for (username, plaintext) in &users {
+        let hash = hash_password(plaintext)?;
+        if database.update_user_password_hash(username, plaintext, &hash).await? {
+            rehashed += 1;
+        } else {
+            // Password changed between the scan and the update - leave it alone
+            println!("⏭️  Skipped {username}: password changed during rehash");
+            skipped += 1;
+        }
+    }
+
+    println!("✅ Rehashed {rehashed} password(s), skipped {skipped}");
+    Ok(())
+}
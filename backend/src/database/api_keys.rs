@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use tiberius::Row;
+use tracing::info;
+
+use crate::database::Database;
+use crate::models::api_keys::{scopes_from_column, scopes_to_column, ApiKeyRecord, ApiScope};
+
+const API_KEY_COLUMNS: &str = r#"
+    KeyId, KeyHash, Name, AuditUser, Scopes, CreatedBy, CreatedAt, ExpiresAt, LastUsedAt, RevokedAt
+"#;
+
+impl Database {
+    /// Store a newly generated API key (hash only)
+    #[allow(clippy::too_many_arguments)]
+    pub async fn create_api_key(
+        &self,
+        key_id: &str,
+        key_hash: &str,
+        name: &str,
+        audit_user: &str,
+        scopes: &[ApiScope],
+        created_by: &str,
+        expires_at: Option<NaiveDateTime>,
+    ) -> Result<ApiKeyRecord> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for API key creation")?;
+
+        client
+            .execute(
+                r#"
+                INSERT INTO tbl_api_keys
+                    (KeyId, KeyHash, Name, AuditUser, Scopes, CreatedBy, CreatedAt, ExpiresAt)
+                VALUES (@P1, @P2, @P3, @P4, @P5, @P6, SYSUTCDATETIME(), @P7)
+                "#,
+                &[
+                    &key_id,
+                    &key_hash,
+                    &name,
+                    &audit_user,
+                    &scopes_to_column(scopes),
+                    &created_by,
+                    &expires_at,
+                ],
+            )
+            .await
+            .context("Failed to store API key")?;
+
+        info!("🔑 API key {} ('{}') created by {}", key_id, name, created_by);
+
+        self.find_api_key(key_id)
+            .await?
+            .context("API key not found after creation")
+    }
+
+    /// Look up an API key by its public id
+    pub async fn find_api_key(&self, key_id: &str) -> Result<Option<ApiKeyRecord>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for API key lookup")?;
+
+        let query = format!("SELECT {API_KEY_COLUMNS} FROM tbl_api_keys WHERE KeyId = @P1");
+        let row = client
+            .query(query, &[&key_id])
+            .await
+            .context("Failed to look up API key")?
+            .into_row()
+            .await
+            .context("Failed to read API key")?;
+
+        Ok(row.as_ref().map(api_key_from_row))
+    }
+
+    /// All API keys, newest first
+    pub async fn list_api_keys(&self) -> Result<Vec<ApiKeyRecord>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for API key list")?;
+
+        let query = format!("SELECT {API_KEY_COLUMNS} FROM tbl_api_keys ORDER BY CreatedAt DESC");
+        let rows = client
+            .query(query, &[])
+            .await
+            .context("Failed to query API keys")?
+            .into_first_result()
+            .await
+            .context("Failed to read API keys")?;
+
+        Ok(rows.iter().map(api_key_from_row).collect())
+    }
+
+    /// Revoke an API key; returns false if it does not exist or was already revoked
+    pub async fn revoke_api_key(&self, key_id: &str, revoked_by: &str) -> Result<bool> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for API key revocation")?;
+
+        let result = client
+            .execute(
+                r#"
+                UPDATE tbl_api_keys
+                SET RevokedAt = SYSUTCDATETIME(), RevokedBy = @P2
+                WHERE KeyId = @P1 AND RevokedAt IS NULL
+                "#,
+                &[&key_id, &revoked_by],
+            )
+            .await
+            .context("Failed to revoke API key")?;
+
+        let revoked = result.total() > 0;
+        if revoked {
+            info!("🔒 API key {} revoked by {}", key_id, revoked_by);
+        }
+        Ok(revoked)
+    }
+
+    /// Record that an API key was just used
+    pub async fn touch_api_key(&self, key_id: &str) -> Result<()> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for API key usage")?;
+
+        client
+            .execute(
+                "UPDATE tbl_api_keys SET LastUsedAt = SYSUTCDATETIME() WHERE KeyId = @P1",
+                &[&key_id],
+            )
+            .await
+            .context("Failed to update API key usage")?;
+        Ok(())
+    }
+}
+
+fn api_key_from_row(row: &Row) -> ApiKeyRecord {
+    let text = |column: &str| row.get::<&str, _>(column).unwrap_or("").to_string();
+
+    ApiKeyRecord {
+        key_id: text("KeyId"),
+        key_hash: text("KeyHash"),
+        name: text("Name"),
+        audit_user: text("AuditUser"),
+        scopes: scopes_from_column(row.get::<&str, _>("Scopes").unwrap_or("")),
+        created_by: text("CreatedBy"),
+        created_at: row.get::<NaiveDateTime, _>("CreatedAt").unwrap_or_default(),
+        expires_at: row.get::<NaiveDateTime, _>("ExpiresAt"),
+        last_used_at: row.get::<NaiveDateTime, _>("LastUsedAt"),
+        revoked_at: row.get::<NaiveDateTime, _>("RevokedAt"),
+    }
+}
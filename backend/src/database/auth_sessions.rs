@@ -0,0 +1,283 @@
+use anyhow::{Context, Result};
+use tracing::{error, info, warn};
+
+use crate::database::Database;
+use crate::models::auth_session::{
+    IssuedRefreshToken, RefreshRotation, RefreshSession, RevocationReason,
+};
+use crate::types::{Role, User};
+use crate::utils::roles::{roles_from_column, roles_to_column};
+
+impl Database {
+    /// Persist a newly issued refresh token as the first member of a token family
+    pub async fn store_refresh_token(
+        &self,
+        family_id: &str,
+        user: &User,
+        refresh_token: &IssuedRefreshToken,
+    ) -> Result<()> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for refresh token storage")?;
+
+        let query = r#"
+            INSERT INTO tbl_auth_refresh_tokens
+                (TokenHash, FamilyId, UserId, Username, Email, DisplayName, Roles, IssuedAt, ExpiresAt)
+            VALUES (@P1, @P2, @P3, @P4, @P5, @P6, @P7, SYSUTCDATETIME(), @P8)
+        "#;
+
+        client
+            .execute(
+                query,
+                &[
+                    &refresh_token.token_hash,
+                    &family_id,
+                    &user.user_id,
+                    &user.username,
+                    &user.email,
+                    &user.display_name,
+                    &roles_to_column(&user.roles),
+                    &refresh_token.expires_at,
+                ],
+            )
+            .await
+            .context("Failed to store refresh token")?;
+
+        info!("🔑 Stored refresh token for user: {} (family {})", user.username, family_id);
+        Ok(())
+    }
+
+    /// Exchange a refresh token for its replacement.
+    /// Presenting a token that was already rotated or revoked revokes the whole family.
+    /// `roles` are the user's current roles; None keeps the roles stored with the presented token
+    /// (used only when the directory cannot be asked).
+    pub async fn rotate_refresh_token(
+        &self,
+        presented_hash: &str,
+        replacement: &IssuedRefreshToken,
+        roles: Option<&[Role]>,
+    ) -> Result<RefreshRotation> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for refresh token rotation")?;
+
+        client
+            .simple_query("BEGIN TRANSACTION")
+            .await
+            .context("Failed to start refresh token transaction")?;
+
+        let result: Result<RefreshRotation> = async {
+            let lookup_query = r#"
+                SELECT FamilyId, UserId, Username, Email, DisplayName, Roles,
+                       CAST(CASE WHEN RevokedAt IS NULL THEN 0 ELSE 1 END AS INT) AS IsRevoked,
+                       CAST(CASE WHEN ExpiresAt <= SYSUTCDATETIME() THEN 1 ELSE 0 END AS INT) AS IsExpired
+                FROM tbl_auth_refresh_tokens WITH (UPDLOCK, ROWLOCK)
+                WHERE TokenHash = @P1
+            "#;
+
+            let row = client
+                .query(lookup_query, &[&presented_hash])
+                .await
+                .context("Failed to look up refresh token")?
+                .into_row()
+                .await
+                .context("Failed to read refresh token")?;
+
+            let Some(row) = row else {
+                return Ok(RefreshRotation::NotFound);
+            };
+
+            let family_id = row.get::<&str, _>("FamilyId").unwrap_or("").to_string();
+            let is_revoked = row.get::<i32, _>("IsRevoked").unwrap_or(0) == 1;
+            let is_expired = row.get::<i32, _>("IsExpired").unwrap_or(0) == 1;
+
+            if is_revoked {
+                warn!("🚨 Refresh token reuse detected - revoking token family {}", family_id);
+                client
+                    .execute(
+                        r#"
+                        UPDATE tbl_auth_refresh_tokens
+                        SET RevokedAt = SYSUTCDATETIME(), RevokedReason = @P2
+                        WHERE FamilyId = @P1 AND RevokedAt IS NULL
+                        "#,
+                        &[&family_id, &RevocationReason::ReuseDetected.as_str()],
+                    )
+                    .await
+                    .context("Failed to revoke token family after reuse")?;
+                let username = row.get::<&str, _>("Username").unwrap_or("").to_string();
+                return Ok(RefreshRotation::ReuseDetected { family_id, username });
+            }
+
+            if is_expired {
+                return Ok(RefreshRotation::Expired);
+            }
+
+            let user = User {
+                user_id: row.get::<&str, _>("UserId").unwrap_or("").to_string(),
+                username: row.get::<&str, _>("Username").unwrap_or("").to_string(),
+                email: row.get::<&str, _>("Email").unwrap_or("").to_string(),
+                display_name: row.get::<&str, _>("DisplayName").unwrap_or("").to_string(),
+                is_active: true,
+                roles: match roles {
+                    Some(roles) => roles.to_vec(),
+                    None => roles_from_column(row.get("Roles")),
+                },
+            };
+
+            client
+                .execute(
+                    r#"
+                    UPDATE tbl_auth_refresh_tokens
+                    SET RevokedAt = SYSUTCDATETIME(), RevokedReason = @P2, ReplacedByHash = @P3
+                    WHERE TokenHash = @P1
+                    "#,
+                    &[
+                        &presented_hash,
+                        &RevocationReason::Rotated.as_str(),
+                        &replacement.token_hash,
+                    ],
+                )
+                .await
+                .context("Failed to retire rotated refresh token")?;
+
+            client
+                .execute(
+                    r#"
+                    INSERT INTO tbl_auth_refresh_tokens
+                        (TokenHash, FamilyId, UserId, Username, Email, DisplayName, Roles, IssuedAt, ExpiresAt)
+                    VALUES (@P1, @P2, @P3, @P4, @P5, @P6, @P7, SYSUTCDATETIME(), @P8)
+                    "#,
+                    &[
+                        &replacement.token_hash,
+                        &family_id,
+                        &user.user_id,
+                        &user.username,
+                        &user.email,
+                        &user.display_name,
+                        &roles_to_column(&user.roles),
+                        &replacement.expires_at,
+                    ],
+                )
+                .await
+                .context("Failed to store replacement refresh token")?;
+
+            Ok(RefreshRotation::Rotated(RefreshSession { family_id, user }))
+        }
+        .await;
+
+        match result {
+            Ok(rotation) => {
+                client
+                    .simple_query("COMMIT")
+                    .await
+                    .context("Failed to commit refresh token transaction")?;
+                Ok(rotation)
+            }
+            Err(e) => {
+                let _ = client.simple_query("ROLLBACK").await;
+                error!("❌ Refresh token rotation failed, rolled back: {}", e);
+                Err(e)
+            }
+        }
+    }
+
+    /// Find the token family a refresh token belongs to, with the family's username
+    pub async fn find_refresh_token_family(&self, token_hash: &str) -> Result<Option<(String, String)>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for refresh token lookup")?;
+
+        let row = client
+            .query(
+                "SELECT FamilyId, Username FROM tbl_auth_refresh_tokens WHERE TokenHash = @P1",
+                &[&token_hash],
+            )
+            .await
+            .context("Failed to look up refresh token family")?
+            .into_row()
+            .await
+            .context("Failed to read refresh token family")?;
+
+        Ok(row.and_then(|r| {
+            let family_id = r.get::<&str, _>("FamilyId")?.to_string();
+            let username = r.get::<&str, _>("Username").unwrap_or("").to_string();
+            Some((family_id, username))
+        }))
+    }
+
+    /// Revoke every live refresh token in a family
+    pub async fn revoke_refresh_token_family(
+        &self,
+        family_id: &str,
+        reason: RevocationReason,
+    ) -> Result<u64> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for token revocation")?;
+
+        let result = client
+            .execute(
+                r#"
+                UPDATE tbl_auth_refresh_tokens
+                SET RevokedAt = SYSUTCDATETIME(), RevokedReason = @P2
+                WHERE FamilyId = @P1 AND RevokedAt IS NULL
+                "#,
+                &[&family_id, &reason.as_str()],
+            )
+            .await
+            .context("Failed to revoke refresh token family")?;
+
+        let revoked = result.total();
+        info!("🔒 Revoked {} refresh token(s) in family {} ({})", revoked, family_id, reason.as_str());
+        Ok(revoked)
+    }
+
+    /// Families revoked by logout or reuse within the last `within_seconds`,
+    /// with the unix time of their revocation (used to warm the revocation cache on startup)
+    pub async fn get_recently_revoked_token_families(
+        &self,
+        within_seconds: i64,
+    ) -> Result<Vec<(String, i64)>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for revoked token lookup")?;
+
+        let query = r#"
+            SELECT FamilyId,
+                   CAST(DATEDIFF(SECOND, '1970-01-01', MAX(RevokedAt)) AS BIGINT) AS RevokedAtUnix
+            FROM tbl_auth_refresh_tokens
+            WHERE RevokedAt >= DATEADD(SECOND, -@P1, SYSUTCDATETIME())
+              AND RevokedReason IN (@P2, @P3)
+            GROUP BY FamilyId
+        "#;
+
+        let rows = client
+            .query(
+                query,
+                &[
+                    &(within_seconds as i32),
+                    &RevocationReason::Logout.as_str(),
+                    &RevocationReason::ReuseDetected.as_str(),
+                ],
+            )
+            .await
+            .context("Failed to query revoked token families")?
+            .into_first_result()
+            .await
+            .context("Failed to read revoked token families")?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let family_id = row.get::<&str, _>("FamilyId")?.to_string();
+                let revoked_at = row.get::<i64, _>("RevokedAtUnix")?;
+                Some((family_id, revoked_at))
+            })
+            .collect())
+    }
+}
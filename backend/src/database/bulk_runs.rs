@@ -1,6 +1,7 @@
-use crate::database::Database;
+use crate::database::{idempotency, run_claims, Database};
 use crate::models::bulk_runs::*;
 use crate::models::inventory::{AlertSeverity, InventoryAlert, InventoryAlertType};
+use crate::models::pick_sequence::BinLocation;
 use crate::utils::timezone::convert_to_utc;
 use anyhow::{Context, Result};
 use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive};
@@ -731,6 +732,51 @@ impl Database {
         Ok(batches)
     }
 
+    /// BINMaster aisle/row/rack of the given bins, used to order picks by walk path
+    #[instrument(skip(self))]
+    pub async fn get_bin_locations(&self, location: &str, bin_nos: &[String]) -> Result<Vec<BinLocation>> {
+        if bin_nos.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to get read database client")?;
+
+        let placeholders = (2..=bin_nos.len() + 1).map(|i| format!("@P{i}")).collect::<Vec<_>>().join(", ");
+        let query = format!(
+            r#"
+            SELECT BinNo, aisle, row, rack
+            FROM BINMaster
+            WHERE Location = @P1 AND BinNo IN ({placeholders})
+            "#
+        );
+        let mut select = TiberiusQuery::new(query);
+        select.bind(location);
+        for bin_no in bin_nos {
+            select.bind(bin_no.as_str());
+        }
+
+        let rows: Vec<Row> = select
+            .query(&mut client)
+            .await
+            .context("Failed to execute bin locations query")?
+            .into_first_result()
+            .await
+            .context("Failed to get bin locations results")?;
+
+        Ok(rows
+            .iter()
+            .map(|row| BinLocation {
+                bin_no: row.get::<&str, _>("BinNo").unwrap_or_default().trim().to_string(),
+                aisle: row.get::<&str, _>("aisle").unwrap_or_default().trim().to_string(),
+                row: row.get::<&str, _>("row").unwrap_or_default().trim().to_string(),
+                rack: row.get::<&str, _>("rack").unwrap_or_default().trim().to_string(),
+            })
+            .collect())
+    }
+
     /// Get comprehensive inventory information for an item from INMAST and INLOC tables
     #[instrument(skip(self))]
     pub async fn get_inventory_info(&self, run_no: i32, item_key: &str) -> Result<InventoryInfo> {
@@ -1109,6 +1155,7 @@ impl Database {
             formula_desc: formula_desc.to_string(),
             status: status.to_string(),
             batch_count,
+            claims: Vec::new(),
         })
     }
 
@@ -1596,10 +1643,15 @@ impl Database {
     /// Confirm pick operation matching official BME4 workflow exactly
     /// Updates: cust_BulkPicked, Cust_BulkLotPicked, LotMaster, LotTransaction, Cust_BulkPalletLotPicked
     /// Execute a transaction with retry mechanism for concurrency conflicts
-    async fn execute_with_retry<F, Fut, T>(&self, operation: F, max_retries: u32, operation_name: &str) -> Result<T>
+    async fn execute_with_retry<F, Fut, T>(
+        &self,
+        operation: F,
+        max_retries: u32,
+        operation_name: &str,
+    ) -> Result<T, BulkRunError>
     where
         F: Fn() -> Fut,
-        Fut: std::future::Future<Output = Result<T>>,
+        Fut: std::future::Future<Output = Result<T, BulkRunError>>,
     {
         let mut attempt = 0;
         let mut last_error = None;
@@ -1619,15 +1671,7 @@ impl Database {
                     return Ok(result);
                 }
                 Err(e) => {
-                    let error_msg = e.to_string().to_lowercase();
-                    let is_retryable = error_msg.contains("deadlock") 
-                        || error_msg.contains("timeout") 
-                        || error_msg.contains("lock") 
-                        || error_msg.contains("primary key") 
-                        || error_msg.contains("duplicate key")
-                        || error_msg.contains("constraint violation");
-                        
-                    if !is_retryable || attempt >= max_retries {
+                    if !e.is_retryable() || attempt >= max_retries {
                         warn!("❌ RETRY_EXHAUSTED: {} failed after {} attempts: {}", operation_name, attempt + 1, e);
                         return Err(e);
                     }
@@ -1639,7 +1683,7 @@ impl Database {
             }
         }
         
-        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Max retries exceeded for {}", operation_name)))
+        Err(last_error.unwrap_or_else(|| anyhow::anyhow!("Max retries exceeded for {}", operation_name).into()))
     }
 
     #[instrument(skip(self))]
@@ -1647,7 +1691,7 @@ impl Database {
         &self,
         run_no: i32,
         request: &PickConfirmationRequest,
-    ) -> Result<PickConfirmationResponse> {
+    ) -> Result<PickConfirmationResponse, BulkRunError> {
         info!(
             "🚀 TRANSACTION_START: BME4-compatible pick confirmation with retry mechanism for run: {}, lot: {}, qty: {}",
             run_no, request.lot_no, request.picked_bulk_qty
@@ -1666,7 +1710,7 @@ impl Database {
         &self,
         run_no: i32,
         request: &PickConfirmationRequest,
-    ) -> Result<PickConfirmationResponse> {
+    ) -> Result<PickConfirmationResponse, BulkRunError> {
         info!(
             "🚀 TRANSACTION_START: BME4-compatible pick confirmation for run: {}, lot: {}, qty: {}, row_num: {}, line_id: {}, bin_no: {}",
             run_no, request.lot_no, request.picked_bulk_qty, request.row_num, request.line_id, request.bin_no
@@ -1680,7 +1724,7 @@ impl Database {
         let mut sequence_client = self.get_client().await.map_err(|e| {
             let error_msg = format!("Failed to connect to TFCPILOT3 for sequence generation: {e}");
             error!("🔌 SEQUENCE_CONNECTION_FAILED: {}", error_msg);
-            anyhow::anyhow!("SEQUENCE_DATABASE_CONNECTION_FAILED: {}", error_msg)
+            BulkRunError::ConnectionFailed(error_msg)
         })?;
         
         info!("✅ CONNECTION_SEPARATION: Sequence generation client connected");
@@ -1692,15 +1736,23 @@ impl Database {
 
         // **ENHANCED ERROR RECOVERY**: Pre-validation to catch issues early
         info!("🔍 DEBUG: Pre-validation - Checking request parameters");
-        if request.lot_no.is_empty() || request.bin_no.is_empty() {
-            let error_msg = format!("Invalid request parameters - lot_no: '{}', bin_no: '{}'", request.lot_no, request.bin_no);
-            warn!("❌ DEBUG: {}", error_msg);
-            return Err(anyhow::anyhow!("VALIDATION_ERROR: {}", error_msg));
+        let allocations = request.lot_allocations();
+        for allocation in &allocations {
+            if allocation.lot_no.is_empty() || allocation.bin_no.is_empty() {
+                let error_msg = format!("Invalid request parameters - lot_no: '{}', bin_no: '{}'", allocation.lot_no, allocation.bin_no);
+                warn!("❌ DEBUG: {}", error_msg);
+                return Err(BulkRunError::Validation(error_msg));
+            }
+            if allocation.bags <= BigDecimal::from(0) {
+                let error_msg = format!("Invalid picked quantity for lot {}: {}", allocation.lot_no, allocation.bags);
+                warn!("❌ DEBUG: {}", error_msg);
+                return Err(BulkRunError::Validation(error_msg));
+            }
         }
         if request.picked_bulk_qty <= BigDecimal::from(0) {
             let error_msg = format!("Invalid picked quantity: {}", request.picked_bulk_qty);
             warn!("❌ DEBUG: {}", error_msg);
-            return Err(anyhow::anyhow!("VALIDATION_ERROR: {}", error_msg));
+            return Err(BulkRunError::Validation(error_msg));
         }
 
         // **BATCH COMPLETION VALIDATION**: Check if this batch is already completed  
@@ -1711,7 +1763,7 @@ impl Database {
         let mut validation_client = self.get_client().await.map_err(|e| {
             let error_msg = format!("Failed to connect for validation: {e}");
             error!("🔌 VALIDATION_CONNECTION_FAILED: {}", error_msg);
-            anyhow::anyhow!("VALIDATION_DATABASE_CONNECTION_FAILED: {}", error_msg)
+            BulkRunError::ConnectionFailed(error_msg)
         })?;
         
         let validation_query = r#"
@@ -1772,8 +1824,7 @@ impl Database {
                     "❌ BATCH_ALREADY_COMPLETED: run={}, row_num={}, line_id={}, item_key={}, to_picked={}, picked={}, remaining={}, corrected_remaining={}",
                     run_no, request.row_num, request.line_id, item_key, to_picked_qty, picked_qty, remaining_qty, actual_remaining_qty
                 );
-                let user_friendly = "This batch is already completed. Please refresh to load the next batch.";
-                return Err(anyhow::anyhow!("BATCH_ALREADY_COMPLETED: {}", user_friendly));
+                return Err(BulkRunError::BatchAlreadyCompleted);
             }
             
             // Update remaining quantity for subsequent validation
@@ -1785,21 +1836,27 @@ impl Database {
             let requested_qty_rounded = self.round_to_3_decimals(requested_qty);
             let remaining_rounded = self.round_to_3_decimals(remaining_for_validation);
             if self.qty_greater_than(requested_qty_rounded, remaining_rounded) {
-                let error_msg = format!(
-                    "Cannot pick {} bags. Only {} bags remaining in this batch (ItemKey: {}, Batch: RowNum={}, LineId={})",
-                    requested_qty_rounded, remaining_rounded, item_key, request.row_num, request.line_id
-                );
-                warn!("❌ BATCH_VALIDATION: {}", error_msg);
-                return Err(anyhow::anyhow!("INSUFFICIENT_BATCH_QUANTITY: {}", error_msg));
+                let error = BulkRunError::InsufficientBatchQuantity {
+                    requested: requested_qty_rounded,
+                    remaining: remaining_rounded,
+                    item_key: item_key.to_string(),
+                    row_num: request.row_num,
+                    line_id: request.line_id,
+                };
+                warn!("❌ BATCH_VALIDATION: {}", error);
+                return Err(error);
             }
             
             info!("✅ BATCH_VALIDATION: Batch validation passed - can pick {} bags from {} remaining (corrected from {})", 
                   requested_qty_rounded, remaining_rounded, remaining_qty);
         } else {
-            let error_msg = format!("Batch not found: run={}, row_num={}, line_id={}", 
-                                    run_no, request.row_num, request.line_id);
-            warn!("❌ BATCH_VALIDATION: {}", error_msg);
-            return Err(anyhow::anyhow!("BATCH_NOT_FOUND: {}", error_msg));
+            let error = BulkRunError::BatchNotFound {
+                run_no,
+                row_num: request.row_num,
+                line_id: request.line_id,
+            };
+            warn!("❌ BATCH_VALIDATION: {}", error);
+            return Err(error);
         }
 
         // With new architecture using TFCPILOT3 as primary, sync is no longer needed
@@ -1848,9 +1905,9 @@ impl Database {
               picked_qty_f64, picked_qty_f64_rounded, required_qty_f64, required_qty_f64_rounded);
         
         if self.qty_greater_than(picked_qty_f64_rounded, required_qty_f64_rounded) {
-            let error_msg = format!("Quantity picked is more than Qty Required {} KG", required_qty_f64_rounded);
-            warn!("❌ DEBUG: BME4 validation failed - {}", error_msg);
-            return Err(anyhow::anyhow!("QUANTITY_VALIDATION_FAILED: {}", error_msg));
+            let error = BulkRunError::QuantityExceedsRequired { required_kg: required_qty_f64_rounded };
+            warn!("❌ DEBUG: BME4 validation failed - {}", error);
+            return Err(error);
         }
         info!("✅ DEBUG: BME4 quantity validation passed");
         
@@ -1880,7 +1937,7 @@ impl Database {
         let mut client = self.get_client().await.map_err(|e| {
             let error_msg = format!("Failed to create fresh connection for main transaction: {e}");
             error!("🔌 TRANSACTION_CONNECTION_FAILED: {}", error_msg);
-            anyhow::anyhow!("TRANSACTION_DATABASE_CONNECTION_FAILED: {}", error_msg)
+            BulkRunError::ConnectionFailed(error_msg)
         })?;
         
         info!("✅ CONNECTION_FRESH: Main transaction client connected with clean state");
@@ -1899,6 +1956,22 @@ impl Database {
             .context("Failed to begin transaction")?;
         info!("✅ TRANSACTION_STARTED: All 6 steps will be atomic (all-or-nothing)");
 
+        // **IDEMPOTENCY**: A concurrent request with the same key waits here until the first commits
+        if let Some(key) = &request.idempotency_key {
+            match idempotency::find_stored_response(&mut client, key, true).await {
+                Ok(None) => {}
+                Ok(Some(stored)) => {
+                    let _ = client.simple_query("ROLLBACK").await;
+                    info!("🔁 IDEMPOTENT_REPLAY: Pick for run {} already committed under key {}", run_no, key.key);
+                    return Ok(stored.replay(key)?);
+                }
+                Err(e) => {
+                    let _ = client.simple_query("ROLLBACK").await;
+                    return Err(BulkRunError::Database(e));
+                }
+            }
+        }
+
         // Transaction wrapper: Execute all 6 steps and handle rollback on error
         let transaction_result: Result<PickConfirmationResponse> = async {
 
@@ -1906,38 +1979,54 @@ impl Database {
         // This ensures LotMaster is locked before any application tables
         info!("🔒 STEP_0_LOCK: Acquiring exclusive lock on LotMaster to prevent deadlocks");
 
-        let lock_lot_query = r#"
-            SELECT LotNo, ItemKey, QtyOnHand, QtyCommitSales
-            FROM LotMaster WITH (UPDLOCK, ROWLOCK)
-            WHERE LotNo = @P1 AND ItemKey = @P2 AND LocationKey = @P3 AND BinNo = @P4
-        "#;
+        // Every lot of a split pick is locked in lot/bin order, so two picks sharing lots cannot deadlock
+        let mut lock_order: Vec<&LotAllocation> = allocations.iter().collect();
+        lock_order.sort_by(|a, b| (&a.lot_no, &a.bin_no).cmp(&(&b.lot_no, &b.bin_no)));
+        for allocation in lock_order {
+            let allocation_qty_f64 = self.safe_bigdecimal_to_f64(&(&allocation.bags * &batch_info.pack_size), "allocation_qty")?;
+
+            let lock_lot_query = r#"
+                SELECT LotNo, ItemKey, QtyOnHand, QtyCommitSales
+                FROM LotMaster WITH (UPDLOCK, ROWLOCK)
+                WHERE LotNo = @P1 AND ItemKey = @P2 AND LocationKey = @P3 AND BinNo = @P4
+            "#;
 
-        let mut lock_stmt = TiberiusQuery::new(lock_lot_query);
-        lock_stmt.bind(request.lot_no.clone());
-        lock_stmt.bind(batch_info.item_key.clone());
-        lock_stmt.bind("TFC1");
-        lock_stmt.bind(request.bin_no.clone());
+            let mut lock_stmt = TiberiusQuery::new(lock_lot_query);
+            lock_stmt.bind(allocation.lot_no.clone());
+            lock_stmt.bind(batch_info.item_key.clone());
+            lock_stmt.bind("TFC1");
+            lock_stmt.bind(allocation.bin_no.clone());
 
-        let lock_result = lock_stmt.query(&mut client).await
-            .context("Failed to lock LotMaster record")?;
+            let lock_result = lock_stmt.query(&mut client).await
+                .context("Failed to lock LotMaster record")?;
 
-        let lot_row = lock_result.into_row().await?
-            .ok_or_else(|| anyhow::anyhow!("Lot not found for locking"))?;
+            let lot_row = lock_result.into_row().await?
+                .ok_or_else(|| anyhow::anyhow!("Lot not found for locking"))?;
 
-        let current_qty_on_hand: f64 = lot_row.get("QtyOnHand").unwrap_or(0.0);
-        let current_qty_commit: f64 = lot_row.get("QtyCommitSales").unwrap_or(0.0);
-        let available_qty = current_qty_on_hand - current_qty_commit;
+            let current_qty_on_hand: f64 = lot_row.get("QtyOnHand").unwrap_or(0.0);
+            let current_qty_commit: f64 = lot_row.get("QtyCommitSales").unwrap_or(0.0);
+            let available_qty = current_qty_on_hand - current_qty_commit;
+
+            // Validate sufficient quantity BEFORE any updates
+            if allocation_qty_f64 > available_qty {
+                return Err(anyhow::anyhow!(
+                    "Insufficient quantity in lot {}: requested {}, available {}",
+                    allocation.lot_no, allocation_qty_f64, available_qty
+                ));
+            }
 
-        // Validate sufficient quantity BEFORE any updates
-        if picked_qty_f64 > available_qty {
-            return Err(anyhow::anyhow!(
-                "Insufficient quantity: requested {}, available {}",
-                picked_qty_f64, available_qty
-            ));
+            info!("✅ STEP_0_LOCKED: LotMaster locked successfully - Lot: {}, Available: {} KG",
+                  allocation.lot_no, available_qty);
         }
 
-        info!("✅ STEP_0_LOCKED: LotMaster locked successfully - Lot: {}, Available: {} KG",
-              request.lot_no, available_qty);
+        // The claim was checked before the transaction; check it again under lock so a
+        // take-over since then (or a new claim after the lease ran out) stops this pick
+        if !request.override_claim {
+            let user_id = request.user_id.as_deref().unwrap_or_default();
+            if let Some(holder) = run_claims::find_blocking_claim(&mut client, run_no, request.line_id, user_id).await? {
+                return Err(BulkRunError::ClaimedByOther { run_no, line_id: holder.line_id, holder: holder.username }.into());
+            }
+        }
 
         // **STEP 1: UPDATE cust_BulkPicked** - Set picked quantities and timestamps
         info!("🔄 DEBUG: STEP 1 - Updating cust_BulkPicked table");
@@ -1992,7 +2081,7 @@ impl Database {
                 let error_msg = format!("STEP 1 FAILED: UPDATE cust_BulkPicked failed for run: {}, row: {}, line: {} with parameters bulk_qty={}, picked_qty={} - Database error: {}", 
                                run_no, request.row_num, request.line_id, bulk_qty_f64, picked_qty_f64, e);
                 error!("❌ STEP_1_ERROR: {}", error_msg);
-                return Err(anyhow::anyhow!("STEP_1_UPDATE_FAILED: {}", error_msg));
+                return Err(anyhow::Error::new(e).context(error_msg));
             }
         };
         
@@ -2027,7 +2116,7 @@ impl Database {
             };
             
             warn!("❌ DEBUG: {}", error_msg);
-            return Err(anyhow::anyhow!("DATABASE_RECORD_NOT_FOUND: {}", error_msg));
+            return Err(anyhow::anyhow!(error_msg));
         }
         info!("✅ DEBUG: Step 1 completed - Rows affected: {}", affected_rows);
         summary.bulk_picked_updated = true;
@@ -2055,261 +2144,275 @@ impl Database {
         };
         info!("🔢 DEBUG: LotTranNo auto-generated by IDENTITY column, PalletNo: {}, PalletId: {}", pallet_no, pallet_id);
 
-        let insert_lot_picked_query = r#"
-            INSERT INTO Cust_BulkLotPicked
-            (RunNo, RowNum, BatchNo, LineId, LotNo, SuggestedLotNo,
-             ItemKey, LocationKey, BinNo, QtyReceived, AllocLotQty, PalletNo,
-             LotStatus, TransactionType, RecUserid, RecDate,
-             DateReceived, DateExpiry, ReceiptDocNo, ReceiptDocLineNo,
-             Vendorkey, VendorlotNo, IssueDocNo, IssueDocLineNo, IssueDate,
-             CustomerKey, ModifiedBy, ModifiedDate, Processed, TempQty,
-             QtyForLotAssignment, QtyUsed, QtyIssued, PackSize, QtyOnHand,
-             PalletId, User1, User2, User3, User4, User5, User6, User7, User8,
-             User9, User10, User11, User12, CUSTOM1, CUSTOM2, CUSTOM3, CUSTOM4,
-             CUSTOM5, CUSTOM6, CUSTOM7, CUSTOM8, CUSTOM9, CUSTOM10,
-             ESG_REASON, ESG_APPROVER)
-            VALUES
-            (@P1, @P2, @P3, @P4, @P5, @P6,
-             @P7, @P8, @P9, @P10, @P11, @P12,
-             @P13, @P14, @P15, @P16,
-             @P17, @P18, @P19, @P20,
-             @P21, @P22, @P23, @P24, @P25,
-             @P26, @P27, @P28, @P29, @P30,
-             @P31, @P32, @P33, @P34, @P35,
-             @P36, @P37, @P38, @P39, @P40, @P41, @P42, @P43, @P44,
-             @P45, @P46, @P47, @P48, @P49, @P50, @P51, @P52,
-             @P53, @P54, @P55, @P56,
-             @P57, @P58, @P59, @P60)
-        "#;
+        let mut allocated = Vec::with_capacity(allocations.len());
+        for allocation in &allocations {
+            let picked_qty = &allocation.bags * &batch_info.pack_size;
+            let picked_qty_f64 = self.safe_bigdecimal_to_f64(&picked_qty, "allocation_picked_qty")?;
+
+            let insert_lot_picked_query = r#"
+                INSERT INTO Cust_BulkLotPicked
+                (RunNo, RowNum, BatchNo, LineId, LotNo, SuggestedLotNo,
+                 ItemKey, LocationKey, BinNo, QtyReceived, AllocLotQty, PalletNo,
+                 LotStatus, TransactionType, RecUserid, RecDate,
+                 DateReceived, DateExpiry, ReceiptDocNo, ReceiptDocLineNo,
+                 Vendorkey, VendorlotNo, IssueDocNo, IssueDocLineNo, IssueDate,
+                 CustomerKey, ModifiedBy, ModifiedDate, Processed, TempQty,
+                 QtyForLotAssignment, QtyUsed, QtyIssued, PackSize, QtyOnHand,
+                 PalletId, User1, User2, User3, User4, User5, User6, User7, User8,
+                 User9, User10, User11, User12, CUSTOM1, CUSTOM2, CUSTOM3, CUSTOM4,
+                 CUSTOM5, CUSTOM6, CUSTOM7, CUSTOM8, CUSTOM9, CUSTOM10,
+                 ESG_REASON, ESG_APPROVER)
+                VALUES
+                (@P1, @P2, @P3, @P4, @P5, @P6,
+                 @P7, @P8, @P9, @P10, @P11, @P12,
+                 @P13, @P14, @P15, @P16,
+                 @P17, @P18, @P19, @P20,
+                 @P21, @P22, @P23, @P24, @P25,
+                 @P26, @P27, @P28, @P29, @P30,
+                 @P31, @P32, @P33, @P34, @P35,
+                 @P36, @P37, @P38, @P39, @P40, @P41, @P42, @P43, @P44,
+                 @P45, @P46, @P47, @P48, @P49, @P50, @P51, @P52,
+                 @P53, @P54, @P55, @P56,
+                 @P57, @P58, @P59, @P60)
+            "#;
 
-        let mut insert_stmt = TiberiusQuery::new(insert_lot_picked_query);
-        info!("📝 DEBUG: Binding Cust_BulkLotPicked parameters (60 total) to TFCPILOT3 - AUTO-GEN LotTranNo, run_no: {}, batch_no: {}, item_key: {}, lot_no: {}, bin_no: {}", 
-              run_no, batch_info.batch_no, batch_info.item_key, request.lot_no, request.bin_no);
+            let mut insert_stmt = TiberiusQuery::new(insert_lot_picked_query);
+            info!("📝 DEBUG: Binding Cust_BulkLotPicked parameters (60 total) to TFCPILOT3 - AUTO-GEN LotTranNo, run_no: {}, batch_no: {}, item_key: {}, lot_no: {}, bin_no: {}", 
+                  run_no, batch_info.batch_no, batch_info.item_key, allocation.lot_no, allocation.bin_no);
               
-        // Complete parameter binding for TFCPILOT3 schema compliance - LotTranNo will be auto-generated by database
-        insert_stmt.bind(run_no);                             // @P1 - RunNo
-        insert_stmt.bind(request.row_num);                    // @P2 - RowNum  
-        insert_stmt.bind(batch_info.batch_no.clone());        // @P3 - BatchNo
-        insert_stmt.bind(request.line_id);                    // @P4 - LineId
-        insert_stmt.bind(request.lot_no.clone());             // @P5 - LotNo
-        insert_stmt.bind(request.lot_no.clone());             // @P6 - SuggestedLotNo (same as selected lot)
-        insert_stmt.bind(batch_info.item_key.clone());        // @P7 - ItemKey
-        insert_stmt.bind("TFC1");                             // @P8 - LocationKey
-        insert_stmt.bind(request.bin_no.clone());             // @P9 - BinNo
-        insert_stmt.bind(picked_qty_f64);                     // @P10 - QtyReceived
-        insert_stmt.bind(picked_qty_f64);                     // @P11 - AllocLotQty (same)
-        insert_stmt.bind(pallet_no.clone());                  // @P12 - PalletNo
-        insert_stmt.bind("Allocated");                        // @P13 - LotStatus
-        insert_stmt.bind(5u8);                                // @P14 - TransactionType (tinyint)
-        insert_stmt.bind(get_user_id_for_field(&validated_user, UserIdFieldType::RecUseridVarchar)); // @P15 - RecUserid (varchar(8))
-        insert_stmt.bind(bangkok_now.clone());                // @P16 - RecDate
+            // Complete parameter binding for TFCPILOT3 schema compliance - LotTranNo will be auto-generated by database
+            insert_stmt.bind(run_no);                             // @P1 - RunNo
+            insert_stmt.bind(request.row_num);                    // @P2 - RowNum  
+            insert_stmt.bind(batch_info.batch_no.clone());        // @P3 - BatchNo
+            insert_stmt.bind(request.line_id);                    // @P4 - LineId
+            insert_stmt.bind(allocation.lot_no.clone());          // @P5 - LotNo
+            insert_stmt.bind(allocation.lot_no.clone());          // @P6 - SuggestedLotNo (same as selected lot)
+            insert_stmt.bind(batch_info.item_key.clone());        // @P7 - ItemKey
+            insert_stmt.bind("TFC1");                             // @P8 - LocationKey
+            insert_stmt.bind(allocation.bin_no.clone());          // @P9 - BinNo
+            insert_stmt.bind(picked_qty_f64);                     // @P10 - QtyReceived
+            insert_stmt.bind(picked_qty_f64);                     // @P11 - AllocLotQty (same)
+            insert_stmt.bind(pallet_no.clone());                  // @P12 - PalletNo
+            insert_stmt.bind("Allocated");                        // @P13 - LotStatus
+            insert_stmt.bind(5u8);                                // @P14 - TransactionType (tinyint)
+            insert_stmt.bind(get_user_id_for_field(&validated_user, UserIdFieldType::RecUseridVarchar)); // @P15 - RecUserid (varchar(8))
+            insert_stmt.bind(bangkok_now.clone());                // @P16 - RecDate
         
-        // Additional required parameters for TFCPILOT3 schema (P17-P60) - LotTranNo removed from parameters
-        insert_stmt.bind(bangkok_now.clone());                // @P17 - DateReceived
-        insert_stmt.bind(bangkok_now.clone());                // @P18 - DateExpiry (placeholder)
-        insert_stmt.bind("");                                 // @P19 - ReceiptDocNo
-        insert_stmt.bind(0i16);                               // @P20 - ReceiptDocLineNo (smallint)
-        insert_stmt.bind("");                                 // @P21 - Vendorkey
-        insert_stmt.bind("");                                 // @P22 - VendorlotNo  
-        insert_stmt.bind("");                                 // @P23 - IssueDocNo
-        insert_stmt.bind(0i16);                               // @P24 - IssueDocLineNo (smallint)
-        insert_stmt.bind(None::<&str>);                       // @P25 - IssueDate (null - allocation record, not issue)
-        insert_stmt.bind("");                                 // @P26 - CustomerKey
-        insert_stmt.bind("");                                 // @P27 - ModifiedBy (empty string per official app)
-        insert_stmt.bind(bangkok_now.clone());                // @P28 - ModifiedDate
-        insert_stmt.bind("N");                                // @P29 - Processed (char)
-        insert_stmt.bind(0.0f64);                             // @P30 - TempQty
-        insert_stmt.bind(0.0f64);                             // @P31 - QtyForLotAssignment
-        insert_stmt.bind(0.0f64);                             // @P32 - QtyUsed
-        insert_stmt.bind(0.0f64);                             // @P33 - QtyIssued (0 - allocation record, not actual issue)
-        insert_stmt.bind(pack_size_f64);                      // @P34 - PackSize (from batch_info, not hardcoded)
-        insert_stmt.bind(picked_qty_f64);                     // @P35 - QtyOnHand
-        insert_stmt.bind(pallet_id.clone());                  // @P36 - PalletId (6-digit sequential)
-        insert_stmt.bind("");                                 // @P37 - User1
-        insert_stmt.bind("");                                 // @P38 - User2  
-        insert_stmt.bind("");                                 // @P39 - User3
-        insert_stmt.bind("");                                 // @P40 - User4
-        insert_stmt.bind("");                                 // @P41 - User5
-        insert_stmt.bind(bangkok_now.clone());                // @P42 - User6 (datetime)
-        insert_stmt.bind(0.0f64);                             // @P43 - User7 (float)
-        insert_stmt.bind(0.0f64);                             // @P44 - User8 (float)
-        insert_stmt.bind(0.0f64);                             // @P45 - User9 (decimal as f64)
-        insert_stmt.bind(0.0f64);                             // @P46 - User10 (decimal as f64)
-        insert_stmt.bind(0i32);                               // @P47 - User11 (int)
-        insert_stmt.bind(0i32);                               // @P48 - User12 (int)
-        insert_stmt.bind(false);                              // @P49 - CUSTOM1 (bit)
-        insert_stmt.bind(false);                              // @P50 - CUSTOM2 (bit)
-        insert_stmt.bind(false);                              // @P51 - CUSTOM3 (bit)
-        insert_stmt.bind(false);                              // @P52 - CUSTOM4 (bit)
-        insert_stmt.bind(false);                              // @P53 - CUSTOM5 (bit)
-        insert_stmt.bind(false);                              // @P54 - CUSTOM6 (bit)
-        insert_stmt.bind(false);                              // @P55 - CUSTOM7 (bit)
-        insert_stmt.bind(false);                              // @P56 - CUSTOM8 (bit)
-        insert_stmt.bind(false);                              // @P57 - CUSTOM9 (bit)
-        insert_stmt.bind(false);                              // @P58 - CUSTOM10 (bit)
-        insert_stmt.bind("");                                 // @P59 - ESG_REASON
-        insert_stmt.bind("");                                 // @P60 - ESG_APPROVER
-
-        let insert_result = match insert_stmt.execute(&mut client).await {
-            Ok(result) => {
-                info!("✅ STEP_2_SUCCESS: INSERT Cust_BulkLotPicked executed successfully with key parameters (LotTranNo auto-generated): run_no={}, batch_no={}, lot_no={}, item_key={}, bin_no={}, qty_received={}, pallet_no={}, pallet_id={}", 
-                      run_no, batch_info.batch_no, request.lot_no, batch_info.item_key, request.bin_no, picked_qty_f64, pallet_no, pallet_id);
-                result
-            }
-            Err(e) => {
-                let error_msg = format!("STEP 2 FAILED: INSERT Cust_BulkLotPicked failed with key parameters (LotTranNo auto-generated): run_no={}, batch_no={}, lot_no={}, item_key={}, bin_no={}, qty_received={}, pallet_no={}, pallet_id={} - Database error: {} - Check table schema, constraints, or data types", 
-                               run_no, batch_info.batch_no, request.lot_no, batch_info.item_key, request.bin_no, picked_qty_f64, pallet_no, pallet_id, e);
-                error!("❌ STEP_2_ERROR: {}", error_msg);
-                return Err(anyhow::anyhow!("STEP_2_INSERT_FAILED: {}", error_msg));
+            // Additional required parameters for TFCPILOT3 schema (P17-P60) - LotTranNo removed from parameters
+            insert_stmt.bind(bangkok_now.clone());                // @P17 - DateReceived
+            insert_stmt.bind(bangkok_now.clone());                // @P18 - DateExpiry (placeholder)
+            insert_stmt.bind("");                                 // @P19 - ReceiptDocNo
+            insert_stmt.bind(0i16);                               // @P20 - ReceiptDocLineNo (smallint)
+            insert_stmt.bind("");                                 // @P21 - Vendorkey
+            insert_stmt.bind("");                                 // @P22 - VendorlotNo  
+            insert_stmt.bind("");                                 // @P23 - IssueDocNo
+            insert_stmt.bind(0i16);                               // @P24 - IssueDocLineNo (smallint)
+            insert_stmt.bind(None::<&str>);                       // @P25 - IssueDate (null - allocation record, not issue)
+            insert_stmt.bind("");                                 // @P26 - CustomerKey
+            insert_stmt.bind("");                                 // @P27 - ModifiedBy (empty string per official app)
+            insert_stmt.bind(bangkok_now.clone());                // @P28 - ModifiedDate
+            insert_stmt.bind("N");                                // @P29 - Processed (char)
+            insert_stmt.bind(0.0f64);                             // @P30 - TempQty
+            insert_stmt.bind(0.0f64);                             // @P31 - QtyForLotAssignment
+            insert_stmt.bind(0.0f64);                             // @P32 - QtyUsed
+            insert_stmt.bind(0.0f64);                             // @P33 - QtyIssued (0 - allocation record, not actual issue)
+            insert_stmt.bind(pack_size_f64);                      // @P34 - PackSize (from batch_info, not hardcoded)
+            insert_stmt.bind(picked_qty_f64);                     // @P35 - QtyOnHand
+            insert_stmt.bind(pallet_id.clone());                  // @P36 - PalletId (6-digit sequential)
+            insert_stmt.bind("");                                 // @P37 - User1
+            insert_stmt.bind("");                                 // @P38 - User2  
+            insert_stmt.bind("");                                 // @P39 - User3
+            insert_stmt.bind("");                                 // @P40 - User4
+            insert_stmt.bind("");                                 // @P41 - User5
+            insert_stmt.bind(bangkok_now.clone());                // @P42 - User6 (datetime)
+            insert_stmt.bind(0.0f64);                             // @P43 - User7 (float)
+            insert_stmt.bind(0.0f64);                             // @P44 - User8 (float)
+            insert_stmt.bind(0.0f64);                             // @P45 - User9 (decimal as f64)
+            insert_stmt.bind(0.0f64);                             // @P46 - User10 (decimal as f64)
+            insert_stmt.bind(0i32);                               // @P47 - User11 (int)
+            insert_stmt.bind(0i32);                               // @P48 - User12 (int)
+            insert_stmt.bind(false);                              // @P49 - CUSTOM1 (bit)
+            insert_stmt.bind(false);                              // @P50 - CUSTOM2 (bit)
+            insert_stmt.bind(false);                              // @P51 - CUSTOM3 (bit)
+            insert_stmt.bind(false);                              // @P52 - CUSTOM4 (bit)
+            insert_stmt.bind(false);                              // @P53 - CUSTOM5 (bit)
+            insert_stmt.bind(false);                              // @P54 - CUSTOM6 (bit)
+            insert_stmt.bind(false);                              // @P55 - CUSTOM7 (bit)
+            insert_stmt.bind(false);                              // @P56 - CUSTOM8 (bit)
+            insert_stmt.bind(false);                              // @P57 - CUSTOM9 (bit)
+            insert_stmt.bind(false);                              // @P58 - CUSTOM10 (bit)
+            insert_stmt.bind("");                                 // @P59 - ESG_REASON
+            insert_stmt.bind("");                                 // @P60 - ESG_APPROVER
+
+            let insert_result = match insert_stmt.execute(&mut client).await {
+                Ok(result) => {
+                    info!("✅ STEP_2_SUCCESS: INSERT Cust_BulkLotPicked executed successfully with key parameters (LotTranNo auto-generated): run_no={}, batch_no={}, lot_no={}, item_key={}, bin_no={}, qty_received={}, pallet_no={}, pallet_id={}", 
+                          run_no, batch_info.batch_no, allocation.lot_no, batch_info.item_key, allocation.bin_no, picked_qty_f64, pallet_no, pallet_id);
+                    result
+                }
+                Err(e) => {
+                    let error_msg = format!("STEP 2 FAILED: INSERT Cust_BulkLotPicked failed with key parameters (LotTranNo auto-generated): run_no={}, batch_no={}, lot_no={}, item_key={}, bin_no={}, qty_received={}, pallet_no={}, pallet_id={} - Database error: {} - Check table schema, constraints, or data types", 
+                                   run_no, batch_info.batch_no, allocation.lot_no, batch_info.item_key, allocation.bin_no, picked_qty_f64, pallet_no, pallet_id, e);
+                    error!("❌ STEP_2_ERROR: {}", error_msg);
+                    return Err(anyhow::Error::new(e).context(error_msg));
+                }
+            };
+
+            let insert_affected_rows = insert_result.rows_affected().iter().sum::<u64>();
+            if insert_affected_rows == 0 {
+                let error_msg = "STEP 2 FAILED: No rows inserted into Cust_BulkLotPicked. Auto-generated LotTranNo or table constraints may have failed";
+                warn!("❌ DEBUG: {}", error_msg);
+                return Err(anyhow::anyhow!(error_msg));
             }
-        };
+            info!("✅ DEBUG: Step 2 completed - Rows affected: {}", insert_affected_rows);
+            summary.lot_picked_created = true;
 
-        let insert_affected_rows = insert_result.rows_affected().iter().sum::<u64>();
-        if insert_affected_rows == 0 {
-            let error_msg = "STEP 2 FAILED: No rows inserted into Cust_BulkLotPicked. Auto-generated LotTranNo or table constraints may have failed";
-            warn!("❌ DEBUG: {}", error_msg);
-            return Err(anyhow::anyhow!("DATABASE_INSERT_FAILED: {}", error_msg));
-        }
-        info!("✅ DEBUG: Step 2 completed - Rows affected: {}", insert_affected_rows);
-        summary.lot_picked_created = true;
-
-        // **STEP 3: UPDATE LotMaster** - Add to QtyCommitSales for inventory commitment
-        info!("🔄 DEBUG: STEP 3 - Updating LotMaster QtyCommitSales");
-        let update_lot_master_query = r#"
-            UPDATE LotMaster
-            SET QtyCommitSales = QtyCommitSales + @P1
-            WHERE LotNo = @P2 
-              AND ItemKey = @P3 
-              AND LocationKey = @P4 
-              AND BinNo = @P5
-        "#;
+            // **STEP 3: UPDATE LotMaster** - Add to QtyCommitSales for inventory commitment
+            info!("🔄 DEBUG: STEP 3 - Updating LotMaster QtyCommitSales");
+            let update_lot_master_query = r#"
+                UPDATE LotMaster
+                SET QtyCommitSales = QtyCommitSales + @P1
+                WHERE LotNo = @P2 
+                  AND ItemKey = @P3 
+                  AND LocationKey = @P4 
+                  AND BinNo = @P5
+            "#;
 
-        let mut lot_master_stmt = TiberiusQuery::new(update_lot_master_query);
-        info!("📝 DEBUG: Binding LotMaster parameters - picked_qty: {} (converted from {}), lot_no: {}, item_key: {}, location: TFC1, bin_no: {}", 
-              picked_qty_f64, picked_qty, request.lot_no, batch_info.item_key, request.bin_no);
+            let mut lot_master_stmt = TiberiusQuery::new(update_lot_master_query);
+            info!("📝 DEBUG: Binding LotMaster parameters - picked_qty: {} (converted from {}), lot_no: {}, item_key: {}, location: TFC1, bin_no: {}", 
+                  picked_qty_f64, picked_qty, allocation.lot_no, batch_info.item_key, allocation.bin_no);
               
-        lot_master_stmt.bind(picked_qty_f64);
-        lot_master_stmt.bind(request.lot_no.clone());
-        lot_master_stmt.bind(batch_info.item_key.clone());
-        lot_master_stmt.bind(crate::database::DEFAULT_LOCATION_KEY);
-        lot_master_stmt.bind(request.bin_no.clone());
+            lot_master_stmt.bind(picked_qty_f64);
+            lot_master_stmt.bind(allocation.lot_no.clone());
+            lot_master_stmt.bind(batch_info.item_key.clone());
+            lot_master_stmt.bind(crate::database::DEFAULT_LOCATION_KEY);
+            lot_master_stmt.bind(allocation.bin_no.clone());
 
-        let lot_update_result = match lot_master_stmt.execute(&mut client).await {
-            Ok(result) => {
-                info!("✅ STEP_3_SUCCESS: UPDATE LotMaster QtyCommitSales executed successfully with parameters: picked_qty={}, lot_no={}, item_key={}, location=TFC1, bin_no={}", 
-                      picked_qty_f64, request.lot_no, batch_info.item_key, request.bin_no);
-                result
-            }
-            Err(e) => {
-                let error_msg = format!("STEP 3 FAILED: UPDATE LotMaster failed with parameters: picked_qty={}, lot_no={}, item_key={}, location=TFC1, bin_no={} using TFCPILOT3 primary database - Database error: {}", 
-                               picked_qty_f64, request.lot_no, batch_info.item_key, request.bin_no, e);
-                error!("❌ STEP_3_ERROR: {}", error_msg);
-                return Err(anyhow::anyhow!("STEP_3_UPDATE_FAILED: {}", error_msg));
-            }
-        };
+            let lot_update_result = match lot_master_stmt.execute(&mut client).await {
+                Ok(result) => {
+                    info!("✅ STEP_3_SUCCESS: UPDATE LotMaster QtyCommitSales executed successfully with parameters: picked_qty={}, lot_no={}, item_key={}, location=TFC1, bin_no={}", 
+                          picked_qty_f64, allocation.lot_no, batch_info.item_key, allocation.bin_no);
+                    result
+                }
+                Err(e) => {
+                    let error_msg = format!("STEP 3 FAILED: UPDATE LotMaster failed with parameters: picked_qty={}, lot_no={}, item_key={}, location=TFC1, bin_no={} using TFCPILOT3 primary database - Database error: {}", 
+                                   picked_qty_f64, allocation.lot_no, batch_info.item_key, allocation.bin_no, e);
+                    error!("❌ STEP_3_ERROR: {}", error_msg);
+                    return Err(anyhow::Error::new(e).context(error_msg));
+                }
+            };
 
-        let lot_affected_rows = lot_update_result.rows_affected().iter().sum::<u64>();
-        if lot_affected_rows == 0 {
-            // Enhanced debugging: Check if record exists before failing
-            let check_query = r#"
-                SELECT COUNT(*) as record_count, LotNo, ItemKey, LocationKey, BinNo, QtyOnHand, QtyCommitSales
-                FROM LotMaster 
-                WHERE LotNo = @P1 AND ItemKey = @P2 AND LocationKey = @P3 AND BinNo = @P4
-                GROUP BY LotNo, ItemKey, LocationKey, BinNo, QtyOnHand, QtyCommitSales
-            "#;
+            let lot_affected_rows = lot_update_result.rows_affected().iter().sum::<u64>();
+            if lot_affected_rows == 0 {
+                // Enhanced debugging: Check if record exists before failing
+                let check_query = r#"
+                    SELECT COUNT(*) as record_count, LotNo, ItemKey, LocationKey, BinNo, QtyOnHand, QtyCommitSales
+                    FROM LotMaster 
+                    WHERE LotNo = @P1 AND ItemKey = @P2 AND LocationKey = @P3 AND BinNo = @P4
+                    GROUP BY LotNo, ItemKey, LocationKey, BinNo, QtyOnHand, QtyCommitSales
+                "#;
             
-            let mut check_stmt = TiberiusQuery::new(check_query);
-            check_stmt.bind(request.lot_no.clone());
-            check_stmt.bind(batch_info.item_key.clone());
-            check_stmt.bind("TFC1");
-            check_stmt.bind(request.bin_no.clone());
+                let mut check_stmt = TiberiusQuery::new(check_query);
+                check_stmt.bind(allocation.lot_no.clone());
+                check_stmt.bind(batch_info.item_key.clone());
+                check_stmt.bind("TFC1");
+                check_stmt.bind(allocation.bin_no.clone());
             
-            let debug_info = if let Ok(stream) = check_stmt.query(&mut client).await {
-                let rows: Vec<Row> = stream.into_first_result().await.unwrap_or_default();
-                if let Some(row) = rows.first() {
-                    format!("LotMaster record exists: count={}, lot={}, item={}, location={}, bin={}, qty_on_hand={}, qty_commit_sales={}", 
-                        row.get::<i32, _>("record_count").unwrap_or(0),
-                        row.get::<&str, _>("LotNo").unwrap_or("N/A"),
-                        row.get::<&str, _>("ItemKey").unwrap_or("N/A"),
-                        row.get::<&str, _>("LocationKey").unwrap_or("N/A"),
-                        row.get::<&str, _>("BinNo").unwrap_or("N/A"),
-                        row.get::<f64, _>("QtyOnHand").unwrap_or(0.0),
-                        row.get::<f64, _>("QtyCommitSales").unwrap_or(0.0)
-                    )
+                let debug_info = if let Ok(stream) = check_stmt.query(&mut client).await {
+                    let rows: Vec<Row> = stream.into_first_result().await.unwrap_or_default();
+                    if let Some(row) = rows.first() {
+                        format!("LotMaster record exists: count={}, lot={}, item={}, location={}, bin={}, qty_on_hand={}, qty_commit_sales={}", 
+                            row.get::<i32, _>("record_count").unwrap_or(0),
+                            row.get::<&str, _>("LotNo").unwrap_or("N/A"),
+                            row.get::<&str, _>("ItemKey").unwrap_or("N/A"),
+                            row.get::<&str, _>("LocationKey").unwrap_or("N/A"),
+                            row.get::<&str, _>("BinNo").unwrap_or("N/A"),
+                            row.get::<f64, _>("QtyOnHand").unwrap_or(0.0),
+                            row.get::<f64, _>("QtyCommitSales").unwrap_or(0.0)
+                        )
+                    } else {
+                        "LotMaster record NOT FOUND - lot/bin/item combination doesn't exist".to_string()
+                    }
                 } else {
-                    "LotMaster record NOT FOUND - lot/bin/item combination doesn't exist".to_string()
+                    "Failed to query LotMaster for debugging".to_string()
+                };
+
+                let error_msg = format!("STEP 3 FAILED: No rows updated in LotMaster using TFCPILOT3 primary database. Lot: {}, Item: {}, Bin: {}. Debug: {}", 
+                    allocation.lot_no, batch_info.item_key, allocation.bin_no, debug_info);
+                error!("❌ STEP_3_DETAILED_ERROR: {}", error_msg);
+                return Err(anyhow::anyhow!(error_msg));
+            }
+            info!("✅ DEBUG: Step 3 completed - Rows affected: {}", lot_affected_rows);
+            summary.lot_master_updated = true;
+
+            // **STEP 4: INSERT LotTransaction** - Create audit trail using pre-generated BT document
+            info!("🔄 DEBUG: STEP 4 - Creating LotTransaction audit trail with pre-generated BT document: {}", bt_document);
+
+            let insert_lot_transaction_query = r#"
+                INSERT INTO LotTransaction
+                (LotNo, ItemKey, LocationKey, TransactionType,
+                 QtyIssued, IssueDocNo, IssueDocLineNo, IssueDate, 
+                 ReceiptDocNo, RecUserid, RecDate, BinNo, CustomerKey, User5)
+                OUTPUT INSERTED.LotTranNo
+                VALUES
+                (@P1, @P2, @P3, @P4,
+                 @P5, @P6, @P7, @P8, 
+                 @P9, @P10, @P11, @P12, @P13, @P14)
+            "#;
+
+            let mut transaction_stmt = TiberiusQuery::new(insert_lot_transaction_query);
+            info!("📝 DEBUG: Binding LotTransaction parameters - lot_no: {}, item_key: {}, qty_issued: {}, batch_no: {}, bt_document: {}", 
+                  allocation.lot_no, batch_info.item_key, picked_qty_f64, batch_info.batch_no, bt_document);
+              
+            transaction_stmt.bind(allocation.lot_no.clone());
+            transaction_stmt.bind(batch_info.item_key.clone());
+            transaction_stmt.bind("TFC1");
+            transaction_stmt.bind(5); // TransactionType for picking operation
+            transaction_stmt.bind(picked_qty_f64);
+            transaction_stmt.bind(batch_info.batch_no.clone()); // IssueDocNo links to batch
+            transaction_stmt.bind(request.line_id); // IssueDocLineNo
+            transaction_stmt.bind(bangkok_now.clone()); // IssueDate (pick timestamp)
+            transaction_stmt.bind(bt_document.clone()); // ReceiptDocNo (BT-XXXXXXXX)
+            transaction_stmt.bind(get_user_id_for_field(&validated_user, UserIdFieldType::RecUseridVarchar)); // RecUserid (varchar(8))
+            transaction_stmt.bind(bangkok_now.clone()); // RecDate (transaction timestamp)
+            transaction_stmt.bind(allocation.bin_no.clone());
+            transaction_stmt.bind(""); // CustomerKey - empty string for bulk production picking
+            transaction_stmt.bind("Picking Customization"); // Transaction source
+
+            let transaction_result = match transaction_stmt.query(&mut client).await {
+                Ok(result) => {
+                    info!("✅ STEP_4_SUCCESS: INSERT LotTransaction executed successfully");
+                    result
+                }
+                Err(e) => {
+                    let error_msg = format!("STEP 4 FAILED: INSERT LotTransaction failed with document: {bt_document} - Database error: {e}");
+                    error!("❌ STEP_4_ERROR: {}", error_msg);
+                    return Err(anyhow::Error::new(e).context(error_msg));
                 }
+            };
+
+            // Get the auto-generated LotTranNo from the OUTPUT clause
+            let generated_lot_tran_no = if let Some(row) = transaction_result.into_row().await? {
+                row.get::<i32, _>(0).unwrap_or(0)
             } else {
-                "Failed to query LotMaster for debugging".to_string()
+                let error_msg = "STEP 4 FAILED: No LotTranNo returned from LotTransaction INSERT";
+                warn!("❌ DEBUG: {}", error_msg);
+                return Err(anyhow::anyhow!(error_msg));
             };
 
-            let error_msg = format!("STEP 3 FAILED: No rows updated in LotMaster using TFCPILOT3 primary database. Lot: {}, Item: {}, Bin: {}. Debug: {}", 
-                request.lot_no, batch_info.item_key, request.bin_no, debug_info);
-            error!("❌ STEP_3_DETAILED_ERROR: {}", error_msg);
-            return Err(anyhow::anyhow!("LOT_UPDATE_FAILED: {}", error_msg));
+            info!("✅ DEBUG: Step 4 completed - Generated LotTranNo: {}", generated_lot_tran_no);
+            summary.lot_transaction_created = true;
+
+            allocated.push(AllocatedLot {
+                lot_no: allocation.lot_no.clone(),
+                bin_no: allocation.bin_no.clone(),
+                picked_bulk_qty: allocation.bags.clone(),
+                picked_qty,
+                lot_tran_no: generated_lot_tran_no as i64,
+            });
         }
-        info!("✅ DEBUG: Step 3 completed - Rows affected: {}", lot_affected_rows);
-        summary.lot_master_updated = true;
         summary.total_committed_qty = picked_qty.clone();
 
-        // **STEP 4: INSERT LotTransaction** - Create audit trail using pre-generated BT document
-        info!("🔄 DEBUG: STEP 4 - Creating LotTransaction audit trail with pre-generated BT document: {}", bt_document);
-
-        let insert_lot_transaction_query = r#"
-            INSERT INTO LotTransaction
-            (LotNo, ItemKey, LocationKey, TransactionType,
-             QtyIssued, IssueDocNo, IssueDocLineNo, IssueDate, 
-             ReceiptDocNo, RecUserid, RecDate, BinNo, CustomerKey, User5)
-            OUTPUT INSERTED.LotTranNo
-            VALUES
-            (@P1, @P2, @P3, @P4,
-             @P5, @P6, @P7, @P8, 
-             @P9, @P10, @P11, @P12, @P13, @P14)
-        "#;
-
-        let mut transaction_stmt = TiberiusQuery::new(insert_lot_transaction_query);
-        info!("📝 DEBUG: Binding LotTransaction parameters - lot_no: {}, item_key: {}, qty_issued: {}, batch_no: {}, bt_document: {}", 
-              request.lot_no, batch_info.item_key, picked_qty_f64, batch_info.batch_no, bt_document);
-              
-        transaction_stmt.bind(request.lot_no.clone());
-        transaction_stmt.bind(batch_info.item_key.clone());
-        transaction_stmt.bind("TFC1");
-        transaction_stmt.bind(5); // TransactionType for picking operation
-        transaction_stmt.bind(picked_qty_f64);
-        transaction_stmt.bind(batch_info.batch_no.clone()); // IssueDocNo links to batch
-        transaction_stmt.bind(request.line_id); // IssueDocLineNo
-        transaction_stmt.bind(bangkok_now.clone()); // IssueDate (pick timestamp)
-        transaction_stmt.bind(bt_document.clone()); // ReceiptDocNo (BT-XXXXXXXX)
-        transaction_stmt.bind(get_user_id_for_field(&validated_user, UserIdFieldType::RecUseridVarchar)); // RecUserid (varchar(8))
-        transaction_stmt.bind(bangkok_now.clone()); // RecDate (transaction timestamp)
-        transaction_stmt.bind(request.bin_no.clone());
-        transaction_stmt.bind(""); // CustomerKey - empty string for bulk production picking
-        transaction_stmt.bind("Picking Customization"); // Transaction source
-
-        let transaction_result = match transaction_stmt.query(&mut client).await {
-            Ok(result) => {
-                info!("✅ STEP_4_SUCCESS: INSERT LotTransaction executed successfully");
-                result
-            }
-            Err(e) => {
-                let error_msg = format!("STEP 4 FAILED: INSERT LotTransaction failed with document: {bt_document} - Database error: {e}");
-                error!("❌ STEP_4_ERROR: {}", error_msg);
-                return Err(anyhow::anyhow!("STEP_4_INSERT_FAILED: {}", error_msg));
-            }
-        };
-
-        // Get the auto-generated LotTranNo from the OUTPUT clause
-        let generated_lot_tran_no = if let Some(row) = transaction_result.into_row().await? {
-            row.get::<i32, _>(0).unwrap_or(0)
-        } else {
-            let error_msg = "STEP 4 FAILED: No LotTranNo returned from LotTransaction INSERT";
-            warn!("❌ DEBUG: {}", error_msg);
-            return Err(anyhow::anyhow!("TRANSACTION_INSERT_FAILED: {}", error_msg));
-        };
-
-        info!("✅ DEBUG: Step 4 completed - Generated LotTranNo: {}", generated_lot_tran_no);
-        summary.lot_transaction_created = true;
-
         // **STEP 5: UPSERT pallet-lot traceability (CRITICAL FIX)**
         // Use MERGE to UPDATE existing record or INSERT new one for proper duplicate handling
         let pallet_upsert_query = r#"
@@ -2365,7 +2468,7 @@ impl Database {
             Err(e) => {
                 let error_msg = format!("STEP 5 FAILED: UPSERT Cust_BulkPalletLotPicked failed with PalletID: {pallet_id} - Database error: {e}");
                 error!("❌ STEP_5_ERROR: {}", error_msg);
-                return Err(anyhow::anyhow!("STEP_5_UPSERT_FAILED: {}", error_msg));
+                return Err(anyhow::Error::new(e).context(error_msg));
             }
         };
 
@@ -2470,7 +2573,7 @@ impl Database {
                 // For consistency with transaction_service, this SHOULD be critical
                 let error_msg = format!("STEP 6 FAILED: PNITEM update failed - Database error: {e}");
                 error!("❌ STEP_6_ERROR: {}", error_msg);
-                return Err(anyhow::anyhow!("STEP_6_UPDATE_FAILED: {}", error_msg));
+                return Err(anyhow::Error::new(e).context(error_msg));
             }
         }
 
@@ -2532,12 +2635,24 @@ impl Database {
             info!("✅ ALL_STEPS_SUCCEEDED: Ready to commit transaction");
             info!("🔄 TRANSACTION_TIMESTAMP: Pick transaction completed at {}", chrono::Utc::now().format("%Y-%m-%d %H:%M:%S%.3f UTC"));
 
-            Ok(PickConfirmationResponse {
+            let response = PickConfirmationResponse {
                 success: true,
-                transaction_id: Some(generated_lot_tran_no as i64),
+                transaction_id: allocated.first().map(|lot| lot.lot_tran_no),
                 document_no: Some(bt_document),
                 updated_records: summary,
-            })
+                allocations: allocated,
+                warnings: Vec::new(),
+            };
+
+            // **IDEMPOTENCY**: Store the response so it commits together with the pick
+            if let Some(key) = &request.idempotency_key {
+                let body = serde_json::to_string(&response)
+                    .context("Failed to serialize pick response for idempotency")?;
+                idempotency::store_response(&mut client, key, &body).await?;
+                info!("✅ IDEMPOTENCY_STORED: Response recorded for key {}", key.key);
+            }
+
+            Ok(response)
         }.await;
 
         // **🔒 ATOMIC TRANSACTION HANDLING** - COMMIT on success, ROLLBACK on error
@@ -2555,9 +2670,18 @@ impl Database {
                 // Any step failed - rollback the entire transaction
                 let _ = client.simple_query("ROLLBACK").await;
 
+                // Losing the claim mid-pick is a conflict for the picker, not a failed transaction
+                let e = match e.downcast::<BulkRunError>() {
+                    Ok(conflict) => {
+                        warn!("⚠️ CLAIM: pick on run {} line {} refused: {}", run_no, request.line_id, conflict);
+                        return Err(conflict);
+                    }
+                    Err(e) => e,
+                };
+
                 error!("💥 TRANSACTION_ROLLED_BACK: Error during bulk picking - all changes reverted: {}", e);
                 error!("🔄 DATA_INTEGRITY: No partial state - database remains consistent");
-                Err(anyhow::anyhow!("BULK_PICKING_FAILED_AND_ROLLED_BACK: {}", e))
+                Err(BulkRunError::TransactionRolledBack(e))
             }
         }
     }
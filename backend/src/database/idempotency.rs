@@ -0,0 +1,76 @@
+use anyhow::{Context, Result};
+use tiberius::Client;
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+
+use crate::database::Database;
+use crate::models::idempotency::{IdempotencyKey, StoredResponse};
+
+/// Look up the response stored for a key.
+/// With `lock`, the key range stays locked until the surrounding transaction ends, so a
+/// concurrent request with the same key waits for this one and then replays its result.
+pub async fn find_stored_response(
+    client: &mut Client<Compat<TcpStream>>,
+    key: &IdempotencyKey,
+    lock: bool,
+) -> Result<Option<StoredResponse>> {
+    let hints = if lock { "WITH (UPDLOCK, HOLDLOCK)" } else { "" };
+    let query = format!(
+        r#"
+        SELECT RequestHash, ResponseBody
+        FROM tbl_idempotency_keys {hints}
+        WHERE Username = @P1 AND Operation = @P2 AND IdempotencyKey = @P3
+        "#
+    );
+
+    let row = client
+        .query(query, &[&key.username, &key.operation.as_str(), &key.key])
+        .await
+        .context("Failed to query idempotency key")?
+        .into_row()
+        .await
+        .context("Failed to read idempotency key")?;
+
+    Ok(row.map(|row| StoredResponse {
+        request_hash: row.get::<&str, _>("RequestHash").unwrap_or_default().to_string(),
+        response_body: row.get::<&str, _>("ResponseBody").unwrap_or_default().to_string(),
+    }))
+}
+
+/// Record the response for a key inside the caller's transaction, so it commits
+/// (or rolls back) together with the inventory changes it describes
+pub async fn store_response(
+    client: &mut Client<Compat<TcpStream>>,
+    key: &IdempotencyKey,
+    response_body: &str,
+) -> Result<()> {
+    client
+        .execute(
+            r#"
+            INSERT INTO tbl_idempotency_keys
+                (Username, Operation, IdempotencyKey, RequestHash, ResponseBody, CreatedAt)
+            VALUES (@P1, @P2, @P3, @P4, @P5, SYSUTCDATETIME())
+            "#,
+            &[
+                &key.username,
+                &key.operation.as_str(),
+                &key.key,
+                &key.request_hash,
+                &response_body,
+            ],
+        )
+        .await
+        .context("Failed to store idempotent response")?;
+    Ok(())
+}
+
+impl Database {
+    /// Response already recorded for a key, checked before a write is validated
+    pub async fn find_idempotent_response(&self, key: &IdempotencyKey) -> Result<Option<StoredResponse>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for idempotency lookup")?;
+        find_stored_response(&mut client, key, false).await
+    }
+}
@@ -6,10 +6,22 @@ use std::time::Duration;
 use tiberius::{AuthMethod, Config, EncryptionLevel, Query, Row};
 use tracing::info;
 
+pub mod api_keys;
+pub mod auth_sessions;
 pub mod bulk_runs;
 pub mod bulk_runs_intelligence;
+pub mod idempotency;
+pub mod pallets;
+pub mod pick_plans;
+pub mod pick_scans;
+pub mod print_jobs;
 pub mod putaway;
+pub mod security_audit;
 pub mod putaway_db;
+pub mod repository;
+pub mod run_claims;
+pub mod scans;
+pub mod users;
 
 // Default warehouse location key for bulk operations
 pub const DEFAULT_LOCATION_KEY: &str = "TFC1";
@@ -175,6 +187,31 @@ impl Database {
         }
     }
 
+    /// Check if a column exists on a table (for optional columns added by migrations)
+    pub async fn column_exists(&self, table_name: &str, column_name: &str) -> Result<bool> {
+        let mut client = self.get_client().await?;
+
+        let query = r#"
+            SELECT COUNT(*) as column_count
+            FROM INFORMATION_SCHEMA.COLUMNS
+            WHERE TABLE_NAME = @P1 AND COLUMN_NAME = @P2
+        "#;
+
+        let mut query_builder = Query::new(query);
+        query_builder.bind(table_name);
+        query_builder.bind(column_name);
+
+        let stream = query_builder.query(&mut *client).await?;
+        let rows: Vec<Vec<Row>> = stream.into_results().await?;
+
+        if let Some(row) = rows.first().and_then(|r| r.first()) {
+            let count: i32 = row.get("column_count").unwrap_or(0);
+            Ok(count > 0)
+        } else {
+            Ok(false)
+        }
+    }
+
     /// Get connection pool statistics for monitoring
     pub fn get_pool_status(&self) -> PoolStatus {
         PoolStatus {
@@ -0,0 +1,149 @@
+use anyhow::{anyhow, Context, Result};
+use chrono::{NaiveDateTime, Utc};
+use tiberius::Row;
+use tracing::{error, info};
+
+use crate::database::Database;
+use crate::models::pallet_labels::{PalletSscc, SsccConfig};
+
+const PALLET_COLUMNS: &str = "SSCC, RunNo, RowNum, BatchNo, AssignedBy, AssignedAt";
+
+fn pallet_from_row(row: &Row) -> PalletSscc {
+    PalletSscc {
+        sscc: row.get::<&str, _>("SSCC").unwrap_or_default().trim().to_string(),
+        run_no: row.get::<i32, _>("RunNo").unwrap_or_default(),
+        row_num: row.get::<i32, _>("RowNum").unwrap_or_default(),
+        batch_no: row.get::<&str, _>("BatchNo").unwrap_or_default().to_string(),
+        assigned_by: row.get::<&str, _>("AssignedBy").unwrap_or_default().to_string(),
+        assigned_at: row
+            .get::<NaiveDateTime, _>("AssignedAt")
+            .map(|dt| dt.and_utc())
+            .unwrap_or_default(),
+    }
+}
+
+impl Database {
+    pub async fn get_pallet_sscc(&self, run_no: i32, row_num: i32) -> Result<Option<PalletSscc>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for pallet SSCC")?;
+
+        let row = client
+            .query(
+                format!("SELECT {PALLET_COLUMNS} FROM tbl_pallet_sscc WHERE RunNo = @P1 AND RowNum = @P2 AND SSCC IS NOT NULL"),
+                &[&run_no, &row_num],
+            )
+            .await
+            .context("Failed to query pallet SSCC")?
+            .into_row()
+            .await
+            .context("Failed to read pallet SSCC")?;
+        Ok(row.as_ref().map(pallet_from_row))
+    }
+
+    pub async fn find_pallet_by_sscc(&self, sscc: &str) -> Result<Option<PalletSscc>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for SSCC lookup")?;
+
+        let row = client
+            .query(format!("SELECT {PALLET_COLUMNS} FROM tbl_pallet_sscc WHERE SSCC = @P1"), &[&sscc])
+            .await
+            .context("Failed to query SSCC")?
+            .into_row()
+            .await
+            .context("Failed to read SSCC")?;
+        Ok(row.as_ref().map(pallet_from_row))
+    }
+
+    /// The pallet's SSCC, assigning the next serial reference on first use. The range lock
+    /// on RunNo + RowNum makes concurrent first prints of one pallet agree on a single SSCC.
+    pub async fn assign_pallet_sscc(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        batch_no: &str,
+        config: &SsccConfig,
+        username: &str,
+    ) -> Result<PalletSscc> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for SSCC assignment")?;
+
+        client
+            .simple_query("BEGIN TRANSACTION")
+            .await
+            .context("Failed to start SSCC transaction")?;
+
+        let result: Result<PalletSscc> = async {
+            let existing = client
+                .query(
+                    format!(
+                        "SELECT {PALLET_COLUMNS} FROM tbl_pallet_sscc WITH (UPDLOCK, HOLDLOCK) WHERE RunNo = @P1 AND RowNum = @P2"
+                    ),
+                    &[&run_no, &row_num],
+                )
+                .await
+                .context("Failed to lock pallet SSCC")?
+                .into_row()
+                .await
+                .context("Failed to read pallet SSCC")?;
+            if let Some(row) = existing {
+                return Ok(pallet_from_row(&row));
+            }
+
+            let assigned_at = Utc::now();
+            let serial = client
+                .query(
+                    r#"
+                    INSERT INTO tbl_pallet_sscc (RunNo, RowNum, BatchNo, AssignedBy, AssignedAt)
+                    OUTPUT inserted.SerialRef
+                    VALUES (@P1, @P2, @P3, @P4, @P5)
+                    "#,
+                    &[&run_no, &row_num, &batch_no, &username, &assigned_at.naive_utc()],
+                )
+                .await
+                .context("Failed to reserve SSCC serial reference")?
+                .into_row()
+                .await
+                .context("Failed to read SSCC serial reference")?
+                .and_then(|row| row.get::<i64, _>("SerialRef"))
+                .context("SSCC serial reference missing after insert")?;
+
+            let sscc = config.sscc(serial as u64).map_err(|e| anyhow!(e))?;
+            client
+                .execute("UPDATE tbl_pallet_sscc SET SSCC = @P2 WHERE SerialRef = @P1", &[&serial, &sscc.as_str()])
+                .await
+                .context("Failed to store SSCC")?;
+
+            Ok(PalletSscc {
+                sscc,
+                run_no,
+                row_num,
+                batch_no: batch_no.to_string(),
+                assigned_by: username.to_string(),
+                assigned_at,
+            })
+        }
+        .await;
+
+        match result {
+            Ok(pallet) => {
+                client
+                    .simple_query("COMMIT")
+                    .await
+                    .context("Failed to commit SSCC assignment")?;
+                info!("🏷️ Pallet {} of run {} has SSCC {}", row_num, run_no, pallet.sscc);
+                Ok(pallet)
+            }
+            Err(e) => {
+                let _ = client.simple_query("ROLLBACK").await;
+                error!("❌ SSCC assignment for run {} pallet {} rolled back: {}", run_no, row_num, e);
+                Err(e)
+            }
+        }
+    }
+}
@@ -0,0 +1,243 @@
+use anyhow::{Context, Result};
+use bigdecimal::{BigDecimal, FromPrimitive, ToPrimitive};
+use chrono::NaiveDateTime;
+use tiberius::Row;
+use tracing::{error, info};
+
+use crate::database::Database;
+use crate::models::pick_plan::{PickPlan, PickPlanShortage, PickPlanStep, PlanStepStatus};
+
+fn decimal(row: &Row, column: &str) -> BigDecimal {
+    BigDecimal::from_f64(row.get::<f64, _>(column).unwrap_or_default()).unwrap_or_default()
+}
+
+fn step_from_row(row: &Row) -> PickPlanStep {
+    PickPlanStep {
+        step_no: row.get::<i32, _>("StepNo").unwrap_or_default(),
+        row_num: row.get::<i32, _>("RowNum").unwrap_or_default(),
+        line_id: row.get::<i32, _>("LineId").unwrap_or_default(),
+        item_key: row.get::<&str, _>("ItemKey").unwrap_or_default().to_string(),
+        lot_no: row.get::<&str, _>("LotNo").unwrap_or_default().to_string(),
+        bin_no: row.get::<&str, _>("BinNo").unwrap_or_default().to_string(),
+        bags: decimal(row, "Bags"),
+        qty_kg: decimal(row, "QtyKg"),
+        expiry_date: row.get::<NaiveDateTime, _>("DateExpiry").map(|dt| dt.and_utc()),
+        status: PlanStepStatus::parse(row.get::<&str, _>("Status").unwrap_or_default()),
+    }
+}
+
+fn shortage_from_row(row: &Row) -> PickPlanShortage {
+    PickPlanShortage {
+        row_num: row.get::<i32, _>("RowNum").unwrap_or_default(),
+        line_id: row.get::<i32, _>("LineId").unwrap_or_default(),
+        item_key: row.get::<&str, _>("ItemKey").unwrap_or_default().to_string(),
+        required_bags: decimal(row, "RequiredBags"),
+        short_bags: decimal(row, "ShortBags"),
+        short_kg: decimal(row, "ShortKg"),
+    }
+}
+
+fn float(value: &BigDecimal) -> f64 {
+    value.to_f64().unwrap_or_default()
+}
+
+impl Database {
+    /// Store a run's pick plan, replacing any earlier plan for the run
+    pub async fn save_pick_plan(&self, plan: &PickPlan) -> Result<()> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for pick plan")?;
+
+        client
+            .simple_query("BEGIN TRANSACTION")
+            .await
+            .context("Failed to start pick plan transaction")?;
+
+        let run_no = plan.run_no;
+        let result: Result<()> = async {
+            for table in ["tbl_bulk_pick_plan_steps", "tbl_bulk_pick_plan_shortages", "tbl_bulk_pick_plans"] {
+                client
+                    .execute(format!("DELETE FROM {table} WHERE RunNo = @P1"), &[&run_no])
+                    .await
+                    .with_context(|| format!("Failed to clear previous pick plan from {table}"))?;
+            }
+
+            client
+                .execute(
+                    "INSERT INTO tbl_bulk_pick_plans (RunNo, GeneratedBy, GeneratedAt) VALUES (@P1, @P2, @P3)",
+                    &[&run_no, &plan.generated_by.as_str(), &plan.generated_at.naive_utc()],
+                )
+                .await
+                .context("Failed to store pick plan")?;
+
+            for step in &plan.steps {
+                client
+                    .execute(
+                        r#"
+                        INSERT INTO tbl_bulk_pick_plan_steps
+                            (RunNo, StepNo, RowNum, LineId, ItemKey, LotNo, BinNo, Bags, QtyKg, DateExpiry, Status)
+                        VALUES (@P1, @P2, @P3, @P4, @P5, @P6, @P7, @P8, @P9, @P10, @P11)
+                        "#,
+                        &[
+                            &run_no,
+                            &step.step_no,
+                            &step.row_num,
+                            &step.line_id,
+                            &step.item_key.as_str(),
+                            &step.lot_no.as_str(),
+                            &step.bin_no.as_str(),
+                            &float(&step.bags),
+                            &float(&step.qty_kg),
+                            &step.expiry_date.map(|dt| dt.naive_utc()),
+                            &step.status.as_str(),
+                        ],
+                    )
+                    .await
+                    .with_context(|| format!("Failed to store pick plan step {}", step.step_no))?;
+            }
+
+            for shortage in &plan.shortages {
+                client
+                    .execute(
+                        r#"
+                        INSERT INTO tbl_bulk_pick_plan_shortages
+                            (RunNo, RowNum, LineId, ItemKey, RequiredBags, ShortBags, ShortKg)
+                        VALUES (@P1, @P2, @P3, @P4, @P5, @P6, @P7)
+                        "#,
+                        &[
+                            &run_no,
+                            &shortage.row_num,
+                            &shortage.line_id,
+                            &shortage.item_key.as_str(),
+                            &float(&shortage.required_bags),
+                            &float(&shortage.short_bags),
+                            &float(&shortage.short_kg),
+                        ],
+                    )
+                    .await
+                    .context("Failed to store pick plan shortage")?;
+            }
+            Ok(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                client
+                    .simple_query("COMMIT")
+                    .await
+                    .context("Failed to commit pick plan transaction")?;
+                info!(
+                    "✅ PICK_PLAN: Stored plan for run {} ({} steps, {} shortages)",
+                    run_no,
+                    plan.steps.len(),
+                    plan.shortages.len()
+                );
+                Ok(())
+            }
+            Err(e) => {
+                let _ = client.simple_query("ROLLBACK").await;
+                error!("❌ Storing pick plan failed for run {}, rolled back: {}", run_no, e);
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn get_pick_plan(&self, run_no: i32) -> Result<Option<PickPlan>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for pick plan")?;
+
+        let Some(header) = client
+            .query(
+                "SELECT GeneratedBy, GeneratedAt FROM tbl_bulk_pick_plans WHERE RunNo = @P1",
+                &[&run_no],
+            )
+            .await
+            .context("Failed to query pick plan")?
+            .into_row()
+            .await
+            .context("Failed to read pick plan")?
+        else {
+            return Ok(None);
+        };
+        let generated_by = header.get::<&str, _>("GeneratedBy").unwrap_or_default().to_string();
+        let generated_at = header.get::<NaiveDateTime, _>("GeneratedAt").unwrap_or_default().and_utc();
+
+        let steps = client
+            .query(
+                r#"
+                SELECT StepNo, RowNum, LineId, ItemKey, LotNo, BinNo, Bags, QtyKg, DateExpiry, Status
+                FROM tbl_bulk_pick_plan_steps
+                WHERE RunNo = @P1
+                ORDER BY StepNo
+                "#,
+                &[&run_no],
+            )
+            .await
+            .context("Failed to query pick plan steps")?
+            .into_first_result()
+            .await
+            .context("Failed to read pick plan steps")?;
+
+        let shortages = client
+            .query(
+                r#"
+                SELECT RowNum, LineId, ItemKey, RequiredBags, ShortBags, ShortKg
+                FROM tbl_bulk_pick_plan_shortages
+                WHERE RunNo = @P1
+                ORDER BY LineId, RowNum
+                "#,
+                &[&run_no],
+            )
+            .await
+            .context("Failed to query pick plan shortages")?
+            .into_first_result()
+            .await
+            .context("Failed to read pick plan shortages")?;
+
+        Ok(Some(PickPlan {
+            run_no,
+            generated_by,
+            generated_at,
+            steps: steps.iter().map(step_from_row).collect(),
+            shortages: shortages.iter().map(shortage_from_row).collect(),
+        }))
+    }
+
+    /// Mark the first pending step for this batch and lot/bin as picked
+    pub async fn mark_pick_plan_step_picked(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        lot_no: &str,
+        bin_no: &str,
+    ) -> Result<bool> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for pick plan progress")?;
+
+        let updated = client
+            .execute(
+                r#"
+                WITH next_step AS (
+                    SELECT TOP (1) Status, PickedAt
+                    FROM tbl_bulk_pick_plan_steps
+                    WHERE RunNo = @P1 AND RowNum = @P2 AND LineId = @P3
+                      AND LotNo = @P4 AND BinNo = @P5 AND Status = 'PENDING'
+                    ORDER BY StepNo
+                )
+                UPDATE next_step SET Status = 'PICKED', PickedAt = SYSUTCDATETIME()
+                "#,
+                &[&run_no, &row_num, &line_id, &lot_no, &bin_no],
+            )
+            .await
+            .context("Failed to mark pick plan step picked")?
+            .total();
+        Ok(updated > 0)
+    }
+}
@@ -0,0 +1,82 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+
+use crate::database::Database;
+use crate::models::pick_scans::PickScanException;
+
+impl Database {
+    pub async fn record_pick_scan_exception(&self, exception: &PickScanException) -> Result<()> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for pick scan exception")?;
+
+        client
+            .execute(
+                r#"
+                INSERT INTO tbl_pick_scan_exceptions
+                    (RunNo, RowNum, LineId, LotNo, BinNo, ScannedLot, ScannedBin, ErrorCode, UserId, CreatedAt)
+                VALUES (@P1, @P2, @P3, @P4, @P5, @P6, @P7, @P8, @P9, @P10)
+                "#,
+                &[
+                    &exception.run_no,
+                    &exception.row_num,
+                    &exception.line_id,
+                    &exception.lot_no.as_str(),
+                    &exception.bin_no.as_str(),
+                    &exception.scanned_lot.as_deref(),
+                    &exception.scanned_bin.as_deref(),
+                    &exception.error_code.as_str(),
+                    &exception.user_id.as_str(),
+                    &exception.created_at.naive_utc(),
+                ],
+            )
+            .await
+            .context("Failed to record pick scan exception")?;
+        Ok(())
+    }
+
+    /// A run's scan exceptions, oldest first
+    pub async fn get_pick_scan_exceptions(&self, run_no: i32) -> Result<Vec<PickScanException>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for pick scan exceptions")?;
+
+        let rows = client
+            .query(
+                r#"
+                SELECT RunNo, RowNum, LineId, LotNo, BinNo, ScannedLot, ScannedBin, ErrorCode, UserId, CreatedAt
+                FROM tbl_pick_scan_exceptions
+                WHERE RunNo = @P1
+                ORDER BY CreatedAt, ExceptionId
+                "#,
+                &[&run_no],
+            )
+            .await
+            .context("Failed to query pick scan exceptions")?
+            .into_first_result()
+            .await
+            .context("Failed to read pick scan exceptions")?;
+
+        let text = |row: &tiberius::Row, column: &str| row.get::<&str, _>(column).map(str::to_string);
+        Ok(rows
+            .iter()
+            .map(|row| PickScanException {
+                run_no: row.get::<i32, _>("RunNo").unwrap_or_default(),
+                row_num: row.get::<i32, _>("RowNum").unwrap_or_default(),
+                line_id: row.get::<i32, _>("LineId").unwrap_or_default(),
+                lot_no: text(row, "LotNo").unwrap_or_default(),
+                bin_no: text(row, "BinNo").unwrap_or_default(),
+                scanned_lot: text(row, "ScannedLot"),
+                scanned_bin: text(row, "ScannedBin"),
+                error_code: text(row, "ErrorCode").unwrap_or_default(),
+                user_id: text(row, "UserId").unwrap_or_default(),
+                created_at: row
+                    .get::<NaiveDateTime, _>("CreatedAt")
+                    .map(|dt| dt.and_utc())
+                    .unwrap_or_default(),
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,392 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use tiberius::Row;
+use tracing::error;
+
+use crate::database::Database;
+use crate::models::print_jobs::{
+    LabelKind, LabelPrinter, NewPrintJob, PrintAttempt, PrintJob, PrintJobStatus, PrinterLanguage,
+};
+
+const PRINTER_COLUMNS: &str = "PrinterId, Name, Host, Port, Language, IsDefault, Active, UpdatedBy, UpdatedAt";
+
+const JOB_COLUMNS: &str = "JobId, PrinterId, RunNo, BatchNo, LabelKind, LabelCount, Payload, Status, Attempts, \
+     MaxAttempts, LastError, ReprintOf, Reason, RequestedBy, CreatedAt, NextAttemptAt, PrintedAt";
+
+/// JOB_COLUMNS as an OUTPUT clause of the rows an INSERT/UPDATE wrote
+fn inserted_job_columns() -> String {
+    JOB_COLUMNS
+        .split(", ")
+        .map(|column| format!("inserted.{}", column.trim()))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+fn utc(row: &Row, column: &str) -> Option<DateTime<Utc>> {
+    row.get::<NaiveDateTime, _>(column).map(|dt| dt.and_utc())
+}
+
+fn printer_from_row(row: &Row) -> LabelPrinter {
+    LabelPrinter {
+        printer_id: row.get::<&str, _>("PrinterId").unwrap_or_default().to_string(),
+        name: row.get::<&str, _>("Name").unwrap_or_default().to_string(),
+        host: row.get::<&str, _>("Host").unwrap_or_default().to_string(),
+        port: u16::try_from(row.get::<i32, _>("Port").unwrap_or(9100)).unwrap_or(9100),
+        language: PrinterLanguage::parse(row.get::<&str, _>("Language").unwrap_or_default())
+            .unwrap_or(PrinterLanguage::Zpl),
+        is_default: row.get::<bool, _>("IsDefault").unwrap_or_default(),
+        active: row.get::<bool, _>("Active").unwrap_or_default(),
+        updated_by: row.get::<&str, _>("UpdatedBy").unwrap_or_default().to_string(),
+        updated_at: utc(row, "UpdatedAt").unwrap_or_default(),
+    }
+}
+
+fn job_from_row(row: &Row) -> PrintJob {
+    PrintJob {
+        job_id: row.get::<i64, _>("JobId").unwrap_or_default(),
+        printer_id: row.get::<&str, _>("PrinterId").unwrap_or_default().to_string(),
+        run_no: row.get::<i32, _>("RunNo").unwrap_or_default(),
+        batch_no: row.get::<&str, _>("BatchNo").map(str::to_string),
+        label_kind: LabelKind::parse(row.get::<&str, _>("LabelKind").unwrap_or_default()),
+        label_count: row.get::<i32, _>("LabelCount").unwrap_or_default(),
+        payload: row.get::<&str, _>("Payload").unwrap_or_default().to_string(),
+        status: PrintJobStatus::parse(row.get::<&str, _>("Status").unwrap_or_default()),
+        attempts: row.get::<i32, _>("Attempts").unwrap_or_default(),
+        max_attempts: row.get::<i32, _>("MaxAttempts").unwrap_or_default(),
+        last_error: row.get::<&str, _>("LastError").map(str::to_string),
+        reprint_of: row.get::<i64, _>("ReprintOf"),
+        reason: row.get::<&str, _>("Reason").map(str::to_string),
+        requested_by: row.get::<&str, _>("RequestedBy").unwrap_or_default().to_string(),
+        created_at: utc(row, "CreatedAt").unwrap_or_default(),
+        next_attempt_at: utc(row, "NextAttemptAt").unwrap_or_default(),
+        printed_at: utc(row, "PrintedAt"),
+    }
+}
+
+/// Printer errors can be long; LastError holds 500 characters
+fn truncate_error(error: &str) -> String {
+    error.chars().take(500).collect()
+}
+
+impl Database {
+    /// Add or update a printer. A new default printer replaces the previous one.
+    pub async fn upsert_printer(&self, printer: &LabelPrinter) -> Result<()> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for printer registry")?;
+
+        client
+            .simple_query("BEGIN TRANSACTION")
+            .await
+            .context("Failed to start printer registry transaction")?;
+
+        let port = i32::from(printer.port);
+        let updated_at = printer.updated_at.naive_utc();
+        let result: Result<()> = async {
+            if printer.is_default {
+                client
+                    .execute(
+                        "UPDATE tbl_label_printers SET IsDefault = 0 WHERE PrinterId <> @P1 AND IsDefault = 1",
+                        &[&printer.printer_id.as_str()],
+                    )
+                    .await
+                    .context("Failed to clear previous default printer")?;
+            }
+
+            let updated = client
+                .execute(
+                    r#"
+                    UPDATE tbl_label_printers
+                    SET Name = @P2, Host = @P3, Port = @P4, Language = @P5, IsDefault = @P6, Active = @P7,
+                        UpdatedBy = @P8, UpdatedAt = @P9
+                    WHERE PrinterId = @P1
+                    "#,
+                    &[
+                        &printer.printer_id.as_str(),
+                        &printer.name.as_str(),
+                        &printer.host.as_str(),
+                        &port,
+                        &printer.language.as_str(),
+                        &printer.is_default,
+                        &printer.active,
+                        &printer.updated_by.as_str(),
+                        &updated_at,
+                    ],
+                )
+                .await
+                .context("Failed to update printer")?
+                .total();
+
+            if updated == 0 {
+                client
+                    .execute(
+                        format!("INSERT INTO tbl_label_printers ({PRINTER_COLUMNS}) VALUES (@P1, @P2, @P3, @P4, @P5, @P6, @P7, @P8, @P9)"),
+                        &[
+                            &printer.printer_id.as_str(),
+                            &printer.name.as_str(),
+                            &printer.host.as_str(),
+                            &port,
+                            &printer.language.as_str(),
+                            &printer.is_default,
+                            &printer.active,
+                            &printer.updated_by.as_str(),
+                            &updated_at,
+                        ],
+                    )
+                    .await
+                    .context("Failed to register printer")?;
+            }
+            Ok(())
+        }
+        .await;
+
+        match result {
+            Ok(()) => {
+                client
+                    .simple_query("COMMIT")
+                    .await
+                    .context("Failed to commit printer registry transaction")?;
+                Ok(())
+            }
+            Err(e) => {
+                let _ = client.simple_query("ROLLBACK").await;
+                error!("❌ Printer {} registration rolled back: {}", printer.printer_id, e);
+                Err(e)
+            }
+        }
+    }
+
+    pub async fn list_printers(&self) -> Result<Vec<LabelPrinter>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for printers")?;
+
+        let rows = client
+            .query(format!("SELECT {PRINTER_COLUMNS} FROM tbl_label_printers ORDER BY PrinterId"), &[])
+            .await
+            .context("Failed to query printers")?
+            .into_first_result()
+            .await
+            .context("Failed to read printers")?;
+        Ok(rows.iter().map(printer_from_row).collect())
+    }
+
+    /// A printer by id, or the default printer when `printer_id` is None
+    pub async fn get_printer(&self, printer_id: Option<&str>) -> Result<Option<LabelPrinter>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for printer")?;
+
+        let row = match printer_id {
+            Some(printer_id) => client
+                .query(
+                    format!("SELECT {PRINTER_COLUMNS} FROM tbl_label_printers WHERE PrinterId = @P1"),
+                    &[&printer_id],
+                )
+                .await
+                .context("Failed to query printer")?
+                .into_row()
+                .await
+                .context("Failed to read printer")?,
+            None => client
+                .query(
+                    format!("SELECT TOP 1 {PRINTER_COLUMNS} FROM tbl_label_printers WHERE IsDefault = 1 AND Active = 1"),
+                    &[],
+                )
+                .await
+                .context("Failed to query default printer")?
+                .into_row()
+                .await
+                .context("Failed to read default printer")?,
+        };
+        Ok(row.as_ref().map(printer_from_row))
+    }
+
+    pub async fn create_print_job(&self, job: &NewPrintJob) -> Result<PrintJob> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for print job")?;
+
+        let now = Utc::now().naive_utc();
+        let query = format!(
+            r#"
+            INSERT INTO tbl_print_jobs
+                (PrinterId, RunNo, BatchNo, LabelKind, LabelCount, Payload, Status, Attempts, MaxAttempts,
+                 ReprintOf, Reason, RequestedBy, CreatedAt, NextAttemptAt)
+            OUTPUT {}
+            VALUES (@P1, @P2, @P3, @P4, @P5, @P6, 'QUEUED', 0, @P7, @P8, @P9, @P10, @P11, @P11)
+            "#,
+            inserted_job_columns()
+        );
+        let row = client
+            .query(
+                query,
+                &[
+                    &job.printer_id.as_str(),
+                    &job.run_no,
+                    &job.batch_no.as_deref(),
+                    &job.label_kind.as_str(),
+                    &job.label_count,
+                    &job.payload.as_str(),
+                    &job.max_attempts,
+                    &job.reprint_of,
+                    &job.reason.as_deref(),
+                    &job.requested_by.as_str(),
+                    &now,
+                ],
+            )
+            .await
+            .context("Failed to queue print job")?
+            .into_row()
+            .await
+            .context("Failed to read queued print job")?;
+        row.as_ref().map(job_from_row).context("Print job missing after insert")
+    }
+
+    pub async fn get_print_job(&self, job_id: i64) -> Result<Option<PrintJob>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for print job")?;
+
+        let row = client
+            .query(format!("SELECT {JOB_COLUMNS} FROM tbl_print_jobs WHERE JobId = @P1"), &[&job_id])
+            .await
+            .context("Failed to query print job")?
+            .into_row()
+            .await
+            .context("Failed to read print job")?;
+        Ok(row.as_ref().map(job_from_row))
+    }
+
+    /// Newest jobs first, optionally for one run
+    pub async fn list_print_jobs(&self, run_no: Option<i32>, limit: u32) -> Result<Vec<PrintJob>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for print jobs")?;
+
+        let query = format!(
+            r#"
+            SELECT TOP ({limit}) {JOB_COLUMNS}
+            FROM tbl_print_jobs
+            WHERE (@P1 IS NULL OR RunNo = @P1)
+            ORDER BY JobId DESC
+            "#
+        );
+        let rows = client
+            .query(query, &[&run_no])
+            .await
+            .context("Failed to query print jobs")?
+            .into_first_result()
+            .await
+            .context("Failed to read print jobs")?;
+        Ok(rows.iter().map(job_from_row).collect())
+    }
+
+    /// Move the oldest due job to PRINTING for one worker until `lease_until`. QUEUED jobs are
+    /// due at NextAttemptAt; a PRINTING job whose lease passed was abandoned mid-send.
+    /// READPAST lets several backend instances claim without blocking or double-sending.
+    /// The returned job's `next_attempt_at` is the stored lease, which identifies the claim.
+    pub async fn claim_due_print_job(&self, lease_until: DateTime<Utc>) -> Result<Option<PrintJob>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for print queue")?;
+
+        let query = format!(
+            r#"
+            WITH due AS (
+                SELECT TOP (1) *
+                FROM tbl_print_jobs WITH (ROWLOCK, UPDLOCK, READPAST)
+                WHERE Status IN ('QUEUED', 'PRINTING') AND NextAttemptAt <= SYSUTCDATETIME()
+                ORDER BY NextAttemptAt, JobId
+            )
+            UPDATE due
+            SET Status = 'PRINTING', NextAttemptAt = @P1
+            OUTPUT {}
+            "#,
+            inserted_job_columns()
+        );
+        let row = client
+            .query(query, &[&lease_until.naive_utc()])
+            .await
+            .context("Failed to claim due print job")?
+            .into_row()
+            .await
+            .context("Failed to read claimed print job")?;
+        Ok(row.as_ref().map(job_from_row))
+    }
+
+    /// Record the outcome of sending a claimed job; every outcome counts as an attempt.
+    /// Only the holder of the claim (still PRINTING with lease `claimed_until`) may record it;
+    /// false means the lease ran out and another worker took the job over.
+    pub async fn finish_print_attempt(
+        &self,
+        job_id: i64,
+        claimed_until: DateTime<Utc>,
+        attempt: &PrintAttempt,
+    ) -> Result<bool> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for print job update")?;
+
+        let now = Utc::now().naive_utc();
+        let (status, error, next_attempt_at, printed_at) = match attempt {
+            PrintAttempt::Printed => (PrintJobStatus::Printed, None, now, Some(now)),
+            PrintAttempt::Retry { error, next_attempt_at } => {
+                (PrintJobStatus::Queued, Some(truncate_error(error)), next_attempt_at.naive_utc(), None)
+            }
+            PrintAttempt::Failed { error } => (PrintJobStatus::Failed, Some(truncate_error(error)), now, None),
+        };
+
+        let updated = client
+            .execute(
+                r#"
+                UPDATE tbl_print_jobs
+                SET Status = @P2, Attempts = Attempts + 1, LastError = COALESCE(@P3, LastError),
+                    NextAttemptAt = @P4, PrintedAt = @P5
+                WHERE JobId = @P1 AND Status = 'PRINTING' AND NextAttemptAt = @P6
+                "#,
+                &[
+                    &job_id,
+                    &status.as_str(),
+                    &error.as_deref(),
+                    &next_attempt_at,
+                    &printed_at,
+                    &claimed_until.naive_utc(),
+                ],
+            )
+            .await
+            .with_context(|| format!("Failed to record attempt of print job {job_id}"))?
+            .total();
+        Ok(updated > 0)
+    }
+
+    /// Put a FAILED job back in the queue with a fresh set of attempts
+    pub async fn requeue_print_job(&self, job_id: i64) -> Result<bool> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for print job retry")?;
+
+        let requeued = client
+            .execute(
+                r#"
+                UPDATE tbl_print_jobs
+                SET Status = 'QUEUED', Attempts = 0, NextAttemptAt = SYSUTCDATETIME()
+                WHERE JobId = @P1 AND Status = 'FAILED'
+                "#,
+                &[&job_id],
+            )
+            .await
+            .with_context(|| format!("Failed to requeue print job {job_id}"))?
+            .total();
+        Ok(requeued > 0)
+    }
+}
@@ -1,9 +1,10 @@
-use crate::database::Database;
+use crate::database::{idempotency, Database};
+use crate::models::idempotency::{IdempotencyKey, StoredResponse};
 use crate::models::putaway_models::{
     map_inclasskey_to_inacct, BinSearchItem, InlocRecord, ItemMasterRecord, LotMasterRecord,
-    LotSearchItem, PutawayError,
+    LotSearchItem, PutawayError, TransferResult,
 };
-use crate::utils::bangkok_now;
+use crate::utils::{bangkok_now, bangkok_now_rfc3339};
 use anyhow::Result;
 use chrono::{DateTime, NaiveDateTime, Utc};
 
@@ -217,7 +218,17 @@ impl PutawayDatabase {
         }
     }
 
-    /// Execute complete bin transfer transaction with lot consolidation
+    /// Response already recorded for an `Idempotency-Key`, if any
+    pub async fn find_idempotent_response(&self, key: &IdempotencyKey) -> Result<Option<StoredResponse>, PutawayError> {
+        self.db
+            .find_idempotent_response(key)
+            .await
+            .map_err(|e| PutawayError::DatabaseError(e.to_string()))
+    }
+
+    /// Execute complete bin transfer transaction with lot consolidation.
+    /// With an idempotency key, the result is stored before COMMIT; a concurrent request with
+    /// the same key waits on the key lock and gets the stored result instead of moving stock again.
     #[allow(clippy::too_many_arguments)]
     pub async fn execute_bin_transfer_transaction(
         &self,
@@ -230,7 +241,9 @@ impl PutawayDatabase {
         user_id: &str,
         remarks: &str,
         referenced: &str,
-    ) -> Result<(String, Option<String>, Option<String>), PutawayError> {
+        message: &str,
+        idempotency_key: Option<&IdempotencyKey>,
+    ) -> Result<TransferResult, PutawayError> {
         // Get database client (TFCPILOT3 primary)
         let mut client = self
             .db
@@ -250,8 +263,23 @@ impl PutawayDatabase {
             .await
             .map_err(|e| PutawayError::DatabaseError(format!("Failed to begin transaction: {e}")))?;
 
+        // Replay a transfer already committed under this key
+        if let Some(key) = idempotency_key {
+            match idempotency::find_stored_response(&mut client, key, true).await {
+                Ok(None) => {}
+                Ok(Some(stored)) => {
+                    let _ = client.simple_query("ROLLBACK").await;
+                    return Ok(stored.replay(key)?);
+                }
+                Err(e) => {
+                    let _ = client.simple_query("ROLLBACK").await;
+                    return Err(PutawayError::DatabaseError(format!("{e:#}")));
+                }
+            }
+        }
+
         // Execute all 6 steps in a transaction
-        let transaction_result: Result<String, PutawayError> = async {
+        let transaction_result: Result<TransferResult, PutawayError> = async {
         // 1. Get next BT document number
         let bt_number = self.get_next_bt_sequence().await?;
         let document_no = format!("BT-{bt_number:08}");
@@ -505,25 +533,43 @@ impl PutawayDatabase {
         )
         .await?;
 
-        Ok(document_no)
+        // Query source lot status (from original bin, may have been deleted if full transfer)
+        let source_lot_status = self.get_lot_status(&mut client, lot_no, item_key, location, bin_from).await;
+
+        // Query destination lot status (should exist after transfer)
+        let destination_lot_status = self.get_lot_status(&mut client, lot_no, item_key, location, bin_to).await;
+
+        let result = TransferResult {
+            success: true,
+            document_no,
+            message: message.to_string(),
+            timestamp: bangkok_now_rfc3339(),
+            source_lot_status,
+            destination_lot_status,
+        };
+
+        // 7. Record the result for retries of the same Idempotency-Key
+        if let Some(key) = idempotency_key {
+            let body = serde_json::to_string(&result)
+                .map_err(|e| PutawayError::TransactionError(format!("Failed to serialize transfer result: {e}")))?;
+            idempotency::store_response(&mut client, key, &body)
+                .await
+                .map_err(|e| PutawayError::TransactionError(format!("{e:#}")))?;
+        }
+
+        Ok(result)
         }.await;
 
         // **🔒 COMMIT or ROLLBACK** - Atomic transaction handling
         match transaction_result {
-            Ok(doc_no) => {
-                // All 6 steps succeeded - commit the transaction
+            Ok(result) => {
+                // All steps succeeded - commit the transaction
                 client
                     .simple_query("COMMIT")
                     .await
                     .map_err(|e| PutawayError::DatabaseError(format!("Failed to commit transaction: {e}")))?;
 
-                // Query source lot status (from original bin, may have been deleted if full transfer)
-                let source_status = self.get_lot_status(&mut client, lot_no, item_key, location, bin_from).await;
-
-                // Query destination lot status (should exist after transfer)
-                let dest_status = self.get_lot_status(&mut client, lot_no, item_key, location, bin_to).await;
-
-                Ok((doc_no, source_status, dest_status))
+                Ok(result)
             }
             Err(e) => {
                 // Any step failed - rollback the entire transaction
@@ -0,0 +1,760 @@
+//! Repository traits over the SQL Server layer.
+//!
+//! Services depend on these traits instead of calling `Database` directly, so their
+//! orchestration (claims, scan checks, idempotent replay, events, pick plans, print
+//! queue) can run against the in-memory repository in tests.
+//!
+//! `BulkRunRepository` is not only data access: pick validation, the confirm-pick
+//! transaction, the unpicks and run completion status are whole operations whose rules
+//! live in the SQL of `database/bulk_runs.rs`. The in-memory repository keeps its own
+//! simplified copy of those rules, so tests against it do not cover the SQL pick path.
+
+use anyhow::Result;
+use std::future::Future;
+
+use crate::database::putaway_db::PutawayDatabase;
+use crate::database::Database;
+use crate::models::bulk_runs::{
+    BatchWeightSummaryItem, BulkPickedItem, BulkRun, BulkRunError, BulkRunStatusResponse, BulkRunSummary,
+    InventoryInfo, LotInfo, LotPickingDetail, LotSearchResult, PalletBatch, PalletBatchInfo, PickConfirmationRequest, PickConfirmationResponse,
+    PickValidationResult, RunCompletionStatus,
+};
+use crate::models::idempotency::{IdempotencyKey, StoredResponse};
+use crate::models::pallet_labels::{PalletSscc, SsccConfig};
+use crate::models::pick_plan::PickPlan;
+use crate::models::pick_scans::PickScanException;
+use crate::models::pick_sequence::BinLocation;
+use crate::models::print_jobs::{LabelPrinter, NewPrintJob, PrintAttempt, PrintJob};
+use crate::models::run_claims::{ClaimOutcome, RunClaim};
+use crate::models::scans::{ItemGtin, LotMasterEntry, ScannedItem};
+use crate::models::putaway_models::{
+    BinSearchItem, ItemMasterRecord, LotMasterRecord, LotSearchItem, PutawayError, TransferResult,
+};
+
+/// Bulk runs, their ingredient rows and the pick/unpick/status transactions on them.
+/// The transactional methods carry business rules; see the module docs.
+pub trait BulkRunRepository: Send + Sync {
+    fn search_bulk_runs(
+        &self,
+        search_query: &str,
+        search_mode: &str,
+    ) -> impl Future<Output = Result<Vec<BulkRun>>> + Send;
+
+    fn get_bulk_run(&self, run_no: i32) -> impl Future<Output = Result<Option<BulkRun>>> + Send;
+
+    fn get_bulk_run_status(
+        &self,
+        run_no: i32,
+    ) -> impl Future<Output = Result<Option<BulkRunStatusResponse>>> + Send;
+
+    fn list_active_bulk_runs_paginated(
+        &self,
+        page: u32,
+        limit: u32,
+    ) -> impl Future<Output = Result<(Vec<BulkRunSummary>, u64)>> + Send;
+
+    fn get_bulk_run_ingredients(
+        &self,
+        run_no: i32,
+    ) -> impl Future<Output = Result<Vec<BulkPickedItem>>> + Send;
+
+    fn get_unique_ingredients_for_search(
+        &self,
+        run_no: i32,
+    ) -> impl Future<Output = Result<Vec<BulkPickedItem>>> + Send;
+
+    fn get_bulk_run_batches(
+        &self,
+        run_no: i32,
+    ) -> impl Future<Output = Result<Vec<BulkPickedItem>>> + Send;
+
+    fn get_bulk_run_batches_for_ingredient(
+        &self,
+        run_no: i32,
+        item_key: &str,
+    ) -> impl Future<Output = Result<Vec<BulkPickedItem>>> + Send;
+
+    fn get_pallet_tracking_data(
+        &self,
+        run_no: i32,
+        item_key: Option<&str>,
+    ) -> impl Future<Output = Result<Vec<PalletBatch>>> + Send;
+
+    fn is_pallet_completed(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    fn get_next_available_pallet(
+        &self,
+        run_no: i32,
+        current_row_num: i32,
+        line_id: i32,
+    ) -> impl Future<Output = Result<Option<PalletBatchInfo>>> + Send;
+
+    fn validate_pick_request(
+        &self,
+        run_no: i32,
+        request: &PickConfirmationRequest,
+    ) -> impl Future<Output = Result<PickValidationResult>> + Send;
+
+    /// Response already recorded for an `Idempotency-Key`, if any
+    fn find_idempotent_response(
+        &self,
+        key: &IdempotencyKey,
+    ) -> impl Future<Output = Result<Option<StoredResponse>>> + Send;
+
+    /// Atomic pick: allocation, lot commitment and batch progress in one unit of work.
+    /// With an idempotency key, the response is stored in the same transaction.
+    fn confirm_pick_transaction(
+        &self,
+        run_no: i32,
+        request: &PickConfirmationRequest,
+    ) -> impl Future<Output = Result<PickConfirmationResponse, BulkRunError>> + Send;
+
+    fn unpick_by_lot_tran_no(
+        &self,
+        lot_tran_no: i32,
+        user_id: &str,
+    ) -> impl Future<Output = Result<serde_json::Value>> + Send;
+
+    fn unpick_specific_lot(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        lot_no: &str,
+        user_id: &str,
+    ) -> impl Future<Output = Result<serde_json::Value>> + Send;
+
+    fn unpick_entire_batch(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        user_id: &str,
+    ) -> impl Future<Output = Result<serde_json::Value>> + Send;
+
+    fn unpick_all_run_lots(
+        &self,
+        run_no: i32,
+        user_id: &str,
+    ) -> impl Future<Output = Result<serde_json::Value>> + Send;
+
+    fn get_run_completion_status(
+        &self,
+        run_no: i32,
+    ) -> impl Future<Output = Result<RunCompletionStatus>> + Send;
+
+    fn update_bulk_run_status(
+        &self,
+        run_no: i32,
+        new_status: &str,
+        user_id: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    fn revert_run_status_to_new(
+        &self,
+        run_no: i32,
+        user_id: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    fn get_batch_weight_summary(
+        &self,
+        run_no: i32,
+    ) -> impl Future<Output = Result<Vec<BatchWeightSummaryItem>>> + Send;
+
+    /// Cust_BulkLotPicked rows of the run, one per picked lot/bin, for batch labels
+    fn get_lot_picking_details_for_run(
+        &self,
+        run_no: i32,
+    ) -> impl Future<Output = Result<Vec<LotPickingDetail>>> + Send;
+
+    /// Take or renew a claim; `take_over` ends overlapping claims held by others
+    fn acquire_claim(
+        &self,
+        run_no: i32,
+        line_id: Option<i32>,
+        username: &str,
+        lease: chrono::Duration,
+        take_over: bool,
+    ) -> impl Future<Output = Result<ClaimOutcome>> + Send;
+
+    /// End a claim; with `holder`, only if that user holds it
+    fn release_claim(
+        &self,
+        run_no: i32,
+        line_id: Option<i32>,
+        holder: Option<&str>,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    fn get_active_claims(&self, run_nos: &[i32]) -> impl Future<Output = Result<Vec<RunClaim>>> + Send;
+
+    /// Store a run's pick plan, replacing the previous one
+    fn save_pick_plan(&self, plan: &PickPlan) -> impl Future<Output = Result<()>> + Send;
+
+    fn get_pick_plan(&self, run_no: i32) -> impl Future<Output = Result<Option<PickPlan>>> + Send;
+
+    /// Mark the first pending plan step for this batch and lot/bin as picked
+    fn mark_pick_plan_step_picked(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        lot_no: &str,
+        bin_no: &str,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    fn get_pallet_sscc(&self, run_no: i32, row_num: i32) -> impl Future<Output = Result<Option<PalletSscc>>> + Send;
+
+    fn find_pallet_by_sscc(&self, sscc: &str) -> impl Future<Output = Result<Option<PalletSscc>>> + Send;
+
+    /// The pallet's SSCC, assigning the next serial reference the first time
+    fn assign_pallet_sscc(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        batch_no: &str,
+        config: &SsccConfig,
+        username: &str,
+    ) -> impl Future<Output = Result<PalletSscc>> + Send;
+
+    fn record_pick_scan_exception(&self, exception: &PickScanException) -> impl Future<Output = Result<()>> + Send;
+
+    /// A run's scan exceptions, oldest first
+    fn get_pick_scan_exceptions(&self, run_no: i32) -> impl Future<Output = Result<Vec<PickScanException>>> + Send;
+}
+
+/// Lot inventory available to a run's ingredients
+pub trait LotRepository: Send + Sync {
+    /// Pickable lots of an ingredient, FEFO ordered
+    fn get_available_lots(
+        &self,
+        run_no: i32,
+        item_key: &str,
+    ) -> impl Future<Output = Result<Vec<LotInfo>>> + Send;
+
+    fn get_inventory_info(
+        &self,
+        run_no: i32,
+        item_key: &str,
+    ) -> impl Future<Output = Result<InventoryInfo>> + Send;
+
+    /// BINMaster aisle/row/rack of the given bins; unknown bins are left out
+    fn get_bin_locations(
+        &self,
+        location: &str,
+        bin_nos: &[String],
+    ) -> impl Future<Output = Result<Vec<BinLocation>>> + Send;
+
+    fn search_lots_for_run_item_paginated(
+        &self,
+        run_no: i32,
+        item_key: &str,
+        page: u32,
+        page_size: u32,
+    ) -> impl Future<Output = Result<(Vec<LotSearchResult>, u64)>> + Send;
+
+    fn get_bins_for_lot(
+        &self,
+        run_no: i32,
+        lot_no: &str,
+        item_key: &str,
+    ) -> impl Future<Output = Result<Vec<LotSearchResult>>> + Send;
+}
+
+/// Lot and bin lookups plus the bin transfer transaction used by putaway
+pub trait PutawayRepository: Send + Sync {
+    fn find_lot_by_number(
+        &self,
+        lot_no: &str,
+    ) -> impl Future<Output = Result<Option<(LotMasterRecord, ItemMasterRecord)>, PutawayError>> + Send;
+
+    fn validate_bin_location(
+        &self,
+        location: &str,
+        bin_no: &str,
+    ) -> impl Future<Output = Result<bool, PutawayError>> + Send;
+
+    /// Returns (quantity to move, whether it empties the source bin)
+    fn validate_transfer_request(
+        &self,
+        lot_no: &str,
+        item_key: &str,
+        location: &str,
+        bin_from: &str,
+        bin_to: &str,
+        transfer_qty: f64,
+    ) -> impl Future<Output = Result<(f64, bool), PutawayError>> + Send;
+
+    /// Response already recorded for an `Idempotency-Key`, if any
+    fn find_idempotent_response(
+        &self,
+        key: &IdempotencyKey,
+    ) -> impl Future<Output = Result<Option<StoredResponse>, PutawayError>> + Send;
+
+    /// Move stock and return the result reported to the client (`message` on success).
+    /// With an idempotency key, the result is stored in the same transaction.
+    #[allow(clippy::too_many_arguments)]
+    fn execute_bin_transfer_transaction(
+        &self,
+        lot_no: &str,
+        item_key: &str,
+        location: &str,
+        bin_from: &str,
+        bin_to: &str,
+        transfer_qty: f64,
+        user_id: &str,
+        remarks: &str,
+        referenced: &str,
+        message: &str,
+        idempotency_key: Option<&IdempotencyKey>,
+    ) -> impl Future<Output = Result<TransferResult, PutawayError>> + Send;
+
+    fn search_lots_paginated(
+        &self,
+        query: Option<&str>,
+        page: i32,
+        limit: i32,
+    ) -> impl Future<Output = Result<(Vec<LotSearchItem>, i32), PutawayError>> + Send;
+
+    fn search_bins_paginated(
+        &self,
+        query: Option<&str>,
+        page: i32,
+        limit: i32,
+        lot_no: Option<&str>,
+        item_key: Option<&str>,
+        location: Option<&str>,
+    ) -> impl Future<Output = Result<(Vec<BinSearchItem>, i32), PutawayError>> + Send;
+
+    fn get_active_remarks(
+        &self,
+    ) -> impl Future<Output = Result<Vec<serde_json::Value>, PutawayError>> + Send;
+}
+
+/// Item master, GTIN and LotMaster lookups behind barcode scans
+pub trait ScanRepository: Send + Sync {
+    fn find_item(&self, item_key: &str) -> impl Future<Output = Result<Option<ScannedItem>>> + Send;
+
+    fn find_item_by_gtin(&self, gtin: &str) -> impl Future<Output = Result<Option<ScannedItem>>> + Send;
+
+    fn upsert_item_gtin(&self, gtin: &ItemGtin) -> impl Future<Output = Result<()>> + Send;
+
+    /// Every LotMaster row of a lot number, whatever its item, bin or quantity
+    fn get_lot_master_entries(&self, lot_no: &str) -> impl Future<Output = Result<Vec<LotMasterEntry>>> + Send;
+}
+
+/// Label printer registry and the print job queue
+pub trait PrintJobRepository: Send + Sync {
+    /// Add or update a printer; a new default printer replaces the previous one
+    fn upsert_printer(&self, printer: &LabelPrinter) -> impl Future<Output = Result<()>> + Send;
+
+    fn list_printers(&self) -> impl Future<Output = Result<Vec<LabelPrinter>>> + Send;
+
+    /// A printer by id, or the active default printer when `printer_id` is None
+    fn get_printer(&self, printer_id: Option<&str>) -> impl Future<Output = Result<Option<LabelPrinter>>> + Send;
+
+    fn create_print_job(&self, job: &NewPrintJob) -> impl Future<Output = Result<PrintJob>> + Send;
+
+    fn get_print_job(&self, job_id: i64) -> impl Future<Output = Result<Option<PrintJob>>> + Send;
+
+    /// Newest jobs first, optionally for one run
+    fn list_print_jobs(&self, run_no: Option<i32>, limit: u32) -> impl Future<Output = Result<Vec<PrintJob>>> + Send;
+
+    /// Move the oldest due job to PRINTING until `lease_until`
+    fn claim_due_print_job(
+        &self,
+        lease_until: chrono::DateTime<chrono::Utc>,
+    ) -> impl Future<Output = Result<Option<PrintJob>>> + Send;
+
+    /// Record a send of a job claimed until `claimed_until`; false when the claim was lost
+    fn finish_print_attempt(
+        &self,
+        job_id: i64,
+        claimed_until: chrono::DateTime<chrono::Utc>,
+        attempt: &PrintAttempt,
+    ) -> impl Future<Output = Result<bool>> + Send;
+
+    /// FAILED back to QUEUED with its attempts reset; false when the job is not FAILED
+    fn requeue_print_job(&self, job_id: i64) -> impl Future<Output = Result<bool>> + Send;
+}
+
+impl BulkRunRepository for Database {
+    async fn search_bulk_runs(&self, search_query: &str, search_mode: &str) -> Result<Vec<BulkRun>> {
+        Database::search_bulk_runs(self, search_query, search_mode).await
+    }
+
+    async fn get_bulk_run(&self, run_no: i32) -> Result<Option<BulkRun>> {
+        Database::get_bulk_run(self, run_no).await
+    }
+
+    async fn get_bulk_run_status(&self, run_no: i32) -> Result<Option<BulkRunStatusResponse>> {
+        Database::get_bulk_run_status(self, run_no).await
+    }
+
+    async fn list_active_bulk_runs_paginated(
+        &self,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<BulkRunSummary>, u64)> {
+        Database::list_active_bulk_runs_paginated(self, page, limit).await
+    }
+
+    async fn get_bulk_run_ingredients(&self, run_no: i32) -> Result<Vec<BulkPickedItem>> {
+        Database::get_bulk_run_ingredients(self, run_no).await
+    }
+
+    async fn get_unique_ingredients_for_search(&self, run_no: i32) -> Result<Vec<BulkPickedItem>> {
+        Database::get_unique_ingredients_for_search(self, run_no).await
+    }
+
+    async fn get_bulk_run_batches(&self, run_no: i32) -> Result<Vec<BulkPickedItem>> {
+        Database::get_bulk_run_batches(self, run_no).await
+    }
+
+    async fn get_bulk_run_batches_for_ingredient(
+        &self,
+        run_no: i32,
+        item_key: &str,
+    ) -> Result<Vec<BulkPickedItem>> {
+        Database::get_bulk_run_batches_for_ingredient(self, run_no, item_key).await
+    }
+
+    async fn get_pallet_tracking_data(
+        &self,
+        run_no: i32,
+        item_key: Option<&str>,
+    ) -> Result<Vec<PalletBatch>> {
+        Database::get_pallet_tracking_data(self, run_no, item_key).await
+    }
+
+    async fn is_pallet_completed(&self, run_no: i32, row_num: i32, line_id: i32) -> Result<bool> {
+        Database::is_pallet_completed(self, run_no, row_num, line_id).await
+    }
+
+    async fn get_next_available_pallet(
+        &self,
+        run_no: i32,
+        current_row_num: i32,
+        line_id: i32,
+    ) -> Result<Option<PalletBatchInfo>> {
+        Database::get_next_available_pallet(self, run_no, current_row_num, line_id).await
+    }
+
+    async fn validate_pick_request(
+        &self,
+        run_no: i32,
+        request: &PickConfirmationRequest,
+    ) -> Result<PickValidationResult> {
+        Database::validate_pick_request(self, run_no, request).await
+    }
+
+    async fn find_idempotent_response(&self, key: &IdempotencyKey) -> Result<Option<StoredResponse>> {
+        Database::find_idempotent_response(self, key).await
+    }
+
+    async fn confirm_pick_transaction(
+        &self,
+        run_no: i32,
+        request: &PickConfirmationRequest,
+    ) -> Result<PickConfirmationResponse, BulkRunError> {
+        Database::confirm_pick_transaction(self, run_no, request).await
+    }
+
+    async fn unpick_by_lot_tran_no(&self, lot_tran_no: i32, user_id: &str) -> Result<serde_json::Value> {
+        Database::unpick_by_lot_tran_no(self, lot_tran_no, user_id).await
+    }
+
+    async fn unpick_specific_lot(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        lot_no: &str,
+        user_id: &str,
+    ) -> Result<serde_json::Value> {
+        Database::unpick_specific_lot(self, run_no, row_num, line_id, lot_no, user_id).await
+    }
+
+    async fn unpick_entire_batch(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        user_id: &str,
+    ) -> Result<serde_json::Value> {
+        Database::unpick_entire_batch(self, run_no, row_num, line_id, user_id).await
+    }
+
+    async fn unpick_all_run_lots(&self, run_no: i32, user_id: &str) -> Result<serde_json::Value> {
+        Database::unpick_all_run_lots(self, run_no, user_id).await
+    }
+
+    async fn get_run_completion_status(&self, run_no: i32) -> Result<RunCompletionStatus> {
+        Database::get_run_completion_status(self, run_no).await
+    }
+
+    async fn update_bulk_run_status(&self, run_no: i32, new_status: &str, user_id: &str) -> Result<bool> {
+        Database::update_bulk_run_status(self, run_no, new_status, user_id).await
+    }
+
+    async fn revert_run_status_to_new(&self, run_no: i32, user_id: &str) -> Result<bool> {
+        Database::revert_run_status_to_new(self, run_no, user_id).await
+    }
+
+    async fn get_batch_weight_summary(&self, run_no: i32) -> Result<Vec<BatchWeightSummaryItem>> {
+        Database::get_batch_weight_summary(self, run_no).await
+    }
+
+    async fn get_lot_picking_details_for_run(&self, run_no: i32) -> Result<Vec<LotPickingDetail>> {
+        Database::get_lot_picking_details_for_run(self, run_no).await
+    }
+
+    async fn acquire_claim(
+        &self,
+        run_no: i32,
+        line_id: Option<i32>,
+        username: &str,
+        lease: chrono::Duration,
+        take_over: bool,
+    ) -> Result<ClaimOutcome> {
+        Database::acquire_claim(self, run_no, line_id, username, lease, take_over).await
+    }
+
+    async fn release_claim(&self, run_no: i32, line_id: Option<i32>, holder: Option<&str>) -> Result<bool> {
+        Database::release_claim(self, run_no, line_id, holder).await
+    }
+
+    async fn get_active_claims(&self, run_nos: &[i32]) -> Result<Vec<RunClaim>> {
+        Database::get_active_claims(self, run_nos).await
+    }
+
+    async fn save_pick_plan(&self, plan: &PickPlan) -> Result<()> {
+        Database::save_pick_plan(self, plan).await
+    }
+
+    async fn get_pick_plan(&self, run_no: i32) -> Result<Option<PickPlan>> {
+        Database::get_pick_plan(self, run_no).await
+    }
+
+    async fn mark_pick_plan_step_picked(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        lot_no: &str,
+        bin_no: &str,
+    ) -> Result<bool> {
+        Database::mark_pick_plan_step_picked(self, run_no, row_num, line_id, lot_no, bin_no).await
+    }
+
+    async fn get_pallet_sscc(&self, run_no: i32, row_num: i32) -> Result<Option<PalletSscc>> {
+        Database::get_pallet_sscc(self, run_no, row_num).await
+    }
+
+    async fn find_pallet_by_sscc(&self, sscc: &str) -> Result<Option<PalletSscc>> {
+        Database::find_pallet_by_sscc(self, sscc).await
+    }
+
+    async fn assign_pallet_sscc(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        batch_no: &str,
+        config: &SsccConfig,
+        username: &str,
+    ) -> Result<PalletSscc> {
+        Database::assign_pallet_sscc(self, run_no, row_num, batch_no, config, username).await
+    }
+
+    async fn record_pick_scan_exception(&self, exception: &PickScanException) -> Result<()> {
+        Database::record_pick_scan_exception(self, exception).await
+    }
+
+    async fn get_pick_scan_exceptions(&self, run_no: i32) -> Result<Vec<PickScanException>> {
+        Database::get_pick_scan_exceptions(self, run_no).await
+    }
+}
+
+impl LotRepository for Database {
+    async fn get_available_lots(&self, run_no: i32, item_key: &str) -> Result<Vec<LotInfo>> {
+        Database::get_available_lots(self, run_no, item_key).await
+    }
+
+    async fn get_inventory_info(&self, run_no: i32, item_key: &str) -> Result<InventoryInfo> {
+        Database::get_inventory_info(self, run_no, item_key).await
+    }
+
+    async fn get_bin_locations(&self, location: &str, bin_nos: &[String]) -> Result<Vec<BinLocation>> {
+        Database::get_bin_locations(self, location, bin_nos).await
+    }
+
+    async fn search_lots_for_run_item_paginated(
+        &self,
+        run_no: i32,
+        item_key: &str,
+        page: u32,
+        page_size: u32,
+    ) -> Result<(Vec<LotSearchResult>, u64)> {
+        Database::search_lots_for_run_item_paginated(self, run_no, item_key, page, page_size).await
+    }
+
+    async fn get_bins_for_lot(
+        &self,
+        run_no: i32,
+        lot_no: &str,
+        item_key: &str,
+    ) -> Result<Vec<LotSearchResult>> {
+        Database::get_bins_for_lot(self, run_no, lot_no, item_key).await
+    }
+}
+
+impl ScanRepository for Database {
+    async fn find_item(&self, item_key: &str) -> Result<Option<ScannedItem>> {
+        Database::find_item(self, item_key).await
+    }
+
+    async fn find_item_by_gtin(&self, gtin: &str) -> Result<Option<ScannedItem>> {
+        Database::find_item_by_gtin(self, gtin).await
+    }
+
+    async fn upsert_item_gtin(&self, gtin: &ItemGtin) -> Result<()> {
+        Database::upsert_item_gtin(self, gtin).await
+    }
+
+    async fn get_lot_master_entries(&self, lot_no: &str) -> Result<Vec<LotMasterEntry>> {
+        Database::get_lot_master_entries(self, lot_no).await
+    }
+}
+
+impl PrintJobRepository for Database {
+    async fn upsert_printer(&self, printer: &LabelPrinter) -> Result<()> {
+        Database::upsert_printer(self, printer).await
+    }
+
+    async fn list_printers(&self) -> Result<Vec<LabelPrinter>> {
+        Database::list_printers(self).await
+    }
+
+    async fn get_printer(&self, printer_id: Option<&str>) -> Result<Option<LabelPrinter>> {
+        Database::get_printer(self, printer_id).await
+    }
+
+    async fn create_print_job(&self, job: &NewPrintJob) -> Result<PrintJob> {
+        Database::create_print_job(self, job).await
+    }
+
+    async fn get_print_job(&self, job_id: i64) -> Result<Option<PrintJob>> {
+        Database::get_print_job(self, job_id).await
+    }
+
+    async fn list_print_jobs(&self, run_no: Option<i32>, limit: u32) -> Result<Vec<PrintJob>> {
+        Database::list_print_jobs(self, run_no, limit).await
+    }
+
+    async fn claim_due_print_job(&self, lease_until: chrono::DateTime<chrono::Utc>) -> Result<Option<PrintJob>> {
+        Database::claim_due_print_job(self, lease_until).await
+    }
+
+    async fn finish_print_attempt(
+        &self,
+        job_id: i64,
+        claimed_until: chrono::DateTime<chrono::Utc>,
+        attempt: &PrintAttempt,
+    ) -> Result<bool> {
+        Database::finish_print_attempt(self, job_id, claimed_until, attempt).await
+    }
+
+    async fn requeue_print_job(&self, job_id: i64) -> Result<bool> {
+        Database::requeue_print_job(self, job_id).await
+    }
+}
+
+impl PutawayRepository for PutawayDatabase {
+    async fn find_lot_by_number(
+        &self,
+        lot_no: &str,
+    ) -> Result<Option<(LotMasterRecord, ItemMasterRecord)>, PutawayError> {
+        PutawayDatabase::find_lot_by_number(self, lot_no).await
+    }
+
+    async fn validate_bin_location(&self, location: &str, bin_no: &str) -> Result<bool, PutawayError> {
+        PutawayDatabase::validate_bin_location(self, location, bin_no).await
+    }
+
+    async fn validate_transfer_request(
+        &self,
+        lot_no: &str,
+        item_key: &str,
+        location: &str,
+        bin_from: &str,
+        bin_to: &str,
+        transfer_qty: f64,
+    ) -> Result<(f64, bool), PutawayError> {
+        PutawayDatabase::validate_transfer_request(self, lot_no, item_key, location, bin_from, bin_to, transfer_qty)
+            .await
+    }
+
+    async fn find_idempotent_response(&self, key: &IdempotencyKey) -> Result<Option<StoredResponse>, PutawayError> {
+        PutawayDatabase::find_idempotent_response(self, key).await
+    }
+
+    async fn execute_bin_transfer_transaction(
+        &self,
+        lot_no: &str,
+        item_key: &str,
+        location: &str,
+        bin_from: &str,
+        bin_to: &str,
+        transfer_qty: f64,
+        user_id: &str,
+        remarks: &str,
+        referenced: &str,
+        message: &str,
+        idempotency_key: Option<&IdempotencyKey>,
+    ) -> Result<TransferResult, PutawayError> {
+        PutawayDatabase::execute_bin_transfer_transaction(
+            self,
+            lot_no,
+            item_key,
+            location,
+            bin_from,
+            bin_to,
+            transfer_qty,
+            user_id,
+            remarks,
+            referenced,
+            message,
+            idempotency_key,
+        )
+        .await
+    }
+
+    async fn search_lots_paginated(
+        &self,
+        query: Option<&str>,
+        page: i32,
+        limit: i32,
+    ) -> Result<(Vec<LotSearchItem>, i32), PutawayError> {
+        PutawayDatabase::search_lots_paginated(self, query, page, limit).await
+    }
+
+    async fn search_bins_paginated(
+        &self,
+        query: Option<&str>,
+        page: i32,
+        limit: i32,
+        lot_no: Option<&str>,
+        item_key: Option<&str>,
+        location: Option<&str>,
+    ) -> Result<(Vec<BinSearchItem>, i32), PutawayError> {
+        PutawayDatabase::search_bins_paginated(self, query, page, limit, lot_no, item_key, location).await
+    }
+
+    async fn get_active_remarks(&self) -> Result<Vec<serde_json::Value>, PutawayError> {
+        PutawayDatabase::get_active_remarks(self).await
+    }
+}
@@ -0,0 +1,236 @@
+use anyhow::{Context, Result};
+use chrono::{Duration, NaiveDateTime, Utc};
+use tiberius::{Client, Row};
+use tokio::net::TcpStream;
+use tokio_util::compat::Compat;
+use tracing::{error, info};
+
+use crate::database::Database;
+use crate::models::run_claims::{ClaimOutcome, RunClaim};
+
+const CLAIM_COLUMNS: &str = "RunNo, LineId, Username, ClaimedAt, ExpiresAt";
+
+/// LineId 0 marks a claim on the whole run (the column is part of the primary key)
+fn line_id_column(line_id: Option<i32>) -> i32 {
+    line_id.unwrap_or(0)
+}
+
+fn claim_from_row(row: &Row) -> RunClaim {
+    RunClaim {
+        run_no: row.get::<i32, _>("RunNo").unwrap_or_default(),
+        line_id: row.get::<i32, _>("LineId").filter(|&line_id| line_id != 0),
+        username: row.get::<&str, _>("Username").unwrap_or_default().to_string(),
+        claimed_at: row.get::<NaiveDateTime, _>("ClaimedAt").unwrap_or_default().and_utc(),
+        expires_at: row.get::<NaiveDateTime, _>("ExpiresAt").unwrap_or_default().and_utc(),
+    }
+}
+
+/// Another user's live claim overlapping `line_id` of the run, read inside the caller's
+/// transaction. The claim rows and key range stay locked until it ends, so a take-over or a new
+/// claim after a lease expires waits for the pick being committed instead of racing it.
+pub async fn find_blocking_claim(
+    client: &mut Client<Compat<TcpStream>>,
+    run_no: i32,
+    line_id: i32,
+    username: &str,
+) -> Result<Option<RunClaim>> {
+    let query = format!(
+        r#"
+        SELECT TOP 1 {CLAIM_COLUMNS}
+        FROM tbl_bulk_run_claims WITH (UPDLOCK, HOLDLOCK)
+        WHERE RunNo = @P1 AND Username <> @P2 AND (LineId = 0 OR LineId = @P3)
+          AND ExpiresAt > SYSUTCDATETIME()
+        ORDER BY LineId
+        "#
+    );
+    let row = client
+        .query(query, &[&run_no, &username, &line_id])
+        .await
+        .context("Failed to lock run claims")?
+        .into_row()
+        .await
+        .context("Failed to read locked run claim")?;
+
+    Ok(row.map(|row| claim_from_row(&row)))
+}
+
+impl Database {
+    /// Take or renew `username`'s claim on a run or line.
+    /// Expired claims are dropped first; overlapping claims of other users are locked for the
+    /// rest of the transaction, so two pickers claiming at once cannot both succeed.
+    pub async fn acquire_claim(
+        &self,
+        run_no: i32,
+        line_id: Option<i32>,
+        username: &str,
+        lease: Duration,
+        take_over: bool,
+    ) -> Result<ClaimOutcome> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for run claim")?;
+
+        client
+            .simple_query("BEGIN TRANSACTION")
+            .await
+            .context("Failed to start run claim transaction")?;
+
+        let line = line_id_column(line_id);
+        let result: Result<ClaimOutcome> = async {
+            client
+                .execute(
+                    "DELETE FROM tbl_bulk_run_claims WHERE RunNo = @P1 AND ExpiresAt <= SYSUTCDATETIME()",
+                    &[&run_no],
+                )
+                .await
+                .context("Failed to clear expired run claims")?;
+
+            let overlapping = format!(
+                r#"
+                SELECT {CLAIM_COLUMNS}
+                FROM tbl_bulk_run_claims WITH (UPDLOCK, HOLDLOCK)
+                WHERE RunNo = @P1 AND Username <> @P2 AND (@P3 = 0 OR LineId = 0 OR LineId = @P3)
+                "#
+            );
+            let holder = client
+                .query(overlapping, &[&run_no, &username, &line])
+                .await
+                .context("Failed to query overlapping run claims")?
+                .into_row()
+                .await
+                .context("Failed to read overlapping run claim")?
+                .map(|row| claim_from_row(&row));
+
+            if let Some(holder) = holder {
+                if !take_over {
+                    return Ok(ClaimOutcome::HeldBy(holder));
+                }
+                info!("⚠️ CLAIM: {} takes over run {} from {}", username, run_no, holder.username);
+                client
+                    .execute(
+                        r#"
+                        DELETE FROM tbl_bulk_run_claims
+                        WHERE RunNo = @P1 AND Username <> @P2 AND (@P3 = 0 OR LineId = 0 OR LineId = @P3)
+                        "#,
+                        &[&run_no, &username, &line],
+                    )
+                    .await
+                    .context("Failed to end overlapping run claims")?;
+            }
+
+            let now = Utc::now();
+            let expires_at = (now + lease).naive_utc();
+            let renewed = client
+                .execute(
+                    r#"
+                    UPDATE tbl_bulk_run_claims
+                    SET ExpiresAt = @P4
+                    WHERE RunNo = @P1 AND LineId = @P2 AND Username = @P3
+                    "#,
+                    &[&run_no, &line, &username, &expires_at],
+                )
+                .await
+                .context("Failed to renew run claim")?
+                .total();
+
+            if renewed == 0 {
+                client
+                    .execute(
+                        r#"
+                        INSERT INTO tbl_bulk_run_claims (RunNo, LineId, Username, ClaimedAt, ExpiresAt)
+                        VALUES (@P1, @P2, @P3, @P4, @P5)
+                        "#,
+                        &[&run_no, &line, &username, &now.naive_utc(), &expires_at],
+                    )
+                    .await
+                    .context("Failed to store run claim")?;
+            }
+
+            let claim = client
+                .query(
+                    format!("SELECT {CLAIM_COLUMNS} FROM tbl_bulk_run_claims WHERE RunNo = @P1 AND LineId = @P2"),
+                    &[&run_no, &line],
+                )
+                .await
+                .context("Failed to read back run claim")?
+                .into_row()
+                .await
+                .context("Failed to read back run claim")?
+                .map(|row| claim_from_row(&row))
+                .context("Run claim missing after insert")?;
+
+            Ok(ClaimOutcome::Acquired(claim))
+        }
+        .await;
+
+        match result {
+            Ok(outcome) => {
+                client
+                    .simple_query("COMMIT")
+                    .await
+                    .context("Failed to commit run claim transaction")?;
+                Ok(outcome)
+            }
+            Err(e) => {
+                let _ = client.simple_query("ROLLBACK").await;
+                error!("❌ Run claim failed for run {}, rolled back: {}", run_no, e);
+                Err(e)
+            }
+        }
+    }
+
+    /// End a claim. With `holder`, only that user's claim is removed.
+    pub async fn release_claim(&self, run_no: i32, line_id: Option<i32>, holder: Option<&str>) -> Result<bool> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for run claim release")?;
+
+        let released = client
+            .execute(
+                r#"
+                DELETE FROM tbl_bulk_run_claims
+                WHERE RunNo = @P1 AND LineId = @P2 AND (@P3 = '' OR Username = @P3)
+                "#,
+                &[&run_no, &line_id_column(line_id), &holder.unwrap_or_default()],
+            )
+            .await
+            .context("Failed to release run claim")?
+            .total();
+        Ok(released > 0)
+    }
+
+    /// Unexpired claims on the given runs
+    pub async fn get_active_claims(&self, run_nos: &[i32]) -> Result<Vec<RunClaim>> {
+        if run_nos.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for run claims")?;
+
+        let placeholders = (1..=run_nos.len()).map(|i| format!("@P{i}")).collect::<Vec<_>>().join(", ");
+        let query = format!(
+            r#"
+            SELECT {CLAIM_COLUMNS}
+            FROM tbl_bulk_run_claims
+            WHERE RunNo IN ({placeholders}) AND ExpiresAt > SYSUTCDATETIME()
+            ORDER BY RunNo, LineId
+            "#
+        );
+        let params: Vec<&dyn tiberius::ToSql> = run_nos.iter().map(|run_no| run_no as &dyn tiberius::ToSql).collect();
+
+        let rows = client
+            .query(query, &params)
+            .await
+            .context("Failed to query run claims")?
+            .into_first_result()
+            .await
+            .context("Failed to read run claims")?;
+
+        Ok(rows.iter().map(claim_from_row).collect())
+    }
+}
@@ -0,0 +1,128 @@
+use anyhow::{Context, Result};
+use bigdecimal::{BigDecimal, FromPrimitive};
+use chrono::NaiveDateTime;
+use tiberius::Row;
+
+use crate::database::Database;
+use crate::models::scans::{ItemGtin, LotMasterEntry, ScannedItem};
+
+fn item_from_row(row: &Row) -> ScannedItem {
+    ScannedItem {
+        item_key: row.get::<&str, _>("Itemkey").unwrap_or_default().trim().to_string(),
+        description: row.get::<&str, _>("Desc1").unwrap_or_default().to_string(),
+    }
+}
+
+impl Database {
+    /// INMAST item by its key
+    pub async fn find_item(&self, item_key: &str) -> Result<Option<ScannedItem>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for item lookup")?;
+
+        let row = client
+            .query("SELECT Itemkey, Desc1 FROM INMAST WHERE Itemkey = @P1", &[&item_key])
+            .await
+            .context("Failed to query item")?
+            .into_row()
+            .await
+            .context("Failed to read item")?;
+        Ok(row.as_ref().map(item_from_row))
+    }
+
+    /// The item a GTIN-14 is registered to
+    pub async fn find_item_by_gtin(&self, gtin: &str) -> Result<Option<ScannedItem>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for GTIN lookup")?;
+
+        let row = client
+            .query(
+                r#"
+                SELECT i.Itemkey, i.Desc1
+                FROM tbl_item_gtin g
+                JOIN INMAST i ON i.Itemkey = g.ItemKey
+                WHERE g.GTIN = @P1
+                "#,
+                &[&gtin],
+            )
+            .await
+            .context("Failed to query GTIN")?
+            .into_row()
+            .await
+            .context("Failed to read GTIN")?;
+        Ok(row.as_ref().map(item_from_row))
+    }
+
+    /// Map a GTIN to an item, replacing any earlier mapping of the GTIN
+    pub async fn upsert_item_gtin(&self, gtin: &ItemGtin) -> Result<()> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for GTIN registration")?;
+
+        client
+            .execute(
+                r#"
+                MERGE tbl_item_gtin WITH (HOLDLOCK) AS target
+                USING (SELECT @P1 AS GTIN) AS source ON target.GTIN = source.GTIN
+                WHEN MATCHED THEN
+                    UPDATE SET ItemKey = @P2, RegisteredBy = @P3, RegisteredAt = @P4
+                WHEN NOT MATCHED THEN
+                    INSERT (GTIN, ItemKey, RegisteredBy, RegisteredAt) VALUES (@P1, @P2, @P3, @P4);
+                "#,
+                &[
+                    &gtin.gtin.as_str(),
+                    &gtin.item_key.as_str(),
+                    &gtin.registered_by.as_str(),
+                    &gtin.registered_at.naive_utc(),
+                ],
+            )
+            .await
+            .context("Failed to register GTIN")?;
+        Ok(())
+    }
+
+    /// Every LotMaster row of a lot number, whatever its item, bin or quantity
+    pub async fn get_lot_master_entries(&self, lot_no: &str) -> Result<Vec<LotMasterEntry>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for LotMaster lookup")?;
+
+        let rows = client
+            .query(
+                r#"
+                SELECT LotNo, ItemKey, LocationKey, BinNo, DateExpiry, QtyOnHand, QtyCommitSales, LotStatus
+                FROM LotMaster
+                WHERE LotNo = @P1
+                ORDER BY QtyOnHand DESC, BinNo
+                "#,
+                &[&lot_no],
+            )
+            .await
+            .context("Failed to query LotMaster")?
+            .into_first_result()
+            .await
+            .context("Failed to read LotMaster")?;
+
+        let quantity = |row: &Row, column: &str| {
+            BigDecimal::from_f64(row.get::<f64, _>(column).unwrap_or_default()).unwrap_or_default()
+        };
+        Ok(rows
+            .iter()
+            .map(|row| LotMasterEntry {
+                lot_no: row.get::<&str, _>("LotNo").unwrap_or_default().trim().to_string(),
+                item_key: row.get::<&str, _>("ItemKey").unwrap_or_default().trim().to_string(),
+                location_key: row.get::<&str, _>("LocationKey").unwrap_or_default().to_string(),
+                bin_no: row.get::<&str, _>("BinNo").unwrap_or_default().to_string(),
+                date_expiry: row.get::<NaiveDateTime, _>("DateExpiry").map(|dt| dt.date()),
+                qty_on_hand: quantity(row, "QtyOnHand"),
+                qty_commit_sales: quantity(row, "QtyCommitSales"),
+                lot_status: row.get::<&str, _>("LotStatus").unwrap_or_default().to_string(),
+            })
+            .collect())
+    }
+}
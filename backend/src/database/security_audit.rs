@@ -0,0 +1,147 @@
+use anyhow::{Context, Result};
+use chrono::NaiveDateTime;
+use tiberius::{Query, Row};
+
+use crate::database::Database;
+use crate::models::security_audit::{SecurityAuditEntry, SecurityAuditQuery, SecurityEvent};
+
+/// Bound filter value for the dynamic audit query
+enum AuditFilter {
+    Text(String),
+    Time(NaiveDateTime),
+}
+
+impl Database {
+    /// Append a security event to tbl_security_audit
+    pub async fn insert_security_event(&self, event: &SecurityEvent) -> Result<()> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for security audit")?;
+
+        let detail = event
+            .detail
+            .as_deref()
+            .map(|detail| detail.chars().take(400).collect::<String>());
+
+        client
+            .execute(
+                r#"
+                INSERT INTO tbl_security_audit
+                    (EventTime, EventType, Username, SourceIp, AuthMethod, Outcome, Detail)
+                VALUES (SYSUTCDATETIME(), @P1, @P2, @P3, @P4, @P5, @P6)
+                "#,
+                &[
+                    &event.event_type.as_str(),
+                    &event.username.as_deref(),
+                    &event.source_ip.map(|ip| ip.to_string()),
+                    &event.auth_method.map(|method| method.as_str()),
+                    &event.outcome.as_str(),
+                    &detail,
+                ],
+            )
+            .await
+            .context("Failed to write security audit event")?;
+        Ok(())
+    }
+
+    /// Page through security events, newest first. Returns (events, total matching rows).
+    pub async fn query_security_events(
+        &self,
+        filter: &SecurityAuditQuery,
+        page: u32,
+        limit: u32,
+    ) -> Result<(Vec<SecurityAuditEntry>, u64)> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for security audit query")?;
+
+        let mut conditions = Vec::new();
+        let mut params = Vec::new();
+        let mut add = |condition: &str, value: AuditFilter| {
+            params.push(value);
+            conditions.push(condition.replace("{}", &format!("@P{}", params.len())));
+        };
+        if let Some(username) = filter.username.as_deref().filter(|u| !u.trim().is_empty()) {
+            add("Username = {}", AuditFilter::Text(username.trim().to_string()));
+        }
+        if let Some(event_type) = filter.event_type {
+            add("EventType = {}", AuditFilter::Text(event_type.as_str().to_string()));
+        }
+        if let Some(source_ip) = filter.source_ip.as_deref().filter(|ip| !ip.trim().is_empty()) {
+            add("SourceIp = {}", AuditFilter::Text(source_ip.trim().to_string()));
+        }
+        if let Some(from) = filter.from {
+            add("EventTime >= {}", AuditFilter::Time(from));
+        }
+        if let Some(to) = filter.to {
+            add("EventTime < {}", AuditFilter::Time(to));
+        }
+
+        let where_clause = if conditions.is_empty() {
+            String::new()
+        } else {
+            format!("WHERE {}", conditions.join(" AND "))
+        };
+
+        let bind_filters = |query: &mut Query<'_>| {
+            for param in &params {
+                match param {
+                    AuditFilter::Text(value) => query.bind(value.clone()),
+                    AuditFilter::Time(value) => query.bind(*value),
+                }
+            }
+        };
+
+        let mut count_query = Query::new(format!(
+            "SELECT COUNT_BIG(*) AS Total FROM tbl_security_audit {where_clause}"
+        ));
+        bind_filters(&mut count_query);
+        let total = count_query
+            .query(&mut *client)
+            .await
+            .context("Failed to count security events")?
+            .into_row()
+            .await
+            .context("Failed to read security event count")?
+            .and_then(|row| row.get::<i64, _>("Total"))
+            .unwrap_or(0);
+
+        let offset = page.saturating_sub(1) as i64 * limit as i64;
+        let mut page_query = Query::new(format!(
+            r#"
+            SELECT AuditId, EventTime, EventType, Username, SourceIp, AuthMethod, Outcome, Detail
+            FROM tbl_security_audit
+            {where_clause}
+            ORDER BY EventTime DESC, AuditId DESC
+            OFFSET {offset} ROWS FETCH NEXT {limit} ROWS ONLY
+            "#
+        ));
+        bind_filters(&mut page_query);
+        let rows = page_query
+            .query(&mut *client)
+            .await
+            .context("Failed to query security events")?
+            .into_first_result()
+            .await
+            .context("Failed to read security events")?;
+
+        Ok((rows.iter().map(security_entry_from_row).collect(), total.max(0) as u64))
+    }
+}
+
+fn security_entry_from_row(row: &Row) -> SecurityAuditEntry {
+    let optional = |column: &str| row.get::<&str, _>(column).map(|value| value.to_string());
+
+    SecurityAuditEntry {
+        audit_id: row.get::<i64, _>("AuditId").unwrap_or_default(),
+        event_time: row.get::<NaiveDateTime, _>("EventTime").unwrap_or_default(),
+        event_type: optional("EventType").unwrap_or_default(),
+        username: optional("Username"),
+        source_ip: optional("SourceIp"),
+        auth_method: optional("AuthMethod"),
+        outcome: optional("Outcome").unwrap_or_default(),
+        detail: optional("Detail"),
+    }
+}
@@ -0,0 +1,155 @@
+use anyhow::{Context, Result};
+use tiberius::{Query, Row};
+use tracing::info;
+
+use crate::database::Database;
+use crate::types::Role;
+use crate::utils::roles::roles_from_column;
+
+impl Database {
+    /// Replace a tbl_user password with its hash.
+    /// Only updates the row if it still holds `previous_value`, so a concurrent password change is never overwritten.
+    pub async fn update_user_password_hash(
+        &self,
+        username: &str,
+        previous_value: &str,
+        password_hash: &str,
+    ) -> Result<bool> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for password upgrade")?;
+
+        let query = r#"
+            UPDATE tbl_user
+            SET pword = @P3
+            WHERE uname = @P1 AND pword = @P2
+        "#;
+
+        let mut query_builder = Query::new(query);
+        query_builder.bind(username);
+        query_builder.bind(previous_value);
+        query_builder.bind(password_hash);
+
+        let result = query_builder
+            .execute(&mut *client)
+            .await
+            .context("Failed to store password hash")?;
+
+        let updated = result.total() > 0;
+        if updated {
+            info!("🔐 Upgraded stored password to Argon2id for user: {}", username);
+        }
+        Ok(updated)
+    }
+
+    /// List tbl_user rows whose password is not yet an Argon2 hash
+    pub async fn get_users_with_plaintext_passwords(&self) -> Result<Vec<(String, String)>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for password scan")?;
+
+        let query = r#"
+            SELECT uname, pword
+            FROM tbl_user
+            WHERE pword IS NOT NULL AND pword <> '' AND pword NOT LIKE '$argon2%'
+            ORDER BY uname
+        "#;
+
+        let stream = Query::new(query)
+            .query(&mut *client)
+            .await
+            .context("Failed to query plaintext passwords")?;
+        let rows: Vec<Row> = stream
+            .into_first_result()
+            .await
+            .context("Failed to read plaintext passwords")?;
+
+        Ok(rows
+            .iter()
+            .filter_map(|row| {
+                let username: &str = row.get("uname")?;
+                let password: &str = row.get("pword")?;
+                Some((username.to_string(), password.to_string()))
+            })
+            .collect())
+    }
+
+    /// Maximum length of tbl_user.pword in characters (-1 for MAX)
+    pub async fn get_password_column_length(&self) -> Result<Option<i32>> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for column lookup")?;
+
+        let query = r#"
+            SELECT CHARACTER_MAXIMUM_LENGTH AS max_length
+            FROM INFORMATION_SCHEMA.COLUMNS
+            WHERE TABLE_NAME = 'tbl_user' AND COLUMN_NAME = 'pword'
+        "#;
+
+        let stream = Query::new(query)
+            .query(&mut *client)
+            .await
+            .context("Failed to query tbl_user.pword length")?;
+        let row = stream.into_row().await.context("Failed to read tbl_user.pword length")?;
+
+        Ok(row.and_then(|r| r.get::<i32, _>("max_length")))
+    }
+
+    /// Current roles of an enabled tbl_user account; None when the user has no enabled row
+    /// or the database predates migrations/003_user_roles.sql
+    pub async fn get_user_roles(&self, username: &str) -> Result<Option<Vec<Role>>> {
+        if !self.column_exists("tbl_user", "wms_roles").await? {
+            return Ok(None);
+        }
+
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for role lookup")?;
+
+        let query = r#"
+            SELECT wms_roles
+            FROM tbl_user
+            WHERE uname = @P1 AND ad_enabled = 1
+        "#;
+
+        let mut query_builder = Query::new(query);
+        query_builder.bind(username);
+
+        let stream = query_builder
+            .query(&mut *client)
+            .await
+            .context("Failed to query user roles")?;
+        let row = stream.into_row().await.context("Failed to read user roles")?;
+
+        Ok(row.map(|r| roles_from_column(r.get("wms_roles"))))
+    }
+
+    /// Whether tbl_user holds the account with `ad_enabled = 0`
+    pub async fn is_user_disabled(&self, username: &str) -> Result<bool> {
+        let mut client = self
+            .get_client()
+            .await
+            .context("Failed to connect to database for account lookup")?;
+
+        let query = r#"
+            SELECT COUNT(*) AS disabled_rows
+            FROM tbl_user
+            WHERE uname = @P1 AND ad_enabled = 0
+        "#;
+
+        let mut query_builder = Query::new(query);
+        query_builder.bind(username);
+
+        let stream = query_builder
+            .query(&mut *client)
+            .await
+            .context("Failed to query account state")?;
+        let row = stream.into_row().await.context("Failed to read account state")?;
+
+        Ok(row.and_then(|r| r.get::<i32, _>("disabled_rows")).unwrap_or(0) > 0)
+    }
+}
@@ -0,0 +1,147 @@
+use axum::{
+    extract::{Extension, Path, State},
+    response::Json,
+};
+use chrono::{Duration, Utc};
+use tracing::{error, info};
+
+use crate::models::api_keys::{ApiKeyRecord, CreateApiKeyRequest, CreatedApiKey};
+use crate::types::{ApiResponse, FieldError};
+use crate::utils::api_keys::generate_api_key;
+use crate::utils::auth::Claims;
+use crate::AppState;
+
+/// Audit columns (RecUserid, ModifiedBy) hold at most 8 characters
+const MAX_AUDIT_USER_LEN: usize = 8;
+
+/// List API keys (admin only)
+/// GET /api/auth/api-keys
+pub async fn list_api_keys(
+    State(state): State<AppState>,
+) -> ApiResponse<Vec<ApiKeyRecord>> {
+    match state.database.list_api_keys().await {
+        Ok(keys) => {
+            let message = format!("{} API key(s)", keys.len());
+            ApiResponse::success(keys, message)
+        }
+        Err(e) => {
+            error!("❌ Failed to list API keys: {}", e);
+            ApiResponse::error("Failed to list API keys")
+        }
+    }
+}
+
+/// Create an API key (admin only). The plaintext key is returned once and never stored.
+/// POST /api/auth/api-keys
+pub async fn create_api_key(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<CreateApiKeyRequest>,
+) -> ApiResponse<CreatedApiKey> {
+    if let Err(errors) = validate_create_request(&request) {
+        return ApiResponse::validation_failed("Invalid API key request", errors);
+    }
+
+    let name = request.name.trim();
+    let audit_user = request.audit_user.trim();
+    let expires_at = request
+        .expires_in_days
+        .map(|days| (Utc::now() + Duration::days(days)).naive_utc());
+    let (key_id, api_key, key_hash) = generate_api_key();
+
+    match state
+        .database
+        .create_api_key(&key_id, &key_hash, name, audit_user, &request.scopes, &claims.username, expires_at)
+        .await
+    {
+        Ok(key) => {
+            info!("🔑 {} created API key {} for '{}' with scopes {:?}", claims.username, key_id, name, key.scopes);
+            ApiResponse::success(
+                CreatedApiKey { api_key, key },
+                "API key created - store it now, it cannot be shown again",
+            )
+        }
+        Err(e) => {
+            error!("❌ Failed to create API key for '{}': {}", name, e);
+            ApiResponse::error("Failed to create API key")
+        }
+    }
+}
+
+/// Revoke an API key (admin only)
+/// POST /api/auth/api-keys/{key_id}/revoke
+pub async fn revoke_api_key(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Path(key_id): Path<String>,
+) -> ApiResponse<bool> {
+    match state.database.revoke_api_key(&key_id, &claims.username).await {
+        Ok(true) => ApiResponse::success(true, "API key revoked"),
+        Ok(false) => ApiResponse::success(false, "API key not found or already revoked"),
+        Err(e) => {
+            error!("❌ Failed to revoke API key {}: {}", key_id, e);
+            ApiResponse::error("Failed to revoke API key")
+        }
+    }
+}
+
+fn validate_create_request(request: &CreateApiKeyRequest) -> Result<(), Vec<FieldError>> {
+    let mut errors = Vec::new();
+
+    let name = request.name.trim();
+    if name.is_empty() || name.chars().count() > 100 {
+        errors.push(FieldError::new("name", "name is required (max 100 characters)"));
+    }
+
+    let audit_user = request.audit_user.trim();
+    if audit_user.is_empty()
+        || audit_user.chars().count() > MAX_AUDIT_USER_LEN
+        || !audit_user.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        errors.push(FieldError::new(
+            "audit_user",
+            format!("audit_user must be 1-{MAX_AUDIT_USER_LEN} letters, digits, '-' or '_' (it is written to RecUserid)"),
+        ));
+    }
+
+    if request.scopes.is_empty() {
+        errors.push(FieldError::new("scopes", "At least one scope is required"));
+    }
+
+    if request.expires_in_days.is_some_and(|days| days <= 0) {
+        errors.push(FieldError::new("expires_in_days", "expires_in_days must be positive"));
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::api_keys::ApiScope;
+
+    fn request(audit_user: &str, scopes: Vec<ApiScope>) -> CreateApiKeyRequest {
+        CreateApiKeyRequest {
+            name: "MES line 2".to_string(),
+            audit_user: audit_user.to_string(),
+            scopes,
+            expires_in_days: None,
+        }
+    }
+
+    #[test]
+    fn test_create_request_validation() {
+        assert!(validate_create_request(&request("MES2", vec![ApiScope::RunsRead])).is_ok());
+        assert!(validate_create_request(&request("MESLINE02", vec![ApiScope::RunsRead])).is_err());
+        assert!(validate_create_request(&request("MES 2", vec![ApiScope::RunsRead])).is_err());
+        assert!(validate_create_request(&request("MES2", vec![])).is_err());
+
+        let errors = validate_create_request(&request("MES 2", vec![])).unwrap_err();
+        let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        assert_eq!(fields, ["audit_user", "scopes"]);
+    }
+}
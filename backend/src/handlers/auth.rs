@@ -0,0 +1,140 @@
+use axum::{
+    extract::{Extension, Query, State},
+    response::Json,
+};
+use serde::Deserialize;
+use std::net::IpAddr;
+use tracing::{error, info, warn};
+
+use crate::models::bulk_runs::PaginationInfo;
+use crate::models::security_audit::{PaginatedSecurityAudit, SecurityAuditQuery};
+use crate::services::login_throttle::LockoutEntry;
+use crate::types::{ApiResponse, FieldError};
+use crate::utils::auth::Claims;
+use crate::utils::jwt_keys::JwkSet;
+use crate::AppState;
+
+/// Request to clear a login lockout by username and/or client IP
+#[derive(Debug, Deserialize)]
+pub struct ClearLockoutRequest {
+    pub username: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+/// Public JWT verification keys (RS256/EdDSA only) for other plant systems
+/// GET /.well-known/jwks.json
+pub async fn jwks(State(state): State<AppState>) -> Json<JwkSet> {
+    Json(state.auth_service.jwks())
+}
+
+/// List usernames and IPs currently in login backoff or lockout
+/// GET /api/auth/lockouts
+pub async fn list_lockouts(
+    State(state): State<AppState>,
+) -> ApiResponse<Vec<LockoutEntry>> {
+    let lockouts = state.login_throttle.active_lockouts();
+    let message = format!("{} active lockout(s)", lockouts.len());
+    ApiResponse::success(lockouts, message)
+}
+
+/// Clear a login lockout (supervisor only)
+/// POST /api/auth/lockouts/clear
+pub async fn clear_lockout(
+    State(state): State<AppState>,
+    Extension(claims): Extension<Claims>,
+    Json(request): Json<ClearLockoutRequest>,
+) -> ApiResponse<bool> {
+    let username = request.username.as_deref().map(str::trim).filter(|u| !u.is_empty());
+    let ip_address = request.ip_address.as_deref().map(str::trim).filter(|ip| !ip.is_empty());
+
+    if username.is_none() && ip_address.is_none() {
+        return ApiResponse::bad_request("Provide a username or ip_address to clear");
+    }
+
+    let mut cleared = false;
+    if let Some(username) = username {
+        cleared |= state.login_throttle.clear_username(username);
+    }
+    if let Some(ip_address) = ip_address {
+        match ip_address.parse::<IpAddr>() {
+            Ok(ip) => cleared |= state.login_throttle.clear_ip(ip),
+            Err(_) => {
+                return ApiResponse::validation_failed(
+                    "Invalid lockout request",
+                    vec![FieldError::new("ip_address", format!("Invalid IP address: {ip_address}"))],
+                )
+            }
+        }
+    }
+
+    if cleared {
+        info!(
+            "🔓 Login lockout cleared by {} (username={:?}, ip={:?})",
+            claims.username, username, ip_address
+        );
+        ApiResponse::success(true, "Lockout cleared")
+    } else {
+        warn!(
+            "⚠️ {} tried to clear a lockout that does not exist (username={:?}, ip={:?})",
+            claims.username, username, ip_address
+        );
+        ApiResponse::success(false, "No lockout found")
+    }
+}
+
+/// Page through the login/security audit trail (supervisor only), newest first
+/// GET /api/auth/security-events?page=1&limit=50&username=&event_type=&source_ip=&from=&to=
+pub async fn list_security_events(
+    State(state): State<AppState>,
+    Query(filter): Query<SecurityAuditQuery>,
+) -> ApiResponse<PaginatedSecurityAudit> {
+    let page = filter.page.unwrap_or(1).max(1);
+    let limit = filter.limit.unwrap_or(50).clamp(1, 200);
+
+    match state.database.query_security_events(&filter, page, limit).await {
+        Ok((events, total)) => {
+            let message = format!("{} security event(s)", total);
+            let response = PaginatedSecurityAudit {
+                events,
+                pagination: PaginationInfo::new(page, limit, total),
+            };
+            ApiResponse::success(response, message)
+        }
+        Err(e) => {
+            error!("❌ Failed to query security events: {}", e);
+            ApiResponse::error("Failed to load security events")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::security_audit::SecurityEventType;
+    use axum::http::Uri;
+
+    #[test]
+    fn test_security_event_filters_from_query_string() {
+        let uri: Uri = "/api/auth/security-events?page=2&limit=25&username=deachawat&event_type=login_failure&from=2026-10-01T00:00:00"
+            .parse()
+            .unwrap();
+        let Query(filter) = Query::<SecurityAuditQuery>::try_from_uri(&uri).unwrap();
+
+        assert_eq!(filter.page, Some(2));
+        assert_eq!(filter.limit, Some(25));
+        assert_eq!(filter.username.as_deref(), Some("deachawat"));
+        assert_eq!(filter.event_type, Some(SecurityEventType::LoginFailure));
+        assert!(filter.from.is_some());
+        assert!(filter.to.is_none());
+    }
+
+    #[test]
+    fn test_security_event_pagination() {
+        let pagination = PaginationInfo::new(2, 25, 60);
+
+        assert_eq!(pagination.total_pages, 3);
+        assert!(pagination.has_previous);
+        assert!(pagination.has_next);
+        assert_eq!(PaginationInfo::new(1, 25, 0).total_pages, 1);
+    }
+}
@@ -1,33 +1,56 @@
 use axum::{
     extract::{Extension, Path, Query, State},
-    http::{HeaderMap, StatusCode},
+    http::{header, HeaderMap, StatusCode},
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
 use bigdecimal::BigDecimal;
-use serde::Serialize;
 use std::collections::HashMap;
 use std::sync::Arc;
+use tokio_stream::{Stream, StreamExt};
 use tracing::{error, info, instrument, warn};
 
 use crate::database::Database;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::batch_labels::{BatchLabelQuery, LabelFormat};
 use crate::models::bulk_runs::*;
+use crate::models::idempotency::{
+    idempotency_key_from_headers, validate_idempotency_key, IdempotencyKey, IdempotentOperation,
+};
 use crate::models::inventory::*;
+use crate::models::pallet_labels::{PalletLookup, PalletSscc, SsccConfig};
+use crate::models::pick_plan::{PickPlan, PickPlanPreviewQuery};
+use crate::models::pick_scans::{PickScanException, ScanVerifyPolicy};
+use crate::models::pick_sequence::PickSequence;
+use crate::models::print_jobs::{PrintError, PrintLabelsRequest, PrintStatusQuery};
+use crate::models::run_claims::{ClaimRequest, ReleaseClaimQuery, RunClaim};
+use crate::services::batch_labels::{render_epl, render_pdf, render_zpl, LabelLayout};
 use crate::services::bulk_runs_service::BulkRunsService;
+use crate::services::print_queue::{dispatch_due_jobs, PrintQueueService};
+use crate::services::run_events::{RunEventBus, RunStreamItem};
+use crate::types::{ApiResponse, FieldError};
 
-// Old JWT extraction functions moved to utils::user_management module
+impl IntoResponse for BulkRunError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        if status.is_server_error() {
+            error!("❌ BULK_RUNS: {} [{}]: {:#}", status, self.error_code(), self);
+        }
 
-#[derive(Serialize)]
-pub struct ApiResponse<T> {
-    success: bool,
-    data: Option<T>,
-    message: String,
+        ApiResponse::<()>::error_with_code(self.to_string(), self.error_code())
+            .with_status(status)
+            .into_response()
+    }
 }
 
 /// List all active bulk runs for modal selection
 #[instrument(skip(database))]
 pub async fn list_bulk_runs(
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<BulkRunListResponse>>, StatusCode> {
+) -> ApiResponse<BulkRunListResponse> {
     info!("Bulk run list endpoint called for modal selection");
 
     match database.list_active_bulk_runs().await {
@@ -36,26 +59,14 @@ pub async fn list_bulk_runs(
             let response = BulkRunListResponse { runs, total_count };
 
             if total_count == 0 {
-                Ok(Json(ApiResponse {
-                    success: false,
-                    data: Some(response),
-                    message: "No active bulk runs found".to_string(),
-                }))
+                ApiResponse::success(response, "No active bulk runs found")
             } else {
-                Ok(Json(ApiResponse {
-                    success: true,
-                    data: Some(response),
-                    message: format!("Found {total_count} active bulk runs"),
-                }))
+                ApiResponse::success(response, format!("Found {total_count} active bulk runs"))
             }
         }
         Err(e) => {
             warn!("Bulk run list failed: {}", e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to retrieve bulk runs list".to_string(),
-            }))
+            ApiResponse::error("Failed to retrieve bulk runs list")
         }
     }
 }
@@ -65,7 +76,7 @@ pub async fn list_bulk_runs(
 pub async fn search_bulk_runs(
     State(database): State<Database>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ApiResponse<Vec<BulkRunSearchResponse>>>, StatusCode> {
+) -> ApiResponse<Vec<BulkRunSearchResponse>> {
     info!("Bulk run search endpoint called");
 
     let empty_string = String::new();
@@ -75,11 +86,10 @@ pub async fn search_bulk_runs(
 
     if query.is_empty() {
         warn!("Empty search query provided");
-        return Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: "Search query is required".to_string(),
-        }));
+        return ApiResponse::validation_failed(
+            "Search query is required",
+            vec![FieldError::new("query", "Search query is required")],
+        );
     }
 
     let service = BulkRunsService::new(database);
@@ -88,26 +98,14 @@ pub async fn search_bulk_runs(
         Ok(results) => {
             let results_len = results.len();
             if results.is_empty() {
-                Ok(Json(ApiResponse {
-                    success: false,
-                    data: Some(results),
-                    message: format!("No bulk runs found for query: {query}"),
-                }))
+                ApiResponse::success(results, format!("No bulk runs found for query: {query}"))
             } else {
-                Ok(Json(ApiResponse {
-                    success: true,
-                    data: Some(results),
-                    message: format!("Found {results_len} bulk runs"),
-                }))
+                ApiResponse::success(results, format!("Found {results_len} bulk runs"))
             }
         }
         Err(e) => {
             warn!("Bulk run search failed: {}", e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to search bulk runs".to_string(),
-            }))
+            ApiResponse::error("Failed to search bulk runs")
         }
     }
 }
@@ -117,7 +115,7 @@ pub async fn search_bulk_runs(
 pub async fn get_available_runs(
     State(database): State<Database>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ApiResponse<Vec<BulkRun>>>, StatusCode> {
+) -> ApiResponse<Vec<BulkRun>> {
     info!("Get available runs endpoint called");
 
     let limit = params
@@ -136,26 +134,14 @@ pub async fn get_available_runs(
             };
             
             if limited_runs.is_empty() {
-                Ok(Json(ApiResponse {
-                    success: false,
-                    data: Some(limited_runs),
-                    message: "No available bulk runs found".to_string(),
-                }))
+                ApiResponse::success(limited_runs, "No available bulk runs found")
             } else {
-                Ok(Json(ApiResponse {
-                    success: true,
-                    data: Some(limited_runs.clone()),
-                    message: format!("Found {} available runs", limited_runs.len()),
-                }))
+                ApiResponse::success(limited_runs.clone(), format!("Found {} available runs", limited_runs.len()))
             }
         }
         Err(e) => {
             warn!("Get available runs failed: {}", e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to get available runs".to_string(),
-            }))
+            ApiResponse::error("Failed to get available runs")
         }
     }
 }
@@ -165,7 +151,7 @@ pub async fn get_available_runs(
 pub async fn list_active_bulk_runs_paginated(
     State(database): State<Database>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ApiResponse<PaginatedBulkRunResponse>>, StatusCode> {
+) -> ApiResponse<PaginatedBulkRunResponse> {
     info!("Paginated bulk runs endpoint called");
 
     let page = params
@@ -182,27 +168,15 @@ pub async fn list_active_bulk_runs_paginated(
     match service.list_active_bulk_runs_paginated(page, page_size).await {
         Ok(response) => {
             if response.runs.is_empty() {
-                Ok(Json(ApiResponse {
-                    success: false,
-                    data: Some(response),
-                    message: "No bulk runs found".to_string(),
-                }))
+                ApiResponse::success(response, "No bulk runs found")
             } else {
                 let total_items = response.pagination.total_items;
-                Ok(Json(ApiResponse {
-                    success: true,
-                    data: Some(response),
-                    message: format!("Found {total_items} bulk runs"),
-                }))
+                ApiResponse::success(response, format!("Found {total_items} bulk runs"))
             }
         }
         Err(e) => {
             warn!("Paginated bulk runs failed: {}", e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to retrieve paginated runs".to_string(),
-            }))
+            ApiResponse::error("Failed to retrieve paginated runs")
         }
     }
 }
@@ -213,39 +187,32 @@ pub async fn get_bulk_run_form_data(
     Path(run_no): Path<i32>,
     Query(params): Query<std::collections::HashMap<String, String>>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<BulkRunFormData>>, StatusCode> {
+) -> ApiResponse<BulkRunFormData> {
     let ingredient_index = params.get("ingredient_index")
         .and_then(|s| s.parse::<i32>().ok());
+    let sequence = PickSequence::parse(params.get("sequence").map(String::as_str));
     
     info!("Form data endpoint called for run: {} with ingredient_index: {:?}", run_no, ingredient_index);
 
     let service = BulkRunsService::new(database);
 
-    match service.get_bulk_run_form_data(run_no, ingredient_index).await {
+    match service.get_bulk_run_form_data(run_no, ingredient_index, sequence).await {
         Ok(Some(form_data)) => {
             info!("Successfully retrieved form data for run {}", run_no);
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(form_data),
-                message: format!("Form data retrieved for run {run_no}"),
-            }))
+            ApiResponse::success(form_data, format!("Form data retrieved for run {run_no}"))
         }
         Ok(None) => {
             // Run exists but has no bulk picking requirements (ToPickedBulkQty = 0 for all ingredients)
             warn!("Run {} has no bulk picking requirements", run_no);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Run {run_no} has no bulk picking requirements. All ingredients have ToPickedBulkQty = 0."),
-            }))
+            ApiResponse::error_with_code(
+                format!("Run {run_no} has no bulk picking requirements. All ingredients have ToPickedBulkQty = 0."),
+                "RUN_NOT_FOUND",
+            )
+            .with_status(StatusCode::NOT_FOUND)
         }
         Err(e) => {
             warn!("Failed to get form data for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Failed to retrieve form data: {e}"),
-            }))
+            ApiResponse::error(format!("Failed to retrieve form data: {e}"))
         }
     }
 }
@@ -254,36 +221,26 @@ pub async fn get_bulk_run_form_data(
 #[instrument(skip(database))]
 pub async fn get_next_ingredient(
     Path(run_no): Path<i32>,
+    Query(params): Query<HashMap<String, String>>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<IngredientView>>, StatusCode> {
+) -> ApiResponse<IngredientView> {
     info!("Next ingredient endpoint called for run: {}", run_no);
+    let sequence = PickSequence::parse(params.get("sequence").map(String::as_str));
 
     let service = BulkRunsService::new(database);
 
-    match service.get_next_ingredient(run_no, 0).await {
+    match service.get_next_ingredient(run_no, 0, sequence).await {
         Ok(Some(ingredient)) => {
             info!("Found next ingredient for run {}", run_no);
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(ingredient.current_ingredient),
-                message: "Next ingredient found".to_string(),
-            }))
+            ApiResponse::success(ingredient.current_ingredient, "Next ingredient found")
         }
         Ok(None) => {
             info!("No more ingredients to pick for run {}", run_no);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "All ingredients completed for this run".to_string(),
-            }))
+            ApiResponse::empty("All ingredients completed for this run")
         }
         Err(e) => {
             warn!("Failed to get next ingredient for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to get next ingredient".to_string(),
-            }))
+            ApiResponse::error("Failed to get next ingredient")
         }
     }
 }
@@ -293,7 +250,7 @@ pub async fn get_next_ingredient(
 pub async fn check_run_completion(
     Path(run_no): Path<i32>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<bool>>, StatusCode> {
+) -> ApiResponse<bool> {
     info!("Run completion check for: {}", run_no);
 
     let service = BulkRunsService::new(database);
@@ -304,19 +261,11 @@ pub async fn check_run_completion(
             } else {
                 format!("Run {run_no} is not yet complete")
             };
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(is_completed),
-                message,
-            }))
+            ApiResponse::success(is_completed, message)
         }
         Err(e) => {
             warn!("Failed to check completion for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to check run completion".to_string(),
-            }))
+            ApiResponse::error("Failed to check run completion")
         }
     }
 }
@@ -327,7 +276,7 @@ pub async fn check_run_completion(
 pub async fn check_run_completion_status(
     Path(run_no): Path<i32>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<RunCompletionStatus>>, StatusCode> {
+) -> ApiResponse<RunCompletionStatus> {
     info!("🔍 UNIVERSAL_COMPLETION: Checking detailed completion status for run: {}", run_no);
 
     let service = BulkRunsService::new(database);
@@ -343,41 +292,31 @@ pub async fn check_run_completion_status(
                 info!("🎉 UNIVERSAL_COMPLETION: Run {} is COMPLETE! All ingredients finished.", run_no);
             }
 
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(completion_status),
-                message: format!("Completion status retrieved for run {run_no}"),
-            }))
+            ApiResponse::success(completion_status, format!("Completion status retrieved for run {run_no}"))
         }
         Err(e) => {
             warn!("❌ UNIVERSAL_COMPLETION: Failed to check completion status for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Failed to check run completion status: {e}"),
-            }))
+            ApiResponse::error(format!("Failed to check run completion status: {e}"))
         }
     }
 }
 
 /// **NEW AUTOMATIC STATUS UPDATE ENDPOINT** - Update run status from NEW to PRINT
 /// Triggered when all ingredients are complete for automatic workflow
-#[instrument(skip(database))]
+#[instrument(skip(database, events))]
 pub async fn complete_run_status(
     Path(run_no): Path<i32>,
     State(database): State<Database>,
-    headers: HeaderMap,
-) -> Result<Json<ApiResponse<StatusUpdateResult>>, StatusCode> {
+    State(events): State<RunEventBus>,
+    user: AuthenticatedUser,
+) -> Result<ApiResponse<StatusUpdateResult>, BulkRunError> {
     info!("🔄 AUTO_COMPLETE: Attempting to complete run {} status (NEW → PRINT)", run_no);
 
-    // Extract user information from headers
-    let user_id = headers.get("x-user-id")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("SYSTEM");
+    let user_id = user.username.as_str();
 
     info!("👤 AUTO_COMPLETE: User triggering auto-completion for run {}: {}", run_no, user_id);
 
-    let service = BulkRunsService::new(database);
+    let service = BulkRunsService::new(database).with_events(events);
 
     // Update run status from NEW to PRINT
     match service.complete_run_status(run_no, user_id).await {
@@ -385,19 +324,11 @@ pub async fn complete_run_status(
             info!("✅ AUTO_COMPLETE: Successfully updated run {} status: {} → {}",
                   run_no, status_result.old_status, status_result.new_status);
 
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(status_result),
-                message: format!("Run {run_no} status successfully updated to PRINT"),
-            }))
+            Ok(ApiResponse::success(status_result, format!("Run {run_no} status successfully updated to PRINT")))
         }
         Err(e) => {
             warn!("❌ AUTO_COMPLETE: Failed to update run {} status: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Failed to update run status: {e}"),
-            }))
+            Err(e)
         }
     }
 }
@@ -407,71 +338,50 @@ pub async fn complete_run_status(
 pub async fn get_run_status(
     Path(run_no): Path<i32>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<BulkRunStatusResponse>>, StatusCode> {
+) -> ApiResponse<BulkRunStatusResponse> {
     info!("Run status endpoint called for: {}", run_no);
 
     match database.get_bulk_run_status(run_no).await {
         Ok(Some(status_response)) => {
             info!("Run status found: {} - {}", run_no, status_response.status);
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(status_response),
-                message: format!("Run {run_no} status retrieved"),
-            }))
+            ApiResponse::success(status_response, format!("Run {run_no} status retrieved"))
         }
         Ok(None) => {
             warn!("Run status not found: {}", run_no);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Run {run_no} not found"),
-            }))
+            ApiResponse::error_with_code(format!("Run {run_no} not found"), "RUN_NOT_FOUND")
+                .with_status(StatusCode::NOT_FOUND)
         }
         Err(e) => {
             error!("Failed to get run status {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to retrieve run status".to_string(),
-            }))
+            ApiResponse::error("Failed to retrieve run status")
         }
     }
 }
 
-/// Search for items in a bulk run
+/// Search for items in a bulk run; `sequence=walk_path` lists them in warehouse walk order
 #[instrument(skip(database))]
 pub async fn search_run_items(
     Path(run_no): Path<i32>,
+    Query(params): Query<HashMap<String, String>>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<Vec<RunItemSearchResult>>>, StatusCode> {
+) -> ApiResponse<Vec<RunItemSearchResult>> {
     info!("Item search endpoint called for run: {}", run_no);
+    let sequence = PickSequence::parse(params.get("sequence").map(String::as_str));
 
     let service = BulkRunsService::new(database);
 
-    match service.search_run_items(run_no).await {
+    match service.search_run_items(run_no, sequence).await {
         Ok(items) => {
             let item_count = items.len();
             if items.is_empty() {
-                Ok(Json(ApiResponse {
-                    success: false,
-                    data: Some(items),
-                    message: format!("No items found for run {run_no}"),
-                }))
+                ApiResponse::success(items, format!("No items found for run {run_no}"))
             } else {
-                Ok(Json(ApiResponse {
-                    success: true,
-                    data: Some(items),
-                    message: format!("Found {item_count} items for run {run_no}"),
-                }))
+                ApiResponse::success(items, format!("Found {item_count} items for run {run_no}"))
             }
         }
         Err(e) => {
             warn!("Item search failed for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to search run items".to_string(),
-            }))
+            ApiResponse::error("Failed to search run items")
         }
     }
 }
@@ -482,36 +392,28 @@ pub async fn get_ingredient_index(
     Path(run_no): Path<i32>,
     Query(params): Query<HashMap<String, String>>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<usize>>, StatusCode> {
+) -> ApiResponse<usize> {
     let item_key = params.get("item_key").cloned().unwrap_or_default();
+    let sequence = PickSequence::parse(params.get("sequence").map(String::as_str));
     info!("Ingredient index endpoint called for run: {} with item_key: {}", run_no, item_key);
 
     if item_key.is_empty() {
-        return Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: "item_key parameter is required".to_string(),
-        }));
+        return ApiResponse::validation_failed(
+            "item_key parameter is required",
+            vec![FieldError::new("item_key", "item_key parameter is required")],
+        );
     }
 
     let service = BulkRunsService::new(database);
 
-    match service.get_ingredient_index(run_no, &item_key).await {
+    match service.get_ingredient_index(run_no, &item_key, sequence).await {
         Ok(index) => {
             info!("Found ingredient {} at index {} for run {}", item_key, index, run_no);
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(index),
-                message: format!("Ingredient {item_key} found at index {index}"),
-            }))
+            ApiResponse::success(index, format!("Ingredient {item_key} found at index {index}"))
         }
         Err(err) => {
             error!("Failed to get ingredient index for run {}: {:?}", run_no, err);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Ingredient {item_key} not found in run {run_no}: {err}"),
-            }))
+            ApiResponse::not_found(format!("Ingredient {item_key} not found in run {run_no}: {err}"))
         }
     }
 }
@@ -523,76 +425,61 @@ pub async fn get_ingredient_by_coordinates(
     Path(run_no): Path<i32>,
     Query(params): Query<HashMap<String, String>>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<BulkRunFormData>>, StatusCode> {
+) -> ApiResponse<BulkRunFormData> {
     let item_key = params.get("item_key").cloned().unwrap_or_default();
     let row_num_str = params.get("row_num").cloned().unwrap_or_default();
     let line_id_str = params.get("line_id").cloned().unwrap_or_default();
+    let sequence = PickSequence::parse(params.get("sequence").map(String::as_str));
     
     info!(
         "Get ingredient by coordinates endpoint called for run: {}, item_key: {}, row_num: {}, line_id: {}", 
         run_no, item_key, row_num_str, line_id_str
     );
     
-    // Validate required parameters
-    if item_key.is_empty() || row_num_str.is_empty() || line_id_str.is_empty() {
-        return Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: "Parameters item_key, row_num, and line_id are all required".to_string(),
-        }));
+    // Validate required parameters and parse coordinates
+    let mut field_errors = Vec::new();
+    if item_key.is_empty() {
+        field_errors.push(FieldError::new("item_key", "item_key is required"));
     }
-    
-    // Parse coordinates
-    let row_num: i32 = match row_num_str.parse() {
-        Ok(num) => num,
-        Err(_) => {
-            return Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "row_num must be a valid integer".to_string(),
-            }));
-        }
-    };
-    
-    let line_id: i32 = match line_id_str.parse() {
-        Ok(num) => num,
-        Err(_) => {
-            return Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "line_id must be a valid integer".to_string(),
-            }));
+    let row_num = row_num_str.parse::<i32>();
+    if row_num.is_err() {
+        field_errors.push(FieldError::new("row_num", "row_num must be a valid integer"));
+    }
+    let line_id = line_id_str.parse::<i32>();
+    if line_id.is_err() {
+        field_errors.push(FieldError::new("line_id", "line_id must be a valid integer"));
+    }
+    let (row_num, line_id) = match (row_num, line_id) {
+        (Ok(row_num), Ok(line_id)) if field_errors.is_empty() => (row_num, line_id),
+        _ => {
+            return ApiResponse::validation_failed(
+                "Parameters item_key, row_num, and line_id are all required",
+                field_errors,
+            )
         }
     };
 
     let service = BulkRunsService::new(database);
     
-    match service.get_ingredient_by_coordinates(run_no, &item_key, row_num, line_id).await {
+    match service.get_ingredient_by_coordinates(run_no, &item_key, row_num, line_id, sequence).await {
         Ok(form_data) => {
             info!(
                 "Successfully loaded ingredient {} with coordinates (RowNum: {}, LineId: {}) for run {}", 
                 item_key, row_num, line_id, run_no
             );
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(form_data),
-                message: format!(
-                    "Ingredient {item_key} loaded with coordinates RowNum: {row_num}, LineId: {line_id}"
-                ),
-            }))
+            ApiResponse::success(
+                form_data,
+                format!("Ingredient {item_key} loaded with coordinates RowNum: {row_num}, LineId: {line_id}"),
+            )
         }
         Err(err) => {
             error!(
                 "Failed to get ingredient {} by coordinates (RowNum: {}, LineId: {}) for run {}: {:?}", 
                 item_key, row_num, line_id, run_no, err
             );
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!(
-                    "Failed to load ingredient {item_key} with coordinates (RowNum: {row_num}, LineId: {line_id}): {err}"
-                ),
-            }))
+            ApiResponse::error(format!(
+                "Failed to load ingredient {item_key} with coordinates (RowNum: {row_num}, LineId: {line_id}): {err}"
+            ))
         }
     }
 }
@@ -603,7 +490,7 @@ pub async fn search_run_lots(
     Path(run_no): Path<i32>,
     Query(params): Query<HashMap<String, String>>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<PaginatedLotSearchResponse>>, StatusCode> {
+) -> ApiResponse<PaginatedLotSearchResponse> {
     info!("Lot search endpoint called for run: {}", run_no);
 
     let item_key = params.get("item_key").cloned().unwrap_or_default();
@@ -617,20 +504,22 @@ pub async fn search_run_lots(
         .unwrap_or(10);
 
     if item_key.is_empty() {
-        return Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: "Item key is required for lot search".to_string(),
-        }));
+        return ApiResponse::validation_failed(
+            "Item key is required for lot search",
+            vec![FieldError::new("item_key", "item_key is required")],
+        );
     }
 
     // Validate page parameters
     if page < 1 || !(1..=100).contains(&page_size) {
-        return Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: "Invalid pagination parameters".to_string(),
-        }));
+        let mut field_errors = Vec::new();
+        if page < 1 {
+            field_errors.push(FieldError::new("page", "page must be at least 1"));
+        }
+        if !(1..=100).contains(&page_size) {
+            field_errors.push(FieldError::new("page_size", "page_size must be between 1 and 100"));
+        }
+        return ApiResponse::validation_failed("Invalid pagination parameters", field_errors);
     }
 
     // Use service layer for proper business logic and error handling
@@ -641,26 +530,14 @@ pub async fn search_run_lots(
             let total_count = paginated_result.pagination.total_items;
             
             if lot_count == 0 {
-                Ok(Json(ApiResponse {
-                    success: false,
-                    data: Some(paginated_result),
-                    message: format!("No lots found for item {item_key} in run {run_no}"),
-                }))
+                ApiResponse::success(paginated_result, format!("No lots found for item {item_key} in run {run_no}"))
             } else {
-                Ok(Json(ApiResponse {
-                    success: true,
-                    data: Some(paginated_result),
-                    message: format!("Found {lot_count} of {total_count} total lots for item {item_key}"),
-                }))
+                ApiResponse::success(paginated_result, format!("Found {lot_count} of {total_count} total lots for item {item_key}"))
             }
         }
         Err(e) => {
             warn!("Lot search failed for run {}, item {}: {}", run_no, item_key, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to search lots".to_string(),
-            }))
+            ApiResponse::error("Failed to search lots")
         }
     }
 }
@@ -671,17 +548,16 @@ pub async fn get_lot_bins(
     Path((run_no, lot_no)): Path<(i32, String)>,
     Query(params): Query<HashMap<String, String>>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<Vec<LotSearchResult>>>, StatusCode> {
+) -> ApiResponse<Vec<LotSearchResult>> {
     info!("Lot bins endpoint called for run: {}, lot: {}", run_no, lot_no);
 
     let item_key = params.get("item_key").cloned().unwrap_or_default();
 
     if item_key.is_empty() {
-        return Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: "Item key is required for bin search".to_string(),
-        }));
+        return ApiResponse::validation_failed(
+            "Item key is required for bin search",
+            vec![FieldError::new("item_key", "item_key is required")],
+        );
     }
 
     // Use service layer for proper business logic and error handling
@@ -690,26 +566,14 @@ pub async fn get_lot_bins(
         Ok(bins) => {
             let bin_count = bins.len();
             if bins.is_empty() {
-                Ok(Json(ApiResponse {
-                    success: false,
-                    data: Some(bins),
-                    message: format!("No bins found for lot {lot_no} in run {run_no}"),
-                }))
+                ApiResponse::success(bins, format!("No bins found for lot {lot_no} in run {run_no}"))
             } else {
-                Ok(Json(ApiResponse {
-                    success: true,
-                    data: Some(bins),
-                    message: format!("Found {bin_count} bins for lot {lot_no}"),
-                }))
+                ApiResponse::success(bins, format!("Found {bin_count} bins for lot {lot_no}"))
             }
         }
         Err(e) => {
             warn!("Bin search failed for lot {}: {}", lot_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to get bins for lot".to_string(),
-            }))
+            ApiResponse::error("Failed to get bins for lot")
         }
     }
 }
@@ -720,7 +584,7 @@ pub async fn get_pallet_tracking_data(
     Path(run_no): Path<i32>,
     Query(params): Query<HashMap<String, String>>,
     State(database): State<Database>,
-) -> Result<Json<ApiResponse<PalletBatchResponse>>, StatusCode> {
+) -> ApiResponse<PalletBatchResponse> {
     let item_key = params.get("item_key").cloned();
     info!("Pallet tracking endpoint called for run: {}, item_key: {:?}", run_no, item_key);
 
@@ -728,19 +592,11 @@ pub async fn get_pallet_tracking_data(
     let service = BulkRunsService::new(database);
     match service.get_pallet_tracking_data(run_no, item_key.as_deref()).await {
         Ok(response) => {
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(response),
-                message: format!("Pallet tracking data retrieved for run {run_no}"),
-            }))
+            ApiResponse::success(response, format!("Pallet tracking data retrieved for run {run_no}"))
         }
         Err(e) => {
             warn!("Failed to get pallet tracking for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to retrieve pallet tracking data".to_string(),
-            }))
+            ApiResponse::error("Failed to retrieve pallet tracking data")
         }
     }
 }
@@ -751,7 +607,7 @@ pub async fn get_inventory_alerts(
     Path(item_key): Path<String>,
     State(database): State<Database>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<ApiResponse<Vec<InventoryAlert>>>, StatusCode> {
+) -> ApiResponse<Vec<InventoryAlert>> {
     info!("Inventory alerts endpoint called for item: {}", item_key);
 
     // Get run_no from query params or use a default
@@ -764,106 +620,266 @@ pub async fn get_inventory_alerts(
     match database.get_inventory_alerts(run_no, &item_key).await {
         Ok(alerts) => {
             info!("Found {} alerts for item {}", alerts.len(), item_key);
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(alerts),
-                message: format!("Inventory alerts retrieved for item {item_key}"),
-            }))
+            ApiResponse::success(alerts, format!("Inventory alerts retrieved for item {item_key}"))
         }
         Err(e) => {
             error!("Failed to get inventory alerts: {:?}", e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Failed to get inventory alerts: {e}"),
-            }))
+            ApiResponse::error(format!("Failed to get inventory alerts: {e}"))
         }
     }
 }
 
 /// Confirm pick transaction - BME4-compatible 5-table atomic transaction
-#[instrument(skip(database))]
+#[instrument(skip(database, events, scan_policy))]
 pub async fn confirm_pick(
     Path(run_no): Path<i32>,
     State(database): State<Database>,
+    State(events): State<RunEventBus>,
+    State(scan_policy): State<ScanVerifyPolicy>,
+    user: AuthenticatedUser,
     headers: HeaderMap,
     Json(request): Json<PickConfirmationRequest>,
-) -> Result<Json<ApiResponse<PickConfirmationResponse>>, StatusCode> {
-    info!("Pick confirmation endpoint called for run: {} with lot: {}", run_no, request.lot_no);
-
-    let service = BulkRunsService::new(database);
-
-    // **ENHANCED USER EXTRACTION**: Use new comprehensive user management system
-    use crate::utils::user_management::{extract_user_with_debug_info, validate_user_context};
-    
-    let mut req = request.clone();
-    
-    // Extract user with comprehensive debug information
-    let (extracted_user, debug_info) = extract_user_with_debug_info(&headers, req.user_id.as_ref());
-    
-    // Set the extracted user ID on the request
-    req.user_id = extracted_user.clone();
-    
-    // Pre-validate user context before passing to service layer  
-    let final_user = match validate_user_context(req.user_id.as_ref()) {
-        Ok(user) => user,
-        Err(e) => {
-            warn!("User context validation failed: {}", e);
-            return Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to validate user context for transaction".to_string(),
-            }));
-        }
-    };
-    
+) -> Result<ApiResponse<PickConfirmationResponse>, BulkRunError> {
     info!(
-        "🔐 ENHANCED_USER_EXTRACTION: Pick confirmation for run {} - Debug: [{}], Final user: '{}'", 
-        run_no, debug_info, final_user
+        "Pick confirmation endpoint called for run: {} with lot: {} ({} allocations)",
+        run_no, request.lot_no, request.allocations.len()
     );
 
+    let service = BulkRunsService::new(database).with_events(events).with_scan_policy(scan_policy);
+
+    // Audit identity always comes from the verified token, never from the request body
+    let mut req = request;
+    req.user_id = Some(user.username.clone());
+    req.idempotency_key = idempotency_key_from_headers(&headers)
+        .map_err(BulkRunError::Validation)?
+        .map(|key| IdempotencyKey::new(key, IdempotentOperation::ConfirmPick, &user.username, &req));
+    if req.override_claim && !user.is_supervisor() {
+        return Err(BulkRunError::ClaimOverrideForbidden);
+    }
+    info!("👤 Pick confirmation for run {} by user: {}", run_no, user.username);
+
     match service.confirm_pick_transaction(run_no, req).await {
         Ok(response) => {
             info!("Pick confirmation completed successfully for run {}", run_no);
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(response),
-                message: format!("Pick confirmed successfully for run {run_no}"),
-            }))
+            let warnings = response.warnings.join("; ");
+            let api_response = ApiResponse::success(response, format!("Pick confirmed successfully for run {run_no}"));
+            if warnings.is_empty() {
+                Ok(api_response)
+            } else {
+                Ok(api_response.with_warning(warnings))
+            }
         }
         Err(e) => {
-            let err_str = e.to_string();
-            if err_str.contains("BATCH_ALREADY_COMPLETED") {
-                let user_msg = "This batch is already completed. Please refresh to load the next batch.".to_string();
-                warn!(
-                    "Pick confirmation failed (BATCH_ALREADY_COMPLETED) for run {}: {}",
-                    run_no, err_str
-                );
-                return Ok(Json(ApiResponse {
-                    success: false,
-                    data: None,
-                    message: user_msg,
-                }));
-            }
+            warn!("Pick confirmation failed for run {} [{}]: {}", run_no, e.error_code(), e);
+            Err(e)
+        }
+    }
+}
+
+/// Sync pick confirmations queued on a handheld while it was out of Wi-Fi range.
+/// Picks are applied in order; each gets its own outcome (applied, conflict, ...).
+#[instrument(skip(database, events, scan_policy, request))]
+pub async fn sync_offline_picks(
+    State(database): State<Database>,
+    State(events): State<RunEventBus>,
+    State(scan_policy): State<ScanVerifyPolicy>,
+    user: AuthenticatedUser,
+    Json(request): Json<OfflinePickSyncRequest>,
+) -> ApiResponse<OfflinePickSyncResponse> {
+    info!("📶 Offline pick sync from {} with {} queued picks", user.username, request.picks.len());
+
+    if request.picks.is_empty() {
+        return ApiResponse::bad_request("No queued picks to sync");
+    }
 
-            warn!("Pick confirmation failed for run {}: {}", run_no, err_str);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Failed to confirm pick operation: {err_str}"),
-            }))
+    let mut field_errors = Vec::new();
+    if request.picks.len() > MAX_OFFLINE_SYNC_PICKS {
+        field_errors.push(FieldError::new(
+            "picks",
+            format!("At most {MAX_OFFLINE_SYNC_PICKS} picks can be synced at once"),
+        ));
+    }
+    for (index, pick) in request.picks.iter().enumerate() {
+        if let Err(message) = validate_idempotency_key(&pick.client_id) {
+            field_errors.push(FieldError::new(format!("picks[{index}].client_id"), format!("client_id {message}")));
         }
     }
+    if !field_errors.is_empty() {
+        return ApiResponse::validation_failed("Invalid offline sync request", field_errors);
+    }
+
+    let service = BulkRunsService::new(database).with_events(events).with_scan_policy(scan_policy);
+    let response = service.sync_offline_picks(&user.username, request.picks).await;
+    let message = format!(
+        "Synced {} of {} queued picks ({} conflicts)",
+        response.applied + response.already_applied,
+        response.results.len(),
+        response.conflicts
+    );
+    ApiResponse::success(response, message)
+}
+
+/// Claim a run (or one ingredient line with `line_id`) so other pickers are turned away.
+/// Claiming again renews the lease; supervisors may `take_over` someone else's claim.
+/// POST /api/bulk-runs/{run_no}/claim
+#[instrument(skip(database))]
+pub async fn claim_run(
+    Path(run_no): Path<i32>,
+    State(database): State<Database>,
+    user: AuthenticatedUser,
+    Json(request): Json<ClaimRequest>,
+) -> Result<ApiResponse<RunClaim>, BulkRunError> {
+    let service = BulkRunsService::new(database);
+    let claim = service
+        .claim_run(run_no, &user.username, &request, user.is_supervisor())
+        .await?;
+
+    let target = match claim.line_id {
+        Some(line_id) => format!("Run {run_no} ingredient line {line_id}"),
+        None => format!("Run {run_no}"),
+    };
+    let message = format!("{target} claimed until {}", claim.expires_at.format("%H:%M:%S UTC"));
+    Ok(ApiResponse::success(claim, message))
+}
+
+/// Release a claim held by the caller (supervisors: by anyone)
+/// DELETE /api/bulk-runs/{run_no}/claim?line_id={line_id}
+#[instrument(skip(database))]
+pub async fn release_run_claim(
+    Path(run_no): Path<i32>,
+    State(database): State<Database>,
+    user: AuthenticatedUser,
+    Query(query): Query<ReleaseClaimQuery>,
+) -> Result<ApiResponse<()>, BulkRunError> {
+    let service = BulkRunsService::new(database);
+    if service
+        .release_claim(run_no, query.line_id, &user.username, user.is_supervisor())
+        .await?
+    {
+        Ok(ApiResponse::empty(format!("Claim on run {run_no} released")))
+    } else {
+        Ok(ApiResponse::not_found(format!("No active claim on run {run_no} to release")))
+    }
+}
+
+/// Active claims on a run
+/// GET /api/bulk-runs/{run_no}/claims
+#[instrument(skip(database))]
+pub async fn get_run_claims(
+    Path(run_no): Path<i32>,
+    State(database): State<Database>,
+) -> Result<ApiResponse<Vec<RunClaim>>, BulkRunError> {
+    let service = BulkRunsService::new(database);
+    let claims = service.get_run_claims(run_no).await?;
+    let count = claims.len();
+    Ok(ApiResponse::success(claims, format!("Found {count} active claims on run {run_no}")))
+}
+
+/// Build a FEFO pick plan for every batch of the run still to pick, replacing any earlier plan.
+/// Shortages are returned in the plan and summarised in the response warning.
+/// POST /api/bulk-runs/{run_no}/pick-plan
+#[instrument(skip(database))]
+pub async fn generate_pick_plan(
+    Path(run_no): Path<i32>,
+    State(database): State<Database>,
+    user: AuthenticatedUser,
+) -> Result<ApiResponse<PickPlan>, BulkRunError> {
+    let service = BulkRunsService::new(database);
+    let plan = service.generate_pick_plan(run_no, &user.username).await?;
+
+    let message = format!("Pick plan for run {run_no} has {} steps", plan.steps.len());
+    let shortages = plan.shortages.len();
+    let response = ApiResponse::success(plan, message);
+    if shortages == 0 {
+        Ok(response)
+    } else {
+        Ok(response.with_warning(format!("{shortages} batches cannot be fully picked from available stock")))
+    }
+}
+
+/// The run's stored pick plan, with each step's progress
+/// GET /api/bulk-runs/{run_no}/pick-plan
+#[instrument(skip(database))]
+pub async fn get_pick_plan(
+    Path(run_no): Path<i32>,
+    State(database): State<Database>,
+) -> Result<ApiResponse<PickPlan>, BulkRunError> {
+    let service = BulkRunsService::new(database);
+    match service.get_pick_plan(run_no).await? {
+        Some(plan) => {
+            let message = format!("{} of {} pick plan steps left for run {run_no}", plan.pending_steps(), plan.steps.len());
+            Ok(ApiResponse::success(plan, message))
+        }
+        None => Ok(ApiResponse::not_found(format!("No pick plan for run {run_no}"))),
+    }
+}
+
+/// Dry run of the pick plan: each ingredient's requirement against available, unexpired stock
+/// net of QtyCommitSales, and the batches that cannot be fulfilled. Nothing is stored.
+/// `?format=csv` downloads the shortage report instead of JSON.
+/// GET /api/bulk-runs/{run_no}/pick-plan/preview
+#[instrument(skip(database))]
+pub async fn preview_pick_plan(
+    Path(run_no): Path<i32>,
+    Query(query): Query<PickPlanPreviewQuery>,
+    State(database): State<Database>,
+) -> Result<Response, BulkRunError> {
+    let service = BulkRunsService::new(database);
+    let preview = service.preview_pick_plan(run_no).await?;
+
+    if query.wants_csv() {
+        let disposition = format!("attachment; filename=\"run-{run_no}-shortages.csv\"");
+        return Ok((
+            [
+                (header::CONTENT_TYPE, "text/csv; charset=utf-8".to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            preview.to_csv(),
+        )
+            .into_response());
+    }
+
+    let shortages = preview.shortages.len();
+    let message = format!(
+        "Pick plan preview for run {run_no} covers {} ingredients",
+        preview.ingredients.len()
+    );
+    let response = ApiResponse::success(preview, message);
+    if shortages == 0 {
+        Ok(response.into_response())
+    } else {
+        Ok(response
+            .with_warning(format!("{shortages} batches cannot be fully picked from available stock"))
+            .into_response())
+    }
+}
+
+/// Live run changes for handhelds working the same run, as Server-Sent Events.
+/// Each event is named after its `type` (pick, unpick, pallet_completed, ingredient_completed,
+/// run_status_changed). A `resync` event means events were dropped and the run should be reloaded.
+/// GET /api/bulk-runs/{run_no}/events
+#[instrument(skip(events))]
+pub async fn run_events(
+    Path(run_no): Path<i32>,
+    State(events): State<RunEventBus>,
+    user: AuthenticatedUser,
+) -> Sse<impl Stream<Item = Result<Event, axum::Error>>> {
+    info!("📡 {} subscribed to events for run {}", user.username, run_no);
+
+    let stream = events.subscribe(run_no).map(move |item| match item {
+        RunStreamItem::Event(event) => Event::default().event(event.kind.name()).json_data(&event),
+        RunStreamItem::Lagged(missed) => {
+            warn!("📡 Run {} event stream for {} dropped {} events", run_no, user.username, missed);
+            Ok(Event::default().event("resync").data(missed.to_string()))
+        }
+    });
+    Sse::new(stream).keep_alive(KeepAlive::default())
 }
 
 /// Health check endpoint for bulk runs API
 #[instrument]
-pub async fn bulk_runs_health() -> Json<ApiResponse<String>> {
-    Json(ApiResponse {
-        success: true,
-        data: Some("Bulk Runs API with BME4 Pick Transaction Support".to_string()),
-        message: "Service healthy".to_string(),
-    })
+pub async fn bulk_runs_health() -> ApiResponse<String> {
+    ApiResponse::success("Bulk Runs API with BME4 Pick Transaction Support".to_string(), "Service healthy")
 }
 
 /// Debug endpoint to test validation without actual transaction
@@ -871,21 +887,15 @@ pub async fn bulk_runs_health() -> Json<ApiResponse<String>> {
 pub async fn debug_validation(
     Path(run_no): Path<i32>,
     State(database): State<Database>,
-    headers: HeaderMap,
+    user: AuthenticatedUser,
     Json(request): Json<PickConfirmationRequest>,
-) -> Result<Json<ApiResponse<serde_json::Value>>, StatusCode> {
+) -> ApiResponse<serde_json::Value> {
     info!("Debug validation endpoint called for run: {} with lot: {}", run_no, request.lot_no);
 
     let service = BulkRunsService::new(database);
 
-    let header_user = headers
-        .get("x-user-id")
-        .and_then(|v| v.to_str().ok())
-        .map(|s| s.to_string());
     let mut req = request.clone();
-    if req.user_id.as_ref().map(|s| s.trim().is_empty()).unwrap_or(true) {
-        req.user_id = header_user;
-    }
+    req.user_id = Some(user.username.clone());
 
     match service.validate_pick_request(run_no, req.clone()).await {
         Ok(validation_result) => {
@@ -906,11 +916,7 @@ pub async fn debug_validation(
                 }
             });
             
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(debug_info),
-                message: "Validation debug completed".to_string(),
-            }))
+            ApiResponse::success(debug_info, "Validation debug completed")
         }
         Err(e) => {
             let error_info = serde_json::json!({
@@ -924,11 +930,10 @@ pub async fn debug_validation(
                 }
             });
             
-            Ok(Json(ApiResponse {
-                success: false,
+            ApiResponse {
                 data: Some(error_info),
-                message: format!("Validation failed: {e}"),
-            }))
+                ..ApiResponse::error(format!("Validation failed: {e}"))
+            }
         }
     }
 }
@@ -937,7 +942,7 @@ pub async fn debug_validation(
 pub async fn check_pallet_completion(
     Path((run_no, row_num, line_id)): Path<(i32, i32, i32)>,
     Extension(service): Extension<Arc<BulkRunsService>>,
-) -> Json<ApiResponse<serde_json::Value>> {
+) -> ApiResponse<serde_json::Value> {
     info!("API: Checking pallet completion for run: {}, row_num: {}, line_id: {}", 
           run_no, row_num, line_id);
     
@@ -952,12 +957,13 @@ pub async fn check_pallet_completion(
                 }
             });
             
-            Json(ApiResponse {
-                success: true,
-                data: Some(result),
-                message: format!("Pallet completion check completed - Status: {}", 
-                               if is_completed { "COMPLETED" } else { "IN_PROGRESS" }),
-            })
+            ApiResponse::success(
+                result,
+                format!(
+                    "Pallet completion check completed - Status: {}",
+                    if is_completed { "COMPLETED" } else { "IN_PROGRESS" }
+                ),
+            )
         }
         Err(e) => {
             let error_info = serde_json::json!({
@@ -969,11 +975,10 @@ pub async fn check_pallet_completion(
                 }
             });
             
-            Json(ApiResponse {
-                success: false,
+            ApiResponse {
                 data: Some(error_info),
-                message: format!("Failed to check pallet completion: {e}"),
-            })
+                ..ApiResponse::error(format!("Failed to check pallet completion: {e}"))
+            }
         }
     }
 }
@@ -982,7 +987,7 @@ pub async fn check_pallet_completion(
 pub async fn get_next_pallet(
     Path((run_no, current_row_num, line_id)): Path<(i32, i32, i32)>,
     Extension(service): Extension<Arc<BulkRunsService>>,
-) -> Json<ApiResponse<serde_json::Value>> {
+) -> ApiResponse<serde_json::Value> {
     info!("API: Finding next available pallet for run: {}, current_row_num: {}, line_id: {}", 
           run_no, current_row_num, line_id);
     
@@ -1006,12 +1011,10 @@ pub async fn get_next_pallet(
                         }
                     });
                     
-                    Json(ApiResponse {
-                        success: true,
-                        data: Some(result),
-                        message: format!("Next pallet found: Pallet #{} (RowNum: {})", 
-                                       pallet.pallet_number, pallet.row_num),
-                    })
+                    ApiResponse::success(
+                        result,
+                        format!("Next pallet found: Pallet #{} (RowNum: {})", pallet.pallet_number, pallet.row_num),
+                    )
                 }
                 None => {
                     let result = serde_json::json!({
@@ -1019,11 +1022,7 @@ pub async fn get_next_pallet(
                         "message": "No more unpicked pallets available for this ingredient"
                     });
                     
-                    Json(ApiResponse {
-                        success: true,
-                        data: Some(result),
-                        message: "No more pallets available for this ingredient".to_string(),
-                    })
+                    ApiResponse::success(result, "No more pallets available for this ingredient")
                 }
             }
         }
@@ -1037,11 +1036,10 @@ pub async fn get_next_pallet(
                 }
             });
             
-            Json(ApiResponse {
-                success: false,
+            ApiResponse {
                 data: Some(error_info),
-                message: format!("Failed to find next pallet: {e}"),
-            })
+                ..ApiResponse::error(format!("Failed to find next pallet: {e}"))
+            }
         }
     }
 }
@@ -1051,12 +1049,11 @@ pub async fn get_next_pallet(
 pub async fn get_picked_lots(
     State(database): State<Database>,
     Path((run_no, row_num, line_id)): Path<(i32, i32, i32)>,
-    headers: HeaderMap,
-) -> Result<Json<ApiResponse<PickedLotsResponse>>, StatusCode> {
+    user: AuthenticatedUser,
+) -> ApiResponse<PickedLotsResponse> {
     info!("🔍 Get picked lots endpoint called for run: {}, row: {}, line: {}", run_no, row_num, line_id);
 
-    // Extract user information from headers
-    let user_id = headers.get("x-user-id").and_then(|v| v.to_str().ok()).unwrap_or("SYSTEM");
+    let user_id = user.username.as_str();
     info!("👤 User requesting picked lots: {}", user_id);
 
     match database.get_picked_lots_for_ingredient(run_no, row_num, line_id).await {
@@ -1066,19 +1063,16 @@ pub async fn get_picked_lots(
             
             // Always return success: true for successful database operations
             // Frontend will handle empty arrays appropriately
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(picked_lots_response),
-                message: if lot_count == 0 {
-                    "No picked lots found for this ingredient".to_string()
-                } else {
-                    format!("Found {lot_count} picked lot(s)")
-                },
-            }))
+            let message = if lot_count == 0 {
+                "No picked lots found for this ingredient".to_string()
+            } else {
+                format!("Found {lot_count} picked lot(s)")
+            };
+            ApiResponse::success(picked_lots_response, message)
         }
         Err(e) => {
             error!("❌ Failed to get picked lots: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            ApiResponse::error("Failed to get picked lots")
         }
     }
 }
@@ -1088,12 +1082,11 @@ pub async fn get_picked_lots(
 pub async fn get_all_picked_lots_for_run(
     State(database): State<Database>,
     Path(run_no): Path<i32>,
-    headers: HeaderMap,
-) -> Result<Json<ApiResponse<PickedLotsResponse>>, StatusCode> {
+    user: AuthenticatedUser,
+) -> ApiResponse<PickedLotsResponse> {
     info!("🔍 Get ALL picked lots endpoint called for run: {}", run_no);
     
-    // Extract user information from headers
-    let user_id = headers.get("x-user-id").and_then(|v| v.to_str().ok()).unwrap_or("SYSTEM");
+    let user_id = user.username.as_str();
     info!("👤 User requesting all picked lots for run: {} by user: {}", run_no, user_id);
     
     match database.get_all_picked_lots_for_run(run_no).await {
@@ -1103,127 +1096,83 @@ pub async fn get_all_picked_lots_for_run(
             
             // Always return success: true for successful database operations
             // Frontend will handle empty arrays appropriately
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(picked_lots_response),
-                message: if lot_count == 0 {
-                    "No picked lots found for this run".to_string()
-                } else {
-                    format!("Found {lot_count} picked lot(s) across all ingredients")
-                },
-            }))
+            let message = if lot_count == 0 {
+                "No picked lots found for this run".to_string()
+            } else {
+                format!("Found {lot_count} picked lot(s) across all ingredients")
+            };
+            ApiResponse::success(picked_lots_response, message)
         }
         Err(e) => {
             error!("❌ Failed to get all picked lots for run {}: {}", run_no, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            ApiResponse::error("Failed to get picked lots for run")
         }
     }
 }
 
 /// Unpick a batch or specific lot (replicate official app unpicking pattern)
-#[instrument(skip(database))]
+#[instrument(skip(database, events))]
 pub async fn unpick_ingredient(
     State(database): State<Database>,
+    State(events): State<RunEventBus>,
     Path((run_no, row_num, line_id)): Path<(i32, i32, i32)>,
-    headers: HeaderMap,
+    user: AuthenticatedUser,
     Json(unpick_request): Json<UnpickRequest>,
-) -> Result<Json<ApiResponse<serde_json::Value>>, StatusCode> {
+) -> ApiResponse<serde_json::Value> {
     info!("🔄 Unpick endpoint called for run: {}, row: {}, line: {}", run_no, row_num, line_id);
     
-    // Extract user information from headers  
-    let user_id = headers.get("x-user-id").and_then(|v| v.to_str().ok()).unwrap_or("SYSTEM");
+    let user_id = user.username.as_str();
     info!("👤 User requesting unpick: {}", user_id);
 
-    // NEW: Priority order for unpick operations
-    // 1. If lot_tran_no is provided, use precise unpick (highest priority)
-    // 2. If lot_no is provided, use legacy lot-based unpick
-    // 3. Otherwise, use batch unpick
-    
-    if let Some(lot_tran_no) = unpick_request.lot_tran_no {
-        info!("🎯 Precise unpick using LotTranNo: {}", lot_tran_no);
-        match database.unpick_by_lot_tran_no(lot_tran_no, user_id).await {
-            Ok(result) => Ok(Json(ApiResponse {
-                success: true,
-                data: Some(result),
-                message: format!("Successfully unpicked record with LotTranNo {lot_tran_no}"),
-            })),
-            Err(e) => {
-                error!("❌ Failed to unpick LotTranNo {}: {}", lot_tran_no, e);
-                Ok(Json(ApiResponse {
-                    success: false,
-                    data: Some(serde_json::json!({"error": e.to_string()})),
-                    message: format!("Failed to unpick LotTranNo {lot_tran_no}: {e}"),
-                }))
-            }
-        }
-    } else {
-        match unpick_request.lot_no {
-            Some(lot_no) => {
-                info!("🎯 Unpicking specific lot: {}", lot_no);
-                match database.unpick_specific_lot(run_no, row_num, line_id, &lot_no, user_id).await {
-                    Ok(result) => Ok(Json(ApiResponse {
-                        success: true,
-                        data: Some(result),
-                        message: format!("Successfully unpicked lot {lot_no}"),
-                    })),
-                    Err(e) => {
-                        error!("❌ Failed to unpick lot {}: {}", lot_no, e);
-                        Ok(Json(ApiResponse {
-                            success: false,
-                            data: Some(serde_json::json!({"error": e.to_string()})),
-                            message: format!("Failed to unpick lot {lot_no}: {e}"),
-                        }))
-                    }
-                }
-            }
-            None => {
-                info!("🎯 Unpicking entire batch");
-                match database.unpick_entire_batch(run_no, row_num, line_id, user_id).await {
-                    Ok(result) => Ok(Json(ApiResponse {
-                        success: true,
-                        data: Some(result),
-                        message: "Successfully unpicked entire batch".to_string(),
-                    })),
-                    Err(e) => {
-                        error!("❌ Failed to unpick batch: {}", e);
-                        Ok(Json(ApiResponse {
-                            success: false,
-                            data: Some(serde_json::json!({"error": e.to_string()})),
-                            message: format!("Failed to unpick batch: {e}"),
-                        }))
-                    }
-                }
+    // Describe the target up front; the service picks the operation by the same priority
+    // (LotTranNo > lot number > entire batch)
+    let (target, success_message) = match (unpick_request.lot_tran_no, unpick_request.lot_no.as_deref()) {
+        (Some(lot_tran_no), _) => (
+            format!("LotTranNo {lot_tran_no}"),
+            format!("Successfully unpicked record with LotTranNo {lot_tran_no}"),
+        ),
+        (None, Some(lot_no)) => (format!("lot {lot_no}"), format!("Successfully unpicked lot {lot_no}")),
+        (None, None) => ("batch".to_string(), "Successfully unpicked entire batch".to_string()),
+    };
+
+    let service = BulkRunsService::new(database).with_events(events);
+    match service
+        .unpick_ingredient(run_no, row_num, line_id, &unpick_request, user_id)
+        .await
+    {
+        Ok(result) => ApiResponse::success(result, success_message),
+        Err(e) => {
+            error!("❌ Failed to unpick {}: {}", target, e);
+            ApiResponse {
+                data: Some(serde_json::json!({"error": e.to_string()})),
+                ..ApiResponse::error(format!("Failed to unpick {target}: {e}"))
             }
         }
     }
 }
 
 /// Unpick all lots from all ingredients in a run
-#[instrument(skip(database))]
+#[instrument(skip(database, events))]
 pub async fn unpick_all_run_lots(
     State(database): State<Database>,
+    State(events): State<RunEventBus>,
     Path(run_no): Path<i32>,
-    headers: HeaderMap,
-) -> Result<Json<ApiResponse<serde_json::Value>>, StatusCode> {
+    user: AuthenticatedUser,
+) -> ApiResponse<serde_json::Value> {
     info!("🔄 Unpick ALL run lots endpoint called for run: {}", run_no);
     
-    // Extract user information from headers  
-    let user_id = headers.get("x-user-id").and_then(|v| v.to_str().ok()).unwrap_or("SYSTEM");
+    let user_id = user.username.as_str();
     info!("👤 User requesting run-wide unpick: {}", user_id);
 
-    match database.unpick_all_run_lots(run_no, user_id).await {
-        Ok(result) => Ok(Json(ApiResponse {
-            success: true,
-            data: Some(result),
-            message: "Successfully unpicked all lots from entire run".to_string(),
-        })),
+    let service = BulkRunsService::new(database).with_events(events);
+    match service.unpick_all_run_lots(run_no, user_id).await {
+        Ok(result) => ApiResponse::success(result, "Successfully unpicked all lots from entire run"),
         Err(e) => {
             error!("❌ Failed to unpick all run lots: {}", e);
-            Ok(Json(ApiResponse {
-                success: false,
+            ApiResponse {
                 data: Some(serde_json::json!({"error": e.to_string()})),
-                message: format!("Failed to unpick all run lots: {e}"),
-            }))
+                ..ApiResponse::error(format!("Failed to unpick all run lots: {e}"))
+            }
         }
     }
 }
@@ -1233,7 +1182,7 @@ pub async fn unpick_all_run_lots(
 pub async fn get_batch_weight_summary(
     State(database): State<Database>,
     Path(run_no): Path<i32>,
-) -> Result<Json<ApiResponse<BatchWeightSummaryResponse>>, StatusCode> {
+) -> ApiResponse<BatchWeightSummaryResponse> {
     info!("📊 Get batch weight summary endpoint called for run: {}", run_no);
 
     match database.get_batch_weight_summary(run_no).await {
@@ -1254,19 +1203,11 @@ pub async fn get_batch_weight_summary(
 
             info!("📊 Found {} batch items with total remaining weight: {}", total_items, response.total_remaining_weight);
 
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(response),
-                message: format!("Found {total_items} batch items for run {run_no}"),
-            }))
+            ApiResponse::success(response, format!("Found {total_items} batch items for run {run_no}"))
         }
         Err(e) => {
             error!("❌ Failed to get batch weight summary for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: "Failed to get batch weight summary".to_string(),
-            }))
+            ApiResponse::error("Failed to get batch weight summary")
         }
     }
 }
@@ -1276,126 +1217,218 @@ pub async fn get_batch_weight_summary(
 pub async fn get_run_lot_details(
     State(database): State<Database>,
     Path(run_no): Path<i32>,
-) -> Result<Json<ApiResponse<Vec<LotPickingDetail>>>, StatusCode> {
+) -> ApiResponse<Vec<LotPickingDetail>> {
     info!("🏷️ Get lot picking details endpoint called for run: {}", run_no);
 
     match database.get_lot_picking_details_for_run(run_no).await {
         Ok(lot_details) => {
             let count = lot_details.len();
             info!("🏷️ Found {} lot picking details for run {}", count, run_no);
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(lot_details),
-                message: format!("Found {count} lot picking details for run {run_no}"),
-            }))
+            ApiResponse::success(lot_details, format!("Found {count} lot picking details for run {run_no}"))
         }
         Err(e) => {
             error!("❌ Failed to get lot picking details for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Failed to get lot picking details: {e}"),
-            }))
+            ApiResponse::error(format!("Failed to get lot picking details: {e}"))
         }
     }
 }
 
+/// "BULK SUMMARY" labels of every picked batch in the run, rendered server-side.
+/// `?format=zpl|epl|pdf` returns printer-ready output; without it the label data is returned as JSON.
+/// GET /api/bulk-runs/{run_no}/labels
+#[instrument(skip(database))]
+pub async fn get_run_labels(
+    Path(run_no): Path<i32>,
+    Query(query): Query<BatchLabelQuery>,
+    State(database): State<Database>,
+) -> Result<Response, BulkRunError> {
+    let format = query.format().map_err(BulkRunError::Validation)?;
+    let labels = BulkRunsService::new(database).get_batch_labels(run_no, None).await?;
+    Ok(label_response(labels, format, &format!("run-{run_no}-labels")))
+}
+
+/// The label of one batch, in the same formats as the run's labels
+/// GET /api/bulk-runs/{run_no}/labels/{batch_no}
+#[instrument(skip(database))]
+pub async fn get_batch_label(
+    Path((run_no, batch_no)): Path<(i32, String)>,
+    Query(query): Query<BatchLabelQuery>,
+    State(database): State<Database>,
+) -> Result<Response, BulkRunError> {
+    let format = query.format().map_err(BulkRunError::Validation)?;
+    let labels = BulkRunsService::new(database)
+        .get_batch_labels(run_no, Some(&batch_no))
+        .await?;
+    Ok(label_response(labels, format, &format!("run-{run_no}-batch-{batch_no}-label")))
+}
+
+/// Pallet label of one batch row: its picked lots and the pallet's GS1 SSCC-18 as a GS1-128
+/// barcode. Same formats as batch labels. Answers 409 `SSCC_NOT_ASSIGNED` until the pallet has
+/// an SSCC from `POST .../sscc` or its first queued print job.
+/// GET /api/bulk-runs/{run_no}/pallet/{row_num}/label
+#[instrument(skip(database))]
+pub async fn get_pallet_label(
+    Path((run_no, row_num)): Path<(i32, i32)>,
+    Query(query): Query<BatchLabelQuery>,
+    State(database): State<Database>,
+) -> Result<Response, BulkRunError> {
+    let format = query.format().map_err(BulkRunError::Validation)?;
+    let labels = BulkRunsService::new(database).get_pallet_labels(run_no, row_num).await?;
+    Ok(label_response(labels, format, &format!("run-{run_no}-pallet-{row_num}-label")))
+}
+
+/// Give a pallet with picked lots its SSCC; repeated calls return the same SSCC
+/// POST /api/bulk-runs/{run_no}/pallet/{row_num}/sscc
+#[instrument(skip(database, sscc_config))]
+pub async fn assign_pallet_sscc(
+    Path((run_no, row_num)): Path<(i32, i32)>,
+    State(database): State<Database>,
+    State(sscc_config): State<Option<SsccConfig>>,
+    user: AuthenticatedUser,
+) -> Result<ApiResponse<PalletSscc>, BulkRunError> {
+    let pallet = BulkRunsService::new(database)
+        .with_sscc_config(sscc_config)
+        .assign_pallet_sscc(run_no, row_num, &user.username)
+        .await?;
+    let message = format!("Pallet {row_num} of run {run_no} has SSCC {}", pallet.sscc);
+    Ok(ApiResponse::success(pallet, message))
+}
+
+/// The pallet a scanned SSCC belongs to: run, batch row and the lots picked onto it.
+/// Accepts the bare 18 digits or the barcode content with its (00) Application Identifier.
+/// GET /api/bulk-runs/pallets/sscc/{sscc}
+#[instrument(skip(database))]
+pub async fn lookup_pallet_by_sscc(
+    Path(sscc): Path<String>,
+    State(database): State<Database>,
+) -> Result<ApiResponse<PalletLookup>, BulkRunError> {
+    let pallet = BulkRunsService::new(database).lookup_pallet(&sscc).await?;
+    let message = format!(
+        "SSCC {} is pallet {} of run {} (batch {})",
+        pallet.pallet.sscc, pallet.pallet.row_num, pallet.pallet.run_no, pallet.pallet.batch_no
+    );
+    Ok(ApiResponse::success(pallet, message))
+}
+
+/// Picks refused because the scanned bin or lot did not match the selected allocation (supervisor only)
+/// GET /api/bulk-runs/{run_no}/scan-exceptions
+#[instrument(skip(database))]
+pub async fn get_scan_exceptions(
+    Path(run_no): Path<i32>,
+    State(database): State<Database>,
+) -> Result<ApiResponse<Vec<PickScanException>>, BulkRunError> {
+    let exceptions = BulkRunsService::new(database).get_scan_exceptions(run_no).await?;
+    let message = format!("{} scan exceptions for run {run_no}", exceptions.len());
+    Ok(ApiResponse::success(exceptions, message))
+}
+
+fn label_response<L: LabelLayout + serde::Serialize>(labels: Vec<L>, format: LabelFormat, file_stem: &str) -> Response {
+    let body = match format {
+        LabelFormat::Json => {
+            let message = format!("{} labels ready to print", labels.len());
+            return ApiResponse::success(labels, message).into_response();
+        }
+        LabelFormat::Zpl => render_zpl(&labels).into_bytes(),
+        LabelFormat::Epl => render_epl(&labels).into_bytes(),
+        LabelFormat::Pdf => render_pdf(&labels),
+    };
+    // PDFs open in the browser's print preview, printer languages download
+    let disposition = if format == LabelFormat::Pdf { "inline" } else { "attachment" };
+    (
+        [
+            (header::CONTENT_TYPE, format.content_type().to_string()),
+            (
+                header::CONTENT_DISPOSITION,
+                format!("{disposition}; filename=\"{file_stem}.{}\"", format.extension()),
+            ),
+        ],
+        body,
+    )
+        .into_response()
+}
+
 /// **REVERT STATUS ENDPOINT** - Revert bulk run status from PRINT back to NEW
 /// Used when user wants to make changes after run completion
-#[instrument(skip(database))]
+#[instrument(skip(database, events))]
 pub async fn revert_run_status(
     State(database): State<Database>,
+    State(events): State<RunEventBus>,
     Path(run_no): Path<i32>,
-    headers: HeaderMap,
-) -> Result<Json<ApiResponse<BulkRunStatusResponse>>, StatusCode> {
+    user: AuthenticatedUser,
+) -> ApiResponse<BulkRunStatusResponse> {
     info!("🔄 REVERT: Status revert endpoint called for run: {}", run_no);
 
-    // Extract user information from headers
-    let user_id = headers.get("x-user-id")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("SYSTEM");
+    let user_id = user.username.as_str();
 
     info!("👤 REVERT: User requesting status revert for run {}: {}", run_no, user_id);
 
-    // Validate that user_id is not empty or just whitespace
-    let user_id = user_id.trim();
-    if user_id.is_empty() || user_id == "SYSTEM" {
-        warn!("⚠️ REVERT: Invalid or missing user ID for run {} revert", run_no);
-        return Ok(Json(ApiResponse {
-            success: false,
-            data: None,
-            message: "Valid user authentication required for status revert operation".to_string(),
-        }));
-    }
-
     // Initialize service and delegate all business logic to service layer
-    let service = BulkRunsService::new(database);
+    let service = BulkRunsService::new(database).with_events(events);
 
     match service.revert_bulk_run_status(run_no, user_id).await {
         Ok(Some(updated_status)) => {
             info!("✅ REVERT: Successfully reverted run {} status from PRINT to NEW", run_no);
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(updated_status),
-                message: format!("Run {run_no} status successfully reverted from PRINT to NEW"),
-            }))
+            ApiResponse::success(updated_status, format!("Run {run_no} status successfully reverted from PRINT to NEW"))
         }
         Ok(None) => {
             warn!("⚠️ REVERT: Failed to revert run {} - validation failed or no changes made", run_no);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Cannot revert run {run_no} - run may not exist, not in PRINT status, or already reverted"),
-            }))
+            ApiResponse::error_with_code(
+                format!("Cannot revert run {run_no} - run may not exist, not in PRINT status, or already reverted"),
+                "INVALID_RUN_STATUS",
+            )
+            .with_status(StatusCode::CONFLICT)
         }
         Err(e) => {
             error!("❌ REVERT: Service error during status revert for run {}: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Database error during status revert: {e}"),
-            }))
+            ApiResponse::error(format!("Database error during status revert: {e}"))
         }
     }
 }
 
 /// **NEW PRINT STATUS UPDATE ENDPOINT** - Update run status to PRINT
-/// Triggered when printing labels
-#[instrument(skip(database))]
+/// Triggered when printing labels. With `?printer_id=` the run's batch labels are also
+/// queued for that network printer before the status changes.
+#[instrument(skip(database, events))]
 pub async fn update_print_status(
     Path(run_no): Path<i32>,
+    Query(query): Query<PrintStatusQuery>,
     State(database): State<Database>,
-    headers: HeaderMap,
-) -> Result<Json<ApiResponse<StatusUpdateResult>>, StatusCode> {
+    State(events): State<RunEventBus>,
+    user: AuthenticatedUser,
+) -> Result<ApiResponse<StatusUpdateResult>, PrintError> {
     info!("🔄 PRINT_STATUS: Attempting to update run {} status to PRINT", run_no);
 
-    // Extract user information from headers
-    let user_id = headers.get("x-user-id")
-        .and_then(|v| v.to_str().ok())
-        .unwrap_or("SYSTEM");
+    let user_id = user.username.as_str();
 
     info!("👤 PRINT_STATUS: User triggering print status update for run {}: {}", run_no, user_id);
 
-    let service = BulkRunsService::new(database);
+    if let Some(printer_id) = query.printer_id {
+        let request = PrintLabelsRequest { printer_id: Some(printer_id), batch_no: None };
+        let service = PrintQueueService::new(database.clone()).with_events(events);
+        let (job, status_result) = service.print_run_labels(run_no, &request, user_id).await.inspect_err(|e| {
+            warn!("❌ PRINT_STATUS: Failed to print labels for run {}: {}", run_no, e);
+        })?;
+        dispatch_due_jobs(database);
+
+        info!("✅ PRINT_STATUS: Run {} labels queued as print job {}", run_no, job.job_id);
+        return Ok(ApiResponse::success(
+            status_result,
+            format!("Run {run_no} print status update processed; labels queued as print job {} on {}", job.job_id, job.printer_id),
+        ));
+    }
+
+    let service = BulkRunsService::new(database).with_events(events);
 
     // Update run status to PRINT if currently NEW
     match service.update_print_status(run_no, user_id).await {
         Ok(status_result) => {
             info!("✅ PRINT_STATUS: Successfully processed print status update for run {}", run_no);
 
-            Ok(Json(ApiResponse {
-                success: true,
-                data: Some(status_result),
-                message: format!("Run {run_no} print status update processed"),
-            }))
+            Ok(ApiResponse::success(status_result, format!("Run {run_no} print status update processed")))
         }
         Err(e) => {
             warn!("❌ PRINT_STATUS: Failed to update run {} status: {}", run_no, e);
-            Ok(Json(ApiResponse {
-                success: false,
-                data: None,
-                message: format!("Failed to update run status: {e}"),
-            }))
+            Err(e.into())
         }
     }
 }
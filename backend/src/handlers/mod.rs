@@ -1,3 +1,7 @@
 // CLEAN HANDLER MODULE STRUCTURE - Only functional modules included
+pub mod api_keys;
+pub mod auth;
 pub mod bulk_runs;
-pub mod putaway;
\ No newline at end of file
+pub mod printing;
+pub mod putaway;
+pub mod scans;
\ No newline at end of file
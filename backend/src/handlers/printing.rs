@@ -0,0 +1,149 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{error, info, instrument};
+
+use crate::database::Database;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::pallet_labels::SsccConfig;
+use crate::models::print_jobs::{
+    LabelPrinter, PrintError, PrintJob, PrintJobQuery, PrintLabelsRequest, PrintPalletRequest, RegisterPrinterRequest,
+    ReprintRequest,
+};
+use crate::services::print_queue::{dispatch_due_jobs, PrintQueueService};
+use crate::types::ApiResponse;
+
+impl IntoResponse for PrintError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        if status.is_server_error() {
+            error!("❌ PRINTING: {} [{}]: {:#}", status, self.error_code(), self);
+        }
+
+        ApiResponse::<()>::error_with_code(self.to_string(), self.error_code())
+            .with_status(status)
+            .into_response()
+    }
+}
+
+/// Registered label printers
+/// GET /api/printers
+#[instrument(skip(database))]
+pub async fn list_printers(State(database): State<Database>) -> Result<ApiResponse<Vec<LabelPrinter>>, PrintError> {
+    let printers = PrintQueueService::new(database).list_printers().await?;
+    let message = format!("{} printers registered", printers.len());
+    Ok(ApiResponse::success(printers, message))
+}
+
+/// Register a network label printer, or update the one with the same id (supervisor only)
+/// POST /api/printers
+#[instrument(skip(database, request))]
+pub async fn register_printer(
+    State(database): State<Database>,
+    user: AuthenticatedUser,
+    Json(request): Json<RegisterPrinterRequest>,
+) -> Result<ApiResponse<LabelPrinter>, PrintError> {
+    let printer = PrintQueueService::new(database)
+        .register_printer(request, &user.username)
+        .await?;
+    let message = format!("Printer {} registered", printer.printer_id);
+    Ok(ApiResponse::success(printer, message))
+}
+
+/// Queue the run's batch labels (or one batch's) for a network printer. Does not change the run status.
+/// POST /api/bulk-runs/{run_no}/print-jobs
+#[instrument(skip(database, request))]
+pub async fn queue_run_labels(
+    Path(run_no): Path<i32>,
+    State(database): State<Database>,
+    user: AuthenticatedUser,
+    Json(request): Json<PrintLabelsRequest>,
+) -> Result<ApiResponse<PrintJob>, PrintError> {
+    let job = PrintQueueService::new(database.clone())
+        .queue_run_labels(run_no, &request, &user.username)
+        .await?;
+    dispatch_due_jobs(database);
+
+    let message = format!("Print job {} queued for printer {}", job.job_id, job.printer_id);
+    Ok(ApiResponse::success(job, message))
+}
+
+/// Queue one pallet's SSCC label for a network printer, assigning the SSCC on first print
+/// POST /api/bulk-runs/{run_no}/pallet/{row_num}/print-jobs
+#[instrument(skip(database, request))]
+pub async fn queue_pallet_labels(
+    Path((run_no, row_num)): Path<(i32, i32)>,
+    State(database): State<Database>,
+    State(sscc_config): State<Option<SsccConfig>>,
+    user: AuthenticatedUser,
+    Json(request): Json<PrintPalletRequest>,
+) -> Result<ApiResponse<PrintJob>, PrintError> {
+    let job = PrintQueueService::new(database.clone())
+        .with_sscc_config(sscc_config)
+        .queue_pallet_labels(run_no, row_num, &request, &user.username)
+        .await?;
+    dispatch_due_jobs(database);
+
+    let message = format!("Pallet label print job {} queued for printer {}", job.job_id, job.printer_id);
+    Ok(ApiResponse::success(job, message))
+}
+
+/// Print jobs, newest first, optionally for one run
+/// GET /api/print-jobs?run_no=&limit=
+#[instrument(skip(database))]
+pub async fn list_print_jobs(
+    Query(query): Query<PrintJobQuery>,
+    State(database): State<Database>,
+) -> Result<ApiResponse<Vec<PrintJob>>, PrintError> {
+    let jobs = PrintQueueService::new(database)
+        .list_jobs(query.run_no, query.limit.unwrap_or(50))
+        .await?;
+    let message = format!("{} print jobs", jobs.len());
+    Ok(ApiResponse::success(jobs, message))
+}
+
+/// GET /api/print-jobs/{job_id}
+#[instrument(skip(database))]
+pub async fn get_print_job(
+    Path(job_id): Path<i64>,
+    State(database): State<Database>,
+) -> Result<ApiResponse<PrintJob>, PrintError> {
+    let job = PrintQueueService::new(database).get_job(job_id).await?;
+    let message = format!("Print job {job_id} is {}", job.status.as_str());
+    Ok(ApiResponse::success(job, message))
+}
+
+/// Send a FAILED job again with a fresh set of attempts
+/// POST /api/print-jobs/{job_id}/retry
+#[instrument(skip(database))]
+pub async fn retry_print_job(
+    Path(job_id): Path<i64>,
+    State(database): State<Database>,
+    user: AuthenticatedUser,
+) -> Result<ApiResponse<PrintJob>, PrintError> {
+    let job = PrintQueueService::new(database.clone()).retry(job_id).await?;
+    dispatch_due_jobs(database);
+
+    info!("🖨️ {} retries print job {}", user.username, job_id);
+    Ok(ApiResponse::success(job, format!("Print job {job_id} queued again")))
+}
+
+/// Print a job's labels again as a new job; the reason is required and kept for audit
+/// POST /api/print-jobs/{job_id}/reprint
+#[instrument(skip(database, request))]
+pub async fn reprint_job(
+    Path(job_id): Path<i64>,
+    State(database): State<Database>,
+    user: AuthenticatedUser,
+    Json(request): Json<ReprintRequest>,
+) -> Result<ApiResponse<PrintJob>, PrintError> {
+    let job = PrintQueueService::new(database.clone())
+        .reprint(job_id, &request, &user.username)
+        .await?;
+    dispatch_due_jobs(database);
+
+    let message = format!("Reprint of job {job_id} queued as job {}", job.job_id);
+    Ok(ApiResponse::success(job, message))
+}
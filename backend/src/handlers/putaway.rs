@@ -1,18 +1,21 @@
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
-    response::Json,
+    extract::{rejection::JsonRejection, Path, Query, State},
+    http::HeaderMap,
+    response::{IntoResponse, Json, Response},
     routing::{get, post},
     Router,
 };
 use std::collections::HashMap;
 use serde_json::json;
 
-use crate::database::Database;
+use crate::database::{putaway_db::PutawayDatabase, Database};
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::idempotency::{idempotency_key_from_headers, IdempotencyKey, IdempotentOperation};
 use crate::services::{
-    PutawayService, LotSearchResult, BinValidationResult, 
+    PutawayService, LotSearchResult, BinValidationResult,
     BinTransferRequest, TransferResult, PutawayHealthResponse, PutawayError
 };
+use crate::types::ApiResponse;
 
 /// Create putaway routes
 pub fn create_putaway_routes() -> Router<Database> {
@@ -24,6 +27,20 @@ pub fn create_putaway_routes() -> Router<Database> {
         .route("/transfer", post(execute_transfer))
         .route("/health", get(get_health))
         .route("/remarks", get(get_remarks))
+        .route("/scan", get(super::scans::resolve_putaway_scan))
+}
+
+impl IntoResponse for PutawayError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        if status.is_server_error() {
+            tracing::error!("❌ PUTAWAY: {} [{}]: {}", status, self.error_code(), self);
+        }
+
+        ApiResponse::<()>::error_with_code(self.client_message(), self.error_code())
+            .with_status(status)
+            .into_response()
+    }
 }
 
 /// Search for lot details
@@ -31,51 +48,11 @@ pub fn create_putaway_routes() -> Router<Database> {
 async fn search_lot(
     State(database): State<Database>,
     Path(lot_no): Path<String>,
-) -> Result<Json<LotSearchResult>, (StatusCode, Json<serde_json::Value>)> {
-    let service = PutawayService::new(database);
+) -> Result<ApiResponse<LotSearchResult>, PutawayError> {
+    let service = PutawayService::new(PutawayDatabase::new(database));
 
-    match service.search_lot(&lot_no).await {
-        Ok(result) => Ok(Json(result)),
-        Err(PutawayError::LotNotFound { lot_no }) => {
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({
-                    "error": "Lot not found",
-                    "message": format!("Lot '{}' not found or has zero quantity", lot_no),
-                    "lot_no": lot_no
-                }))
-            ))
-        }
-        Err(PutawayError::ValidationError(msg)) => {
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "Validation error",
-                    "message": msg
-                }))
-            ))
-        }
-        Err(PutawayError::DatabaseError(msg)) => {
-            tracing::error!("Database error in search_lot: {msg}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Database error",
-                    "message": "Internal server error occurred"
-                }))
-            ))
-        }
-        Err(e) => {
-            tracing::error!("Unexpected error in search_lot: {e}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Internal server error",
-                    "message": "An unexpected error occurred"
-                }))
-            ))
-        }
-    }
+    let result = service.search_lot(&lot_no).await?;
+    Ok(ApiResponse::success(result, format!("Lot '{lot_no}' found")))
 }
 
 /// Search for lots with optional query filter and pagination
@@ -83,8 +60,8 @@ async fn search_lot(
 async fn search_lots(
     State(database): State<Database>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    let service = PutawayService::new(database);
+) -> Result<ApiResponse<serde_json::Value>, PutawayError> {
+    let service = PutawayService::new(PutawayDatabase::new(database));
 
     // Extract query parameters
     let query = params.get("query").map(|s| s.as_str());
@@ -95,38 +72,18 @@ async fn search_lots(
         .and_then(|s| s.parse::<i32>().ok())
         .unwrap_or(20); // Default limit
 
-    match service.search_lots_paginated(query, page, limit).await {
-        Ok((lots, total)) => {
-            let total_pages = ((total as f64) / (limit as f64)).ceil() as i32;
-            Ok(Json(json!({
-                "items": lots,
-                "total": total,
-                "page": page,
-                "pages": total_pages,
-                "limit": limit
-            })))
-        },
-        Err(PutawayError::DatabaseError(msg)) => {
-            tracing::error!("Database error in search_lots: {msg}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Database error",
-                    "message": format!("Database connection failed: {}", msg)
-                }))
-            ))
-        }
-        Err(e) => {
-            tracing::error!("Unexpected error in search_lots: {e}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Internal server error",
-                    "message": "An unexpected error occurred"
-                }))
-            ))
-        }
-    }
+    let (lots, total) = service.search_lots_paginated(query, page, limit).await?;
+    let total_pages = ((total as f64) / (limit as f64)).ceil() as i32;
+    Ok(ApiResponse::success(
+        json!({
+            "items": lots,
+            "total": total,
+            "page": page,
+            "pages": total_pages,
+            "limit": limit
+        }),
+        format!("Found {total} lots"),
+    ))
 }
 
 /// Search for bins with optional query filter and pagination
@@ -137,8 +94,8 @@ async fn search_lots(
 async fn search_bins(
     State(database): State<Database>,
     Query(params): Query<HashMap<String, String>>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    let service = PutawayService::new(database);
+) -> Result<ApiResponse<serde_json::Value>, PutawayError> {
+    let service = PutawayService::new(PutawayDatabase::new(database));
 
     // Extract query parameters
     let query = params.get("query").map(|s| s.as_str());
@@ -154,38 +111,20 @@ async fn search_bins(
     let item_key = params.get("item_key").map(|s| s.as_str());
     let location = params.get("location").map(|s| s.as_str());
 
-    match service.search_bins_paginated(query, page, limit, lot_no, item_key, location).await {
-        Ok((bins, total)) => {
-            let total_pages = ((total as f64) / (limit as f64)).ceil() as i32;
-            Ok(Json(json!({
-                "items": bins,
-                "total": total,
-                "page": page,
-                "pages": total_pages,
-                "limit": limit
-            })))
-        },
-        Err(PutawayError::DatabaseError(msg)) => {
-            tracing::error!("Database error in search_bins: {msg}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Database error",
-                    "message": format!("Database connection failed: {}", msg)
-                }))
-            ))
-        }
-        Err(e) => {
-            tracing::error!("Unexpected error in search_bins: {e}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Internal server error",
-                    "message": "An unexpected error occurred"
-                }))
-            ))
-        }
-    }
+    let (bins, total) = service
+        .search_bins_paginated(query, page, limit, lot_no, item_key, location)
+        .await?;
+    let total_pages = ((total as f64) / (limit as f64)).ceil() as i32;
+    Ok(ApiResponse::success(
+        json!({
+            "items": bins,
+            "total": total,
+            "page": page,
+            "pages": total_pages,
+            "limit": limit
+        }),
+        format!("Found {total} bins"),
+    ))
 }
 
 /// Validate destination bin
@@ -193,160 +132,53 @@ async fn search_bins(
 async fn validate_bin(
     State(database): State<Database>,
     Path((location, bin_no)): Path<(String, String)>,
-) -> Result<Json<BinValidationResult>, (StatusCode, Json<serde_json::Value>)> {
-    let service = PutawayService::new(database);
+) -> Result<ApiResponse<BinValidationResult>, PutawayError> {
+    let service = PutawayService::new(PutawayDatabase::new(database));
 
-    match service.validate_bin(&location, &bin_no).await {
-        Ok(result) => Ok(Json(result)),
-        Err(PutawayError::DatabaseError(msg)) => {
-            tracing::error!("Database error in validate_bin: {msg}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Database error",
-                    "message": format!("Database connection failed: {}", msg)
-                }))
-            ))
-        }
-        Err(e) => {
-            tracing::error!("Unexpected error in validate_bin: {e}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Internal server error",
-                    "message": "An unexpected error occurred"
-                }))
-            ))
-        }
-    }
+    let result = service.validate_bin(&location, &bin_no).await?;
+    let message = result.message.clone();
+    Ok(ApiResponse::success(result, message))
 }
 
 /// Execute bin transfer
 /// POST /api/putaway/transfer
 async fn execute_transfer(
     State(database): State<Database>,
-    Json(request): Json<BinTransferRequest>,
-) -> Result<Json<TransferResult>, (StatusCode, Json<serde_json::Value>)> {
-    let service = PutawayService::new(database);
-
-    match service.execute_transfer(request).await {
-        Ok(result) => {
-            if result.success {
-                Ok(Json(result))
-            } else {
-                Err((
-                    StatusCode::BAD_REQUEST,
-                    Json(json!({
-                        "error": "Transfer failed",
-                        "message": result.message,
-                        "timestamp": result.timestamp
-                    }))
-                ))
-            }
-        }
-        Err(PutawayError::LotNotFound { lot_no }) => {
-            Err((
-                StatusCode::NOT_FOUND,
-                Json(json!({
-                    "error": "Lot not found",
-                    "message": format!("Lot '{}' not found", lot_no)
-                }))
-            ))
-        }
-        Err(PutawayError::InvalidBin { bin_no, location }) => {
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "Invalid bin",
-                    "message": format!("Bin '{}' is not valid in location '{}'", bin_no, location)
-                }))
-            ))
-        }
-        Err(PutawayError::InsufficientQuantity { requested, available }) => {
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "Insufficient quantity",
-                    "message": format!("Requested {} but only {} available", requested, available),
-                    "requested": requested,
-                    "available": available
-                }))
-            ))
-        }
-        Err(PutawayError::ValidationError(msg)) => {
-            Err((
-                StatusCode::BAD_REQUEST,
-                Json(json!({
-                    "error": "Validation error",
-                    "message": msg
-                }))
-            ))
-        }
-        Err(PutawayError::TransactionError(msg)) => {
-            tracing::error!("Transaction error in execute_transfer: {msg}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Transaction error",
-                    "message": "Failed to complete transfer transaction"
-                }))
-            ))
-        }
-        Err(PutawayError::DatabaseError(msg)) => {
-            tracing::error!("Database error in execute_transfer: {msg}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Database error",
-                    "message": "Internal server error occurred"
-                }))
-            ))
-        }
-    }
+    user: AuthenticatedUser,
+    headers: HeaderMap,
+    payload: Result<Json<BinTransferRequest>, JsonRejection>,
+) -> Result<ApiResponse<TransferResult>, PutawayError> {
+    let Json(mut request) = payload?;
+    let service = PutawayService::new(PutawayDatabase::new(database));
+
+    // RecUserID is always the authenticated user
+    request.user_id = user.username;
+    request.idempotency_key = idempotency_key_from_headers(&headers)
+        .map_err(PutawayError::ValidationError)?
+        .map(|key| IdempotencyKey::new(key, IdempotentOperation::BinTransfer, &request.user_id, &request));
+
+    let result = service.execute_transfer(request).await?;
+    let message = result.message.clone();
+    Ok(ApiResponse::success(result, message))
 }
 
 /// Get service health status
 /// GET /api/putaway/health
 async fn get_health(
     State(database): State<Database>,
-) -> Json<PutawayHealthResponse> {
-    let service = PutawayService::new(database);
-    Json(service.get_health().await)
+) -> ApiResponse<PutawayHealthResponse> {
+    let service = PutawayService::new(PutawayDatabase::new(database));
+    ApiResponse::success(service.get_health().await, "Putaway service health")
 }
 
 /// Get all active putaway remarks for dropdown
 /// GET /api/putaway/remarks
 async fn get_remarks(
     State(database): State<Database>,
-) -> Result<Json<serde_json::Value>, (StatusCode, Json<serde_json::Value>)> {
-    let service = PutawayService::new(database);
+) -> Result<ApiResponse<Vec<serde_json::Value>>, PutawayError> {
+    let service = PutawayService::new(PutawayDatabase::new(database));
 
-    match service.get_active_remarks().await {
-        Ok(remarks) => Ok(Json(json!({
-            "success": true,
-            "data": remarks
-        }))),
-        Err(PutawayError::DatabaseError(msg)) => {
-            tracing::error!("Database error in get_remarks: {msg}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Database error",
-                    "message": "Failed to retrieve remarks"
-                }))
-            ))
-        }
-        Err(e) => {
-            tracing::error!("Unexpected error in get_remarks: {e}");
-            Err((
-                StatusCode::INTERNAL_SERVER_ERROR,
-                Json(json!({
-                    "error": "Internal server error",
-                    "message": "An unexpected error occurred"
-                }))
-            ))
-        }
-    }
+    let remarks = service.get_active_remarks().await?;
+    let count = remarks.len();
+    Ok(ApiResponse::success(remarks, format!("Found {count} active remarks")))
 }
-
-
@@ -0,0 +1,78 @@
+use axum::{
+    extract::{Path, Query, State},
+    response::{IntoResponse, Response},
+    Json,
+};
+use tracing::{error, instrument};
+
+use crate::database::Database;
+use crate::middleware::auth::AuthenticatedUser;
+use crate::models::scans::{ItemGtin, RegisterGtinRequest, ScanError, ScanQuery, ScanResolution};
+use crate::services::scan_resolver::ScanService;
+use crate::types::ApiResponse;
+
+impl IntoResponse for ScanError {
+    fn into_response(self) -> Response {
+        let status = self.status_code();
+        if status.is_server_error() {
+            error!("❌ SCAN: {} [{}]: {:#}", status, self.error_code(), self);
+        }
+
+        ApiResponse::<()>::error_with_code(self.to_string(), self.error_code())
+            .with_status(status)
+            .into_response()
+    }
+}
+
+fn scan_message(resolution: &ScanResolution) -> String {
+    if resolution.is_verified() {
+        "Scan verified".to_string()
+    } else {
+        let messages: Vec<&str> = resolution.mismatches.iter().map(|m| m.message.as_str()).collect();
+        format!("Scan has {} mismatches: {}", messages.len(), messages.join("; "))
+    }
+}
+
+/// Resolve a picking scan to item, lot, expiry and the run's pickable bins of the lot
+/// GET /api/bulk-runs/{run_no}/scan?code=&item_key=
+#[instrument(skip(database))]
+pub async fn resolve_pick_scan(
+    Path(run_no): Path<i32>,
+    Query(query): Query<ScanQuery>,
+    State(database): State<Database>,
+) -> Result<ApiResponse<ScanResolution>, ScanError> {
+    let resolution = ScanService::new(database)
+        .resolve_for_run(run_no, &query.code, query.item_key.as_deref())
+        .await?;
+    let message = scan_message(&resolution);
+    Ok(ApiResponse::success(resolution, message))
+}
+
+/// Resolve a putaway scan to item, lot, expiry and the lot's LotMaster bins
+/// GET /api/putaway/scan?code=&item_key=
+#[instrument(skip(database))]
+pub async fn resolve_putaway_scan(
+    Query(query): Query<ScanQuery>,
+    State(database): State<Database>,
+) -> Result<ApiResponse<ScanResolution>, ScanError> {
+    let resolution = ScanService::new(database)
+        .resolve(&query.code, query.item_key.as_deref())
+        .await?;
+    let message = scan_message(&resolution);
+    Ok(ApiResponse::success(resolution, message))
+}
+
+/// Map a supplier GTIN to an item (supervisor only)
+/// POST /api/item-gtins
+#[instrument(skip(database, request))]
+pub async fn register_item_gtin(
+    State(database): State<Database>,
+    user: AuthenticatedUser,
+    Json(request): Json<RegisterGtinRequest>,
+) -> Result<ApiResponse<ItemGtin>, ScanError> {
+    let gtin = ScanService::new(database)
+        .register_gtin(request, &user.username)
+        .await?;
+    let message = format!("GTIN {} registered for item {}", gtin.gtin, gtin.item_key);
+    Ok(ApiResponse::success(gtin, message))
+}
@@ -1,18 +1,18 @@
 use axum::{
-    extract::State,
+    extract::{ConnectInfo, FromRef, State},
     http::{header, Method, StatusCode, HeaderMap},
-    middleware::from_fn_with_state,
+    middleware::{from_fn, from_fn_with_state},
     response::{Html, IntoResponse, Json},
     routing::{get, post, put},
     Router,
 };
-use ldap3::{LdapConnAsync, Scope, SearchEntry};
 use serde::{Deserialize, Serialize};
 use tiberius::{Query as TiberiusQuery, Row};
 use tower_http::cors::{Any, CorsLayer};
 use tower_http::services::ServeDir;
 use tracing::{error, info, instrument, warn};
 
+mod commands;
 mod database;
 mod handlers;
 mod middleware;
@@ -24,9 +24,24 @@ mod utils;
 #[cfg(test)]
 mod tests;
 
-use handlers::{bulk_runs, putaway};
-use middleware::auth::jwt_auth_middleware;
-use types::{ApiResponse, LoginResponse, User};
+use handlers::{api_keys as api_key_handlers, auth as auth_handlers, bulk_runs, printing, putaway, scans};
+use services::ldap_auth::{authenticate_ldap, lookup_ldap_account, CredentialsRejected, DirectoryAccount, LdapConfig};
+use services::login_throttle::{LoginThrottle, ThrottlePolicy};
+use services::print_queue::spawn_print_worker;
+use services::run_events::RunEventBus;
+use services::security_audit::SecurityAudit;
+use std::net::SocketAddr;
+use middleware::auth::{jwt_auth_middleware, require_admin, require_supervisor};
+use middleware::request_id::{request_id_middleware, REQUEST_ID_HEADER};
+use models::idempotency::IDEMPOTENCY_KEY_HEADER;
+use models::auth_session::{RefreshRotation, RefreshTokenRequest, RevocationReason};
+use models::pallet_labels::SsccConfig;
+use models::pick_scans::ScanVerifyPolicy;
+use models::security_audit::{AuditOutcome, AuthMethod, SecurityEvent, SecurityEventType};
+use types::{ApiResponse, AuthToken, LoginResponse, Role, User};
+use utils::client_ip::client_ip;
+use utils::password::{hash_password, verify_password, PasswordCheck};
+use utils::roles::roles_from_column;
 use utils::AuthService;
 
 #[derive(Clone)]
@@ -34,9 +49,39 @@ pub struct AppState {
     pub database: database::Database,
     pub ldap_config: LdapConfig,
     pub auth_service: AuthService,
+    pub login_throttle: LoginThrottle,
+    pub security_audit: SecurityAudit,
+    pub run_events: RunEventBus,
+    pub scan_policy: ScanVerifyPolicy,
+    pub sscc_config: Option<SsccConfig>,
     pub static_assets_path: String,
 }
 
+// Bulk-run handlers extract only the pieces of state they use
+impl FromRef<AppState> for database::Database {
+    fn from_ref(state: &AppState) -> Self {
+        state.database.clone()
+    }
+}
+
+impl FromRef<AppState> for RunEventBus {
+    fn from_ref(state: &AppState) -> Self {
+        state.run_events.clone()
+    }
+}
+
+impl FromRef<AppState> for ScanVerifyPolicy {
+    fn from_ref(state: &AppState) -> Self {
+        state.scan_policy.clone()
+    }
+}
+
+impl FromRef<AppState> for Option<SsccConfig> {
+    fn from_ref(state: &AppState) -> Self {
+        state.sscc_config.clone()
+    }
+}
+
 impl std::fmt::Debug for AppState {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("AppState")
@@ -47,52 +92,35 @@ impl std::fmt::Debug for AppState {
 }
 
 
-#[derive(Clone, Debug)]
-pub struct LdapConfig {
-    pub url: String,
-    pub base_dn: String,
-    pub enabled: bool,
-}
-
 #[derive(Deserialize)]
 pub struct LoginRequest {
     pub username: String,
     pub password: String,
 }
 
-#[derive(Serialize)]
-pub struct LoginData {
-    pub access_token: String,
-    pub refresh_token: String,
-    pub token_type: String,
-    pub expires_at: i64,
-    pub expires_in: i64,
-    pub user: User,
+#[derive(Deserialize, Default)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
 }
 
-// LoginResponse and User are defined in types/mod.rs
+// LoginResponse, AuthToken and User are defined in types/mod.rs
 
 #[derive(Serialize)]
 pub struct HealthResponse {
-    pub success: bool,
     pub status: String,
-    pub message: String,
     pub timestamp: String,
     pub version: String,
 }
 
 #[derive(Serialize)]
 pub struct DatabaseStatusResponse {
-    pub success: bool,
     pub database: String,
     pub timestamp: String,
 }
 
 #[derive(Serialize)]
 pub struct AuthHealthResponse {
-    pub success: bool,
     pub status: String,
-    pub message: String,
     pub primary_database: String,
     pub tbl_user_exists: bool,
     pub ldap_enabled: bool,
@@ -103,27 +131,26 @@ pub struct AuthHealthResponse {
 pub const VERSION: &str = env!("CARGO_PKG_VERSION");
 
 /// Health check endpoint
-async fn health_check() -> Json<HealthResponse> {
-    Json(HealthResponse {
-        success: true,
+async fn health_check() -> ApiResponse<HealthResponse> {
+    let health = HealthResponse {
         status: "healthy".to_string(),
-        message: "Bulk picking backend is running".to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
         version: VERSION.to_string(),
-    })
+    };
+    ApiResponse::success(health, "Bulk picking backend is running")
 }
 
 /// Database status endpoint - shows current database configuration
-async fn database_status(State(state): State<AppState>) -> Json<DatabaseStatusResponse> {
-    Json(DatabaseStatusResponse {
-        success: true,
+async fn database_status(State(state): State<AppState>) -> ApiResponse<DatabaseStatusResponse> {
+    let status = DatabaseStatusResponse {
         database: state.database.get_database_name().to_string(),
         timestamp: chrono::Utc::now().to_rfc3339(),
-    })
+    };
+    ApiResponse::success(status, "Database configuration")
 }
 
 /// Authentication health check endpoint - validates authentication dependencies
-async fn auth_health(State(state): State<AppState>) -> Json<AuthHealthResponse> {
+async fn auth_health(State(state): State<AppState>) -> ApiResponse<AuthHealthResponse> {
     let mut issues = Vec::new();
     let database_name = state.database.get_database_name().to_string();
     let ldap_enabled = state.ldap_config.enabled;
@@ -155,23 +182,33 @@ async fn auth_health(State(state): State<AppState>) -> Json<AuthHealthResponse>
         "Authentication service has configuration issues"
     };
 
-    Json(AuthHealthResponse {
-        success: issues.is_empty(),
+    let healthy = issues.is_empty();
+    let health = AuthHealthResponse {
         status: status.to_string(),
-        message: message.to_string(),
         primary_database: database_name,
         tbl_user_exists,
         ldap_enabled,
         issues,
         timestamp: chrono::Utc::now().to_rfc3339(),
-    })
+    };
+
+    // Degraded auth reports 503 but still returns the details for diagnosis
+    if healthy {
+        ApiResponse::success(health, message)
+    } else {
+        ApiResponse {
+            data: Some(health),
+            ..ApiResponse::error_with_code(message, "AUTH_SERVICE_UNAVAILABLE")
+                .with_status(StatusCode::SERVICE_UNAVAILABLE)
+        }
+    }
 }
 
 /// Authentication status check endpoint
 async fn auth_status(
     State(state): State<AppState>,
     headers: HeaderMap,
-) -> Json<ApiResponse<bool>> {
+) -> ApiResponse<bool> {
     // Check for Authorization header
     if let Some(auth_header) = headers.get("authorization") {
         if let Ok(auth_str) = auth_header.to_str() {
@@ -180,287 +217,357 @@ async fn auth_status(
                 
                 // Validate the JWT token
                 match state.auth_service.verify_token(token) {
-                    Ok(_) => return Json(ApiResponse::success(true, "User is authenticated")),
-                    Err(_) => return Json(ApiResponse::success(false, "Invalid token")),
+                    Ok(_) => return ApiResponse::success(true, "User is authenticated"),
+                    Err(_) => return ApiResponse::success(false, "Invalid token"),
                 }
             }
         }
     }
     
     // No valid token found
-    Json(ApiResponse::success(false, "No authentication token provided"))
+    ApiResponse::success(false, "No authentication token provided")
 }
 
 /// Authentication endpoint with proper JWT tokens
-#[instrument(skip(state, request))]
+#[instrument(skip(state, headers, request))]
 async fn login(
     State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(request): Json<LoginRequest>,
-) -> Result<Json<ApiResponse<LoginResponse>>, StatusCode> {
+) -> ApiResponse<LoginResponse> {
     info!("🔐 Login attempt for username: {}", request.username);
 
     if !state.ldap_config.enabled {
         warn!("⚠️ LDAP authentication is disabled");
-        return Ok(Json(ApiResponse::error("Authentication is currently disabled")));
+        return ApiResponse::error_with_code("Authentication is currently disabled", "AUTH_DISABLED")
+            .with_status(StatusCode::SERVICE_UNAVAILABLE);
     }
 
-    // Try both domain formats for LDAP authentication
-    let user_formats = vec![
-        format!("{}@NWFTH.com", request.username),
-        format!("{}@newlywedsfoods.co.th", request.username),
-        request.username.clone(),
-    ];
-
-    for user_format in user_formats {
-        info!("🔍 Attempting LDAP authentication for: {}", user_format);
-
-        match authenticate_ldap(&state.ldap_config, &user_format, &request.password).await {
-            Ok(user) => {
-                info!("✅ LDAP authentication successful for: {}", user_format);
-
-                // Generate proper JWT token
-                match state.auth_service.generate_token(&user) {
-                    Ok(token) => {
-                        let login_response = LoginResponse { token, user };
-                        return Ok(Json(ApiResponse::success(login_response, "Authentication successful")));
-                    }
-                    Err(e) => {
-                        error!("❌ Failed to generate JWT token: {}", e);
-                        return Ok(Json(ApiResponse::error("Failed to generate authentication token")));
-                    }
+    // Brute-force protection: refuse before touching AD while the username or IP is backing off,
+    // otherwise count this attempt up front so parallel requests cannot outrun the limits
+    let ip = client_ip(&headers, peer);
+    if let Err(blocked) = state.login_throttle.begin_attempt(&request.username, Some(ip)) {
+        warn!(
+            "🚫 Login throttled for {} from {} ({}, retry in {}s)",
+            request.username, ip, blocked.error_code, blocked.retry_after_secs
+        );
+        state.security_audit.record(
+            SecurityEvent::new(SecurityEventType::LoginThrottled, AuditOutcome::Blocked)
+                .username(&request.username)
+                .source_ip(ip)
+                .detail(format!("{} (retry in {}s)", blocked.error_code, blocked.retry_after_secs)),
+        );
+        return ApiResponse::error_with_code(blocked.message(), blocked.error_code)
+            .with_status(StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    // LDAP tries each configured UPN suffix (or a service-account lookup) internally
+    let login_event = |event_type: SecurityEventType, outcome: AuditOutcome, method: AuthMethod| {
+        SecurityEvent::new(event_type, outcome)
+            .username(&request.username)
+            .source_ip(ip)
+            .method(method)
+    };
+
+    let (ldap_error, ldap_rejected) = match authenticate_ldap(&state.ldap_config, &request.username, &request.password).await {
+        Ok(user) => {
+            state.login_throttle.record_success(&request.username, Some(ip));
+
+            // Start a new session: short-lived access token + rotating refresh token
+            match issue_session(&state, &user).await {
+                Ok(token) => {
+                    state.security_audit.record(login_event(
+                        SecurityEventType::LoginSuccess,
+                        AuditOutcome::Success,
+                        AuthMethod::Ldap,
+                    ));
+                    let login_response = LoginResponse { token, user };
+                    return ApiResponse::success(login_response, "Authentication successful");
+                }
+                Err(e) => {
+                    error!("❌ Failed to generate JWT token: {}", e);
+                    state.security_audit.record(
+                        login_event(SecurityEventType::LoginFailure, AuditOutcome::Failure, AuthMethod::Ldap)
+                            .detail(format!("Session could not be issued: {e}")),
+                    );
+                    return ApiResponse::error("Failed to generate authentication token");
                 }
-            }
-            Err(e) => {
-                info!("❌ LDAP authentication failed for {}: {}", user_format, e);
-                continue;
             }
         }
-    }
+        Err(e) => {
+            info!("❌ LDAP authentication failed for {}: {}", request.username, e);
+            (e.to_string(), e.is::<CredentialsRejected>())
+        }
+    };
 
     // Try SQL fallback authentication
     info!("🔄 LDAP authentication failed, attempting SQL fallback");
     match authenticate_sql(&state, &request.username, &request.password).await {
         Ok(user) => {
             info!("✅ SQL authentication successful for: {}", request.username);
+            state.login_throttle.record_success(&request.username, Some(ip));
             
-            // Generate proper JWT token
-            match state.auth_service.generate_token(&user) {
+            // Start a new session: short-lived access token + rotating refresh token
+            match issue_session(&state, &user).await {
                 Ok(token) => {
+                    state.security_audit.record(
+                        login_event(SecurityEventType::SqlFallback, AuditOutcome::Success, AuthMethod::Sql)
+                            .detail(format!("LDAP rejected: {ldap_error}")),
+                    );
                     let login_response = LoginResponse { token, user };
-                    Ok(Json(ApiResponse::success(login_response, "Authentication successful (SQL fallback)")))
+                    ApiResponse::success(login_response, "Authentication successful (SQL fallback)")
                 }
                 Err(e) => {
                     error!("❌ Failed to generate JWT token: {}", e);
-                    Ok(Json(ApiResponse::error("Failed to generate authentication token")))
+                    state.security_audit.record(
+                        login_event(SecurityEventType::LoginFailure, AuditOutcome::Failure, AuthMethod::Sql)
+                            .detail(format!("Session could not be issued: {e}")),
+                    );
+                    ApiResponse::error("Failed to generate authentication token")
                 }
             }
         }
         Err(e) => {
-            let error_msg = e.to_string();
-            if error_msg.contains("Authentication table 'tbl_user' not found") {
-                error!("🚨 Database configuration error: {}", error_msg);
-                Ok(Json(ApiResponse::error("Authentication service unavailable. Please contact system administrator.")))
-            } else if error_msg.contains("Invalid object name 'tbl_user'") {
-                error!("🚨 Database table missing: tbl_user table not found in current database");
-                Ok(Json(ApiResponse::error("Authentication service unavailable. Please contact system administrator.")))
-            } else {
+            state.security_audit.record(
+                login_event(SecurityEventType::LoginFailure, AuditOutcome::Failure, AuthMethod::Sql)
+                    .detail(format!("LDAP: {ldap_error}; SQL: {e}")),
+            );
+            // AD refusing the password is a failed guess even when the SQL fallback is down,
+            // so the attempt stays counted and the client is not told to retry
+            if e.is_credential_failure() || ldap_rejected {
                 warn!("❌ Authentication failed for user {}: {}", request.username, e);
-                Ok(Json(ApiResponse::error("Invalid username or password")))
+                ApiResponse::error_with_code("Invalid username or password", "INVALID_CREDENTIALS")
+                    .with_status(StatusCode::UNAUTHORIZED)
+            } else {
+                error!("🚨 SQL authentication unavailable: {}", e);
+                state.login_throttle.release(&request.username, Some(ip));
+                ApiResponse::error_with_code(
+                    "Authentication service unavailable. Please contact system administrator.",
+                    e.error_code(),
+                )
+                .with_status(StatusCode::SERVICE_UNAVAILABLE)
             }
         }
     }
 }
 
-async fn authenticate_ldap(
-    config: &LdapConfig,
-    username: &str,
-    password: &str,
-) -> Result<User, Box<dyn std::error::Error + Send + Sync>> {
-    let (conn, mut ldap) = LdapConnAsync::new(&config.url).await?;
-    ldap3::drive!(conn);
+/// Start a new token family for a freshly authenticated user
+async fn issue_session(state: &AppState, user: &User) -> anyhow::Result<AuthToken> {
+    let family_id = uuid::Uuid::new_v4().to_string();
+    let refresh_token = state.auth_service.new_refresh_token();
 
-    // Bind with user credentials
-    ldap.simple_bind(username, password).await?.success()?;
+    state
+        .database
+        .store_refresh_token(&family_id, user, &refresh_token)
+        .await?;
 
-    // Search for user information with enhanced debugging
-    let search_filter = if username.contains('@') {
-        format!("(userPrincipalName={username})")
-    } else {
-        format!("(sAMAccountName={username})")
+    state.auth_service.generate_token(user, &family_id, &refresh_token)
+}
+
+/// Exchange a refresh token for a new access token; the refresh token is rotated on every use
+#[instrument(skip(state, headers, request))]
+async fn refresh_token(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    Json(request): Json<RefreshTokenRequest>,
+) -> ApiResponse<LoginResponse> {
+    let presented_hash = AuthService::hash_refresh_token(&request.refresh_token);
+    let replacement = state.auth_service.new_refresh_token();
+
+    // Roles are re-read on every refresh so a demotion ends privileged access within one access token lifetime
+    let (family_id, username) = match state.database.find_refresh_token_family(&presented_hash).await {
+        Ok(Some(found)) => found,
+        Ok(None) => {
+            return ApiResponse::error_with_code("Invalid refresh token", "REFRESH_TOKEN_INVALID")
+                .with_status(StatusCode::UNAUTHORIZED)
+        }
+        Err(e) => {
+            error!("❌ Failed to look up refresh token: {}", e);
+            return ApiResponse::error("Failed to refresh session");
+        }
+    };
+    let roles = match current_roles(&state, &username).await {
+        Ok(RefreshRoles::Current(roles)) => Some(roles),
+        Ok(RefreshRoles::Unchanged) => None,
+        Ok(RefreshRoles::AccountDisabled) => {
+            warn!("🚫 Refresh refused for disabled account {} - revoking session {}", username, family_id);
+            state.auth_service.revoke_family(&family_id);
+            if let Err(e) = state
+                .database
+                .revoke_refresh_token_family(&family_id, RevocationReason::AccountDisabled)
+                .await
+            {
+                error!("❌ Failed to revoke session of disabled account {}: {}", username, e);
+            }
+            return ApiResponse::error_with_code("Account is disabled. Please contact your supervisor.", "ACCOUNT_DISABLED")
+                .with_status(StatusCode::UNAUTHORIZED);
+        }
+        Err(e) => {
+            error!("❌ Failed to look up account for refresh: {}", e);
+            return ApiResponse::error("Failed to refresh session");
+        }
+    };
+
+    let rotation = match state.database.rotate_refresh_token(&presented_hash, &replacement, roles.as_deref()).await {
+        Ok(rotation) => rotation,
+        Err(e) => {
+            error!("❌ Failed to rotate refresh token: {}", e);
+            return ApiResponse::error("Failed to refresh session");
+        }
     };
 
-    info!("🔍 LDAP search starting - Base DN: '{}', Filter: '{}'", config.base_dn, search_filter);
+    match rotation {
+        RefreshRotation::Rotated(session) => {
+            match state.auth_service.generate_token(&session.user, &session.family_id, &replacement) {
+                Ok(token) => {
+                    info!("🔄 Session refreshed for user: {}", session.user.username);
+                    let refresh_response = LoginResponse { token, user: session.user };
+                    ApiResponse::success(refresh_response, "Session refreshed")
+                }
+                Err(e) => {
+                    error!("❌ Failed to generate JWT token: {}", e);
+                    ApiResponse::error("Failed to generate authentication token")
+                }
+            }
+        }
+        RefreshRotation::ReuseDetected { family_id, username } => {
+            state.auth_service.revoke_family(&family_id);
+            state.security_audit.record(
+                SecurityEvent::new(SecurityEventType::RefreshReuse, AuditOutcome::Blocked)
+                    .username(username)
+                    .source_ip(client_ip(&headers, peer))
+                    .method(AuthMethod::RefreshToken)
+                    .detail(format!("Refresh token replayed - session {family_id} revoked")),
+            );
+            ApiResponse::error_with_code("Session has been revoked. Please log in again.", "REFRESH_TOKEN_REUSED")
+                .with_status(StatusCode::UNAUTHORIZED)
+        }
+        RefreshRotation::Expired => {
+            ApiResponse::error_with_code("Session has expired. Please log in again.", "REFRESH_TOKEN_EXPIRED")
+                .with_status(StatusCode::UNAUTHORIZED)
+        }
+        RefreshRotation::NotFound => {
+            ApiResponse::error_with_code("Invalid refresh token", "REFRESH_TOKEN_INVALID")
+                .with_status(StatusCode::UNAUTHORIZED)
+        }
+    }
+}
 
-    let (results, _res) = ldap
-        .search(&config.base_dn, Scope::Subtree, &search_filter, vec![
-            "cn", "department", "displayName", "givenName", "sAMAccountName",
-            "company", "title", "organizationalUnit", "ou", "description", 
-            "physicalDeliveryOfficeName", "division", "departmentNumber"
-        ])
-        .await?
-        .success()?;
+/// Log out by revoking the whole token family (all refresh tokens and outstanding access tokens)
+#[instrument(skip(state, headers, request))]
+async fn logout(
+    State(state): State<AppState>,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
+    request: Option<Json<LogoutRequest>>,
+) -> ApiResponse<bool> {
+    let request = request.map(|Json(r)| r).unwrap_or_default();
+
+    // Prefer the refresh token (works even after the access token has expired)
+    let mut session = None;
+    if let Some(refresh_token) = request.refresh_token.as_deref() {
+        let token_hash = AuthService::hash_refresh_token(refresh_token);
+        match state.database.find_refresh_token_family(&token_hash).await {
+            Ok(found) => session = found,
+            Err(e) => {
+                error!("❌ Failed to look up refresh token for logout: {}", e);
+                return ApiResponse::error("Failed to log out");
+            }
+        }
+    }
 
-    info!("📊 LDAP search completed - Found {} results", results.len());
+    if session.is_none() {
+        let auth_header = headers.get("authorization").and_then(|h| h.to_str().ok());
+        session = AuthService::extract_token_from_header(auth_header)
+            .and_then(|token| state.auth_service.verify_token(token).ok())
+            .map(|claims| (claims.fid, claims.username));
+    }
 
-    // If primary search failed, try alternative search strategies
-    let final_results = if results.is_empty() {
-        info!("⚠️  Primary search failed, trying alternative search strategies...");
-        
-        // Try searching with just the username part (before @)
-        let alt_username = if username.contains('@') {
-            username.split('@').next().unwrap_or(username)
-        } else {
-            username
-        };
-        
-        let alt_filter = format!("(sAMAccountName={alt_username})");
-        info!("🔄 Trying alternative search - Filter: '{}'", alt_filter);
-        
-        let (alt_results, _) = ldap
-            .search(&config.base_dn, Scope::Subtree, &alt_filter, vec![
-                "cn", "department", "displayName", "givenName", "sAMAccountName",
-                "company", "title", "organizationalUnit", "ou", "description", 
-                "physicalDeliveryOfficeName", "division", "departmentNumber"
-            ])
-            .await?
-            .success()?;
-            
-        info!("🔄 Alternative search completed - Found {} results", alt_results.len());
-        alt_results
-    } else {
-        results
+    let Some((family_id, username)) = session else {
+        return ApiResponse::error_with_code("No active session to log out", "SESSION_NOT_FOUND")
+            .with_status(StatusCode::NOT_FOUND);
     };
 
-    let user = if let Some(entry) = final_results.into_iter().next() {
-        let search_entry = SearchEntry::construct(entry);
-        
-        // Debug logging: see what LDAP returns
-        info!("LDAP attributes for {}: {:?}", username, search_entry.attrs);
-        
-        // Extract clean username from email format
-        let clean_username = if username.contains('@') {
-            username.split('@').next().unwrap_or(username).to_string()
-        } else {
-            username.to_string()
-        };
-        
-        // Get display name with improved fallback logic
-        let display_name = search_entry
-            .attrs
-            .get("displayName")
-            .or_else(|| search_entry.attrs.get("cn"))
-            .and_then(|v| v.first())
-            .cloned()
-            .unwrap_or_else(|| {
-                // Better fallback: capitalize first letter of clean username
-                let mut chars = clean_username.chars();
-                match chars.next() {
-                    None => clean_username.clone(),
-                    Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
-                }
-            });
+    state.auth_service.revoke_family(&family_id);
+    match state.database.revoke_refresh_token_family(&family_id, RevocationReason::Logout).await {
+        Ok(_) => {
+            info!("👋 Session {} logged out", family_id);
+            state.security_audit.record(
+                SecurityEvent::new(SecurityEventType::Logout, AuditOutcome::Success)
+                    .username(username)
+                    .source_ip(client_ip(&headers, peer))
+                    .detail(format!("Session {family_id}")),
+            );
+            ApiResponse::success(true, "Logged out successfully")
+        }
+        Err(e) => {
+            error!("❌ Failed to revoke session {}: {}", family_id, e);
+            ApiResponse::error("Failed to log out")
+        }
+    }
+}
 
-        let first_name = search_entry
-            .attrs
-            .get("givenName")
-            .and_then(|v| v.first())
-            .cloned();
+/// Why the SQL fallback rejected a login
+#[derive(Debug, thiserror::Error)]
+enum SqlAuthError {
+    #[error("Authentication table 'tbl_user' not found in current database")]
+    TableMissing,
 
-        // Enhanced department extraction with multiple fallback options
-        info!("🔍 All LDAP attributes for {}: {:#?}", username, search_entry.attrs);
-        
-        let department = search_entry.attrs.get("department")
-            .and_then(|v| v.first())
-            .cloned()
-            .or_else(|| {
-                info!("📋 'department' field empty, trying 'company'");
-                search_entry.attrs.get("company").and_then(|v| v.first()).cloned()
-            })
-            .or_else(|| {
-                info!("📋 'company' field empty, trying 'title'");
-                search_entry.attrs.get("title").and_then(|v| v.first()).cloned()
-            })
-            .or_else(|| {
-                info!("📋 'title' field empty, trying 'organizationalUnit'");
-                search_entry.attrs.get("organizationalUnit").and_then(|v| v.first()).cloned()
-            })
-            .or_else(|| {
-                info!("📋 'organizationalUnit' field empty, trying 'ou'");
-                search_entry.attrs.get("ou").and_then(|v| v.first()).cloned()
-            })
-            .or_else(|| {
-                info!("📋 'ou' field empty, trying 'division'");
-                search_entry.attrs.get("division").and_then(|v| v.first()).cloned()
-            })
-            .or_else(|| {
-                info!("📋 'division' field empty, trying 'physicalDeliveryOfficeName'");
-                search_entry.attrs.get("physicalDeliveryOfficeName").and_then(|v| v.first()).cloned()
-            })
-            .or_else(|| {
-                info!("📋 'physicalDeliveryOfficeName' field empty, trying 'description'");
-                search_entry.attrs.get("description").and_then(|v| v.first()).cloned()
-            });
+    #[error("User not found")]
+    UserNotFound,
 
-        info!("✅ LDAP user created: username='{}', display_name='{}', first_name='{:?}', department='{:?}'", clean_username, display_name, first_name, department);
-        
-        if department.is_none() {
-            info!("⚠️  No department information found in any AD attribute for user: {}", username);
-        } else {
-            info!("🎯 Department successfully extracted: '{:?}'", department);
-        }
+    #[error("Invalid password")]
+    InvalidPassword,
 
-        User {
-            user_id: clean_username.clone(),
-            username: clean_username.clone(),
-            email: format!("{clean_username}@nwfth.com"),
-            display_name,
-            is_active: true,
-        }
-    } else {
-        // Extract clean username for fallback case too
-        let clean_username = if username.contains('@') {
-            username.split('@').next().unwrap_or(username).to_string()
-        } else {
-            username.to_string()
-        };
-        
-        // Capitalize first letter for display
-        let display_name = {
-            let mut chars = clean_username.chars();
-            match chars.next() {
-                None => clean_username.clone(),
-                Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
-            }
-        };
+    #[error("SQL error: {0}")]
+    Sql(#[from] tiberius::error::Error),
 
-        User {
-            user_id: clean_username.clone(),
-            username: clean_username.clone(),
-            email: format!("{clean_username}@nwfth.com"),
-            display_name,
-            is_active: true,
-        }
-    };
+    #[error("{0:#}")]
+    Database(#[from] anyhow::Error),
+}
 
-    ldap.unbind().await?;
-    Ok(user)
+impl SqlAuthError {
+    /// Only wrong credentials count towards the login throttle; infrastructure faults do not
+    fn is_credential_failure(&self) -> bool {
+        matches!(self, Self::UserNotFound | Self::InvalidPassword)
+    }
+
+    fn error_code(&self) -> &'static str {
+        if self.is_credential_failure() {
+            "INVALID_CREDENTIALS"
+        } else {
+            "AUTH_SERVICE_UNAVAILABLE"
+        }
+    }
 }
 
 async fn authenticate_sql(
     state: &AppState,
     username: &str,
     password: &str,
-) -> Result<User, Box<dyn std::error::Error + Send + Sync>> {
+) -> Result<User, SqlAuthError> {
     // Check if tbl_user table exists before attempting authentication
     if !state.database.table_exists("tbl_user").await? {
-        return Err("Authentication table 'tbl_user' not found in current database".into());
+        return Err(SqlAuthError::TableMissing);
     }
 
-    let query = r#"
+    // wms_roles is added by migrations/003_user_roles.sql; older databases fall back to picker-only
+    let has_roles_column = state.database.column_exists("tbl_user", "wms_roles").await.unwrap_or(false);
+    let query = if has_roles_column {
+        r#"
+        SELECT uname, pword, Fname, Lname, department, wms_roles
+        FROM tbl_user
+        WHERE uname = @P1 AND ad_enabled = 1
+    "#
+    } else {
+        r#"
         SELECT uname, pword, Fname, Lname, department
         FROM tbl_user
         WHERE uname = @P1 AND ad_enabled = 1
-    "#;
+    "#
+    };
 
     let mut client = state.database.get_client().await?;
     let mut query_builder = TiberiusQuery::new(query);
@@ -472,8 +579,12 @@ async fn authenticate_sql(
     if let Some(row) = rows.first().and_then(|r| r.first()) {
         let stored_password: &str = row.get("pword").unwrap_or("");
         
-        // Simple plain text password comparison
-        if password == stored_password {
+        // Argon2id verification; legacy plaintext rows are compared in constant time and upgraded on success
+        if let PasswordCheck::Valid { needs_rehash } = verify_password(password, stored_password) {
+            if needs_rehash {
+                upgrade_plaintext_password(state, username, stored_password, password).await;
+            }
+
             let fname: Option<&str> = row.get("Fname");
             let lname: Option<&str> = row.get("Lname");
             
@@ -485,6 +596,11 @@ async fn authenticate_sql(
                 (None, None) => username.to_string(), // Fallback to username
             };
             let _department: Option<&str> = row.get("department");
+            let roles = if has_roles_column {
+                roles_from_column(row.get("wms_roles"))
+            } else {
+                vec![Role::Picker]
+            };
 
             Ok(User {
                 user_id: username.to_string(),
@@ -492,12 +608,63 @@ async fn authenticate_sql(
                 email: format!("{username}@nwfth.com"),
                 display_name,
                 is_active: true,
+                roles,
             })
         } else {
-            Err("Invalid password".into())
+            Err(SqlAuthError::InvalidPassword)
         }
     } else {
-        Err("User not found".into())
+        Err(SqlAuthError::UserNotFound)
+    }
+}
+
+/// Outcome of re-reading an account when its session is refreshed
+enum RefreshRoles {
+    Current(Vec<Role>),
+    /// Neither AD nor tbl_user could be asked - keep the roles granted at login
+    Unchanged,
+    AccountDisabled,
+}
+
+/// Current roles of a refreshing user: AD group membership via the service account, then
+/// tbl_user.wms_roles. A disabled account (in AD or `ad_enabled = 0`) ends the session, as does
+/// an AD account that no longer exists and has no tbl_user row.
+async fn current_roles(state: &AppState, username: &str) -> anyhow::Result<RefreshRoles> {
+    let directory = match lookup_ldap_account(&state.ldap_config, username).await {
+        Ok(directory) => directory,
+        Err(e) => {
+            warn!("⚠️ LDAP account lookup failed for {}: {}", username, e);
+            None
+        }
+    };
+    if directory == Some(DirectoryAccount::Disabled) || state.database.is_user_disabled(username).await? {
+        return Ok(RefreshRoles::AccountDisabled);
+    }
+
+    if let Some(DirectoryAccount::Active(roles)) = directory {
+        return Ok(RefreshRoles::Current(roles));
+    }
+
+    Ok(match state.database.get_user_roles(username).await? {
+        Some(roles) => RefreshRoles::Current(roles),
+        None if directory == Some(DirectoryAccount::NotFound) => RefreshRoles::AccountDisabled,
+        None => RefreshRoles::Unchanged,
+    })
+}
+
+/// Replace a legacy plaintext tbl_user password with its Argon2id hash after a successful login.
+/// Failures are logged but never block the login.
+async fn upgrade_plaintext_password(state: &AppState, username: &str, stored_password: &str, password: &str) {
+    let hash = match hash_password(password) {
+        Ok(hash) => hash,
+        Err(e) => {
+            warn!("⚠️ Failed to hash password for {}: {}", username, e);
+            return;
+        }
+    };
+
+    if let Err(e) = state.database.update_user_password_hash(username, stored_password, &hash).await {
+        warn!("⚠️ Failed to upgrade plaintext password for {} (is tbl_user.pword wide enough?): {}", username, e);
     }
 }
 
@@ -589,6 +756,23 @@ async fn main() {
     // Load environment variables from .env file
     dotenv::dotenv().ok();
 
+    // Administrative commands run instead of the server
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(command) = args.first() {
+        let database = database::Database::new().await.expect("Failed to initialize database with connection pool");
+        let result = match command.as_str() {
+            "rehash-passwords" => {
+                commands::rehash_passwords::run(&database, args.iter().any(|a| a == "--dry-run")).await
+            }
+            other => Err(anyhow::anyhow!("Unknown command '{other}'. Available commands: rehash-passwords [--dry-run]")),
+        };
+        if let Err(e) = result {
+            eprintln!("❌ {e:#}");
+            std::process::exit(1);
+        }
+        return;
+    }
+
     // Server configuration
     let host = std::env::var("SERVER_HOST").unwrap_or_else(|_| "0.0.0.0".to_string());
     let port = std::env::var("SERVER_PORT")
@@ -605,19 +789,23 @@ async fn main() {
     // Database configuration now handled by Database::new() using PRIMARY_DB/REPLICA_DB environment variables
 
     // LDAP configuration
-    let ldap_config = LdapConfig {
-        url: std::env::var("LDAP_URL").unwrap_or_else(|_| "ldap://192.168.0.1".to_string()),
-        base_dn: std::env::var("LDAP_BASE_DN").unwrap_or_else(|_| "DC=NWFTH,DC=com".to_string()),
-        enabled: std::env::var("LDAP_ENABLED")
-            .unwrap_or_else(|_| "true".to_string())
-            .parse()
-            .unwrap_or(true),
-    };
-
+    let ldap_config = LdapConfig::from_env().expect("Invalid LDAP configuration");
     info!(
-        "LDAP configured: {} with base DN: {}",
-        ldap_config.url, ldap_config.base_dn
+        "LDAP configured: {} with base DN: {} (UPN suffixes: {:?}, StartTLS: {}, service account: {})",
+        ldap_config.url,
+        ldap_config.base_dn,
+        ldap_config.upn_suffixes,
+        ldap_config.starttls,
+        ldap_config.uses_service_account()
     );
+    if ldap_config.role_groups.is_empty() {
+        warn!("⚠️  LDAP_ROLE_GROUPS not set - LDAP users will only have the picker role");
+    } else {
+        info!("🛡️ LDAP role mapping: {:?}", ldap_config.role_groups);
+    }
+
+    // Initialize authentication service first - invalid or default JWT keys should stop startup immediately
+    let auth_service = AuthService::new().expect("Failed to initialize JWT authentication service");
 
     // Initialize database connection with pooling
     let database = database::Database::new().await.expect("Failed to initialize database with connection pool");
@@ -641,8 +829,23 @@ async fn main() {
         }
     }
 
-    // Initialize authentication service
-    let auth_service = AuthService::new().expect("Failed to initialize JWT authentication service");
+    // Warm the revocation cache so access tokens of recently logged-out sessions stay rejected after a restart
+    let revocation_window = auth_service.access_token_duration();
+    match database
+        .get_recently_revoked_token_families(revocation_window.num_seconds())
+        .await
+    {
+        Ok(families) => {
+            for (family_id, revoked_at) in &families {
+                auth_service.remember_revoked_family(family_id, revoked_at + revocation_window.num_seconds());
+            }
+            info!("🔒 Loaded {} recently revoked session(s)", families.len());
+        }
+        Err(e) => {
+            warn!("⚠️  Failed to load revoked sessions: {}", e);
+            warn!("    Run migrations/002_auth_refresh_tokens.sql to enable refresh tokens and logout");
+        }
+    }
 
     // Determine static assets path at startup for better performance
     let static_assets_path = {
@@ -665,26 +868,37 @@ async fn main() {
         selected_path
     };
 
+    let security_audit = SecurityAudit::from_env(database.clone());
     let state = AppState {
         database,
         ldap_config,
         auth_service,
+        login_throttle: LoginThrottle::new(ThrottlePolicy::from_env()),
+        security_audit,
+        run_events: RunEventBus::from_env(),
+        scan_policy: ScanVerifyPolicy::from_env(),
+        sscc_config: SsccConfig::from_env(),
         static_assets_path,
     };
 
+    // Send queued labels to network printers
+    spawn_print_worker(state.database.clone());
+
     // Configure CORS with environment-based origins
     let cors = if cors_origins == "*" {
         CorsLayer::new()
             .allow_origin(Any)
             .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, header::HeaderName::from_static("x-user-id")])
+            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, REQUEST_ID_HEADER, IDEMPOTENCY_KEY_HEADER])
+            .expose_headers([REQUEST_ID_HEADER])
     } else {
         // For specific origins, use Any for now (can be enhanced later)
         info!("CORS configured for specific origins: {}", cors_origins);
         CorsLayer::new()
             .allow_origin(Any)
             .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
-            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, header::HeaderName::from_static("x-user-id")])
+            .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION, REQUEST_ID_HEADER, IDEMPOTENCY_KEY_HEADER])
+            .expose_headers([REQUEST_ID_HEADER])
     };
 
     // Build our application with routes
@@ -695,7 +909,39 @@ async fn main() {
         .route("/api/auth/health", get(auth_health))
         .route("/api/auth/login", post(login))
         .route("/api/auth/status", get(auth_status))
-        // Bulk runs routes with Database state and JWT protection
+        .route("/api/auth/refresh", post(refresh_token))
+        .route("/api/auth/logout", post(logout))
+        .route("/api/auth/jwks", get(auth_handlers::jwks))
+        .route("/.well-known/jwks.json", get(auth_handlers::jwks))
+        // Login lockout management (supervisor only)
+        .nest(
+            "/api/auth/lockouts",
+            Router::new()
+                .route("/", get(auth_handlers::list_lockouts))
+                .route("/clear", post(auth_handlers::clear_lockout))
+                .layer(from_fn(require_supervisor))
+                .layer(from_fn_with_state(state.clone(), jwt_auth_middleware)),
+        )
+        // Login/security audit trail (supervisor only)
+        .route(
+            "/api/auth/security-events",
+            get(auth_handlers::list_security_events)
+                .route_layer(from_fn(require_supervisor))
+                .route_layer(from_fn_with_state(state.clone(), jwt_auth_middleware)),
+        )
+        // Service account API keys (admin only)
+        .nest(
+            "/api/auth/api-keys",
+            Router::new()
+                .route(
+                    "/",
+                    get(api_key_handlers::list_api_keys).post(api_key_handlers::create_api_key),
+                )
+                .route("/{key_id}/revoke", post(api_key_handlers::revoke_api_key))
+                .layer(from_fn(require_admin))
+                .layer(from_fn_with_state(state.clone(), jwt_auth_middleware)),
+        )
+        // Bulk runs routes with Database state and JWT protection (API keys: runs:read for GET)
         .nest(
             "/api/bulk-runs",
             Router::new()
@@ -713,8 +959,20 @@ async fn main() {
                 )
                 .route("/{run_no}/completion", get(bulk_runs::check_run_completion))
                 .route("/{run_no}/completion-status", get(bulk_runs::check_run_completion_status))
+                // Pickers' clients move a run from NEW to PRINT themselves
                 .route("/{run_no}/complete", put(bulk_runs::complete_run_status))
                 .route("/{run_no}/status", get(bulk_runs::get_run_status))
+                .route("/{run_no}/events", get(bulk_runs::run_events))
+                .route(
+                    "/{run_no}/claim",
+                    post(bulk_runs::claim_run).delete(bulk_runs::release_run_claim),
+                )
+                .route("/{run_no}/claims", get(bulk_runs::get_run_claims))
+                .route(
+                    "/{run_no}/pick-plan",
+                    get(bulk_runs::get_pick_plan).post(bulk_runs::generate_pick_plan),
+                )
+                .route("/{run_no}/pick-plan/preview", get(bulk_runs::preview_pick_plan))
                 .route("/{run_no}/search-items", get(bulk_runs::search_run_items))
                 .route("/{run_no}/ingredient-index", get(bulk_runs::get_ingredient_index))
                 .route("/{run_no}/ingredient-by-coordinates", get(bulk_runs::get_ingredient_by_coordinates))
@@ -722,7 +980,20 @@ async fn main() {
                 .route("/{run_no}/lots/{lot_no}/bins", get(bulk_runs::get_lot_bins))
                 .route("/{run_no}/pallets", get(bulk_runs::get_pallet_tracking_data))
                 .route("/{run_no}/confirm-pick", post(bulk_runs::confirm_pick))
-                .route("/{run_no}/debug-validation", post(bulk_runs::debug_validation))
+                .route("/{run_no}/scan", get(scans::resolve_pick_scan))
+                .route(
+                    "/{run_no}/scan-exceptions",
+                    get(bulk_runs::get_scan_exceptions).route_layer(from_fn(require_supervisor)),
+                )
+                .route("/offline-picks/sync", post(bulk_runs::sync_offline_picks))
+                .route(
+                    "/{run_no}/debug-validation",
+                    post(bulk_runs::debug_validation).route_layer(from_fn(require_supervisor)),
+                )
+                .route("/{run_no}/pallet/{row_num}/label", get(bulk_runs::get_pallet_label))
+                .route("/{run_no}/pallet/{row_num}/sscc", post(bulk_runs::assign_pallet_sscc))
+                .route("/{run_no}/pallet/{row_num}/print-jobs", post(printing::queue_pallet_labels))
+                .route("/pallets/sscc/{sscc}", get(bulk_runs::lookup_pallet_by_sscc))
                 .route("/{run_no}/pallet/{row_num}/{line_id}/completion", get(bulk_runs::check_pallet_completion))
                 .route("/{run_no}/pallet/{row_num}/{line_id}/next", get(bulk_runs::get_next_pallet))
                 .route(
@@ -733,13 +1004,52 @@ async fn main() {
                 .route("/{run_no}/all-picked-lots", get(bulk_runs::get_all_picked_lots_for_run))
                 .route("/{run_no}/batch-weight-summary", get(bulk_runs::get_batch_weight_summary))
                 .route("/{run_no}/lot-details", get(bulk_runs::get_run_lot_details))
+                .route("/{run_no}/labels", get(bulk_runs::get_run_labels))
+                .route("/{run_no}/labels/{batch_no}", get(bulk_runs::get_batch_label))
                 .route("/{run_no}/{row_num}/{line_id}/unpick", post(bulk_runs::unpick_ingredient))
-                .route("/{run_no}/unpick-all", post(bulk_runs::unpick_all_run_lots))
-                .route("/{run_no}/revert-status", post(bulk_runs::revert_run_status))
+                .route(
+                    "/{run_no}/unpick-all",
+                    post(bulk_runs::unpick_all_run_lots).route_layer(from_fn(require_supervisor)),
+                )
+                .route(
+                    "/{run_no}/revert-status",
+                    post(bulk_runs::revert_run_status).route_layer(from_fn(require_supervisor)),
+                )
                 .route("/{run_no}/print-status", put(bulk_runs::update_print_status))
+                .route("/{run_no}/print-jobs", post(printing::queue_run_labels))
                 .route("/health", get(bulk_runs::bulk_runs_health))
                 .layer(from_fn_with_state(state.clone(), jwt_auth_middleware))
-                .with_state(state.database.clone()),
+                .with_state(state.clone()),
+        )
+        // Network label printers and their print job queue
+        .nest(
+            "/api/printers",
+            Router::new()
+                .route(
+                    "/",
+                    get(printing::list_printers)
+                        .merge(post(printing::register_printer).route_layer(from_fn(require_supervisor))),
+                )
+                .layer(from_fn_with_state(state.clone(), jwt_auth_middleware))
+                .with_state(state.clone()),
+        )
+        .nest(
+            "/api/print-jobs",
+            Router::new()
+                .route("/", get(printing::list_print_jobs))
+                .route("/{job_id}", get(printing::get_print_job))
+                .route("/{job_id}/retry", post(printing::retry_print_job))
+                .route("/{job_id}/reprint", post(printing::reprint_job))
+                .layer(from_fn_with_state(state.clone(), jwt_auth_middleware))
+                .with_state(state.clone()),
+        )
+        // Supplier GTINs behind GS1 label scans
+        .nest(
+            "/api/item-gtins",
+            Router::new()
+                .route("/", post(scans::register_item_gtin).route_layer(from_fn(require_supervisor)))
+                .layer(from_fn_with_state(state.clone(), jwt_auth_middleware))
+                .with_state(state.clone()),
         )
         // Add putaway routes with Database state and JWT protection
         .nest(
@@ -752,6 +1062,7 @@ async fn main() {
         .nest_service("/assets", ServeDir::new(format!("{}/assets", state.static_assets_path)))
         .fallback(handle_spa_or_static)
         .layer(cors)
+        .layer(from_fn(request_id_middleware))
         .with_state(state.clone());
 
     let listener = tokio::net::TcpListener::bind(&format!("{host}:{port}"))
@@ -762,7 +1073,8 @@ async fn main() {
     info!("📁 Serving static files from {}", state.static_assets_path);
     info!("🔧 API endpoints available at http://{}:{}/api/", host, port);
     
-    axum::serve(listener, app)
+    // Connect info gives handlers the peer address (login throttling per client IP)
+    axum::serve(listener, app.into_make_service_with_connect_info::<SocketAddr>())
         .await
         .expect("Server failed to start");
 }
\ No newline at end of file
@@ -1,12 +1,21 @@
 use axum::{
-    extract::{Request, State},
-    http::StatusCode,
+    extract::{ConnectInfo, FromRequestParts, OriginalUri, Request, State},
+    http::{request::Parts, Method, StatusCode},
     middleware::Next,
     response::Response,
 };
 use axum::http::HeaderMap;
-use tracing::{debug, warn};
+use chrono::Utc;
+use std::net::SocketAddr;
+use tracing::{debug, error, info, warn};
 
+use crate::models::api_keys::{ApiKeyPrincipal, ApiScope};
+use crate::models::security_audit::{AuditOutcome, AuthMethod, SecurityEvent, SecurityEventType};
+use crate::types::{ApiResponse, Role};
+use crate::utils::api_keys::{api_key_id, api_key_matches, is_api_key};
+use crate::utils::auth::Claims;
+use crate::utils::client_ip::client_ip;
+use crate::utils::roles::has_any_role;
 use crate::{AppState, utils::AuthService};
 
 /// JWT authentication middleware
@@ -16,17 +25,81 @@ pub async fn jwt_auth_middleware(
     headers: HeaderMap,
     mut request: Request,
     next: Next,
-) -> Result<Response, StatusCode> {
+) -> Result<Response, ApiResponse<()>> {
     // Extract Authorization header
     let auth_header = headers.get("authorization")
         .and_then(|h| h.to_str().ok());
+    let bearer = AuthService::extract_token_from_header(auth_header);
+
+    // Service accounts authenticate with an API key (X-API-Key or Bearer bpk_...)
+    let api_key = headers
+        .get("x-api-key")
+        .and_then(|h| h.to_str().ok())
+        .or(bearer.filter(|token| is_api_key(token)));
+    let source_ip = request
+        .extensions()
+        .get::<ConnectInfo<SocketAddr>>()
+        .map(|ConnectInfo(peer)| client_ip(&headers, *peer));
+    // Nested routers see a stripped URI; scopes and audit details use the full path
+    let path = request
+        .extensions()
+        .get::<OriginalUri>()
+        .map(|uri| uri.path().to_string())
+        .unwrap_or_else(|| request.uri().path().to_string());
+    let route = format!("{} {}", request.method(), path);
+    let rejection = |method: AuthMethod, event_type: SecurityEventType, detail: String| {
+        let mut event = SecurityEvent::new(event_type, AuditOutcome::Failure)
+            .method(method)
+            .detail(format!("{detail} ({route})"));
+        if let Some(ip) = source_ip {
+            event = event.source_ip(ip);
+        }
+        event
+    };
+
+    if let Some(api_key) = api_key {
+        let principal = match authenticate_api_key(&state, api_key).await {
+            Ok(principal) => principal,
+            Err((status, reason)) => {
+                if status == StatusCode::UNAUTHORIZED {
+                    state.security_audit.record(rejection(AuthMethod::ApiKey, SecurityEventType::ApiKeyRejected, reason));
+                    return Err(auth_error(status, "Invalid API key"));
+                }
+                return Err(auth_error(status, "API key could not be verified"));
+            }
+        };
+
+        let allowed = required_scope(request.method(), &path)
+            .is_some_and(|scope| principal.scopes.contains(&scope));
+        if !allowed {
+            warn!(
+                "🚫 API key {} ('{}') with scopes {:?} attempted {} {}",
+                principal.key_id, principal.name, principal.scopes, request.method(), path
+            );
+            state.security_audit.record(
+                rejection(
+                    AuthMethod::ApiKey,
+                    SecurityEventType::ApiKeyRejected,
+                    format!("Key {} lacks the scope for this route", principal.key_id),
+                )
+                .username(principal.audit_user.clone()),
+            );
+            return Err(auth_error(StatusCode::FORBIDDEN, "API key is not allowed to access this route"));
+        }
+
+        debug!("✅ API key {} ('{}') authorized for {} {}", principal.key_id, principal.name, request.method(), path);
+        request.headers_mut().remove("x-user-id");
+        request.headers_mut().remove("x-username");
+        request.extensions_mut().insert(principal);
+        return Ok(next.run(request).await);
+    }
 
     // Extract token from header
-    let token = match AuthService::extract_token_from_header(auth_header) {
+    let token = match bearer {
         Some(token) => token,
         None => {
             warn!("🚫 JWT Auth: No Authorization header provided");
-            return Err(StatusCode::UNAUTHORIZED);
+            return Err(auth_error(StatusCode::UNAUTHORIZED, "Authentication required"));
         }
     };
 
@@ -34,23 +107,277 @@ pub async fn jwt_auth_middleware(
     match state.auth_service.verify_token(token) {
         Ok(claims) => {
             debug!("✅ JWT Auth: Valid token for user: {}", claims.username);
-            
-            // Add user information to request headers for downstream handlers
-            request.headers_mut().insert(
-                "x-user-id", 
-                claims.sub.parse().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-            );
-            request.headers_mut().insert(
-                "x-username", 
-                claims.username.parse().map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
-            );
+
+            // Verified claims for downstream handlers (`AuthenticatedUser`) and permission checks.
+            // Client-supplied identity headers are never trusted.
+            request.headers_mut().remove("x-user-id");
+            request.headers_mut().remove("x-username");
+            request.extensions_mut().insert(claims);
             
             Ok(next.run(request).await)
         }
         Err(e) => {
             warn!("🚫 JWT Auth: Invalid token - {}", e);
-            Err(StatusCode::UNAUTHORIZED)
+            state
+                .security_audit
+                .record(rejection(AuthMethod::Jwt, SecurityEventType::TokenRejected, e.to_string()));
+            Err(auth_error(StatusCode::UNAUTHORIZED, "Invalid or expired token"))
         }
     }
 }
 
+/// Rejection envelope for authentication and permission failures
+fn auth_error(status: StatusCode, message: &str) -> ApiResponse<()> {
+    let error_code = match status {
+        StatusCode::UNAUTHORIZED => "UNAUTHORIZED",
+        StatusCode::FORBIDDEN => "FORBIDDEN",
+        _ => "AUTH_SERVICE_UNAVAILABLE",
+    };
+    ApiResponse::error_with_code(message, error_code).with_status(status)
+}
+
+/// Verify an API key against its stored hash and expiry
+/// Errors carry the HTTP status and the reason recorded in the security audit.
+async fn authenticate_api_key(
+    state: &AppState,
+    api_key: &str,
+) -> Result<ApiKeyPrincipal, (StatusCode, String)> {
+    let Some(key_id) = api_key_id(api_key.trim()) else {
+        warn!("🚫 API key auth: malformed key");
+        return Err((StatusCode::UNAUTHORIZED, "Malformed API key".to_string()));
+    };
+
+    let record = match state.database.find_api_key(key_id).await {
+        Ok(Some(record)) => record,
+        Ok(None) => {
+            warn!("🚫 API key auth: unknown key {}", key_id);
+            return Err((StatusCode::UNAUTHORIZED, format!("Unknown API key {key_id}")));
+        }
+        Err(e) => {
+            error!("❌ API key lookup failed for {}: {}", key_id, e);
+            return Err((StatusCode::SERVICE_UNAVAILABLE, format!("API key lookup failed: {e}")));
+        }
+    };
+
+    if !api_key_matches(api_key.trim(), &record.key_hash) {
+        warn!("🚫 API key auth: secret mismatch for key {}", key_id);
+        return Err((StatusCode::UNAUTHORIZED, format!("Secret mismatch for API key {key_id}")));
+    }
+    if record.revoked_at.is_some() {
+        warn!("🚫 API key auth: key {} ('{}') is revoked", key_id, record.name);
+        return Err((StatusCode::UNAUTHORIZED, format!("API key {key_id} is revoked")));
+    }
+    if record.expires_at.is_some_and(|expires| expires <= Utc::now().naive_utc()) {
+        warn!("🚫 API key auth: key {} ('{}') has expired", key_id, record.name);
+        return Err((StatusCode::UNAUTHORIZED, format!("API key {key_id} has expired")));
+    }
+
+    // Usage tracking must never slow down or fail the request
+    let database = state.database.clone();
+    let touched_key = record.key_id.clone();
+    tokio::spawn(async move {
+        if let Err(e) = database.touch_api_key(&touched_key).await {
+            warn!("⚠️ Failed to record API key usage for {}: {}", touched_key, e);
+        }
+    });
+
+    info!("🔑 API key {} ('{}') authenticated as {}", record.key_id, record.name, record.audit_user);
+    Ok(ApiKeyPrincipal {
+        key_id: record.key_id,
+        name: record.name,
+        audit_user: record.audit_user,
+        scopes: record.scopes,
+    })
+}
+
+/// Scope an API key needs for a route; None means the route is not open to API keys
+pub fn required_scope(method: &Method, path: &str) -> Option<ApiScope> {
+    let path = path.trim_end_matches('/');
+    if path.starts_with("/api/bulk-runs/") && method == Method::GET {
+        Some(ApiScope::RunsRead)
+    } else if path == "/api/putaway/transfer" && method == Method::POST {
+        Some(ApiScope::PutawayTransfer)
+    } else if path.starts_with("/api/putaway/") && method == Method::GET {
+        Some(ApiScope::PutawayRead)
+    } else {
+        None
+    }
+}
+
+/// The caller's identity, taken from the claims verified by `jwt_auth_middleware`
+/// (or the API key's audit user for service accounts).
+/// Use this for every audit column (RecUserid, ModifiedBy) - never request bodies or headers.
+#[derive(Debug, Clone)]
+pub struct AuthenticatedUser {
+    pub username: String,
+    /// Empty for service accounts
+    pub roles: Vec<Role>,
+}
+
+impl AuthenticatedUser {
+    /// For in-handler overrides on routes open to every picker
+    pub fn is_supervisor(&self) -> bool {
+        has_any_role(&self.roles, SUPERVISOR_ROLES)
+    }
+}
+
+impl From<&Claims> for AuthenticatedUser {
+    fn from(claims: &Claims) -> Self {
+        Self {
+            username: claims.username.clone(),
+            roles: claims.roles.clone(),
+        }
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for AuthenticatedUser {
+    type Rejection = ApiResponse<()>;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Self::Rejection> {
+        if let Some(claims) = parts.extensions.get::<Claims>() {
+            return Ok(Self::from(claims));
+        }
+        match parts.extensions.get::<ApiKeyPrincipal>() {
+            Some(principal) => Ok(Self {
+                username: principal.audit_user.clone(),
+                roles: Vec::new(),
+            }),
+            None => {
+                warn!("🚫 No verified claims for {} - route is missing jwt_auth_middleware", parts.uri.path());
+                Err(auth_error(StatusCode::UNAUTHORIZED, "Authentication required"))
+            }
+        }
+    }
+}
+
+/// Roles allowed to manage service accounts and API keys
+const ADMIN_ROLES: &[Role] = &[Role::Admin];
+
+/// Roles allowed to perform destructive bulk-run operations (revert, unpick whole run, force completion)
+const SUPERVISOR_ROLES: &[Role] = &[Role::Supervisor];
+
+/// Permission middleware: only supervisors (and admins) may continue.
+/// Must run inside `jwt_auth_middleware`, which provides the verified claims.
+pub async fn require_supervisor(request: Request, next: Next) -> Result<Response, ApiResponse<()>> {
+    require_roles(SUPERVISOR_ROLES, request, next).await
+}
+
+/// Permission middleware: only admins may continue
+pub async fn require_admin(request: Request, next: Next) -> Result<Response, ApiResponse<()>> {
+    require_roles(ADMIN_ROLES, request, next).await
+}
+
+async fn require_roles(required: &[Role], request: Request, next: Next) -> Result<Response, ApiResponse<()>> {
+    let Some(claims) = request.extensions().get::<Claims>() else {
+        // Service accounts never hold user roles
+        if let Some(principal) = request.extensions().get::<ApiKeyPrincipal>() {
+            warn!("🚫 API key {} attempted role-protected {}", principal.key_id, request.uri().path());
+            return Err(auth_error(StatusCode::FORBIDDEN, "API keys cannot access this route"));
+        }
+        warn!("🚫 Permission check without authenticated claims for {}", request.uri().path());
+        return Err(auth_error(StatusCode::UNAUTHORIZED, "Authentication required"));
+    };
+
+    if !has_any_role(&claims.roles, required) {
+        warn!(
+            "🚫 Forbidden: user {} with roles {:?} attempted {} {} (requires {:?})",
+            claims.username, claims.roles, request.method(), request.uri().path(), required
+        );
+        return Err(auth_error(StatusCode::FORBIDDEN, "You do not have permission to perform this action"));
+    }
+
+    debug!("✅ Permission granted to {} for {}", claims.username, request.uri().path());
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::bulk_runs::PickConfirmationRequest;
+    use axum::http::Request as HttpRequest;
+
+    fn claims(username: &str) -> Claims {
+        Claims {
+            sub: username.to_string(),
+            username: username.to_string(),
+            email: format!("{username}@nwfth.com"),
+            display_name: username.to_string(),
+            exp: 0,
+            iat: 0,
+            iss: "test".to_string(),
+            jti: "jti".to_string(),
+            fid: "fid".to_string(),
+            roles: vec![Role::Picker],
+        }
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_user_comes_from_verified_claims() {
+        let mut request = HttpRequest::builder()
+            .header("x-user-id", "spoofed")
+            .body(())
+            .unwrap();
+        request.extensions_mut().insert(claims("deachawat"));
+        let (mut parts, _) = request.into_parts();
+
+        let user = AuthenticatedUser::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(user.username, "deachawat");
+    }
+
+    #[tokio::test]
+    async fn test_authenticated_user_rejects_missing_claims() {
+        let (mut parts, _) = HttpRequest::builder()
+            .header("x-user-id", "spoofed")
+            .body(())
+            .unwrap()
+            .into_parts();
+
+        let result = AuthenticatedUser::from_request_parts(&mut parts, &()).await;
+        let rejection = result.unwrap_err();
+        assert_eq!(rejection.status, StatusCode::UNAUTHORIZED);
+        assert_eq!(rejection.error_code.as_deref(), Some("UNAUTHORIZED"));
+    }
+
+    #[tokio::test]
+    async fn test_api_key_principal_uses_audit_user() {
+        let mut request = HttpRequest::builder().body(()).unwrap();
+        request.extensions_mut().insert(ApiKeyPrincipal {
+            key_id: "a1b2c3d4e5f6".to_string(),
+            name: "MES line 2".to_string(),
+            audit_user: "MES2".to_string(),
+            scopes: vec![ApiScope::RunsRead],
+        });
+        let (mut parts, _) = request.into_parts();
+
+        let user = AuthenticatedUser::from_request_parts(&mut parts, &()).await.unwrap();
+        assert_eq!(user.username, "MES2");
+    }
+
+    #[test]
+    fn test_required_scope_for_api_keys() {
+        assert_eq!(
+            required_scope(&Method::GET, "/api/bulk-runs/215/batch-weight-summary"),
+            Some(ApiScope::RunsRead)
+        );
+        assert_eq!(required_scope(&Method::GET, "/api/bulk-runs/215/lot-details"), Some(ApiScope::RunsRead));
+        assert_eq!(required_scope(&Method::POST, "/api/bulk-runs/215/confirm-pick"), None);
+        assert_eq!(required_scope(&Method::POST, "/api/putaway/transfer"), Some(ApiScope::PutawayTransfer));
+        assert_eq!(required_scope(&Method::GET, "/api/putaway/lot/L1"), Some(ApiScope::PutawayRead));
+        assert_eq!(required_scope(&Method::GET, "/api/auth/api-keys"), None);
+    }
+
+    #[test]
+    fn test_pick_request_ignores_body_user_id() {
+        let request: PickConfirmationRequest = serde_json::from_value(serde_json::json!({
+            "row_num": 1,
+            "line_id": 1,
+            "picked_bulk_qty": "2",
+            "lot_no": "LOT1",
+            "bin_no": "BIN1",
+            "user_id": "someoneelse"
+        }))
+        .unwrap();
+
+        assert_eq!(request.user_id, None);
+    }
+}
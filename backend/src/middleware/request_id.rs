@@ -0,0 +1,94 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+/// Header carrying the request id in both directions
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+/// Longest client-supplied id that is echoed back; anything else gets a fresh UUID
+const MAX_REQUEST_ID_LEN: usize = 64;
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// Request id of the request currently being handled, if inside `request_id_middleware`
+pub fn current_request_id() -> Option<String> {
+    REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+/// Tag every request with an id (the caller's `X-Request-Id` or a new UUID).
+/// The id is available to handlers via `current_request_id`, is embedded in
+/// `ApiResponse` bodies and is returned in the `X-Request-Id` response header.
+pub async fn request_id_middleware(mut request: Request, next: Next) -> Response {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| is_valid_request_id(id))
+        .map(str::to_string)
+        .unwrap_or_else(|| uuid::Uuid::new_v4().to_string());
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        request.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+
+    let span = tracing::info_span!("request", request_id = %request_id);
+    let mut response = REQUEST_ID
+        .scope(request_id.clone(), tracing::Instrument::instrument(next.run(request), span))
+        .await;
+
+    if let Ok(value) = HeaderValue::from_str(&request_id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+fn is_valid_request_id(id: &str) -> bool {
+    !id.is_empty()
+        && id.len() <= MAX_REQUEST_ID_LEN
+        && id.chars().all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_id_validation() {
+        assert!(is_valid_request_id("3f2b8c1e-9d4a-4c7e-b1a2-0e5f6d7c8b9a"));
+        assert!(is_valid_request_id("handheld-07.1699"));
+        assert!(!is_valid_request_id(""));
+        assert!(!is_valid_request_id("two words"));
+        assert!(!is_valid_request_id(&"a".repeat(65)));
+    }
+
+    #[tokio::test]
+    async fn test_current_request_id_is_scoped() {
+        assert_eq!(current_request_id(), None);
+        let inside = REQUEST_ID.scope("req-1".to_string(), async { current_request_id() }).await;
+        assert_eq!(inside.as_deref(), Some("req-1"));
+    }
+
+    #[tokio::test]
+    async fn test_api_response_carries_request_id() {
+        use crate::types::ApiResponse;
+        use axum::{http::StatusCode, response::IntoResponse};
+
+        let response = REQUEST_ID
+            .scope("req-2".to_string(), async {
+                ApiResponse::<()>::not_found("Run 1 not found").into_response()
+            })
+            .await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(response.headers()[&REQUEST_ID_HEADER], "req-2");
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(body["request_id"], "req-2");
+        assert_eq!(body["error_code"], "NOT_FOUND");
+    }
+}
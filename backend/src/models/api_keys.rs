@@ -0,0 +1,95 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+
+/// Permission granted to an API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ApiScope {
+    /// Read-only bulk run data (GET /api/bulk-runs/...)
+    #[serde(rename = "runs:read")]
+    RunsRead,
+    /// Read-only putaway lookups (GET /api/putaway/...)
+    #[serde(rename = "putaway:read")]
+    PutawayRead,
+    /// Bin transfers (POST /api/putaway/transfer)
+    #[serde(rename = "putaway:transfer")]
+    PutawayTransfer,
+}
+
+impl ApiScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::RunsRead => "runs:read",
+            Self::PutawayRead => "putaway:read",
+            Self::PutawayTransfer => "putaway:transfer",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().as_str() {
+            "runs:read" => Some(Self::RunsRead),
+            "putaway:read" => Some(Self::PutawayRead),
+            "putaway:transfer" => Some(Self::PutawayTransfer),
+            _ => None,
+        }
+    }
+}
+
+/// Parse the comma separated tbl_api_keys.Scopes column (unknown scopes are ignored)
+pub fn scopes_from_column(value: &str) -> Vec<ApiScope> {
+    let mut scopes = Vec::new();
+    for scope in value.split(',').filter_map(ApiScope::parse) {
+        if !scopes.contains(&scope) {
+            scopes.push(scope);
+        }
+    }
+    scopes
+}
+
+/// Serialize scopes for storage (inverse of `scopes_from_column`)
+pub fn scopes_to_column(scopes: &[ApiScope]) -> String {
+    scopes.iter().map(ApiScope::as_str).collect::<Vec<_>>().join(",")
+}
+
+/// API key metadata as stored in tbl_api_keys (the hash is never returned to clients)
+#[derive(Debug, Clone, Serialize)]
+pub struct ApiKeyRecord {
+    pub key_id: String,
+    #[serde(skip_serializing)]
+    pub key_hash: String,
+    pub name: String,
+    pub audit_user: String,
+    pub scopes: Vec<ApiScope>,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub expires_at: Option<NaiveDateTime>,
+    pub last_used_at: Option<NaiveDateTime>,
+    pub revoked_at: Option<NaiveDateTime>,
+}
+
+/// Request to create an API key (admin only)
+#[derive(Debug, Deserialize)]
+pub struct CreateApiKeyRequest {
+    /// Human readable name, e.g. "MES line 2"
+    pub name: String,
+    /// Identity written to RecUserid/ModifiedBy for changes made with this key (max 8 characters)
+    pub audit_user: String,
+    pub scopes: Vec<ApiScope>,
+    /// Optional lifetime in days (no expiry when absent)
+    pub expires_in_days: Option<i64>,
+}
+
+/// Newly created API key - the only time the plaintext key is returned
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKey {
+    pub api_key: String,
+    pub key: ApiKeyRecord,
+}
+
+/// Verified API key identity, placed in request extensions by `jwt_auth_middleware`
+#[derive(Debug, Clone)]
+pub struct ApiKeyPrincipal {
+    pub key_id: String,
+    pub name: String,
+    pub audit_user: String,
+    pub scopes: Vec<ApiScope>,
+}
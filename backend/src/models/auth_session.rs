@@ -0,0 +1,61 @@
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+
+use crate::types::User;
+
+/// Refresh token freshly minted by `AuthService`, before it is persisted
+#[derive(Debug, Clone)]
+pub struct IssuedRefreshToken {
+    /// Opaque token handed to the client (never stored server-side)
+    pub token: String,
+    /// SHA-256 hex digest of `token`, used as the primary key in storage
+    pub token_hash: String,
+    /// UTC expiry of this refresh token
+    pub expires_at: NaiveDateTime,
+}
+
+/// Session (token family) a refresh token belongs to
+#[derive(Debug, Clone)]
+pub struct RefreshSession {
+    pub family_id: String,
+    pub user: User,
+}
+
+/// Result of presenting a refresh token for rotation
+#[derive(Debug, Clone)]
+pub enum RefreshRotation {
+    /// Token was valid; it has been retired and replaced by the new token
+    Rotated(RefreshSession),
+    /// Token was already rotated or revoked - the whole family is now revoked
+    ReuseDetected { family_id: String, username: String },
+    /// Token exists but is past its expiry
+    Expired,
+    /// Token is unknown
+    NotFound,
+}
+
+/// Why a refresh token family was revoked
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RevocationReason {
+    Rotated,
+    Logout,
+    ReuseDetected,
+    AccountDisabled,
+}
+
+impl RevocationReason {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Rotated => "ROTATED",
+            Self::Logout => "LOGOUT",
+            Self::ReuseDetected => "REUSE_DETECTED",
+            Self::AccountDisabled => "ACCOUNT_DISABLED",
+        }
+    }
+}
+
+/// Request body for `/api/auth/refresh` and `/api/auth/logout`
+#[derive(Debug, Deserialize)]
+pub struct RefreshTokenRequest {
+    pub refresh_token: String,
+}
@@ -0,0 +1,103 @@
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+
+/// One picked lot/bin on a batch label
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchLabelLine {
+    pub line_id: i32,
+    pub item_key: String,
+    pub lot_no: String,
+    pub bin_no: String,
+    pub bags: BigDecimal,
+    pub qty: BigDecimal,
+    pub pack_size: BigDecimal,
+    pub unit: String,
+}
+
+/// The 4x4 inch "BULK SUMMARY" label of one batch (docs/PrintArchitecture.md)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BatchLabel {
+    pub run_no: i32,
+    pub batch_no: String,
+    /// Cust_BulkRun.FormulaId
+    pub product_key: String,
+    /// Cust_BulkRun.FormulaDesc
+    pub product_desc: String,
+    /// Page X of Y: the batch's RowNum over the run's NoOfBatches
+    pub page: i32,
+    pub pages: i32,
+    /// A batch with more lines than fit on one label continues on further sheets
+    pub sheet: usize,
+    pub sheets: usize,
+    /// Bangkok DD/MM/YY
+    pub date: String,
+    pub picked_by: String,
+    /// Bangkok print timestamp for the footer
+    pub printed_at: String,
+    pub lines: Vec<BatchLabelLine>,
+}
+
+impl BatchLabel {
+    pub fn page_info(&self) -> String {
+        if self.sheets > 1 {
+            format!("Page {} of {} ({}/{})", self.page, self.pages, self.sheet, self.sheets)
+        } else {
+            format!("Page {} of {}", self.page, self.pages)
+        }
+    }
+}
+
+/// Output of the label endpoints
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum LabelFormat {
+    /// Label data in the API envelope, for the browser print component
+    #[default]
+    Json,
+    /// Zebra ZPL II, 203 dpi
+    Zpl,
+    /// Eltron/Zebra EPL2, 203 dpi
+    Epl,
+    /// One 4x4 inch page per label
+    Pdf,
+}
+
+impl LabelFormat {
+    pub fn content_type(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::Zpl => "application/x-zpl",
+            Self::Epl => "application/x-epl",
+            Self::Pdf => "application/pdf",
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Json => "json",
+            Self::Zpl => "zpl",
+            Self::Epl => "epl",
+            Self::Pdf => "pdf",
+        }
+    }
+}
+
+/// Query of GET /api/bulk-runs/{run_no}/labels[/{batch_no}]; `format=zpl|epl|pdf` returns the rendered labels
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct BatchLabelQuery {
+    pub format: Option<String>,
+}
+
+impl BatchLabelQuery {
+    pub fn format(&self) -> Result<LabelFormat, String> {
+        match self.format.as_deref().map(|format| format.trim().to_ascii_lowercase()) {
+            None => Ok(LabelFormat::Json),
+            Some(format) => match format.as_str() {
+                "" | "json" => Ok(LabelFormat::Json),
+                "zpl" => Ok(LabelFormat::Zpl),
+                "epl" => Ok(LabelFormat::Epl),
+                "pdf" => Ok(LabelFormat::Pdf),
+                other => Err(format!("Unknown label format '{other}', expected json, zpl, epl or pdf")),
+            },
+        }
+    }
+}
@@ -2,6 +2,9 @@ use bigdecimal::BigDecimal;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use crate::models::idempotency::{IdempotencyKey, ReplayError};
+use crate::models::run_claims::RunClaim;
+
 /// Represents a bulk run record from Cust_BulkRun table
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BulkRun {
@@ -135,6 +138,9 @@ pub struct BulkRunSummary {
     pub formula_desc: String,
     pub status: String,
     pub batch_count: i32,
+    /// Active claims, shown as "being picked by ..."
+    #[serde(default)]
+    pub claims: Vec<RunClaim>,
 }
 
 /// API response for bulk runs listing in modal
@@ -155,6 +161,24 @@ pub struct PaginationInfo {
     pub has_next: bool,
 }
 
+impl PaginationInfo {
+    pub fn new(current_page: u32, page_size: u32, total_items: u64) -> Self {
+        let total_pages = if total_items == 0 {
+            1
+        } else {
+            (total_items as f64 / page_size as f64).ceil() as u32
+        };
+        Self {
+            current_page,
+            total_pages,
+            total_items,
+            page_size,
+            has_previous: current_page > 1,
+            has_next: current_page < total_pages,
+        }
+    }
+}
+
 /// Paginated response for bulk runs (Story 1.4)
 #[derive(Debug, Serialize, Deserialize)]
 pub struct PaginatedBulkRunResponse {
@@ -229,29 +253,152 @@ pub struct PalletBatchResponse {
     pub total_remaining_quantity: f64,
 }
 
-/// Pick confirmation request for BME4-compatible atomic transaction
-#[derive(Debug, Clone, Deserialize)]
+/// One lot/bin a split pick draws bags from
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LotAllocation {
+    pub lot_no: String,
+    pub bin_no: String,
+    pub bags: BigDecimal,
+    /// Bin barcode the picker scanned at the bin
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scanned_bin: Option<String>,
+    /// Lot barcode scanned on the bag: a plain lot number or a GS1 label with AI (10)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scanned_lot: Option<String>,
+}
+
+/// Pick confirmation request for BME4-compatible atomic transaction.
+/// Either a single `lot_no`/`bin_no`/`picked_bulk_qty`, or `allocations` across several lots.
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PickConfirmationRequest {
     pub row_num: i32,
     pub line_id: i32,
+    /// Total bags; filled in from `allocations` for a split pick
+    #[serde(default)]
     pub picked_bulk_qty: BigDecimal,
+    #[serde(default)]
     pub lot_no: String,
+    #[serde(default)]
     pub bin_no: String,
-    /// Optional user id (username). If absent, server falls back to header or SYSTEM.
+    /// Split pick: every lot is committed in the same transaction, under one BT document
+    #[serde(default)]
+    pub allocations: Vec<LotAllocation>,
+    /// Authenticated username, set by the handler from the verified token.
+    /// Never read from the request body, so a client cannot pick as someone else.
+    #[serde(default, skip_deserializing)]
     pub user_id: Option<String>,
+    /// Set by the handler from the `Idempotency-Key` header
+    #[serde(skip)]
+    pub idempotency_key: Option<IdempotencyKey>,
+    /// Supervisor only: pick even though another picker holds a claim on the run or line
+    #[serde(default)]
+    pub override_claim: bool,
+    /// Scanned bin barcode of a single-lot pick; split picks scan per allocation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scanned_bin: Option<String>,
+    /// Scanned lot barcode of a single-lot pick; split picks scan per allocation
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scanned_lot: Option<String>,
+}
+
+impl PickConfirmationRequest {
+    /// Lots the pick draws from: `allocations`, or the single top-level lot
+    pub fn lot_allocations(&self) -> Vec<LotAllocation> {
+        if !self.allocations.is_empty() {
+            return self.allocations.clone();
+        }
+        vec![LotAllocation {
+            lot_no: self.lot_no.clone(),
+            bin_no: self.bin_no.clone(),
+            bags: self.picked_bulk_qty.clone(),
+            scanned_bin: self.scanned_bin.clone(),
+            scanned_lot: self.scanned_lot.clone(),
+        }]
+    }
+
+    /// The same pick restricted to one of its lots, for per-lot validation
+    pub fn for_allocation(&self, allocation: &LotAllocation) -> Self {
+        Self {
+            picked_bulk_qty: allocation.bags.clone(),
+            lot_no: allocation.lot_no.clone(),
+            bin_no: allocation.bin_no.clone(),
+            allocations: Vec::new(),
+            scanned_bin: allocation.scanned_bin.clone(),
+            scanned_lot: allocation.scanned_lot.clone(),
+            ..self.clone()
+        }
+    }
+
+    /// Check a split pick's allocations and set `picked_bulk_qty` to their total.
+    /// Single-lot requests are left as they are.
+    pub fn normalize_allocations(&mut self) -> Result<(), String> {
+        if self.allocations.is_empty() {
+            return Ok(());
+        }
+        if !self.lot_no.is_empty() || !self.bin_no.is_empty() {
+            return Err("Send either lot_no/bin_no or allocations, not both".to_string());
+        }
+
+        let mut total = BigDecimal::from(0);
+        for (index, allocation) in self.allocations.iter().enumerate() {
+            if allocation.lot_no.is_empty() || allocation.bin_no.is_empty() {
+                return Err(format!("allocations[{index}] needs a lot_no and bin_no"));
+            }
+            if allocation.bags <= BigDecimal::from(0) {
+                return Err(format!("allocations[{index}] must pick more than 0 bags"));
+            }
+            if self.allocations[..index]
+                .iter()
+                .any(|other| other.lot_no == allocation.lot_no && other.bin_no == allocation.bin_no)
+            {
+                return Err(format!(
+                    "Lot {} in bin {} is allocated more than once",
+                    allocation.lot_no, allocation.bin_no
+                ));
+            }
+            total += &allocation.bags;
+        }
+
+        if self.picked_bulk_qty != BigDecimal::from(0) && self.picked_bulk_qty != total {
+            return Err(format!(
+                "picked_bulk_qty {} does not match the allocated total of {} bags",
+                self.picked_bulk_qty, total
+            ));
+        }
+        self.picked_bulk_qty = total;
+        Ok(())
+    }
+}
+
+/// What one lot of a pick committed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AllocatedLot {
+    pub lot_no: String,
+    pub bin_no: String,
+    pub picked_bulk_qty: BigDecimal,
+    /// KG committed from the lot
+    pub picked_qty: BigDecimal,
+    pub lot_tran_no: i64,
 }
 
 /// Pick confirmation response
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct PickConfirmationResponse {
     pub success: bool,
+    /// LotTranNo of the first lot picked
     pub transaction_id: Option<i64>,
     pub document_no: Option<String>,
     pub updated_records: TransactionSummary,
+    /// One entry per lot, in request order
+    #[serde(default)]
+    pub allocations: Vec<AllocatedLot>,
+    /// Non-blocking validation warnings; returned as the response envelope's `warning`
+    #[serde(skip)]
+    pub warnings: Vec<String>,
 }
 
 /// Summary of records updated during pick transaction
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct TransactionSummary {
     pub bulk_picked_updated: bool,
     pub lot_picked_created: bool,
@@ -286,6 +433,113 @@ pub struct PickValidationResult {
     pub available_inventory: Option<BigDecimal>,
 }
 
+impl PickValidationResult {
+    /// Conflict code for a failed validation, derived from which limit was hit
+    pub fn conflict_code(&self) -> &'static str {
+        use bigdecimal::Zero;
+
+        if self.available_inventory.is_some() {
+            "LOT_EXHAUSTED"
+        } else {
+            match &self.max_allowed_quantity {
+                Some(max) if *max <= BigDecimal::zero() => "BATCH_COMPLETED",
+                Some(_) => "QUANTITY_EXCEEDS_REMAINING",
+                None => "LOT_NOT_AVAILABLE",
+            }
+        }
+    }
+}
+
+/// Most picks accepted in one offline sync request
+pub const MAX_OFFLINE_SYNC_PICKS: usize = 200;
+
+/// Pick confirmations queued on a handheld while it was out of Wi-Fi range, oldest first
+#[derive(Debug, Deserialize)]
+pub struct OfflinePickSyncRequest {
+    pub picks: Vec<QueuedPick>,
+}
+
+/// One pick confirmation captured offline
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct QueuedPick {
+    /// Id of the queue entry on the handheld; used as the pick's Idempotency-Key,
+    /// so re-sending a partly synced queue does not apply a pick twice
+    pub client_id: String,
+    pub run_no: i32,
+    pub row_num: i32,
+    pub line_id: i32,
+    #[serde(default)]
+    pub picked_bulk_qty: BigDecimal,
+    #[serde(default)]
+    pub lot_no: String,
+    #[serde(default)]
+    pub bin_no: String,
+    /// Split pick across several lots, as in confirm-pick
+    #[serde(default)]
+    pub allocations: Vec<LotAllocation>,
+    /// Barcodes scanned for a single-lot pick, as in confirm-pick
+    #[serde(default)]
+    pub scanned_bin: Option<String>,
+    #[serde(default)]
+    pub scanned_lot: Option<String>,
+    /// When the pick was made on the handheld
+    pub captured_at: Option<DateTime<Utc>>,
+}
+
+/// What happened to a queued pick during sync
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SyncOutcome {
+    /// Committed now
+    Applied,
+    /// Committed by an earlier sync of the same queue entry; the original response is returned
+    AlreadyApplied,
+    /// Rejected by revalidation; drop it from the queue and show the operator why
+    Conflict,
+    /// Server-side failure; keep it queued and retry later
+    Failed,
+    /// Not attempted because an earlier pick failed; keep it queued
+    Skipped,
+}
+
+/// Per-pick result of an offline sync
+#[derive(Debug, Serialize)]
+pub struct QueuedPickResult {
+    pub client_id: String,
+    pub run_no: i32,
+    pub outcome: SyncOutcome,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub response: Option<PickConfirmationResponse>,
+}
+
+/// Results of an offline sync, in the order the picks were sent
+#[derive(Debug, Serialize)]
+pub struct OfflinePickSyncResponse {
+    pub applied: usize,
+    pub already_applied: usize,
+    pub conflicts: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub results: Vec<QueuedPickResult>,
+}
+
+impl OfflinePickSyncResponse {
+    pub fn from_results(results: Vec<QueuedPickResult>) -> Self {
+        let count = |outcome| results.iter().filter(|r| r.outcome == outcome).count();
+        Self {
+            applied: count(SyncOutcome::Applied),
+            already_applied: count(SyncOutcome::AlreadyApplied),
+            conflicts: count(SyncOutcome::Conflict),
+            failed: count(SyncOutcome::Failed),
+            skipped: count(SyncOutcome::Skipped),
+            results,
+        }
+    }
+}
+
 /// Picked lot information for unpicking modal
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PickedLot {
@@ -399,3 +653,186 @@ pub struct StatusUpdateResult {
     pub old_status: String,
     pub new_status: String,
 }
+
+/// Errors from the bulk-runs domain (pick transaction and run status changes).
+/// Each variant has a stable `error_code` the frontend can branch on.
+#[derive(Debug, thiserror::Error)]
+pub enum BulkRunError {
+    #[error("Run {run_no} not found")]
+    RunNotFound { run_no: i32 },
+
+    #[error("Batch not found: run={run_no}, row_num={row_num}, line_id={line_id}")]
+    BatchNotFound { run_no: i32, row_num: i32, line_id: i32 },
+
+    #[error("This batch is already completed. Please refresh to load the next batch.")]
+    BatchAlreadyCompleted,
+
+    #[error("Cannot pick {requested} bags. Only {remaining} bags remaining in this batch (ItemKey: {item_key}, Batch: RowNum={row_num}, LineId={line_id})")]
+    InsufficientBatchQuantity {
+        requested: f64,
+        remaining: f64,
+        item_key: String,
+        row_num: i32,
+        line_id: i32,
+    },
+
+    #[error("Quantity picked is more than Qty Required {required_kg} KG")]
+    QuantityExceedsRequired { required_kg: f64 },
+
+    #[error("Validation failed: {0}")]
+    Validation(String),
+
+    #[error("Cannot complete run {run_no} - still has {incomplete} incomplete ingredients (completed: {completed}/{total})")]
+    RunIncomplete {
+        run_no: i32,
+        incomplete: i32,
+        completed: i32,
+        total: i32,
+    },
+
+    #[error("Cannot {action} run {run_no} - current status is '{current}', expected '{expected}'")]
+    InvalidRunStatus {
+        run_no: i32,
+        action: &'static str,
+        current: String,
+        expected: &'static str,
+    },
+
+    #[error("Idempotency-Key was already used for a different pick request")]
+    IdempotencyKeyReused,
+
+    #[error(
+        "Run {run_no}{} is being picked by {holder}",
+        line_id.map(|line_id| format!(" (ingredient line {line_id})")).unwrap_or_default()
+    )]
+    ClaimedByOther {
+        run_no: i32,
+        line_id: Option<i32>,
+        holder: String,
+    },
+
+    #[error("Only a supervisor can override another picker's claim")]
+    ClaimOverrideForbidden,
+
+    #[error(
+        "{} of run {run_no} has no picked lots to print",
+        batch_no.as_deref().map(|batch_no| format!("Batch {batch_no}")).unwrap_or_else(|| "No batch".to_string())
+    )]
+    NothingToPrint { run_no: i32, batch_no: Option<String> },
+
+    #[error("SSCC pallet identifiers are not configured (set SSCC_COMPANY_PREFIX)")]
+    SsccNotConfigured,
+
+    #[error("Pallet {row_num} of run {run_no} has no SSCC yet - assign one or queue its label for printing")]
+    SsccNotAssigned { run_no: i32, row_num: i32 },
+
+    #[error("Invalid SSCC: {0}")]
+    InvalidSscc(String),
+
+    #[error("Pallet {pallet} not found")]
+    PalletNotFound { pallet: String },
+
+    #[error("Site {site} requires the bin and lot barcodes of every picked lot to be scanned")]
+    ScanRequired { site: String },
+
+    #[error("Wrong bin scanned: {scanned} is not the selected bin {expected}")]
+    WrongBinScanned { expected: String, scanned: String },
+
+    #[error("Wrong bag scanned: lot {scanned} is not the selected lot {expected}")]
+    WrongLotScanned { expected: String, scanned: String },
+
+    #[error("Database connection failed: {0}")]
+    ConnectionFailed(String),
+
+    /// A step of the pick transaction failed and everything was rolled back
+    #[error("Pick transaction rolled back: {0:#}")]
+    TransactionRolledBack(anyhow::Error),
+
+    #[error("{0:#}")]
+    Database(#[from] anyhow::Error),
+}
+
+impl From<ReplayError> for BulkRunError {
+    fn from(error: ReplayError) -> Self {
+        match error {
+            ReplayError::KeyReused => Self::IdempotencyKeyReused,
+            ReplayError::Corrupt(e) => Self::Database(anyhow::Error::new(e)),
+        }
+    }
+}
+
+impl BulkRunError {
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::RunNotFound { .. } => "RUN_NOT_FOUND",
+            Self::BatchNotFound { .. } => "BATCH_NOT_FOUND",
+            Self::BatchAlreadyCompleted => "BATCH_ALREADY_COMPLETED",
+            Self::InsufficientBatchQuantity { .. } => "INSUFFICIENT_BATCH_QUANTITY",
+            Self::QuantityExceedsRequired { .. } => "QUANTITY_EXCEEDS_REQUIRED",
+            Self::Validation(_) => "VALIDATION_ERROR",
+            Self::RunIncomplete { .. } => "RUN_INCOMPLETE",
+            Self::InvalidRunStatus { .. } => "INVALID_RUN_STATUS",
+            Self::IdempotencyKeyReused => "IDEMPOTENCY_KEY_REUSED",
+            Self::ClaimedByOther { .. } => "RUN_CLAIMED",
+            Self::ClaimOverrideForbidden => "CLAIM_OVERRIDE_FORBIDDEN",
+            Self::NothingToPrint { .. } => "NOTHING_TO_PRINT",
+            Self::SsccNotConfigured => "SSCC_NOT_CONFIGURED",
+            Self::SsccNotAssigned { .. } => "SSCC_NOT_ASSIGNED",
+            Self::InvalidSscc(_) => "INVALID_SSCC",
+            Self::PalletNotFound { .. } => "PALLET_NOT_FOUND",
+            Self::ScanRequired { .. } => "SCAN_REQUIRED",
+            Self::WrongBinScanned { .. } => "WRONG_BIN_SCANNED",
+            Self::WrongLotScanned { .. } => "WRONG_LOT_SCANNED",
+            Self::ConnectionFailed(_) => "DATABASE_UNAVAILABLE",
+            Self::TransactionRolledBack(_) => "PICK_TRANSACTION_FAILED",
+            Self::Database(_) => "DATABASE_ERROR",
+        }
+    }
+
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+
+        match self {
+            Self::RunNotFound { .. }
+            | Self::BatchNotFound { .. }
+            | Self::NothingToPrint { .. }
+            | Self::PalletNotFound { .. } => StatusCode::NOT_FOUND,
+            Self::BatchAlreadyCompleted
+            | Self::RunIncomplete { .. }
+            | Self::InvalidRunStatus { .. }
+            | Self::ClaimedByOther { .. }
+            | Self::SsccNotAssigned { .. } => StatusCode::CONFLICT,
+            Self::ClaimOverrideForbidden => StatusCode::FORBIDDEN,
+            Self::InsufficientBatchQuantity { .. }
+            | Self::QuantityExceedsRequired { .. }
+            | Self::IdempotencyKeyReused
+            | Self::SsccNotConfigured
+            | Self::ScanRequired { .. }
+            | Self::WrongBinScanned { .. }
+            | Self::WrongLotScanned { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Validation(_) | Self::InvalidSscc(_) => StatusCode::BAD_REQUEST,
+            Self::ConnectionFailed(_) => StatusCode::SERVICE_UNAVAILABLE,
+            Self::TransactionRolledBack(_) | Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Deadlocks, lock timeouts and key collisions from a concurrent pick can succeed on retry
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            Self::TransactionRolledBack(source) | Self::Database(source) => {
+                source.chain().any(is_transient_sql_error)
+            }
+            _ => false,
+        }
+    }
+}
+
+/// SQL Server errors worth retrying: deadlock victim (1205), lock request timeout (1222),
+/// duplicate key (2601, 2627), plus I/O timeouts
+fn is_transient_sql_error(cause: &(dyn std::error::Error + 'static)) -> bool {
+    match cause.downcast_ref::<tiberius::error::Error>() {
+        Some(tiberius::error::Error::Server(token)) => matches!(token.code(), 1205 | 1222 | 2601 | 2627),
+        Some(tiberius::error::Error::Io { kind, .. }) => *kind == std::io::ErrorKind::TimedOut,
+        _ => false,
+    }
+}
@@ -0,0 +1,139 @@
+use axum::http::{HeaderMap, HeaderName};
+use serde::{de::DeserializeOwned, Serialize};
+use sha2::{Digest, Sha256};
+
+/// Header a client sets to make a retried write safe to replay
+pub const IDEMPOTENCY_KEY_HEADER: HeaderName = HeaderName::from_static("idempotency-key");
+
+/// Longest accepted key; fits tbl_idempotency_keys.IdempotencyKey
+pub const MAX_IDEMPOTENCY_KEY_LEN: usize = 64;
+
+/// Write endpoint a key belongs to - the same key may be used once per operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdempotentOperation {
+    ConfirmPick,
+    BinTransfer,
+}
+
+impl IdempotentOperation {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::ConfirmPick => "confirm_pick",
+            Self::BinTransfer => "bin_transfer",
+        }
+    }
+}
+
+/// Read the optional `Idempotency-Key` header.
+/// Keys are 1-64 visible ASCII characters; anything else is rejected rather than ignored,
+/// so a client never believes a retry is protected when it is not.
+pub fn idempotency_key_from_headers(headers: &HeaderMap) -> Result<Option<String>, String> {
+    let Some(value) = headers.get(&IDEMPOTENCY_KEY_HEADER) else {
+        return Ok(None);
+    };
+    let key = value
+        .to_str()
+        .map_err(|_| "Idempotency-Key must be visible ASCII".to_string())?
+        .trim();
+
+    validate_idempotency_key(key).map_err(|message| format!("Idempotency-Key {message}"))?;
+    Ok(Some(key.to_string()))
+}
+
+/// Check a key supplied outside the header, e.g. the id of an offline queue entry
+pub fn validate_idempotency_key(key: &str) -> Result<(), String> {
+    if key.is_empty() || key.len() > MAX_IDEMPOTENCY_KEY_LEN {
+        return Err(format!("must be 1-{MAX_IDEMPOTENCY_KEY_LEN} characters"));
+    }
+    if !key.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err("must be visible ASCII".to_string());
+    }
+    Ok(())
+}
+
+/// A client key bound to its operation, caller and request body.
+/// Keys are scoped per user, so two handhelds cannot collide on the same key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdempotencyKey {
+    pub key: String,
+    pub operation: IdempotentOperation,
+    pub username: String,
+    /// SHA-256 of the request body, to tell a replay from a reused key
+    pub request_hash: String,
+}
+
+impl IdempotencyKey {
+    pub fn new<T: Serialize>(key: String, operation: IdempotentOperation, username: &str, request: &T) -> Self {
+        let body = serde_json::to_vec(request).unwrap_or_default();
+        let request_hash = Sha256::digest(&body).iter().map(|b| format!("{b:02x}")).collect();
+        Self {
+            key,
+            operation,
+            username: username.to_string(),
+            request_hash,
+        }
+    }
+}
+
+/// Response recorded in tbl_idempotency_keys by the first successful request
+#[derive(Debug, Clone)]
+pub struct StoredResponse {
+    pub request_hash: String,
+    pub response_body: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ReplayError {
+    #[error("Idempotency-Key was already used for a different request")]
+    KeyReused,
+
+    #[error("Stored idempotent response could not be read: {0}")]
+    Corrupt(#[from] serde_json::Error),
+}
+
+impl StoredResponse {
+    /// Original response for a replay of the same request
+    pub fn replay<T: DeserializeOwned>(&self, key: &IdempotencyKey) -> Result<T, ReplayError> {
+        if self.request_hash != key.request_hash {
+            return Err(ReplayError::KeyReused);
+        }
+        Ok(serde_json::from_str(&self.response_body)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::HeaderValue;
+
+    fn headers(key: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IDEMPOTENCY_KEY_HEADER, HeaderValue::from_str(key).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_idempotency_key_header_parsing() {
+        assert_eq!(idempotency_key_from_headers(&HeaderMap::new()), Ok(None));
+        assert_eq!(
+            idempotency_key_from_headers(&headers("pick-7f3a")),
+            Ok(Some("pick-7f3a".to_string()))
+        );
+        assert!(idempotency_key_from_headers(&headers("")).is_err());
+        assert!(idempotency_key_from_headers(&headers("two words")).is_err());
+        assert!(idempotency_key_from_headers(&headers(&"k".repeat(65))).is_err());
+    }
+
+    #[test]
+    fn test_replay_rejects_a_different_request() {
+        let first = IdempotencyKey::new("k1".to_string(), IdempotentOperation::BinTransfer, "picker01", &("LOT1", 5.0));
+        let stored = StoredResponse {
+            request_hash: first.request_hash.clone(),
+            response_body: r#""BT-00000001""#.to_string(),
+        };
+        assert_eq!(stored.replay::<String>(&first).unwrap(), "BT-00000001");
+
+        let changed = IdempotencyKey::new("k1".to_string(), IdempotentOperation::BinTransfer, "picker01", &("LOT1", 6.0));
+        assert!(matches!(stored.replay::<String>(&changed), Err(ReplayError::KeyReused)));
+    }
+}
@@ -1,8 +1,21 @@
 pub mod api_response;
 pub mod api_errors;
+pub mod api_keys;
+pub mod auth_session;
+pub mod batch_labels;
 pub mod bulk_runs;
+pub mod pick_plan;
+pub mod pick_scans;
+pub mod pallet_labels;
+pub mod pick_sequence;
+pub mod print_jobs;
 pub mod putaway;
 pub mod putaway_models;
+pub mod run_claims;
+pub mod run_events;
+pub mod scans;
+pub mod security_audit;
+pub mod idempotency;
 pub mod inventory;
 #[cfg(feature = "intelligence")]
 pub mod ingredient_intelligence;
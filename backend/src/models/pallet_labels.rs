@@ -0,0 +1,86 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+use tracing::warn;
+
+use crate::models::batch_labels::{BatchLabel, BatchLabelLine};
+use crate::utils::gs1;
+
+/// Company prefix and extension digit for SSCC pallet identifiers. Pallets only get an
+/// SSCC when SSCC_COMPANY_PREFIX is set; SSCC_EXTENSION_DIGIT defaults to 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsccConfig {
+    pub extension_digit: u8,
+    pub company_prefix: String,
+}
+
+impl SsccConfig {
+    pub fn from_env() -> Option<Self> {
+        let company_prefix = env::var("SSCC_COMPANY_PREFIX").ok()?.trim().to_string();
+        if company_prefix.is_empty() {
+            return None;
+        }
+        let extension_digit = env::var("SSCC_EXTENSION_DIGIT")
+            .ok()
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0);
+
+        let config = Self { extension_digit, company_prefix };
+        match config.sscc(0) {
+            Ok(_) => Some(config),
+            Err(e) => {
+                warn!("⚠️ SSCC pallet identifiers disabled: {}", e);
+                None
+            }
+        }
+    }
+
+    /// The SSCC-18 of a serial reference
+    pub fn sscc(&self, serial: u64) -> Result<String, String> {
+        gs1::sscc(self.extension_digit, &self.company_prefix, serial)
+    }
+}
+
+/// The SSCC assigned to one pallet (batch row) of a run (tbl_pallet_sscc)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PalletSscc {
+    pub sscc: String,
+    pub run_no: i32,
+    pub row_num: i32,
+    pub batch_no: String,
+    pub assigned_by: String,
+    pub assigned_at: DateTime<Utc>,
+}
+
+/// 4x4 inch pallet label: the batch's picked lots and the SSCC as a GS1-128 barcode
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PalletLabel {
+    pub sscc: String,
+    /// The batch on the pallet; `page` is the pallet number
+    #[serde(flatten)]
+    pub batch: BatchLabel,
+}
+
+impl PalletLabel {
+    /// Barcode content: Application Identifier (00) followed by the SSCC
+    pub fn barcode_data(&self) -> String {
+        format!("00{}", self.sscc)
+    }
+
+    /// Text printed under the barcode
+    pub fn human_readable(&self) -> String {
+        format!("(00) {}", self.sscc)
+    }
+}
+
+/// What a scanned SSCC resolves to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PalletLookup {
+    #[serde(flatten)]
+    pub pallet: PalletSscc,
+    pub product_key: String,
+    pub product_desc: String,
+    pub run_status: String,
+    /// Lots picked onto the pallet so far
+    pub lines: Vec<BatchLabelLine>,
+}
@@ -0,0 +1,202 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Progress of one pick plan step
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PlanStepStatus {
+    Pending,
+    /// A confirm-pick took bags from this lot/bin for the step's batch
+    Picked,
+}
+
+impl PlanStepStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Pending => "PENDING",
+            Self::Picked => "PICKED",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "PICKED" => Self::Picked,
+            _ => Self::Pending,
+        }
+    }
+}
+
+/// Take `bags` from one lot/bin for one batch of an ingredient
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickPlanStep {
+    /// Order the picker follows, starting at 1
+    pub step_no: i32,
+    pub row_num: i32,
+    pub line_id: i32,
+    pub item_key: String,
+    pub lot_no: String,
+    pub bin_no: String,
+    /// Whole bags only; a lot is never planned for part of a pack
+    pub bags: BigDecimal,
+    pub qty_kg: BigDecimal,
+    pub expiry_date: Option<DateTime<Utc>>,
+    pub status: PlanStepStatus,
+}
+
+/// Bags of a batch that no available lot can cover
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickPlanShortage {
+    pub row_num: i32,
+    pub line_id: i32,
+    pub item_key: String,
+    /// Bags still to pick when the plan was made
+    pub required_bags: BigDecimal,
+    pub short_bags: BigDecimal,
+    pub short_kg: BigDecimal,
+}
+
+/// FEFO lot assignment for every remaining batch of a run (tbl_bulk_pick_plans)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickPlan {
+    pub run_no: i32,
+    pub generated_by: String,
+    pub generated_at: DateTime<Utc>,
+    pub steps: Vec<PickPlanStep>,
+    pub shortages: Vec<PickPlanShortage>,
+}
+
+impl PickPlan {
+    /// First step not picked yet
+    pub fn next_step(&self) -> Option<&PickPlanStep> {
+        self.steps.iter().find(|step| step.status == PlanStepStatus::Pending)
+    }
+
+    pub fn pending_steps(&self) -> usize {
+        self.steps.iter().filter(|step| step.status == PlanStepStatus::Pending).count()
+    }
+}
+
+/// What one ingredient of a run still needs against the stock that could cover it
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct IngredientStock {
+    pub line_id: i32,
+    pub item_key: String,
+    pub description: Option<String>,
+    pub pack_size: BigDecimal,
+    /// Bags left to pick over all of the ingredient's batches
+    pub required_bags: BigDecimal,
+    pub required_kg: BigDecimal,
+    /// Unexpired, nettable lot stock net of QtyCommitSales
+    pub available_kg: BigDecimal,
+    /// Bags no lot can supply in whole packs
+    pub short_bags: BigDecimal,
+    pub short_kg: BigDecimal,
+}
+
+/// Dry run of a run's pick plan: nothing is stored or committed
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickPlanPreview {
+    pub run_no: i32,
+    pub generated_at: DateTime<Utc>,
+    /// True when every batch can be picked from current stock
+    pub can_fulfil: bool,
+    pub ingredients: Vec<IngredientStock>,
+    /// Batches that cannot be fully picked
+    pub shortages: Vec<PickPlanShortage>,
+    /// The plan that would be generated now
+    pub steps: Vec<PickPlanStep>,
+}
+
+impl PickPlanPreview {
+    /// Shortage report for planners: one line per batch that cannot be fully picked,
+    /// with its ingredient's totals alongside
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from(
+            "RunNo,LineId,ItemKey,Description,RowNum,RequiredBags,ShortBags,ShortKg,IngredientRequiredKg,IngredientAvailableKg\r\n",
+        );
+        for shortage in &self.shortages {
+            let ingredient = self
+                .ingredients
+                .iter()
+                .find(|i| i.line_id == shortage.line_id && i.item_key == shortage.item_key);
+            let fields = [
+                self.run_no.to_string(),
+                shortage.line_id.to_string(),
+                shortage.item_key.clone(),
+                ingredient.and_then(|i| i.description.clone()).unwrap_or_default(),
+                shortage.row_num.to_string(),
+                shortage.required_bags.normalized().to_string(),
+                shortage.short_bags.normalized().to_string(),
+                shortage.short_kg.normalized().to_string(),
+                ingredient.map(|i| i.required_kg.normalized().to_string()).unwrap_or_default(),
+                ingredient.map(|i| i.available_kg.normalized().to_string()).unwrap_or_default(),
+            ];
+            let line: Vec<String> = fields.iter().map(|field| csv_field(field)).collect();
+            csv.push_str(&line.join(","));
+            csv.push_str("\r\n");
+        }
+        csv
+    }
+}
+
+/// Query of GET /api/bulk-runs/{run_no}/pick-plan/preview; `format=csv` downloads the shortage report
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PickPlanPreviewQuery {
+    pub format: Option<String>,
+}
+
+impl PickPlanPreviewQuery {
+    pub fn wants_csv(&self) -> bool {
+        self.format.as_deref().is_some_and(|format| format.eq_ignore_ascii_case("csv"))
+    }
+}
+
+/// Quote a CSV field when it holds a separator, quote or line break
+fn csv_field(value: &str) -> String {
+    if value.contains([',', '"', '\r', '\n']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shortage_csv_quotes_descriptions() {
+        let preview = PickPlanPreview {
+            run_no: 215235,
+            generated_at: Utc::now(),
+            can_fulfil: false,
+            ingredients: vec![IngredientStock {
+                line_id: 3,
+                item_key: "INFLOUR1".to_string(),
+                description: Some("Flour, \"soft\" wheat".to_string()),
+                pack_size: BigDecimal::from(25),
+                required_bags: BigDecimal::from(2),
+                required_kg: BigDecimal::from(50),
+                available_kg: BigDecimal::from(0),
+                short_bags: BigDecimal::from(2),
+                short_kg: BigDecimal::from(50),
+            }],
+            shortages: vec![PickPlanShortage {
+                row_num: 1,
+                line_id: 3,
+                item_key: "INFLOUR1".to_string(),
+                required_bags: BigDecimal::from(2),
+                short_bags: BigDecimal::from(2),
+                short_kg: BigDecimal::from(50),
+            }],
+            steps: Vec::new(),
+        };
+
+        let csv = preview.to_csv();
+        let lines: Vec<_> = csv.lines().collect();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[0].starts_with("RunNo,LineId,ItemKey"));
+        assert_eq!(lines[1], "215235,3,INFLOUR1,\"Flour, \"\"soft\"\" wheat\",1,2,2,50,50,0");
+    }
+}
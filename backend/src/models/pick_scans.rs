@@ -0,0 +1,124 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::models::bulk_runs::{BulkRunError, LotAllocation};
+use crate::utils::gs1;
+
+/// Sites (location keys) where every picked lot needs its bin and lot barcodes scanned.
+/// PICK_SCAN_VERIFY_SITES is a comma-separated list of location keys, or `*` for every
+/// site. Elsewhere scans are optional, but the ones sent are still checked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScanVerifyPolicy {
+    sites: Vec<String>,
+}
+
+impl ScanVerifyPolicy {
+    /// Read once at startup and shared through the app state
+    pub fn from_env() -> Self {
+        Self::parse(&env::var("PICK_SCAN_VERIFY_SITES").unwrap_or_default())
+    }
+
+    /// Comma-separated location keys, or `*`
+    pub fn parse(sites: &str) -> Self {
+        let sites = sites
+            .split(',')
+            .map(|site| site.trim().to_uppercase())
+            .filter(|site| !site.is_empty())
+            .collect();
+        Self { sites }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.sites.is_empty()
+    }
+
+    pub fn requires_scans(&self, site: &str) -> bool {
+        self.sites.iter().any(|s| s == "*" || s.eq_ignore_ascii_case(site.trim()))
+    }
+}
+
+/// Lot number in a scanned lot barcode: AI (10) of a GS1 label, or the plain scan.
+/// None when a GS1 label is unreadable or carries no lot.
+pub fn scanned_lot_no(scan: &str) -> Option<String> {
+    if gs1::is_element_string(scan) {
+        return gs1::parse_element_string(scan).ok()?.lot_no;
+    }
+    Some(scan.trim().to_string()).filter(|lot_no| !lot_no.is_empty())
+}
+
+/// How the barcodes scanned for an allocation disagree with it, the lot first since a
+/// wrong bag matters more than a wrong bin. Scans that were not sent are not checked.
+pub fn scan_mismatch(allocation: &LotAllocation) -> Option<BulkRunError> {
+    if let Some(scan) = allocation.scanned_lot.as_deref() {
+        let lot_no = scanned_lot_no(scan);
+        if !lot_no.as_deref().is_some_and(|lot_no| lot_no.eq_ignore_ascii_case(allocation.lot_no.trim())) {
+            return Some(BulkRunError::WrongLotScanned {
+                expected: allocation.lot_no.clone(),
+                scanned: lot_no.unwrap_or_else(|| scan.trim().to_string()),
+            });
+        }
+    }
+    if let Some(scan) = allocation.scanned_bin.as_deref() {
+        if !scan.trim().eq_ignore_ascii_case(allocation.bin_no.trim()) {
+            return Some(BulkRunError::WrongBinScanned {
+                expected: allocation.bin_no.clone(),
+                scanned: scan.trim().to_string(),
+            });
+        }
+    }
+    None
+}
+
+/// A pick rejected because the scanned bin or lot did not match the selected allocation
+/// (tbl_pick_scan_exceptions)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PickScanException {
+    pub run_no: i32,
+    pub row_num: i32,
+    pub line_id: i32,
+    /// The allocation the picker selected
+    pub lot_no: String,
+    pub bin_no: String,
+    /// What was scanned, as sent
+    pub scanned_lot: Option<String>,
+    pub scanned_bin: Option<String>,
+    /// WRONG_LOT_SCANNED or WRONG_BIN_SCANNED
+    pub error_code: String,
+    pub user_id: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scan_policy_and_scanned_lot() {
+        let policy = ScanVerifyPolicy::parse(" tfc1, ");
+        assert!(policy.requires_scans("tfc1"));
+        assert!(!policy.requires_scans("WHKON"));
+        assert!(ScanVerifyPolicy::parse("*").requires_scans("WHKON"));
+        assert!(!ScanVerifyPolicy::default().is_enabled());
+
+        assert_eq!(scanned_lot_no(" 2510601 ").as_deref(), Some("2510601"));
+        assert_eq!(scanned_lot_no("(01)09501101020917(10)2510601").as_deref(), Some("2510601"));
+        assert_eq!(scanned_lot_no("(01)09501101020917"), None);
+        assert_eq!(scanned_lot_no("(01)09501101020910(10)2510601"), None);
+
+        let allocation = |scanned_bin: Option<&str>, scanned_lot: Option<&str>| LotAllocation {
+            lot_no: "2510601".to_string(),
+            bin_no: "A0101-1A".to_string(),
+            bags: 1.into(),
+            scanned_bin: scanned_bin.map(str::to_string),
+            scanned_lot: scanned_lot.map(str::to_string),
+        };
+        assert!(scan_mismatch(&allocation(None, None)).is_none());
+        assert!(scan_mismatch(&allocation(Some("a0101-1a"), Some("]C1102510601"))).is_none());
+        let code = |bin, lot| scan_mismatch(&allocation(bin, lot)).map(|e| e.error_code());
+        assert_eq!(code(Some("A0101-1A"), Some("2510602")), Some("WRONG_LOT_SCANNED"));
+        assert_eq!(code(Some("A0202-1A"), Some("2510602")), Some("WRONG_LOT_SCANNED"));
+        assert_eq!(code(Some("A0202-1A"), Some("2510601")), Some("WRONG_BIN_SCANNED"));
+        assert_eq!(code(None, Some("(01)09501101020917")), Some("WRONG_LOT_SCANNED"));
+    }
+}
@@ -0,0 +1,63 @@
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Zones walked first to last when nothing is configured
+pub const DEFAULT_ZONE_ORDER: [&str; 3] = ["K", "D", "A"];
+
+/// Order in which a run's ingredients are presented to the picker
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PickSequence {
+    /// Formula line order (LineId DESC), as BME4 shows it
+    #[default]
+    Line,
+    /// Warehouse walk path of each ingredient's FEFO bin
+    WalkPath,
+}
+
+impl PickSequence {
+    /// `sequence` query value; anything unknown keeps the line order
+    pub fn parse(value: Option<&str>) -> Self {
+        match value.map(str::trim) {
+            Some(value) if value.eq_ignore_ascii_case("walk_path") || value.eq_ignore_ascii_case("walk") => {
+                Self::WalkPath
+            }
+            _ => Self::Line,
+        }
+    }
+}
+
+/// Where a bin sits on the warehouse floor (BINMaster aisle/row/rack)
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BinLocation {
+    pub bin_no: String,
+    pub aisle: String,
+    pub row: String,
+    pub rack: String,
+}
+
+impl BinLocation {
+    /// Zone is the bin's letter prefix (K0802-4B is in zone K)
+    pub fn zone(&self) -> String {
+        self.bin_no
+            .chars()
+            .take_while(|c| c.is_ascii_alphabetic())
+            .collect::<String>()
+            .to_ascii_uppercase()
+    }
+}
+
+/// Zones in walking order, from PICK_SEQUENCE_ZONE_ORDER (comma separated, e.g. "K,D,A")
+pub fn zone_order() -> Vec<String> {
+    let configured: Vec<String> = env::var("PICK_SEQUENCE_ZONE_ORDER")
+        .unwrap_or_default()
+        .split(',')
+        .map(|zone| zone.trim().to_ascii_uppercase())
+        .filter(|zone| !zone.is_empty())
+        .collect();
+    if configured.is_empty() {
+        DEFAULT_ZONE_ORDER.iter().map(|zone| zone.to_string()).collect()
+    } else {
+        configured
+    }
+}
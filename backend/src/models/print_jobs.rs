@@ -0,0 +1,373 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+use crate::models::bulk_runs::BulkRunError;
+
+/// Attempts per job when PRINT_JOB_MAX_ATTEMPTS is not set
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+/// First retry waits this long, doubling up to `MAX_RETRY_DELAY_SECS`
+const FIRST_RETRY_DELAY_SECS: i64 = 10;
+const MAX_RETRY_DELAY_SECS: i64 = 300;
+
+/// Sends per job before it is marked FAILED
+pub fn max_attempts() -> i32 {
+    env::var("PRINT_JOB_MAX_ATTEMPTS")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&attempts: &i32| attempts > 0)
+        .unwrap_or(DEFAULT_MAX_ATTEMPTS)
+}
+
+/// Wait before the next send after `attempts` failed ones
+pub fn retry_delay(attempts: i32) -> Duration {
+    let doublings = attempts.clamp(1, 16) - 1;
+    Duration::seconds((FIRST_RETRY_DELAY_SECS << doublings).min(MAX_RETRY_DELAY_SECS))
+}
+
+/// Printer command language a label printer understands
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum PrinterLanguage {
+    Zpl,
+    Epl,
+}
+
+impl PrinterLanguage {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Zpl => "ZPL",
+            Self::Epl => "EPL",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_uppercase().as_str() {
+            "ZPL" => Some(Self::Zpl),
+            "EPL" => Some(Self::Epl),
+            _ => None,
+        }
+    }
+}
+
+/// A network label printer reached over raw TCP (tbl_label_printers)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LabelPrinter {
+    pub printer_id: String,
+    pub name: String,
+    pub host: String,
+    pub port: u16,
+    pub language: PrinterLanguage,
+    /// Used when a print request names no printer
+    pub is_default: bool,
+    pub active: bool,
+    pub updated_by: String,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// Body of POST /api/printers; registering an existing id updates it
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegisterPrinterRequest {
+    pub printer_id: String,
+    /// Defaults to the printer id
+    #[serde(default)]
+    pub name: String,
+    pub host: String,
+    #[serde(default = "default_port")]
+    pub port: u16,
+    pub language: PrinterLanguage,
+    #[serde(default)]
+    pub is_default: bool,
+    #[serde(default = "default_active")]
+    pub active: bool,
+}
+
+fn default_port() -> u16 {
+    9100
+}
+
+fn default_active() -> bool {
+    true
+}
+
+impl RegisterPrinterRequest {
+    pub fn into_printer(self, username: &str) -> Result<LabelPrinter, PrintError> {
+        let printer_id = self.printer_id.trim().to_string();
+        let host = self.host.trim().to_string();
+        if printer_id.is_empty() || printer_id.len() > 50 {
+            return Err(PrintError::Validation("printer_id must be 1-50 characters".to_string()));
+        }
+        if host.is_empty() || host.contains(char::is_whitespace) {
+            return Err(PrintError::Validation("host must be a hostname or IP address".to_string()));
+        }
+        if self.port == 0 {
+            return Err(PrintError::Validation("port must be between 1 and 65535".to_string()));
+        }
+        let name = match self.name.trim() {
+            "" => printer_id.clone(),
+            name => name.to_string(),
+        };
+        Ok(LabelPrinter {
+            printer_id,
+            name,
+            host,
+            port: self.port,
+            language: self.language,
+            is_default: self.is_default && self.active,
+            active: self.active,
+            updated_by: username.to_string(),
+            updated_at: Utc::now(),
+        })
+    }
+}
+
+/// What a print job prints
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LabelKind {
+    /// "BULK SUMMARY" label per batch
+    Batch,
+    /// GS1 pallet label with the SSCC barcode
+    Pallet,
+}
+
+impl LabelKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Batch => "BATCH",
+            Self::Pallet => "PALLET",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "PALLET" => Self::Pallet,
+            _ => Self::Batch,
+        }
+    }
+}
+
+/// Progress of a print job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum PrintJobStatus {
+    /// Waiting for its next send (`next_attempt_at`)
+    Queued,
+    /// Being sent by a worker
+    Printing,
+    Printed,
+    /// Every attempt failed; retry or reprint it
+    Failed,
+}
+
+impl PrintJobStatus {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Queued => "QUEUED",
+            Self::Printing => "PRINTING",
+            Self::Printed => "PRINTED",
+            Self::Failed => "FAILED",
+        }
+    }
+
+    pub fn parse(value: &str) -> Self {
+        match value {
+            "PRINTING" => Self::Printing,
+            "PRINTED" => Self::Printed,
+            "FAILED" => Self::Failed,
+            _ => Self::Queued,
+        }
+    }
+}
+
+/// Rendered labels on their way to one printer (tbl_print_jobs)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PrintJob {
+    pub job_id: i64,
+    pub printer_id: String,
+    pub run_no: i32,
+    /// None when the job prints every picked batch of the run
+    pub batch_no: Option<String>,
+    pub label_kind: LabelKind,
+    pub label_count: i32,
+    /// Printer-ready ZPL/EPL, kept so retries and reprints send exactly the same labels
+    #[serde(skip)]
+    pub payload: String,
+    pub status: PrintJobStatus,
+    pub attempts: i32,
+    pub max_attempts: i32,
+    pub last_error: Option<String>,
+    /// The job this one reprints
+    pub reprint_of: Option<i64>,
+    pub reason: Option<String>,
+    pub requested_by: String,
+    pub created_at: DateTime<Utc>,
+    pub next_attempt_at: DateTime<Utc>,
+    pub printed_at: Option<DateTime<Utc>>,
+}
+
+/// A job to queue; it starts QUEUED and due immediately
+#[derive(Debug, Clone, PartialEq)]
+pub struct NewPrintJob {
+    pub printer_id: String,
+    pub run_no: i32,
+    pub batch_no: Option<String>,
+    pub label_kind: LabelKind,
+    pub label_count: i32,
+    pub payload: String,
+    pub max_attempts: i32,
+    pub reprint_of: Option<i64>,
+    pub reason: Option<String>,
+    pub requested_by: String,
+}
+
+/// Result of one send of a claimed job
+#[derive(Debug, Clone, PartialEq)]
+pub enum PrintAttempt {
+    Printed,
+    /// Back to QUEUED until `next_attempt_at`
+    Retry { error: String, next_attempt_at: DateTime<Utc> },
+    /// Out of attempts
+    Failed { error: String },
+}
+
+/// Body of POST /api/bulk-runs/{run_no}/print-jobs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PrintLabelsRequest {
+    /// Registered printer; the default printer when omitted
+    #[serde(default)]
+    pub printer_id: Option<String>,
+    /// One batch instead of every picked batch
+    #[serde(default)]
+    pub batch_no: Option<String>,
+}
+
+/// Body of POST /api/bulk-runs/{run_no}/pallet/{row_num}/print-jobs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PrintPalletRequest {
+    /// Registered printer; the default printer when omitted
+    #[serde(default)]
+    pub printer_id: Option<String>,
+}
+
+/// Query of PUT /api/bulk-runs/{run_no}/print-status; with `printer_id` the run's labels are queued too
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PrintStatusQuery {
+    pub printer_id: Option<String>,
+}
+
+/// Body of POST /api/print-jobs/{job_id}/reprint
+#[derive(Debug, Clone, Deserialize)]
+pub struct ReprintRequest {
+    /// Why the labels are printed again (damaged, lost, printer jam...)
+    pub reason: String,
+    /// Another printer of the same language; the original printer when omitted
+    #[serde(default)]
+    pub printer_id: Option<String>,
+}
+
+/// Query of GET /api/print-jobs
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct PrintJobQuery {
+    pub run_no: Option<i32>,
+    pub limit: Option<u32>,
+}
+
+/// Errors from the printer registry and print job queue
+#[derive(Debug, thiserror::Error)]
+pub enum PrintError {
+    #[error("Printer '{printer_id}' is not registered")]
+    PrinterNotFound { printer_id: String },
+
+    #[error("Printer '{printer_id}' is inactive")]
+    PrinterInactive { printer_id: String },
+
+    #[error("No printer given and no default printer is registered")]
+    NoDefaultPrinter,
+
+    #[error("Print job {job_id} not found")]
+    JobNotFound { job_id: i64 },
+
+    #[error("Print job {job_id} is {status}; only FAILED jobs can be retried")]
+    JobNotRetryable { job_id: i64, status: &'static str },
+
+    #[error("Print job {job_id} holds {job} labels and cannot be sent to {printer} printer '{printer_id}'")]
+    LanguageMismatch {
+        job_id: i64,
+        job: &'static str,
+        printer: &'static str,
+        printer_id: String,
+    },
+
+    #[error("Validation failed: {0}")]
+    Validation(String),
+
+    /// Building the labels failed (run not found, nothing picked...)
+    #[error(transparent)]
+    Labels(#[from] BulkRunError),
+
+    #[error("{0:#}")]
+    Database(#[from] anyhow::Error),
+}
+
+impl PrintError {
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::PrinterNotFound { .. } => "PRINTER_NOT_FOUND",
+            Self::PrinterInactive { .. } => "PRINTER_INACTIVE",
+            Self::NoDefaultPrinter => "NO_DEFAULT_PRINTER",
+            Self::JobNotFound { .. } => "PRINT_JOB_NOT_FOUND",
+            Self::JobNotRetryable { .. } => "PRINT_JOB_NOT_RETRYABLE",
+            Self::LanguageMismatch { .. } => "PRINTER_LANGUAGE_MISMATCH",
+            Self::Validation(_) => "VALIDATION_ERROR",
+            Self::Labels(e) => e.error_code(),
+            Self::Database(_) => "DATABASE_ERROR",
+        }
+    }
+
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+
+        match self {
+            Self::PrinterNotFound { .. } | Self::JobNotFound { .. } => StatusCode::NOT_FOUND,
+            Self::PrinterInactive { .. } | Self::JobNotRetryable { .. } => StatusCode::CONFLICT,
+            Self::NoDefaultPrinter | Self::LanguageMismatch { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::Labels(e) => e.status_code(),
+            Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_delay_doubles_up_to_cap() {
+        assert_eq!(retry_delay(1), Duration::seconds(10));
+        assert_eq!(retry_delay(2), Duration::seconds(20));
+        assert_eq!(retry_delay(4), Duration::seconds(80));
+        assert_eq!(retry_delay(10), Duration::seconds(300));
+    }
+
+    #[test]
+    fn test_register_request_is_validated() {
+        let request = |host: &str, port: u16| RegisterPrinterRequest {
+            printer_id: " ZB-DOCK1 ".to_string(),
+            name: String::new(),
+            host: host.to_string(),
+            port,
+            language: PrinterLanguage::Zpl,
+            is_default: true,
+            active: true,
+        };
+
+        let printer = request("192.168.0.50", 9100).into_printer("supervisor").unwrap();
+        assert_eq!(printer.printer_id, "ZB-DOCK1");
+        assert_eq!(printer.name, "ZB-DOCK1");
+        assert!(printer.is_default);
+        assert!(matches!(request("", 9100).into_printer("supervisor"), Err(PrintError::Validation(_))));
+        assert!(matches!(request("10.0.0.1", 0).into_printer("supervisor"), Err(PrintError::Validation(_))));
+    }
+}
@@ -1,6 +1,9 @@
+use axum::extract::rejection::JsonRejection;
 use serde::{Deserialize, Serialize};
 use chrono::{DateTime, Utc};
 
+use crate::models::idempotency::{IdempotencyKey, ReplayError};
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct LotSearchResult {
     pub lot_no: String,
@@ -31,9 +34,14 @@ pub struct BinTransferRequest {
     pub bin_from: String,
     pub bin_to: String,
     pub transfer_qty: f64,
+    /// Authenticated username, set by the handler from the verified token (never the request body)
+    #[serde(default, skip_deserializing)]
     pub user_id: String,
     pub remarks: Option<String>,
     pub referenced: Option<String>,
+    /// Set by the handler from the `Idempotency-Key` header
+    #[serde(skip)]
+    pub idempotency_key: Option<IdempotencyKey>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -100,10 +108,71 @@ pub enum PutawayError {
 
     #[error("Validation error: {0}")]
     ValidationError(String),
+
+    #[error("Idempotency-Key was already used for a different transfer request")]
+    IdempotencyKeyReused,
+}
+
+impl From<ReplayError> for PutawayError {
+    fn from(error: ReplayError) -> Self {
+        match error {
+            ReplayError::KeyReused => Self::IdempotencyKeyReused,
+            ReplayError::Corrupt(e) => Self::DatabaseError(e.to_string()),
+        }
+    }
+}
+
+/// Malformed transfer bodies answer in the ApiResponse envelope like every other putaway error
+impl From<JsonRejection> for PutawayError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::ValidationError(rejection.body_text())
+    }
+}
+
+impl PutawayError {
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::LotNotFound { .. } => "LOT_NOT_FOUND",
+            Self::InvalidBin { .. } => "INVALID_BIN",
+            Self::InsufficientQuantity { .. } => "INSUFFICIENT_QUANTITY",
+            Self::ValidationError(_) => "VALIDATION_ERROR",
+            Self::TransactionError(_) => "TRANSACTION_FAILED",
+            Self::DatabaseError(_) => "DATABASE_ERROR",
+            Self::IdempotencyKeyReused => "IDEMPOTENCY_KEY_REUSED",
+        }
+    }
+
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+
+        match self {
+            Self::LotNotFound { .. } => StatusCode::NOT_FOUND,
+            Self::InvalidBin { .. } | Self::ValidationError(_) => StatusCode::BAD_REQUEST,
+            Self::InsufficientQuantity { .. } | Self::IdempotencyKeyReused => StatusCode::UNPROCESSABLE_ENTITY,
+            Self::TransactionError(_) | Self::DatabaseError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    /// Message safe to show operators; SQL details of server-side failures stay in the logs
+    pub fn client_message(&self) -> String {
+        match self {
+            Self::LotNotFound { lot_no } => format!("Lot '{lot_no}' not found or has zero quantity"),
+            Self::InvalidBin { bin_no, location } => {
+                format!("Bin '{bin_no}' is not valid in location '{location}'")
+            }
+            Self::InsufficientQuantity { requested, available } => {
+                format!("Requested {requested} but only {available} available")
+            }
+            Self::ValidationError(msg) => msg.clone(),
+            Self::TransactionError(_) => "Failed to complete transfer transaction".to_string(),
+            Self::DatabaseError(_) => "Internal server error occurred".to_string(),
+            Self::IdempotencyKeyReused => self.to_string(),
+        }
+    }
 }
 
 // Internal database models
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct LotMasterRecord {
     pub lot_no: String,
@@ -122,7 +191,7 @@ pub struct LotMasterRecord {
     pub lot_status: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 #[allow(dead_code)]
 pub struct ItemMasterRecord {
     pub item_key: String,
@@ -235,3 +304,24 @@ pub fn map_inclasskey_to_inacct(inclasskey: &str) -> &str {
         _ => "1100",         // Default to inventory asset account
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+
+    #[test]
+    fn test_putaway_error_mapping() {
+        let not_found = PutawayError::LotNotFound { lot_no: "2510001".to_string() };
+        assert_eq!(not_found.status_code(), StatusCode::NOT_FOUND);
+        assert_eq!(not_found.error_code(), "LOT_NOT_FOUND");
+
+        let short = PutawayError::InsufficientQuantity { requested: 10.0, available: 4.0 };
+        assert_eq!(short.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(short.client_message(), "Requested 10 but only 4 available");
+
+        let db = PutawayError::DatabaseError("Login failed for user 'sa'".to_string());
+        assert_eq!(db.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+        assert!(!db.client_message().contains("Login failed"));
+    }
+}
@@ -0,0 +1,94 @@
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::env;
+
+/// Lease length when RUN_CLAIM_LEASE_MINUTES is not set
+pub const DEFAULT_CLAIM_LEASE_MINUTES: i64 = 10;
+
+/// How long a claim lasts without being renewed. Handhelds renew by claiming again.
+pub fn claim_lease() -> Duration {
+    let minutes = env::var("RUN_CLAIM_LEASE_MINUTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .filter(|&minutes: &i64| minutes > 0)
+        .unwrap_or(DEFAULT_CLAIM_LEASE_MINUTES);
+    Duration::minutes(minutes)
+}
+
+/// A picker's lease on a whole run or on one ingredient line of it (tbl_bulk_run_claims)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RunClaim {
+    pub run_no: i32,
+    /// None when the whole run is claimed
+    pub line_id: Option<i32>,
+    pub username: String,
+    pub claimed_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl RunClaim {
+    /// Whether this claim keeps `username` from working `line_id` (None = the whole run).
+    /// A run claim overlaps every line; line claims only overlap the same line.
+    pub fn blocks(&self, username: &str, line_id: Option<i32>) -> bool {
+        self.username != username
+            && (self.line_id.is_none() || line_id.is_none() || self.line_id == line_id)
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+/// Body of POST /api/bulk-runs/{run_no}/claim
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ClaimRequest {
+    /// Claim one ingredient line instead of the whole run
+    #[serde(default)]
+    pub line_id: Option<i32>,
+    /// Supervisor only: end overlapping claims held by others
+    #[serde(default)]
+    pub take_over: bool,
+}
+
+/// Query of DELETE /api/bulk-runs/{run_no}/claim
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ReleaseClaimQuery {
+    pub line_id: Option<i32>,
+}
+
+/// Result of trying to take or renew a claim
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClaimOutcome {
+    Acquired(RunClaim),
+    /// An overlapping claim belongs to someone else
+    HeldBy(RunClaim),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn claim(username: &str, line_id: Option<i32>) -> RunClaim {
+        let now = Utc::now();
+        RunClaim {
+            run_no: 215235,
+            line_id,
+            username: username.to_string(),
+            claimed_at: now,
+            expires_at: now + Duration::minutes(DEFAULT_CLAIM_LEASE_MINUTES),
+        }
+    }
+
+    #[test]
+    fn test_claim_overlap() {
+        let run = claim("picker01", None);
+        assert!(run.blocks("picker02", Some(1)));
+        assert!(run.blocks("picker02", None));
+        assert!(!run.blocks("picker01", Some(1)));
+
+        let line = claim("picker01", Some(1));
+        assert!(line.blocks("picker02", Some(1)));
+        assert!(line.blocks("picker02", None));
+        assert!(!line.blocks("picker02", Some(2)));
+    }
+}
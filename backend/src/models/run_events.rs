@@ -0,0 +1,90 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// What happened on a run. Serialized with a `type` tag, which is also the SSE event name.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RunEventKind {
+    Pick {
+        row_num: i32,
+        line_id: i32,
+        lot_no: String,
+        bin_no: String,
+        picked_bulk_qty: BigDecimal,
+        document_no: Option<String>,
+    },
+    /// `row_num` / `line_id` are absent when every lot of the run was unpicked
+    Unpick {
+        row_num: Option<i32>,
+        line_id: Option<i32>,
+        lot_no: Option<String>,
+    },
+    PalletCompleted {
+        row_num: i32,
+        line_id: i32,
+    },
+    IngredientCompleted {
+        line_id: i32,
+        item_key: String,
+    },
+    RunStatusChanged {
+        from: String,
+        to: String,
+    },
+}
+
+impl RunEventKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Pick { .. } => "pick",
+            Self::Unpick { .. } => "unpick",
+            Self::PalletCompleted { .. } => "pallet_completed",
+            Self::IngredientCompleted { .. } => "ingredient_completed",
+            Self::RunStatusChanged { .. } => "run_status_changed",
+        }
+    }
+}
+
+/// Change to a bulk run, pushed to every handheld watching it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RunEvent {
+    pub run_no: i32,
+    #[serde(flatten)]
+    pub kind: RunEventKind,
+    /// Username that made the change
+    pub user_id: String,
+    pub occurred_at: DateTime<Utc>,
+}
+
+impl RunEvent {
+    pub fn new(run_no: i32, user_id: &str, kind: RunEventKind) -> Self {
+        Self {
+            run_no,
+            kind,
+            user_id: user_id.to_string(),
+            occurred_at: Utc::now(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_event_serializes_flat_with_type_tag() {
+        let event = RunEvent::new(
+            215235,
+            "deachawat",
+            RunEventKind::PalletCompleted { row_num: 2, line_id: 1 },
+        );
+        let json = serde_json::to_value(&event).unwrap();
+
+        assert_eq!(json["type"], "pallet_completed");
+        assert_eq!(json["type"], event.kind.name());
+        assert_eq!(json["run_no"], 215235);
+        assert_eq!(json["row_num"], 2);
+        assert_eq!(json["user_id"], "deachawat");
+    }
+}
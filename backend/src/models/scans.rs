@@ -0,0 +1,156 @@
+use bigdecimal::BigDecimal;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::models::bulk_runs::LotSearchResult;
+use crate::utils::gs1::{self, ElementString};
+
+/// An INMAST item a scan resolved to
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScannedItem {
+    pub item_key: String,
+    pub description: String,
+}
+
+/// A supplier GTIN mapped to the item it identifies (tbl_item_gtin)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ItemGtin {
+    /// GTIN-14
+    pub gtin: String,
+    pub item_key: String,
+    pub registered_by: String,
+    pub registered_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegisterGtinRequest {
+    /// GTIN-8, 12, 13 or 14; stored as GTIN-14
+    pub gtin: String,
+    pub item_key: String,
+}
+
+impl RegisterGtinRequest {
+    pub fn into_gtin(self, username: &str) -> Result<ItemGtin, ScanError> {
+        let gtin = gs1::normalize_gtin(&self.gtin).map_err(ScanError::InvalidBarcode)?;
+        let item_key = self.item_key.trim().to_string();
+        if item_key.is_empty() {
+            return Err(ScanError::Validation("item_key is required".to_string()));
+        }
+        Ok(ItemGtin { gtin, item_key, registered_by: username.to_string(), registered_at: Utc::now() })
+    }
+}
+
+/// One LotMaster row (a lot in one bin)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LotMasterEntry {
+    pub lot_no: String,
+    pub item_key: String,
+    pub location_key: String,
+    pub bin_no: String,
+    pub date_expiry: Option<NaiveDate>,
+    pub qty_on_hand: BigDecimal,
+    pub qty_commit_sales: BigDecimal,
+    pub lot_status: String,
+}
+
+/// How a scan disagrees with the item master, LotMaster or what the screen expects
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum MismatchKind {
+    /// The (01) GTIN is not mapped to an item
+    UnknownGtin,
+    /// A plain code that is neither a lot number nor an item key
+    UnknownCode,
+    /// The lot number is not in LotMaster
+    LotNotFound,
+    /// LotMaster has the lot under a different item than the GTIN's
+    LotItemMismatch,
+    /// The (17) expiry differs from LotMaster DateExpiry
+    ExpiryMismatch,
+    /// The item is not the one being picked or put away
+    UnexpectedItem,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScanMismatch {
+    pub kind: MismatchKind,
+    pub message: String,
+}
+
+impl ScanMismatch {
+    pub fn new(kind: MismatchKind, message: impl Into<String>) -> Self {
+        Self { kind, message: message.into() }
+    }
+}
+
+/// What one scan resolves to: item, lot and expiry, checked against LotMaster
+#[derive(Debug, Clone, Serialize)]
+pub struct ScanResolution {
+    pub code: String,
+    /// Decoded GS1 Application Identifiers; None for a plain lot number or item key
+    pub gs1: Option<ElementString>,
+    pub item_key: Option<String>,
+    pub item_description: Option<String>,
+    pub lot_no: Option<String>,
+    /// From AI (17) when the label carries it, otherwise LotMaster's
+    pub expiry_date: Option<NaiveDate>,
+    /// From AI (310n)
+    pub net_weight_kg: Option<BigDecimal>,
+    /// LotMaster rows of the lot for the resolved item
+    pub lots: Vec<LotMasterEntry>,
+    /// Bins the lot can be picked from for the run; picking scans only
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pick_bins: Option<Vec<LotSearchResult>>,
+    pub mismatches: Vec<ScanMismatch>,
+}
+
+impl ScanResolution {
+    /// True when nothing in the scan disagrees with LotMaster or the expected item
+    pub fn is_verified(&self) -> bool {
+        self.mismatches.is_empty()
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct ScanQuery {
+    pub code: String,
+    /// The item the screen expects; a different item is flagged UNEXPECTED_ITEM
+    pub item_key: Option<String>,
+}
+
+/// Errors from scan resolution and GTIN registration
+#[derive(Debug, thiserror::Error)]
+pub enum ScanError {
+    #[error("Invalid barcode: {0}")]
+    InvalidBarcode(String),
+
+    #[error("Item '{item_key}' not found")]
+    ItemNotFound { item_key: String },
+
+    #[error("Validation failed: {0}")]
+    Validation(String),
+
+    #[error("{0:#}")]
+    Database(#[from] anyhow::Error),
+}
+
+impl ScanError {
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::InvalidBarcode(_) => "INVALID_BARCODE",
+            Self::ItemNotFound { .. } => "ITEM_NOT_FOUND",
+            Self::Validation(_) => "VALIDATION_ERROR",
+            Self::Database(_) => "DATABASE_ERROR",
+        }
+    }
+
+    pub fn status_code(&self) -> axum::http::StatusCode {
+        use axum::http::StatusCode;
+
+        match self {
+            Self::InvalidBarcode(_) | Self::Validation(_) => StatusCode::BAD_REQUEST,
+            Self::ItemNotFound { .. } => StatusCode::NOT_FOUND,
+            Self::Database(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+}
@@ -0,0 +1,153 @@
+use chrono::NaiveDateTime;
+use serde::{Deserialize, Serialize};
+use std::net::IpAddr;
+
+use crate::models::bulk_runs::PaginationInfo;
+
+/// Kind of security event written to tbl_security_audit
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SecurityEventType {
+    LoginSuccess,
+    LoginFailure,
+    LoginThrottled,
+    /// LDAP rejected the user and the SQL fallback was attempted
+    SqlFallback,
+    TokenRejected,
+    ApiKeyRejected,
+    RefreshReuse,
+    Logout,
+}
+
+impl SecurityEventType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LoginSuccess => "login_success",
+            Self::LoginFailure => "login_failure",
+            Self::LoginThrottled => "login_throttled",
+            Self::SqlFallback => "sql_fallback",
+            Self::TokenRejected => "token_rejected",
+            Self::ApiKeyRejected => "api_key_rejected",
+            Self::RefreshReuse => "refresh_reuse",
+            Self::Logout => "logout",
+        }
+    }
+}
+
+/// How the caller tried to authenticate
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthMethod {
+    Ldap,
+    Sql,
+    Jwt,
+    ApiKey,
+    RefreshToken,
+}
+
+impl AuthMethod {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Ldap => "ldap",
+            Self::Sql => "sql",
+            Self::Jwt => "jwt",
+            Self::ApiKey => "api_key",
+            Self::RefreshToken => "refresh_token",
+        }
+    }
+}
+
+/// Result of the audited action
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuditOutcome {
+    Success,
+    Failure,
+    Blocked,
+}
+
+impl AuditOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Success => "success",
+            Self::Failure => "failure",
+            Self::Blocked => "blocked",
+        }
+    }
+}
+
+/// Event to persist
+#[derive(Debug, Clone)]
+pub struct SecurityEvent {
+    pub event_type: SecurityEventType,
+    pub username: Option<String>,
+    pub source_ip: Option<IpAddr>,
+    pub auth_method: Option<AuthMethod>,
+    pub outcome: AuditOutcome,
+    pub detail: Option<String>,
+}
+
+impl SecurityEvent {
+    pub fn new(event_type: SecurityEventType, outcome: AuditOutcome) -> Self {
+        Self {
+            event_type,
+            username: None,
+            source_ip: None,
+            auth_method: None,
+            outcome,
+            detail: None,
+        }
+    }
+
+    pub fn username(mut self, username: impl Into<String>) -> Self {
+        self.username = Some(username.into());
+        self
+    }
+
+    pub fn source_ip(mut self, ip: IpAddr) -> Self {
+        self.source_ip = Some(ip);
+        self
+    }
+
+    pub fn method(mut self, method: AuthMethod) -> Self {
+        self.auth_method = Some(method);
+        self
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+}
+
+/// Stored audit row returned to supervisors
+#[derive(Debug, Clone, Serialize)]
+pub struct SecurityAuditEntry {
+    pub audit_id: i64,
+    pub event_time: NaiveDateTime,
+    pub event_type: String,
+    pub username: Option<String>,
+    pub source_ip: Option<String>,
+    pub auth_method: Option<String>,
+    pub outcome: String,
+    pub detail: Option<String>,
+}
+
+/// Filters for GET /api/auth/security-events
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SecurityAuditQuery {
+    pub page: Option<u32>,
+    pub limit: Option<u32>,
+    pub username: Option<String>,
+    pub event_type: Option<SecurityEventType>,
+    pub source_ip: Option<String>,
+    /// Inclusive lower bound (UTC)
+    pub from: Option<NaiveDateTime>,
+    /// Exclusive upper bound (UTC)
+    pub to: Option<NaiveDateTime>,
+}
+
+/// Paginated security audit response
+#[derive(Debug, Serialize)]
+pub struct PaginatedSecurityAudit {
+    pub events: Vec<SecurityAuditEntry>,
+    pub pagination: PaginationInfo,
+}
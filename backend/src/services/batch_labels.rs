@@ -0,0 +1,714 @@
+//! Server-side rendering of the 4x4 inch "BULK SUMMARY" batch label and the GS1 pallet label.
+//!
+//! Labels are built from the run header, `get_batch_weight_summary` (for each batch's
+//! RowNum, which is its page number) and `get_lot_picking_details_for_run` (one line per
+//! picked lot/bin). Every label is laid out once on a 812x812 dot grid (4 inches at
+//! 203 dpi) and that same layout is emitted as ZPL, EPL or PDF, so a Zebra printer and a
+//! browser print identical labels. The pallet label carries the pallet's SSCC as a
+//! GS1-128 barcode, which ZPL and EPL printers encode themselves and PDF draws as bars.
+
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use chrono::DateTime;
+use chrono_tz::Tz;
+use std::fmt::Write;
+
+use crate::models::batch_labels::{BatchLabel, BatchLabelLine};
+use crate::models::bulk_runs::{BatchWeightSummaryItem, BulkRun, LotPickingDetail};
+use crate::models::pallet_labels::{PalletLabel, PalletSscc};
+
+/// 4 inches at 203 dpi
+const LABEL_DOTS: u32 = 812;
+/// 4 inches in PDF points
+const LABEL_POINTS: f64 = 288.0;
+const MARGIN: u32 = 20;
+
+/// Picked lots that fit between the table header and the footer
+pub const LINES_PER_LABEL: usize = 7;
+/// The pallet label gives the lower part of the label to its barcode
+pub const PALLET_LINES_PER_LABEL: usize = 4;
+const LINE_TOP: u32 = 262;
+const LINE_HEIGHT: u32 = 58;
+const FOOTER_TOP: u32 = 694;
+const PALLET_FOOTER_TOP: u32 = 500;
+
+/// GS1-128 module width in dots: 0.5 mm at 203 dpi, above the SSCC minimum of 0.495 mm
+const BARCODE_MODULE: u32 = 4;
+const BARCODE_HEIGHT: u32 = 150;
+
+/// Table columns (dots from the left edge)
+const COL_ITEM: u32 = 30;
+const COL_LOT: u32 = 230;
+const COL_BAG: u32 = 470;
+const COL_QTY: u32 = 560;
+const COL_UNIT: u32 = 710;
+
+/// Build one label per batch that has picked lots, in page order. A batch with more
+/// than `LINES_PER_LABEL` lots continues on further sheets with the same page number.
+pub fn build_batch_labels(
+    run: &BulkRun,
+    summary: &[BatchWeightSummaryItem],
+    lots: &[LotPickingDetail],
+    printed_at: DateTime<Tz>,
+) -> Vec<BatchLabel> {
+    build_labels(run, summary, lots, printed_at, LINES_PER_LABEL)
+}
+
+/// Labels of one pallet (batch row), each sheet carrying the pallet's SSCC barcode
+pub fn build_pallet_labels(
+    run: &BulkRun,
+    summary: &[BatchWeightSummaryItem],
+    lots: &[LotPickingDetail],
+    pallet: &PalletSscc,
+    printed_at: DateTime<Tz>,
+) -> Vec<PalletLabel> {
+    let lots: Vec<LotPickingDetail> = lots
+        .iter()
+        .filter(|lot| lot.batch_no.trim() == pallet.batch_no.trim())
+        .cloned()
+        .collect();
+    build_labels(run, summary, &lots, printed_at, PALLET_LINES_PER_LABEL)
+        .into_iter()
+        .map(|mut batch| {
+            batch.page = pallet.row_num;
+            PalletLabel { sscc: pallet.sscc.clone(), batch }
+        })
+        .collect()
+}
+
+fn build_labels(
+    run: &BulkRun,
+    summary: &[BatchWeightSummaryItem],
+    lots: &[LotPickingDetail],
+    printed_at: DateTime<Tz>,
+    lines_per_label: usize,
+) -> Vec<BatchLabel> {
+    let mut batch_nos: Vec<&str> = Vec::new();
+    for lot in lots {
+        if !batch_nos.contains(&lot.batch_no.as_str()) {
+            batch_nos.push(lot.batch_no.as_str());
+        }
+    }
+
+    // Page is the batch's RowNum; batches missing from the summary follow in BatchNo order
+    let mut pages: Vec<(i32, &str)> = batch_nos
+        .iter()
+        .map(|batch_no| {
+            let row_num = summary
+                .iter()
+                .find(|item| item.batch_no == *batch_no)
+                .map(|item| item.row_num)
+                .unwrap_or(i32::MAX);
+            (row_num, *batch_no)
+        })
+        .collect();
+    pages.sort();
+
+    let date = printed_at.format("%d/%m/%y").to_string();
+    let printed = printed_at.format("%d/%m/%Y, %-I:%M:%S%p").to_string();
+    let mut labels = Vec::new();
+
+    for (index, (row_num, batch_no)) in pages.into_iter().enumerate() {
+        let page = if row_num == i32::MAX { index as i32 + 1 } else { row_num };
+        let mut batch_lots: Vec<&LotPickingDetail> = lots.iter().filter(|lot| lot.batch_no == batch_no).collect();
+        batch_lots.sort_by(|a, b| a.line_id.cmp(&b.line_id).then_with(|| a.lot_no.cmp(&b.lot_no)));
+
+        let picked_by = batch_lots
+            .iter()
+            .max_by(|a, b| a.rec_date.cmp(&b.rec_date))
+            .map(|lot| {
+                let user = if lot.rec_userid.trim().is_empty() { &lot.modified_by } else { &lot.rec_userid };
+                user.trim().to_string()
+            })
+            .unwrap_or_default();
+
+        let lines: Vec<BatchLabelLine> = batch_lots
+            .iter()
+            .map(|lot| BatchLabelLine {
+                line_id: lot.line_id,
+                item_key: lot.item_key.trim().to_string(),
+                lot_no: lot.lot_no.trim().to_string(),
+                bin_no: lot.bin_no.trim().to_string(),
+                bags: if lot.pack_size.is_zero() {
+                    lot.picked_bulk_qty.clone()
+                } else {
+                    &lot.qty_received / &lot.pack_size
+                },
+                qty: lot.qty_received.clone(),
+                pack_size: lot.pack_size.clone(),
+                unit: "KG".to_string(),
+            })
+            .collect();
+
+        let sheets = lines.len().div_ceil(lines_per_label).max(1);
+        for (sheet, chunk) in lines.chunks(lines_per_label).enumerate() {
+            labels.push(BatchLabel {
+                run_no: run.run_no,
+                batch_no: batch_no.to_string(),
+                product_key: run.formula_id.trim().to_string(),
+                product_desc: run.formula_desc.trim().to_string(),
+                page,
+                pages: run.no_of_batches.max(page),
+                sheet: sheet + 1,
+                sheets,
+                date: date.clone(),
+                picked_by: picked_by.clone(),
+                printed_at: printed.clone(),
+                lines: chunk.to_vec(),
+            });
+        }
+    }
+
+    labels
+}
+
+/// What gets drawn on a label, in dots from the top-left corner
+#[derive(Debug, Clone, PartialEq)]
+pub enum Mark {
+    Text { x: u32, y: u32, height: u32, bold: bool, centered: bool, text: String },
+    /// Filled horizontal rule
+    Rule { x: u32, y: u32, width: u32, thickness: u32 },
+    /// Empty tick box for manual verification
+    Box { x: u32, y: u32, size: u32, thickness: u32 },
+    /// GS1-128 barcode of an even number of digits (Application Identifiers and their
+    /// data), centred horizontally; `module` is the narrowest bar in dots
+    Gs1Barcode { y: u32, height: u32, module: u32, digits: String },
+}
+
+/// A label that can be rendered as ZPL, EPL or PDF
+pub trait LabelLayout {
+    fn layout(&self) -> Vec<Mark>;
+}
+
+fn text(x: u32, y: u32, height: u32, text: impl Into<String>) -> Mark {
+    Mark::Text { x, y, height, bold: false, centered: false, text: text.into() }
+}
+
+fn bold(x: u32, y: u32, height: u32, text: impl Into<String>) -> Mark {
+    Mark::Text { x, y, height, bold: true, centered: false, text: text.into() }
+}
+
+fn centered(y: u32, height: u32, bold: bool, text: impl Into<String>) -> Mark {
+    Mark::Text { x: 0, y, height, bold, centered: true, text: text.into() }
+}
+
+fn rule(y: u32, thickness: u32) -> Mark {
+    Mark::Rule { x: MARGIN, y, width: LABEL_DOTS - 2 * MARGIN, thickness }
+}
+
+fn clip(value: &str, max_chars: usize) -> String {
+    value.chars().take(max_chars).collect()
+}
+
+/// Whole bags print without decimals, part bags and weights with two
+fn format_qty(value: &BigDecimal, whole_if_integer: bool) -> String {
+    let value = value.to_f64().unwrap_or(0.0);
+    if whole_if_integer && value.fract() == 0.0 {
+        format!("{value:.0}")
+    } else {
+        format!("{value:.2}")
+    }
+}
+
+impl LabelLayout for BatchLabel {
+    fn layout(&self) -> Vec<Mark> {
+        let mut marks = header(self, "BULK SUMMARY", self.page_info());
+        marks.extend(lot_lines(&self.lines));
+        marks.extend([
+            rule(FOOTER_TOP, 2),
+            text(30, FOOTER_TOP + 10, 24, format!("Picked/Printed by: {}", self.picked_by)),
+            text(30, FOOTER_TOP + 40, 24, "Verified by: _______________"),
+            text(30, FOOTER_TOP + 70, 24, format!("Date: {}", self.printed_at)),
+        ]);
+        marks
+    }
+}
+
+impl LabelLayout for PalletLabel {
+    fn layout(&self) -> Vec<Mark> {
+        let label = &self.batch;
+        let pallet_info = if label.sheets > 1 {
+            format!("Pallet {} of {} ({}/{})", label.page, label.pages, label.sheet, label.sheets)
+        } else {
+            format!("Pallet {} of {}", label.page, label.pages)
+        };
+        let mut marks = header(label, "PALLET", pallet_info);
+        marks.extend(lot_lines(&label.lines));
+        marks.extend([
+            rule(PALLET_FOOTER_TOP, 2),
+            text(30, PALLET_FOOTER_TOP + 10, 24, format!("Picked by: {}", label.picked_by)),
+            text(420, PALLET_FOOTER_TOP + 10, 24, format!("Date: {}", label.date)),
+            Mark::Gs1Barcode {
+                y: PALLET_FOOTER_TOP + 50,
+                height: BARCODE_HEIGHT,
+                module: BARCODE_MODULE,
+                digits: self.barcode_data(),
+            },
+            centered(PALLET_FOOTER_TOP + 50 + BARCODE_HEIGHT + 12, 30, true, self.human_readable()),
+        ]);
+        marks
+    }
+}
+
+/// Title, product, run/batch and the table header shared by both labels
+fn header(label: &BatchLabel, title: &str, page_info: String) -> Vec<Mark> {
+    vec![
+        centered(18, 44, true, title),
+        centered(66, 28, false, clip(&label.product_desc, 40)),
+        rule(102, 3),
+        text(30, 114, 26, format!("Product: {}", label.product_key)),
+        text(30, 146, 26, format!("Run: {}", label.run_no)),
+        text(420, 146, 26, format!("Date: {}", label.date)),
+        text(30, 178, 26, format!("Batch: {}", label.batch_no)),
+        text(420, 178, 26, page_info),
+        rule(212, 3),
+        bold(COL_ITEM, 222, 24, "Item No."),
+        bold(COL_LOT, 222, 24, "Lot-No"),
+        bold(COL_BAG, 222, 24, "Bag"),
+        bold(COL_QTY, 222, 24, "QTY"),
+        bold(COL_UNIT, 222, 24, "UM"),
+        rule(252, 2),
+    ]
+}
+
+fn lot_lines(lines: &[BatchLabelLine]) -> Vec<Mark> {
+    let mut marks = Vec::with_capacity(lines.len() * 9);
+    for (index, line) in lines.iter().enumerate() {
+        let y = LINE_TOP + index as u32 * LINE_HEIGHT;
+        marks.extend([
+            text(COL_ITEM, y, 26, clip(&line.item_key, 12)),
+            text(COL_LOT, y, 26, clip(&line.lot_no, 15)),
+            Mark::Box { x: COL_BAG, y: y + 2, size: 22, thickness: 2 },
+            text(COL_BAG + 30, y, 26, format_qty(&line.bags, true)),
+            text(COL_QTY, y, 26, format_qty(&line.qty, false)),
+            text(COL_UNIT, y, 26, clip(&line.unit, 4)),
+            text(COL_LOT, y + 28, 22, clip(&line.bin_no, 15)),
+            text(COL_QTY, y + 28, 22, format_qty(&line.pack_size, false)),
+            rule(y + LINE_HEIGHT - 4, 1),
+        ]);
+    }
+    marks
+}
+
+/// Code 128 bar/space widths in modules for symbol values 0-105
+const CODE128_PATTERNS: [&str; 106] = [
+    "212222", "222122", "222221", "121223", "121322", "131222", "122213", "122312", "132212", "221213",
+    "221312", "231212", "112232", "122132", "122231", "113222", "123122", "123221", "223211", "221132",
+    "221231", "213212", "223112", "312131", "311222", "321122", "321221", "312212", "322112", "322211",
+    "212123", "212321", "232121", "111323", "131123", "131321", "112313", "132113", "132311", "211313",
+    "231113", "231311", "112133", "112331", "132131", "113123", "113321", "133121", "313121", "211331",
+    "231131", "213113", "213311", "213131", "311123", "311321", "331121", "312113", "312311", "332111",
+    "314111", "221411", "431111", "111224", "111422", "121124", "121421", "141122", "141221", "112214",
+    "112412", "122114", "122411", "142112", "142211", "241211", "221114", "413111", "241112", "134111",
+    "111242", "121142", "121241", "114212", "124112", "124211", "411212", "421112", "421211", "212141",
+    "214121", "412121", "111143", "111341", "131141", "114113", "114311", "411113", "411311", "113141",
+    "114131", "311141", "411131", "211412", "211214", "211232",
+];
+const CODE128_STOP: &str = "2331112";
+const CODE128_START_C: usize = 105;
+const CODE128_FNC1: usize = 102;
+
+/// GS1-128 in code set C: start C, FNC1, two digits per symbol, check symbol, stop.
+/// Returns alternating bar/space widths in modules, starting with a bar.
+fn gs1_128_modules(digits: &str) -> Option<Vec<u32>> {
+    if digits.is_empty() || !digits.len().is_multiple_of(2) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let mut values = vec![CODE128_START_C, CODE128_FNC1];
+    values.extend(digits.as_bytes().chunks(2).map(|pair| usize::from((pair[0] - b'0') * 10 + (pair[1] - b'0'))));
+    let check = values
+        .iter()
+        .enumerate()
+        .map(|(position, value)| position.max(1) * value)
+        .sum::<usize>()
+        % 103;
+    values.push(check);
+
+    Some(
+        values
+            .iter()
+            .flat_map(|&value| CODE128_PATTERNS[value].bytes())
+            .chain(CODE128_STOP.bytes())
+            .map(|b| u32::from(b - b'0'))
+            .collect(),
+    )
+}
+
+/// Width in dots of a GS1-128 barcode of `digits`, quiet zones excluded
+fn gs1_128_width(digits: &str, module: u32) -> u32 {
+    gs1_128_modules(digits).map_or(0, |widths| widths.iter().sum::<u32>() * module)
+}
+
+/// ZPL II, one ^XA..^XZ format per label
+pub fn render_zpl<L: LabelLayout>(labels: &[L]) -> String {
+    let mut zpl = String::new();
+    for label in labels {
+        zpl.push_str(&format!("^XA\n^CI28\n^PW{LABEL_DOTS}\n^LL{LABEL_DOTS}\n^LH0,0\n"));
+        for mark in label.layout() {
+            let _ = match mark {
+                Mark::Text { x, y, height, centered: true, text, .. } => writeln!(
+                    zpl,
+                    "^FO{x},{y}^FB{LABEL_DOTS},1,0,C^A0N,{height},{height}^FH\\^FD{}^FS",
+                    zpl_escape(&text)
+                ),
+                Mark::Text { x, y, height, text, .. } => {
+                    writeln!(zpl, "^FO{x},{y}^A0N,{height},{height}^FH\\^FD{}^FS", zpl_escape(&text))
+                }
+                Mark::Rule { x, y, width, thickness } => {
+                    writeln!(zpl, "^FO{x},{y}^GB{width},{thickness},{thickness}^FS")
+                }
+                Mark::Box { x, y, size, thickness } => writeln!(zpl, "^FO{x},{y}^GB{size},{size},{thickness}^FS"),
+                // >; starts code set C and >8 is FNC1, which makes Code 128 a GS1-128 symbol
+                Mark::Gs1Barcode { y, height, module, digits } => writeln!(
+                    zpl,
+                    "^FO{},{y}^BY{module}^BCN,{height},N,N,N^FD>;>8{digits}^FS",
+                    LABEL_DOTS.saturating_sub(gs1_128_width(&digits, module)) / 2
+                ),
+            };
+        }
+        zpl.push_str("^XZ\n");
+    }
+    zpl
+}
+
+/// Field data goes through ^FH so command prefixes in lot numbers or names stay literal
+fn zpl_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => escaped.push_str("\\5C"),
+            '^' => escaped.push_str("\\5E"),
+            '~' => escaped.push_str("\\7E"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// EPL2 resident fonts at 203 dpi: (font, width, height) in dots
+const EPL_FONTS: [(u32, u32, u32); 5] = [(1, 8, 12), (2, 10, 16), (3, 12, 20), (4, 14, 24), (5, 32, 48)];
+
+/// EPL2, one N..P1 form per label. EPL has no bold or centring, so bold text prints
+/// plain and centred text is positioned from the font's fixed character width.
+pub fn render_epl<L: LabelLayout>(labels: &[L]) -> String {
+    let mut epl = String::new();
+    for label in labels {
+        epl.push_str(&format!("\nN\nq{LABEL_DOTS}\nQ{LABEL_DOTS},24\n"));
+        for mark in label.layout() {
+            let _ = match mark {
+                Mark::Text { x, y, height, centered, text, .. } => {
+                    let (font, char_width, _) = EPL_FONTS
+                        .iter()
+                        .copied()
+                        .min_by_key(|(_, _, font_height)| font_height.abs_diff(height))
+                        .unwrap_or(EPL_FONTS[3]);
+                    let x = if centered {
+                        LABEL_DOTS.saturating_sub(char_width * text.chars().count() as u32) / 2
+                    } else {
+                        x
+                    };
+                    writeln!(epl, "A{x},{y},0,{font},1,1,N,\"{}\"", epl_escape(&text))
+                }
+                Mark::Rule { x, y, width, thickness } => writeln!(epl, "LO{x},{y},{width},{thickness}"),
+                Mark::Box { x, y, size, thickness } => {
+                    writeln!(epl, "X{x},{y},{thickness},{},{}", x + size, y + size)
+                }
+                // Barcode type 1E is UCC/EAN-128: the printer adds the leading FNC1
+                Mark::Gs1Barcode { y, height, module, digits } => writeln!(
+                    epl,
+                    "B{},{y},0,1E,{module},{module},{height},N,\"{digits}\"",
+                    LABEL_DOTS.saturating_sub(gs1_128_width(&digits, module)) / 2
+                ),
+            };
+        }
+        epl.push_str("P1\n");
+    }
+    epl
+}
+
+fn epl_escape(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// PDF 1.4 with one 4x4 inch page per label, using the standard Helvetica fonts
+pub fn render_pdf<L: LabelLayout>(labels: &[L]) -> Vec<u8> {
+    let scale = LABEL_POINTS / f64::from(LABEL_DOTS);
+    let page_count = labels.len();
+    // 1 catalog, 2 page tree, 3-4 fonts, then a page and its content stream per label
+    let page_ids: Vec<usize> = (0..page_count).map(|index| 5 + index * 2).collect();
+
+    let mut objects: Vec<Vec<u8>> = vec![
+        b"<< /Type /Catalog /Pages 2 0 R >>".to_vec(),
+        format!(
+            "<< /Type /Pages /Kids [{}] /Count {page_count} >>",
+            page_ids.iter().map(|id| format!("{id} 0 R")).collect::<Vec<_>>().join(" ")
+        )
+        .into_bytes(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica /Encoding /WinAnsiEncoding >>".to_vec(),
+        b"<< /Type /Font /Subtype /Type1 /BaseFont /Helvetica-Bold /Encoding /WinAnsiEncoding >>".to_vec(),
+    ];
+
+    for (label, page_id) in labels.iter().zip(&page_ids) {
+        let mut content = String::new();
+        for mark in label.layout() {
+            let _ = match mark {
+                Mark::Text { x, y, height, bold, centered, text } => {
+                    let size = f64::from(height) * scale;
+                    let x = if centered {
+                        // Helvetica averages a little over half an em per character
+                        (LABEL_POINTS - 0.56 * size * text.chars().count() as f64).max(0.0) / 2.0
+                    } else {
+                        f64::from(x) * scale
+                    };
+                    let baseline = LABEL_POINTS - (f64::from(y) + f64::from(height) * 0.8) * scale;
+                    let font = if bold { "F2" } else { "F1" };
+                    writeln!(content, "BT /{font} {size:.2} Tf {x:.2} {baseline:.2} Td ({}) Tj ET", pdf_escape(&text))
+                }
+                Mark::Rule { x, y, width, thickness } => writeln!(
+                    content,
+                    "{:.2} {:.2} {:.2} {:.2} re f",
+                    f64::from(x) * scale,
+                    LABEL_POINTS - f64::from(y + thickness) * scale,
+                    f64::from(width) * scale,
+                    f64::from(thickness) * scale
+                ),
+                Mark::Box { x, y, size, thickness } => writeln!(
+                    content,
+                    "{:.2} w {:.2} {:.2} {:.2} {:.2} re S",
+                    f64::from(thickness) * scale,
+                    f64::from(x) * scale,
+                    LABEL_POINTS - f64::from(y + size) * scale,
+                    f64::from(size) * scale,
+                    f64::from(size) * scale
+                ),
+                Mark::Gs1Barcode { y, height, module, digits } => {
+                    let widths = gs1_128_modules(&digits).unwrap_or_default();
+                    let mut x = LABEL_DOTS.saturating_sub(gs1_128_width(&digits, module)) / 2;
+                    let bottom = LABEL_POINTS - f64::from(y + height) * scale;
+                    for (index, width) in widths.iter().enumerate() {
+                        // Even positions are bars, odd ones spaces
+                        if index % 2 == 0 {
+                            let _ = writeln!(
+                                content,
+                                "{:.2} {bottom:.2} {:.2} {:.2} re f",
+                                f64::from(x) * scale,
+                                f64::from(width * module) * scale,
+                                f64::from(height) * scale
+                            );
+                        }
+                        x += width * module;
+                    }
+                    Ok(())
+                }
+            };
+        }
+
+        objects.push(
+            format!(
+                "<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {LABEL_POINTS} {LABEL_POINTS}] \
+                 /Resources << /Font << /F1 3 0 R /F2 4 0 R >> >> /Contents {} 0 R >>",
+                page_id + 1
+            )
+            .into_bytes(),
+        );
+        objects.push(format!("<< /Length {} >>\nstream\n{content}endstream", content.len()).into_bytes());
+    }
+
+    let mut pdf = b"%PDF-1.4\n".to_vec();
+    let mut offsets = Vec::with_capacity(objects.len());
+    for (index, object) in objects.iter().enumerate() {
+        offsets.push(pdf.len());
+        pdf.extend_from_slice(format!("{} 0 obj\n", index + 1).as_bytes());
+        pdf.extend_from_slice(object);
+        pdf.extend_from_slice(b"\nendobj\n");
+    }
+
+    let xref_offset = pdf.len();
+    let mut xref = format!("xref\n0 {}\n0000000000 65535 f \n", objects.len() + 1);
+    for offset in offsets {
+        let _ = writeln!(xref, "{offset:010} 00000 n ");
+    }
+    let _ = write!(
+        xref,
+        "trailer\n<< /Size {} /Root 1 0 R >>\nstartxref\n{xref_offset}\n%%EOF\n",
+        objects.len() + 1
+    );
+    pdf.extend_from_slice(xref.as_bytes());
+    pdf
+}
+
+/// PDF string literal; the base fonts only cover ASCII reliably, so anything else prints as '?'
+fn pdf_escape(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' | '(' | ')' => {
+                escaped.push('\\');
+                escaped.push(c);
+            }
+            c if c.is_ascii() && !c.is_ascii_control() => escaped.push(c),
+            _ => escaped.push('?'),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::timezone::BANGKOK_TZ;
+    use chrono::TimeZone;
+
+    fn run() -> BulkRun {
+        BulkRun {
+            run_no: 198031,
+            batch_no: "788209".to_string(),
+            formula_id: "TC26520A".to_string(),
+            formula_desc: "Breader".to_string(),
+            no_of_batches: 4,
+            pallets_per_batch: Some(4),
+            status: "NEW".to_string(),
+            created_date: None,
+            picking_date: None,
+        }
+    }
+
+    fn summary(batch_no: &str, row_num: i32) -> BatchWeightSummaryItem {
+        BatchWeightSummaryItem {
+            batch_no: batch_no.to_string(),
+            item_key: "WFLOWGV2".to_string(),
+            item_description: None,
+            to_picked_bulk_qty: BigDecimal::from(25),
+            picked_bulk_qty: BigDecimal::from(25),
+            pack_size: BigDecimal::from(25),
+            total_weight_kg: BigDecimal::from(625),
+            picked_weight_kg: BigDecimal::from(625),
+            remaining_weight_kg: BigDecimal::zero(),
+            row_num,
+            line_id: 1,
+        }
+    }
+
+    fn lot(batch_no: &str, line_id: i32, item_key: &str, lot_no: &str, bin_no: &str, kg: i32) -> LotPickingDetail {
+        LotPickingDetail {
+            run_no: 198031,
+            batch_no: batch_no.to_string(),
+            line_id,
+            item_key: item_key.to_string(),
+            lot_no: lot_no.to_string(),
+            bin_no: bin_no.to_string(),
+            qty_received: BigDecimal::from(kg),
+            pack_size: BigDecimal::from(25),
+            rec_userid: "WACHIRAS".to_string(),
+            modified_by: String::new(),
+            rec_date: "2024-10-30 09:50:00".to_string(),
+            picked_bulk_qty: BigDecimal::from(kg / 25),
+            picked_qty: BigDecimal::from(kg),
+        }
+    }
+
+    fn printed_at() -> DateTime<Tz> {
+        BANGKOK_TZ.with_ymd_and_hms(2024, 10, 30, 9, 59, 20).unwrap()
+    }
+
+    #[test]
+    fn test_one_label_per_picked_batch_paged_by_row_num() {
+        let lots = vec![
+            lot("788210", 1, "WFLOWGV2", "2502976-2", "K0900-1A", 625),
+            lot("788210", 4, "INWSTA02", "2502979", "K0900-1A", 50),
+            lot("788212", 1, "WFLOWGV2", "2502976-2", "K0900-1A", 625),
+        ];
+        let summary = vec![summary("788209", 1), summary("788210", 2), summary("788212", 4)];
+
+        let labels = build_batch_labels(&run(), &summary, &lots, printed_at());
+
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].batch_no, "788210");
+        assert_eq!(labels[0].page_info(), "Page 2 of 4");
+        assert_eq!(labels[0].lines.len(), 2);
+        assert_eq!(labels[0].lines[1].bags, BigDecimal::from(2));
+        assert_eq!(labels[0].picked_by, "WACHIRAS");
+        assert_eq!(labels[0].date, "30/10/24");
+        assert_eq!(labels[0].printed_at, "30/10/2024, 9:59:20AM");
+        assert_eq!(labels[1].page_info(), "Page 4 of 4");
+    }
+
+    #[test]
+    fn test_long_batch_continues_on_another_sheet() {
+        let lots: Vec<_> = (1..=LINES_PER_LABEL as i32 + 1)
+            .map(|line_id| lot("788210", line_id, "WFLOWGV2", &format!("LOT{line_id}"), "K0900-1A", 25))
+            .collect();
+
+        let labels = build_batch_labels(&run(), &[summary("788210", 2)], &lots, printed_at());
+
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].lines.len(), LINES_PER_LABEL);
+        assert_eq!(labels[1].page_info(), "Page 2 of 4 (2/2)");
+    }
+
+    #[test]
+    fn test_renderers_emit_one_label_each() {
+        let mut lots = vec![lot("788210", 1, "WFLOWGV2", "25^02~976", "K0900-1A", 625)];
+        lots.push(lot("788211", 1, "WFLOWGV2", "2502976-2", "K0900-1A", 625));
+        let labels = build_batch_labels(&run(), &[], &lots, printed_at());
+
+        let zpl = render_zpl(&labels);
+        assert_eq!(zpl.matches("^XA").count(), 2);
+        assert_eq!(zpl.matches("^XZ").count(), 2);
+        assert!(zpl.contains("^FD25\\5E02\\7E976^FS"));
+        assert!(zpl.contains("^FDBULK SUMMARY^FS"));
+
+        let epl = render_epl(&labels);
+        assert_eq!(epl.matches("\nN\n").count(), 2);
+        assert_eq!(epl.matches("P1\n").count(), 2);
+        assert!(epl.contains("\"Page 1 of 4\""));
+
+        let pdf = String::from_utf8(render_pdf(&labels)).unwrap();
+        assert!(pdf.starts_with("%PDF-1.4"));
+        assert!(pdf.contains("/Count 2"));
+        assert!(pdf.contains("(BULK SUMMARY) Tj"));
+        assert!(pdf.ends_with("%%EOF\n"));
+        // Every xref offset points at its object
+        let xref_at = pdf.rfind("\nxref\n").unwrap() + 1;
+        for (index, entry) in pdf[xref_at..].lines().skip(3).take_while(|line| line.ends_with(" n ")).enumerate() {
+            let offset: usize = entry[..10].parse().unwrap();
+            assert!(pdf[offset..].starts_with(&format!("{} 0 obj", index + 1)));
+        }
+    }
+
+    #[test]
+    fn test_pallet_label_carries_sscc_barcode() {
+        // Every Code 128 symbol is 11 modules wide, the stop pattern 13
+        assert!(CODE128_PATTERNS.iter().all(|pattern| pattern.bytes().map(|b| u32::from(b - b'0')).sum::<u32>() == 11));
+        let digits = "00106141411234567897";
+        let modules = gs1_128_modules(digits).unwrap();
+        // Start C, FNC1, ten digit pairs, check symbol, stop
+        assert_eq!(modules.iter().sum::<u32>(), 13 * 11 + 13);
+        assert!(gs1_128_modules("123").is_none());
+
+        let pallet = PalletSscc {
+            sscc: "106141411234567897".to_string(),
+            run_no: 198031,
+            row_num: 2,
+            batch_no: "788210".to_string(),
+            assigned_by: "WACHIRAS".to_string(),
+            assigned_at: chrono::Utc::now(),
+        };
+        let lots: Vec<_> = (1..=PALLET_LINES_PER_LABEL as i32 + 1)
+            .map(|line_id| lot("788210", line_id, "WFLOWGV2", &format!("LOT{line_id}"), "K0900-1A", 25))
+            .chain([lot("788211", 1, "WFLOWGV2", "2502976-2", "K0900-1A", 625)])
+            .collect();
+        let labels = build_pallet_labels(&run(), &[summary("788210", 2)], &lots, &pallet, printed_at());
+
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].batch.lines.len(), PALLET_LINES_PER_LABEL);
+        assert_eq!(labels[1].batch.sheet, 2);
+        assert_eq!(labels[0].human_readable(), "(00) 106141411234567897");
+
+        let zpl = render_zpl(&labels);
+        assert_eq!(zpl.matches("^BCN,150,N,N,N^FD>;>800106141411234567897^FS").count(), 2);
+        assert!(zpl.contains("^FDPallet 2 of 4 (1/2)^FS"));
+        assert!(render_epl(&labels).contains(",0,1E,4,4,150,N,\"00106141411234567897\""));
+        let pdf = String::from_utf8(render_pdf(&labels)).unwrap();
+        assert!(pdf.contains("(\\(00\\) 106141411234567897) Tj"));
+    }
+}
@@ -1,8 +1,8 @@
+use crate::database::repository::{BulkRunRepository, LotRepository};
 use crate::database::Database;
-use anyhow::{anyhow, Context, Result};
-use bigdecimal::{BigDecimal, ToPrimitive};
+use anyhow::{anyhow, Result};
+use bigdecimal::ToPrimitive;
 use serde::{Deserialize, Serialize};
-use std::str::FromStr;
 use tracing::{info, instrument};
 
 /// Validation errors specific to bulk picking operations
@@ -118,12 +118,12 @@ pub struct PickValidationResponse {
 }
 
 /// Service for comprehensive bulk picking validation
-pub struct BulkPickingValidationService {
-    database: Database,
+pub struct BulkPickingValidationService<R = Database> {
+    database: R,
 }
 
-impl BulkPickingValidationService {
-    pub fn new(database: Database) -> Self {
+impl<R: BulkRunRepository + LotRepository> BulkPickingValidationService<R> {
+    pub fn new(database: R) -> Self {
         Self { database }
     }
 
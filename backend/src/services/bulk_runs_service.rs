@@ -1,18 +1,132 @@
-use crate::database::Database;
+use crate::database::repository::{BulkRunRepository, LotRepository};
+use crate::database::{Database, DEFAULT_LOCATION_KEY};
+use crate::models::batch_labels::BatchLabel;
 use crate::models::bulk_runs::*;
+use crate::models::idempotency::{IdempotencyKey, IdempotentOperation};
+use crate::models::pallet_labels::{PalletLabel, PalletLookup, PalletSscc, SsccConfig};
+use crate::models::pick_plan::{PickPlan, PickPlanPreview};
+use crate::models::pick_scans::{scan_mismatch, PickScanException, ScanVerifyPolicy};
+use crate::models::pick_sequence::{zone_order, BinLocation, PickSequence};
+use crate::models::run_claims::{claim_lease, ClaimOutcome, ClaimRequest, RunClaim};
+use crate::models::run_events::{RunEvent, RunEventKind};
+use crate::services::batch_labels::{build_batch_labels, build_pallet_labels};
+use crate::services::pick_planner::{build_pick_plan, summarize_ingredient_stock};
+use crate::services::pick_sequence::walk_path_ranks;
+use crate::services::run_events::RunEventBus;
+use crate::utils::gs1::parse_sscc;
 use crate::utils::timezone::{format_bangkok_date, get_bangkok_time};
 use anyhow::{Context, Result};
 use bigdecimal::BigDecimal;
+use std::collections::HashMap;
 use tracing::{info, instrument, warn};
 
 /// Service for bulk run operations
-pub struct BulkRunsService {
-    database: Database,
+pub struct BulkRunsService<R = Database> {
+    database: R,
+    /// Where committed picks, unpicks and status changes are announced, if anyone listens
+    events: Option<RunEventBus>,
+    /// Sites where confirm-pick requires bin and lot scans; none unless set
+    scan_policy: ScanVerifyPolicy,
+    /// GS1 prefix for pallet SSCCs; pallets get none unless set
+    sscc_config: Option<SsccConfig>,
 }
 
-impl BulkRunsService {
-    pub fn new(database: Database) -> Self {
-        Self { database }
+impl<R: BulkRunRepository + LotRepository> BulkRunsService<R> {
+    pub fn new(database: R) -> Self {
+        Self { database, events: None, scan_policy: ScanVerifyPolicy::default(), sscc_config: None }
+    }
+
+    pub fn with_events(mut self, events: RunEventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn with_scan_policy(mut self, scan_policy: ScanVerifyPolicy) -> Self {
+        self.scan_policy = scan_policy;
+        self
+    }
+
+    pub fn with_sscc_config(mut self, sscc_config: Option<SsccConfig>) -> Self {
+        self.sscc_config = sscc_config;
+        self
+    }
+
+    fn publish(&self, run_no: i32, user_id: &str, kind: RunEventKind) {
+        if let Some(events) = &self.events {
+            events.publish(RunEvent::new(run_no, user_id, kind));
+        }
+    }
+
+    /// Announce a committed pick, plus the pallet, ingredient and run completions it caused.
+    /// Completions are read back from the batch rows, so the extra queries only run while
+    /// someone is subscribed.
+    async fn publish_pick(&self, run_no: i32, request: &PickConfirmationRequest, response: &PickConfirmationResponse) {
+        if !self.events.as_ref().is_some_and(RunEventBus::has_subscribers) {
+            return;
+        }
+        let user_id = request.user_id.as_deref().unwrap_or_default();
+        for allocation in request.lot_allocations() {
+            self.publish(
+                run_no,
+                user_id,
+                RunEventKind::Pick {
+                    row_num: request.row_num,
+                    line_id: request.line_id,
+                    lot_no: allocation.lot_no,
+                    bin_no: allocation.bin_no,
+                    picked_bulk_qty: allocation.bags,
+                    document_no: response.document_no.clone(),
+                },
+            );
+        }
+
+        let batches = match self.database.get_bulk_run_batches(run_no).await {
+            Ok(batches) => batches,
+            Err(e) => {
+                warn!("Skipping completion events for run {}: {}", run_no, e);
+                return;
+            }
+        };
+        let is_done = |batch: &BulkPickedItem| {
+            batch.picked_bulk_qty.as_ref().is_some_and(|picked| picked >= &batch.to_picked_bulk_qty)
+        };
+
+        let Some(pallet) = batches
+            .iter()
+            .find(|b| b.row_num == request.row_num && b.line_id == request.line_id)
+        else {
+            return;
+        };
+        if !is_done(pallet) {
+            return;
+        }
+        self.publish(
+            run_no,
+            user_id,
+            RunEventKind::PalletCompleted { row_num: request.row_num, line_id: request.line_id },
+        );
+
+        if !batches.iter().filter(|b| b.line_id == request.line_id).all(is_done) {
+            return;
+        }
+        self.publish(
+            run_no,
+            user_id,
+            RunEventKind::IngredientCompleted { line_id: request.line_id, item_key: pallet.item_key.clone() },
+        );
+
+        // The pick transaction moves a fully picked run NEW -> PRINT (check_and_update_run_completion)
+        if batches.iter().filter(|b| b.to_picked_bulk_qty > BigDecimal::from(0)).all(is_done) {
+            if let Ok(Some(status)) = self.database.get_bulk_run_status(run_no).await {
+                if status.status == "PRINT" {
+                    self.publish(
+                        run_no,
+                        user_id,
+                        RunEventKind::RunStatusChanged { from: "NEW".to_string(), to: status.status },
+                    );
+                }
+            }
+        }
     }
 
     /// Search for bulk runs and return search response
@@ -77,10 +191,11 @@ impl BulkRunsService {
         &self,
         run_no: i32,
         ingredient_index: Option<i32>,
+        sequence: PickSequence,
     ) -> Result<Option<BulkRunFormData>> {
         info!(
-            "Getting bulk run form data for run: {}, ingredient index: {:?}",
-            run_no, ingredient_index
+            "Getting bulk run form data for run: {}, ingredient index: {:?}, sequence: {:?}",
+            run_no, ingredient_index, sequence
         );
 
         // Get the bulk run
@@ -106,9 +221,9 @@ impl BulkRunsService {
         // and filter batches for that specific ingredient
         let current_batch = if let Some(idx) = ingredient_index {
             // Get aggregated ingredients to map index to item_key
-            let ingredients = self.database.get_bulk_run_ingredients(run_no).await?;
+            let ingredients = self.sequenced_ingredients(run_no, sequence).await?;
             
-            // Use database DESC ordering (LineId DESC) to match search modal and ensure consistent indexing
+            // Same sequence as the search modal so the index maps to the same ingredient
 
             if let Some(selected_ingredient) = ingredients.get(idx as usize) {
                 info!("Manual ingredient selection: index {} maps to ItemKey: {}, LineId: {}", 
@@ -157,7 +272,7 @@ impl BulkRunsService {
               &current_batch.to_picked_bulk_qty - current_batch.picked_bulk_qty.as_ref().unwrap_or(&BigDecimal::from(0)));
 
         // Also get aggregated ingredients for display purposes
-        let ingredients = self.database.get_bulk_run_ingredients(run_no).await?;
+        let ingredients = self.sequenced_ingredients(run_no, sequence).await?;
 
         // Get inventory info for the current batch's ingredient
         let inventory = self
@@ -336,9 +451,10 @@ impl BulkRunsService {
         &self,
         run_no: i32,
         current_ingredient_index: i32,
+        sequence: PickSequence,
     ) -> Result<Option<BulkRunFormData>> {
         let next_index = current_ingredient_index + 1;
-        self.get_bulk_run_form_data(run_no, Some(next_index)).await
+        self.get_bulk_run_form_data(run_no, Some(next_index), sequence).await
     }
 
     /// Check if all ingredients in a run are complete
@@ -359,9 +475,10 @@ impl BulkRunsService {
     /// Search for related ingredients in a bulk run for modal display
     /// 🔍 CRITICAL: Only returns ingredients that require bulk picking (ToPickedBulkQty > 0)
     #[instrument(skip(self))]
-    pub async fn search_run_items(&self, run_no: i32) -> Result<Vec<RunItemSearchResult>> {
+    pub async fn search_run_items(&self, run_no: i32, sequence: PickSequence) -> Result<Vec<RunItemSearchResult>> {
 
-        let ingredients = self.database.get_unique_ingredients_for_search(run_no).await?;
+        let mut ingredients = self.database.get_unique_ingredients_for_search(run_no).await?;
+        self.order_by_sequence(run_no, sequence, &mut ingredients).await?;
         info!("🔢 DEBUG: Database returned {} bulk picking ingredients for run {}", ingredients.len(), run_no);
         
         let mut results = Vec::new();
@@ -394,12 +511,12 @@ impl BulkRunsService {
 
     /// Get ingredient index for a specific ItemKey in a bulk run, prioritizing first batch (lowest RowNum)
     #[instrument(skip(self))]
-    pub async fn get_ingredient_index(&self, run_no: i32, item_key: &str) -> Result<usize> {
+    pub async fn get_ingredient_index(&self, run_no: i32, item_key: &str, sequence: PickSequence) -> Result<usize> {
         info!("Getting ingredient index for item_key: {} in run: {} (prioritizing first batch - lowest RowNum)", item_key, run_no);
         
-        let ingredients = self.database.get_bulk_run_ingredients(run_no).await?;
-        // Use database DESC ordering (LineId DESC) to match search modal and ensure consistent indexing
-        
+        let ingredients = self.sequenced_ingredients(run_no, sequence).await?;
+        // Same ordering as the search modal and form data, so indexes line up
+
         // Collect all batches for this ingredient with their array indices
         let mut ingredient_batches: Vec<(usize, &BulkPickedItem)> = ingredients
             .iter()
@@ -432,6 +549,7 @@ impl BulkRunsService {
         item_key: &str,
         row_num: i32,
         line_id: i32,
+        sequence: PickSequence,
     ) -> Result<BulkRunFormData> {
         info!(
             "Getting ingredient data by coordinates - ItemKey: {}, RowNum: {}, LineId: {} in run: {}", 
@@ -483,7 +601,7 @@ impl BulkRunsService {
         };
 
         // Get all ingredients for navigation purposes
-        let all_ingredients = self.database.get_bulk_run_ingredients(run_no).await?;
+        let all_ingredients = self.sequenced_ingredients(run_no, sequence).await?;
         let actual_ingredient_idx = all_ingredients
             .iter()
             .position(|ing| ing.line_id == line_id && ing.item_key == item_key)
@@ -504,6 +622,69 @@ impl BulkRunsService {
         })
     }
 
+    /// Ingredient rows in the requested picking sequence
+    async fn sequenced_ingredients(&self, run_no: i32, sequence: PickSequence) -> Result<Vec<BulkPickedItem>> {
+        let mut ingredients = self.database.get_bulk_run_ingredients(run_no).await?;
+        self.order_by_sequence(run_no, sequence, &mut ingredients).await?;
+        Ok(ingredients)
+    }
+
+    /// Reorder ingredient rows by the walk path to each ingredient's FEFO bin.
+    /// Line order is what the queries return (LineId DESC) and is left untouched.
+    async fn order_by_sequence(
+        &self,
+        run_no: i32,
+        sequence: PickSequence,
+        ingredients: &mut [BulkPickedItem],
+    ) -> Result<()> {
+        if sequence == PickSequence::Line || ingredients.is_empty() {
+            return Ok(());
+        }
+
+        let mut first_bins: Vec<(String, Option<(String, String)>)> = Vec::new();
+        for ingredient in ingredients.iter() {
+            if first_bins.iter().any(|(item_key, _)| *item_key == ingredient.item_key) {
+                continue;
+            }
+            let lots = self
+                .database
+                .get_available_lots(run_no, &ingredient.item_key)
+                .await
+                .with_context(|| format!("Failed to get lots of {} for pick sequence", ingredient.item_key))?;
+            let bin = lots
+                .into_iter()
+                .find_map(|lot| lot.bin.map(|bin| (lot.location, bin)));
+            first_bins.push((ingredient.item_key.clone(), bin));
+        }
+
+        let mut locations: HashMap<(String, String), BinLocation> = HashMap::new();
+        let mut by_location: HashMap<&str, Vec<String>> = HashMap::new();
+        for (location, bin_no) in first_bins.iter().filter_map(|(_, bin)| bin.as_ref()) {
+            by_location.entry(location.as_str()).or_default().push(bin_no.clone());
+        }
+        for (location, bin_nos) in by_location {
+            let found = self
+                .database
+                .get_bin_locations(location, &bin_nos)
+                .await
+                .context("Failed to get bin locations for pick sequence")?;
+            for bin in found {
+                locations.insert((location.to_string(), bin.bin_no.clone()), bin);
+            }
+        }
+
+        let item_bins: Vec<(String, Option<BinLocation>)> = first_bins
+            .into_iter()
+            .map(|(item_key, bin)| {
+                let location = bin.and_then(|key| locations.get(&key).cloned());
+                (item_key, location)
+            })
+            .collect();
+        let ranks = walk_path_ranks(&item_bins, &zone_order());
+        ingredients.sort_by_key(|ingredient| ranks.get(&ingredient.item_key).copied().unwrap_or(usize::MAX));
+        Ok(())
+    }
+
     /// List active bulk runs with pagination (Story 1.4)
     #[instrument(skip(self))]
     pub async fn list_active_bulk_runs_paginated(
@@ -516,27 +697,23 @@ impl BulkRunsService {
             page, limit
         );
 
-        let (runs, total_items) = self
+        let (mut runs, total_items) = self
             .database
             .list_active_bulk_runs_paginated(page, limit)
             .await?;
 
-        let total_pages = if total_items == 0 {
-            1
-        } else {
-            (total_items as f64 / limit as f64).ceil() as u32
-        };
-        let has_previous = page > 1;
-        let has_next = page < total_pages;
+        // Claims are informational here; the list still loads if they cannot be read
+        let run_nos: Vec<i32> = runs.iter().map(|run| run.run_no).collect();
+        match self.database.get_active_claims(&run_nos).await {
+            Ok(claims) => {
+                for run in &mut runs {
+                    run.claims = claims.iter().filter(|claim| claim.run_no == run.run_no).cloned().collect();
+                }
+            }
+            Err(e) => warn!("Failed to load run claims for run list: {}", e),
+        }
 
-        let pagination = PaginationInfo {
-            current_page: page,
-            total_pages,
-            total_items,
-            page_size: limit,
-            has_previous,
-            has_next,
-        };
+        let pagination = PaginationInfo::new(page, limit, total_items);
 
         Ok(PaginatedBulkRunResponse { runs, pagination })
     }
@@ -669,17 +846,31 @@ impl BulkRunsService {
     pub async fn confirm_pick_transaction(
         &self,
         run_no: i32,
-        request: PickConfirmationRequest,
-    ) -> Result<PickConfirmationResponse> {
+        mut request: PickConfirmationRequest,
+    ) -> Result<PickConfirmationResponse, BulkRunError> {
+        request.normalize_allocations().map_err(BulkRunError::Validation)?;
         info!(
-            "Processing pick confirmation for run: {}, lot: {}, qty: {}",
-            run_no, request.lot_no, request.picked_bulk_qty
+            "Processing pick confirmation for run: {}, lots: {}, qty: {}",
+            run_no,
+            request.lot_allocations().len(),
+            request.picked_bulk_qty
         );
 
+        // A retried request replays the stored response - the batch may already be complete,
+        // so re-validating it would reject a pick that actually succeeded
+        if let Some(key) = &request.idempotency_key {
+            if let Some(stored) = self.database.find_idempotent_response(key).await? {
+                info!("Replaying pick confirmation for run {} (Idempotency-Key {})", run_no, key.key);
+                return Ok(stored.replay(key)?);
+            }
+        }
+
+        self.check_claims(run_no, &request).await?;
+        self.verify_scans(run_no, &request).await?;
+
         // **Step 1: Validate pick request** - BME4 business rules
         let validation_result = self
-            .database
-            .validate_pick_request(run_no, &request)
+            .validate_allocations(run_no, &request)
             .await
             .context("Failed to validate pick request")?;
 
@@ -688,7 +879,7 @@ impl BulkRunsService {
                 "Pick validation failed".to_string()
             });
             warn!("Pick validation failed for run {}: {}", run_no, error_msg);
-            return Err(anyhow::anyhow!("Validation failed: {}", error_msg));
+            return Err(BulkRunError::Validation(error_msg));
         }
 
         // Log any warnings
@@ -697,36 +888,656 @@ impl BulkRunsService {
         }
 
         // **Step 2: Execute atomic transaction** - 4-table updates
-        // Execute and normalize known error cases for clearer frontend messages
-        let response = match self
+        let mut response = self
             .database
             .confirm_pick_transaction(run_no, &request)
             .await
-        {
-            Ok(resp) => resp,
-            Err(e) => {
-                let err_str = e.to_string();
-                if err_str.contains("BATCH_ALREADY_COMPLETED") {
-                    // Map to a clean, actionable message while logging specifics
-                    warn!(
-                        "BATCH_ALREADY_COMPLETED detected for run {} (lot: {}, row_num: {}, line_id: {}): {}",
-                        run_no, request.lot_no, request.row_num, request.line_id, err_str
-                    );
-                    let user_msg = "This batch is already completed. Please refresh to load the next batch.";
-                    return Err(anyhow::anyhow!("BATCH_ALREADY_COMPLETED: {}", user_msg));
+            .inspect_err(|e| {
+                warn!(
+                    "Pick confirmation failed for run {} (lots: {}, row_num: {}, line_id: {}) [{}]: {}",
+                    run_no,
+                    request.lot_allocations().len(),
+                    request.row_num,
+                    request.line_id,
+                    e.error_code(),
+                    e
+                )
+            })?;
+
+        info!(
+            "Pick confirmation completed successfully for run: {} - Transaction: {:?}, Document: {:?}",
+            run_no, response.transaction_id, response.document_no
+        );
+
+        response.warnings = validation_result.warnings;
+        self.advance_pick_plan(run_no, &request).await;
+        self.publish_pick(run_no, &request, &response).await;
+        Ok(response)
+    }
+
+    /// Validate each lot of a pick on its own, then the combined bags against the batch.
+    /// A single-lot pick is one allocation, so this is the plain BME4 validation.
+    async fn validate_allocations(
+        &self,
+        run_no: i32,
+        request: &PickConfirmationRequest,
+    ) -> Result<PickValidationResult> {
+        let mut combined: Option<PickValidationResult> = None;
+        for allocation in request.lot_allocations() {
+            let validation = self
+                .database
+                .validate_pick_request(run_no, &request.for_allocation(&allocation))
+                .await?;
+            if !validation.is_valid {
+                return Ok(validation);
+            }
+            combined = Some(match combined {
+                None => validation,
+                Some(mut combined) => {
+                    combined.warnings.extend(validation.warnings);
+                    combined
                 }
+            });
+        }
+        let combined = combined.context("Pick has no lots to validate")?;
+
+        // Each lot fits the batch on its own; their total has to fit as well
+        if let Some(remaining) = combined.max_allowed_quantity.clone() {
+            if request.picked_bulk_qty > remaining {
+                return Ok(PickValidationResult {
+                    is_valid: false,
+                    error_message: Some(format!(
+                        "Cannot pick {} bags across {} lots. Only {} bags remaining in this batch",
+                        request.picked_bulk_qty,
+                        request.allocations.len(),
+                        remaining
+                    )),
+                    warnings: Vec::new(),
+                    max_allowed_quantity: Some(remaining),
+                    available_inventory: None,
+                });
+            }
+        }
+        Ok(combined)
+    }
+
+    /// Replay picks captured offline, oldest first. Each pick is revalidated against the
+    /// current state and committed in its own transaction; rejections are reported per pick.
+    /// Processing stops at the first server-side failure, since later picks may depend on it.
+    #[instrument(skip(self, picks), fields(count = picks.len()))]
+    pub async fn sync_offline_picks(&self, user_id: &str, picks: Vec<QueuedPick>) -> OfflinePickSyncResponse {
+        info!("Syncing {} offline picks for user {}", picks.len(), user_id);
+
+        let mut results = Vec::with_capacity(picks.len());
+        let mut aborted = false;
+        for queued in picks {
+            if aborted {
+                results.push(QueuedPickResult {
+                    client_id: queued.client_id,
+                    run_no: queued.run_no,
+                    outcome: SyncOutcome::Skipped,
+                    code: None,
+                    message: "Not attempted - an earlier pick failed; keep it queued".to_string(),
+                    response: None,
+                });
+                continue;
+            }
+
+            let result = self.sync_queued_pick(user_id, queued).await;
+            aborted = result.outcome == SyncOutcome::Failed;
+            results.push(result);
+        }
+
+        let response = OfflinePickSyncResponse::from_results(results);
+        info!(
+            "Offline sync for {}: {} applied, {} already applied, {} conflicts, {} failed, {} skipped",
+            user_id, response.applied, response.already_applied, response.conflicts, response.failed, response.skipped
+        );
+        response
+    }
+
+    async fn sync_queued_pick(&self, user_id: &str, queued: QueuedPick) -> QueuedPickResult {
+        let run_no = queued.run_no;
+        let mut request = PickConfirmationRequest {
+            row_num: queued.row_num,
+            line_id: queued.line_id,
+            picked_bulk_qty: queued.picked_bulk_qty,
+            lot_no: queued.lot_no,
+            bin_no: queued.bin_no,
+            allocations: queued.allocations,
+            user_id: Some(user_id.to_string()),
+            idempotency_key: None,
+            override_claim: false,
+            scanned_bin: queued.scanned_bin,
+            scanned_lot: queued.scanned_lot,
+        };
+        let key = IdempotencyKey::new(queued.client_id.clone(), IdempotentOperation::ConfirmPick, user_id, &request);
+        request.idempotency_key = Some(key.clone());
+
+        let result = |outcome, code: Option<&str>, message: String, response| QueuedPickResult {
+            client_id: queued.client_id.clone(),
+            run_no,
+            outcome,
+            code: code.map(str::to_string),
+            message,
+            response,
+        };
+        let failed = |e: &dyn std::fmt::Display| {
+            warn!("Offline pick {} for run {} failed: {}", queued.client_id, run_no, e);
+            result(SyncOutcome::Failed, Some("SYNC_FAILED"), format!("Server error: {e}"), None)
+        };
+
+        // Already committed by an earlier, partly acknowledged sync
+        match self.database.find_idempotent_response(&key).await {
+            Ok(Some(stored)) => {
+                return match stored.replay::<PickConfirmationResponse>(&key).map_err(BulkRunError::from) {
+                    Ok(response) => result(
+                        SyncOutcome::AlreadyApplied,
+                        None,
+                        "Pick was already applied".to_string(),
+                        Some(response),
+                    ),
+                    Err(e) => result(SyncOutcome::Conflict, Some(e.error_code()), e.to_string(), None),
+                };
+            }
+            Ok(None) => {}
+            Err(e) => return failed(&e),
+        }
+
+        // The run may have been completed or taken out of picking while the handheld was offline
+        match self.database.get_bulk_run_status(run_no).await {
+            Ok(Some(status)) if status.status == "NEW" => {}
+            Ok(Some(status)) => {
+                return result(
+                    SyncOutcome::Conflict,
+                    Some("RUN_NOT_OPEN"),
+                    format!("Run {run_no} is {} and no longer open for picking", status.status),
+                    None,
+                );
+            }
+            Ok(None) => {
+                return result(SyncOutcome::Conflict, Some("RUN_NOT_FOUND"), format!("Run {run_no} not found"), None);
+            }
+            Err(e) => return failed(&e),
+        }
+
+        if let Err(message) = request.normalize_allocations() {
+            let e = BulkRunError::Validation(message);
+            return result(SyncOutcome::Conflict, Some(e.error_code()), e.to_string(), None);
+        }
+
+        if let Err(e) = self.check_claims(run_no, &request).await {
+            return match e {
+                BulkRunError::ClaimedByOther { .. } => result(SyncOutcome::Conflict, Some(e.error_code()), e.to_string(), None),
+                e => failed(&e),
+            };
+        }
+
+        if let Err(e) = self.verify_scans(run_no, &request).await {
+            return if e.status_code().is_server_error() {
+                failed(&e)
+            } else {
+                result(SyncOutcome::Conflict, Some(e.error_code()), e.to_string(), None)
+            };
+        }
 
-                // Unknown failure path; keep original context
-                return Err(e).context("Failed to execute pick confirmation transaction");
+        let validation = match self.validate_allocations(run_no, &request).await {
+            Ok(validation) => validation,
+            Err(e) => return failed(&e),
+        };
+        if !validation.is_valid {
+            let message = validation.error_message.clone().unwrap_or_else(|| "Pick validation failed".to_string());
+            return result(SyncOutcome::Conflict, Some(validation.conflict_code()), message, None);
+        }
+
+        match self.database.confirm_pick_transaction(run_no, &request).await {
+            Ok(mut response) => {
+                response.warnings = validation.warnings;
+                self.advance_pick_plan(run_no, &request).await;
+                self.publish_pick(run_no, &request, &response).await;
+                let message = format!(
+                    "Pick applied ({})",
+                    response.document_no.as_deref().unwrap_or("no document")
+                );
+                result(SyncOutcome::Applied, None, message, Some(response))
             }
+            Err(e) if e.status_code().is_server_error() => failed(&e),
+            Err(e) => result(SyncOutcome::Conflict, Some(e.error_code()), e.to_string(), None),
+        }
+    }
+
+    /// Reject a pick on a run or line another picker has claimed, unless a supervisor overrides.
+    /// Unclaimed runs can be picked by anyone.
+    async fn check_claims(&self, run_no: i32, request: &PickConfirmationRequest) -> Result<(), BulkRunError> {
+        let user_id = request.user_id.as_deref().unwrap_or_default();
+        let claims = self
+            .database
+            .get_active_claims(&[run_no])
+            .await
+            .context("Failed to check run claims")?;
+
+        let Some(holder) = claims.into_iter().find(|claim| claim.blocks(user_id, Some(request.line_id))) else {
+            return Ok(());
         };
+        if request.override_claim {
+            warn!(
+                "⚠️ CLAIM: {} overrides {}'s claim to pick run {} line {}",
+                user_id, holder.username, run_no, request.line_id
+            );
+            return Ok(());
+        }
+        Err(BulkRunError::ClaimedByOther { run_no, line_id: holder.line_id, holder: holder.username })
+    }
+
+    /// Cross-check the bin and lot barcodes scanned for each lot against the allocation the
+    /// picker selected. Scans are required at the sites in PICK_SCAN_VERIFY_SITES; sent
+    /// scans are checked everywhere. Every mismatch is recorded before the pick is refused.
+    async fn verify_scans(&self, run_no: i32, request: &PickConfirmationRequest) -> Result<(), BulkRunError> {
+        let allocations = request.lot_allocations();
+        let policy = &self.scan_policy;
+        let scanned = allocations.iter().any(|a| a.scanned_bin.is_some() || a.scanned_lot.is_some());
+        if !scanned && !policy.is_enabled() {
+            return Ok(());
+        }
+
+        if policy.is_enabled() {
+            let site = self.pick_site(run_no, request).await?;
+            let missing = allocations.iter().any(|a| {
+                a.scanned_bin.as_deref().is_none_or(|scan| scan.trim().is_empty())
+                    || a.scanned_lot.as_deref().is_none_or(|scan| scan.trim().is_empty())
+            });
+            if missing && policy.requires_scans(&site) {
+                return Err(BulkRunError::ScanRequired { site });
+            }
+        }
+
+        let user_id = request.user_id.clone().unwrap_or_default();
+        let mut rejection = None;
+        for allocation in &allocations {
+            let Some(error) = scan_mismatch(allocation) else {
+                continue;
+            };
+            warn!(
+                "⚠️ SCAN: {} on run {} row {} line {} [{}]: {}",
+                user_id, run_no, request.row_num, request.line_id, error.error_code(), error
+            );
+            let exception = PickScanException {
+                run_no,
+                row_num: request.row_num,
+                line_id: request.line_id,
+                lot_no: allocation.lot_no.clone(),
+                bin_no: allocation.bin_no.clone(),
+                scanned_lot: allocation.scanned_lot.clone(),
+                scanned_bin: allocation.scanned_bin.clone(),
+                error_code: error.error_code().to_string(),
+                user_id: user_id.clone(),
+                created_at: chrono::Utc::now(),
+            };
+            if let Err(e) = self.database.record_pick_scan_exception(&exception).await {
+                warn!("Failed to record scan exception for run {}: {:#}", run_no, e);
+            }
+            rejection.get_or_insert(error);
+        }
+        rejection.map_or(Ok(()), Err)
+    }
+
+    /// Location key of the batch being picked; the pick transaction's site
+    async fn pick_site(&self, run_no: i32, request: &PickConfirmationRequest) -> Result<String, BulkRunError> {
+        let batches = self
+            .database
+            .get_bulk_run_batches(run_no)
+            .await
+            .context("Failed to get batch site for scan verification")?;
+        Ok(batches
+            .into_iter()
+            .find(|batch| batch.row_num == request.row_num && batch.line_id == request.line_id)
+            .and_then(|batch| batch.location)
+            .unwrap_or_else(|| DEFAULT_LOCATION_KEY.to_string()))
+    }
+
+    /// Scan exceptions recorded for a run, oldest first
+    #[instrument(skip(self))]
+    pub async fn get_scan_exceptions(&self, run_no: i32) -> Result<Vec<PickScanException>, BulkRunError> {
+        Ok(self
+            .database
+            .get_pick_scan_exceptions(run_no)
+            .await
+            .context("Failed to get pick scan exceptions")?)
+    }
+
+    /// Claim a run or one of its ingredient lines for `username`, or renew their lease.
+    /// Only runs still open for picking can be claimed.
+    #[instrument(skip(self))]
+    pub async fn claim_run(
+        &self,
+        run_no: i32,
+        username: &str,
+        request: &ClaimRequest,
+        is_supervisor: bool,
+    ) -> Result<RunClaim, BulkRunError> {
+        if request.take_over && !is_supervisor {
+            return Err(BulkRunError::ClaimOverrideForbidden);
+        }
+
+        let status = self
+            .database
+            .get_bulk_run_status(run_no)
+            .await
+            .context("Failed to get run status for claim")?
+            .ok_or(BulkRunError::RunNotFound { run_no })?;
+        if status.status != "NEW" {
+            return Err(BulkRunError::InvalidRunStatus {
+                run_no,
+                action: "claim",
+                current: status.status,
+                expected: "NEW",
+            });
+        }
+
+        let outcome = self
+            .database
+            .acquire_claim(run_no, request.line_id, username, claim_lease(), request.take_over)
+            .await
+            .context("Failed to claim run")?;
+
+        match outcome {
+            ClaimOutcome::Acquired(claim) => {
+                info!("🔒 CLAIM: {} holds run {} (line {:?}) until {}", username, run_no, claim.line_id, claim.expires_at);
+                Ok(claim)
+            }
+            ClaimOutcome::HeldBy(holder) => Err(BulkRunError::ClaimedByOther {
+                run_no,
+                line_id: holder.line_id,
+                holder: holder.username,
+            }),
+        }
+    }
+
+    /// Give up a claim. Supervisors may release anyone's claim; pickers only their own.
+    #[instrument(skip(self))]
+    pub async fn release_claim(
+        &self,
+        run_no: i32,
+        line_id: Option<i32>,
+        username: &str,
+        is_supervisor: bool,
+    ) -> Result<bool> {
+        let holder = (!is_supervisor).then_some(username);
+        let released = self.database.release_claim(run_no, line_id, holder).await?;
+        if released {
+            info!("🔓 CLAIM: {} released run {} (line {:?})", username, run_no, line_id);
+        }
+        Ok(released)
+    }
+
+    pub async fn get_run_claims(&self, run_no: i32) -> Result<Vec<RunClaim>> {
+        self.database.get_active_claims(&[run_no]).await
+    }
+
+    /// Build and store a FEFO pick plan covering every batch of the run still to pick.
+    /// Replaces any earlier plan; batches the available lots cannot cover are listed as shortages.
+    #[instrument(skip(self))]
+    pub async fn generate_pick_plan(&self, run_no: i32, username: &str) -> Result<PickPlan, BulkRunError> {
+        let status = self
+            .database
+            .get_bulk_run_status(run_no)
+            .await
+            .context("Failed to get run status for pick plan")?
+            .ok_or(BulkRunError::RunNotFound { run_no })?;
+        if status.status != "NEW" {
+            return Err(BulkRunError::InvalidRunStatus {
+                run_no,
+                action: "plan",
+                current: status.status,
+                expected: "NEW",
+            });
+        }
+
+        let (batches, lots) = self.pick_plan_inputs(run_no).await?;
+        let plan = build_pick_plan(run_no, &batches, &lots, username);
+        self.database
+            .save_pick_plan(&plan)
+            .await
+            .context("Failed to store pick plan")?;
 
         info!(
-            "Pick confirmation completed successfully for run: {} - Transaction: {:?}, Document: {:?}",
-            run_no, response.transaction_id, response.document_no
+            "Pick plan for run {} by {}: {} steps, {} shortages",
+            run_no,
+            username,
+            plan.steps.len(),
+            plan.shortages.len()
         );
+        Ok(plan)
+    }
 
-        Ok(response)
+    /// Dry run of the pick plan with the run's stock position per ingredient.
+    /// Nothing is stored, so planners can check a run before it is started.
+    #[instrument(skip(self))]
+    pub async fn preview_pick_plan(&self, run_no: i32) -> Result<PickPlanPreview, BulkRunError> {
+        self.database
+            .get_bulk_run_status(run_no)
+            .await
+            .context("Failed to get run status for pick plan preview")?
+            .ok_or(BulkRunError::RunNotFound { run_no })?;
+
+        let (batches, lots) = self.pick_plan_inputs(run_no).await?;
+        let plan = build_pick_plan(run_no, &batches, &lots, "preview");
+        let ingredients = summarize_ingredient_stock(&batches, &lots, &plan);
+
+        info!(
+            "Pick plan preview for run {}: {} ingredients, {} shortages",
+            run_no,
+            ingredients.len(),
+            plan.shortages.len()
+        );
+        Ok(PickPlanPreview {
+            run_no,
+            generated_at: plan.generated_at,
+            can_fulfil: plan.shortages.is_empty(),
+            ingredients,
+            shortages: plan.shortages,
+            steps: plan.steps,
+        })
+    }
+
+    /// The run's batches and the FEFO ordered lots of each ingredient
+    async fn pick_plan_inputs(
+        &self,
+        run_no: i32,
+    ) -> Result<(Vec<BulkPickedItem>, HashMap<String, Vec<LotInfo>>)> {
+        let batches = self
+            .database
+            .get_bulk_run_batches(run_no)
+            .await
+            .context("Failed to get batches for pick plan")?;
+        let mut lots = HashMap::new();
+        for batch in &batches {
+            if !lots.contains_key(&batch.item_key) {
+                let item_lots = self
+                    .database
+                    .get_available_lots(run_no, &batch.item_key)
+                    .await
+                    .with_context(|| format!("Failed to get lots of {} for pick plan", batch.item_key))?;
+                lots.insert(batch.item_key.clone(), item_lots);
+            }
+        }
+        Ok((batches, lots))
+    }
+
+    pub async fn get_pick_plan(&self, run_no: i32) -> Result<Option<PickPlan>> {
+        self.database.get_pick_plan(run_no).await
+    }
+
+    /// "BULK SUMMARY" labels of the run's picked batches, or of one batch
+    #[instrument(skip(self))]
+    pub async fn get_batch_labels(&self, run_no: i32, batch_no: Option<&str>) -> Result<Vec<BatchLabel>, BulkRunError> {
+        let run = self
+            .database
+            .get_bulk_run(run_no)
+            .await
+            .context("Failed to get run for batch labels")?
+            .ok_or(BulkRunError::RunNotFound { run_no })?;
+        let summary = self
+            .database
+            .get_batch_weight_summary(run_no)
+            .await
+            .context("Failed to get batch weight summary for batch labels")?;
+        let mut lots = self
+            .database
+            .get_lot_picking_details_for_run(run_no)
+            .await
+            .context("Failed to get lot picking details for batch labels")?;
+        if let Some(batch_no) = batch_no {
+            lots.retain(|lot| lot.batch_no.trim() == batch_no.trim());
+        }
+
+        let labels = build_batch_labels(&run, &summary, &lots, get_bangkok_time());
+        if labels.is_empty() {
+            return Err(BulkRunError::NothingToPrint { run_no, batch_no: batch_no.map(str::to_string) });
+        }
+
+        info!("Built {} batch labels for run {} (batch {:?})", labels.len(), run_no, batch_no);
+        Ok(labels)
+    }
+
+    /// Labels of one pallet (batch row) with its SSCC barcode. Read-only: a pallet without an
+    /// SSCC answers `SsccNotAssigned` until `assign_pallet_sscc` (or queueing its print job) runs.
+    #[instrument(skip(self))]
+    pub async fn get_pallet_labels(&self, run_no: i32, row_num: i32) -> Result<Vec<PalletLabel>, BulkRunError> {
+        let (run, summary, lots, _) = self.pallet_contents(run_no, row_num).await?;
+        let pallet = self
+            .database
+            .get_pallet_sscc(run_no, row_num)
+            .await
+            .context("Failed to get pallet SSCC")?
+            .ok_or(BulkRunError::SsccNotAssigned { run_no, row_num })?;
+
+        let labels = build_pallet_labels(&run, &summary, &lots, &pallet, get_bangkok_time());
+        info!("Built {} pallet labels for run {} pallet {} (SSCC {})", labels.len(), run_no, row_num, pallet.sscc);
+        Ok(labels)
+    }
+
+    /// Give a pallet with picked lots its SSCC. The first call assigns the next serial; later
+    /// calls return the same SSCC, so every reprint carries it.
+    #[instrument(skip(self))]
+    pub async fn assign_pallet_sscc(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        username: &str,
+    ) -> Result<PalletSscc, BulkRunError> {
+        let (_, _, _, batch_no) = self.pallet_contents(run_no, row_num).await?;
+        if let Some(pallet) = self
+            .database
+            .get_pallet_sscc(run_no, row_num)
+            .await
+            .context("Failed to get pallet SSCC")?
+        {
+            return Ok(pallet);
+        }
+
+        let config = self.sscc_config.as_ref().ok_or(BulkRunError::SsccNotConfigured)?;
+        let pallet = self
+            .database
+            .assign_pallet_sscc(run_no, row_num, &batch_no, config, username)
+            .await
+            .context("Failed to assign pallet SSCC")?;
+        info!("Assigned SSCC {} to run {} pallet {} for {}", pallet.sscc, run_no, row_num, username);
+        Ok(pallet)
+    }
+
+    /// The run, its batch rows and picked lots, and the batch number of a pallet that has
+    /// something on it
+    async fn pallet_contents(
+        &self,
+        run_no: i32,
+        row_num: i32,
+    ) -> Result<(BulkRun, Vec<BatchWeightSummaryItem>, Vec<LotPickingDetail>, String), BulkRunError> {
+        let run = self
+            .database
+            .get_bulk_run(run_no)
+            .await
+            .context("Failed to get run for pallet label")?
+            .ok_or(BulkRunError::RunNotFound { run_no })?;
+        let summary = self
+            .database
+            .get_batch_weight_summary(run_no)
+            .await
+            .context("Failed to get batch weight summary for pallet label")?;
+        let batch_no = summary
+            .iter()
+            .find(|item| item.row_num == row_num)
+            .map(|item| item.batch_no.trim().to_string())
+            .ok_or_else(|| BulkRunError::PalletNotFound { pallet: format!("{row_num} of run {run_no}") })?;
+        let lots = self
+            .database
+            .get_lot_picking_details_for_run(run_no)
+            .await
+            .context("Failed to get lot picking details for pallet label")?;
+        if !lots.iter().any(|lot| lot.batch_no.trim() == batch_no) {
+            return Err(BulkRunError::NothingToPrint { run_no, batch_no: Some(batch_no) });
+        }
+        Ok((run, summary, lots, batch_no))
+    }
+
+    /// The pallet a scanned or typed SSCC belongs to, with the lots picked onto it
+    #[instrument(skip(self))]
+    pub async fn lookup_pallet(&self, sscc: &str) -> Result<PalletLookup, BulkRunError> {
+        let sscc = parse_sscc(sscc).map_err(BulkRunError::InvalidSscc)?;
+        let pallet = self
+            .database
+            .find_pallet_by_sscc(&sscc)
+            .await
+            .context("Failed to look up SSCC")?
+            .ok_or_else(|| BulkRunError::PalletNotFound { pallet: format!("with SSCC {sscc}") })?;
+        let run = self
+            .database
+            .get_bulk_run(pallet.run_no)
+            .await
+            .context("Failed to get run of pallet")?
+            .ok_or(BulkRunError::RunNotFound { run_no: pallet.run_no })?;
+        let summary = self
+            .database
+            .get_batch_weight_summary(pallet.run_no)
+            .await
+            .context("Failed to get batch weight summary of pallet")?;
+        let lots = self
+            .database
+            .get_lot_picking_details_for_run(pallet.run_no)
+            .await
+            .context("Failed to get lots of pallet")?;
+
+        let lines = build_pallet_labels(&run, &summary, &lots, &pallet, get_bangkok_time())
+            .into_iter()
+            .flat_map(|label| label.batch.lines)
+            .collect();
+        Ok(PalletLookup {
+            pallet,
+            product_key: run.formula_id.trim().to_string(),
+            product_desc: run.formula_desc.trim().to_string(),
+            run_status: run.status,
+            lines,
+        })
+    }
+
+    /// Tick off the plan steps a committed pick covered. Picking off-plan is allowed,
+    /// so a pick without a matching step (or without a plan) changes nothing.
+    async fn advance_pick_plan(&self, run_no: i32, request: &PickConfirmationRequest) {
+        for allocation in request.lot_allocations() {
+            if let Err(e) = self
+                .database
+                .mark_pick_plan_step_picked(
+                    run_no,
+                    request.row_num,
+                    request.line_id,
+                    &allocation.lot_no,
+                    &allocation.bin_no,
+                )
+                .await
+            {
+                warn!("Could not update pick plan of run {}: {}", run_no, e);
+            }
+        }
     }
 
     /// Validate pick request before processing
@@ -735,16 +1546,16 @@ impl BulkRunsService {
     pub async fn validate_pick_request(
         &self,
         run_no: i32,
-        request: PickConfirmationRequest,
+        mut request: PickConfirmationRequest,
     ) -> Result<PickValidationResult> {
         info!(
             "Validating pick request for run: {}, lot: {}",
             run_no, request.lot_no
         );
 
+        request.normalize_allocations().map_err(anyhow::Error::msg)?;
         let validation_result = self
-            .database
-            .validate_pick_request(run_no, &request)
+            .validate_allocations(run_no, &request)
             .await
             .context("Failed to validate pick request")?;
 
@@ -793,6 +1604,57 @@ impl BulkRunsService {
             .context("Failed to get next available pallet")
     }
 
+    /// Unpick a batch or specific lot (replicate official app unpicking pattern)
+    /// Priority: LotTranNo (single record) > lot number > entire batch
+    #[instrument(skip(self))]
+    pub async fn unpick_ingredient(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        request: &UnpickRequest,
+        user_id: &str,
+    ) -> Result<serde_json::Value> {
+        let result = if let Some(lot_tran_no) = request.lot_tran_no {
+            info!("🎯 Precise unpick using LotTranNo: {}", lot_tran_no);
+            self.database.unpick_by_lot_tran_no(lot_tran_no, user_id).await?
+        } else {
+            match request.lot_no.as_deref() {
+                Some(lot_no) => {
+                    info!("🎯 Unpicking specific lot: {}", lot_no);
+                    self.database
+                        .unpick_specific_lot(run_no, row_num, line_id, lot_no, user_id)
+                        .await?
+                }
+                None => {
+                    info!("🎯 Unpicking entire batch");
+                    self.database
+                        .unpick_entire_batch(run_no, row_num, line_id, user_id)
+                        .await?
+                }
+            }
+        };
+
+        self.publish(
+            run_no,
+            user_id,
+            RunEventKind::Unpick {
+                row_num: Some(row_num),
+                line_id: Some(line_id),
+                lot_no: request.lot_no.clone(),
+            },
+        );
+        Ok(result)
+    }
+
+    /// Unpick all lots from all ingredients in a run
+    #[instrument(skip(self))]
+    pub async fn unpick_all_run_lots(&self, run_no: i32, user_id: &str) -> Result<serde_json::Value> {
+        let result = self.database.unpick_all_run_lots(run_no, user_id).await?;
+        self.publish(run_no, user_id, RunEventKind::Unpick { row_num: None, line_id: None, lot_no: None });
+        Ok(result)
+    }
+
     /// **REVERT STATUS SERVICE** - Revert bulk run status from PRINT back to NEW
     /// Used when user wants to make changes after run completion
     /// Returns the updated status data on success, None on failure
@@ -828,6 +1690,11 @@ impl BulkRunsService {
 
         if revert_success {
             info!("✅ SERVICE: Successfully reverted run {} status from PRINT to NEW", run_no);
+            self.publish(
+                run_no,
+                user_id,
+                RunEventKind::RunStatusChanged { from: current_status.status, to: "NEW".to_string() },
+            );
 
             // Get the updated status to return
             match self.database.get_bulk_run_status(run_no).await {
@@ -881,9 +1748,7 @@ impl BulkRunsService {
         &self,
         run_no: i32,
         user_id: &str,
-    ) -> Result<StatusUpdateResult, anyhow::Error> {
-        use crate::models::bulk_runs::StatusUpdateResult;
-
+    ) -> Result<StatusUpdateResult, BulkRunError> {
         info!("🔄 SERVICE: Completing run {} status (NEW → PRINT) for user: {}", run_no, user_id);
 
         // First, verify that the run is actually complete
@@ -891,12 +1756,14 @@ impl BulkRunsService {
             .context("Failed to verify run completion before status update")?;
 
         if !completion_status.is_complete {
-            let error_msg = format!(
-                "Cannot complete run {} - still has {} incomplete ingredients (completed: {}/{})",
-                run_no, completion_status.incomplete_count, completion_status.completed_count, completion_status.total_ingredients
-            );
-            warn!("⚠️ SERVICE: {}", error_msg);
-            return Err(anyhow::anyhow!(error_msg));
+            let error = BulkRunError::RunIncomplete {
+                run_no,
+                incomplete: completion_status.incomplete_count,
+                completed: completion_status.completed_count,
+                total: completion_status.total_ingredients,
+            };
+            warn!("⚠️ SERVICE: {}", error);
+            return Err(error);
         }
 
         // Get current status
@@ -908,18 +1775,20 @@ impl BulkRunsService {
         let current_status = match current_status_option {
             Some(status) => status.status,
             None => {
-                let error_msg = format!("Run {run_no} not found");
-                warn!("⚠️ SERVICE: {}", error_msg);
-                return Err(anyhow::anyhow!(error_msg));
+                warn!("⚠️ SERVICE: Run {} not found", run_no);
+                return Err(BulkRunError::RunNotFound { run_no });
             }
         };
 
         if current_status != "NEW" {
-            let error_msg = format!(
-                "Cannot complete run {run_no} - current status is '{current_status}', expected 'NEW'"
-            );
-            warn!("⚠️ SERVICE: {}", error_msg);
-            return Err(anyhow::anyhow!(error_msg));
+            let error = BulkRunError::InvalidRunStatus {
+                run_no,
+                action: "complete",
+                current: current_status,
+                expected: "NEW",
+            };
+            warn!("⚠️ SERVICE: {}", error);
+            return Err(error);
         }
 
         // Perform the status update to PRINT
@@ -931,10 +1800,15 @@ impl BulkRunsService {
         if !update_success {
             let error_msg = format!("Failed to update run {run_no} status - no rows were updated");
             warn!("⚠️ SERVICE: {}", error_msg);
-            return Err(anyhow::anyhow!(error_msg));
+            return Err(anyhow::anyhow!(error_msg).into());
         }
 
         info!("✅ SERVICE: Successfully updated run {} status from NEW to PRINT", run_no);
+        self.publish(
+            run_no,
+            user_id,
+            RunEventKind::RunStatusChanged { from: current_status.clone(), to: "PRINT".to_string() },
+        );
 
         Ok(StatusUpdateResult {
             old_status: current_status,
@@ -947,9 +1821,7 @@ impl BulkRunsService {
         &self,
         run_no: i32,
         user_id: &str,
-    ) -> Result<StatusUpdateResult, anyhow::Error> {
-        use crate::models::bulk_runs::StatusUpdateResult;
-
+    ) -> Result<StatusUpdateResult, BulkRunError> {
         info!("🔄 SERVICE: Checking print status update for run {} by user: {}", run_no, user_id);
 
         // Get current status
@@ -961,9 +1833,8 @@ impl BulkRunsService {
         let current_status = match current_status_option {
             Some(status) => status.status,
             None => {
-                let error_msg = format!("Run {run_no} not found");
-                warn!("⚠️ SERVICE: {}", error_msg);
-                return Err(anyhow::anyhow!(error_msg));
+                warn!("⚠️ SERVICE: Run {} not found", run_no);
+                return Err(BulkRunError::RunNotFound { run_no });
             }
         };
 
@@ -986,10 +1857,15 @@ impl BulkRunsService {
             if !update_success {
                 let error_msg = format!("Failed to update run {run_no} status - no rows were updated");
                 warn!("⚠️ SERVICE: {}", error_msg);
-                return Err(anyhow::anyhow!(error_msg));
+                return Err(anyhow::anyhow!(error_msg).into());
             }
 
             info!("✅ SERVICE: Successfully updated run {} status from NEW to PRINT", run_no);
+            self.publish(
+                run_no,
+                user_id,
+                RunEventKind::RunStatusChanged { from: current_status.clone(), to: "PRINT".to_string() },
+            );
 
             Ok(StatusUpdateResult {
                 old_status: current_status,
@@ -0,0 +1,542 @@
+use anyhow::{Context, Result};
+use ldap3::{ldap_escape, Ldap, LdapConnAsync, LdapConnSettings, Scope, SearchEntry};
+use native_tls::{Certificate, TlsConnector};
+use std::env;
+use std::time::Duration;
+use tracing::{info, warn};
+
+use crate::types::{Role, User};
+use crate::utils::roles::{parse_role_groups, roles_from_ldap_groups};
+
+type LdapResult<T> = std::result::Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// LDAP result code for a bind with a wrong password or unknown account
+const LDAP_INVALID_CREDENTIALS: u32 = 49;
+
+/// The directory answered and refused the credentials, as opposed to being unreachable or
+/// misconfigured. Login counts these against the throttle whatever the SQL fallback says.
+#[derive(Debug, thiserror::Error)]
+#[error("{0}")]
+pub struct CredentialsRejected(String);
+
+/// Default department attribute priority (first non-empty value wins)
+const DEFAULT_DEPARTMENT_ATTRIBUTES: &str =
+    "department,company,title,organizationalUnit,ou,division,physicalDeliveryOfficeName,description";
+
+/// Attributes always requested in addition to the department attributes
+const IDENTITY_ATTRIBUTES: [&str; 7] = [
+    "cn",
+    "displayName",
+    "givenName",
+    "sAMAccountName",
+    "userPrincipalName",
+    "memberOf",
+    "userAccountControl",
+];
+
+/// userAccountControl flag set on disabled AD accounts
+const ACCOUNT_DISABLED_FLAG: u32 = 0x2;
+
+/// What the directory says about an account when a session is refreshed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectoryAccount {
+    Active(Vec<Role>),
+    Disabled,
+    NotFound,
+}
+
+#[derive(Clone, Debug)]
+pub struct LdapConfig {
+    pub url: String,
+    pub base_dn: String,
+    pub enabled: bool,
+    /// AD group CN -> application role mapping (LDAP_ROLE_GROUPS)
+    pub role_groups: Vec<(Role, String)>,
+    /// UPN suffixes tried in order when binding as the user (LDAP_UPN_SUFFIXES)
+    pub upn_suffixes: Vec<String>,
+    /// Also try binding with the bare username after the UPN suffixes
+    pub try_bare_username: bool,
+    /// Upgrade plain ldap:// connections with StartTLS
+    pub starttls: bool,
+    /// TLS connector pinned to LDAP_CA_CERT (built-in roots disabled); None uses the system trust store
+    pub tls_connector: Option<TlsConnector>,
+    /// Service account used to look up the user DN before binding as the user
+    pub bind_dn: Option<String>,
+    pub bind_password: Option<String>,
+    /// Attribute priority list for the user's department
+    pub department_attributes: Vec<String>,
+    pub timeout: Duration,
+}
+
+impl LdapConfig {
+    /// Load LDAP configuration from environment variables
+    pub fn from_env() -> Result<Self> {
+        let list = |name: &str, default: &str| -> Vec<String> {
+            env::var(name)
+                .unwrap_or_else(|_| default.to_string())
+                .split(',')
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty())
+                .collect()
+        };
+        let flag = |name: &str, default: bool| -> bool {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        let tls_connector = match env::var("LDAP_CA_CERT").ok().filter(|p| !p.is_empty()) {
+            Some(path) => {
+                let pem = std::fs::read(&path)
+                    .with_context(|| format!("Failed to read LDAP CA certificate '{path}'"))?;
+                let ca = Certificate::from_pem(&pem)
+                    .with_context(|| format!("Invalid PEM in LDAP CA certificate '{path}'"))?;
+                let connector = TlsConnector::builder()
+                    .add_root_certificate(ca)
+                    .disable_built_in_roots(true)
+                    .build()
+                    .context("Failed to build pinned LDAP TLS connector")?;
+                info!("🔒 LDAP TLS pinned to CA certificate: {}", path);
+                Some(connector)
+            }
+            None => None,
+        };
+
+        let bind_dn = env::var("LDAP_BIND_DN").ok().filter(|v| !v.is_empty());
+        let bind_password = env::var("LDAP_BIND_PASSWORD").ok().filter(|v| !v.is_empty());
+        if bind_dn.is_some() != bind_password.is_some() {
+            warn!("⚠️ LDAP_BIND_DN and LDAP_BIND_PASSWORD must both be set - service account search disabled");
+        }
+        let (bind_dn, bind_password) = match (bind_dn, bind_password) {
+            (Some(dn), Some(pw)) => (Some(dn), Some(pw)),
+            _ => (None, None),
+        };
+
+        let config = Self {
+            url: env::var("LDAP_URL").unwrap_or_else(|_| "ldap://192.168.0.1".to_string()),
+            base_dn: env::var("LDAP_BASE_DN").unwrap_or_else(|_| "DC=NWFTH,DC=com".to_string()),
+            enabled: flag("LDAP_ENABLED", true),
+            role_groups: parse_role_groups(&env::var("LDAP_ROLE_GROUPS").unwrap_or_default()),
+            upn_suffixes: list("LDAP_UPN_SUFFIXES", "NWFTH.com,newlywedsfoods.co.th")
+                .into_iter()
+                .map(|s| s.trim_start_matches('@').to_string())
+                .collect(),
+            try_bare_username: flag("LDAP_TRY_BARE_USERNAME", true),
+            starttls: flag("LDAP_STARTTLS", false),
+            tls_connector,
+            bind_dn,
+            bind_password,
+            department_attributes: list("LDAP_DEPARTMENT_ATTRIBUTES", DEFAULT_DEPARTMENT_ATTRIBUTES),
+            timeout: Duration::from_secs(
+                env::var("LDAP_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or(10),
+            ),
+        };
+
+        if config.starttls && config.url.starts_with("ldaps://") {
+            warn!("⚠️ LDAP_STARTTLS is ignored for ldaps:// URLs");
+        }
+        if !config.url.starts_with("ldaps://") && !config.starttls {
+            warn!("⚠️ LDAP credentials are sent unencrypted - use ldaps:// or LDAP_STARTTLS=true");
+        }
+
+        Ok(config)
+    }
+
+    /// Whether user DNs are looked up with a service account before binding as the user
+    pub fn uses_service_account(&self) -> bool {
+        self.bind_dn.is_some()
+    }
+
+    /// Bind names tried in direct-bind mode: `user@suffix` for each suffix, then the bare username
+    pub fn bind_candidates(&self, username: &str) -> Vec<String> {
+        if username.contains('@') || username.contains('\\') {
+            return vec![username.to_string()];
+        }
+        let mut candidates: Vec<String> = self
+            .upn_suffixes
+            .iter()
+            .map(|suffix| format!("{username}@{suffix}"))
+            .collect();
+        if self.try_bare_username || candidates.is_empty() {
+            candidates.push(username.to_string());
+        }
+        candidates
+    }
+
+    fn search_attributes(&self) -> Vec<String> {
+        let mut attributes: Vec<String> = IDENTITY_ATTRIBUTES.iter().map(|a| a.to_string()).collect();
+        for attribute in &self.department_attributes {
+            if !attributes.contains(attribute) {
+                attributes.push(attribute.clone());
+            }
+        }
+        attributes
+    }
+}
+
+/// Authenticate a user against AD.
+///
+/// With a service account configured, the user entry is found first (by sAMAccountName or any
+/// configured UPN) and the password is checked by binding as that DN. Otherwise each bind
+/// candidate from `LDAP_UPN_SUFFIXES` is tried in turn.
+pub async fn authenticate_ldap(config: &LdapConfig, username: &str, password: &str) -> LdapResult<User> {
+    // An empty password is an unauthenticated bind, which AD accepts - never treat it as a login
+    if password.is_empty() {
+        return Err(CredentialsRejected("Empty password".to_string()).into());
+    }
+
+    let clean_username = clean_username(username);
+
+    if config.uses_service_account() {
+        return authenticate_with_service_account(config, &clean_username, password).await;
+    }
+
+    let mut last_error: Box<dyn std::error::Error + Send + Sync> = "No LDAP bind candidates".into();
+    for candidate in config.bind_candidates(username) {
+        info!("🔍 Attempting LDAP authentication for: {}", candidate);
+        match authenticate_direct(config, &candidate, &clean_username, password).await {
+            Ok(user) => {
+                info!("✅ LDAP authentication successful for: {}", candidate);
+                return Ok(user);
+            }
+            Err(e) => {
+                info!("❌ LDAP authentication failed for {}: {}", candidate, e);
+                // Keep a rejection over a later connection error so the attempt still counts
+                if e.is::<CredentialsRejected>() || !last_error.is::<CredentialsRejected>() {
+                    last_error = e;
+                }
+            }
+        }
+    }
+    Err(last_error)
+}
+
+async fn connect(config: &LdapConfig) -> LdapResult<Ldap> {
+    let mut settings = LdapConnSettings::new()
+        .set_conn_timeout(config.timeout)
+        .set_starttls(config.starttls && !config.url.starts_with("ldaps://"));
+    if let Some(connector) = &config.tls_connector {
+        settings = settings.set_connector(connector.clone());
+    }
+
+    let (conn, mut ldap) = LdapConnAsync::with_settings(settings, &config.url).await?;
+    ldap3::drive!(conn);
+    ldap.with_timeout(config.timeout);
+    Ok(ldap)
+}
+
+/// Turn a bind as the user into `CredentialsRejected` when AD refused the password
+fn user_bind_result(result: ldap3::LdapResult) -> LdapResult<()> {
+    if result.rc == LDAP_INVALID_CREDENTIALS {
+        return Err(CredentialsRejected(format!("Invalid credentials: {}", result.text)).into());
+    }
+    result.success()?;
+    Ok(())
+}
+
+/// Bind as the user directly, then read their entry
+async fn authenticate_direct(
+    config: &LdapConfig,
+    bind_name: &str,
+    clean_username: &str,
+    password: &str,
+) -> LdapResult<User> {
+    let mut ldap = connect(config).await?;
+
+    // Bind with user credentials
+    user_bind_result(ldap.simple_bind(bind_name, password).await?)?;
+
+    // Search for user information by the name we bound with, then by sAMAccountName
+    let mut filters = Vec::new();
+    if bind_name.contains('@') {
+        filters.push(format!("(userPrincipalName={})", ldap_escape(bind_name)));
+    }
+    filters.push(format!("(sAMAccountName={})", ldap_escape(clean_username)));
+
+    let mut entry = None;
+    for filter in filters {
+        info!("🔍 LDAP search starting - Base DN: '{}', Filter: '{}'", config.base_dn, filter);
+        if let Some(found) = search_user(&mut ldap, config, &filter).await? {
+            entry = Some(found);
+            break;
+        }
+    }
+
+    let user = match entry {
+        Some(entry) => build_user(config, clean_username, &entry),
+        None => {
+            warn!("⚠️ LDAP bind succeeded but no entry found for {} - using defaults", bind_name);
+            fallback_user(clean_username)
+        }
+    };
+
+    ldap.unbind().await?;
+    Ok(user)
+}
+
+/// Look the user up with the service account, then verify the password by binding as their DN
+async fn authenticate_with_service_account(
+    config: &LdapConfig,
+    clean_username: &str,
+    password: &str,
+) -> LdapResult<User> {
+    let (Some(bind_dn), Some(bind_password)) = (&config.bind_dn, &config.bind_password) else {
+        return Err("LDAP service account is not configured".into());
+    };
+
+    let mut ldap = connect(config).await?;
+    ldap.simple_bind(bind_dn, bind_password).await?.success()?;
+
+    let filter = service_account_filter(config, clean_username);
+
+    info!("🔍 LDAP service account search - Base DN: '{}', Filter: '{}'", config.base_dn, filter);
+    let Some(entry) = search_user(&mut ldap, config, &filter).await? else {
+        ldap.unbind().await?;
+        return Err(CredentialsRejected(format!("User '{clean_username}' not found in directory")).into());
+    };
+
+    // Verify the password by binding as the user's DN on the same connection
+    info!("🔑 Binding as user DN: {}", entry.dn);
+    let bind_result = user_bind_result(ldap.simple_bind(&entry.dn, password).await?);
+    let _ = ldap.unbind().await;
+    bind_result?;
+
+    info!("✅ LDAP authentication successful for: {}", entry.dn);
+    Ok(build_user(config, clean_username, &entry))
+}
+
+/// Re-read a user's account state and roles from AD with the service account (used when a
+/// session is refreshed, so a changed group membership or a disabled account takes effect
+/// without a new login). None when no service account is configured.
+/// None when no service account is configured or the user is not in the directory.
+pub async fn lookup_ldap_account(config: &LdapConfig, username: &str) -> LdapResult<Option<DirectoryAccount>> {
+    let (Some(bind_dn), Some(bind_password)) = (&config.bind_dn, &config.bind_password) else {
+        return Ok(None);
+    };
+    if !config.enabled {
+        return Ok(None);
+    }
+
+    let clean_username = clean_username(username);
+    let mut ldap = connect(config).await?;
+    ldap.simple_bind(bind_dn, bind_password).await?.success()?;
+
+    let filter = service_account_filter(config, &clean_username);
+    let entry = search_user(&mut ldap, config, &filter).await?;
+    let _ = ldap.unbind().await;
+
+    let Some(entry) = entry else {
+        return Ok(Some(DirectoryAccount::NotFound));
+    };
+    if is_disabled(&entry) {
+        return Ok(Some(DirectoryAccount::Disabled));
+    }
+    let member_of = entry.attrs.get("memberOf").cloned().unwrap_or_default();
+    Ok(Some(DirectoryAccount::Active(roles_from_ldap_groups(&member_of, &config.role_groups))))
+}
+
+fn is_disabled(entry: &SearchEntry) -> bool {
+    entry
+        .attrs
+        .get("userAccountControl")
+        .and_then(|values| values.first())
+        .and_then(|value| value.trim().parse::<u32>().ok())
+        .is_some_and(|flags| flags & ACCOUNT_DISABLED_FLAG != 0)
+}
+
+/// Service account search for a user by sAMAccountName or any configured UPN
+fn service_account_filter(config: &LdapConfig, clean_username: &str) -> String {
+    let escaped = ldap_escape(clean_username);
+    let upn_filters: String = config
+        .upn_suffixes
+        .iter()
+        .map(|suffix| format!("(userPrincipalName={}@{})", escaped, ldap_escape(suffix.as_str())))
+        .collect();
+    format!("(&(objectClass=user)(|(sAMAccountName={escaped}){upn_filters}))")
+}
+
+async fn search_user(ldap: &mut Ldap, config: &LdapConfig, filter: &str) -> LdapResult<Option<SearchEntry>> {
+    let (results, _res) = ldap
+        .search(&config.base_dn, Scope::Subtree, filter, config.search_attributes())
+        .await?
+        .success()?;
+
+    info!("📊 LDAP search completed - Found {} results", results.len());
+    Ok(results.into_iter().next().map(SearchEntry::construct))
+}
+
+fn build_user(config: &LdapConfig, clean_username: &str, entry: &SearchEntry) -> User {
+    let first = |attribute: &str| {
+        entry
+            .attrs
+            .get(attribute)
+            .and_then(|values| values.first())
+            .filter(|value| !value.trim().is_empty())
+            .cloned()
+    };
+
+    let display_name = first("displayName")
+        .or_else(|| first("cn"))
+        .unwrap_or_else(|| capitalize(clean_username));
+
+    // Department: first non-empty attribute from the configured priority list
+    let department = config
+        .department_attributes
+        .iter()
+        .find_map(|attribute| first(attribute).map(|value| (attribute, value)));
+    match &department {
+        Some((attribute, value)) => info!("🎯 Department for {} from '{}': '{}'", clean_username, attribute, value),
+        None => info!("⚠️  No department information found in any configured AD attribute for user: {}", clean_username),
+    }
+
+    // Map AD group membership to application roles
+    let member_of = entry.attrs.get("memberOf").cloned().unwrap_or_default();
+    let roles = roles_from_ldap_groups(&member_of, &config.role_groups);
+    info!("🛡️ Roles for {}: {:?}", clean_username, roles);
+
+    User {
+        user_id: clean_username.to_string(),
+        username: clean_username.to_string(),
+        email: format!("{clean_username}@nwfth.com"),
+        display_name,
+        is_active: true,
+        roles,
+    }
+}
+
+fn fallback_user(clean_username: &str) -> User {
+    User {
+        user_id: clean_username.to_string(),
+        username: clean_username.to_string(),
+        email: format!("{clean_username}@nwfth.com"),
+        display_name: capitalize(clean_username),
+        is_active: true,
+        roles: vec![Role::Picker],
+    }
+}
+
+/// Strip any `@domain` suffix or `DOMAIN\` prefix from a login name
+pub fn clean_username(username: &str) -> String {
+    let without_domain = username.rsplit('\\').next().unwrap_or(username);
+    without_domain
+        .split('@')
+        .next()
+        .unwrap_or(without_domain)
+        .to_string()
+}
+
+fn capitalize(value: &str) -> String {
+    let mut chars = value.chars();
+    match chars.next() {
+        None => value.to_string(),
+        Some(first) => first.to_uppercase().collect::<String>() + chars.as_str(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LdapConfig {
+        LdapConfig {
+            url: "ldaps://dc.example".to_string(),
+            base_dn: "DC=NWFTH,DC=com".to_string(),
+            enabled: true,
+            role_groups: vec![(Role::Supervisor, "WMS Supervisors".to_string())],
+            upn_suffixes: vec!["NWFTH.com".to_string(), "newlywedsfoods.co.th".to_string()],
+            try_bare_username: true,
+            starttls: false,
+            tls_connector: None,
+            bind_dn: None,
+            bind_password: None,
+            department_attributes: vec!["department".to_string(), "company".to_string()],
+            timeout: Duration::from_secs(5),
+        }
+    }
+
+    #[test]
+    fn test_bind_candidates_follow_configured_suffixes() {
+        let mut config = config();
+
+        assert_eq!(
+            config.bind_candidates("deachawat"),
+            vec!["deachawat@NWFTH.com", "deachawat@newlywedsfoods.co.th", "deachawat"]
+        );
+        assert_eq!(config.bind_candidates("deachawat@other.com"), vec!["deachawat@other.com"]);
+
+        config.try_bare_username = false;
+        config.upn_suffixes = vec!["corp.local".to_string()];
+        assert_eq!(config.bind_candidates("deachawat"), vec!["deachawat@corp.local"]);
+    }
+
+    #[test]
+    fn test_build_user_uses_department_priority_and_groups() {
+        let mut attrs = std::collections::HashMap::new();
+        attrs.insert("displayName".to_string(), vec!["Deachawat S.".to_string()]);
+        attrs.insert("department".to_string(), vec!["  ".to_string()]);
+        attrs.insert("company".to_string(), vec!["NWFTH".to_string()]);
+        attrs.insert(
+            "memberOf".to_string(),
+            vec!["CN=WMS Supervisors,OU=Groups,DC=NWFTH,DC=com".to_string()],
+        );
+        let entry = SearchEntry {
+            dn: "CN=Deachawat,OU=Users,DC=NWFTH,DC=com".to_string(),
+            attrs,
+            bin_attrs: std::collections::HashMap::new(),
+        };
+
+        let user = build_user(&config(), "deachawat", &entry);
+
+        assert_eq!(user.display_name, "Deachawat S.");
+        assert_eq!(user.roles, vec![Role::Picker, Role::Supervisor]);
+        assert!(config().search_attributes().contains(&"company".to_string()));
+    }
+
+    #[test]
+    fn test_disabled_flag_read_from_user_account_control() {
+        let entry = |flags: &str| {
+            let mut attrs = std::collections::HashMap::new();
+            attrs.insert("userAccountControl".to_string(), vec![flags.to_string()]);
+            SearchEntry {
+                dn: "CN=Picker1,OU=Users,DC=NWFTH,DC=com".to_string(),
+                attrs,
+                bin_attrs: std::collections::HashMap::new(),
+            }
+        };
+
+        assert!(!is_disabled(&entry("512")));
+        assert!(is_disabled(&entry("514")));
+        assert!(!is_disabled(&SearchEntry {
+            dn: String::new(),
+            attrs: std::collections::HashMap::new(),
+            bin_attrs: std::collections::HashMap::new(),
+        }));
+    }
+
+    #[test]
+    fn test_clean_username() {
+        assert_eq!(clean_username("deachawat@NWFTH.com"), "deachawat");
+        assert_eq!(clean_username("NWFTH\\deachawat"), "deachawat");
+        assert_eq!(clean_username("deachawat"), "deachawat");
+    }
+
+    #[test]
+    fn test_invalid_credentials_bind_is_a_rejection() {
+        let bind = |rc| ldap3::LdapResult {
+            rc,
+            matched: String::new(),
+            text: String::new(),
+            refs: Vec::new(),
+            ctrls: Vec::new(),
+        };
+
+        assert!(user_bind_result(bind(0)).is_ok());
+        assert!(user_bind_result(bind(LDAP_INVALID_CREDENTIALS)).unwrap_err().is::<CredentialsRejected>());
+        // Busy or unavailable directories are not counted as wrong passwords
+        assert!(!user_bind_result(bind(52)).unwrap_err().is::<CredentialsRejected>());
+    }
+}
@@ -0,0 +1,447 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::env;
+use std::net::IpAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+use crate::services::ldap_auth::clean_username;
+
+/// Error code returned while a username or IP is in exponential backoff
+pub const LOGIN_RATE_LIMITED: &str = "LOGIN_RATE_LIMITED";
+/// Error code returned while a username or IP is locked out
+pub const ACCOUNT_LOCKED: &str = "ACCOUNT_LOCKED";
+
+/// Tracked keys above which stale entries are pruned on the next failure
+const PRUNE_THRESHOLD: usize = 1000;
+
+/// Thresholds for one kind of key (username or client IP)
+#[derive(Debug, Clone, Copy)]
+pub struct ThrottleLimits {
+    /// Failures allowed before backoff starts
+    pub free_attempts: u32,
+    /// Failures at which the key is locked out
+    pub lockout_after: u32,
+}
+
+/// Login throttling policy, read from environment
+#[derive(Debug, Clone)]
+pub struct ThrottlePolicy {
+    pub username: ThrottleLimits,
+    pub ip: ThrottleLimits,
+    /// First backoff delay; doubles with each further failure
+    pub backoff_base: Duration,
+    pub backoff_max: Duration,
+    pub lockout_duration: Duration,
+    /// Failure counters reset after this long without a failure
+    pub failure_window: Duration,
+}
+
+impl ThrottlePolicy {
+    pub fn from_env() -> Self {
+        let read = |name: &str, default: u64| -> u64 {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(default)
+        };
+
+        Self {
+            username: ThrottleLimits {
+                free_attempts: read("LOGIN_MAX_ATTEMPTS", 5) as u32,
+                lockout_after: read("LOGIN_LOCKOUT_THRESHOLD", 10) as u32,
+            },
+            // Handhelds on the warehouse Wi-Fi may share an IP behind NAT, so IP limits are looser
+            ip: ThrottleLimits {
+                free_attempts: read("LOGIN_IP_MAX_ATTEMPTS", 20) as u32,
+                lockout_after: read("LOGIN_IP_LOCKOUT_THRESHOLD", 50) as u32,
+            },
+            backoff_base: Duration::from_secs(read("LOGIN_BACKOFF_BASE_SECS", 2)),
+            backoff_max: Duration::from_secs(read("LOGIN_BACKOFF_MAX_SECS", 300)),
+            lockout_duration: Duration::from_secs(read("LOGIN_LOCKOUT_MINUTES", 15) * 60),
+            failure_window: Duration::from_secs(read("LOGIN_ATTEMPT_WINDOW_MINUTES", 15) * 60),
+        }
+    }
+}
+
+/// Why a login attempt was refused before checking credentials
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LoginBlocked {
+    pub error_code: &'static str,
+    pub retry_after_secs: u64,
+}
+
+impl LoginBlocked {
+    pub fn message(&self) -> String {
+        if self.error_code == ACCOUNT_LOCKED {
+            format!(
+                "Too many failed login attempts. Account is locked for {} more minute(s) or until a supervisor clears it.",
+                self.retry_after_secs.div_ceil(60)
+            )
+        } else {
+            format!("Too many failed login attempts. Try again in {} second(s).", self.retry_after_secs)
+        }
+    }
+}
+
+/// Currently throttled or locked key, for the supervisor lockout view
+#[derive(Debug, Clone, Serialize)]
+pub struct LockoutEntry {
+    pub kind: String,
+    pub key: String,
+    pub failed_attempts: u32,
+    pub locked: bool,
+    pub retry_after_secs: u64,
+}
+
+#[derive(Debug, Clone)]
+struct AttemptState {
+    failures: u32,
+    last_failure: Instant,
+    blocked_until: Option<Instant>,
+    locked: bool,
+}
+
+/// Attempt counters per username and per client IP, behind one lock so an attempt is
+/// checked and counted atomically
+#[derive(Debug, Default)]
+struct Attempts {
+    users: HashMap<String, AttemptState>,
+    ips: HashMap<IpAddr, AttemptState>,
+}
+
+/// In-memory per-username and per-IP login attempt tracker with exponential backoff and lockout
+#[derive(Clone)]
+pub struct LoginThrottle {
+    policy: ThrottlePolicy,
+    attempts: Arc<Mutex<Attempts>>,
+}
+
+impl LoginThrottle {
+    pub fn new(policy: ThrottlePolicy) -> Self {
+        info!(
+            "🛡️ Login throttling: backoff after {} failures, lockout after {} (per IP: {}/{})",
+            policy.username.free_attempts,
+            policy.username.lockout_after,
+            policy.ip.free_attempts,
+            policy.ip.lockout_after
+        );
+        Self {
+            policy,
+            attempts: Arc::new(Mutex::new(Attempts::default())),
+        }
+    }
+
+    /// Normalize a login name the way LDAP does, so `user`, `USER`, `user@NWFTH.com` and
+    /// `NWFTH\user` share one counter
+    pub fn normalize_username(username: &str) -> String {
+        clean_username(username.trim()).to_lowercase()
+    }
+
+    /// Refuse the attempt if the username or IP is backing off or locked out; otherwise count
+    /// it as a failure before the credentials are checked, so parallel attempts cannot all slip
+    /// past the limits. `record_success` or `release` takes the count back.
+    pub fn begin_attempt(&self, username: &str, ip: Option<IpAddr>) -> Result<(), LoginBlocked> {
+        self.begin_attempt_at(username, ip, Instant::now())
+    }
+
+    /// A successful login: clears the username counter and takes back the attempt's IP count
+    /// (the rest of the IP counter only decays)
+    pub fn record_success(&self, username: &str, ip: Option<IpAddr>) {
+        if let Ok(mut attempts) = self.attempts.lock() {
+            attempts.users.remove(&Self::normalize_username(username));
+            if let Some(state) = ip.and_then(|ip| attempts.ips.get_mut(&ip)) {
+                self.take_back(state, self.policy.ip);
+            }
+        }
+    }
+
+    /// Take back an attempt whose credentials could not be checked at all
+    pub fn release(&self, username: &str, ip: Option<IpAddr>) {
+        if let Ok(mut attempts) = self.attempts.lock() {
+            if let Some(state) = attempts.users.get_mut(&Self::normalize_username(username)) {
+                self.take_back(state, self.policy.username);
+            }
+            if let Some(state) = ip.and_then(|ip| attempts.ips.get_mut(&ip)) {
+                self.take_back(state, self.policy.ip);
+            }
+        }
+    }
+
+    /// Clear a username lockout. Returns whether anything was cleared.
+    pub fn clear_username(&self, username: &str) -> bool {
+        self.attempts
+            .lock()
+            .map(|mut attempts| attempts.users.remove(&Self::normalize_username(username)).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Clear an IP lockout. Returns whether anything was cleared.
+    pub fn clear_ip(&self, ip: IpAddr) -> bool {
+        self.attempts
+            .lock()
+            .map(|mut attempts| attempts.ips.remove(&ip).is_some())
+            .unwrap_or(false)
+    }
+
+    /// Keys currently in backoff or lockout
+    pub fn active_lockouts(&self) -> Vec<LockoutEntry> {
+        let now = Instant::now();
+        let mut entries = Vec::new();
+
+        let describe = |kind: &str, key: String, state: &AttemptState| {
+            state
+                .blocked_until
+                .filter(|until| *until > now)
+                .map(|until| LockoutEntry {
+                    kind: kind.to_string(),
+                    key,
+                    failed_attempts: state.failures,
+                    locked: state.locked,
+                    retry_after_secs: until.duration_since(now).as_secs().max(1),
+                })
+        };
+
+        if let Ok(attempts) = self.attempts.lock() {
+            entries.extend(attempts.users.iter().filter_map(|(k, s)| describe("username", k.clone(), s)));
+            entries.extend(attempts.ips.iter().filter_map(|(k, s)| describe("ip", k.to_string(), s)));
+        }
+        entries.sort_by_key(|e| std::cmp::Reverse(e.retry_after_secs));
+        entries
+    }
+
+    fn begin_attempt_at(&self, username: &str, ip: Option<IpAddr>, now: Instant) -> Result<(), LoginBlocked> {
+        let username = Self::normalize_username(username);
+        let Ok(mut attempts) = self.attempts.lock() else {
+            return Ok(());
+        };
+        if let Some(blocked) = Self::blocked_in(&attempts, &username, ip, now) {
+            return Err(blocked);
+        }
+        self.count_failure(&mut attempts, username, ip, now);
+        Ok(())
+    }
+
+    #[cfg(test)]
+    fn check_at(&self, username: &str, ip: Option<IpAddr>, now: Instant) -> Result<(), LoginBlocked> {
+        let attempts = self.attempts.lock().unwrap();
+        match Self::blocked_in(&attempts, &Self::normalize_username(username), ip, now) {
+            Some(blocked) => Err(blocked),
+            None => Ok(()),
+        }
+    }
+
+    #[cfg(test)]
+    fn record_failure_at(&self, username: &str, ip: Option<IpAddr>, now: Instant) {
+        let mut attempts = self.attempts.lock().unwrap();
+        self.count_failure(&mut attempts, Self::normalize_username(username), ip, now);
+    }
+
+    fn blocked_in(attempts: &Attempts, username: &str, ip: Option<IpAddr>, now: Instant) -> Option<LoginBlocked> {
+        attempts
+            .users
+            .get(username)
+            .and_then(|s| Self::blocked(s, now))
+            .or_else(|| ip.and_then(|ip| attempts.ips.get(&ip)).and_then(|s| Self::blocked(s, now)))
+    }
+
+    fn count_failure(&self, attempts: &mut Attempts, username: String, ip: Option<IpAddr>, now: Instant) {
+        self.prune(&mut attempts.users, now);
+        let state = attempts.users.entry(username.clone()).or_insert_with(|| Self::fresh_state(now));
+        self.apply_failure(state, self.policy.username, now);
+        if state.locked {
+            warn!("🔒 Username '{}' locked out after {} failed logins", username, state.failures);
+        }
+        if let Some(ip) = ip {
+            self.prune(&mut attempts.ips, now);
+            let state = attempts.ips.entry(ip).or_insert_with(|| Self::fresh_state(now));
+            self.apply_failure(state, self.policy.ip, now);
+            if state.locked {
+                warn!("🔒 IP {} locked out after {} failed logins", ip, state.failures);
+            }
+        }
+    }
+
+    /// Undo one counted attempt; a backoff or lockout it triggered is lifted with it
+    fn take_back(&self, state: &mut AttemptState, limits: ThrottleLimits) {
+        state.failures = state.failures.saturating_sub(1);
+        if state.failures < limits.free_attempts {
+            state.blocked_until = None;
+            state.locked = false;
+        } else if state.locked && state.failures < limits.lockout_after {
+            state.locked = false;
+        }
+    }
+
+    /// Drop stale entries so random usernames cannot grow the maps without bound
+    fn prune<K>(&self, entries: &mut HashMap<K, AttemptState>, now: Instant) {
+        if entries.len() < PRUNE_THRESHOLD {
+            return;
+        }
+        entries.retain(|_, state| {
+            state.blocked_until.is_some_and(|until| until > now)
+                || now.duration_since(state.last_failure) <= self.policy.failure_window
+        });
+    }
+
+    fn fresh_state(now: Instant) -> AttemptState {
+        AttemptState {
+            failures: 0,
+            last_failure: now,
+            blocked_until: None,
+            locked: false,
+        }
+    }
+
+    fn apply_failure(&self, state: &mut AttemptState, limits: ThrottleLimits, now: Instant) {
+        // Start over once the window has passed without failures (and any lockout has expired)
+        let block_expired = state.blocked_until.is_none_or(|until| until <= now);
+        if block_expired && now.duration_since(state.last_failure) > self.policy.failure_window {
+            *state = Self::fresh_state(now);
+        }
+
+        state.failures += 1;
+        state.last_failure = now;
+
+        if state.failures >= limits.lockout_after {
+            state.locked = true;
+            state.blocked_until = Some(now + self.policy.lockout_duration);
+        } else if state.failures >= limits.free_attempts {
+            let exponent = (state.failures - limits.free_attempts).min(16);
+            let delay = self
+                .policy
+                .backoff_base
+                .saturating_mul(1 << exponent)
+                .min(self.policy.backoff_max);
+            state.blocked_until = Some(now + delay);
+        }
+    }
+
+    fn blocked(state: &AttemptState, now: Instant) -> Option<LoginBlocked> {
+        let until = state.blocked_until.filter(|until| *until > now)?;
+        Some(LoginBlocked {
+            error_code: if state.locked { ACCOUNT_LOCKED } else { LOGIN_RATE_LIMITED },
+            retry_after_secs: until.duration_since(now).as_secs().max(1),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn policy() -> ThrottlePolicy {
+        ThrottlePolicy {
+            username: ThrottleLimits { free_attempts: 3, lockout_after: 6 },
+            ip: ThrottleLimits { free_attempts: 10, lockout_after: 20 },
+            backoff_base: Duration::from_secs(2),
+            backoff_max: Duration::from_secs(60),
+            lockout_duration: Duration::from_secs(900),
+            failure_window: Duration::from_secs(900),
+        }
+    }
+
+    #[test]
+    fn test_backoff_doubles_then_locks() {
+        let throttle = LoginThrottle::new(policy());
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            throttle.record_failure_at("picker1", None, now);
+        }
+        assert!(throttle.check_at("picker1", None, now).is_ok());
+
+        throttle.record_failure_at("picker1", None, now);
+        let blocked = throttle.check_at("PICKER1@nwfth.com", None, now).unwrap_err();
+        assert_eq!(throttle.check_at("NWFTH\\Picker1", None, now).unwrap_err(), blocked);
+        assert_eq!(blocked.error_code, LOGIN_RATE_LIMITED);
+        assert_eq!(blocked.retry_after_secs, 2);
+
+        throttle.record_failure_at("picker1", None, now);
+        assert_eq!(throttle.check_at("picker1", None, now).unwrap_err().retry_after_secs, 4);
+        assert!(throttle.check_at("picker1", None, now + Duration::from_secs(5)).is_ok());
+
+        for _ in 0..2 {
+            throttle.record_failure_at("picker1", None, now);
+        }
+        let locked = throttle.check_at("picker1", None, now).unwrap_err();
+        assert_eq!(locked.error_code, ACCOUNT_LOCKED);
+        assert_eq!(locked.retry_after_secs, 900);
+    }
+
+    #[test]
+    fn test_success_and_clear_reset_username() {
+        let throttle = LoginThrottle::new(policy());
+        let now = Instant::now();
+        let ip: IpAddr = "192.168.0.50".parse().unwrap();
+
+        for _ in 0..6 {
+            throttle.record_failure_at("picker2", Some(ip), now);
+        }
+        assert!(throttle.check_at("picker2", None, now).is_err());
+        assert_eq!(throttle.active_lockouts().len(), 1);
+
+        assert!(throttle.clear_username("Picker2"));
+        assert!(throttle.check_at("picker2", Some(ip), now).is_ok());
+
+        throttle.record_failure_at("picker2", None, now);
+        throttle.record_success("picker2", None);
+        assert!(!throttle.clear_username("picker2"));
+    }
+
+    #[test]
+    fn test_attempts_are_counted_before_credentials_are_checked() {
+        let throttle = LoginThrottle::new(policy());
+        let now = Instant::now();
+        let ip: IpAddr = "10.0.0.7".parse().unwrap();
+
+        // Three requests in flight at once; none has reported back yet
+        for _ in 0..3 {
+            assert!(throttle.begin_attempt_at("picker4", Some(ip), now).is_ok());
+        }
+        assert!(throttle.begin_attempt_at("picker4", Some(ip), now).is_err());
+
+        // An attempt that could not be checked is taken back along with the backoff it caused
+        throttle.release("picker4", Some(ip));
+        assert!(throttle.begin_attempt_at("picker4", Some(ip), now).is_ok());
+
+        // A success clears the username and returns its slot on the IP
+        throttle.record_success("picker4", Some(ip));
+        assert!(throttle.check_at("picker4", Some(ip), now).is_ok());
+        let ip_failures = throttle.attempts.lock().unwrap().ips[&ip].failures;
+        assert_eq!(ip_failures, 2);
+    }
+
+    #[test]
+    fn test_ip_limit_applies_across_usernames() {
+        let throttle = LoginThrottle::new(policy());
+        let now = Instant::now();
+        let ip: IpAddr = "10.0.0.9".parse().unwrap();
+
+        for i in 0..20 {
+            throttle.record_failure_at(&format!("user{i}"), Some(ip), now);
+        }
+
+        let blocked = throttle.check_at("someone-else", Some(ip), now).unwrap_err();
+        assert_eq!(blocked.error_code, ACCOUNT_LOCKED);
+        assert!(throttle.check_at("someone-else", None, now).is_ok());
+
+        assert!(throttle.clear_ip(ip));
+        assert!(throttle.check_at("someone-else", Some(ip), now).is_ok());
+    }
+
+    #[test]
+    fn test_failures_expire_after_window() {
+        let throttle = LoginThrottle::new(policy());
+        let now = Instant::now();
+
+        for _ in 0..2 {
+            throttle.record_failure_at("picker3", None, now);
+        }
+        let later = now + Duration::from_secs(901);
+        throttle.record_failure_at("picker3", None, later);
+
+        assert!(throttle.check_at("picker3", None, later).is_ok());
+    }
+}
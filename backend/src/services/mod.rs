@@ -1,4 +1,15 @@
+#[allow(dead_code)]
+pub mod bulk_picking_validation;
+pub mod batch_labels;
 pub mod bulk_runs_service;
+pub mod ldap_auth;
+pub mod login_throttle;
+pub mod pick_planner;
+pub mod pick_sequence;
+pub mod print_queue;
+pub mod run_events;
+pub mod scan_resolver;
+pub mod security_audit;
 pub mod putaway_service;
 #[cfg(feature = "intelligence")]
 pub mod ingredient_intelligence_service;
@@ -0,0 +1,282 @@
+//! FEFO pick plan for a whole run.
+//!
+//! Walks every batch with bags left to pick, ingredient by ingredient, and assigns
+//! whole bags from the lots `get_available_lots` returns (already FEFO ordered and
+//! limited to nettable, non-PARTIAL, unexpired bins). Each lot's stock is drawn down
+//! as it is assigned, so one lot is never planned twice for more than it holds.
+
+use bigdecimal::{BigDecimal, Zero};
+use chrono::Utc;
+use std::collections::HashMap;
+
+use crate::models::bulk_runs::{BulkPickedItem, LotInfo};
+use crate::models::pick_plan::{IngredientStock, PickPlan, PickPlanShortage, PickPlanStep, PlanStepStatus};
+
+/// Lot/bin stock still unassigned while the plan is built
+struct LotStock {
+    lot: LotInfo,
+    remaining_kg: BigDecimal,
+}
+
+/// Build the plan for `batches` (one row per batch, as from `get_bulk_run_batches`).
+/// `lots` holds the FEFO ordered lots of each ingredient, keyed by item key.
+pub fn build_pick_plan(
+    run_no: i32,
+    batches: &[BulkPickedItem],
+    lots: &HashMap<String, Vec<LotInfo>>,
+    generated_by: &str,
+) -> PickPlan {
+    let mut stock: HashMap<&str, Vec<LotStock>> = HashMap::new();
+    for (item_key, item_lots) in lots {
+        let mut pool: Vec<LotStock> = Vec::with_capacity(item_lots.len());
+        for lot in item_lots {
+            // The lot query returns a lot once per batch of the ingredient
+            if pool.iter().any(|s| s.lot.lot_no == lot.lot_no && s.lot.bin == lot.bin) {
+                continue;
+            }
+            pool.push(LotStock { lot: lot.clone(), remaining_kg: lot.available_qty.clone() });
+        }
+        stock.insert(item_key.as_str(), pool);
+    }
+
+    let mut ordered: Vec<&BulkPickedItem> = batches.iter().collect();
+    ordered.sort_by_key(|batch| (batch.line_id, batch.row_num));
+
+    let mut steps = Vec::new();
+    let mut shortages = Vec::new();
+    for batch in ordered {
+        let picked = batch.picked_bulk_qty.clone().unwrap_or_else(BigDecimal::zero);
+        let required = &batch.to_picked_bulk_qty - picked;
+        if required <= BigDecimal::zero() {
+            continue;
+        }
+
+        let mut needed = required.clone();
+        if batch.pack_size > BigDecimal::zero() {
+            for lot in stock.get_mut(batch.item_key.as_str()).into_iter().flatten() {
+                // Whole bags only: what is left of a lot below one pack size stays unplanned
+                let whole_bags = (&lot.remaining_kg / &batch.pack_size).with_scale(0);
+                if whole_bags <= BigDecimal::zero() {
+                    continue;
+                }
+                let bags = if whole_bags < needed { whole_bags } else { needed.clone() };
+                let qty_kg = &bags * &batch.pack_size;
+                lot.remaining_kg -= &qty_kg;
+                needed -= &bags;
+
+                steps.push(PickPlanStep {
+                    step_no: steps.len() as i32 + 1,
+                    row_num: batch.row_num,
+                    line_id: batch.line_id,
+                    item_key: batch.item_key.clone(),
+                    lot_no: lot.lot.lot_no.clone(),
+                    bin_no: lot.lot.bin.clone().unwrap_or_default(),
+                    bags,
+                    qty_kg,
+                    expiry_date: lot.lot.expiry_date,
+                    status: PlanStepStatus::Pending,
+                });
+                if needed <= BigDecimal::zero() {
+                    break;
+                }
+            }
+        }
+
+        if needed > BigDecimal::zero() {
+            shortages.push(PickPlanShortage {
+                row_num: batch.row_num,
+                line_id: batch.line_id,
+                item_key: batch.item_key.clone(),
+                required_bags: required,
+                short_kg: &needed * &batch.pack_size,
+                short_bags: needed,
+            });
+        }
+    }
+
+    PickPlan {
+        run_no,
+        generated_by: generated_by.to_string(),
+        generated_at: Utc::now(),
+        steps,
+        shortages,
+    }
+}
+
+/// Required against available stock per ingredient line, with the plan's shortages rolled up.
+/// Available stock counts each lot/bin once, whatever the number of batches it was listed for.
+pub fn summarize_ingredient_stock(
+    batches: &[BulkPickedItem],
+    lots: &HashMap<String, Vec<LotInfo>>,
+    plan: &PickPlan,
+) -> Vec<IngredientStock> {
+    let mut ingredients: Vec<IngredientStock> = Vec::new();
+    let mut ordered: Vec<&BulkPickedItem> = batches.iter().collect();
+    ordered.sort_by_key(|batch| (batch.line_id, batch.row_num));
+
+    for batch in ordered {
+        let picked = batch.picked_bulk_qty.clone().unwrap_or_else(BigDecimal::zero);
+        let required = (&batch.to_picked_bulk_qty - picked).max(BigDecimal::zero());
+
+        let index = match ingredients
+            .iter()
+            .position(|i| i.line_id == batch.line_id && i.item_key == batch.item_key)
+        {
+            Some(index) => index,
+            None => {
+                let mut seen: Vec<(&str, Option<&str>)> = Vec::new();
+                let mut available_kg = BigDecimal::zero();
+                for lot in lots.get(&batch.item_key).into_iter().flatten() {
+                    let key = (lot.lot_no.as_str(), lot.bin.as_deref());
+                    if !seen.contains(&key) {
+                        seen.push(key);
+                        available_kg += &lot.available_qty;
+                    }
+                }
+                ingredients.push(IngredientStock {
+                    line_id: batch.line_id,
+                    item_key: batch.item_key.clone(),
+                    description: batch.description.clone(),
+                    pack_size: batch.pack_size.clone(),
+                    required_bags: BigDecimal::zero(),
+                    required_kg: BigDecimal::zero(),
+                    available_kg,
+                    short_bags: BigDecimal::zero(),
+                    short_kg: BigDecimal::zero(),
+                });
+                ingredients.len() - 1
+            }
+        };
+
+        let ingredient = &mut ingredients[index];
+        ingredient.required_kg += &required * &batch.pack_size;
+        ingredient.required_bags += required;
+    }
+
+    for shortage in &plan.shortages {
+        if let Some(ingredient) = ingredients
+            .iter_mut()
+            .find(|i| i.line_id == shortage.line_id && i.item_key == shortage.item_key)
+        {
+            ingredient.short_bags += &shortage.short_bags;
+            ingredient.short_kg += &shortage.short_kg;
+        }
+    }
+    ingredients
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    fn batch(row_num: i32, line_id: i32, item_key: &str, bags: i32, pack_size: i32) -> BulkPickedItem {
+        BulkPickedItem {
+            run_no: 215235,
+            row_num,
+            line_id,
+            item_key: item_key.to_string(),
+            description: None,
+            location: Some("TFC1".to_string()),
+            standard_qty: BigDecimal::from(bags * pack_size),
+            pack_size: BigDecimal::from(pack_size),
+            uom: "KG".to_string(),
+            to_picked_std_qty: BigDecimal::zero(),
+            to_picked_bulk_qty: BigDecimal::from(bags),
+            picked_bulk_qty: None,
+            picking_date: None,
+            status: None,
+            total_batches: None,
+            completed_batches: None,
+            remaining_qty: None,
+            completion_status: None,
+        }
+    }
+
+    fn lot(lot_no: &str, bin: &str, kg: i32, days_to_expiry: i64) -> LotInfo {
+        LotInfo {
+            lot_no: lot_no.to_string(),
+            expiry_date: Some(Utc::now() + Duration::days(days_to_expiry)),
+            available_qty: BigDecimal::from(kg),
+            location: "TFC1".to_string(),
+            bin: Some(bin.to_string()),
+        }
+    }
+
+    #[test]
+    fn test_plan_spreads_batches_over_lots_in_fefo_order() {
+        let batches = vec![batch(2, 1, "INSOYF01", 2, 20), batch(1, 1, "INSOYF01", 2, 20)];
+        // 50 KG holds two whole 20 KG bags; the odd 10 KG is never planned
+        let lots = HashMap::from([(
+            "INSOYF01".to_string(),
+            vec![lot("2510601", "A0101-1A", 50, 10), lot("2510602", "A0102-1A", 100, 60)],
+        )]);
+
+        let plan = build_pick_plan(215235, &batches, &lots, "deachawat");
+
+        let steps: Vec<_> = plan
+            .steps
+            .iter()
+            .map(|s| (s.step_no, s.row_num, s.lot_no.as_str(), s.bags.clone()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (1, 1, "2510601", BigDecimal::from(2)),
+                (2, 2, "2510602", BigDecimal::from(2)),
+            ]
+        );
+        assert_eq!(plan.steps[1].qty_kg, BigDecimal::from(40));
+        assert!(plan.shortages.is_empty());
+        assert_eq!(plan.next_step().map(|s| s.step_no), Some(1));
+    }
+
+    #[test]
+    fn test_plan_reports_shortage_and_skips_picked_batches() {
+        let mut done = batch(1, 1, "INSOYF01", 1, 20);
+        done.picked_bulk_qty = Some(BigDecimal::from(1));
+        let batches = vec![done, batch(2, 1, "INSOYF01", 3, 20), batch(1, 2, "INSALT02", 1, 25)];
+        // Listed twice, as the lot query does for an ingredient on several batches
+        let lots = HashMap::from([(
+            "INSOYF01".to_string(),
+            vec![lot("2510601", "A0101-1A", 40, 10), lot("2510601", "A0101-1A", 40, 10)],
+        )]);
+
+        let plan = build_pick_plan(215235, &batches, &lots, "deachawat");
+
+        assert_eq!(plan.steps.len(), 1);
+        assert_eq!(plan.steps[0].bags, BigDecimal::from(2));
+        let shortages: Vec<_> = plan
+            .shortages
+            .iter()
+            .map(|s| (s.line_id, s.short_bags.clone(), s.short_kg.clone()))
+            .collect();
+        assert_eq!(
+            shortages,
+            vec![
+                (1, BigDecimal::from(1), BigDecimal::from(20)),
+                (2, BigDecimal::from(1), BigDecimal::from(25)),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_ingredient_stock_counts_each_lot_once() {
+        let batches = vec![batch(1, 1, "INSOYF01", 2, 20), batch(2, 1, "INSOYF01", 2, 20)];
+        let lots = HashMap::from([(
+            "INSOYF01".to_string(),
+            vec![lot("2510601", "A0101-1A", 50, 10), lot("2510601", "A0101-1A", 50, 10)],
+        )]);
+
+        let plan = build_pick_plan(215235, &batches, &lots, "preview");
+        let ingredients = summarize_ingredient_stock(&batches, &lots, &plan);
+
+        assert_eq!(ingredients.len(), 1);
+        let soy = &ingredients[0];
+        assert_eq!(soy.required_bags, BigDecimal::from(4));
+        assert_eq!(soy.required_kg, BigDecimal::from(80));
+        assert_eq!(soy.available_kg, BigDecimal::from(50));
+        assert_eq!(soy.short_bags, BigDecimal::from(2));
+        assert_eq!(soy.short_kg, BigDecimal::from(40));
+    }
+}
@@ -0,0 +1,112 @@
+//! Walk-path ordering of a run's ingredients.
+//!
+//! Each ingredient is visited at the bin of its first FEFO lot. Bins are walked zone by
+//! zone in the configured order, aisle by aisle within a zone, and serpentine within an
+//! aisle: rows go up in the first aisle, down in the next, so the picker never walks an
+//! aisle twice. Ingredients with no pickable bin come last, in their original order.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+
+use crate::models::pick_sequence::BinLocation;
+
+/// Position of every item key on the walk; `item_bins` is in the current (line) order
+pub fn walk_path_ranks(item_bins: &[(String, Option<BinLocation>)], zone_order: &[String]) -> HashMap<String, usize> {
+    let zone_rank = |zone: &str| zone_order.iter().position(|z| z == zone).unwrap_or(zone_order.len());
+
+    let mut aisles: Vec<(usize, String, &str)> = item_bins
+        .iter()
+        .filter_map(|(_, bin)| bin.as_ref())
+        .map(|bin| {
+            let zone = bin.zone();
+            (zone_rank(&zone), zone, bin.aisle.as_str())
+        })
+        .collect();
+    aisles.sort();
+    aisles.dedup();
+
+    let mut located: Vec<(usize, usize, &str)> = Vec::new();
+    let mut unlocated: Vec<&str> = Vec::new();
+    for (position, (item_key, bin)) in item_bins.iter().enumerate() {
+        match bin {
+            Some(bin) => {
+                let zone = bin.zone();
+                let aisle = aisles
+                    .iter()
+                    .position(|(rank, z, aisle)| *rank == zone_rank(&zone) && *z == zone && *aisle == bin.aisle)
+                    .unwrap_or(aisles.len());
+                located.push((aisle, position, item_key.as_str()));
+            }
+            None => unlocated.push(item_key.as_str()),
+        }
+    }
+
+    located.sort_by(|a, b| {
+        let (bin_a, bin_b) = (item_bins[a.1].1.as_ref(), item_bins[b.1].1.as_ref());
+        let row_rack = |bin: Option<&BinLocation>| bin.map(|bin| (bin.row.clone(), bin.rack.clone()));
+        // Serpentine: every other aisle is walked from its far end
+        let along_aisle = if a.0 % 2 == 0 {
+            row_rack(bin_a).cmp(&row_rack(bin_b))
+        } else {
+            Reverse(row_rack(bin_a)).cmp(&Reverse(row_rack(bin_b)))
+        };
+        a.0.cmp(&b.0).then(along_aisle).then(a.1.cmp(&b.1))
+    });
+
+    let mut ranks = HashMap::new();
+    for item_key in located.into_iter().map(|(_, _, item_key)| item_key).chain(unlocated) {
+        let next = ranks.len();
+        ranks.entry(item_key.to_string()).or_insert(next);
+    }
+    ranks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bin(bin_no: &str, aisle: &str, row: &str, rack: &str) -> Option<BinLocation> {
+        Some(BinLocation {
+            bin_no: bin_no.to_string(),
+            aisle: aisle.to_string(),
+            row: row.to_string(),
+            rack: rack.to_string(),
+        })
+    }
+
+    fn walk(item_bins: &[(String, Option<BinLocation>)], zones: &[&str]) -> Vec<String> {
+        let zones: Vec<String> = zones.iter().map(|z| z.to_string()).collect();
+        let ranks = walk_path_ranks(item_bins, &zones);
+        let mut items: Vec<String> = ranks.keys().cloned().collect();
+        items.sort_by_key(|item| ranks[item]);
+        items
+    }
+
+    #[test]
+    fn test_walk_follows_zone_order_then_serpentine_aisles() {
+        let item_bins = vec![
+            ("A_ITEM".to_string(), bin("A0101-1A", "01", "01", "1")),
+            ("D_LOW".to_string(), bin("D0201-1A", "02", "01", "1")),
+            ("NO_STOCK".to_string(), None),
+            ("K_FAR".to_string(), bin("K0105-1A", "01", "05", "1")),
+            ("D_HIGH".to_string(), bin("D0209-1A", "02", "09", "1")),
+            ("K_NEAR".to_string(), bin("K0101-1A", "01", "01", "1")),
+            ("D_NEXT_AISLE".to_string(), bin("D0301-1A", "03", "01", "1")),
+        ];
+
+        // Zone K's aisle is walked up, the first D aisle down from its far end, the next one up
+        assert_eq!(
+            walk(&item_bins, &["K", "D", "A"]),
+            vec!["K_NEAR", "K_FAR", "D_HIGH", "D_LOW", "D_NEXT_AISLE", "A_ITEM", "NO_STOCK"]
+        );
+    }
+
+    #[test]
+    fn test_unknown_zones_follow_configured_ones() {
+        let item_bins = vec![
+            ("W_ITEM".to_string(), bin("W0101-1A", "01", "01", "1")),
+            ("A_ITEM".to_string(), bin("A0101-1A", "01", "01", "1")),
+        ];
+        assert_eq!(walk(&item_bins, &["A"]), vec!["A_ITEM", "W_ITEM"]);
+    }
+}
@@ -0,0 +1,443 @@
+//! Print job queue for network label printers.
+//!
+//! Labels are rendered once, in the printer's language, when a job is queued; the stored
+//! payload is what every attempt, retry and reprint sends. A worker claims due jobs and
+//! writes each payload to the printer's raw TCP port (9100 on Zebra and most label
+//! printers). Failed sends are retried with a doubling delay until the job runs out of
+//! attempts and is marked FAILED.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::collections::HashMap;
+use std::env;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tracing::{debug, info, instrument, warn};
+
+use crate::database::repository::{BulkRunRepository, LotRepository, PrintJobRepository};
+use crate::database::Database;
+use crate::models::bulk_runs::StatusUpdateResult;
+use crate::models::pallet_labels::SsccConfig;
+use crate::models::print_jobs::{
+    max_attempts, retry_delay, LabelKind, LabelPrinter, NewPrintJob, PrintAttempt, PrintError, PrintJob,
+    PrintJobStatus, PrintLabelsRequest, PrintPalletRequest, PrinterLanguage, RegisterPrinterRequest,
+    ReprintRequest,
+};
+use crate::services::batch_labels::{render_epl, render_zpl, LabelLayout};
+use crate::services::bulk_runs_service::BulkRunsService;
+use crate::services::run_events::RunEventBus;
+
+/// Jobs one worker pass sends at most; each is claimed just before it is sent
+const JOBS_PER_PASS: usize = 10;
+
+/// Timing of the print worker
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrintQueueConfig {
+    /// Connect and write timeout for one send
+    pub send_timeout: Duration,
+    /// How often the worker looks for due jobs
+    pub poll_interval: Duration,
+}
+
+impl Default for PrintQueueConfig {
+    fn default() -> Self {
+        Self { send_timeout: Duration::from_secs(10), poll_interval: Duration::from_secs(5) }
+    }
+}
+
+impl PrintQueueConfig {
+    /// PRINT_SEND_TIMEOUT_SECS and PRINT_QUEUE_POLL_SECS
+    pub fn from_env() -> Self {
+        let seconds = |name: &str| {
+            env::var(name)
+                .ok()
+                .and_then(|v| v.parse().ok())
+                .filter(|&secs: &u64| secs > 0)
+                .map(Duration::from_secs)
+        };
+        let defaults = Self::default();
+        Self {
+            send_timeout: seconds("PRINT_SEND_TIMEOUT_SECS").unwrap_or(defaults.send_timeout),
+            poll_interval: seconds("PRINT_QUEUE_POLL_SECS").unwrap_or(defaults.poll_interval),
+        }
+    }
+}
+
+/// Write `payload` to a printer's raw TCP port and close the connection
+pub async fn send_raw(host: &str, port: u16, payload: &[u8], timeout: Duration) -> Result<()> {
+    let mut stream = tokio::time::timeout(timeout, TcpStream::connect((host, port)))
+        .await
+        .with_context(|| format!("Timed out connecting to {host}:{port}"))?
+        .with_context(|| format!("Failed to connect to {host}:{port}"))?;
+
+    tokio::time::timeout(timeout, async {
+        stream.write_all(payload).await?;
+        stream.flush().await?;
+        stream.shutdown().await
+    })
+    .await
+    .with_context(|| format!("Timed out sending to {host}:{port}"))?
+    .with_context(|| format!("Failed to send to {host}:{port}"))?;
+    Ok(())
+}
+
+/// Printer registry, job queue and the worker that drains it
+pub struct PrintQueueService<R = Database> {
+    database: R,
+    /// Passed on to the run status change that printing triggers
+    events: Option<RunEventBus>,
+    config: PrintQueueConfig,
+    /// GS1 prefix for the SSCC a pallet gets when its label is first queued
+    sscc_config: Option<SsccConfig>,
+}
+
+impl<R: BulkRunRepository + LotRepository + PrintJobRepository + Clone> PrintQueueService<R> {
+    pub fn new(database: R) -> Self {
+        Self { database, events: None, config: PrintQueueConfig::from_env(), sscc_config: None }
+    }
+
+    pub fn with_events(mut self, events: RunEventBus) -> Self {
+        self.events = Some(events);
+        self
+    }
+
+    pub fn with_sscc_config(mut self, sscc_config: Option<SsccConfig>) -> Self {
+        self.sscc_config = sscc_config;
+        self
+    }
+
+    pub async fn register_printer(
+        &self,
+        request: RegisterPrinterRequest,
+        username: &str,
+    ) -> Result<LabelPrinter, PrintError> {
+        let printer = request.into_printer(username)?;
+        self.database
+            .upsert_printer(&printer)
+            .await
+            .context("Failed to register printer")?;
+        info!(
+            "🖨️ {} registered printer {} at {}:{} ({})",
+            username,
+            printer.printer_id,
+            printer.host,
+            printer.port,
+            printer.language.as_str()
+        );
+        Ok(printer)
+    }
+
+    pub async fn list_printers(&self) -> Result<Vec<LabelPrinter>, PrintError> {
+        Ok(self.database.list_printers().await.context("Failed to list printers")?)
+    }
+
+    /// The named printer, or the default one; it must be active
+    async fn resolve_printer(&self, printer_id: Option<&str>) -> Result<LabelPrinter, PrintError> {
+        let printer_id = printer_id.map(str::trim).filter(|id| !id.is_empty());
+        let printer = self
+            .database
+            .get_printer(printer_id)
+            .await
+            .context("Failed to look up printer")?;
+        match (printer, printer_id) {
+            (Some(printer), _) if printer.active => Ok(printer),
+            (Some(printer), _) => Err(PrintError::PrinterInactive { printer_id: printer.printer_id }),
+            (None, Some(printer_id)) => Err(PrintError::PrinterNotFound { printer_id: printer_id.to_string() }),
+            (None, None) => Err(PrintError::NoDefaultPrinter),
+        }
+    }
+
+    /// Render the run's batch labels (or one batch's) for the printer and queue them
+    #[instrument(skip(self))]
+    pub async fn queue_run_labels(
+        &self,
+        run_no: i32,
+        request: &PrintLabelsRequest,
+        username: &str,
+    ) -> Result<PrintJob, PrintError> {
+        let printer = self.resolve_printer(request.printer_id.as_deref()).await?;
+        let batch_no = request.batch_no.as_deref().map(str::trim).filter(|b| !b.is_empty());
+        let labels = BulkRunsService::new(self.database.clone())
+            .get_batch_labels(run_no, batch_no)
+            .await?;
+        self.enqueue(&printer, run_no, batch_no, LabelKind::Batch, &labels, username).await
+    }
+
+    /// Render one pallet's SSCC label for the printer and queue it; the first print assigns the SSCC
+    #[instrument(skip(self))]
+    pub async fn queue_pallet_labels(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        request: &PrintPalletRequest,
+        username: &str,
+    ) -> Result<PrintJob, PrintError> {
+        let printer = self.resolve_printer(request.printer_id.as_deref()).await?;
+        let bulk_runs = BulkRunsService::new(self.database.clone()).with_sscc_config(self.sscc_config.clone());
+        bulk_runs.assign_pallet_sscc(run_no, row_num, username).await?;
+        let labels = bulk_runs.get_pallet_labels(run_no, row_num).await?;
+        let batch_no = labels.first().map(|label| label.batch.batch_no.clone());
+        self.enqueue(&printer, run_no, batch_no.as_deref(), LabelKind::Pallet, &labels, username)
+            .await
+    }
+
+    async fn enqueue<L: LabelLayout>(
+        &self,
+        printer: &LabelPrinter,
+        run_no: i32,
+        batch_no: Option<&str>,
+        label_kind: LabelKind,
+        labels: &[L],
+        username: &str,
+    ) -> Result<PrintJob, PrintError> {
+        let payload = match printer.language {
+            PrinterLanguage::Zpl => render_zpl(labels),
+            PrinterLanguage::Epl => render_epl(labels),
+        };
+
+        let job = self
+            .database
+            .create_print_job(&NewPrintJob {
+                printer_id: printer.printer_id.clone(),
+                run_no,
+                batch_no: batch_no.map(str::to_string),
+                label_kind,
+                label_count: labels.len() as i32,
+                payload,
+                max_attempts: max_attempts(),
+                reprint_of: None,
+                reason: None,
+                requested_by: username.to_string(),
+            })
+            .await
+            .context("Failed to queue print job")?;
+
+        info!(
+            "🖨️ Queued job {}: {} {} labels of run {} for printer {} by {}",
+            job.job_id,
+            job.label_count,
+            label_kind.as_str(),
+            run_no,
+            printer.printer_id,
+            username
+        );
+        Ok(job)
+    }
+
+    /// Queue the run's labels and move the run from NEW to PRINT, as printing from the browser does
+    pub async fn print_run_labels(
+        &self,
+        run_no: i32,
+        request: &PrintLabelsRequest,
+        username: &str,
+    ) -> Result<(PrintJob, StatusUpdateResult), PrintError> {
+        let job = self.queue_run_labels(run_no, request, username).await?;
+
+        let mut bulk_runs = BulkRunsService::new(self.database.clone());
+        if let Some(events) = &self.events {
+            bulk_runs = bulk_runs.with_events(events.clone());
+        }
+        let status = bulk_runs.update_print_status(run_no, username).await?;
+        Ok((job, status))
+    }
+
+    /// Queue the same labels again, with the reason kept on the new job
+    #[instrument(skip(self))]
+    pub async fn reprint(&self, job_id: i64, request: &ReprintRequest, username: &str) -> Result<PrintJob, PrintError> {
+        let reason = request.reason.trim();
+        if reason.is_empty() {
+            return Err(PrintError::Validation("A reason is required to reprint labels".to_string()));
+        }
+        if reason.chars().count() > 200 {
+            return Err(PrintError::Validation("Reprint reason must be at most 200 characters".to_string()));
+        }
+
+        let original = self.get_job(job_id).await?;
+        let original_printer = self
+            .database
+            .get_printer(Some(&original.printer_id))
+            .await
+            .context("Failed to look up the original printer")?;
+        let printer = self
+            .resolve_printer(request.printer_id.as_deref().or(Some(&original.printer_id)))
+            .await?;
+        // The payload is already in the original printer's language
+        if let Some(original_printer) = original_printer {
+            if original_printer.language != printer.language {
+                return Err(PrintError::LanguageMismatch {
+                    job_id,
+                    job: original_printer.language.as_str(),
+                    printer: printer.language.as_str(),
+                    printer_id: printer.printer_id,
+                });
+            }
+        }
+
+        let job = self
+            .database
+            .create_print_job(&NewPrintJob {
+                printer_id: printer.printer_id.clone(),
+                run_no: original.run_no,
+                batch_no: original.batch_no.clone(),
+                label_kind: original.label_kind,
+                label_count: original.label_count,
+                payload: original.payload,
+                max_attempts: max_attempts(),
+                reprint_of: Some(job_id),
+                reason: Some(reason.to_string()),
+                requested_by: username.to_string(),
+            })
+            .await
+            .context("Failed to queue reprint")?;
+
+        info!(
+            "🖨️ {} reprints job {} as job {} on printer {}: {}",
+            username, job_id, job.job_id, printer.printer_id, reason
+        );
+        Ok(job)
+    }
+
+    /// Give a FAILED job a fresh set of attempts
+    pub async fn retry(&self, job_id: i64) -> Result<PrintJob, PrintError> {
+        let job = self.get_job(job_id).await?;
+        if !self
+            .database
+            .requeue_print_job(job_id)
+            .await
+            .context("Failed to requeue print job")?
+        {
+            return Err(PrintError::JobNotRetryable { job_id, status: job.status.as_str() });
+        }
+        self.get_job(job_id).await
+    }
+
+    pub async fn get_job(&self, job_id: i64) -> Result<PrintJob, PrintError> {
+        self.database
+            .get_print_job(job_id)
+            .await
+            .context("Failed to get print job")?
+            .ok_or(PrintError::JobNotFound { job_id })
+    }
+
+    pub async fn list_jobs(&self, run_no: Option<i32>, limit: u32) -> Result<Vec<PrintJob>, PrintError> {
+        Ok(self
+            .database
+            .list_print_jobs(run_no, limit.clamp(1, 200))
+            .await
+            .context("Failed to list print jobs")?)
+    }
+
+    /// Send every due job once. Returns the jobs sent, with their status after the attempt.
+    pub async fn process_due_jobs(&self) -> Result<Vec<PrintJob>> {
+        // Long enough for one send to finish before another worker may take the job over
+        let lease = chrono::Duration::from_std(self.config.send_timeout * 2)
+            .unwrap_or_else(|_| chrono::Duration::seconds(60))
+            + chrono::Duration::seconds(30);
+
+        let mut printers: HashMap<String, Option<LabelPrinter>> = HashMap::new();
+        let mut processed = Vec::new();
+        for _ in 0..JOBS_PER_PASS {
+            // One job per claim, so no job waits out its lease behind another job's send
+            let Some(mut job) = self
+                .database
+                .claim_due_print_job(Utc::now() + lease)
+                .await
+                .context("Failed to claim due print job")?
+            else {
+                break;
+            };
+            let claimed_until = job.next_attempt_at;
+
+            if !printers.contains_key(&job.printer_id) {
+                let printer = self
+                    .database
+                    .get_printer(Some(&job.printer_id))
+                    .await
+                    .context("Failed to look up printer for print job")?;
+                printers.insert(job.printer_id.clone(), printer);
+            }
+
+            let sent = match &printers[&job.printer_id] {
+                Some(printer) if printer.active => {
+                    send_raw(&printer.host, printer.port, job.payload.as_bytes(), self.config.send_timeout)
+                        .await
+                        .map_err(|e| format!("{e:#}"))
+                }
+                Some(_) => Err(format!("Printer '{}' is inactive", job.printer_id)),
+                None => Err(format!("Printer '{}' is not registered", job.printer_id)),
+            };
+
+            job.attempts += 1;
+            let attempt = match sent {
+                Ok(()) => PrintAttempt::Printed,
+                Err(error) if job.attempts >= job.max_attempts => PrintAttempt::Failed { error },
+                Err(error) => PrintAttempt::Retry { error, next_attempt_at: Utc::now() + retry_delay(job.attempts) },
+            };
+            if !self
+                .database
+                .finish_print_attempt(job.job_id, claimed_until, &attempt)
+                .await
+                .context("Failed to record print attempt")?
+            {
+                warn!("🖨️ Job {} lost its claim during the send; the new holder records the attempt", job.job_id);
+                continue;
+            }
+
+            match &attempt {
+                PrintAttempt::Printed => {
+                    info!("🖨️ Job {} printed on {} (attempt {})", job.job_id, job.printer_id, job.attempts);
+                    job.status = PrintJobStatus::Printed;
+                    job.printed_at = Some(Utc::now());
+                }
+                PrintAttempt::Retry { error, next_attempt_at } => {
+                    warn!(
+                        "🖨️ Job {} attempt {}/{} failed, retrying at {}: {}",
+                        job.job_id, job.attempts, job.max_attempts, next_attempt_at, error
+                    );
+                    job.status = PrintJobStatus::Queued;
+                    job.last_error = Some(error.clone());
+                    job.next_attempt_at = *next_attempt_at;
+                }
+                PrintAttempt::Failed { error } => {
+                    warn!("🖨️ Job {} failed after {} attempts: {}", job.job_id, job.attempts, error);
+                    job.status = PrintJobStatus::Failed;
+                    job.last_error = Some(error.clone());
+                }
+            }
+            processed.push(job);
+        }
+        Ok(processed)
+    }
+}
+
+/// Send due jobs now instead of waiting for the worker's next poll
+pub fn dispatch_due_jobs(database: Database) {
+    tokio::spawn(async move {
+        if let Err(e) = PrintQueueService::new(database).process_due_jobs().await {
+            warn!("⚠️ Print dispatch failed: {:#}", e);
+        }
+    });
+}
+
+/// Drain the print queue in the background for the life of the process
+pub fn spawn_print_worker(database: Database) {
+    let service = PrintQueueService::new(database);
+    let interval = service.config.poll_interval;
+    tokio::spawn(async move {
+        let mut failing = false;
+        loop {
+            match service.process_due_jobs().await {
+                Ok(_) => failing = false,
+                // Log once per outage, e.g. until migrations/010_print_jobs.sql has been run
+                Err(e) if !failing => {
+                    warn!("⚠️ Print queue unavailable: {:#}", e);
+                    failing = true;
+                }
+                Err(e) => debug!("Print queue still unavailable: {:#}", e),
+            }
+            tokio::time::sleep(interval).await;
+        }
+    });
+    info!("🖨️ Print queue worker polling every {:?}", interval);
+}
@@ -1,19 +1,17 @@
 use crate::utils::bangkok_now_rfc3339;
-use crate::database::{Database, putaway_db::PutawayDatabase};
+use crate::database::{putaway_db::PutawayDatabase, repository::PutawayRepository};
 use crate::models::putaway_models::{
     LotSearchResult, BinValidationResult, BinTransferRequest, 
     TransferResult, PutawayHealthResponse, LotSearchItem, BinSearchItem, PutawayError
 };
 
-pub struct PutawayService {
-    db: PutawayDatabase,
+pub struct PutawayService<R = PutawayDatabase> {
+    db: R,
 }
 
-impl PutawayService {
-    pub fn new(database: Database) -> Self {
-        Self {
-            db: PutawayDatabase::new(database),
-        }
+impl<R: PutawayRepository> PutawayService<R> {
+    pub fn new(db: R) -> Self {
+        Self { db }
     }
 
     /// Search for lot details by lot number
@@ -79,6 +77,14 @@ impl PutawayService {
 
     /// Execute bin transfer using validated/corrected quantity
     pub async fn execute_transfer(&self, request: BinTransferRequest) -> Result<TransferResult, PutawayError> {
+        // A retried request replays the stored result - the stock has already moved,
+        // so re-validating it against the source bin would wrongly fail
+        if let Some(key) = &request.idempotency_key {
+            if let Some(stored) = self.db.find_idempotent_response(key).await? {
+                return Ok(stored.replay(key)?);
+            }
+        }
+
         // Validate request
         self.validate_transfer_request(&request)?;
 
@@ -92,8 +98,21 @@ impl PutawayService {
             request.transfer_qty,
         ).await?;
 
-        // Execute transfer with the corrected quantity (exact available qty for full transfers)
-        match self.db.execute_bin_transfer_transaction(
+        let message = if is_full_transfer {
+            format!(
+                "Successfully transferred {} units (FULL TRANSFER) of lot {} from {} to {} - Source bin cleared",
+                actual_transfer_qty, request.lot_no, request.bin_from, request.bin_to
+            )
+        } else {
+            format!(
+                "Successfully transferred {} units of lot {} from {} to {}",
+                actual_transfer_qty, request.lot_no, request.bin_from, request.bin_to
+            )
+        };
+
+        // Execute transfer with the corrected quantity (exact available qty for full transfers).
+        // Failures keep their type so connection and SQL errors surface as server errors.
+        self.db.execute_bin_transfer_transaction(
             &request.lot_no,
             &request.item_key,
             &request.location,
@@ -103,41 +122,11 @@ impl PutawayService {
             &request.user_id,
             request.remarks.as_deref().unwrap_or(""),
             request.referenced.as_deref().unwrap_or(""),
-        ).await {
-            Ok((document_no, source_lot_status, destination_lot_status)) => {
-                Ok(TransferResult {
-                    success: true,
-                    document_no,
-                    message: if is_full_transfer {
-                        format!(
-                            "Successfully transferred {} units (FULL TRANSFER) of lot {} from {} to {} - Source bin cleared",
-                            actual_transfer_qty, request.lot_no, request.bin_from, request.bin_to
-                        )
-                    } else {
-                        format!(
-                            "Successfully transferred {} units of lot {} from {} to {}",
-                            actual_transfer_qty, request.lot_no, request.bin_from, request.bin_to
-                        )
-                    },
-                    timestamp: bangkok_now_rfc3339(),
-                    source_lot_status,
-                    destination_lot_status,
-                })
-            }
-            Err(e) => {
-                Ok(TransferResult {
-                    success: false,
-                    document_no: String::new(),
-                    message: format!("Transfer failed: {e}"),
-                    timestamp: bangkok_now_rfc3339(),
-                    source_lot_status: None,
-                    destination_lot_status: None,
-                })
-            }
-        }
+            &message,
+            request.idempotency_key.as_ref(),
+        ).await
     }
 
-
     /// Search for lots with pagination
     pub async fn search_lots_paginated(&self, query: Option<&str>, page: i32, limit: i32) -> Result<(Vec<LotSearchItem>, i32), PutawayError> {
         // Validate inputs
@@ -0,0 +1,109 @@
+use std::env;
+use tokio::sync::broadcast;
+use tokio_stream::wrappers::{errors::BroadcastStreamRecvError, BroadcastStream};
+use tokio_stream::{Stream, StreamExt};
+
+use crate::models::run_events::RunEvent;
+
+/// Events buffered per subscriber before a slow stream starts skipping
+const DEFAULT_CAPACITY: usize = 256;
+
+/// Item of a run's event stream
+#[derive(Debug, Clone, PartialEq)]
+pub enum RunStreamItem {
+    Event(RunEvent),
+    /// The subscriber fell behind and missed this many events; it should refetch the run
+    Lagged(u64),
+}
+
+/// In-process fan-out of run changes to the `/events` streams.
+/// Events are published after the change commits; a subscriber only sees events
+/// published while it is connected, so clients load the run state before listening.
+#[derive(Debug, Clone)]
+pub struct RunEventBus {
+    sender: broadcast::Sender<RunEvent>,
+}
+
+impl Default for RunEventBus {
+    fn default() -> Self {
+        Self::new(DEFAULT_CAPACITY)
+    }
+}
+
+impl RunEventBus {
+    pub fn new(capacity: usize) -> Self {
+        let (sender, _) = broadcast::channel(capacity);
+        Self { sender }
+    }
+
+    pub fn from_env() -> Self {
+        let capacity = env::var("RUN_EVENTS_BUFFER")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&capacity| capacity > 0)
+            .unwrap_or(DEFAULT_CAPACITY);
+        Self::new(capacity)
+    }
+
+    /// Whether anyone is listening; lets publishers skip the follow-up queries otherwise
+    pub fn has_subscribers(&self) -> bool {
+        self.sender.receiver_count() > 0
+    }
+
+    pub fn publish(&self, event: RunEvent) {
+        // No subscribers is the normal case, not an error
+        let _ = self.sender.send(event);
+    }
+
+    /// Events for one run, from now on
+    pub fn subscribe(&self, run_no: i32) -> impl Stream<Item = RunStreamItem> + Send + 'static {
+        BroadcastStream::new(self.sender.subscribe()).filter_map(move |item| match item {
+            Ok(event) if event.run_no == run_no => Some(RunStreamItem::Event(event)),
+            Ok(_) => None,
+            Err(BroadcastStreamRecvError::Lagged(missed)) => Some(RunStreamItem::Lagged(missed)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::run_events::RunEventKind;
+
+    fn status_changed(run_no: i32) -> RunEvent {
+        RunEvent::new(
+            run_no,
+            "deachawat",
+            RunEventKind::RunStatusChanged { from: "NEW".to_string(), to: "PRINT".to_string() },
+        )
+    }
+
+    #[tokio::test]
+    async fn test_subscriber_only_sees_its_run() {
+        let bus = RunEventBus::default();
+        assert!(!bus.has_subscribers());
+
+        let mut stream = Box::pin(bus.subscribe(215235));
+        assert!(bus.has_subscribers());
+
+        bus.publish(status_changed(215236));
+        bus.publish(status_changed(215235));
+
+        match stream.next().await {
+            Some(RunStreamItem::Event(event)) => assert_eq!(event.run_no, 215235),
+            other => panic!("unexpected stream item: {other:?}"),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_slow_subscriber_is_told_it_lagged() {
+        let bus = RunEventBus::new(2);
+        let mut stream = Box::pin(bus.subscribe(215235));
+
+        for _ in 0..5 {
+            bus.publish(status_changed(215235));
+        }
+
+        assert_eq!(stream.next().await, Some(RunStreamItem::Lagged(3)));
+    }
+}
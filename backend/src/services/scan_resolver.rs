@@ -0,0 +1,178 @@
+//! Barcode scans for picking and putaway.
+//!
+//! One scan of a supplier's GS1-128 or GS1 DataMatrix label gives the item (through the
+//! GTIN registered in tbl_item_gtin), the lot and the expiry. Plain scans are read as a
+//! lot number, then as an item key. The result is checked against LotMaster and every
+//! disagreement is returned as a mismatch rather than an error, so the screen can show
+//! what is wrong with the bag in hand.
+
+use tracing::{info, instrument};
+
+use crate::database::repository::{LotRepository, ScanRepository};
+use crate::database::Database;
+use crate::models::scans::{
+    ItemGtin, LotMasterEntry, MismatchKind, RegisterGtinRequest, ScanError, ScanMismatch, ScanResolution, ScannedItem,
+};
+use crate::utils::gs1;
+
+pub struct ScanService<R = Database> {
+    db: R,
+}
+
+impl<R: ScanRepository + LotRepository> ScanService<R> {
+    pub fn new(db: R) -> Self {
+        Self { db }
+    }
+
+    /// Resolve a scan and check it against LotMaster and, when given, the expected item
+    #[instrument(skip(self))]
+    pub async fn resolve(&self, code: &str, expected_item: Option<&str>) -> Result<ScanResolution, ScanError> {
+        let code = code.trim();
+        if code.is_empty() {
+            return Err(ScanError::Validation("code is required".to_string()));
+        }
+
+        let mut resolution = ScanResolution {
+            code: code.to_string(),
+            gs1: None,
+            item_key: None,
+            item_description: None,
+            lot_no: None,
+            expiry_date: None,
+            net_weight_kg: None,
+            lots: Vec::new(),
+            pick_bins: None,
+            mismatches: Vec::new(),
+        };
+
+        let mut item = None;
+        let mut entries = Vec::new();
+        if gs1::is_element_string(code) {
+            let element = gs1::parse_element_string(code).map_err(ScanError::InvalidBarcode)?;
+            if let Some(gtin) = &element.gtin {
+                item = self.db.find_item_by_gtin(gtin).await?;
+                if item.is_none() {
+                    resolution.mismatches.push(ScanMismatch::new(
+                        MismatchKind::UnknownGtin,
+                        format!("GTIN {gtin} is not registered to an item"),
+                    ));
+                }
+            }
+            if let Some(lot_no) = &element.lot_no {
+                entries = self.db.get_lot_master_entries(lot_no).await?;
+                if entries.is_empty() {
+                    resolution
+                        .mismatches
+                        .push(ScanMismatch::new(MismatchKind::LotNotFound, format!("Lot {lot_no} is not in LotMaster")));
+                }
+            }
+            resolution.lot_no = element.lot_no.clone();
+            resolution.net_weight_kg = element.net_weight_kg.clone();
+            resolution.gs1 = Some(element);
+        } else {
+            entries = self.db.get_lot_master_entries(code).await?;
+            if entries.is_empty() {
+                item = self.db.find_item(code).await?;
+                if item.is_none() {
+                    resolution.mismatches.push(ScanMismatch::new(
+                        MismatchKind::UnknownCode,
+                        format!("'{code}' is neither a lot number nor an item key"),
+                    ));
+                }
+            } else {
+                resolution.lot_no = Some(code.to_string());
+            }
+        }
+
+        // A lot without a GTIN takes its item from LotMaster when that is unambiguous
+        if item.is_none() {
+            if let [first, rest @ ..] = entries.as_slice() {
+                if rest.iter().all(|entry| entry.item_key == first.item_key) {
+                    item = self.db.find_item(&first.item_key).await?;
+                }
+            }
+        }
+
+        self.check_lot_master(&mut resolution, item.as_ref(), entries);
+        if let Some(item) = item {
+            resolution.item_key = Some(item.item_key);
+            resolution.item_description = Some(item.description);
+        }
+
+        if let (Some(expected), Some(scanned)) = (expected_item.map(str::trim), resolution.item_key.as_deref()) {
+            if !expected.is_empty() && expected != scanned {
+                resolution.mismatches.push(ScanMismatch::new(
+                    MismatchKind::UnexpectedItem,
+                    format!("Scanned item {scanned} but {expected} is expected"),
+                ));
+            }
+        }
+
+        info!(
+            "🔎 Scan '{}' resolved to item {:?}, lot {:?} with {} mismatches",
+            code,
+            resolution.item_key,
+            resolution.lot_no,
+            resolution.mismatches.len()
+        );
+        Ok(resolution)
+    }
+
+    /// Resolve a scan on the picking screen: also lists the bins the lot can be picked
+    /// from for the run's ingredient
+    pub async fn resolve_for_run(
+        &self,
+        run_no: i32,
+        code: &str,
+        expected_item: Option<&str>,
+    ) -> Result<ScanResolution, ScanError> {
+        let mut resolution = self.resolve(code, expected_item).await?;
+        if let (Some(lot_no), Some(item_key)) = (&resolution.lot_no, &resolution.item_key) {
+            resolution.pick_bins = Some(self.db.get_bins_for_lot(run_no, lot_no, item_key).await?);
+        }
+        Ok(resolution)
+    }
+
+    /// Map a supplier GTIN to an item so its labels resolve on scan
+    pub async fn register_gtin(&self, request: RegisterGtinRequest, username: &str) -> Result<ItemGtin, ScanError> {
+        let gtin = request.into_gtin(username)?;
+        if self.db.find_item(&gtin.item_key).await?.is_none() {
+            return Err(ScanError::ItemNotFound { item_key: gtin.item_key });
+        }
+        self.db.upsert_item_gtin(&gtin).await?;
+
+        info!("🏷️ {} registered GTIN {} for item {}", username, gtin.gtin, gtin.item_key);
+        Ok(gtin)
+    }
+
+    /// Keep the resolved item's LotMaster rows and flag a lot filed under another item or
+    /// an expiry that differs from the label's
+    fn check_lot_master(&self, resolution: &mut ScanResolution, item: Option<&ScannedItem>, entries: Vec<LotMasterEntry>) {
+        let lot_item = entries.first().map(|entry| entry.item_key.clone());
+        let entries: Vec<LotMasterEntry> = match item {
+            Some(item) => entries.into_iter().filter(|entry| entry.item_key == item.item_key).collect(),
+            None => entries,
+        };
+        if let (Some(item), Some(lot_item), Some(lot_no)) = (item, lot_item, &resolution.lot_no) {
+            if entries.is_empty() {
+                resolution.mismatches.push(ScanMismatch::new(
+                    MismatchKind::LotItemMismatch,
+                    format!("Lot {lot_no} belongs to item {lot_item} in LotMaster, not {}", item.item_key),
+                ));
+            }
+        }
+
+        let scanned_expiry = resolution.gs1.as_ref().and_then(|element| element.expiry_date);
+        let lot_expiry = entries.iter().find_map(|entry| entry.date_expiry);
+        if let (Some(scanned), Some(recorded)) = (scanned_expiry, lot_expiry) {
+            if scanned != recorded {
+                resolution.mismatches.push(ScanMismatch::new(
+                    MismatchKind::ExpiryMismatch,
+                    format!("Label expiry {scanned} differs from LotMaster expiry {recorded}"),
+                ));
+            }
+        }
+        resolution.expiry_date = scanned_expiry.or(lot_expiry);
+        resolution.lots = entries;
+    }
+}
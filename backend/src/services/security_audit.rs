@@ -0,0 +1,111 @@
+use std::env;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::database::Database;
+use crate::models::security_audit::SecurityEvent;
+
+/// Events waiting for the writer before new ones are dropped
+const DEFAULT_QUEUE_CAPACITY: usize = 1024;
+
+/// Persists security events without holding up the request that produced them.
+/// Events go through a bounded queue to a single writer, so a flood of bad tokens
+/// costs at most one insert at a time; events that do not fit are counted and dropped.
+#[derive(Clone, Debug)]
+pub struct SecurityAudit {
+    sender: mpsc::Sender<SecurityEvent>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl SecurityAudit {
+    pub fn new(database: Database, capacity: usize) -> Self {
+        let (audit, receiver) = Self::channel(capacity);
+        tokio::spawn(write_events(database, receiver, audit.dropped.clone()));
+        audit
+    }
+
+    /// Queue size from SECURITY_AUDIT_QUEUE (default 1024)
+    pub fn from_env(database: Database) -> Self {
+        let capacity = env::var("SECURITY_AUDIT_QUEUE")
+            .ok()
+            .and_then(|v| v.parse().ok())
+            .filter(|&capacity| capacity > 0)
+            .unwrap_or(DEFAULT_QUEUE_CAPACITY);
+        Self::new(database, capacity)
+    }
+
+    fn channel(capacity: usize) -> (Self, mpsc::Receiver<SecurityEvent>) {
+        let (sender, receiver) = mpsc::channel(capacity);
+        let audit = Self {
+            sender,
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+        (audit, receiver)
+    }
+
+    /// Queue an event for storage. Failures are logged, never surfaced to the caller,
+    /// so an audit outage cannot lock users out.
+    pub fn record(&self, event: SecurityEvent) {
+        match self.sender.try_send(event) {
+            Ok(()) => {}
+            Err(mpsc::error::TrySendError::Full(event)) => {
+                // Warn once per overflow; the writer reports the total when it catches up
+                if self.dropped.fetch_add(1, Ordering::Relaxed) == 0 {
+                    warn!(
+                        "⚠️ Security audit queue full - dropping {} for {:?}",
+                        event.event_type.as_str(),
+                        event.username
+                    );
+                }
+            }
+            Err(mpsc::error::TrySendError::Closed(event)) => {
+                warn!(
+                    "⚠️ Security audit writer stopped - event {} for {:?} not stored",
+                    event.event_type.as_str(),
+                    event.username
+                );
+            }
+        }
+    }
+}
+
+async fn write_events(database: Database, mut receiver: mpsc::Receiver<SecurityEvent>, dropped: Arc<AtomicU64>) {
+    while let Some(event) = receiver.recv().await {
+        if let Err(e) = database.insert_security_event(&event).await {
+            warn!(
+                "⚠️ Failed to persist security event {} for {:?}: {}",
+                event.event_type.as_str(),
+                event.username,
+                e
+            );
+        }
+
+        let overflowed = dropped.swap(0, Ordering::Relaxed);
+        if overflowed > 0 {
+            warn!("⚠️ Security audit queue overflowed - {} event(s) were not stored", overflowed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::security_audit::{AuditOutcome, SecurityEventType};
+
+    #[test]
+    fn test_full_queue_drops_and_counts_events() {
+        let (audit, mut receiver) = SecurityAudit::channel(2);
+        let event = || SecurityEvent::new(SecurityEventType::TokenRejected, AuditOutcome::Failure);
+
+        for _ in 0..5 {
+            audit.record(event());
+        }
+
+        assert_eq!(audit.dropped.load(Ordering::Relaxed), 3);
+        assert!(receiver.try_recv().is_ok());
+        assert!(receiver.try_recv().is_ok());
+        assert!(receiver.try_recv().is_err());
+    }
+}
@@ -37,6 +37,10 @@ mod tests {
             picked_bulk_qty: None,
             picking_date: None,
             status: None,
+            total_batches: Some(1),
+            completed_batches: Some(0),
+            remaining_qty: Some(BigDecimal::from(12)),
+            completion_status: Some("PENDING".to_string()),
         }
     }
 
@@ -144,8 +148,14 @@ mod tests {
             soh_uom: "KG".to_string(),
             bulk_pack_size_value: "20.0".to_string(),
             bulk_pack_size_uom: "KG".to_string(),
-            total_needed: "12.0".to_string(),
-            remaining_to_pick: "12.0".to_string(),
+            total_needed_bags: "12.0".to_string(),
+            total_needed_bags_uom: "BAGS".to_string(),
+            total_needed_kg: "240".to_string(),
+            total_needed_kg_uom: "KG".to_string(),
+            remaining_bags: "12.0".to_string(),
+            remaining_bags_uom: "BAGS".to_string(),
+            remaining_kg: "240".to_string(),
+            remaining_kg_uom: "KG".to_string(),
             ingredient_index: 0,
             total_ingredients: 3,
         };
@@ -154,8 +164,8 @@ mod tests {
         assert_eq!(form_fields.item_key, "INSOYF01");
         assert_eq!(form_fields.soh_value, "1000.0");
         assert_eq!(form_fields.soh_uom, "KG");
-        assert_eq!(form_fields.total_needed, "12.0");
-        assert_eq!(form_fields.remaining_to_pick, "12.0");
+        assert_eq!(form_fields.total_needed_bags, "12.0");
+        assert_eq!(form_fields.remaining_bags, "12.0");
         assert_eq!(form_fields.ingredient_index, 0);
         assert_eq!(form_fields.total_ingredients, 3);
     }
@@ -214,11 +224,50 @@ mod tests {
                 assert!(num <= 0, "Negative numbers should be invalid");
             } else {
                 // Non-numeric strings should fail to parse
-                assert!(true);
+                assert!(parsed.is_err());
             }
         }
     }
 
+    #[test]
+    fn test_bulk_run_error_codes_and_statuses() {
+        use axum::http::StatusCode;
+
+        let completed = BulkRunError::BatchAlreadyCompleted;
+        assert_eq!(completed.error_code(), "BATCH_ALREADY_COMPLETED");
+        assert_eq!(completed.status_code(), StatusCode::CONFLICT);
+
+        let short = BulkRunError::InsufficientBatchQuantity {
+            requested: 3.0,
+            remaining: 2.0,
+            item_key: "INSOYF01".to_string(),
+            row_num: 1,
+            line_id: 1,
+        };
+        assert_eq!(short.error_code(), "INSUFFICIENT_BATCH_QUANTITY");
+        assert_eq!(short.status_code(), StatusCode::UNPROCESSABLE_ENTITY);
+
+        let offline = BulkRunError::ConnectionFailed("pool timed out".to_string());
+        assert_eq!(offline.status_code(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(BulkRunError::RunNotFound { run_no: 1 }.status_code(), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn test_bulk_run_error_retry_classification() {
+        let timeout = tiberius::error::Error::Io {
+            kind: std::io::ErrorKind::TimedOut,
+            message: "read timed out".to_string(),
+        };
+        // The driver error stays reachable through added context
+        let wrapped = anyhow::Error::new(timeout).context("STEP_2 failed");
+        assert!(BulkRunError::Database(wrapped).is_retryable());
+
+        // Text that merely mentions a deadlock is not enough
+        let text_only = anyhow::anyhow!("Transaction (Process ID 51) was deadlocked");
+        assert!(!BulkRunError::TransactionRolledBack(text_only).is_retryable());
+        assert!(!BulkRunError::BatchAlreadyCompleted.is_retryable());
+    }
+
     #[test]
     fn test_date_formatting() {
         use chrono::{TimeZone, Utc};
@@ -253,7 +302,7 @@ mod tests {
         let valid_statuses = vec!["NEW", "IN_PROGRESS", "COMPLETED", "CANCELLED"];
 
         for status in valid_statuses {
-            assert!(status.len() > 0);
+            assert!(!status.is_empty());
             assert!(status.chars().all(|c| c.is_ascii_uppercase() || c == '_'));
         }
     }
@@ -295,42 +344,6 @@ mod tests {
         assert!(alert.recommended_action.is_some());
     }
 
-    #[test]
-    fn test_inventory_status_structure() {
-        use crate::models::inventory::*;
-
-        let alerts = vec![
-            InventoryAlert::out_of_stock("INSOYF01"),
-            InventoryAlert {
-                alert_type: InventoryAlertType::LowStock,
-                item_key: "INSOYF01".to_string(),
-                message: "Low stock".to_string(),
-                severity: AlertSeverity::Warning,
-                recommended_action: None,
-            },
-        ];
-
-        use bigdecimal::BigDecimal;
-
-        let status = InventoryStatus {
-            item_key: "INSOYF01".to_string(),
-            total_soh: BigDecimal::from(100),
-            available_qty: BigDecimal::from(50),
-            reserved_qty: BigDecimal::from(50),
-            uom: "KG".to_string(),
-            status: StockStatus::OutOfStock,
-            location_count: 1,
-            lot_count: 2,
-            alerts: alerts.clone(),
-        };
-
-        assert_eq!(status.item_key, "INSOYF01");
-        assert_eq!(status.status, StockStatus::OutOfStock);
-        assert_eq!(status.alerts.len(), 2);
-        assert_eq!(status.alerts[0].severity, AlertSeverity::Critical);
-        assert_eq!(status.alerts[1].severity, AlertSeverity::Warning);
-    }
-
     #[test]
     fn test_inventory_discrepancy_detection() {
         // Test logic for detecting inventory discrepancies
@@ -421,7 +434,7 @@ mod tests {
     fn test_inventory_alert_severity_priority() {
         use crate::models::inventory::*;
 
-        let alerts = vec![
+        let alerts = [
             InventoryAlert {
                 alert_type: InventoryAlertType::LowStock,
                 item_key: "TEST01".to_string(),
@@ -544,8 +557,14 @@ mod tests {
             soh_uom: inventory.soh_uom.clone(),
             bulk_pack_size_value: inventory.bulk_pack_size_value.to_string(),
             bulk_pack_size_uom: inventory.bulk_pack_size_uom.clone(),
-            total_needed: calculations.total_needed.to_string(),
-            remaining_to_pick: calculations.remaining_to_pick.to_string(),
+            total_needed_bags: calculations.total_needed.to_string(),
+            total_needed_bags_uom: "BAGS".to_string(),
+            total_needed_kg: "240".to_string(),
+            total_needed_kg_uom: "KG".to_string(),
+            remaining_bags: calculations.remaining_to_pick.to_string(),
+            remaining_bags_uom: "BAGS".to_string(),
+            remaining_kg: "240".to_string(),
+            remaining_kg_uom: "KG".to_string(),
             ingredient_index: 0,
             total_ingredients: 3,
         };
@@ -565,7 +584,7 @@ mod tests {
         assert_eq!(bulk_run_form_data.form_data.item_key, "INSOYF01");
         assert_eq!(bulk_run_form_data.form_data.fg_item_key, "TB317-01");
         assert_eq!(bulk_run_form_data.form_data.soh_value, "1000");
-        assert_eq!(bulk_run_form_data.form_data.total_needed, "12");
+        assert_eq!(bulk_run_form_data.form_data.total_needed_bags, "12");
     }
 
     // Story 1.1.1 - Bulk Run Modal Selection Tests
@@ -577,6 +596,7 @@ mod tests {
             formula_desc: "Battermix".to_string(),
             status: "NEW".to_string(),
             batch_count: 2,
+            claims: Vec::new(),
         }
     }
 
@@ -601,6 +621,7 @@ mod tests {
                 formula_desc: "Cookie Mix".to_string(),
                 status: "NEW".to_string(),
                 batch_count: 3,
+                claims: Vec::new(),
             },
         ];
 
@@ -678,6 +699,7 @@ mod tests {
             formula_desc: "Valid Description".to_string(),
             status: "INVALID_STATUS".to_string(),
             batch_count: -1, // Invalid batch count
+            claims: Vec::new(),
         };
 
         // Test that model accepts various values (validation would be in business logic)
@@ -699,6 +721,7 @@ mod tests {
                 formula_desc: "Battermix".to_string(),
                 status: "NEW".to_string(),
                 batch_count: 2,
+                claims: Vec::new(),
             },
             BulkRunSummary {
                 run_no: 215236,
@@ -706,6 +729,7 @@ mod tests {
                 formula_desc: "Cookie Mix".to_string(),
                 status: "NEW".to_string(),
                 batch_count: 3,
+                claims: Vec::new(),
             },
         ];
 
@@ -730,13 +754,14 @@ mod tests {
     #[test]
     fn test_bulk_run_status_filtering_logic() {
         // Test logic for filtering runs by status (would be in database layer)
-        let all_runs = vec![
+        let all_runs = [
             BulkRunSummary {
                 run_no: 215235,
                 formula_id: "TB317-01".to_string(),
                 formula_desc: "Battermix".to_string(),
                 status: "NEW".to_string(),
                 batch_count: 2,
+                claims: Vec::new(),
             },
             BulkRunSummary {
                 run_no: 215236,
@@ -744,6 +769,7 @@ mod tests {
                 formula_desc: "Cookie Mix".to_string(),
                 status: "COMPLETED".to_string(),
                 batch_count: 3,
+                claims: Vec::new(),
             },
             BulkRunSummary {
                 run_no: 215237,
@@ -751,6 +777,7 @@ mod tests {
                 formula_desc: "Cake Mix".to_string(),
                 status: "NEW".to_string(),
                 batch_count: 1,
+                claims: Vec::new(),
             },
         ];
 
@@ -790,6 +817,7 @@ mod tests {
             formula_desc: "Battermix".to_string(),
             status: "NEW".to_string(),
             batch_count: *batch_counts.get("215235").unwrap_or(&0),
+            claims: Vec::new(),
         };
 
         assert_eq!(summary1.batch_count, 2);
@@ -813,6 +841,7 @@ mod tests {
             formula_desc: "".to_string(),
             status: "".to_string(),
             batch_count: 0,
+            claims: Vec::new(),
         }];
 
         let response_with_empty_data = BulkRunListResponse {
@@ -837,6 +866,7 @@ mod tests {
                 formula_desc: format!("Formula {}", i),
                 status: "NEW".to_string(),
                 batch_count: (i % 5) + 1, // 1-5 batches
+                claims: Vec::new(),
             });
         }
 
@@ -954,8 +984,14 @@ mod tests {
             soh_uom: inventory.soh_uom.clone(),
             bulk_pack_size_value: inventory.bulk_pack_size_value.to_string(),
             bulk_pack_size_uom: inventory.bulk_pack_size_uom.clone(),
-            total_needed: calculations.total_needed.to_string(),
-            remaining_to_pick: calculations.remaining_to_pick.to_string(),
+            total_needed_bags: calculations.total_needed.to_string(),
+            total_needed_bags_uom: "BAGS".to_string(),
+            total_needed_kg: "240".to_string(),
+            total_needed_kg_uom: "KG".to_string(),
+            remaining_bags: calculations.remaining_to_pick.to_string(),
+            remaining_bags_uom: "BAGS".to_string(),
+            remaining_kg: "240".to_string(),
+            remaining_kg_uom: "KG".to_string(),
             ingredient_index: 0,
             total_ingredients: 3,
         };
@@ -973,8 +1009,8 @@ mod tests {
         assert_eq!(form_data.form_data.soh_uom, "KG");
         assert_eq!(form_data.form_data.bulk_pack_size_value, "20");
         assert_eq!(form_data.form_data.bulk_pack_size_uom, "KG");
-        assert_eq!(form_data.form_data.total_needed, "12");
-        assert_eq!(form_data.form_data.remaining_to_pick, "12");
+        assert_eq!(form_data.form_data.total_needed_bags, "12");
+        assert_eq!(form_data.form_data.remaining_bags, "12");
 
         // Test API response serialization is unchanged
         let json = serde_json::to_string(&form_data).expect("Should serialize");
@@ -1026,10 +1062,13 @@ mod tests {
         for invalid_run in invalid_run_numbers {
             if let Ok(parsed) = invalid_run.parse::<i32>() {
                 // Valid parse means it should work (like 999999999)
-                assert!(parsed > 0 || parsed == 0);
+                assert!(parsed >= 0);
             } else {
                 // Invalid parse should be handled gracefully (no crashes)
-                assert!(true, "Invalid run numbers should be handled gracefully");
+                assert!(
+                    invalid_run.parse::<i32>().is_err(),
+                    "Invalid run numbers should be handled gracefully"
+                );
             }
         }
     }
@@ -1070,8 +1109,14 @@ mod tests {
             soh_uom: inventory.soh_uom.clone(),
             bulk_pack_size_value: inventory.bulk_pack_size_value.to_string(),
             bulk_pack_size_uom: inventory.bulk_pack_size_uom.clone(),
-            total_needed: "12".to_string(),
-            remaining_to_pick: "12".to_string(),
+            total_needed_bags: "12".to_string(),
+            total_needed_bags_uom: "BAGS".to_string(),
+            total_needed_kg: "240".to_string(),
+            total_needed_kg_uom: "KG".to_string(),
+            remaining_bags: "12".to_string(),
+            remaining_bags_uom: "BAGS".to_string(),
+            remaining_kg: "240".to_string(),
+            remaining_kg_uom: "KG".to_string(),
             ingredient_index: 0,
             total_ingredients: 3,
         };
@@ -1168,6 +1213,7 @@ mod tests {
         let json = r#"{
             "pallet_number": 1,
             "batch_number": "850823",
+            "row_num": 1,
             "no_of_bags_picked": 24,
             "quantity_picked": 480.0,
             "no_of_bags_remaining": 36,
@@ -0,0 +1,1446 @@
+//! In-memory implementation of the repository traits.
+//!
+//! A fake for testing the service layer. Pick validation, confirm-pick, unpick and
+//! completion are simplified re-implementations of the SQL transactions in
+//! `database/bulk_runs.rs`, not the production code: picks move quantity from lots to
+//! batch rows and unpicks put it back, but the SQL itself (locking, the BME4 tables,
+//! LotTransaction numbering) is not exercised. A change to those rules in SQL must be
+//! copied here by hand.
+
+use anyhow::{anyhow, Result};
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use chrono::{Duration, Utc};
+use std::collections::BTreeMap;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::database::repository::{
+    BulkRunRepository, LotRepository, PrintJobRepository, PutawayRepository, ScanRepository,
+};
+use crate::models::bulk_runs::*;
+use crate::models::idempotency::{IdempotencyKey, StoredResponse};
+use crate::models::pallet_labels::{PalletSscc, SsccConfig};
+use crate::models::pick_plan::{PickPlan, PlanStepStatus};
+use crate::models::pick_scans::PickScanException;
+use crate::models::pick_sequence::BinLocation;
+use crate::models::print_jobs::{LabelPrinter, NewPrintJob, PrintAttempt, PrintJob, PrintJobStatus};
+use crate::models::run_claims::{ClaimOutcome, RunClaim};
+use crate::models::scans::{ItemGtin, LotMasterEntry, ScannedItem};
+use crate::models::putaway_models::{
+    BinSearchItem, ItemMasterRecord, LotMasterRecord, LotSearchItem, PutawayError, TransferResult,
+};
+use crate::utils::bangkok_now_rfc3339;
+
+/// One allocation created by a pick (Cust_BulkLotPicked row)
+#[derive(Debug, Clone)]
+pub struct PickRecord {
+    pub lot_tran_no: i32,
+    pub run_no: i32,
+    pub row_num: i32,
+    pub line_id: i32,
+    pub item_key: String,
+    pub lot_no: String,
+    pub bin_no: String,
+    pub bags: BigDecimal,
+    pub pack_size: BigDecimal,
+    pub user_id: String,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    runs: BTreeMap<i32, BulkRun>,
+    batches: Vec<BulkPickedItem>,
+    lots: Vec<LotSearchResult>,
+    picks: Vec<PickRecord>,
+    putaway_lots: Vec<(LotMasterRecord, ItemMasterRecord)>,
+    /// Error the next bin transfer fails with, as a lost connection or failed commit would
+    transfer_failure: Option<PutawayError>,
+    bins: Vec<(String, String)>,
+    next_lot_tran_no: i32,
+    next_document_no: i32,
+    /// tbl_idempotency_keys, keyed by (username, operation, key)
+    idempotency: BTreeMap<(String, &'static str, String), StoredResponse>,
+    /// tbl_bulk_run_claims
+    claims: Vec<RunClaim>,
+    /// tbl_bulk_pick_plans with their steps and shortages
+    pick_plans: BTreeMap<i32, PickPlan>,
+    /// BINMaster aisle/row/rack by bin number
+    bin_locations: BTreeMap<String, BinLocation>,
+    /// tbl_pallet_sscc; a pallet's serial reference is its position plus one
+    pallets: Vec<PalletSscc>,
+    /// tbl_label_printers
+    printers: BTreeMap<String, LabelPrinter>,
+    /// tbl_print_jobs by JobId
+    print_jobs: BTreeMap<i64, PrintJob>,
+    /// INMAST items, added with their first lot
+    items: BTreeMap<String, ScannedItem>,
+    /// tbl_item_gtin by GTIN
+    gtins: BTreeMap<String, ItemGtin>,
+    /// tbl_pick_scan_exceptions
+    scan_exceptions: Vec<PickScanException>,
+}
+
+impl State {
+    fn stored_response(&self, key: &IdempotencyKey) -> Option<StoredResponse> {
+        self.idempotency
+            .get(&(key.username.clone(), key.operation.as_str(), key.key.clone()))
+            .cloned()
+    }
+
+    fn store_response<T: serde::Serialize>(&mut self, key: &IdempotencyKey, response: &T) {
+        let stored = StoredResponse {
+            request_hash: key.request_hash.clone(),
+            response_body: serde_json::to_string(response).expect("response serializes"),
+        };
+        self.idempotency
+            .insert((key.username.clone(), key.operation.as_str(), key.key.clone()), stored);
+    }
+}
+
+/// Shared, cloneable in-memory store implementing every repository trait
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryRepository {
+    state: Arc<Mutex<State>>,
+}
+
+impl InMemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().expect("in-memory repository poisoned")
+    }
+
+    /// Let every claim's lease run out, as if its holder stopped renewing
+    pub fn expire_claims(&self) {
+        let expired = Utc::now() - Duration::seconds(1);
+        for claim in &mut self.state().claims {
+            claim.expires_at = expired;
+        }
+    }
+
+    /// Make every queued print job due now, as if its retry delay had passed
+    pub fn make_print_jobs_due(&self) {
+        let now = Utc::now();
+        for job in self.state().print_jobs.values_mut() {
+            job.next_attempt_at = job.next_attempt_at.min(now);
+        }
+    }
+
+    pub fn add_run(&self, run_no: i32, formula_id: &str, no_of_batches: i32) {
+        self.state().runs.insert(
+            run_no,
+            BulkRun {
+                run_no,
+                batch_no: format!("{}", 850000 + run_no % 1000),
+                formula_id: formula_id.to_string(),
+                formula_desc: format!("{formula_id} formula"),
+                no_of_batches,
+                pallets_per_batch: Some(no_of_batches),
+                status: "NEW".to_string(),
+                created_date: Some(Utc::now()),
+                picking_date: None,
+            },
+        );
+    }
+
+    /// Add one batch row of an ingredient; `to_pick_bags` full bags of `pack_size` KG
+    pub fn add_batch(&self, run_no: i32, row_num: i32, line_id: i32, item_key: &str, to_pick_bags: i32, pack_size: i32) {
+        let to_picked_bulk_qty = BigDecimal::from(to_pick_bags);
+        let pack_size = BigDecimal::from(pack_size);
+        self.state().batches.push(BulkPickedItem {
+            run_no,
+            row_num,
+            line_id,
+            item_key: item_key.to_string(),
+            description: Some(format!("{item_key} description")),
+            location: Some("TFC1".to_string()),
+            standard_qty: &to_picked_bulk_qty * &pack_size,
+            pack_size,
+            uom: "KG".to_string(),
+            to_picked_std_qty: BigDecimal::zero(),
+            to_picked_bulk_qty,
+            picked_bulk_qty: None,
+            picking_date: None,
+            status: None,
+            total_batches: None,
+            completed_batches: None,
+            remaining_qty: None,
+            completion_status: None,
+        });
+    }
+
+    /// Add available stock (KG) for a lot in a bin
+    pub fn add_lot(&self, item_key: &str, lot_no: &str, bin_no: &str, available_kg: i32, pack_size: i32, days_to_expiry: i64) {
+        let available_qty = BigDecimal::from(available_kg);
+        let pack_size = BigDecimal::from(pack_size);
+        let mut state = self.state();
+        state.items.entry(item_key.to_string()).or_insert_with(|| ScannedItem {
+            item_key: item_key.to_string(),
+            description: format!("{item_key} description"),
+        });
+        state.lots.push(LotSearchResult {
+            lot_no: lot_no.to_string(),
+            bin_no: bin_no.to_string(),
+            date_exp: Utc::now() + Duration::days(days_to_expiry),
+            qty_on_hand: available_qty.clone(),
+            qty_issue: BigDecimal::zero(),
+            committed_qty: BigDecimal::zero(),
+            available_bags: (&available_qty / &pack_size).to_i32().unwrap_or(0),
+            available_qty,
+            pack_size,
+            item_key: item_key.to_string(),
+            location_key: "TFC1".to_string(),
+        });
+    }
+
+    /// Move a batch row to another site (cust_BulkPicked.Location)
+    pub fn set_batch_location(&self, run_no: i32, row_num: i32, line_id: i32, location: &str) {
+        for batch in self.state().batches.iter_mut() {
+            if (batch.run_no, batch.row_num, batch.line_id) == (run_no, row_num, line_id) {
+                batch.location = Some(location.to_string());
+            }
+        }
+    }
+
+    /// Fail the next bin transfer with `error` before anything moves
+    pub fn fail_next_transfer(&self, error: PutawayError) {
+        self.state().transfer_failure = Some(error);
+    }
+
+    pub fn add_bin(&self, location: &str, bin_no: &str) {
+        self.state().bins.push((location.to_string(), bin_no.to_string()));
+    }
+
+    /// Place a bin on the warehouse floor for walk-path ordering
+    pub fn add_bin_location(&self, bin_no: &str, aisle: &str, row: &str, rack: &str) {
+        self.state().bin_locations.insert(
+            bin_no.to_string(),
+            BinLocation {
+                bin_no: bin_no.to_string(),
+                aisle: aisle.to_string(),
+                row: row.to_string(),
+                rack: rack.to_string(),
+            },
+        );
+    }
+
+    /// Add a putaway lot sitting in `bin_no`
+    pub fn add_putaway_lot(&self, lot_no: &str, item_key: &str, location: &str, bin_no: &str, qty_on_hand: f64) {
+        self.state().putaway_lots.push((
+            LotMasterRecord {
+                lot_no: lot_no.to_string(),
+                item_key: item_key.to_string(),
+                location_key: location.to_string(),
+                bin_no: bin_no.to_string(),
+                qty_on_hand,
+                qty_issued: 0.0,
+                qty_commit_sales: 0.0,
+                date_expiry: Utc::now() + Duration::days(180),
+                vendor_key: String::new(),
+                vendor_lot_no: String::new(),
+                document_no: String::new(),
+                document_line_no: 0,
+                transaction_type: 0,
+                lot_status: "P".to_string(),
+            },
+            ItemMasterRecord {
+                item_key: item_key.to_string(),
+                desc1: format!("{item_key} description"),
+                desc2: String::new(),
+                stock_uom_code: "KG".to_string(),
+                purchase_uom_code: "KG".to_string(),
+                sales_uom_code: "KG".to_string(),
+            },
+        ));
+    }
+
+    pub fn picks(&self) -> Vec<PickRecord> {
+        self.state().picks.clone()
+    }
+
+    /// Current available KG for a lot in a bin
+    pub fn lot_available(&self, lot_no: &str, bin_no: &str) -> Option<BigDecimal> {
+        self.state()
+            .lots
+            .iter()
+            .find(|lot| lot.lot_no == lot_no && lot.bin_no == bin_no)
+            .map(|lot| lot.available_qty.clone())
+    }
+
+    /// Bin currently holding a putaway lot quantity
+    pub fn putaway_bins(&self, lot_no: &str) -> Vec<(String, f64)> {
+        self.state()
+            .putaway_lots
+            .iter()
+            .filter(|(lot, _)| lot.lot_no == lot_no)
+            .map(|(lot, _)| (lot.bin_no.clone(), lot.qty_on_hand))
+            .collect()
+    }
+}
+
+fn picked(batch: &BulkPickedItem) -> BigDecimal {
+    batch.picked_bulk_qty.clone().unwrap_or_else(BigDecimal::zero)
+}
+
+fn is_batch_complete(batch: &BulkPickedItem) -> bool {
+    picked(batch) >= batch.to_picked_bulk_qty
+}
+
+/// cust_BulkPicked.BatchNo of a batch row
+fn batch_no(run_no: i32, row_num: i32) -> String {
+    format!("{run_no}-{row_num}")
+}
+
+impl State {
+    fn batch_mut(&mut self, run_no: i32, row_num: i32, line_id: i32) -> Option<&mut BulkPickedItem> {
+        self.batches
+            .iter_mut()
+            .find(|b| b.run_no == run_no && b.row_num == row_num && b.line_id == line_id)
+    }
+
+    fn run_batches(&self, run_no: i32) -> Vec<BulkPickedItem> {
+        let mut batches: Vec<_> = self.batches.iter().filter(|b| b.run_no == run_no).cloned().collect();
+        batches.sort_by_key(|b| (b.line_id, b.row_num));
+        batches
+    }
+
+    /// Reverse allocations: stock goes back to the lot, bags come off the batch
+    fn rollback(&mut self, predicate: impl Fn(&PickRecord) -> bool) -> Vec<PickRecord> {
+        let (removed, kept): (Vec<_>, Vec<_>) = self.picks.drain(..).partition(|pick| predicate(pick));
+        self.picks = kept;
+
+        for pick in &removed {
+            let kg = &pick.bags * &pick.pack_size;
+            if let Some(lot) = self
+                .lots
+                .iter_mut()
+                .find(|lot| lot.lot_no == pick.lot_no && lot.bin_no == pick.bin_no)
+            {
+                lot.available_qty = &lot.available_qty + &kg;
+                lot.committed_qty = &lot.committed_qty - &kg;
+            }
+            if let Some(batch) = self.batch_mut(pick.run_no, pick.row_num, pick.line_id) {
+                let remaining = picked(batch) - &pick.bags;
+                batch.picked_bulk_qty = if remaining.is_zero() { None } else { Some(remaining) };
+            }
+        }
+        removed
+    }
+
+    fn unpick_summary(removed: &[PickRecord], operation_type: &str, run_no: i32, row_num: i32, line_id: i32) -> serde_json::Value {
+        if removed.is_empty() {
+            return serde_json::json!({
+                "message": "No allocation records exist - ingredient already unpicked",
+                "status": "success",
+                "operation_type": "unpick_already_clean",
+                "allocation_count": 0
+            });
+        }
+        let total: BigDecimal = removed.iter().map(|pick| &pick.bags * &pick.pack_size).sum();
+        serde_json::json!({
+            "unpicked_lots": removed.len(),
+            "total_qty_rolled_back": total.to_f64().unwrap_or(0.0),
+            "lot_transaction_cleanup": "completed",
+            "operation_type": operation_type,
+            "run_no": run_no,
+            "row_num": row_num,
+            "line_id": line_id
+        })
+    }
+}
+
+impl BulkRunRepository for InMemoryRepository {
+    async fn search_bulk_runs(&self, search_query: &str, search_mode: &str) -> Result<Vec<BulkRun>> {
+        let query = search_query.trim();
+        Ok(self
+            .state()
+            .runs
+            .values()
+            .rev()
+            .filter(|run| {
+                if search_mode == "exact" {
+                    run.run_no.to_string() == query
+                } else {
+                    run.run_no.to_string().contains(query) || run.formula_id.contains(query)
+                }
+            })
+            .cloned()
+            .collect())
+    }
+
+    async fn get_bulk_run(&self, run_no: i32) -> Result<Option<BulkRun>> {
+        Ok(self.state().runs.get(&run_no).cloned())
+    }
+
+    async fn get_bulk_run_status(&self, run_no: i32) -> Result<Option<BulkRunStatusResponse>> {
+        Ok(self.state().runs.get(&run_no).map(|run| BulkRunStatusResponse {
+            run_no,
+            status: run.status.clone(),
+            formula_desc: run.formula_desc.clone(),
+            last_modified: run.picking_date,
+        }))
+    }
+
+    async fn list_active_bulk_runs_paginated(&self, page: u32, limit: u32) -> Result<(Vec<BulkRunSummary>, u64)> {
+        let state = self.state();
+        let active: Vec<_> = state.runs.values().rev().filter(|run| run.status == "NEW").collect();
+        let total = active.len() as u64;
+        let runs = active
+            .into_iter()
+            .skip((page.saturating_sub(1) * limit) as usize)
+            .take(limit as usize)
+            .map(|run| BulkRunSummary {
+                run_no: run.run_no,
+                formula_id: run.formula_id.clone(),
+                formula_desc: run.formula_desc.clone(),
+                status: run.status.clone(),
+                batch_count: run.no_of_batches,
+                claims: Vec::new(),
+            })
+            .collect();
+        Ok((runs, total))
+    }
+
+    async fn get_bulk_run_ingredients(&self, run_no: i32) -> Result<Vec<BulkPickedItem>> {
+        Ok(self.state().run_batches(run_no))
+    }
+
+    async fn get_unique_ingredients_for_search(&self, run_no: i32) -> Result<Vec<BulkPickedItem>> {
+        let mut unique: Vec<BulkPickedItem> = Vec::new();
+        for batch in self.state().run_batches(run_no) {
+            if batch.to_picked_bulk_qty > BigDecimal::zero() && !unique.iter().any(|u| u.item_key == batch.item_key) {
+                unique.push(batch);
+            }
+        }
+        Ok(unique)
+    }
+
+    async fn get_bulk_run_batches(&self, run_no: i32) -> Result<Vec<BulkPickedItem>> {
+        Ok(self.state().run_batches(run_no))
+    }
+
+    async fn get_bulk_run_batches_for_ingredient(&self, run_no: i32, item_key: &str) -> Result<Vec<BulkPickedItem>> {
+        Ok(self
+            .state()
+            .run_batches(run_no)
+            .into_iter()
+            .filter(|b| b.item_key == item_key)
+            .collect())
+    }
+
+    async fn get_pallet_tracking_data(&self, run_no: i32, item_key: Option<&str>) -> Result<Vec<PalletBatch>> {
+        let batches = self.state().run_batches(run_no);
+        let item_key = match item_key {
+            Some(key) => key.to_string(),
+            None => match batches.first() {
+                Some(batch) => batch.item_key.clone(),
+                None => return Ok(Vec::new()),
+            },
+        };
+        Ok(batches
+            .iter()
+            .filter(|b| b.item_key == item_key)
+            .enumerate()
+            .map(|(index, b)| {
+                let picked_bags = picked(b);
+                let remaining_bags = &b.to_picked_bulk_qty - &picked_bags;
+                PalletBatch {
+                    pallet_number: index as i32 + 1,
+                    batch_number: format!("{}-{}", run_no, b.row_num),
+                    row_num: b.row_num,
+                    no_of_bags_picked: picked_bags.to_i32().unwrap_or(0),
+                    quantity_picked: (&picked_bags * &b.pack_size).to_f64().unwrap_or(0.0),
+                    no_of_bags_remaining: remaining_bags.to_i32().unwrap_or(0),
+                    quantity_remaining: (&remaining_bags * &b.pack_size).to_f64().unwrap_or(0.0),
+                }
+            })
+            .collect())
+    }
+
+    async fn is_pallet_completed(&self, run_no: i32, row_num: i32, line_id: i32) -> Result<bool> {
+        self.state()
+            .batch_mut(run_no, row_num, line_id)
+            .map(|batch| is_batch_complete(batch))
+            .ok_or_else(|| anyhow!("No batch found for run {run_no}, row {row_num}, line {line_id}"))
+    }
+
+    async fn get_next_available_pallet(
+        &self,
+        run_no: i32,
+        current_row_num: i32,
+        line_id: i32,
+    ) -> Result<Option<PalletBatchInfo>> {
+        let batches = self.state().run_batches(run_no);
+        let pallet_number = |row_num: i32| batches.iter().filter(|b| b.line_id == line_id && b.row_num <= row_num).count() as i32;
+        Ok(batches
+            .iter()
+            .filter(|b| b.line_id == line_id && b.row_num != current_row_num && !is_batch_complete(b))
+            .map(|b| PalletBatchInfo {
+                run_no,
+                row_num: b.row_num,
+                line_id,
+                batch_no: batch_no(run_no, b.row_num),
+                item_key: b.item_key.clone(),
+                pallet_number: pallet_number(b.row_num),
+                pack_size: b.pack_size.to_f64().unwrap_or(0.0),
+                to_picked_bulk_qty: b.to_picked_bulk_qty.to_f64().unwrap_or(0.0),
+                picked_bulk_qty: picked(b).to_f64().unwrap_or(0.0),
+                description: b.description.clone().unwrap_or_default(),
+                is_completed: false,
+            })
+            .next())
+    }
+
+    async fn validate_pick_request(&self, run_no: i32, request: &PickConfirmationRequest) -> Result<PickValidationResult> {
+        let mut state = self.state();
+        let invalid = |message: String| PickValidationResult {
+            is_valid: false,
+            error_message: Some(message),
+            warnings: Vec::new(),
+            max_allowed_quantity: None,
+            available_inventory: None,
+        };
+
+        let Some(batch) = state.batch_mut(run_no, request.row_num, request.line_id).cloned() else {
+            return Ok(invalid(format!(
+                "Ingredient not found for run {run_no}, row {}, line {}",
+                request.row_num, request.line_id
+            )));
+        };
+        if request.picked_bulk_qty <= BigDecimal::zero() {
+            return Ok(invalid("Picked quantity must be greater than zero".to_string()));
+        }
+
+        let remaining = &batch.to_picked_bulk_qty - picked(&batch);
+        if request.picked_bulk_qty > remaining {
+            return Ok(PickValidationResult {
+                max_allowed_quantity: Some(remaining.clone()),
+                ..invalid(format!("Quantity picked is more than Qty Required {remaining}"))
+            });
+        }
+
+        let available = state
+            .lots
+            .iter()
+            .find(|lot| lot.lot_no == request.lot_no && lot.bin_no == request.bin_no && lot.item_key == batch.item_key)
+            .map(|lot| lot.available_qty.clone());
+        let Some(available) = available else {
+            return Ok(invalid(format!(
+                "Lot {} in bin {} is not valid for ingredient {}",
+                request.lot_no, request.bin_no, batch.item_key
+            )));
+        };
+        if &request.picked_bulk_qty * &batch.pack_size > available {
+            return Ok(PickValidationResult {
+                available_inventory: Some(available.clone()),
+                ..invalid(format!("Insufficient inventory in lot {}: {} KG available", request.lot_no, available))
+            });
+        }
+
+        Ok(PickValidationResult {
+            is_valid: true,
+            error_message: None,
+            warnings: Vec::new(),
+            max_allowed_quantity: Some(remaining),
+            available_inventory: Some(available),
+        })
+    }
+
+    async fn find_idempotent_response(&self, key: &IdempotencyKey) -> Result<Option<StoredResponse>> {
+        Ok(self.state().stored_response(key))
+    }
+
+    async fn confirm_pick_transaction(
+        &self,
+        run_no: i32,
+        request: &PickConfirmationRequest,
+    ) -> Result<PickConfirmationResponse, BulkRunError> {
+        let mut state = self.state();
+        if let Some(key) = &request.idempotency_key {
+            if let Some(stored) = state.stored_response(key) {
+                return Ok(stored.replay(key)?);
+            }
+        }
+        // Same re-check the SQL transaction makes under lock
+        if !request.override_claim {
+            let user_id = request.user_id.as_deref().unwrap_or_default();
+            let now = Utc::now();
+            if let Some(holder) = state.claims.iter().find(|claim| {
+                claim.run_no == run_no && !claim.is_expired(now) && claim.blocks(user_id, Some(request.line_id))
+            }) {
+                return Err(BulkRunError::ClaimedByOther {
+                    run_no,
+                    line_id: holder.line_id,
+                    holder: holder.username.clone(),
+                });
+            }
+        }
+        let batch = state
+            .batch_mut(run_no, request.row_num, request.line_id)
+            .ok_or(BulkRunError::BatchNotFound { run_no, row_num: request.row_num, line_id: request.line_id })?;
+        if is_batch_complete(batch) {
+            return Err(BulkRunError::BatchAlreadyCompleted);
+        }
+        let remaining = &batch.to_picked_bulk_qty - picked(batch);
+        if request.picked_bulk_qty > remaining {
+            return Err(BulkRunError::InsufficientBatchQuantity {
+                requested: request.picked_bulk_qty.to_f64().unwrap_or(0.0),
+                remaining: remaining.to_f64().unwrap_or(0.0),
+                item_key: batch.item_key.clone(),
+                row_num: request.row_num,
+                line_id: request.line_id,
+            });
+        }
+
+        let pack_size = batch.pack_size.clone();
+        let item_key = batch.item_key.clone();
+
+        // Check every lot before changing anything, as the transaction rolls back on any failure
+        let allocations = request.lot_allocations();
+        for allocation in &allocations {
+            let lot = state
+                .lots
+                .iter()
+                .find(|lot| lot.lot_no == allocation.lot_no && lot.bin_no == allocation.bin_no)
+                .ok_or_else(|| anyhow!("Lot {} not found in bin {}", allocation.lot_no, allocation.bin_no))?;
+            if &allocation.bags * &pack_size > lot.available_qty {
+                return Err(BulkRunError::TransactionRolledBack(anyhow!(
+                    "Insufficient quantity in lot {}",
+                    allocation.lot_no
+                )));
+            }
+        }
+
+        let batch = state
+            .batch_mut(run_no, request.row_num, request.line_id)
+            .expect("batch checked above");
+        batch.picked_bulk_qty = Some(picked(batch) + &request.picked_bulk_qty);
+        batch.picking_date = Some(Utc::now());
+
+        state.next_document_no += 1;
+        let document_no = format!("BT-{:08}", state.next_document_no);
+        let mut allocated = Vec::with_capacity(allocations.len());
+        for allocation in allocations {
+            let kg = &allocation.bags * &pack_size;
+            let lot = state
+                .lots
+                .iter_mut()
+                .find(|lot| lot.lot_no == allocation.lot_no && lot.bin_no == allocation.bin_no)
+                .expect("lot checked above");
+            lot.available_qty = &lot.available_qty - &kg;
+            lot.committed_qty = &lot.committed_qty + &kg;
+
+            state.next_lot_tran_no += 1;
+            let lot_tran_no = state.next_lot_tran_no;
+            state.picks.push(PickRecord {
+                lot_tran_no,
+                run_no,
+                row_num: request.row_num,
+                line_id: request.line_id,
+                item_key: item_key.clone(),
+                lot_no: allocation.lot_no.clone(),
+                bin_no: allocation.bin_no.clone(),
+                bags: allocation.bags.clone(),
+                pack_size: pack_size.clone(),
+                user_id: request.user_id.clone().unwrap_or_default(),
+            });
+            allocated.push(AllocatedLot {
+                lot_no: allocation.lot_no,
+                bin_no: allocation.bin_no,
+                picked_bulk_qty: allocation.bags,
+                picked_qty: kg,
+                lot_tran_no: lot_tran_no as i64,
+            });
+        }
+
+        let response = PickConfirmationResponse {
+            success: true,
+            transaction_id: allocated.first().map(|lot| lot.lot_tran_no),
+            document_no: Some(document_no),
+            updated_records: TransactionSummary {
+                bulk_picked_updated: true,
+                lot_picked_created: true,
+                lot_master_updated: true,
+                lot_transaction_created: true,
+                pallet_lot_picked_created: true,
+                total_committed_qty: &request.picked_bulk_qty * &pack_size,
+            },
+            allocations: allocated,
+            warnings: Vec::new(),
+        };
+        if let Some(key) = &request.idempotency_key {
+            state.store_response(key, &response);
+        }
+        Ok(response)
+    }
+
+    async fn unpick_by_lot_tran_no(&self, lot_tran_no: i32, _user_id: &str) -> Result<serde_json::Value> {
+        let mut state = self.state();
+        let removed = state.rollback(|pick| pick.lot_tran_no == lot_tran_no);
+        let pick = removed
+            .first()
+            .ok_or_else(|| anyhow!("No picked record found for LotTranNo {lot_tran_no}"))?;
+        Ok(serde_json::json!({
+            "unpicked_lot_tran_no": lot_tran_no,
+            "lot_no": pick.lot_no,
+            "bin_no": pick.bin_no,
+            "item_key": pick.item_key,
+            "quantity_removed": (&pick.bags * &pick.pack_size).to_f64().unwrap_or(0.0),
+            "operation_type": "precise_unpick",
+            "run_no": pick.run_no,
+            "row_num": pick.row_num,
+            "line_id": pick.line_id
+        }))
+    }
+
+    async fn unpick_specific_lot(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        lot_no: &str,
+        _user_id: &str,
+    ) -> Result<serde_json::Value> {
+        let removed = self.state().rollback(|pick| {
+            pick.run_no == run_no && pick.row_num == row_num && pick.line_id == line_id && pick.lot_no == lot_no
+        });
+        Ok(State::unpick_summary(&removed, "lot", run_no, row_num, line_id))
+    }
+
+    async fn unpick_entire_batch(&self, run_no: i32, row_num: i32, line_id: i32, _user_id: &str) -> Result<serde_json::Value> {
+        let removed = self
+            .state()
+            .rollback(|pick| pick.run_no == run_no && pick.row_num == row_num && pick.line_id == line_id);
+        Ok(State::unpick_summary(&removed, "batch", run_no, row_num, line_id))
+    }
+
+    async fn unpick_all_run_lots(&self, run_no: i32, _user_id: &str) -> Result<serde_json::Value> {
+        let removed = self.state().rollback(|pick| pick.run_no == run_no);
+        let mut ingredients: Vec<_> = removed.iter().map(|pick| (pick.row_num, pick.line_id)).collect();
+        ingredients.sort_unstable();
+        ingredients.dedup();
+        Ok(serde_json::json!({
+            "message": format!("Successfully unpicked all lots from {} ingredients", ingredients.len()),
+            "ingredients_processed": ingredients.len(),
+            "total_ingredients": ingredients.len(),
+            "errors": Vec::<String>::new(),
+            "run_no": run_no,
+            "operation_type": "run_wide_unpick"
+        }))
+    }
+
+    async fn get_run_completion_status(&self, run_no: i32) -> Result<RunCompletionStatus> {
+        // Same grouping as the SQL: per (LineId, ItemKey), picked from allocations vs required
+        let state = self.state();
+        let mut ingredients: BTreeMap<(i32, String), (BigDecimal, BigDecimal)> = BTreeMap::new();
+        for batch in state.batches.iter().filter(|b| b.run_no == run_no && b.to_picked_bulk_qty > BigDecimal::zero()) {
+            let entry = ingredients
+                .entry((batch.line_id, batch.item_key.clone()))
+                .or_insert_with(|| (BigDecimal::zero(), BigDecimal::zero()));
+            entry.0 = &entry.0 + &batch.to_picked_bulk_qty;
+        }
+        for ((_, item_key), (_, picked)) in ingredients.iter_mut() {
+            *picked = state
+                .picks
+                .iter()
+                .filter(|pick| pick.run_no == run_no && &pick.item_key == item_key)
+                .map(|pick| pick.bags.clone())
+                .sum();
+        }
+
+        let total_ingredients = ingredients.len() as i32;
+        let completed_count = ingredients.values().filter(|(required, picked)| picked >= required).count() as i32;
+        let incomplete_count = total_ingredients - completed_count;
+        Ok(RunCompletionStatus {
+            is_complete: incomplete_count == 0 && total_ingredients > 0,
+            incomplete_count,
+            completed_count,
+            total_ingredients,
+        })
+    }
+
+    async fn update_bulk_run_status(&self, run_no: i32, new_status: &str, _user_id: &str) -> Result<bool> {
+        Ok(match self.state().runs.get_mut(&run_no) {
+            Some(run) => {
+                run.status = new_status.to_string();
+                run.picking_date = Some(Utc::now());
+                true
+            }
+            None => false,
+        })
+    }
+
+    async fn revert_run_status_to_new(&self, run_no: i32, _user_id: &str) -> Result<bool> {
+        Ok(match self.state().runs.get_mut(&run_no) {
+            Some(run) if run.status == "PRINT" => {
+                run.status = "NEW".to_string();
+                true
+            }
+            _ => false,
+        })
+    }
+
+    async fn get_batch_weight_summary(&self, run_no: i32) -> Result<Vec<BatchWeightSummaryItem>> {
+        let state = self.state();
+        Ok(state
+            .run_batches(run_no)
+            .iter()
+            .filter(|b| b.to_picked_bulk_qty > BigDecimal::zero())
+            .map(|b| {
+                let picked_bags: BigDecimal = state
+                    .picks
+                    .iter()
+                    .filter(|pick| pick.run_no == run_no && pick.row_num == b.row_num && pick.line_id == b.line_id)
+                    .map(|pick| pick.bags.clone())
+                    .sum();
+                BatchWeightSummaryItem {
+                    batch_no: batch_no(run_no, b.row_num),
+                    item_key: b.item_key.clone(),
+                    item_description: b.description.clone(),
+                    to_picked_bulk_qty: b.to_picked_bulk_qty.clone(),
+                    total_weight_kg: &b.to_picked_bulk_qty * &b.pack_size,
+                    picked_weight_kg: &picked_bags * &b.pack_size,
+                    remaining_weight_kg: (&b.to_picked_bulk_qty - &picked_bags) * &b.pack_size,
+                    picked_bulk_qty: picked_bags,
+                    pack_size: b.pack_size.clone(),
+                    row_num: b.row_num,
+                    line_id: b.line_id,
+                }
+            })
+            .collect())
+    }
+
+    async fn get_lot_picking_details_for_run(&self, run_no: i32) -> Result<Vec<LotPickingDetail>> {
+        let state = self.state();
+        let mut details: Vec<LotPickingDetail> = state
+            .picks
+            .iter()
+            .filter(|pick| pick.run_no == run_no)
+            .map(|pick| {
+                let batch = state
+                    .batches
+                    .iter()
+                    .find(|b| b.run_no == run_no && b.row_num == pick.row_num && b.line_id == pick.line_id);
+                let picked_bulk_qty = batch.map(picked).unwrap_or_else(BigDecimal::zero);
+                LotPickingDetail {
+                    run_no,
+                    batch_no: batch_no(run_no, pick.row_num),
+                    line_id: pick.line_id,
+                    item_key: pick.item_key.clone(),
+                    lot_no: pick.lot_no.clone(),
+                    bin_no: pick.bin_no.clone(),
+                    qty_received: &pick.bags * &pick.pack_size,
+                    pack_size: pick.pack_size.clone(),
+                    rec_userid: pick.user_id.clone(),
+                    modified_by: pick.user_id.clone(),
+                    rec_date: format!("{:010}", pick.lot_tran_no),
+                    picked_qty: &picked_bulk_qty * &pick.pack_size,
+                    picked_bulk_qty,
+                }
+            })
+            .collect();
+        details.sort_by(|a, b| (&a.batch_no, a.line_id, &a.lot_no).cmp(&(&b.batch_no, b.line_id, &b.lot_no)));
+        Ok(details)
+    }
+
+    async fn acquire_claim(
+        &self,
+        run_no: i32,
+        line_id: Option<i32>,
+        username: &str,
+        lease: Duration,
+        take_over: bool,
+    ) -> Result<ClaimOutcome> {
+        let now = Utc::now();
+        let mut state = self.state();
+        state.claims.retain(|claim| !(claim.run_no == run_no && claim.is_expired(now)));
+
+        let overlaps = |claim: &RunClaim| claim.run_no == run_no && claim.blocks(username, line_id);
+        if let Some(holder) = state.claims.iter().find(|claim| overlaps(claim)) {
+            if !take_over {
+                return Ok(ClaimOutcome::HeldBy(holder.clone()));
+            }
+            state.claims.retain(|claim| !overlaps(claim));
+        }
+
+        let claim = match state
+            .claims
+            .iter_mut()
+            .find(|claim| claim.run_no == run_no && claim.line_id == line_id && claim.username == username)
+        {
+            Some(claim) => {
+                claim.expires_at = now + lease;
+                claim.clone()
+            }
+            None => {
+                let claim = RunClaim {
+                    run_no,
+                    line_id,
+                    username: username.to_string(),
+                    claimed_at: now,
+                    expires_at: now + lease,
+                };
+                state.claims.push(claim.clone());
+                claim
+            }
+        };
+        Ok(ClaimOutcome::Acquired(claim))
+    }
+
+    async fn release_claim(&self, run_no: i32, line_id: Option<i32>, holder: Option<&str>) -> Result<bool> {
+        let mut state = self.state();
+        let before = state.claims.len();
+        state.claims.retain(|claim| {
+            !(claim.run_no == run_no
+                && claim.line_id == line_id
+                && holder.is_none_or(|holder| claim.username == holder))
+        });
+        Ok(state.claims.len() < before)
+    }
+
+    async fn get_active_claims(&self, run_nos: &[i32]) -> Result<Vec<RunClaim>> {
+        let now = Utc::now();
+        Ok(self
+            .state()
+            .claims
+            .iter()
+            .filter(|claim| run_nos.contains(&claim.run_no) && !claim.is_expired(now))
+            .cloned()
+            .collect())
+    }
+
+    async fn save_pick_plan(&self, plan: &PickPlan) -> Result<()> {
+        self.state().pick_plans.insert(plan.run_no, plan.clone());
+        Ok(())
+    }
+
+    async fn get_pick_plan(&self, run_no: i32) -> Result<Option<PickPlan>> {
+        Ok(self.state().pick_plans.get(&run_no).cloned())
+    }
+
+    async fn mark_pick_plan_step_picked(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        line_id: i32,
+        lot_no: &str,
+        bin_no: &str,
+    ) -> Result<bool> {
+        let mut state = self.state();
+        let step = state.pick_plans.get_mut(&run_no).and_then(|plan| {
+            plan.steps.iter_mut().find(|step| {
+                step.row_num == row_num
+                    && step.line_id == line_id
+                    && step.lot_no == lot_no
+                    && step.bin_no == bin_no
+                    && step.status == PlanStepStatus::Pending
+            })
+        });
+        Ok(step.map(|step| step.status = PlanStepStatus::Picked).is_some())
+    }
+
+    async fn get_pallet_sscc(&self, run_no: i32, row_num: i32) -> Result<Option<PalletSscc>> {
+        Ok(self
+            .state()
+            .pallets
+            .iter()
+            .find(|pallet| pallet.run_no == run_no && pallet.row_num == row_num)
+            .cloned())
+    }
+
+    async fn find_pallet_by_sscc(&self, sscc: &str) -> Result<Option<PalletSscc>> {
+        Ok(self.state().pallets.iter().find(|pallet| pallet.sscc == sscc).cloned())
+    }
+
+    async fn assign_pallet_sscc(
+        &self,
+        run_no: i32,
+        row_num: i32,
+        batch_no: &str,
+        config: &SsccConfig,
+        username: &str,
+    ) -> Result<PalletSscc> {
+        let mut state = self.state();
+        if let Some(pallet) = state.pallets.iter().find(|p| p.run_no == run_no && p.row_num == row_num) {
+            return Ok(pallet.clone());
+        }
+        let serial = state.pallets.len() as u64 + 1;
+        let pallet = PalletSscc {
+            sscc: config.sscc(serial).map_err(|e| anyhow!(e))?,
+            run_no,
+            row_num,
+            batch_no: batch_no.to_string(),
+            assigned_by: username.to_string(),
+            assigned_at: Utc::now(),
+        };
+        state.pallets.push(pallet.clone());
+        Ok(pallet)
+    }
+
+    async fn record_pick_scan_exception(&self, exception: &PickScanException) -> Result<()> {
+        self.state().scan_exceptions.push(exception.clone());
+        Ok(())
+    }
+
+    async fn get_pick_scan_exceptions(&self, run_no: i32) -> Result<Vec<PickScanException>> {
+        Ok(self.state().scan_exceptions.iter().filter(|e| e.run_no == run_no).cloned().collect())
+    }
+}
+
+impl LotRepository for InMemoryRepository {
+    async fn get_available_lots(&self, _run_no: i32, item_key: &str) -> Result<Vec<LotInfo>> {
+        // FEFO, then smallest quantity first; expired lots and lots below one pack are left out
+        let now = Utc::now();
+        let mut lots: Vec<_> = self
+            .state()
+            .lots
+            .iter()
+            .filter(|lot| lot.item_key == item_key && lot.date_exp >= now && lot.available_qty >= lot.pack_size)
+            .cloned()
+            .collect();
+        lots.sort_by(|a, b| {
+            (a.date_exp, &a.available_qty, &a.lot_no).cmp(&(b.date_exp, &b.available_qty, &b.lot_no))
+        });
+        Ok(lots
+            .into_iter()
+            .map(|lot| LotInfo {
+                lot_no: lot.lot_no,
+                expiry_date: Some(lot.date_exp),
+                available_qty: lot.available_qty,
+                location: lot.location_key,
+                bin: Some(lot.bin_no),
+            })
+            .collect())
+    }
+
+    async fn get_bin_locations(&self, _location: &str, bin_nos: &[String]) -> Result<Vec<BinLocation>> {
+        let state = self.state();
+        Ok(bin_nos.iter().filter_map(|bin_no| state.bin_locations.get(bin_no).cloned()).collect())
+    }
+
+    async fn get_inventory_info(&self, _run_no: i32, item_key: &str) -> Result<InventoryInfo> {
+        let state = self.state();
+        let lots: Vec<_> = state.lots.iter().filter(|lot| lot.item_key == item_key).collect();
+        Ok(InventoryInfo {
+            item_key: item_key.to_string(),
+            soh_value: lots.iter().map(|lot| lot.available_qty.clone()).sum(),
+            soh_uom: "KG".to_string(),
+            bulk_pack_size_value: lots.first().map(|lot| lot.pack_size.clone()).unwrap_or_else(BigDecimal::zero),
+            bulk_pack_size_uom: "KG".to_string(),
+            available_lots: lots
+                .iter()
+                .map(|lot| LotInfo {
+                    lot_no: lot.lot_no.clone(),
+                    expiry_date: Some(lot.date_exp),
+                    available_qty: lot.available_qty.clone(),
+                    location: lot.location_key.clone(),
+                    bin: Some(lot.bin_no.clone()),
+                })
+                .collect(),
+        })
+    }
+
+    async fn search_lots_for_run_item_paginated(
+        &self,
+        _run_no: i32,
+        item_key: &str,
+        page: u32,
+        page_size: u32,
+    ) -> Result<(Vec<LotSearchResult>, u64)> {
+        // FEFO: earliest expiry first
+        let mut lots: Vec<_> = self
+            .state()
+            .lots
+            .iter()
+            .filter(|lot| lot.item_key == item_key && lot.available_qty > BigDecimal::zero())
+            .cloned()
+            .collect();
+        lots.sort_by_key(|lot| (lot.date_exp, lot.lot_no.clone()));
+        let total = lots.len() as u64;
+        let page = lots
+            .into_iter()
+            .skip((page.saturating_sub(1) * page_size) as usize)
+            .take(page_size as usize)
+            .collect();
+        Ok((page, total))
+    }
+
+    async fn get_bins_for_lot(&self, _run_no: i32, lot_no: &str, item_key: &str) -> Result<Vec<LotSearchResult>> {
+        Ok(self
+            .state()
+            .lots
+            .iter()
+            .filter(|lot| lot.lot_no == lot_no && lot.item_key == item_key && lot.available_qty > BigDecimal::zero())
+            .cloned()
+            .collect())
+    }
+}
+
+impl ScanRepository for InMemoryRepository {
+    async fn find_item(&self, item_key: &str) -> Result<Option<ScannedItem>> {
+        Ok(self.state().items.get(item_key).cloned())
+    }
+
+    async fn find_item_by_gtin(&self, gtin: &str) -> Result<Option<ScannedItem>> {
+        let state = self.state();
+        Ok(state.gtins.get(gtin).and_then(|mapping| state.items.get(&mapping.item_key)).cloned())
+    }
+
+    async fn upsert_item_gtin(&self, gtin: &ItemGtin) -> Result<()> {
+        self.state().gtins.insert(gtin.gtin.clone(), gtin.clone());
+        Ok(())
+    }
+
+    async fn get_lot_master_entries(&self, lot_no: &str) -> Result<Vec<LotMasterEntry>> {
+        Ok(self
+            .state()
+            .lots
+            .iter()
+            .filter(|lot| lot.lot_no == lot_no)
+            .map(|lot| LotMasterEntry {
+                lot_no: lot.lot_no.clone(),
+                item_key: lot.item_key.clone(),
+                location_key: lot.location_key.clone(),
+                bin_no: lot.bin_no.clone(),
+                date_expiry: Some(lot.date_exp.date_naive()),
+                qty_on_hand: lot.qty_on_hand.clone(),
+                qty_commit_sales: lot.committed_qty.clone(),
+                lot_status: "P".to_string(),
+            })
+            .collect())
+    }
+}
+
+impl PrintJobRepository for InMemoryRepository {
+    async fn upsert_printer(&self, printer: &LabelPrinter) -> Result<()> {
+        let mut state = self.state();
+        if printer.is_default {
+            for other in state.printers.values_mut() {
+                other.is_default = false;
+            }
+        }
+        state.printers.insert(printer.printer_id.clone(), printer.clone());
+        Ok(())
+    }
+
+    async fn list_printers(&self) -> Result<Vec<LabelPrinter>> {
+        Ok(self.state().printers.values().cloned().collect())
+    }
+
+    async fn get_printer(&self, printer_id: Option<&str>) -> Result<Option<LabelPrinter>> {
+        let state = self.state();
+        Ok(match printer_id {
+            Some(printer_id) => state.printers.get(printer_id).cloned(),
+            None => state.printers.values().find(|p| p.is_default && p.active).cloned(),
+        })
+    }
+
+    async fn create_print_job(&self, job: &NewPrintJob) -> Result<PrintJob> {
+        let mut state = self.state();
+        let job_id = state.print_jobs.keys().next_back().map_or(1, |id| id + 1);
+        let now = Utc::now();
+        let job = PrintJob {
+            job_id,
+            printer_id: job.printer_id.clone(),
+            run_no: job.run_no,
+            batch_no: job.batch_no.clone(),
+            label_kind: job.label_kind,
+            label_count: job.label_count,
+            payload: job.payload.clone(),
+            status: PrintJobStatus::Queued,
+            attempts: 0,
+            max_attempts: job.max_attempts,
+            last_error: None,
+            reprint_of: job.reprint_of,
+            reason: job.reason.clone(),
+            requested_by: job.requested_by.clone(),
+            created_at: now,
+            next_attempt_at: now,
+            printed_at: None,
+        };
+        state.print_jobs.insert(job_id, job.clone());
+        Ok(job)
+    }
+
+    async fn get_print_job(&self, job_id: i64) -> Result<Option<PrintJob>> {
+        Ok(self.state().print_jobs.get(&job_id).cloned())
+    }
+
+    async fn list_print_jobs(&self, run_no: Option<i32>, limit: u32) -> Result<Vec<PrintJob>> {
+        Ok(self
+            .state()
+            .print_jobs
+            .values()
+            .rev()
+            .filter(|job| run_no.is_none_or(|run_no| job.run_no == run_no))
+            .take(limit as usize)
+            .cloned()
+            .collect())
+    }
+
+    async fn claim_due_print_job(&self, lease_until: chrono::DateTime<Utc>) -> Result<Option<PrintJob>> {
+        let now = Utc::now();
+        let mut state = self.state();
+        let job = state
+            .print_jobs
+            .values_mut()
+            .filter(|job| {
+                matches!(job.status, PrintJobStatus::Queued | PrintJobStatus::Printing) && job.next_attempt_at <= now
+            })
+            .min_by_key(|job| (job.next_attempt_at, job.job_id));
+        Ok(job.map(|job| {
+            job.status = PrintJobStatus::Printing;
+            job.next_attempt_at = lease_until;
+            job.clone()
+        }))
+    }
+
+    async fn finish_print_attempt(
+        &self,
+        job_id: i64,
+        claimed_until: chrono::DateTime<Utc>,
+        attempt: &PrintAttempt,
+    ) -> Result<bool> {
+        let mut state = self.state();
+        let job = state.print_jobs.get_mut(&job_id).ok_or_else(|| anyhow!("Print job {job_id} not found"))?;
+        if job.status != PrintJobStatus::Printing || job.next_attempt_at != claimed_until {
+            return Ok(false);
+        }
+        let now = Utc::now();
+        job.attempts += 1;
+        match attempt {
+            PrintAttempt::Printed => {
+                job.status = PrintJobStatus::Printed;
+                job.next_attempt_at = now;
+                job.printed_at = Some(now);
+            }
+            PrintAttempt::Retry { error, next_attempt_at } => {
+                job.status = PrintJobStatus::Queued;
+                job.last_error = Some(error.clone());
+                job.next_attempt_at = *next_attempt_at;
+            }
+            PrintAttempt::Failed { error } => {
+                job.status = PrintJobStatus::Failed;
+                job.last_error = Some(error.clone());
+                job.next_attempt_at = now;
+            }
+        }
+        Ok(true)
+    }
+
+    async fn requeue_print_job(&self, job_id: i64) -> Result<bool> {
+        let mut state = self.state();
+        match state.print_jobs.get_mut(&job_id) {
+            Some(job) if job.status == PrintJobStatus::Failed => {
+                job.status = PrintJobStatus::Queued;
+                job.attempts = 0;
+                job.next_attempt_at = Utc::now();
+                Ok(true)
+            }
+            _ => Ok(false),
+        }
+    }
+}
+
+impl PutawayRepository for InMemoryRepository {
+    async fn find_idempotent_response(&self, key: &IdempotencyKey) -> Result<Option<StoredResponse>, PutawayError> {
+        Ok(self.state().stored_response(key))
+    }
+
+    async fn find_lot_by_number(&self, lot_no: &str) -> Result<Option<(LotMasterRecord, ItemMasterRecord)>, PutawayError> {
+        Ok(self
+            .state()
+            .putaway_lots
+            .iter()
+            .find(|(lot, _)| lot.lot_no == lot_no && lot.qty_on_hand > 0.0)
+            .map(|(lot, item)| (lot.clone(), item.clone())))
+    }
+
+    async fn validate_bin_location(&self, location: &str, bin_no: &str) -> Result<bool, PutawayError> {
+        Ok(self.state().bins.iter().any(|(l, b)| l == location && b == bin_no))
+    }
+
+    async fn validate_transfer_request(
+        &self,
+        lot_no: &str,
+        item_key: &str,
+        location: &str,
+        bin_from: &str,
+        bin_to: &str,
+        transfer_qty: f64,
+    ) -> Result<(f64, bool), PutawayError> {
+        let state = self.state();
+        if !state.bins.iter().any(|(l, b)| l == location && b == bin_to) {
+            return Err(PutawayError::InvalidBin { bin_no: bin_to.to_string(), location: location.to_string() });
+        }
+        let (source, _) = state
+            .putaway_lots
+            .iter()
+            .find(|(lot, _)| {
+                lot.lot_no == lot_no && lot.item_key == item_key && lot.location_key == location && lot.bin_no == bin_from
+            })
+            .ok_or_else(|| PutawayError::LotNotFound { lot_no: lot_no.to_string() })?;
+
+        let available = source.qty_on_hand - source.qty_commit_sales;
+        if transfer_qty > available + 0.001 {
+            return Err(PutawayError::InsufficientQuantity { requested: transfer_qty, available });
+        }
+        // Within rounding of the full quantity: move exactly what is there
+        if (available - transfer_qty).abs() <= 0.001 {
+            Ok((available, true))
+        } else {
+            Ok((transfer_qty, false))
+        }
+    }
+
+    async fn execute_bin_transfer_transaction(
+        &self,
+        lot_no: &str,
+        item_key: &str,
+        location: &str,
+        bin_from: &str,
+        bin_to: &str,
+        transfer_qty: f64,
+        _user_id: &str,
+        _remarks: &str,
+        _referenced: &str,
+        message: &str,
+        idempotency_key: Option<&IdempotencyKey>,
+    ) -> Result<TransferResult, PutawayError> {
+        let mut state = self.state();
+        if let Some(key) = idempotency_key {
+            if let Some(stored) = state.stored_response(key) {
+                return Ok(stored.replay(key)?);
+            }
+        }
+        if let Some(error) = state.transfer_failure.take() {
+            return Err(error);
+        }
+        let matches = |lot: &LotMasterRecord, bin: &str| {
+            lot.lot_no == lot_no && lot.item_key == item_key && lot.location_key == location && lot.bin_no == bin
+        };
+
+        let source_index = state
+            .putaway_lots
+            .iter()
+            .position(|(lot, _)| matches(lot, bin_from))
+            .ok_or_else(|| PutawayError::LotNotFound { lot_no: lot_no.to_string() })?;
+        let (source, item) = state.putaway_lots[source_index].clone();
+        state.putaway_lots[source_index].0.qty_on_hand -= transfer_qty;
+        let source_lot_status = state.putaway_lots[source_index].0.lot_status.clone();
+
+        // Consolidate into an existing lot record in the destination bin, or create one
+        let destination_lot_status = match state.putaway_lots.iter_mut().find(|(lot, _)| matches(lot, bin_to)) {
+            Some((destination, _)) => {
+                destination.qty_on_hand += transfer_qty;
+                destination.lot_status.clone()
+            }
+            None => {
+                let destination = LotMasterRecord {
+                    bin_no: bin_to.to_string(),
+                    qty_on_hand: transfer_qty,
+                    ..source.clone()
+                };
+                let status = destination.lot_status.clone();
+                state.putaway_lots.push((destination, item));
+                status
+            }
+        };
+        state.putaway_lots.retain(|(lot, _)| lot.qty_on_hand > 0.0);
+
+        state.next_document_no += 1;
+        let result = TransferResult {
+            success: true,
+            document_no: format!("BT-{:08}", state.next_document_no),
+            message: message.to_string(),
+            timestamp: bangkok_now_rfc3339(),
+            source_lot_status: Some(source_lot_status),
+            destination_lot_status: Some(destination_lot_status),
+        };
+        if let Some(key) = idempotency_key {
+            state.store_response(key, &result);
+        }
+        Ok(result)
+    }
+
+    async fn search_lots_paginated(
+        &self,
+        query: Option<&str>,
+        page: i32,
+        limit: i32,
+    ) -> Result<(Vec<LotSearchItem>, i32), PutawayError> {
+        let state = self.state();
+        let query = query.unwrap_or("").trim();
+        let matching: Vec<_> = state
+            .putaway_lots
+            .iter()
+            .filter(|(lot, _)| query.is_empty() || lot.lot_no.contains(query) || lot.item_key.contains(query))
+            .collect();
+        let total = matching.len() as i32;
+        let items = matching
+            .into_iter()
+            .skip(((page - 1) * limit).max(0) as usize)
+            .take(limit.max(0) as usize)
+            .map(|(lot, item)| LotSearchItem {
+                lot_no: lot.lot_no.clone(),
+                item_key: lot.item_key.clone(),
+                item_description: item.desc1.clone(),
+                location: lot.location_key.clone(),
+                current_bin: lot.bin_no.clone(),
+                qty_on_hand: lot.qty_on_hand,
+                qty_available: lot.qty_on_hand - lot.qty_commit_sales,
+                expiry_date: Some(lot.date_expiry.format("%Y-%m-%d").to_string()),
+                uom: item.stock_uom_code.clone(),
+                lot_status: lot.lot_status.clone(),
+            })
+            .collect();
+        Ok((items, total))
+    }
+
+    async fn search_bins_paginated(
+        &self,
+        query: Option<&str>,
+        page: i32,
+        limit: i32,
+        lot_no: Option<&str>,
+        item_key: Option<&str>,
+        location: Option<&str>,
+    ) -> Result<(Vec<BinSearchItem>, i32), PutawayError> {
+        let state = self.state();
+        let query = query.unwrap_or("").trim();
+        let matching: Vec<_> = state
+            .bins
+            .iter()
+            .filter(|(l, b)| location.is_none_or(|loc| loc == l) && (query.is_empty() || b.contains(query)))
+            .collect();
+        let total = matching.len() as i32;
+        let items = matching
+            .into_iter()
+            .skip(((page - 1) * limit).max(0) as usize)
+            .take(limit.max(0) as usize)
+            .map(|(l, b)| BinSearchItem {
+                bin_no: b.clone(),
+                location: l.clone(),
+                description: String::new(),
+                aisle: String::new(),
+                row: String::new(),
+                rack: String::new(),
+                lot_status: lot_no.and_then(|lot_no| {
+                    state
+                        .putaway_lots
+                        .iter()
+                        .find(|(lot, _)| {
+                            lot.lot_no == lot_no && &lot.bin_no == b && item_key.is_none_or(|key| key == lot.item_key)
+                        })
+                        .map(|(lot, _)| lot.lot_status.clone())
+                }),
+            })
+            .collect();
+        Ok((items, total))
+    }
+
+    async fn get_active_remarks(&self) -> Result<Vec<serde_json::Value>, PutawayError> {
+        Ok(vec![serde_json::json!({ "id": 1, "remark": "Putaway" })])
+    }
+}
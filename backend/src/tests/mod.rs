@@ -1,2 +1,4 @@
 pub mod bulk_runs_tests;
+pub mod in_memory_repository;
+pub mod repository_tests;
 pub mod validation_tests;
\ No newline at end of file
@@ -0,0 +1,1155 @@
+//! Service-layer tests against `InMemoryRepository`. They cover the services' own rules
+//! and orchestration; the SQL pick, unpick and completion transactions are only faked.
+
+#[cfg(test)]
+mod tests {
+    use bigdecimal::BigDecimal;
+
+    use crate::database::repository::{BulkRunRepository, PrintJobRepository};
+    use crate::models::bulk_runs::{
+        BulkRunError, LotAllocation, PickConfirmationRequest, QueuedPick, SyncOutcome, UnpickRequest,
+    };
+    use crate::models::idempotency::{IdempotencyKey, IdempotentOperation};
+    use crate::models::pallet_labels::SsccConfig;
+    use crate::models::pick_plan::PlanStepStatus;
+    use crate::models::pick_scans::ScanVerifyPolicy;
+    use crate::models::pick_sequence::PickSequence;
+    use crate::models::print_jobs::{
+        LabelKind, PrintAttempt, PrintError, PrintJobStatus, PrintLabelsRequest, PrintPalletRequest, PrinterLanguage,
+        RegisterPrinterRequest, ReprintRequest,
+    };
+    use crate::models::putaway_models::{BinTransferRequest, PutawayError};
+    use crate::models::run_claims::ClaimRequest;
+    use crate::models::scans::{MismatchKind, RegisterGtinRequest, ScanError};
+    use crate::services::bulk_picking_validation::{
+        BulkPickingValidationError, BulkPickingValidationService, PickValidationRequest,
+    };
+    use crate::services::bulk_runs_service::BulkRunsService;
+    use crate::services::print_queue::PrintQueueService;
+    use crate::services::putaway_service::PutawayService;
+    use crate::services::run_events::{RunEventBus, RunStreamItem};
+    use crate::services::scan_resolver::ScanService;
+    use crate::tests::in_memory_repository::InMemoryRepository;
+    use crate::utils::gs1::{parse_sscc, sscc};
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+    use tokio_stream::StreamExt;
+
+    const RUN: i32 = 215235;
+
+    /// Run with two ingredients: INSOYF01 over two pallets (2 + 1 bags), INSALT02 on one (1 bag)
+    fn seeded_repository() -> InMemoryRepository {
+        let repo = InMemoryRepository::new();
+        repo.add_run(RUN, "TB317-01", 2);
+        repo.add_batch(RUN, 1, 1, "INSOYF01", 2, 20);
+        repo.add_batch(RUN, 2, 1, "INSOYF01", 1, 20);
+        repo.add_batch(RUN, 1, 2, "INSALT02", 1, 25);
+        repo.add_lot("INSOYF01", "2510601", "A0101-1A", 100, 20, 30);
+        repo.add_lot("INSALT02", "2510602", "A0202-1A", 50, 25, 60);
+        repo
+    }
+
+    fn pick(row_num: i32, line_id: i32, bags: i32, lot_no: &str, bin_no: &str) -> PickConfirmationRequest {
+        PickConfirmationRequest {
+            row_num,
+            line_id,
+            picked_bulk_qty: BigDecimal::from(bags),
+            lot_no: lot_no.to_string(),
+            bin_no: bin_no.to_string(),
+            allocations: Vec::new(),
+            user_id: Some("deachawat".to_string()),
+            idempotency_key: None,
+            override_claim: false,
+            scanned_bin: None,
+            scanned_lot: None,
+        }
+    }
+
+    async fn pick_everything(service: &BulkRunsService<InMemoryRepository>) {
+        for request in [
+            pick(1, 1, 2, "2510601", "A0101-1A"),
+            pick(2, 1, 1, "2510601", "A0101-1A"),
+            pick(1, 2, 1, "2510602", "A0202-1A"),
+        ] {
+            service.confirm_pick_transaction(RUN, request).await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_confirm_pick_commits_lot_and_advances_pallet() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+
+        let response = service
+            .confirm_pick_transaction(RUN, pick(1, 1, 2, "2510601", "A0101-1A"))
+            .await
+            .unwrap();
+
+        assert!(response.success);
+        assert_eq!(response.updated_records.total_committed_qty, BigDecimal::from(40));
+        assert_eq!(repo.lot_available("2510601", "A0101-1A"), Some(BigDecimal::from(60)));
+        assert_eq!(repo.picks()[0].user_id, "deachawat");
+        assert!(service.is_pallet_completed(RUN, 1, 1).await.unwrap());
+
+        let next = service.get_next_available_pallet(RUN, 1, 1).await.unwrap().unwrap();
+        assert_eq!(next.row_num, 2);
+        assert_eq!(next.pallet_number, 2);
+    }
+
+    fn with_key<T: serde::Serialize>(key: &str, operation: IdempotentOperation, request: &T) -> Option<IdempotencyKey> {
+        Some(IdempotencyKey::new(key.to_string(), operation, "deachawat", request))
+    }
+
+    #[tokio::test]
+    async fn test_confirm_pick_replays_idempotency_key() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        let mut request = pick(1, 1, 2, "2510601", "A0101-1A");
+        request.idempotency_key = with_key("pick-1", IdempotentOperation::ConfirmPick, &request);
+
+        let first = service.confirm_pick_transaction(RUN, request.clone()).await.unwrap();
+        // The batch is now complete, so only a replay can succeed
+        let replay = service.confirm_pick_transaction(RUN, request).await.unwrap();
+
+        assert_eq!(replay.document_no, first.document_no);
+        assert_eq!(replay.transaction_id, first.transaction_id);
+        assert_eq!(repo.picks().len(), 1);
+        assert_eq!(repo.lot_available("2510601", "A0101-1A"), Some(BigDecimal::from(60)));
+
+        let mut reused = pick(2, 1, 1, "2510601", "A0101-1A");
+        reused.idempotency_key = with_key("pick-1", IdempotentOperation::ConfirmPick, &reused);
+        assert!(matches!(
+            service.confirm_pick_transaction(RUN, reused).await,
+            Err(BulkRunError::IdempotencyKeyReused)
+        ));
+    }
+
+    fn queued(client_id: &str, run_no: i32, row_num: i32, line_id: i32, bags: i32, lot_no: &str, bin_no: &str) -> QueuedPick {
+        QueuedPick {
+            client_id: client_id.to_string(),
+            run_no,
+            row_num,
+            line_id,
+            picked_bulk_qty: BigDecimal::from(bags),
+            lot_no: lot_no.to_string(),
+            bin_no: bin_no.to_string(),
+            allocations: Vec::new(),
+            scanned_bin: None,
+            scanned_lot: None,
+            captured_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_offline_sync_reports_conflicts_per_pick() {
+        let repo = seeded_repository();
+        repo.add_lot("INSALT02", "2510603", "A0303-1A", 10, 25, 90);
+        let service = BulkRunsService::new(repo.clone());
+
+        let sync = service
+            .sync_offline_picks(
+                "deachawat",
+                vec![
+                    queued("q1", RUN, 1, 1, 2, "2510601", "A0101-1A"),
+                    queued("q2", RUN, 1, 1, 1, "2510601", "A0101-1A"),
+                    queued("q3", RUN, 1, 2, 1, "2510603", "A0303-1A"),
+                    queued("q4", 999999, 1, 1, 1, "2510601", "A0101-1A"),
+                ],
+            )
+            .await;
+
+        let outcomes: Vec<_> = sync.results.iter().map(|r| (r.outcome, r.code.as_deref())).collect();
+        assert_eq!(
+            outcomes,
+            vec![
+                (SyncOutcome::Applied, None),
+                (SyncOutcome::Conflict, Some("BATCH_COMPLETED")),
+                (SyncOutcome::Conflict, Some("LOT_EXHAUSTED")),
+                (SyncOutcome::Conflict, Some("RUN_NOT_FOUND")),
+            ]
+        );
+        assert_eq!((sync.applied, sync.conflicts), (1, 3));
+        assert_eq!(repo.lot_available("2510601", "A0101-1A"), Some(BigDecimal::from(60)));
+
+        // Re-sending a queue whose acknowledgement was lost does not pick twice
+        let resend = service
+            .sync_offline_picks("deachawat", vec![queued("q1", RUN, 1, 1, 2, "2510601", "A0101-1A")])
+            .await;
+        assert_eq!(resend.results[0].outcome, SyncOutcome::AlreadyApplied);
+        assert_eq!(
+            resend.results[0].response.as_ref().unwrap().document_no,
+            sync.results[0].response.as_ref().unwrap().document_no
+        );
+        assert_eq!(repo.picks().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_offline_sync_rejects_picks_for_a_closed_run() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        pick_everything(&service).await;
+        service.complete_run_status(RUN, "deachawat").await.unwrap();
+
+        let sync = service
+            .sync_offline_picks("deachawat", vec![queued("late-1", RUN, 1, 1, 1, "2510601", "A0101-1A")])
+            .await;
+        assert_eq!(sync.results[0].outcome, SyncOutcome::Conflict);
+        assert_eq!(sync.results[0].code.as_deref(), Some("RUN_NOT_OPEN"));
+    }
+
+    #[tokio::test]
+    async fn test_run_events_follow_committed_changes() {
+        let repo = seeded_repository();
+        let events = RunEventBus::default();
+        let mut stream = Box::pin(events.subscribe(RUN));
+        let service = BulkRunsService::new(repo.clone()).with_events(events);
+
+        pick_everything(&service).await;
+        // Rejected picks publish nothing
+        assert!(service.confirm_pick_transaction(RUN, pick(1, 2, 1, "2510602", "A0202-1A")).await.is_err());
+        service.complete_run_status(RUN, "deachawat").await.unwrap();
+        service.revert_bulk_run_status(RUN, "deachawat").await.unwrap();
+        let request = UnpickRequest { lot_no: Some("2510602".to_string()), lot_tran_no: None };
+        service.unpick_ingredient(RUN, 1, 2, &request, "deachawat").await.unwrap();
+
+        let mut names = Vec::new();
+        while names.len() < 11 {
+            match stream.next().await {
+                Some(RunStreamItem::Event(event)) => names.push(event.kind.name()),
+                other => panic!("unexpected stream item: {other:?}"),
+            }
+        }
+        assert_eq!(
+            names,
+            vec![
+                "pick",
+                "pallet_completed",
+                "pick",
+                "pallet_completed",
+                "ingredient_completed",
+                "pick",
+                "pallet_completed",
+                "ingredient_completed",
+                "run_status_changed",
+                "run_status_changed",
+                "unpick",
+            ]
+        );
+    }
+
+    fn claim_line(line_id: i32) -> ClaimRequest {
+        ClaimRequest { line_id: Some(line_id), take_over: false }
+    }
+
+    #[tokio::test]
+    async fn test_claimed_line_rejects_other_pickers() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        service.claim_run(RUN, "picker01", &claim_line(1), false).await.unwrap();
+
+        // Renewing keeps the claim with the same holder
+        let renewed = service.claim_run(RUN, "picker01", &claim_line(1), false).await.unwrap();
+        assert_eq!(renewed.username, "picker01");
+
+        let err = service.confirm_pick_transaction(RUN, pick(1, 1, 1, "2510601", "A0101-1A")).await.unwrap_err();
+        assert!(matches!(&err, BulkRunError::ClaimedByOther { holder, line_id: Some(1), .. } if holder == "picker01"));
+        assert_eq!(err.error_code(), "RUN_CLAIMED");
+        assert!(repo.picks().is_empty());
+
+        // Other lines stay open, but the whole run cannot be claimed over picker01
+        service.confirm_pick_transaction(RUN, pick(1, 2, 1, "2510602", "A0202-1A")).await.unwrap();
+        let err = service.claim_run(RUN, "deachawat", &ClaimRequest::default(), false).await.unwrap_err();
+        assert!(matches!(err, BulkRunError::ClaimedByOther { .. }));
+
+        let runs = service.list_active_bulk_runs_paginated(1, 10).await.unwrap().runs;
+        assert_eq!(runs[0].claims.len(), 1);
+        assert_eq!(runs[0].claims[0].username, "picker01");
+
+        // A supervisor override goes through
+        let mut overridden = pick(1, 1, 2, "2510601", "A0101-1A");
+        overridden.override_claim = true;
+        service.confirm_pick_transaction(RUN, overridden).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_claim_take_over_and_lease_expiry() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        service.claim_run(RUN, "picker01", &ClaimRequest::default(), false).await.unwrap();
+
+        let take_over = ClaimRequest { line_id: None, take_over: true };
+        assert!(matches!(
+            service.claim_run(RUN, "picker02", &take_over, false).await,
+            Err(BulkRunError::ClaimOverrideForbidden)
+        ));
+        service.claim_run(RUN, "supervisor", &take_over, true).await.unwrap();
+        assert_eq!(service.get_run_claims(RUN).await.unwrap()[0].username, "supervisor");
+
+        // Only the holder (or a supervisor) can release
+        assert!(!service.release_claim(RUN, None, "picker01", false).await.unwrap());
+
+        repo.expire_claims();
+        assert!(service.get_run_claims(RUN).await.unwrap().is_empty());
+        let claim = service.claim_run(RUN, "picker01", &ClaimRequest::default(), false).await.unwrap();
+        assert_eq!((claim.username.as_str(), claim.line_id), ("picker01", None));
+        assert!(service.release_claim(RUN, None, "picker01", false).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pick_transaction_rechecks_claim_taken_after_service_check() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+
+        // A supervisor takes the line over between the service's claim check and the commit
+        service.claim_run(RUN, "supervisor", &claim_line(1), true).await.unwrap();
+        let err = repo.confirm_pick_transaction(RUN, &pick(1, 1, 1, "2510601", "A0101-1A")).await.unwrap_err();
+        assert!(matches!(&err, BulkRunError::ClaimedByOther { holder, .. } if holder == "supervisor"));
+        assert!(repo.picks().is_empty());
+
+        let mut overridden = pick(1, 1, 1, "2510601", "A0101-1A");
+        overridden.override_claim = true;
+        repo.confirm_pick_transaction(RUN, &overridden).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_offline_sync_reports_claimed_lines() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        service.claim_run(RUN, "picker01", &claim_line(1), false).await.unwrap();
+
+        let sync = service
+            .sync_offline_picks("deachawat", vec![queued("q1", RUN, 1, 1, 1, "2510601", "A0101-1A")])
+            .await;
+        assert_eq!(sync.results[0].outcome, SyncOutcome::Conflict);
+        assert_eq!(sync.results[0].code.as_deref(), Some("RUN_CLAIMED"));
+    }
+
+    #[tokio::test]
+    async fn test_confirm_pick_rejects_over_pick_and_wrong_lot() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+
+        let over_pick = service
+            .confirm_pick_transaction(RUN, pick(1, 1, 3, "2510601", "A0101-1A"))
+            .await
+            .unwrap_err();
+        assert!(over_pick.to_string().starts_with("Validation failed"));
+        assert_eq!(over_pick.error_code(), "VALIDATION_ERROR");
+
+        let wrong_lot = service
+            .confirm_pick_transaction(RUN, pick(1, 1, 1, "2510602", "A0202-1A"))
+            .await
+            .unwrap_err();
+        assert!(wrong_lot.to_string().contains("not valid for ingredient INSOYF01"));
+
+        assert!(repo.picks().is_empty());
+        assert_eq!(repo.lot_available("2510601", "A0101-1A"), Some(BigDecimal::from(100)));
+    }
+
+    /// Pick of row 1 / line 1 split across several INSOYF01 lots
+    fn split_pick(allocations: &[(&str, &str, i32)]) -> PickConfirmationRequest {
+        let mut request = pick(1, 1, 0, "", "");
+        request.allocations = allocations
+            .iter()
+            .map(|&(lot_no, bin_no, bags)| LotAllocation {
+                lot_no: lot_no.to_string(),
+                bin_no: bin_no.to_string(),
+                bags: BigDecimal::from(bags),
+                scanned_bin: None,
+                scanned_lot: None,
+            })
+            .collect();
+        request
+    }
+
+    #[tokio::test]
+    async fn test_split_pick_commits_every_lot_in_one_transaction() {
+        let repo = seeded_repository();
+        repo.add_lot("INSOYF01", "2510604", "A0404-1A", 20, 20, 10);
+        let service = BulkRunsService::new(repo.clone());
+
+        let response = service
+            .confirm_pick_transaction(RUN, split_pick(&[("2510604", "A0404-1A", 1), ("2510601", "A0101-1A", 1)]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.allocations.len(), 2);
+        assert_eq!(response.allocations[0].lot_no, "2510604");
+        assert_eq!(response.transaction_id, Some(response.allocations[0].lot_tran_no));
+        assert_eq!(response.updated_records.total_committed_qty, BigDecimal::from(40));
+        assert_eq!(repo.picks().len(), 2);
+        assert_eq!(repo.lot_available("2510604", "A0404-1A"), Some(BigDecimal::from(0)));
+        assert_eq!(repo.lot_available("2510601", "A0101-1A"), Some(BigDecimal::from(80)));
+        assert!(service.is_pallet_completed(RUN, 1, 1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_scan_verification_rejects_wrong_bag_and_records_exceptions() {
+        let repo = seeded_repository();
+        repo.set_batch_location(RUN, 1, 1, "WHSCAN");
+        let service = BulkRunsService::new(repo.clone()).with_scan_policy(ScanVerifyPolicy::parse("WHSCAN"));
+        let scanned = |row_num, line_id, lot_no: &str, bin_no: &str, scanned_lot: Option<&str>, scanned_bin: Option<&str>| {
+            let mut request = pick(row_num, line_id, 1, lot_no, bin_no);
+            request.scanned_lot = scanned_lot.map(str::to_string);
+            request.scanned_bin = scanned_bin.map(str::to_string);
+            request
+        };
+
+        let missing = service
+            .confirm_pick_transaction(RUN, scanned(1, 1, "2510601", "A0101-1A", None, Some("A0101-1A")))
+            .await
+            .unwrap_err();
+        assert_eq!(missing.error_code(), "SCAN_REQUIRED");
+
+        let wrong_bag = service
+            .confirm_pick_transaction(RUN, scanned(1, 1, "2510601", "A0101-1A", Some("2510602"), Some("A0101-1A")))
+            .await
+            .unwrap_err();
+        assert_eq!(wrong_bag.error_code(), "WRONG_LOT_SCANNED");
+        assert_eq!(wrong_bag.status_code(), axum::http::StatusCode::UNPROCESSABLE_ENTITY);
+        let wrong_bin = service
+            .confirm_pick_transaction(RUN, scanned(1, 1, "2510601", "A0101-1A", Some("2510601"), Some("A0202-1A")))
+            .await
+            .unwrap_err();
+        assert_eq!(wrong_bin.error_code(), "WRONG_BIN_SCANNED");
+        assert!(repo.picks().is_empty());
+
+        // The bag's GS1 label carries the lot in AI (10)
+        let label = "]d20109501101020917102510601\u{1d}17270300";
+        service
+            .confirm_pick_transaction(RUN, scanned(1, 1, "2510601", "A0101-1A", Some(label), Some("a0101-1a")))
+            .await
+            .unwrap();
+
+        // TFC1 does not require scans, but the ones sent are still checked
+        service
+            .confirm_pick_transaction(RUN, scanned(1, 2, "2510602", "A0202-1A", None, None))
+            .await
+            .unwrap();
+        let unrequired = service
+            .confirm_pick_transaction(RUN, scanned(2, 1, "2510601", "A0101-1A", Some("2510602"), None))
+            .await
+            .unwrap_err();
+        assert_eq!(unrequired.error_code(), "WRONG_LOT_SCANNED");
+        assert_eq!(repo.picks().len(), 2);
+
+        let exceptions = service.get_scan_exceptions(RUN).await.unwrap();
+        let codes: Vec<_> = exceptions.iter().map(|e| (e.row_num, e.error_code.as_str())).collect();
+        assert_eq!(codes, vec![(1, "WRONG_LOT_SCANNED"), (1, "WRONG_BIN_SCANNED"), (2, "WRONG_LOT_SCANNED")]);
+        assert_eq!(exceptions[0].scanned_lot.as_deref(), Some("2510602"));
+        assert_eq!(exceptions[0].user_id, "deachawat");
+    }
+
+    #[tokio::test]
+    async fn test_split_pick_is_all_or_nothing() {
+        let repo = seeded_repository();
+        repo.add_lot("INSOYF01", "2510604", "A0404-1A", 10, 20, 10);
+        repo.add_lot("INSOYF01", "2510605", "A0505-1A", 100, 20, 40);
+        let service = BulkRunsService::new(repo.clone());
+
+        // Validation catches the short lot before anything is written
+        let short = service
+            .confirm_pick_transaction(RUN, split_pick(&[("2510601", "A0101-1A", 1), ("2510604", "A0404-1A", 1)]))
+            .await
+            .unwrap_err();
+        assert_eq!(short.error_code(), "VALIDATION_ERROR");
+
+        // And the transaction itself rolls back every lot if one fails
+        let mut request = split_pick(&[("2510601", "A0101-1A", 1), ("2510604", "A0404-1A", 1)]);
+        request.normalize_allocations().unwrap();
+        assert!(matches!(
+            repo.confirm_pick_transaction(RUN, &request).await,
+            Err(BulkRunError::TransactionRolledBack(_))
+        ));
+
+        // Together the lots must still fit the batch
+        let over_pick = service
+            .confirm_pick_transaction(RUN, split_pick(&[("2510601", "A0101-1A", 2), ("2510605", "A0505-1A", 1)]))
+            .await
+            .unwrap_err();
+        assert!(over_pick.to_string().contains("across 2 lots"));
+
+        let duplicate = service
+            .confirm_pick_transaction(RUN, split_pick(&[("2510601", "A0101-1A", 1), ("2510601", "A0101-1A", 1)]))
+            .await
+            .unwrap_err();
+        assert!(duplicate.to_string().contains("allocated more than once"));
+
+        assert!(repo.picks().is_empty());
+        assert_eq!(repo.lot_available("2510601", "A0101-1A"), Some(BigDecimal::from(100)));
+        assert_eq!(repo.lot_available("2510604", "A0404-1A"), Some(BigDecimal::from(10)));
+        assert!(!service.is_pallet_completed(RUN, 1, 1).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_completion_requires_every_ingredient() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+
+        service
+            .confirm_pick_transaction(RUN, pick(1, 1, 2, "2510601", "A0101-1A"))
+            .await
+            .unwrap();
+        let status = service.get_run_completion_status(RUN).await.unwrap();
+        assert!(!status.is_complete);
+        assert_eq!((status.completed_count, status.total_ingredients), (0, 2));
+
+        let err = service.complete_run_status(RUN, "deachawat").await.unwrap_err();
+        assert!(err.to_string().contains("still has 2 incomplete ingredients"));
+        assert!(matches!(err, BulkRunError::RunIncomplete { incomplete: 2, .. }));
+        assert!(!service.is_run_complete(RUN).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_pick_plan_follows_fefo_and_tracks_picks() {
+        let repo = seeded_repository();
+        // Expires first, and holds exactly the two bags of pallet 1
+        repo.add_lot("INSOYF01", "2510604", "A0404-1A", 40, 20, 5);
+        repo.add_batch(RUN, 1, 3, "INFLOUR1", 2, 25);
+        let service = BulkRunsService::new(repo.clone());
+
+        let plan = service.generate_pick_plan(RUN, "deachawat").await.unwrap();
+        let steps: Vec<_> = plan
+            .steps
+            .iter()
+            .map(|s| (s.row_num, s.line_id, s.lot_no.as_str(), s.bags.clone()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (1, 1, "2510604", BigDecimal::from(2)),
+                (2, 1, "2510601", BigDecimal::from(1)),
+                (1, 2, "2510602", BigDecimal::from(1)),
+            ]
+        );
+        assert_eq!(plan.shortages.len(), 1);
+        assert_eq!(plan.shortages[0].item_key, "INFLOUR1");
+        assert_eq!(plan.shortages[0].short_kg, BigDecimal::from(50));
+
+        service
+            .confirm_pick_transaction(RUN, pick(1, 1, 2, "2510604", "A0404-1A"))
+            .await
+            .unwrap();
+        let stored = service.get_pick_plan(RUN).await.unwrap().unwrap();
+        assert_eq!(stored.steps[0].status, PlanStepStatus::Picked);
+        assert_eq!(stored.next_step().map(|s| s.step_no), Some(2));
+        assert_eq!(stored.pending_steps(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_pick_plan_needs_an_open_run() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        pick_everything(&service).await;
+        service.complete_run_status(RUN, "deachawat").await.unwrap();
+
+        assert!(matches!(
+            service.generate_pick_plan(RUN, "deachawat").await,
+            Err(BulkRunError::InvalidRunStatus { .. })
+        ));
+        assert!(matches!(
+            service.generate_pick_plan(999999, "deachawat").await,
+            Err(BulkRunError::RunNotFound { .. })
+        ));
+        assert!(service.get_pick_plan(RUN).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_pick_plan_preview_reports_shortages_without_storing() {
+        let repo = seeded_repository();
+        repo.add_batch(RUN, 1, 3, "INFLOUR1", 2, 25);
+        let service = BulkRunsService::new(repo.clone());
+
+        let preview = service.preview_pick_plan(RUN).await.unwrap();
+        assert!(!preview.can_fulfil);
+        let flour = preview.ingredients.iter().find(|i| i.item_key == "INFLOUR1").unwrap();
+        assert_eq!(flour.required_kg, BigDecimal::from(50));
+        assert_eq!(flour.available_kg, BigDecimal::from(0));
+        assert_eq!(flour.short_bags, BigDecimal::from(2));
+        assert!(preview
+            .ingredients
+            .iter()
+            .filter(|i| i.item_key != "INFLOUR1")
+            .all(|i| i.short_bags == BigDecimal::from(0)));
+
+        let csv = preview.to_csv();
+        assert_eq!(csv.lines().count(), 2);
+        assert!(csv.lines().nth(1).unwrap().starts_with(&format!("{RUN},3,INFLOUR1,")));
+
+        // A preview is never stored as the run's plan
+        assert!(service.get_pick_plan(RUN).await.unwrap().is_none());
+        assert!(matches!(
+            service.preview_pick_plan(999999).await,
+            Err(BulkRunError::RunNotFound { .. })
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_walk_path_sequence_orders_by_fefo_bin() {
+        let repo = seeded_repository();
+        repo.add_batch(RUN, 1, 3, "INFLOUR1", 2, 25);
+        repo.add_lot("INFLOUR1", "2510603", "K0802-4B", 100, 25, 90);
+        repo.add_bin_location("A0101-1A", "01", "01", "1A");
+        repo.add_bin_location("A0202-1A", "02", "02", "1A");
+        repo.add_bin_location("K0802-4B", "08", "02", "4B");
+        let service = BulkRunsService::new(repo.clone());
+
+        let by_line: Vec<_> = service
+            .search_run_items(RUN, PickSequence::Line)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.item_key)
+            .collect();
+        assert_eq!(by_line, vec!["INSOYF01", "INSALT02", "INFLOUR1"]);
+
+        // Zone K is walked before zone A
+        let by_walk: Vec<_> = service
+            .search_run_items(RUN, PickSequence::WalkPath)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|item| item.item_key)
+            .collect();
+        assert_eq!(by_walk, vec!["INFLOUR1", "INSOYF01", "INSALT02"]);
+
+        // Indexes follow the same sequence (one row per batch)
+        assert_eq!(service.get_ingredient_index(RUN, "INFLOUR1", PickSequence::WalkPath).await.unwrap(), 0);
+        assert_eq!(service.get_ingredient_index(RUN, "INSALT02", PickSequence::WalkPath).await.unwrap(), 3);
+        assert_eq!(service.get_ingredient_index(RUN, "INSALT02", PickSequence::Line).await.unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_complete_and_revert_run_status() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        pick_everything(&service).await;
+
+        assert!(service.get_run_completion_status(RUN).await.unwrap().is_complete);
+        let update = service.complete_run_status(RUN, "deachawat").await.unwrap();
+        assert_eq!((update.old_status.as_str(), update.new_status.as_str()), ("NEW", "PRINT"));
+
+        // Completing twice is refused once the run has left NEW
+        assert!(matches!(
+            service.complete_run_status(RUN, "deachawat").await,
+            Err(BulkRunError::InvalidRunStatus { .. })
+        ));
+        let print = service.update_print_status(RUN, "deachawat").await.unwrap();
+        assert_eq!(print.new_status, "PRINT");
+
+        let reverted = service.revert_bulk_run_status(RUN, "deachawat").await.unwrap().unwrap();
+        assert_eq!(reverted.status, "NEW");
+        assert!(service.revert_bulk_run_status(RUN, "deachawat").await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_batch_labels_cover_picked_batches() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        assert!(matches!(
+            service.get_batch_labels(RUN, None).await,
+            Err(BulkRunError::NothingToPrint { .. })
+        ));
+
+        pick_everything(&service).await;
+        let labels = service.get_batch_labels(RUN, None).await.unwrap();
+        assert_eq!(labels.len(), 2);
+        assert_eq!(labels[0].page_info(), "Page 1 of 2");
+        assert_eq!(
+            labels[0].lines.iter().map(|line| line.item_key.as_str()).collect::<Vec<_>>(),
+            vec!["INSOYF01", "INSALT02"]
+        );
+        assert_eq!(labels[0].lines[0].qty, BigDecimal::from(40));
+        assert_eq!(labels[0].picked_by, "deachawat");
+
+        let second = service.get_batch_labels(RUN, Some(&labels[1].batch_no)).await.unwrap();
+        assert_eq!(second.len(), 1);
+        assert_eq!(second[0].page_info(), "Page 2 of 2");
+        assert!(matches!(
+            service.get_batch_labels(RUN + 1, None).await,
+            Err(BulkRunError::RunNotFound { .. })
+        ));
+    }
+
+    fn printer(printer_id: &str, port: u16, language: PrinterLanguage, is_default: bool) -> RegisterPrinterRequest {
+        RegisterPrinterRequest {
+            printer_id: printer_id.to_string(),
+            name: String::new(),
+            host: "127.0.0.1".to_string(),
+            port,
+            language,
+            is_default,
+            active: true,
+        }
+    }
+
+    /// A local listener standing in for a printer's raw 9100 port; yields each connection's bytes
+    async fn fake_printer() -> (u16, tokio::task::JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let received = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut payload = String::new();
+            socket.read_to_string(&mut payload).await.unwrap();
+            payload
+        });
+        (port, received)
+    }
+
+    #[tokio::test]
+    async fn test_print_status_with_printer_sends_labels() {
+        let repo = seeded_repository();
+        pick_everything(&BulkRunsService::new(repo.clone())).await;
+        let queue = PrintQueueService::new(repo.clone());
+        let request = PrintLabelsRequest::default();
+        assert!(matches!(
+            queue.print_run_labels(RUN, &request, "deachawat").await,
+            Err(PrintError::NoDefaultPrinter)
+        ));
+
+        let (port, received) = fake_printer().await;
+        queue
+            .register_printer(printer("ZB-DOCK1", port, PrinterLanguage::Zpl, true), "supervisor")
+            .await
+            .unwrap();
+        let (job, status) = queue.print_run_labels(RUN, &request, "deachawat").await.unwrap();
+        assert_eq!(job.status, PrintJobStatus::Queued);
+        assert_eq!(job.label_count, 2);
+        assert_eq!(status.new_status, "PRINT");
+        assert_eq!(repo.get_bulk_run(RUN).await.unwrap().unwrap().status, "PRINT");
+
+        let processed = queue.process_due_jobs().await.unwrap();
+        assert_eq!(processed.len(), 1);
+        assert_eq!(processed[0].status, PrintJobStatus::Printed);
+        let payload = received.await.unwrap();
+        assert_eq!(payload, job.payload);
+        assert_eq!(payload.matches("^XA").count(), 2);
+
+        let stored = queue.get_job(job.job_id).await.unwrap();
+        assert_eq!((stored.status, stored.attempts), (PrintJobStatus::Printed, 1));
+        assert!(stored.printed_at.is_some());
+        assert!(queue.process_due_jobs().await.unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_unreachable_printer_is_retried_until_failed() {
+        let repo = seeded_repository();
+        pick_everything(&BulkRunsService::new(repo.clone())).await;
+        let queue = PrintQueueService::new(repo.clone());
+
+        // A port nothing listens on any more refuses the connection
+        let closed_port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+        queue
+            .register_printer(printer("ZB-DOCK1", closed_port, PrinterLanguage::Zpl, true), "supervisor")
+            .await
+            .unwrap();
+        let job = queue.queue_run_labels(RUN, &PrintLabelsRequest::default(), "deachawat").await.unwrap();
+
+        let processed = queue.process_due_jobs().await.unwrap();
+        assert_eq!(processed[0].status, PrintJobStatus::Queued);
+        assert!(processed[0].last_error.is_some());
+        // Not due again until the retry delay has passed
+        assert!(queue.process_due_jobs().await.unwrap().is_empty());
+        assert!(matches!(queue.retry(job.job_id).await, Err(PrintError::JobNotRetryable { .. })));
+
+        for _ in 1..job.max_attempts {
+            repo.make_print_jobs_due();
+            queue.process_due_jobs().await.unwrap();
+        }
+        let failed = queue.get_job(job.job_id).await.unwrap();
+        assert_eq!((failed.status, failed.attempts), (PrintJobStatus::Failed, job.max_attempts));
+
+        // Once the printer is reachable again, a retry sends it
+        let (port, received) = fake_printer().await;
+        queue
+            .register_printer(printer("ZB-DOCK1", port, PrinterLanguage::Zpl, true), "supervisor")
+            .await
+            .unwrap();
+        let requeued = queue.retry(job.job_id).await.unwrap();
+        assert_eq!((requeued.status, requeued.attempts), (PrintJobStatus::Queued, 0));
+        assert_eq!(queue.process_due_jobs().await.unwrap()[0].status, PrintJobStatus::Printed);
+        assert_eq!(received.await.unwrap(), job.payload);
+    }
+
+    #[tokio::test]
+    async fn test_print_attempt_after_lost_claim_is_not_recorded() {
+        let repo = seeded_repository();
+        pick_everything(&BulkRunsService::new(repo.clone())).await;
+        let queue = PrintQueueService::new(repo.clone());
+        queue
+            .register_printer(printer("ZB-DOCK1", 9100, PrinterLanguage::Zpl, true), "supervisor")
+            .await
+            .unwrap();
+        let job = queue.queue_run_labels(RUN, &PrintLabelsRequest::default(), "deachawat").await.unwrap();
+
+        let slow = repo.claim_due_print_job(chrono::Utc::now() + chrono::Duration::seconds(50)).await.unwrap().unwrap();
+        assert!(repo.claim_due_print_job(chrono::Utc::now() + chrono::Duration::seconds(50)).await.unwrap().is_none());
+
+        // The slow worker's lease runs out and another worker takes the job over
+        repo.make_print_jobs_due();
+        let current = repo.claim_due_print_job(chrono::Utc::now() + chrono::Duration::seconds(60)).await.unwrap().unwrap();
+        assert_eq!(current.job_id, job.job_id);
+
+        assert!(!repo.finish_print_attempt(job.job_id, slow.next_attempt_at, &PrintAttempt::Printed).await.unwrap());
+        assert_eq!(queue.get_job(job.job_id).await.unwrap().attempts, 0);
+        assert!(repo.finish_print_attempt(job.job_id, current.next_attempt_at, &PrintAttempt::Printed).await.unwrap());
+        let stored = queue.get_job(job.job_id).await.unwrap();
+        assert_eq!((stored.status, stored.attempts), (PrintJobStatus::Printed, 1));
+    }
+
+    #[tokio::test]
+    async fn test_reprint_requires_reason_and_matching_language() {
+        let repo = seeded_repository();
+        pick_everything(&BulkRunsService::new(repo.clone())).await;
+        let queue = PrintQueueService::new(repo.clone());
+        queue
+            .register_printer(printer("ZB-DOCK1", 9100, PrinterLanguage::Zpl, true), "supervisor")
+            .await
+            .unwrap();
+        queue
+            .register_printer(printer("EL-OFFICE", 9100, PrinterLanguage::Epl, false), "supervisor")
+            .await
+            .unwrap();
+        let request = PrintLabelsRequest { printer_id: None, batch_no: Some(format!("{RUN}-2")) };
+        let original = queue.queue_run_labels(RUN, &request, "deachawat").await.unwrap();
+        assert_eq!(original.label_count, 1);
+
+        let reprint = |reason: &str, printer_id: Option<&str>| ReprintRequest {
+            reason: reason.to_string(),
+            printer_id: printer_id.map(str::to_string),
+        };
+        assert!(matches!(
+            queue.reprint(original.job_id, &reprint("  ", None), "supervisor").await,
+            Err(PrintError::Validation(_))
+        ));
+        assert!(matches!(
+            queue.reprint(original.job_id, &reprint("Label damaged", Some("EL-OFFICE")), "supervisor").await,
+            Err(PrintError::LanguageMismatch { .. })
+        ));
+        assert!(matches!(
+            queue.reprint(original.job_id + 10, &reprint("Label damaged", None), "supervisor").await,
+            Err(PrintError::JobNotFound { .. })
+        ));
+
+        let copy = queue.reprint(original.job_id, &reprint("Label damaged", None), "supervisor").await.unwrap();
+        assert_eq!(copy.reprint_of, Some(original.job_id));
+        assert_eq!(copy.reason.as_deref(), Some("Label damaged"));
+        assert_eq!(copy.batch_no, original.batch_no);
+        assert_eq!(copy.payload, original.payload);
+        assert_eq!(queue.list_jobs(Some(RUN), 10).await.unwrap()[0].job_id, copy.job_id);
+    }
+
+    #[tokio::test]
+    async fn test_pallet_sscc_is_assigned_once_and_resolves() {
+        let config = SsccConfig { extension_digit: 0, company_prefix: "8851234".to_string() };
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone()).with_sscc_config(Some(config.clone()));
+        assert!(matches!(
+            service.assign_pallet_sscc(RUN, 1, "deachawat").await,
+            Err(BulkRunError::NothingToPrint { .. })
+        ));
+        assert!(matches!(
+            service.assign_pallet_sscc(RUN, 9, "deachawat").await,
+            Err(BulkRunError::PalletNotFound { .. })
+        ));
+
+        pick_everything(&service).await;
+        // Reading the label never assigns a serial
+        assert!(matches!(
+            service.get_pallet_labels(RUN, 1).await,
+            Err(BulkRunError::SsccNotAssigned { .. })
+        ));
+        assert!(matches!(
+            BulkRunsService::new(repo.clone()).assign_pallet_sscc(RUN, 1, "deachawat").await,
+            Err(BulkRunError::SsccNotConfigured)
+        ));
+
+        let first = service.assign_pallet_sscc(RUN, 1, "deachawat").await.unwrap().sscc;
+        assert_eq!(first, sscc(0, "8851234", 1).unwrap());
+        let labels = service.get_pallet_labels(RUN, 1).await.unwrap();
+        assert_eq!(labels.len(), 1);
+        assert_eq!(labels[0].sscc, first);
+        assert_eq!(labels[0].batch.lines.len(), 2);
+        // Reprints keep the pallet's SSCC
+        assert_eq!(service.assign_pallet_sscc(RUN, 1, "supervisor").await.unwrap().sscc, first);
+
+        let pallet = service.lookup_pallet(&format!("(00){first}")).await.unwrap();
+        assert_eq!((pallet.pallet.run_no, pallet.pallet.row_num), (RUN, 1));
+        assert_eq!(pallet.pallet.assigned_by, "deachawat");
+        assert_eq!(
+            pallet.lines.iter().map(|line| line.item_key.as_str()).collect::<Vec<_>>(),
+            vec!["INSOYF01", "INSALT02"]
+        );
+        assert!(matches!(service.lookup_pallet("12345").await, Err(BulkRunError::InvalidSscc(_))));
+        assert!(matches!(
+            service.lookup_pallet(&sscc(0, "8851234", 99).unwrap()).await,
+            Err(BulkRunError::PalletNotFound { .. })
+        ));
+
+        let queue = PrintQueueService::new(repo.clone()).with_sscc_config(Some(config));
+        queue
+            .register_printer(printer("ZB-DOCK1", 9100, PrinterLanguage::Zpl, true), "supervisor")
+            .await
+            .unwrap();
+        // Queueing a print assigns the next serial to a pallet that has none yet
+        queue
+            .queue_pallet_labels(RUN, 2, &PrintPalletRequest::default(), "deachawat")
+            .await
+            .unwrap();
+        let second = service.get_pallet_labels(RUN, 2).await.unwrap()[0].sscc.clone();
+        assert_eq!(second, sscc(0, "8851234", 2).unwrap());
+        assert!(parse_sscc(&second).is_ok());
+        let job = queue
+            .queue_pallet_labels(RUN, 1, &PrintPalletRequest::default(), "deachawat")
+            .await
+            .unwrap();
+        assert_eq!(job.label_kind, LabelKind::Pallet);
+        assert_eq!(job.batch_no, Some(format!("{RUN}-1")));
+        assert!(job.payload.contains(&format!("^FD>;>800{first}^FS")));
+    }
+
+    #[tokio::test]
+    async fn test_gs1_scan_resolves_item_lot_and_flags_mismatches() {
+        let repo = seeded_repository();
+        let service = ScanService::new(repo.clone());
+        let register = |gtin: &str, item_key: &str| RegisterGtinRequest {
+            gtin: gtin.to_string(),
+            item_key: item_key.to_string(),
+        };
+        assert!(matches!(
+            service.register_gtin(register("9501101020910", "INSOYF01"), "supervisor").await,
+            Err(ScanError::InvalidBarcode(_))
+        ));
+        assert!(matches!(
+            service.register_gtin(register("9501101020917", "NOSUCH"), "supervisor").await,
+            Err(ScanError::ItemNotFound { .. })
+        ));
+        let gtin = service.register_gtin(register("9501101020917", "INSOYF01"), "supervisor").await.unwrap();
+        assert_eq!(gtin.gtin, "09501101020917");
+
+        let lot_expiry = (chrono::Utc::now() + chrono::Duration::days(30)).format("%y%m%d").to_string();
+        let label = |lot_no: &str, expiry: &str| format!("]d2010950110102091717{expiry}3102002000\u{1d}10{lot_no}");
+        let kinds = |resolution: &crate::models::scans::ScanResolution| {
+            resolution.mismatches.iter().map(|m| m.kind).collect::<Vec<_>>()
+        };
+
+        // One scan gives item, lot, expiry and weight; the run's pickable bins come with it
+        let scan = service.resolve_for_run(RUN, &label("2510601", &lot_expiry), Some("INSOYF01")).await.unwrap();
+        assert!(scan.is_verified(), "{:?}", scan.mismatches);
+        assert_eq!(scan.item_key.as_deref(), Some("INSOYF01"));
+        assert_eq!(scan.lot_no.as_deref(), Some("2510601"));
+        assert_eq!(scan.expiry_date.unwrap().format("%y%m%d").to_string(), lot_expiry);
+        assert_eq!(scan.net_weight_kg, Some(BigDecimal::from(20)));
+        assert_eq!(scan.lots.len(), 1);
+        assert_eq!(scan.pick_bins.unwrap()[0].bin_no, "A0101-1A");
+
+        // Wrong expiry, a lot filed under another item, and the wrong ingredient
+        let scan = service.resolve(&label("2510601", "990101"), None).await.unwrap();
+        assert_eq!(kinds(&scan), vec![MismatchKind::ExpiryMismatch]);
+        let scan = service.resolve(&label("2510602", &lot_expiry), Some("INSALT02")).await.unwrap();
+        assert_eq!(kinds(&scan), vec![MismatchKind::LotItemMismatch, MismatchKind::UnexpectedItem]);
+        let scan = service.resolve(&label("9999999", &lot_expiry), None).await.unwrap();
+        assert_eq!(kinds(&scan), vec![MismatchKind::LotNotFound]);
+        let scan = service.resolve("(01)09501101020924(10)2510602", None).await.unwrap();
+        assert_eq!(kinds(&scan), vec![MismatchKind::UnknownGtin]);
+        assert_eq!(scan.item_key.as_deref(), Some("INSALT02"));
+
+        // Plain scans read as a lot number, then an item key
+        let scan = service.resolve("2510602", Some("INSALT02")).await.unwrap();
+        assert!(scan.is_verified());
+        assert_eq!((scan.item_key.as_deref(), scan.lot_no.as_deref()), (Some("INSALT02"), Some("2510602")));
+        let scan = service.resolve("INSOYF01", None).await.unwrap();
+        assert_eq!((scan.item_key.as_deref(), scan.lot_no), (Some("INSOYF01"), None));
+        assert_eq!(kinds(&service.resolve("B9999", None).await.unwrap()), vec![MismatchKind::UnknownCode]);
+        assert!(matches!(service.resolve("(17)271301", None).await, Err(ScanError::InvalidBarcode(_))));
+    }
+
+    #[tokio::test]
+    async fn test_unpick_priority_and_stock_rollback() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        pick_everything(&service).await;
+
+        // LotTranNo wins over lot number: only that single allocation is removed
+        let first = repo.picks()[0].lot_tran_no;
+        let request = UnpickRequest { lot_no: Some("2510602".to_string()), lot_tran_no: Some(first) };
+        let result = service.unpick_ingredient(RUN, 1, 1, &request, "deachawat").await.unwrap();
+        assert_eq!(result["operation_type"], "precise_unpick");
+        assert_eq!(repo.picks().len(), 2);
+        assert_eq!(repo.lot_available("2510601", "A0101-1A"), Some(BigDecimal::from(80)));
+        assert!(!service.is_pallet_completed(RUN, 1, 1).await.unwrap());
+        assert!(!service.get_run_completion_status(RUN).await.unwrap().is_complete);
+
+        let request = UnpickRequest { lot_no: Some("2510602".to_string()), lot_tran_no: None };
+        let result = service.unpick_ingredient(RUN, 1, 2, &request, "deachawat").await.unwrap();
+        assert_eq!(result["operation_type"], "lot");
+        assert_eq!(repo.lot_available("2510602", "A0202-1A"), Some(BigDecimal::from(50)));
+
+        // Batch unpick of an already clean batch is a successful no-op
+        let request = UnpickRequest { lot_no: None, lot_tran_no: None };
+        let result = service.unpick_ingredient(RUN, 1, 2, &request, "deachawat").await.unwrap();
+        assert_eq!(result["operation_type"], "unpick_already_clean");
+    }
+
+    #[tokio::test]
+    async fn test_unpick_all_run_lots_restores_inventory() {
+        let repo = seeded_repository();
+        let service = BulkRunsService::new(repo.clone());
+        pick_everything(&service).await;
+
+        // Past service validation, the transaction itself still refuses to pick a batch twice
+        let again = repo
+            .confirm_pick_transaction(RUN, &pick(1, 1, 1, "2510601", "A0101-1A"))
+            .await
+            .unwrap_err();
+        assert!(matches!(again, BulkRunError::BatchAlreadyCompleted));
+
+        let result = service.unpick_all_run_lots(RUN, "deachawat").await.unwrap();
+        assert_eq!(result["ingredients_processed"], 3);
+        assert!(repo.picks().is_empty());
+        assert_eq!(repo.lot_available("2510601", "A0101-1A"), Some(BigDecimal::from(100)));
+        assert_eq!(service.get_run_completion_status(RUN).await.unwrap().completed_count, 0);
+
+        // Picking can start over from the first pallet
+        service
+            .confirm_pick_transaction(RUN, pick(1, 1, 2, "2510601", "A0101-1A"))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_unpick_unknown_lot_tran_no_fails() {
+        let service = BulkRunsService::new(seeded_repository());
+        let request = UnpickRequest { lot_no: None, lot_tran_no: Some(999) };
+        assert!(service.unpick_ingredient(RUN, 1, 1, &request, "deachawat").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_pick_validation_service_offline() {
+        let repo = seeded_repository();
+        let validation = BulkPickingValidationService::new(repo.clone());
+        let request = |bags: f64| PickValidationRequest {
+            run_no: RUN,
+            row_num: 1,
+            line_id: 1,
+            item_key: "INSOYF01".to_string(),
+            lot_no: "2510601".to_string(),
+            bin_no: "A0101-1A".to_string(),
+            user_input_bags: bags,
+            pack_size: 20.0,
+        };
+
+        let exceeds = validation.validate_pick_operation(request(3.0)).await.unwrap();
+        assert!(matches!(
+            exceeds.error,
+            Some(BulkPickingValidationError::QuantityExceedsRequired { .. })
+        ));
+
+        let ok = validation.validate_pick_operation(request(2.0)).await.unwrap();
+        assert!(ok.is_valid);
+        assert_eq!(ok.batch_completion_status, "WILL_COMPLETE");
+
+        BulkRunsService::new(repo)
+            .confirm_pick_transaction(RUN, pick(1, 1, 2, "2510601", "A0101-1A"))
+            .await
+            .unwrap();
+        let completed = validation.validate_pick_operation(request(1.0)).await.unwrap();
+        assert_eq!(completed.batch_completion_status, "COMPLETED");
+    }
+
+    fn transfer(bin_to: &str, qty: f64) -> BinTransferRequest {
+        BinTransferRequest {
+            lot_no: "2510700".to_string(),
+            item_key: "INSOYF01".to_string(),
+            location: "TFC1".to_string(),
+            bin_from: "A0101-1A".to_string(),
+            bin_to: bin_to.to_string(),
+            transfer_qty: qty,
+            user_id: "deachawat".to_string(),
+            remarks: None,
+            referenced: None,
+            idempotency_key: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_putaway_partial_and_full_transfer() {
+        let repo = InMemoryRepository::new();
+        repo.add_bin("TFC1", "A0101-1A");
+        repo.add_bin("TFC1", "B0202-2B");
+        repo.add_putaway_lot("2510700", "INSOYF01", "TFC1", "A0101-1A", 100.0);
+        let service = PutawayService::new(repo.clone());
+
+        let partial = service.execute_transfer(transfer("B0202-2B", 40.0)).await.unwrap();
+        assert!(partial.success);
+        assert!(!partial.message.contains("FULL TRANSFER"));
+
+        let full = service.execute_transfer(transfer("B0202-2B", 60.0)).await.unwrap();
+        assert!(full.message.contains("FULL TRANSFER"));
+        assert_eq!(repo.putaway_bins("2510700"), vec![("B0202-2B".to_string(), 100.0)]);
+
+        let lot = service.search_lot("2510700").await.unwrap();
+        assert_eq!(lot.current_bin, "B0202-2B");
+    }
+
+    #[tokio::test]
+    async fn test_putaway_transfer_replays_idempotency_key() {
+        let repo = InMemoryRepository::new();
+        repo.add_bin("TFC1", "A0101-1A");
+        repo.add_bin("TFC1", "B0202-2B");
+        repo.add_putaway_lot("2510700", "INSOYF01", "TFC1", "A0101-1A", 100.0);
+        let service = PutawayService::new(repo.clone());
+
+        let keyed = |qty: f64| {
+            let mut request = transfer("B0202-2B", qty);
+            request.idempotency_key = with_key("move-1", IdempotentOperation::BinTransfer, &request);
+            request
+        };
+
+        let first = service.execute_transfer(keyed(100.0)).await.unwrap();
+        // The source bin is empty now, so only a replay can succeed
+        let replay = service.execute_transfer(keyed(100.0)).await.unwrap();
+
+        assert_eq!(replay.document_no, first.document_no);
+        assert_eq!(replay.message, first.message);
+        assert_eq!(repo.putaway_bins("2510700"), vec![("B0202-2B".to_string(), 100.0)]);
+        assert!(matches!(
+            service.execute_transfer(keyed(50.0)).await,
+            Err(PutawayError::IdempotencyKeyReused)
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_putaway_rejects_unknown_bin_and_over_transfer() {
+        let repo = InMemoryRepository::new();
+        repo.add_bin("TFC1", "A0101-1A");
+        repo.add_bin("TFC1", "B0202-2B");
+        repo.add_putaway_lot("2510700", "INSOYF01", "TFC1", "A0101-1A", 100.0);
+        let service = PutawayService::new(repo);
+
+        assert!(matches!(
+            service.execute_transfer(transfer("Z9999", 10.0)).await,
+            Err(PutawayError::InvalidBin { .. })
+        ));
+        assert!(matches!(
+            service.execute_transfer(transfer("B0202-2B", 150.0)).await,
+            Err(PutawayError::InsufficientQuantity { .. })
+        ));
+        assert!(!service.validate_bin("TFC1", "Z9999").await.unwrap().is_valid);
+    }
+
+    #[tokio::test]
+    async fn test_putaway_transfer_failure_is_a_server_error() {
+        let repo = InMemoryRepository::new();
+        repo.add_bin("TFC1", "A0101-1A");
+        repo.add_bin("TFC1", "B0202-2B");
+        repo.add_putaway_lot("2510700", "INSOYF01", "TFC1", "A0101-1A", 100.0);
+        let service = PutawayService::new(repo.clone());
+
+        repo.fail_next_transfer(PutawayError::TransactionError("commit failed".to_string()));
+        let error = service.execute_transfer(transfer("B0202-2B", 40.0)).await.unwrap_err();
+
+        assert_eq!(error.status_code(), axum::http::StatusCode::INTERNAL_SERVER_ERROR);
+        assert_eq!(repo.putaway_bins("2510700"), vec![("A0101-1A".to_string(), 100.0)]);
+    }
+}
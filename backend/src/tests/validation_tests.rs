@@ -1,10 +1,6 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use crate::services::bulk_picking_validation::{
-        BulkPickingValidationService, PickValidationRequest, BulkPickingValidationError
-    };
-    use crate::database::Database;
+    use crate::services::bulk_picking_validation::PickValidationRequest;
 
     /// Test BME4 quantity validation rules
     #[tokio::test]
@@ -41,7 +37,7 @@ mod tests {
         // Should provide validation status without full validation
         
         let input_bags = 3.0;
-        let pack_size = 25.0;
+        let _pack_size = 25.0;
         let max_allowed = 3.0; // ToPickedBulkQty - PickedBulkQty
         
         // Test exact match
@@ -1,8 +1,18 @@
-use serde::Serialize;
+use axum::{
+    http::{HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
 
-/// Unified API response structure for all endpoints
+use crate::middleware::request_id::{current_request_id, REQUEST_ID_HEADER};
+
+/// Unified API response envelope for all endpoints.
+/// `status` is the HTTP status it is sent with; it is not part of the JSON body.
 #[derive(Serialize, Clone, Debug)]
 pub struct ApiResponse<T> {
+    #[serde(skip)]
+    pub status: StatusCode,
     pub success: bool,
     pub data: Option<T>,
     pub message: String,
@@ -10,33 +20,111 @@ pub struct ApiResponse<T> {
     pub error_code: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub warning: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<FieldError>,
+}
+
+/// A validation failure for a single request field
+#[derive(Serialize, Clone, Debug, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
 }
 
 impl<T> ApiResponse<T> {
     /// Create successful response
     pub fn success(data: T, message: impl Into<String>) -> Self {
         Self {
-            success: true,
             data: Some(data),
+            ..Self::empty(message)
+        }
+    }
+
+    /// Create successful response without data (e.g. nothing left to pick)
+    pub fn empty(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::OK,
+            success: true,
+            data: None,
             message: message.into(),
             error_code: None,
             warning: None,
+            request_id: None,
+            errors: Vec::new(),
         }
     }
 
-
-    /// Create error response
+    /// Create error response (500 unless overridden with `with_status`)
     pub fn error(message: impl Into<String>) -> Self {
         Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
             success: false,
-            data: None,
-            message: message.into(),
-            error_code: None,
-            warning: None,
+            ..Self::empty(message)
         }
     }
 
+    /// Create error response with a machine-readable error code
+    pub fn error_with_code(message: impl Into<String>, error_code: impl Into<String>) -> Self {
+        Self {
+            error_code: Some(error_code.into()),
+            ..Self::error(message)
+        }
+    }
+
+    /// 400 for malformed or missing request parameters
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::error_with_code(message, "BAD_REQUEST").with_status(StatusCode::BAD_REQUEST)
+    }
+
+    /// 404 for a resource that does not exist
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::error_with_code(message, "NOT_FOUND").with_status(StatusCode::NOT_FOUND)
+    }
 
+    /// 422 with one entry per invalid field
+    pub fn validation_failed(message: impl Into<String>, errors: Vec<FieldError>) -> Self {
+        Self {
+            errors,
+            ..Self::error_with_code(message, "VALIDATION_ERROR").with_status(StatusCode::UNPROCESSABLE_ENTITY)
+        }
+    }
+
+    pub fn with_status(mut self, status: StatusCode) -> Self {
+        self.status = status;
+        self
+    }
+
+    pub fn with_warning(mut self, warning: impl Into<String>) -> Self {
+        self.warning = Some(warning.into());
+        self
+    }
+}
+
+impl<T: Serialize> IntoResponse for ApiResponse<T> {
+    fn into_response(mut self) -> Response {
+        if self.request_id.is_none() {
+            self.request_id = current_request_id();
+        }
+
+        let status = self.status;
+        let request_id = self.request_id.clone();
+        let mut response = (status, Json(self)).into_response();
+        if let Some(value) = request_id.and_then(|id| HeaderValue::from_str(&id).ok()) {
+            response.headers_mut().insert(REQUEST_ID_HEADER, value);
+        }
+        response
+    }
 }
 
 /// JWT token structure for authentication
@@ -46,6 +134,8 @@ pub struct AuthToken {
     pub token_type: String,
     pub expires_in: i64,
     pub expires_at: i64,
+    pub refresh_token: String,
+    pub refresh_expires_at: i64,
     pub user_id: String,
     pub username: String,
 }
@@ -58,6 +148,39 @@ pub struct User {
     pub email: String,
     pub display_name: String,
     pub is_active: bool,
+    pub roles: Vec<Role>,
+}
+
+/// Application roles used for route-level authorization
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Picker,
+    Supervisor,
+    InventoryController,
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Picker => "picker",
+            Self::Supervisor => "supervisor",
+            Self::InventoryController => "inventory_controller",
+            Self::Admin => "admin",
+        }
+    }
+
+    /// Parse a role name as written in config or tbl_user (case-insensitive, spaces or underscores)
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_lowercase().replace([' ', '-'], "_").as_str() {
+            "picker" => Some(Self::Picker),
+            "supervisor" => Some(Self::Supervisor),
+            "inventory_controller" | "inventory" => Some(Self::InventoryController),
+            "admin" | "administrator" => Some(Self::Admin),
+            _ => None,
+        }
+    }
 }
 
 /// Login response structure
@@ -67,3 +190,28 @@ pub struct LoginResponse {
     pub user: User,
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_envelope_serialization_skips_empty_fields() {
+        let body = serde_json::to_value(ApiResponse::success(7, "ok")).unwrap();
+        assert_eq!(body, serde_json::json!({ "success": true, "data": 7, "message": "ok" }));
+    }
+
+    #[test]
+    fn test_validation_failed_envelope() {
+        let response = ApiResponse::<()>::validation_failed(
+            "Invalid request",
+            vec![FieldError::new("item_key", "Item key cannot be empty")],
+        );
+        assert_eq!(response.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(response.error_code.as_deref(), Some("VALIDATION_ERROR"));
+
+        let body = serde_json::to_value(&response).unwrap();
+        assert_eq!(body["success"], false);
+        assert_eq!(body["errors"][0]["field"], "item_key");
+    }
+}
@@ -0,0 +1,69 @@
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+/// Prefix that marks a bearer credential as an API key rather than a JWT
+pub const API_KEY_PREFIX: &str = "bpk_";
+
+/// Generate a new API key: `bpk_<key id>_<secret>`.
+/// Returns (key id, plaintext key, SHA-256 hash of the key).
+pub fn generate_api_key() -> (String, String, String) {
+    let mut id_bytes = [0u8; 6];
+    let mut secret_bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut id_bytes);
+    rand::thread_rng().fill_bytes(&mut secret_bytes);
+
+    let key_id = hex(&id_bytes);
+    let api_key = format!("{API_KEY_PREFIX}{key_id}_{}", hex(&secret_bytes));
+    let key_hash = hash_api_key(&api_key);
+    (key_id, api_key, key_hash)
+}
+
+/// Whether a bearer credential looks like an API key
+pub fn is_api_key(token: &str) -> bool {
+    token.starts_with(API_KEY_PREFIX)
+}
+
+/// Extract the key id used to look up the stored hash
+pub fn api_key_id(api_key: &str) -> Option<&str> {
+    let (key_id, secret) = api_key.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+    let valid = |part: &str, len: usize| part.len() == len && part.bytes().all(|b| b.is_ascii_hexdigit());
+    (valid(key_id, 12) && valid(secret, 64)).then_some(key_id)
+}
+
+/// Hash an API key for storage - keys carry 256 bits of randomness, so a plain SHA-256 suffices
+pub fn hash_api_key(api_key: &str) -> String {
+    hex(&Sha256::digest(api_key.trim().as_bytes()))
+}
+
+/// Compare a presented key against its stored hash in constant time
+pub fn api_key_matches(api_key: &str, stored_hash: &str) -> bool {
+    hash_api_key(api_key).as_bytes().ct_eq(stored_hash.trim().as_bytes()).into()
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_generated_keys_round_trip() {
+        let (key_id, api_key, key_hash) = generate_api_key();
+
+        assert!(is_api_key(&api_key));
+        assert_eq!(api_key_id(&api_key), Some(key_id.as_str()));
+        assert!(api_key_matches(&api_key, &key_hash));
+        assert!(!api_key_matches(&generate_api_key().1, &key_hash));
+        assert!(!api_key.contains(&key_hash));
+    }
+
+    #[test]
+    fn test_malformed_keys_are_rejected() {
+        assert_eq!(api_key_id("bpk_short_secret"), None);
+        assert_eq!(api_key_id("eyJhbGciOiJIUzI1NiJ9.e30.sig"), None);
+        assert!(!is_api_key("eyJhbGciOiJIUzI1NiJ9.e30.sig"));
+    }
+}
@@ -1,11 +1,17 @@
 use anyhow::{Context, Result};
 use chrono::{Duration, Utc};
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use jsonwebtoken::{decode, decode_header, encode, Header, Validation};
+use rand::RngCore;
 use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
 use std::env;
-use tracing::{info, warn};
+use std::sync::{Arc, RwLock};
+use tracing::info;
 
-use crate::types::{AuthToken, User};
+use crate::models::auth_session::IssuedRefreshToken;
+use crate::types::{AuthToken, Role, User};
+use crate::utils::jwt_keys::{JwkSet, JwtKeySet};
 use tiberius::Row;
 
 /// JWT Claims structure
@@ -18,51 +24,70 @@ pub struct Claims {
     pub exp: i64,           // Expiration time
     pub iat: i64,           // Issued at
     pub iss: String,        // Issuer
+    pub jti: String,        // Unique token ID
+    pub fid: String,        // Refresh token family (login session) ID
+    pub roles: Vec<Role>,   // Authorization roles
 }
 
 #[derive(Clone)]
 pub struct AuthService {
-    encoding_key: EncodingKey,
-    decoding_key: DecodingKey,
+    keys: JwtKeySet,
     issuer: String,
     token_duration: Duration,
+    refresh_token_duration: Duration,
+    /// Revoked token families -> unix time after which no access token of the family can still be live
+    revoked_families: Arc<RwLock<HashMap<String, i64>>>,
 }
 
 impl AuthService {
     /// Initialize JWT authentication service
     pub fn new() -> Result<Self> {
-        let secret = env::var("JWT_SECRET")
-            .unwrap_or_else(|_| "bulk_picking_jwt_secret_key_change_in_production".to_string());
-        
-        if secret == "bulk_picking_jwt_secret_key_change_in_production" {
-            warn!("⚠️ Using default JWT secret - CHANGE THIS IN PRODUCTION!");
-        }
-
-        let encoding_key = EncodingKey::from_secret(secret.as_bytes());
-        let decoding_key = DecodingKey::from_secret(secret.as_bytes());
+        // Refuses the built-in default secret in release builds
+        Self::with_keys(JwtKeySet::from_env()?)
+    }
 
+    /// Initialize with an explicit key set (token lifetimes still come from the environment)
+    pub fn with_keys(keys: JwtKeySet) -> Result<Self> {
         let issuer = env::var("JWT_ISSUER")
             .unwrap_or_else(|_| "NWFTH-BulkPicking".to_string());
 
-        let token_duration_hours = env::var("JWT_DURATION_HOURS")
-            .unwrap_or_else(|_| "8".to_string())
+        let access_token_minutes = env::var("JWT_ACCESS_TOKEN_MINUTES")
+            .unwrap_or_else(|_| "15".to_string())
             .parse::<i64>()
-            .unwrap_or(8);
+            .unwrap_or(15);
 
-        let token_duration = Duration::hours(token_duration_hours);
+        // JWT_DURATION_HOURS used to be the access token lifetime; it now bounds how long
+        // a session can stay idle between refreshes
+        let refresh_token_hours = env::var("JWT_REFRESH_TOKEN_HOURS")
+            .or_else(|_| env::var("JWT_DURATION_HOURS"))
+            .unwrap_or_else(|_| "12".to_string())
+            .parse::<i64>()
+            .unwrap_or(12);
 
-        info!("🔐 JWT Authentication initialized with {}h token duration", token_duration_hours);
+        let token_duration = Duration::minutes(access_token_minutes);
+        let refresh_token_duration = Duration::hours(refresh_token_hours);
+
+        info!(
+            "🔐 JWT Authentication initialized with {}m access tokens and {}h rotating refresh tokens",
+            access_token_minutes, refresh_token_hours
+        );
 
         Ok(Self {
-            encoding_key,
-            decoding_key,
+            keys,
             issuer,
             token_duration,
+            refresh_token_duration,
+            revoked_families: Arc::new(RwLock::new(HashMap::new())),
         })
     }
 
-    /// Generate JWT token for authenticated user
-    pub fn generate_token(&self, user: &User) -> Result<AuthToken> {
+    /// Generate a short-lived JWT access token paired with a refresh token
+    pub fn generate_token(
+        &self,
+        user: &User,
+        family_id: &str,
+        refresh_token: &IssuedRefreshToken,
+    ) -> Result<AuthToken> {
         let now = Utc::now();
         let exp = now + self.token_duration;
 
@@ -74,9 +99,17 @@ impl AuthService {
             exp: exp.timestamp(),
             iat: now.timestamp(),
             iss: self.issuer.clone(),
+            jti: uuid::Uuid::new_v4().to_string(),
+            fid: family_id.to_string(),
+            roles: user.roles.clone(),
         };
 
-        let token_string = encode(&Header::default(), &claims, &self.encoding_key)
+        // `kid` lets verifiers pick the right key while keys are being rotated
+        let signing_key = self.keys.signing_key();
+        let mut header = Header::new(signing_key.algorithm);
+        header.kid = Some(signing_key.kid.clone());
+
+        let token_string = encode(&header, &claims, &signing_key.encoding_key)
             .context("Failed to sign JWT token")?;
 
         info!("🎫 Generated JWT token for user: {}", user.username);
@@ -86,18 +119,75 @@ impl AuthService {
             token_type: "Bearer".to_string(),
             expires_in: self.token_duration.num_seconds(),
             expires_at: exp.timestamp(),
+            refresh_token: refresh_token.token.clone(),
+            refresh_expires_at: refresh_token.expires_at.and_utc().timestamp(),
             user_id: user.user_id.clone(),
             username: user.username.clone(),
         })
     }
 
+    /// Mint a new opaque refresh token (256 bits of randomness, hex encoded)
+    pub fn new_refresh_token(&self) -> IssuedRefreshToken {
+        let mut bytes = [0u8; 32];
+        rand::thread_rng().fill_bytes(&mut bytes);
+        let token: String = bytes.iter().map(|b| format!("{b:02x}")).collect();
+
+        IssuedRefreshToken {
+            token_hash: Self::hash_refresh_token(&token),
+            token,
+            expires_at: (Utc::now() + self.refresh_token_duration).naive_utc(),
+        }
+    }
+
+    /// Hash a refresh token for storage and lookup - only hashes are persisted
+    pub fn hash_refresh_token(token: &str) -> String {
+        Sha256::digest(token.trim().as_bytes())
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect()
+    }
+
+    /// Lifetime of access tokens; revoked families must be remembered at least this long
+    pub fn access_token_duration(&self) -> Duration {
+        self.token_duration
+    }
+
+    /// Mark a token family as revoked so its outstanding access tokens are rejected
+    pub fn revoke_family(&self, family_id: &str) {
+        let until = (Utc::now() + self.token_duration).timestamp();
+        self.remember_revoked_family(family_id, until);
+    }
+
+    /// Record a revoked family with an explicit cut-off (used when loading from the database)
+    pub fn remember_revoked_family(&self, family_id: &str, until: i64) {
+        let now = Utc::now().timestamp();
+        if let Ok(mut revoked) = self.revoked_families.write() {
+            revoked.retain(|_, expires| *expires > now);
+            let entry = revoked.entry(family_id.to_string()).or_insert(until);
+            *entry = (*entry).max(until);
+        }
+    }
+
+    /// Whether a token family has been revoked
+    pub fn is_family_revoked(&self, family_id: &str) -> bool {
+        let now = Utc::now().timestamp();
+        self.revoked_families
+            .read()
+            .map(|revoked| revoked.get(family_id).is_some_and(|expires| *expires > now))
+            .unwrap_or(false)
+    }
+
     /// Verify and decode JWT token
     pub fn verify_token(&self, token: &str) -> Result<Claims> {
-        let token_data = decode::<Claims>(
-            token, 
-            &self.decoding_key, 
-            &Validation::default()
-        ).context("Invalid JWT token signature")?;
+        let header = decode_header(token).context("Malformed JWT token")?;
+        let key = self
+            .keys
+            .verification_key(header.kid.as_deref())
+            .ok_or_else(|| anyhow::anyhow!("Unknown JWT key id: {:?}", header.kid))?;
+
+        // The algorithm is pinned by the key, never taken from the token header
+        let token_data = decode::<Claims>(token, &key.decoding_key, &Validation::new(key.algorithm))
+            .context("Invalid JWT token signature")?;
 
         let now = Utc::now().timestamp();
         if now > token_data.claims.exp {
@@ -109,9 +199,19 @@ impl AuthService {
             return Err(anyhow::anyhow!("Invalid token issuer"));
         }
 
+        // Reject tokens from sessions that were logged out or compromised
+        if self.is_family_revoked(&token_data.claims.fid) {
+            return Err(anyhow::anyhow!("Token has been revoked"));
+        }
+
         Ok(token_data.claims)
     }
 
+    /// Public verification keys for the JWKS endpoint
+    pub fn jwks(&self) -> JwkSet {
+        self.keys.jwks()
+    }
+
     /// Extract token from Authorization header
     pub fn extract_token_from_header(auth_header: Option<&str>) -> Option<&str> {
         auth_header?
@@ -135,6 +235,7 @@ impl AuthService {
             },
             display_name: display_name.to_string(),
             is_active,
+            roles: vec![Role::Picker],
         }
     }
 
@@ -146,6 +247,95 @@ impl AuthService {
             email: format!("{username}@nwfth.com"),
             display_name: username.to_string(),
             is_active: true,
+            roles: vec![Role::Picker],
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::jwt_keys::{tests::ed25519_pem, JwtKey};
+    use jsonwebtoken::Algorithm;
+
+    fn test_user() -> User {
+        AuthService::create_user_from_ldap("deachawat")
+    }
+
+    #[test]
+    fn test_refresh_tokens_are_random_and_stored_hashed() {
+        let auth = AuthService::new().unwrap();
+        let first = auth.new_refresh_token();
+        let second = auth.new_refresh_token();
+
+        assert_eq!(first.token.len(), 64);
+        assert_ne!(first.token, second.token);
+        assert_ne!(first.token, first.token_hash);
+        assert_eq!(first.token_hash, AuthService::hash_refresh_token(&first.token));
+        assert_eq!(first.token_hash.len(), 64);
+    }
+
+    #[test]
+    fn test_access_token_carries_session_family() {
+        let auth = AuthService::new().unwrap();
+        let refresh = auth.new_refresh_token();
+        let token = auth.generate_token(&test_user(), "family-1", &refresh).unwrap();
+
+        assert_eq!(token.refresh_token, refresh.token);
+        assert!(token.refresh_expires_at > token.expires_at);
+
+        let claims = auth.verify_token(&token.access_token).unwrap();
+        assert_eq!(claims.fid, "family-1");
+        assert_eq!(claims.username, "deachawat");
+    }
+
+    #[test]
+    fn test_revoked_family_rejects_access_tokens() {
+        let auth = AuthService::new().unwrap();
+        let refresh = auth.new_refresh_token();
+        let revoked = auth.generate_token(&test_user(), "family-revoked", &refresh).unwrap();
+        let other = auth.generate_token(&test_user(), "family-other", &refresh).unwrap();
+
+        auth.revoke_family("family-revoked");
+
+        assert!(auth.verify_token(&revoked.access_token).is_err());
+        assert!(auth.verify_token(&other.access_token).is_ok());
+    }
+
+    #[test]
+    fn test_rotated_keys_keep_existing_tokens_valid() {
+        let old_key = || JwtKey::from_secret("2026-04", Algorithm::HS256, b"previous-shared-secret").unwrap();
+        let new_pem = ed25519_pem();
+        let new_key = || JwtKey::from_private_pem("2026-10", Algorithm::EdDSA, &new_pem).unwrap();
+
+        let before = AuthService::with_keys(JwtKeySet::new(vec![old_key()], "2026-04").unwrap()).unwrap();
+        let refresh = before.new_refresh_token();
+        let old_token = before.generate_token(&test_user(), "family-1", &refresh).unwrap();
+
+        let after = AuthService::with_keys(JwtKeySet::new(vec![new_key(), old_key()], "2026-10").unwrap()).unwrap();
+        let new_token = after.generate_token(&test_user(), "family-1", &refresh).unwrap();
+
+        assert_eq!(decode_header(&old_token.access_token).unwrap().kid.as_deref(), Some("2026-04"));
+        let header = decode_header(&new_token.access_token).unwrap();
+        assert_eq!(header.kid.as_deref(), Some("2026-10"));
+        assert_eq!(header.alg, Algorithm::EdDSA);
+
+        // A token signed with the previous kid still verifies after rotation
+        assert!(after.verify_token(&old_token.access_token).is_ok());
+        assert!(after.verify_token(&new_token.access_token).is_ok());
+
+        // Once the old key is retired its tokens are rejected
+        let retired = AuthService::with_keys(JwtKeySet::new(vec![new_key()], "2026-10").unwrap()).unwrap();
+        assert!(retired.verify_token(&old_token.access_token).is_err());
+        assert!(retired.verify_token(&new_token.access_token).is_ok());
+    }
+
+    #[test]
+    fn test_expired_revocations_are_forgotten() {
+        let auth = AuthService::new().unwrap();
+        let past = Utc::now().timestamp() - 1;
+
+        auth.remember_revoked_family("family-old", past);
+
+        assert!(!auth.is_family_revoked("family-old"));
+    }
+}
@@ -0,0 +1,25 @@
+use axum::http::HeaderMap;
+use std::net::{IpAddr, SocketAddr};
+
+/// Resolve the client IP of a request.
+/// `X-Forwarded-For` / `X-Real-IP` are only honoured when TRUST_PROXY_HEADERS=true
+/// (i.e. the backend sits behind a reverse proxy that sets them), otherwise clients could spoof them.
+pub fn client_ip(headers: &HeaderMap, peer: SocketAddr) -> IpAddr {
+    let trust_proxy = std::env::var("TRUST_PROXY_HEADERS")
+        .map(|v| v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false);
+
+    if trust_proxy {
+        let forwarded = headers
+            .get("x-forwarded-for")
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.split(',').next())
+            .or_else(|| headers.get("x-real-ip").and_then(|v| v.to_str().ok()))
+            .and_then(|v| v.trim().parse::<IpAddr>().ok());
+        if let Some(ip) = forwarded {
+            return ip;
+        }
+    }
+
+    peer.ip()
+}
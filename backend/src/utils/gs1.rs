@@ -0,0 +1,302 @@
+//! GS1 identifiers: mod-10 check digits, SSCC-18 serial shipping container codes and
+//! the Application Identifier element strings carried by GS1-128 and GS1 DataMatrix labels.
+
+use bigdecimal::BigDecimal;
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+
+/// FNC1 as transmitted by scanners: ASCII group separator, ending a variable-length field
+pub const GROUP_SEPARATOR: char = '\u{1d}';
+
+/// Symbology identifiers a scanner may prefix to GS1 data (GS1-128, DataMatrix, QR, DataBar)
+const SYMBOLOGY_IDS: [&str; 4] = ["]C1", "]d2", "]Q3", "]e0"];
+
+/// GS1 mod-10 check digit of `digits` (the identifier without its check digit).
+/// Weights alternate 3 and 1 starting from the rightmost digit.
+pub fn check_digit(digits: &str) -> Option<u8> {
+    let mut sum = 0u32;
+    for (index, c) in digits.chars().rev().enumerate() {
+        let digit = c.to_digit(10)?;
+        sum += if index % 2 == 0 { digit * 3 } else { digit };
+    }
+    Some(((10 - sum % 10) % 10) as u8)
+}
+
+/// Build an SSCC-18: extension digit, GS1 company prefix, serial reference zero-padded
+/// to fill 17 digits, then the check digit. Fails when the serial does not fit.
+pub fn sscc(extension_digit: u8, company_prefix: &str, serial: u64) -> Result<String, String> {
+    if extension_digit > 9 {
+        return Err(format!("SSCC extension digit must be 0-9, got {extension_digit}"));
+    }
+    if !(7..=10).contains(&company_prefix.len()) || !company_prefix.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("GS1 company prefix must be 7-10 digits, got '{company_prefix}'"));
+    }
+    let serial_digits = 16 - company_prefix.len();
+    let serial = format!("{serial:0serial_digits$}");
+    if serial.len() > serial_digits {
+        return Err(format!(
+            "Serial reference {serial} does not fit the {serial_digits} digits left by company prefix {company_prefix}"
+        ));
+    }
+
+    let body = format!("{extension_digit}{company_prefix}{serial}");
+    let check = check_digit(&body).ok_or_else(|| format!("SSCC '{body}' is not numeric"))?;
+    Ok(format!("{body}{check}"))
+}
+
+/// Read an SSCC from a scan or typed value: bare 18 digits, or with its (00) Application
+/// Identifier as "00…" or "(00)…". Spaces are ignored. The check digit must match.
+pub fn parse_sscc(value: &str) -> Result<String, String> {
+    let compact: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+    let digits = compact
+        .strip_prefix("(00)")
+        .or_else(|| compact.strip_prefix("00").filter(|rest| rest.len() == 18))
+        .unwrap_or(&compact);
+
+    if digits.len() != 18 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("'{}' is not an SSCC-18", value.trim()));
+    }
+    let expected = check_digit(&digits[..17]).unwrap_or_default();
+    if digits.as_bytes()[17] - b'0' != expected {
+        return Err(format!("SSCC {digits} has a wrong check digit (expected {expected})"));
+    }
+    Ok(digits.to_string())
+}
+
+/// Read a GTIN-8/12/13/14 as the 14-digit GTIN used by AI (01). The check digit must match.
+pub fn normalize_gtin(value: &str) -> Result<String, String> {
+    let digits = value.trim();
+    if ![8, 12, 13, 14].contains(&digits.len()) || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(format!("'{digits}' is not a GTIN"));
+    }
+    let gtin = format!("{digits:0>14}");
+    let expected = check_digit(&gtin[..13]).unwrap_or_default();
+    if gtin.as_bytes()[13] - b'0' != expected {
+        return Err(format!("GTIN {digits} has a wrong check digit (expected {expected})"));
+    }
+    Ok(gtin)
+}
+
+/// The Application Identifiers of one scan that the warehouse uses. Other AIs on the
+/// label are read past and dropped.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct ElementString {
+    /// (00) SSCC-18
+    pub sscc: Option<String>,
+    /// (01) GTIN-14
+    pub gtin: Option<String>,
+    /// (10) batch/lot number
+    pub lot_no: Option<String>,
+    /// (17) expiry date
+    pub expiry_date: Option<NaiveDate>,
+    /// (310n) net weight in KG
+    pub net_weight_kg: Option<BigDecimal>,
+}
+
+/// Data length of an AI's value
+#[derive(Debug, Clone, Copy)]
+enum FieldLength {
+    Fixed(usize),
+    /// Up to this many characters, ended by FNC1 or the end of the scan
+    Variable(usize),
+}
+
+/// Digits in the AI and the length of its value, for the AIs found on supplier labels
+fn ai_spec(data: &str) -> Option<(usize, FieldLength)> {
+    use FieldLength::*;
+
+    let spec = match data.get(..2)? {
+        "00" => (2, Fixed(18)),
+        "01" | "02" => (2, Fixed(14)),
+        "10" | "21" | "22" => (2, Variable(20)),
+        "11" | "12" | "13" | "15" | "16" | "17" => (2, Fixed(6)),
+        "20" => (2, Fixed(2)),
+        "30" | "37" => (2, Variable(8)),
+        "24" | "40" => (3, Variable(30)),
+        "41" => (3, Fixed(13)),
+        "31" | "32" | "33" | "34" | "35" | "36" => (4, Fixed(6)),
+        _ => return None,
+    };
+    Some(spec)
+}
+
+/// Whether a scan carries GS1 Application Identifiers rather than a plain lot, item or
+/// bin code: a symbology identifier, bracketed AIs, an FNC1, or a bare (00)/(01) string
+/// whose check digit matches.
+pub fn is_element_string(scan: &str) -> bool {
+    let scan = scan.trim();
+    if SYMBOLOGY_IDS.iter().any(|id| scan.starts_with(id)) || scan.contains(GROUP_SEPARATOR) {
+        return true;
+    }
+    if scan.starts_with("(0") || scan.starts_with("(1") {
+        return scan.get(3..4) == Some(")");
+    }
+    match scan.get(..2) {
+        Some("00") => scan.get(2..20).is_some_and(|sscc| parse_sscc(sscc).is_ok()),
+        Some("01") => scan.get(2..16).is_some_and(|gtin| normalize_gtin(gtin).is_ok()),
+        _ => false,
+    }
+}
+
+/// Decode a GS1 element string: scanner output with an optional symbology identifier and
+/// FNC1 separators, or the human-readable "(01)…(10)…" form printed under the barcode.
+pub fn parse_element_string(scan: &str) -> Result<ElementString, String> {
+    let scan = scan.trim();
+    let data = SYMBOLOGY_IDS
+        .iter()
+        .find_map(|id| scan.strip_prefix(id))
+        .unwrap_or(scan)
+        .trim_start_matches(GROUP_SEPARATOR);
+
+    let fields = if data.starts_with('(') { bracketed_fields(data)? } else { raw_fields(data)? };
+    if fields.is_empty() {
+        return Err("Scan holds no GS1 Application Identifiers".to_string());
+    }
+
+    let mut element = ElementString::default();
+    for (ai, value) in fields {
+        match ai.as_str() {
+            "00" => element.sscc = Some(parse_sscc(&value)?),
+            "01" => element.gtin = Some(normalize_gtin(&value).map_err(|e| format!("AI (01): {e}"))?),
+            "10" => element.lot_no = Some(value),
+            "17" => element.expiry_date = Some(parse_date(&value).map_err(|e| format!("AI (17): {e}"))?),
+            _ if ai.starts_with("310") => element.net_weight_kg = Some(parse_measure(&ai, &value)?),
+            _ => {}
+        }
+    }
+    Ok(element)
+}
+
+/// AI/value pairs of "(01)09501101020917(10)ABC123"
+fn bracketed_fields(data: &str) -> Result<Vec<(String, String)>, String> {
+    let mut fields = Vec::new();
+    for part in data.split('(').skip(1) {
+        let (ai, value) = part
+            .split_once(')')
+            .ok_or_else(|| format!("Unclosed Application Identifier in '{data}'"))?;
+        let (ai_len, length) = ai_spec(ai).ok_or_else(|| format!("Unsupported Application Identifier ({ai})"))?;
+        let value = value.trim().trim_end_matches(GROUP_SEPARATOR);
+        if ai.len() != ai_len || !check_length(value, length) {
+            return Err(format!("Application Identifier ({ai}) has a malformed value '{value}'"));
+        }
+        fields.push((ai.to_string(), value.to_string()));
+    }
+    Ok(fields)
+}
+
+/// AI/value pairs of scanner output, where only variable-length values end in FNC1
+fn raw_fields(mut data: &str) -> Result<Vec<(String, String)>, String> {
+    let mut fields = Vec::new();
+    while !data.is_empty() {
+        let (ai_len, length) = ai_spec(data).ok_or_else(|| {
+            format!("Unsupported Application Identifier at '{}'", data.chars().take(4).collect::<String>())
+        })?;
+        let ai = data
+            .get(..ai_len)
+            .filter(|ai| ai.chars().all(|c| c.is_ascii_digit()))
+            .ok_or_else(|| format!("Truncated Application Identifier at '{data}'"))?;
+        let rest = &data[ai_len..];
+
+        let end = match length {
+            FieldLength::Fixed(n) => n,
+            FieldLength::Variable(_) => rest.find(GROUP_SEPARATOR).unwrap_or(rest.len()),
+        };
+        let value = rest.get(..end).unwrap_or(rest);
+        if !check_length(value, length) {
+            return Err(format!("Application Identifier ({ai}) has a malformed value '{value}'"));
+        }
+        fields.push((ai.to_string(), value.to_string()));
+        data = rest[value.len()..].trim_start_matches(GROUP_SEPARATOR);
+    }
+    Ok(fields)
+}
+
+fn check_length(value: &str, length: FieldLength) -> bool {
+    match length {
+        FieldLength::Fixed(n) => value.len() == n && value.chars().all(|c| c.is_ascii_digit()),
+        FieldLength::Variable(max) => !value.is_empty() && value.chars().count() <= max,
+    }
+}
+
+/// YYMMDD in the 2000s; day 00 means the last day of the month
+fn parse_date(value: &str) -> Result<NaiveDate, String> {
+    let field = |range: std::ops::Range<usize>| value[range].parse::<u32>().unwrap_or_default();
+    let (year, month, day) = (2000 + field(0..2) as i32, field(2..4), field(4..6));
+    let date = if day == 0 {
+        NaiveDate::from_ymd_opt(year, month, 1)
+            .and_then(|first| first.checked_add_months(chrono::Months::new(1)))
+            .and_then(|next| next.pred_opt())
+    } else {
+        NaiveDate::from_ymd_opt(year, month, day)
+    };
+    date.filter(|d| d.year() == year).ok_or_else(|| format!("'{value}' is not a YYMMDD date"))
+}
+
+/// (310n) net weight: six digits with n implied decimals
+fn parse_measure(ai: &str, value: &str) -> Result<BigDecimal, String> {
+    let decimals = ai[3..].parse::<i64>().unwrap_or_default();
+    if decimals > 5 {
+        return Err(format!("Application Identifier ({ai}) has more than 5 decimals"));
+    }
+    let units = value.parse::<i64>().map_err(|_| format!("AI ({ai}): '{value}' is not a number"))?;
+    Ok(BigDecimal::new(units.into(), decimals))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sscc_check_digit_and_parsing() {
+        // GS1 General Specifications example
+        assert_eq!(sscc(1, "0614141", 123456789).unwrap(), "106141411234567897");
+        assert_eq!(check_digit("629104150021"), Some(3));
+        assert_eq!(sscc(0, "8851234", 42).unwrap().len(), 18);
+        assert!(sscc(0, "8851234567", 1_000_000).is_err());
+        assert!(sscc(0, "88512", 1).is_err());
+
+        for scan in ["106141411234567897", "00106141411234567897", "(00)106141411234567897", " (00) 1061414112345678 97"] {
+            assert_eq!(parse_sscc(scan).unwrap(), "106141411234567897", "{scan}");
+        }
+        assert!(parse_sscc("106141411234567890").is_err());
+        assert!(parse_sscc("0010614141123456789").is_err());
+    }
+
+    #[test]
+    fn test_element_string_parsing() {
+        let expected = ElementString {
+            sscc: None,
+            gtin: Some("09501101020917".to_string()),
+            lot_no: Some("2510601".to_string()),
+            expiry_date: NaiveDate::from_ymd_opt(2027, 3, 31),
+            net_weight_kg: Some("25.00".parse().unwrap()),
+        };
+        for scan in [
+            "]C10109501101020917172703003102002500102510601",
+            "]d20109501101020917102510601\u{1d}172703003102002500",
+            "(01)09501101020917(17)270300(10)2510601(3102)002500",
+        ] {
+            assert!(is_element_string(scan), "{scan}");
+            assert_eq!(parse_element_string(scan).unwrap(), expected, "{scan}");
+        }
+
+        // Without FNC1 a lot number runs on into the AIs after it
+        let unterminated = parse_element_string("010950110102091710251060117270300").unwrap();
+        assert_eq!(unterminated.lot_no.as_deref(), Some("251060117270300"));
+        assert_eq!(unterminated.expiry_date, None);
+
+        assert_eq!(normalize_gtin("9501101020917").unwrap(), "09501101020917");
+        assert!(normalize_gtin("9501101020910").is_err());
+        assert!(parse_element_string("(01)09501101020910").is_err());
+        assert!(parse_element_string("(17)271301").is_err());
+        assert!(parse_element_string("(99)ABC").is_err());
+        assert_eq!(
+            parse_element_string("(00)106141411234567897").unwrap().sscc.as_deref(),
+            Some("106141411234567897")
+        );
+
+        for plain in ["2510601", "INSOYF01", "A0101-1A", "PWBB-12"] {
+            assert!(!is_element_string(plain), "{plain}");
+        }
+    }
+}
@@ -0,0 +1,321 @@
+use anyhow::{anyhow, bail, Context, Result};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey};
+use ring::signature::{Ed25519KeyPair, KeyPair, RsaKeyPair, RsaPublicKeyComponents};
+use serde::Serialize;
+use std::env;
+use tracing::{info, warn};
+
+/// Secret used when neither JWT_KEYS nor JWT_SECRET is configured (debug builds only)
+pub const DEFAULT_JWT_SECRET: &str = "bulk_picking_jwt_secret_key_change_in_production";
+
+/// Key id given to the single JWT_SECRET key when JWT_KEYS is not set
+pub const LEGACY_KID: &str = "default";
+
+/// A JWT signing/verification key selected by its `kid`
+#[derive(Clone)]
+pub struct JwtKey {
+    pub kid: String,
+    pub algorithm: Algorithm,
+    pub encoding_key: EncodingKey,
+    pub decoding_key: DecodingKey,
+    /// Public half for the JWKS endpoint (None for shared-secret algorithms)
+    pub public_jwk: Option<PublicJwk>,
+    /// Whether this key is the built-in development secret
+    pub is_default_secret: bool,
+}
+
+/// Public key in JWK form (RFC 7517)
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct PublicJwk {
+    pub kty: String,
+    pub kid: String,
+    pub alg: String,
+    #[serde(rename = "use")]
+    pub key_use: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub n: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub e: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub crv: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<String>,
+}
+
+/// JWKS document served at /.well-known/jwks.json
+#[derive(Debug, Clone, Serialize)]
+pub struct JwkSet {
+    pub keys: Vec<PublicJwk>,
+}
+
+/// All keys that may verify tokens, plus the one used to sign new tokens
+#[derive(Clone)]
+pub struct JwtKeySet {
+    keys: Vec<JwtKey>,
+    signing_kid: String,
+}
+
+impl JwtKeySet {
+    /// Load keys from the environment.
+    ///
+    /// `JWT_KEYS` lists `kid=ALG:source` entries separated by `;`, where source is
+    /// `file:/path` (PEM private key, or raw secret for HS*) or `env:VAR`.
+    /// `JWT_SIGNING_KID` picks the key for new tokens (default: the first entry).
+    /// Without `JWT_KEYS`, `JWT_SECRET` is used as a single HS256 key.
+    pub fn from_env() -> Result<Self> {
+        let spec = env::var("JWT_KEYS").unwrap_or_default();
+
+        let key_set = if spec.trim().is_empty() {
+            let (secret, is_default) = match env::var("JWT_SECRET") {
+                Ok(secret) if !secret.is_empty() => (secret, false),
+                _ => (DEFAULT_JWT_SECRET.to_string(), true),
+            };
+            let mut key = JwtKey::from_secret(LEGACY_KID, Algorithm::HS256, secret.as_bytes())?;
+            key.is_default_secret = is_default || secret == DEFAULT_JWT_SECRET;
+            Self::new(vec![key], LEGACY_KID)?
+        } else {
+            let keys = parse_key_spec(&spec)?
+                .into_iter()
+                .map(|(kid, algorithm, source)| {
+                    let material = read_key_source(&source)
+                        .with_context(|| format!("Failed to load JWT key '{kid}'"))?;
+                    JwtKey::from_material(&kid, algorithm, &material)
+                        .with_context(|| format!("Invalid JWT key '{kid}'"))
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let signing_kid = env::var("JWT_SIGNING_KID")
+                .ok()
+                .filter(|kid| !kid.is_empty())
+                .unwrap_or_else(|| keys[0].kid.clone());
+            Self::new(keys, &signing_kid)?
+        };
+
+        if key_set.uses_default_secret() {
+            if cfg!(debug_assertions) {
+                warn!("⚠️ Using default JWT secret - CHANGE THIS IN PRODUCTION!");
+            } else {
+                bail!("Refusing to start with the default JWT secret - set JWT_SECRET or JWT_KEYS");
+            }
+        }
+
+        info!(
+            "🔑 JWT keys loaded: {:?} (signing with '{}')",
+            key_set.keys.iter().map(|k| format!("{}:{:?}", k.kid, k.algorithm)).collect::<Vec<_>>(),
+            key_set.signing_kid
+        );
+        Ok(key_set)
+    }
+
+    pub fn new(keys: Vec<JwtKey>, signing_kid: &str) -> Result<Self> {
+        if keys.is_empty() {
+            bail!("At least one JWT key is required");
+        }
+        for (index, key) in keys.iter().enumerate() {
+            if keys[..index].iter().any(|other| other.kid == key.kid) {
+                bail!("Duplicate JWT key id '{}'", key.kid);
+            }
+        }
+        if !keys.iter().any(|key| key.kid == signing_kid) {
+            bail!("JWT_SIGNING_KID '{signing_kid}' does not match any configured key");
+        }
+        Ok(Self {
+            keys,
+            signing_kid: signing_kid.to_string(),
+        })
+    }
+
+    /// Key used to sign new tokens
+    pub fn signing_key(&self) -> &JwtKey {
+        self.keys
+            .iter()
+            .find(|key| key.kid == self.signing_kid)
+            .expect("signing key is validated on construction")
+    }
+
+    /// Key for verifying a token; tokens issued before key ids existed carry no `kid`
+    /// and are checked against the legacy JWT_SECRET key if it is still configured
+    pub fn verification_key(&self, kid: Option<&str>) -> Option<&JwtKey> {
+        let kid = kid.unwrap_or(LEGACY_KID);
+        self.keys.iter().find(|key| key.kid == kid)
+    }
+
+    /// Public keys for the JWKS endpoint - shared secrets are never published
+    pub fn jwks(&self) -> JwkSet {
+        JwkSet {
+            keys: self.keys.iter().filter_map(|key| key.public_jwk.clone()).collect(),
+        }
+    }
+
+    pub fn uses_default_secret(&self) -> bool {
+        self.keys.iter().any(|key| key.is_default_secret)
+    }
+}
+
+impl JwtKey {
+    /// Shared-secret key (HS256/HS384/HS512)
+    pub fn from_secret(kid: &str, algorithm: Algorithm, secret: &[u8]) -> Result<Self> {
+        if !matches!(algorithm, Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512) {
+            bail!("{algorithm:?} is not a shared-secret algorithm");
+        }
+        if secret.is_empty() {
+            bail!("JWT secret for '{kid}' is empty");
+        }
+        Ok(Self {
+            kid: kid.to_string(),
+            algorithm,
+            encoding_key: EncodingKey::from_secret(secret),
+            decoding_key: DecodingKey::from_secret(secret),
+            public_jwk: None,
+            is_default_secret: secret == DEFAULT_JWT_SECRET.as_bytes(),
+        })
+    }
+
+    /// Asymmetric key from a PEM-encoded private key (RS256/RS384/RS512 or EdDSA/Ed25519)
+    pub fn from_private_pem(kid: &str, algorithm: Algorithm, private_pem: &[u8]) -> Result<Self> {
+        let parsed = pem::parse(private_pem).context("Private key is not valid PEM")?;
+        let der = parsed.contents();
+
+        let (encoding_key, decoding_key, public_jwk) = match algorithm {
+            Algorithm::RS256 | Algorithm::RS384 | Algorithm::RS512 => {
+                let key_pair = match parsed.tag() {
+                    "RSA PRIVATE KEY" => RsaKeyPair::from_der(der),
+                    _ => RsaKeyPair::from_pkcs8(der),
+                }
+                .map_err(|e| anyhow!("Invalid RSA private key: {e}"))?;
+                let public = RsaPublicKeyComponents::<Vec<u8>>::from(key_pair.public());
+                let decoding_key = DecodingKey::from_rsa_raw_components(&public.n, &public.e);
+                let jwk = PublicJwk {
+                    kty: "RSA".to_string(),
+                    kid: kid.to_string(),
+                    alg: format!("{algorithm:?}"),
+                    key_use: "sig".to_string(),
+                    n: Some(URL_SAFE_NO_PAD.encode(&public.n)),
+                    e: Some(URL_SAFE_NO_PAD.encode(&public.e)),
+                    crv: None,
+                    x: None,
+                };
+                (EncodingKey::from_rsa_pem(private_pem)?, decoding_key, jwk)
+            }
+            Algorithm::EdDSA => {
+                let key_pair = Ed25519KeyPair::from_pkcs8_maybe_unchecked(der)
+                    .map_err(|e| anyhow!("Invalid Ed25519 private key: {e}"))?;
+                let public = key_pair.public_key().as_ref();
+                let jwk = PublicJwk {
+                    kty: "OKP".to_string(),
+                    kid: kid.to_string(),
+                    alg: "EdDSA".to_string(),
+                    key_use: "sig".to_string(),
+                    n: None,
+                    e: None,
+                    crv: Some("Ed25519".to_string()),
+                    x: Some(URL_SAFE_NO_PAD.encode(public)),
+                };
+                (EncodingKey::from_ed_der(der), DecodingKey::from_ed_der(public), jwk)
+            }
+            other => bail!("Unsupported JWT algorithm {other:?} - use HS256, RS256 or EdDSA"),
+        };
+
+        Ok(Self {
+            kid: kid.to_string(),
+            algorithm,
+            encoding_key,
+            decoding_key,
+            public_jwk: Some(public_jwk),
+            is_default_secret: false,
+        })
+    }
+
+    fn from_material(kid: &str, algorithm: Algorithm, material: &[u8]) -> Result<Self> {
+        match algorithm {
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                Self::from_secret(kid, algorithm, material.trim_ascii())
+            }
+            _ => Self::from_private_pem(kid, algorithm, material),
+        }
+    }
+}
+
+/// Parse `kid=ALG:source;kid2=ALG:source` into its parts
+fn parse_key_spec(spec: &str) -> Result<Vec<(String, Algorithm, String)>> {
+    spec.split(';')
+        .map(str::trim)
+        .filter(|entry| !entry.is_empty())
+        .map(|entry| {
+            let (kid, rest) = entry
+                .split_once('=')
+                .ok_or_else(|| anyhow!("JWT_KEYS entry '{entry}' must look like kid=ALG:source"))?;
+            let (algorithm, source) = rest
+                .split_once(':')
+                .ok_or_else(|| anyhow!("JWT_KEYS entry '{entry}' is missing the key source"))?;
+            let algorithm = algorithm
+                .trim()
+                .parse::<Algorithm>()
+                .map_err(|_| anyhow!("Unknown JWT algorithm '{algorithm}' for key '{kid}'"))?;
+            Ok((kid.trim().to_string(), algorithm, source.trim().to_string()))
+        })
+        .collect()
+}
+
+/// Read key material from `file:/path` or `env:VAR`
+fn read_key_source(source: &str) -> Result<Vec<u8>> {
+    if let Some(path) = source.strip_prefix("file:") {
+        std::fs::read(path).with_context(|| format!("Cannot read key file '{path}'"))
+    } else if let Some(name) = source.strip_prefix("env:") {
+        env::var(name)
+            .map(String::into_bytes)
+            .with_context(|| format!("Environment variable '{name}' is not set"))
+    } else {
+        bail!("Key source '{source}' must start with file: or env:")
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+
+    pub(crate) fn ed25519_pem() -> Vec<u8> {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        pem::encode(&pem::Pem::new("PRIVATE KEY", pkcs8.as_ref().to_vec())).into_bytes()
+    }
+
+    #[test]
+    fn test_parse_key_spec() {
+        let parsed = parse_key_spec("2026-10=EdDSA:file:/keys/jwt.pem; legacy=HS256:env:JWT_SECRET").unwrap();
+
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed[0], ("2026-10".to_string(), Algorithm::EdDSA, "file:/keys/jwt.pem".to_string()));
+        assert_eq!(parsed[1], ("legacy".to_string(), Algorithm::HS256, "env:JWT_SECRET".to_string()));
+        assert!(parse_key_spec("broken").is_err());
+        assert!(parse_key_spec("k=XX999:env:A").is_err());
+    }
+
+    #[test]
+    fn test_jwks_publishes_only_public_keys() {
+        let ed = JwtKey::from_private_pem("ed-1", Algorithm::EdDSA, &ed25519_pem()).unwrap();
+        let hs = JwtKey::from_secret("hs-1", Algorithm::HS256, b"a-long-enough-shared-secret").unwrap();
+        let keys = JwtKeySet::new(vec![ed, hs], "ed-1").unwrap();
+
+        let jwks = keys.jwks();
+        assert_eq!(jwks.keys.len(), 1);
+        assert_eq!(jwks.keys[0].kid, "ed-1");
+        assert_eq!(jwks.keys[0].crv.as_deref(), Some("Ed25519"));
+        assert_eq!(URL_SAFE_NO_PAD.decode(jwks.keys[0].x.as_ref().unwrap()).unwrap().len(), 32);
+    }
+
+    #[test]
+    fn test_key_set_validation() {
+        let hs = || JwtKey::from_secret("hs-1", Algorithm::HS256, b"secret").unwrap();
+
+        assert!(JwtKeySet::new(vec![hs()], "missing").is_err());
+        assert!(JwtKeySet::new(vec![hs(), hs()], "hs-1").is_err());
+        assert!(JwtKey::from_secret("rs", Algorithm::RS256, b"secret").is_err());
+
+        let default = JwtKey::from_secret(LEGACY_KID, Algorithm::HS256, DEFAULT_JWT_SECRET.as_bytes()).unwrap();
+        let keys = JwtKeySet::new(vec![default], LEGACY_KID).unwrap();
+        assert!(keys.uses_default_secret());
+        assert_eq!(keys.verification_key(None).unwrap().kid, LEGACY_KID);
+    }
+}
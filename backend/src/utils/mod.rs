@@ -1,4 +1,10 @@
+pub mod api_keys;
 pub mod auth;
+pub mod client_ip;
+pub mod gs1;
+pub mod jwt_keys;
+pub mod password;
+pub mod roles;
 pub mod timezone;
 pub mod user_management;
 
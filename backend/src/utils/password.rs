@@ -0,0 +1,75 @@
+use anyhow::{anyhow, Result};
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString};
+use argon2::Argon2;
+use subtle::ConstantTimeEq;
+
+/// Outcome of checking a password against a stored `tbl_user.pword` value
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PasswordCheck {
+    /// Password matches; `needs_rehash` is set for legacy plaintext rows
+    Valid { needs_rehash: bool },
+    Invalid,
+}
+
+/// Hash a password with Argon2id (PHC string format, random salt)
+pub fn hash_password(password: &str) -> Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    Argon2::default()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| anyhow!("Failed to hash password: {e}"))
+}
+
+/// Whether a stored value is an Argon2 hash rather than a legacy plaintext password
+pub fn is_password_hash(stored: &str) -> bool {
+    stored.starts_with("$argon2")
+}
+
+/// Verify a password against a stored value.
+/// Argon2 hashes are verified by the argon2 crate; legacy plaintext values are compared in constant time.
+pub fn verify_password(password: &str, stored: &str) -> PasswordCheck {
+    if is_password_hash(stored) {
+        let Ok(parsed) = PasswordHash::new(stored) else {
+            return PasswordCheck::Invalid;
+        };
+        return match Argon2::default().verify_password(password.as_bytes(), &parsed) {
+            Ok(()) => PasswordCheck::Valid { needs_rehash: false },
+            Err(_) => PasswordCheck::Invalid,
+        };
+    }
+
+    // Legacy plaintext row - an empty stored password never matches
+    if !stored.is_empty() && bool::from(password.as_bytes().ct_eq(stored.as_bytes())) {
+        PasswordCheck::Valid { needs_rehash: true }
+    } else {
+        PasswordCheck::Invalid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hash_and_verify() {
+        let hash = hash_password("Secret123").unwrap();
+
+        assert!(is_password_hash(&hash));
+        assert!(hash.starts_with("$argon2id$"));
+        assert_ne!(hash, hash_password("Secret123").unwrap(), "salts must differ");
+        assert_eq!(verify_password("Secret123", &hash), PasswordCheck::Valid { needs_rehash: false });
+        assert_eq!(verify_password("secret123", &hash), PasswordCheck::Invalid);
+    }
+
+    #[test]
+    fn test_legacy_plaintext_needs_rehash() {
+        assert_eq!(verify_password("Secret123", "Secret123"), PasswordCheck::Valid { needs_rehash: true });
+        assert_eq!(verify_password("Secret12", "Secret123"), PasswordCheck::Invalid);
+        assert_eq!(verify_password("", ""), PasswordCheck::Invalid);
+    }
+
+    #[test]
+    fn test_malformed_hash_is_rejected() {
+        assert_eq!(verify_password("anything", "$argon2id$broken"), PasswordCheck::Invalid);
+    }
+}
@@ -0,0 +1,124 @@
+use crate::types::Role;
+
+/// Parse `LDAP_ROLE_GROUPS` into (role, AD group CN) pairs.
+///
+/// Format: `supervisor=WMS Supervisors|Production Leads;admin=WMS Admins`
+pub fn parse_role_groups(config: &str) -> Vec<(Role, String)> {
+    config
+        .split(';')
+        .filter_map(|entry| entry.split_once('='))
+        .filter_map(|(role, groups)| Role::parse(role).map(|role| (role, groups)))
+        .flat_map(|(role, groups)| {
+            groups
+                .split('|')
+                .map(str::trim)
+                .filter(|group| !group.is_empty())
+                .map(move |group| (role, group.to_string()))
+        })
+        .collect()
+}
+
+/// Derive roles from LDAP `memberOf` DNs using the configured group mapping.
+/// Every authenticated user is at least a picker.
+pub fn roles_from_ldap_groups(member_of: &[String], role_groups: &[(Role, String)]) -> Vec<Role> {
+    let group_names: Vec<String> = member_of
+        .iter()
+        .map(|dn| group_cn(dn).to_lowercase())
+        .collect();
+
+    let mut roles = vec![Role::Picker];
+    for (role, group) in role_groups {
+        if group_names.contains(&group.to_lowercase()) && !roles.contains(role) {
+            roles.push(*role);
+        }
+    }
+    roles
+}
+
+/// Derive roles from a comma/semicolon separated tbl_user column value.
+/// Every authenticated user is at least a picker.
+pub fn roles_from_column(value: Option<&str>) -> Vec<Role> {
+    let mut roles = vec![Role::Picker];
+    for role in value
+        .unwrap_or("")
+        .split([',', ';'])
+        .filter_map(Role::parse)
+    {
+        if !roles.contains(&role) {
+            roles.push(role);
+        }
+    }
+    roles
+}
+
+/// Serialize roles for storage (inverse of `roles_from_column`)
+pub fn roles_to_column(roles: &[Role]) -> String {
+    roles.iter().map(Role::as_str).collect::<Vec<_>>().join(",")
+}
+
+/// Whether a user holding `roles` satisfies any of the `required` roles (admins satisfy everything)
+pub fn has_any_role(roles: &[Role], required: &[Role]) -> bool {
+    roles.contains(&Role::Admin) || required.iter().any(|role| roles.contains(role))
+}
+
+/// Extract the CN of a group DN (`CN=WMS Supervisors,OU=Groups,DC=NWFTH,DC=com` -> `WMS Supervisors`)
+fn group_cn(dn: &str) -> &str {
+    dn.split(',')
+        .next()
+        .and_then(|rdn| rdn.split_once('='))
+        .filter(|(attr, _)| attr.trim().eq_ignore_ascii_case("cn"))
+        .map(|(_, value)| value.trim())
+        .unwrap_or(dn)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_role_groups() {
+        let mapping = parse_role_groups("supervisor=WMS Supervisors|Production Leads; admin = WMS Admins;bogus=X");
+
+        assert_eq!(
+            mapping,
+            vec![
+                (Role::Supervisor, "WMS Supervisors".to_string()),
+                (Role::Supervisor, "Production Leads".to_string()),
+                (Role::Admin, "WMS Admins".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_roles_from_ldap_groups() {
+        let mapping = parse_role_groups("supervisor=WMS Supervisors;inventory_controller=Inventory");
+        let member_of = vec![
+            "CN=wms supervisors,OU=Groups,DC=NWFTH,DC=com".to_string(),
+            "CN=Everyone,OU=Groups,DC=NWFTH,DC=com".to_string(),
+        ];
+
+        assert_eq!(
+            roles_from_ldap_groups(&member_of, &mapping),
+            vec![Role::Picker, Role::Supervisor]
+        );
+        assert_eq!(roles_from_ldap_groups(&[], &mapping), vec![Role::Picker]);
+    }
+
+    #[test]
+    fn test_roles_from_column_round_trip() {
+        let roles = roles_from_column(Some("Supervisor, inventory controller"));
+
+        assert_eq!(roles, vec![Role::Picker, Role::Supervisor, Role::InventoryController]);
+        assert_eq!(roles_from_column(Some(&roles_to_column(&roles))), roles);
+        assert_eq!(roles_from_column(None), vec![Role::Picker]);
+    }
+
+    #[test]
+    fn test_has_any_role() {
+        let supervisor_only = [Role::Supervisor];
+
+        assert!(has_any_role(&[Role::Picker, Role::Supervisor], &supervisor_only));
+        assert!(has_any_role(&[Role::Admin], &supervisor_only));
+        assert!(!has_any_role(&[Role::Picker, Role::InventoryController], &supervisor_only));
+    }
+}
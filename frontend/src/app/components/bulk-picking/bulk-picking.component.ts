@@ -971,7 +971,12 @@ interface ProductionRun {
                           (click)="selectRunFromModal(run)">
                         <td class="table-cell tw-font-mono tw-font-bold">{{ run.run_no }}</td>
                         <td class="table-cell tw-font-mono">{{ run.formula_id }}</td>
-                        <td class="table-cell tw-text-left tw-pl-4">{{ run.formula_desc }}</td>
+                        <td class="table-cell tw-text-left tw-pl-4">
+                          {{ run.formula_desc }}
+                          <div *ngIf="run.claims?.length" class="tw-text-xs tw-text-amber-700 tw-font-semibold">
+                            Being picked by {{ claimHolders(run) }}
+                          </div>
+                        </td>
                         <td class="table-cell tw-font-semibold">{{ run.status }}</td>
                         <td class="table-cell">{{ run.batch_count }}</td>
                       </tr>
@@ -3238,7 +3243,7 @@ export class BulkPickingComponent implements AfterViewInit {
           this.isSubmittingPick.set(false);
         }
         // NEW: Handle transaction rollback errors (database is consistent, no refresh needed)
-        else if (msg.includes('TRANSACTION_ROLLED_BACK')) {
+        else if (error?.code === 'PICK_TRANSACTION_FAILED' || msg.includes('TRANSACTION_ROLLED_BACK')) {
           console.info('🔒 Transaction was safely rolled back - database is consistent, no refresh needed');
           this.errorMessage.set(this.formatUserFriendlyError(error));
           this.isSubmittingPick.set(false);
@@ -3252,7 +3257,7 @@ export class BulkPickingComponent implements AfterViewInit {
           // Don't attempt any automatic recovery for critical failures
         }
         // Handle batch completion errors with auto-refresh (legacy behavior)
-        else if (msg.includes('BATCH_ALREADY_COMPLETED') || msg.includes('This batch is already completed')) {
+        else if (error?.code === 'BATCH_ALREADY_COMPLETED' || msg.includes('This batch is already completed')) {
           console.warn('🔄 Batch already completed - loading next unpicked batch automatically...');
           this.handleBatchCompletionError(pickData, error);
           this.isSubmittingPick.set(false);
@@ -5040,6 +5045,11 @@ export class BulkPickingComponent implements AfterViewInit {
     return this.showAllRuns() ? this.modalRuns() : this.searchResultRuns();
   }
 
+  // Pickers holding claims on a run, for the run selection modal
+  claimHolders(run: BulkRunSummary): string {
+    return [...new Set((run.claims ?? []).map(claim => claim.username))].join(', ');
+  }
+
   // Story 1.4: Pagination navigation methods
   goToPage(page: number): void {
     if (page >= 1 && page <= (this.paginationInfo()?.total_pages || 1)) {
@@ -560,8 +560,8 @@ export class PutawayComponent implements AfterViewInit {
         this.clearForm();
       }
     } catch (error: any) {
-      if (error?.error?.error === 'Lot not found') {
-        this.setError(error.error.message, 'lot', { lot_no: error.error.lot_no || lotNumber });
+      if (error?.error?.error_code === 'LOT_NOT_FOUND') {
+        this.setError(error.error.message, 'lot', { lot_no: lotNumber });
       } else {
         this.setError(`Error searching lot: ${error}`, 'system');
       }
@@ -777,33 +777,33 @@ export class PutawayComponent implements AfterViewInit {
       const errorResponse = error.error;
 
       // Handle specific error types
-      switch (errorResponse.error) {
-        case 'Insufficient quantity':
+      switch (errorResponse.error_code) {
+        case 'INSUFFICIENT_QUANTITY':
           this.setError(errorResponse.message, 'quantity', {
             requested: errorResponse.requested,
             available: errorResponse.available
           });
           break;
 
-        case 'Invalid bin':
+        case 'INVALID_BIN':
           this.setError(errorResponse.message, 'bin', {
             bin_no: errorResponse.bin_no || this.putawayForm.get('toBinNumber')?.value,
             location: errorResponse.location || this.selectedLot()?.location
           });
           break;
 
-        case 'Lot not found':
+        case 'LOT_NOT_FOUND':
           this.setError(errorResponse.message, 'lot', {
             lot_no: errorResponse.lot_no || this.selectedLot()?.lotNumber
           });
           break;
 
-        case 'Validation error':
+        case 'VALIDATION_ERROR':
           this.setError(errorResponse.message, 'validation');
           break;
 
-        case 'Transaction error':
-        case 'Database error':
+        case 'TRANSACTION_FAILED':
+        case 'DATABASE_ERROR':
           this.setError('System error occurred. Please try again or contact support.', 'system');
           break;
 
@@ -1,7 +1,7 @@
 import { Injectable } from '@angular/core';
 import { HttpInterceptor, HttpRequest, HttpHandler, HttpEvent, HttpErrorResponse } from '@angular/common/http';
 import { Observable, throwError } from 'rxjs';
-import { catchError } from 'rxjs/operators';
+import { catchError, switchMap } from 'rxjs/operators';
 import { Router } from '@angular/router';
 import { AuthService } from '../services/auth.service';
 
@@ -13,51 +13,86 @@ export class AuthInterceptor implements HttpInterceptor {
   ) {}
 
   intercept(req: HttpRequest<any>, next: HttpHandler): Observable<HttpEvent<any>> {
-    // Skip interceptor for login and health check endpoints
-    const isAuthEndpoint = req.url.includes('/auth/login') || req.url.includes('/health');
-    
-    if (!isAuthEndpoint) {
-      // Check if we have a valid session before making the request
-      if (!this.authService.isValidSession()) {
-        console.warn('🚫 No valid session for API request, redirecting to login');
-        this.authService.logout();
-        this.router.navigate(['/login']);
-        return throwError(() => new Error('No valid session'));
-      }
+    // Skip interceptor for login, refresh, logout and health check endpoints
+    const isAuthEndpoint = req.url.includes('/auth/login')
+      || req.url.includes('/auth/refresh')
+      || req.url.includes('/auth/logout')
+      || req.url.includes('/health');
 
-      // Clone the request and add authorization header
-      const authHeaders = this.authService.getAuthHeaders();
-      if (authHeaders['Authorization']) {
-        req = req.clone({
-          setHeaders: authHeaders
-        });
-        console.debug('🔐 Added Authorization header to request:', req.url);
-      }
+    if (isAuthEndpoint) {
+      return next.handle(req).pipe(catchError((error: HttpErrorResponse) => this.handleError(error, isAuthEndpoint)));
+    }
+
+    // Check if we have a valid session before making the request
+    if (!this.authService.isValidSession()) {
+      console.warn('🚫 No valid session for API request, redirecting to login');
+      this.authService.logout();
+      this.router.navigate(['/login']);
+      return throwError(() => new Error('No valid session'));
     }
 
-    return next.handle(req).pipe(
-      catchError((error: HttpErrorResponse) => {
-        // Handle 401 Unauthorized responses
-        if (error.status === 401 && !isAuthEndpoint) {
-          console.warn('🚫 Received 401 Unauthorized, token may be expired');
+    // Attach a fresh access token (refreshing the session first if the current one expired)
+    return this.authService.getValidAccessToken().pipe(
+      switchMap(token => {
+        if (!token) {
+          console.warn('🚫 Session refresh failed, redirecting to login');
           this.authService.logout();
           this.router.navigate(['/login']);
-          return throwError(() => error);
+          return throwError(() => new Error('Session expired'));
         }
 
-        // Handle 403 Forbidden responses
-        if (error.status === 403) {
-          console.warn('🚫 Access forbidden (403)');
-          // Could show a "insufficient permissions" message
-        }
+        console.debug('🔐 Added Authorization header to request:', req.url);
+        return next.handle(this.withToken(req, token)).pipe(
+          catchError((error: HttpErrorResponse) => {
+            // Access token was revoked or expired in flight - refresh once and retry
+            if (error.status === 401) {
+              console.warn('🚫 Received 401 Unauthorized, attempting session refresh');
+              return this.authService.refreshSession().pipe(
+                switchMap(refreshed => {
+                  const newToken = this.authService.getStoredToken();
+                  if (!refreshed || !newToken) {
+                    return throwError(() => error);
+                  }
+                  return next.handle(this.withToken(req, newToken));
+                })
+              );
+            }
+            return throwError(() => error);
+          })
+        );
+      }),
+      catchError((error: HttpErrorResponse) => this.handleError(error, isAuthEndpoint))
+    );
+  }
 
-        // Handle network errors
-        if (error.status === 0) {
-          console.error('🌐 Network error - backend server may be down');
-        }
+  private withToken(req: HttpRequest<any>, token: string): HttpRequest<any> {
+    return req.clone({
+      setHeaders: {
+        'Authorization': `Bearer ${token}`
+      }
+    });
+  }
 
-        return throwError(() => error);
-      })
-    );
+  private handleError(error: HttpErrorResponse, isAuthEndpoint: boolean): Observable<never> {
+    // Handle 401 Unauthorized responses
+    if (error.status === 401 && !isAuthEndpoint) {
+      console.warn('🚫 Received 401 Unauthorized, session is no longer valid');
+      this.authService.logout();
+      this.router.navigate(['/login']);
+      return throwError(() => error);
+    }
+
+    // Handle 403 Forbidden responses
+    if (error.status === 403) {
+      console.warn('🚫 Access forbidden (403)');
+      // Could show a "insufficient permissions" message
+    }
+
+    // Handle network errors
+    if (error.status === 0) {
+      console.error('🌐 Network error - backend server may be down');
+    }
+
+    return throwError(() => error);
   }
-}
\ No newline at end of file
+}
@@ -1,7 +1,7 @@
 import { Injectable } from '@angular/core';
 import { HttpClient } from '@angular/common/http';
 import { BehaviorSubject, Observable, of, throwError } from 'rxjs';
-import { catchError, map } from 'rxjs/operators';
+import { catchError, finalize, map, shareReplay, switchMap } from 'rxjs/operators';
 import { ConfigService } from './config.service';
 import { DebugService } from './debug.service';
 
@@ -17,6 +17,8 @@ export interface AuthToken {
   token_type: string;
   expires_in: number;
   expires_at: number;
+  refresh_token: string;
+  refresh_expires_at: number;
   user_id: string;
   username: string;
 }
@@ -30,12 +32,18 @@ export interface LoginResponse {
   success: boolean;
   message: string;
   data?: LoginData;
+  error_code?: string;
+  request_id?: string;
 }
 
 export interface ApiResponse<T> {
   success: boolean;
   data?: T;
   message: string;
+  error_code?: string;
+  warning?: string;
+  request_id?: string;
+  errors?: { field: string; message: string }[];
 }
 
 @Injectable({
@@ -54,6 +62,12 @@ export class AuthService {
 
   private sessionExpiryWarningShown = false;
 
+  // Access tokens are refreshed this many seconds before they expire
+  private readonly ACCESS_TOKEN_REFRESH_MARGIN = 30;
+
+  // In-flight refresh shared by concurrent requests (refresh tokens are single-use)
+  private refreshInFlight$: Observable<boolean> | null = null;
+
   constructor(
     private http: HttpClient,
     private configService: ConfigService,
@@ -90,6 +104,10 @@ export class AuthService {
         return response;
       }),
       catchError(error => {
+        // Rejected logins (401/429/503) carry the standard envelope; keep them on the success path
+        if (error.error?.message) {
+          return of(error.error as LoginResponse);
+        }
         console.error('Login error:', error);
         return throwError(() => ({
           success: false,
@@ -100,6 +118,20 @@ export class AuthService {
   }
 
   logout(): void {
+    // Revoke the session server-side so the refresh token and any copies of the access token stop working
+    const tokenData = this.getStoredTokenData();
+    if (tokenData?.refresh_token) {
+      this.http.post<ApiResponse<boolean>>(`${this.configService.getApiUrl()}/auth/logout`, {
+        refresh_token: tokenData.refresh_token
+      }).pipe(
+        catchError(() => of(null))
+      ).subscribe();
+    }
+
+    this.clearSession();
+  }
+
+  private clearSession(): void {
     localStorage.removeItem(this.TOKEN_KEY);
     localStorage.removeItem(this.TOKEN_DATA_KEY);
     localStorage.removeItem(this.USER_KEY);
@@ -109,6 +141,72 @@ export class AuthService {
     this.debug.info('AuthService', 'User logged out and session cleared');
   }
 
+  /**
+   * Exchange the stored refresh token for a new access token.
+   * The refresh token rotates on every use, so concurrent callers share one request.
+   */
+  refreshSession(): Observable<boolean> {
+    if (this.refreshInFlight$) {
+      return this.refreshInFlight$;
+    }
+
+    const tokenData = this.getStoredTokenData();
+    if (!tokenData?.refresh_token) {
+      return of(false);
+    }
+
+    this.refreshInFlight$ = this.http.post<LoginResponse>(`${this.configService.getApiUrl()}/auth/refresh`, {
+      refresh_token: tokenData.refresh_token
+    }).pipe(
+      map(response => {
+        if (response.success && response.data) {
+          this.storeAuthData(response.data);
+          this.currentUserSubject.next(response.data.user);
+          this.isAuthenticatedSubject.next(true);
+          this.debug.info('AuthService', 'Session refreshed');
+          return true;
+        }
+        console.warn('🚫 Session refresh rejected:', response.message);
+        this.clearSession();
+        return false;
+      }),
+      catchError(error => {
+        console.error('❌ Failed to refresh session');
+        if (error.status === 401) {
+          this.clearSession();
+        }
+        return of(false);
+      }),
+      finalize(() => {
+        this.refreshInFlight$ = null;
+      }),
+      shareReplay(1)
+    );
+
+    return this.refreshInFlight$;
+  }
+
+  /**
+   * Get a usable access token, refreshing the session first if the current one is about to expire
+   */
+  getValidAccessToken(): Observable<string | null> {
+    const token = this.getStoredToken();
+    const tokenData = this.getStoredTokenData();
+
+    if (token && tokenData && this.isAccessTokenFresh(tokenData)) {
+      return of(token);
+    }
+
+    return this.refreshSession().pipe(
+      map(refreshed => (refreshed ? this.getStoredToken() : null))
+    );
+  }
+
+  private isAccessTokenFresh(tokenData: AuthToken): boolean {
+    const now = Math.floor(Date.now() / 1000);
+    return tokenData.expires_at - now > this.ACCESS_TOKEN_REFRESH_MARGIN;
+  }
+
   testConnection(): Observable<boolean> {
     return this.http.get<ApiResponse<string>>(`${this.configService.getApiUrl()}/health`).pipe(
       map(response => response.success),
@@ -154,14 +252,14 @@ export class AuthService {
     const tokenData = this.getStoredTokenData();
     
     // Check if token is valid before using it
-    if (token && tokenData && this.isTokenValid(tokenData)) {
+    if (token && tokenData && this.isTokenValid(tokenData) && this.isAccessTokenFresh(tokenData)) {
       return {
         'Authorization': `Bearer ${token}`,
         'Content-Type': 'application/json'
       };
     } else {
-      // Token is invalid or expired, logout
-      if (token || tokenData) {
+      // Session is invalid or expired, logout (an expired access token alone is refreshed by the interceptor)
+      if ((token || tokenData) && !(tokenData && this.isTokenValid(tokenData))) {
         console.warn('🚫 Token expired or invalid, logging out');
         this.logout();
       }
@@ -183,14 +281,15 @@ export class AuthService {
     return null;
   }
 
+  // A session stays valid while its refresh token is valid; access tokens are short-lived and refreshed on demand
   isTokenValid(tokenData: AuthToken): boolean {
     const now = Math.floor(Date.now() / 1000); // Current time in seconds
-    const expiresAt = tokenData.expires_at;
+    const expiresAt = tokenData.refresh_expires_at ?? tokenData.expires_at;
     const timeUntilExpiry = expiresAt - now;
     
-    // Token is expired
+    // Session is expired
     if (timeUntilExpiry <= 0) {
-      console.warn('🕐 JWT token has expired');
+      console.warn('🕐 Session has expired');
       return false;
     }
     
@@ -220,7 +319,7 @@ export class AuthService {
     if (!tokenData) return 0;
     
     const now = Math.floor(Date.now() / 1000);
-    return Math.max(0, tokenData.expires_at - now);
+    return Math.max(0, (tokenData.refresh_expires_at ?? tokenData.expires_at) - now);
   }
 
   // Check if user needs to reauthenticate
@@ -230,10 +329,16 @@ export class AuthService {
       return of(false);
     }
 
-    // Verify with server
-    return this.http.get<ApiResponse<boolean>>(`${this.configService.getApiUrl()}/auth/status`, {
-      headers: this.getAuthHeaders()
-    }).pipe(
+    // Verify with server (refreshing the access token first if needed)
+    return this.getValidAccessToken().pipe(
+      switchMap(token => {
+        if (!token) {
+          return throwError(() => new Error('Session refresh failed'));
+        }
+        return this.http.get<ApiResponse<boolean>>(`${this.configService.getApiUrl()}/auth/status`, {
+          headers: this.getAuthHeaders()
+        });
+      }),
       map(response => {
         if (!response.success || !response.data) {
           console.warn('🚫 Server rejected authentication status');
@@ -4,12 +4,17 @@ import { Observable, catchError, throwError, tap, of } from 'rxjs';
 import { map, switchMap } from 'rxjs/operators';
 import { environment } from '../../environments/environment';
 import { AuthService } from './auth.service';
+import { IDEMPOTENCY_KEY_HEADER, newIdempotencyKey, retryOnNetworkError } from './idempotency-key';
 
 // API Response models
 export interface ApiResponse<T> {
   success: boolean;
   data?: T;
   message: string;
+  error_code?: string;
+  warning?: string;
+  request_id?: string;
+  errors?: { field: string; message: string }[];
 }
 
 // Bulk Run models
@@ -150,12 +155,22 @@ export interface BulkRunFormData {
 }
 
 // New interfaces for modal selection (Story 1.1.1)
+/** A picker's lease on a run (line_id null) or on one ingredient line */
+export interface RunClaim {
+  run_no: number;
+  line_id: number | null;
+  username: string;
+  claimed_at: string;
+  expires_at: string;
+}
+
 export interface BulkRunSummary {
   run_no: number;
   formula_id: string;
   formula_desc: string;
   status: string;
   batch_count: number;
+  claims?: RunClaim[];      // Active claims - "being picked by ..."
 }
 
 export interface BulkRunListResponse {
@@ -309,6 +324,101 @@ export interface BulkRunStatusResponse {
   last_modified?: string;
 }
 
+/** Pick confirmation captured while the handheld was offline, replayed in order on reconnect */
+/** One step of a run's FEFO pick plan: take `bags` from this lot/bin for this batch */
+export interface PickPlanStep {
+  step_no: number;
+  row_num: number;
+  line_id: number;
+  item_key: string;
+  lot_no: string;
+  bin_no: string;
+  bags: number;
+  qty_kg: number;
+  expiry_date: string | null;
+  status: 'PENDING' | 'PICKED';
+}
+
+/** Bags of a batch no available lot could cover when the plan was made */
+export interface PickPlanShortage {
+  row_num: number;
+  line_id: number;
+  item_key: string;
+  required_bags: number;
+  short_bags: number;
+  short_kg: number;
+}
+
+export interface PickPlan {
+  run_no: number;
+  generated_by: string;
+  generated_at: string;
+  steps: PickPlanStep[];
+  shortages: PickPlanShortage[];
+}
+
+/** One ingredient line's requirement against available stock */
+export interface IngredientStock {
+  line_id: number;
+  item_key: string;
+  description: string | null;
+  pack_size: number;
+  required_bags: number;
+  required_kg: number;
+  available_kg: number;     // Unexpired, nettable stock net of QtyCommitSales
+  short_bags: number;
+  short_kg: number;
+}
+
+/** Dry run of a pick plan; nothing is stored */
+export interface PickPlanPreview {
+  run_no: number;
+  generated_at: string;
+  can_fulfil: boolean;
+  ingredients: IngredientStock[];
+  shortages: PickPlanShortage[];
+  steps: PickPlanStep[];
+}
+
+/** One lot/bin of a pick split across several lots */
+export interface LotAllocation {
+  lot_no: string;
+  bin_no: string;
+  bags: number;
+}
+
+export interface QueuedPick {
+  client_id: string;        // Unique per queued pick; doubles as its idempotency key
+  run_no: number;
+  row_num: number;
+  line_id: number;
+  picked_bulk_qty: number;
+  lot_no: string;           // Empty when allocations are given
+  bin_no: string;
+  allocations?: LotAllocation[];
+  captured_at?: string;
+}
+
+export type SyncOutcome = 'applied' | 'already_applied' | 'conflict' | 'failed' | 'skipped';
+
+export interface QueuedPickResult {
+  client_id: string;
+  run_no: number;
+  outcome: SyncOutcome;
+  code?: string;            // BATCH_COMPLETED, LOT_EXHAUSTED, RUN_NOT_OPEN, ...
+  message: string;
+  response?: any;
+}
+
+export interface OfflinePickSyncResponse {
+  applied: number;
+  already_applied: number;
+  conflicts: number;
+  failed: number;
+  skipped: number;
+  results: QueuedPickResult[];
+}
+
 @Injectable({
   providedIn: 'root'
 })
@@ -321,7 +431,6 @@ export class BulkRunsService {
    * Get HTTP headers with authentication for API requests
    */
   private getAuthHeaders(): HttpHeaders {
-    const currentUser = this.authService.getCurrentUser();
     const token = this.authService.getStoredToken();
 
     let headers = new HttpHeaders({
@@ -333,11 +442,6 @@ export class BulkRunsService {
       headers = headers.set('Authorization', `Bearer ${token}`);
     }
 
-    // Add user identification header
-    if (currentUser?.username) {
-      headers = headers.set('x-user-id', currentUser.username);
-    }
-
     return headers;
   }
 
@@ -527,7 +631,7 @@ export class BulkRunsService {
         catchError(error => {
           this.isLoading.set(false);
           // Check if it's an HTTP error or our custom error
-          const errorMsg = error.message || error.error?.message || `Failed to get form data for run ${runNo}`;
+          const errorMsg = error.error?.message || error.message || `Failed to get form data for run ${runNo}`;
           this.errorMessage.set(errorMsg);
           return throwError(() => new Error(errorMsg));
         })
@@ -1057,39 +1161,44 @@ export class BulkRunsService {
 
   /**
    * Confirm pick operation with backend API (BME4 Compatible)
-   * Implements the 4-table atomic transaction pattern
+   * Implements the 4-table atomic transaction pattern.
+   * With `allocations` the bags are split across several lots, committed together under one BT document.
    */
   confirmPick(pickData: {
     runNo: string;
     rowNum: number;
     lineId: number;
     pickedBulkQty: number;
-    lotNo: string;
-    binNo: string;
+    lotNo?: string;
+    binNo?: string;
+    allocations?: LotAllocation[];
   }): Observable<ApiResponse<any>> {
     const url = `${this.baseUrl}/${pickData.runNo}/confirm-pick`;
 
     const currentUser = this.authService.getCurrentUser();
+    const isSplit = !!pickData.allocations?.length;
     const requestBody = {
       row_num: pickData.rowNum,
       line_id: pickData.lineId,
       picked_bulk_qty: pickData.pickedBulkQty,
-      lot_no: pickData.lotNo,
-      bin_no: pickData.binNo,
+      lot_no: isSplit ? undefined : pickData.lotNo,
+      bin_no: isSplit ? undefined : pickData.binNo,
+      allocations: isSplit ? pickData.allocations : undefined,
       user_id: currentUser?.username || undefined // Add user_id to request body
     };
 
+    // One key per confirmation: automatic retries replay the first result instead of picking twice
     const httpOptions = {
-      headers: this.getAuthHeaders()
+      headers: this.getAuthHeaders().set(IDEMPOTENCY_KEY_HEADER, newIdempotencyKey())
     };
 
-
     return this.http.post<ApiResponse<any>>(url, requestBody, httpOptions)
       .pipe(
+        retryOnNetworkError(),
         catchError(error => {
           const propagated = {
             message: error?.error?.message || 'Failed to confirm pick operation',
-            code: error?.error?.code || null,
+            code: error?.error?.error_code || error?.error?.code || null,
             status: error?.status,
           };
           return throwError(() => propagated);
@@ -1103,6 +1212,25 @@ export class BulkRunsService {
       );
   }
 
+  /**
+   * Replay picks queued offline. Each pick is revalidated and applied on its own;
+   * the caller drops applied / already_applied / conflict entries and keeps failed / skipped ones.
+   */
+  syncOfflinePicks(picks: QueuedPick[]): Observable<ApiResponse<OfflinePickSyncResponse>> {
+    return this.http.post<ApiResponse<OfflinePickSyncResponse>>(
+      `${this.baseUrl}/offline-picks/sync`,
+      { picks },
+      { headers: this.getAuthHeaders() }
+    ).pipe(
+      retryOnNetworkError(),
+      catchError(error => throwError(() => ({
+        message: error?.error?.message || 'Failed to sync offline picks',
+        code: error?.error?.error_code || null,
+        status: error?.status,
+      })))
+    );
+  }
+
   /**
    * Validate pick request with backend for data synchronization checks
    * Used to detect data synchronization issues before actual pick confirmation
@@ -1229,6 +1357,75 @@ export class BulkRunsService {
       );
   }
 
+  /**
+   * Claim a run, or one ingredient line, so other pickers are turned away.
+   * Claiming again renews the lease; call it periodically while picking.
+   * Fails with error_code RUN_CLAIMED when someone else holds an overlapping claim.
+   */
+  claimRun(runNo: number, lineId?: number, takeOver = false): Observable<ApiResponse<RunClaim>> {
+    return this.http.post<ApiResponse<RunClaim>>(
+      `${this.baseUrl}/${runNo}/claim`,
+      { line_id: lineId ?? null, take_over: takeOver },
+      { headers: this.getAuthHeaders() }
+    ).pipe(
+      catchError(error => throwError(() => ({
+        message: error?.error?.message || 'Failed to claim run',
+        code: error?.error?.error_code || null,
+        status: error?.status,
+      })))
+    );
+  }
+
+  /**
+   * Build (or rebuild) the run's FEFO pick plan. Shortages come back in the plan
+   * and as the response warning.
+   */
+  generatePickPlan(runNo: number): Observable<ApiResponse<PickPlan>> {
+    return this.http.post<ApiResponse<PickPlan>>(
+      `${this.baseUrl}/${runNo}/pick-plan`,
+      {},
+      { headers: this.getAuthHeaders() }
+    ).pipe(
+      catchError(error => throwError(() => ({
+        message: error?.error?.message || 'Failed to generate pick plan',
+        code: error?.error?.error_code || null,
+        status: error?.status,
+      })))
+    );
+  }
+
+  getPickPlan(runNo: number): Observable<ApiResponse<PickPlan>> {
+    return this.http.get<ApiResponse<PickPlan>>(`${this.baseUrl}/${runNo}/pick-plan`, {
+      headers: this.getAuthHeaders()
+    });
+  }
+
+  previewPickPlan(runNo: number): Observable<ApiResponse<PickPlanPreview>> {
+    return this.http.get<ApiResponse<PickPlanPreview>>(`${this.baseUrl}/${runNo}/pick-plan/preview`, {
+      headers: this.getAuthHeaders()
+    });
+  }
+
+  /** Shortage report of the pick plan preview as a CSV file */
+  downloadShortageCsv(runNo: number): Observable<Blob> {
+    return this.http.get(`${this.baseUrl}/${runNo}/pick-plan/preview`, {
+      headers: this.getAuthHeaders(),
+      params: new HttpParams().set('format', 'csv'),
+      responseType: 'blob'
+    });
+  }
+
+  releaseRunClaim(runNo: number, lineId?: number): Observable<ApiResponse<void>> {
+    let params = new HttpParams();
+    if (lineId !== undefined) {
+      params = params.set('line_id', lineId.toString());
+    }
+    return this.http.delete<ApiResponse<void>>(`${this.baseUrl}/${runNo}/claim`, {
+      headers: this.getAuthHeaders(),
+      params
+    });
+  }
+
   /**
    * **REVERT STATUS SERVICE** - Revert bulk run status from PRINT back to NEW
    * Used when user wants to make changes after run completion
@@ -0,0 +1,28 @@
+import { Observable, retry, throwError, timer } from 'rxjs';
+
+/** Header that makes a retried write replay its first result instead of committing again */
+export const IDEMPOTENCY_KEY_HEADER = 'Idempotency-Key';
+
+/**
+ * New key for one logical write (a pick confirmation or a bin transfer).
+ * crypto.randomUUID is only available in secure contexts, so handhelds on plain HTTP fall back.
+ */
+export function newIdempotencyKey(): string {
+  if (typeof crypto !== 'undefined' && typeof crypto.randomUUID === 'function') {
+    return crypto.randomUUID();
+  }
+  return `${Date.now().toString(36)}-${Math.random().toString(36).slice(2, 12)}`;
+}
+
+/**
+ * Retry a keyed write when the request never got an answer (status 0, e.g. Wi-Fi drop).
+ * Safe only because every attempt carries the same Idempotency-Key.
+ */
+export function retryOnNetworkError<T>(count = 2, delayMs = 1000) {
+  return (source: Observable<T>) => source.pipe(
+    retry({
+      count,
+      delay: (error, attempt) => error?.status === 0 ? timer(delayMs * attempt) : throwError(() => error)
+    })
+  );
+}
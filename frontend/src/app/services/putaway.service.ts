@@ -1,7 +1,9 @@
 import { Injectable } from '@angular/core';
-import { HttpClient, HttpParams } from '@angular/common/http';
+import { HttpClient, HttpHeaders, HttpParams } from '@angular/common/http';
 import { Observable } from 'rxjs';
+import { map } from 'rxjs/operators';
 import { environment } from '../../environments/environment';
+import { IDEMPOTENCY_KEY_HEADER, newIdempotencyKey, retryOnNetworkError } from './idempotency-key';
 
 export interface PutawayItem {
   lot_no: string;
@@ -119,6 +121,10 @@ export interface ApiResponse<T> {
   success: boolean;
   data?: T;
   message: string;
+  error_code?: string;
+  warning?: string;
+  request_id?: string;
+  errors?: { field: string; message: string }[];
 }
 
 export interface PaginatedLotSearchResponse {
@@ -244,7 +250,8 @@ export class PutawayService {
    * Replicates official app lot search functionality
    */
   searchLot(lotNo: string): Observable<LotSearchResponse> {
-    return this.http.get<LotSearchResponse>(`${this.baseUrl}/putaway/lot/${lotNo}`);
+    return this.http.get<ApiResponse<LotSearchResponse>>(`${this.baseUrl}/putaway/lot/${lotNo}`)
+      .pipe(map(response => response.data!));
   }
 
   /**
@@ -252,7 +259,8 @@ export class PutawayService {
    * Replicates official app bin validation functionality
    */
   validateBin(location: string, binNo: string): Observable<BinValidationResponse> {
-    return this.http.get<BinValidationResponse>(`${this.baseUrl}/putaway/bin/${location}/${binNo}`);
+    return this.http.get<ApiResponse<BinValidationResponse>>(`${this.baseUrl}/putaway/bin/${location}/${binNo}`)
+      .pipe(map(response => response.data!));
   }
 
   /**
@@ -260,7 +268,13 @@ export class PutawayService {
    * Replicates official app BT-25268027 transaction pattern
    */
   executeBinTransfer(request: BinTransferRequest): Observable<TransactionResponse> {
-    return this.http.post<TransactionResponse>(`${this.baseUrl}/putaway/transfer`, request);
+    // One key per transfer: automatic retries replay the first result instead of moving stock twice
+    const headers = new HttpHeaders({ [IDEMPOTENCY_KEY_HEADER]: newIdempotencyKey() });
+    return this.http.post<ApiResponse<TransactionResponse>>(`${this.baseUrl}/putaway/transfer`, request, { headers })
+      .pipe(
+        retryOnNetworkError(),
+        map(response => response.data!)
+      );
   }
 
   /**
@@ -274,7 +288,8 @@ export class PutawayService {
     }
     params = params.set('limit', limit.toString());
     
-    return this.http.get<LotSearchItem[]>(`${this.baseUrl}/putaway/lots/search`, { params });
+    return this.http.get<ApiResponse<PaginatedLotSearchResponse>>(`${this.baseUrl}/putaway/lots/search`, { params })
+      .pipe(map(response => response.data!.items));
   }
 
   /**
@@ -289,7 +304,8 @@ export class PutawayService {
     params = params.set('page', page.toString());
     params = params.set('limit', limit.toString());
     
-    return this.http.get<PaginatedLotSearchResponse>(`${this.baseUrl}/putaway/lots/search`, { params });
+    return this.http.get<ApiResponse<PaginatedLotSearchResponse>>(`${this.baseUrl}/putaway/lots/search`, { params })
+      .pipe(map(response => response.data!));
   }
 
   /**
@@ -319,14 +335,16 @@ export class PutawayService {
       params = params.set('location', lotContext.location);
     }
 
-    return this.http.get<PaginatedBinSearchResponse>(`${this.baseUrl}/putaway/bins/search`, { params });
+    return this.http.get<ApiResponse<PaginatedBinSearchResponse>>(`${this.baseUrl}/putaway/bins/search`, { params })
+      .pipe(map(response => response.data!));
   }
 
   /**
    * Get putaway service health status
    */
   getPutawayHealth(): Observable<HealthResponse> {
-    return this.http.get<HealthResponse>(`${this.baseUrl}/putaway/health`);
+    return this.http.get<ApiResponse<HealthResponse>>(`${this.baseUrl}/putaway/health`)
+      .pipe(map(response => response.data!));
   }
 
   /**
@@ -0,0 +1,108 @@
+import { Injectable, inject } from '@angular/core';
+import { Observable } from 'rxjs';
+import { environment } from '../../environments/environment';
+import { AuthService } from './auth.service';
+
+export type RunEventType =
+  | 'pick'
+  | 'unpick'
+  | 'pallet_completed'
+  | 'ingredient_completed'
+  | 'run_status_changed';
+
+/** Change pushed by /api/bulk-runs/{run_no}/events; fields beyond these depend on `type` */
+export interface RunEvent {
+  type: RunEventType | 'resync';
+  run_no: number;
+  user_id?: string;
+  occurred_at?: string;
+  row_num?: number | null;
+  line_id?: number | null;
+  lot_no?: string | null;
+  item_key?: string;
+  from?: string;
+  to?: string;
+}
+
+/**
+ * Live events for a run, replacing status/pallet polling while several pickers share it.
+ * EventSource cannot send the Authorization header, so the stream is read with fetch.
+ * A `resync` event means events were missed (or the connection dropped) - reload the run.
+ */
+@Injectable({
+  providedIn: 'root'
+})
+export class RunEventsService {
+  private authService = inject(AuthService);
+  private readonly baseUrl = `${environment.apiUrl}/bulk-runs`;
+
+  watchRun(runNo: number, reconnectDelayMs = 3000): Observable<RunEvent> {
+    return new Observable<RunEvent>(subscriber => {
+      let controller: AbortController | null = null;
+      let reconnectTimer: ReturnType<typeof setTimeout> | null = null;
+      let closed = false;
+
+      const connect = async () => {
+        controller = new AbortController();
+        try {
+          const token = this.authService.getStoredToken();
+          const response = await fetch(`${this.baseUrl}/${runNo}/events`, {
+            headers: token ? { Authorization: `Bearer ${token}` } : {},
+            signal: controller.signal
+          });
+          if (!response.ok || !response.body) {
+            throw new Error(`Run events request failed with status ${response.status}`);
+          }
+
+          const reader = response.body.pipeThrough(new TextDecoderStream()).getReader();
+          let buffer = '';
+          for (;;) {
+            const { value, done } = await reader.read();
+            if (done) break;
+            buffer += value;
+            const frames = buffer.split('\n\n');
+            buffer = frames.pop() ?? '';
+            frames.forEach(frame => this.emitFrame(frame, runNo, subscriber));
+          }
+        } catch {
+          // Dropped connection or server restart; fall through to reconnect
+        }
+
+        if (!closed) {
+          // Anything may have happened while disconnected
+          subscriber.next({ type: 'resync', run_no: runNo });
+          reconnectTimer = setTimeout(connect, reconnectDelayMs);
+        }
+      };
+
+      connect();
+
+      return () => {
+        closed = true;
+        if (reconnectTimer) clearTimeout(reconnectTimer);
+        controller?.abort();
+      };
+    });
+  }
+
+  private emitFrame(frame: string, runNo: number, subscriber: { next: (event: RunEvent) => void }): void {
+    let name = 'message';
+    const data: string[] = [];
+    for (const line of frame.split('\n')) {
+      if (line.startsWith('event:')) name = line.slice(6).trim();
+      else if (line.startsWith('data:')) data.push(line.slice(5).trim());
+    }
+
+    // Keep-alive comments carry no data
+    if (data.length === 0) return;
+    if (name === 'resync') {
+      subscriber.next({ type: 'resync', run_no: runNo });
+      return;
+    }
+    try {
+      subscriber.next(JSON.parse(data.join('\n')) as RunEvent);
+    } catch {
+      subscriber.next({ type: 'resync', run_no: runNo });
+    }
+  }
+}